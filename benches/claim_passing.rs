@@ -0,0 +1,86 @@
+//! Cost of handing a claim from its biller to history, its payer, and its remittance record
+//!
+//! Compares copying the claim at each hand-off, as the pipeline used to, with sharing one
+//! `Arc<PayerClaim>` as it does now, counting heap allocations and timing both.
+//! Run with `cargo bench --bench claim_passing`
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use healthtechsim::clock::SimClock;
+use healthtechsim::json_faker::fake_payer_claim;
+use healthtechsim::message::{ClaimMetadata, ClaimStatus, PayerMessage};
+use healthtechsim::remittance::{Remittance, RemittanceRecord};
+use healthtechsim::schema::PayerClaim;
+
+/// Claims handed through the pipeline per strategy
+const CLAIMS: usize = 100_000;
+
+/// Counts every allocation, so the strategies can be compared without a profiler
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// The history entry, payer message, and remittance record made for one claim
+fn hand_off(claim: &Arc<PayerClaim>, remittance: &Remittance, share: impl Fn(&Arc<PayerClaim>) -> Arc<PayerClaim>) {
+    let now = Instant::now();
+    let submitted = ClaimStatus::Submitted {
+        claim: share(claim),
+        tenant_id: String::new(),
+        submitted_at: now,
+        metadata: ClaimMetadata::default(),
+    };
+    let message = PayerMessage::Adjudicate(share(claim), tracing::Span::none());
+    let record = RemittanceRecord::new(share(claim), remittance.clone(), now, now);
+    black_box((submitted, message, record));
+}
+
+/// Allocations per claim and total time for `CLAIMS` hand-offs
+fn measure(claims: &[(Arc<PayerClaim>, Remittance)], share: impl Fn(&Arc<PayerClaim>) -> Arc<PayerClaim> + Copy) -> (f64, Duration) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let started = Instant::now();
+    for (claim, remittance) in claims.iter().cycle().take(CLAIMS) {
+        hand_off(claim, remittance, share);
+    }
+    let elapsed = started.elapsed();
+    let per_claim = (ALLOCATIONS.load(Ordering::Relaxed) - allocations) as f64 / CLAIMS as f64;
+    (per_claim, elapsed)
+}
+
+fn main() {
+    let today = SimClock::default().now().date();
+    let claims: Vec<(Arc<PayerClaim>, Remittance)> = (0..1_000)
+        .map(|_| {
+            let claim = fake_payer_claim();
+            let remittance = Remittance::from_claim(&claim, today);
+            (Arc::new(claim), remittance)
+        })
+        .collect();
+    // warm up, so neither strategy pays for first-touch page faults
+    measure(&claims, |claim| Arc::new(PayerClaim::clone(claim)));
+
+    let (copied_allocations, copied) = measure(&claims, |claim| Arc::new(PayerClaim::clone(claim)));
+    let (shared_allocations, shared) = measure(&claims, Arc::clone);
+    println!("{} claims, each handed to history, its payer, and its remittance record", CLAIMS);
+    println!("{:<8} {:>18} {:>12}", "", "allocs per claim", "time");
+    println!("{:<8} {:>18.1} {:>12.2?}", "copied", copied_allocations, copied);
+    println!("{:<8} {:>18.1} {:>12.2?}", "shared", shared_allocations, shared);
+    println!("{:.1}x faster", copied.as_secs_f64() / shared.as_secs_f64());
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::remittance::{DenialReason, Remittance};
+use crate::schema::PayerClaim;
+
+/// What an adjudicator decided about a claim
+#[derive(Debug, Clone)]
+pub enum AdjudicationResult {
+    /// Remit the claim as priced
+    Remit(Remittance),
+    /// Deny the whole claim, for the reason given if there is one
+    Deny(Option<DenialReason>),
+}
+
+impl AdjudicationResult {
+    /// The remittance the payer sends for `claim`
+    pub fn into_remittance(self, claim: &PayerClaim) -> Remittance {
+        match self {
+            AdjudicationResult::Remit(remittance) => remittance,
+            AdjudicationResult::Deny(Some(reason)) => Remittance::denied_for(claim, reason),
+            AdjudicationResult::Deny(None) => Remittance::denied(claim),
+        }
+    }
+}
+
+/// Decides a payer's claims
+///
+/// Register one with `Payer::with_adjudicator` or `PayerSpec::with_adjudicator` in place of the
+/// built-in simulated payer. The payer runner around it still queues claims by priority, spreads
+/// them over its workers, waits out the payer's response time, holds claims for attachments,
+/// handles voids and replacements, and checks each remittance against its claim
+pub trait Adjudicator: Send + Sync + 'static {
+    fn adjudicate(&self, claim: &PayerClaim) -> impl Future<Output = AdjudicationResult> + Send;
+
+    /// Undo what adjudicating a claim recorded, before it is adjudicated again because its
+    /// remittance did not balance
+    fn retract(&self, _claim: &PayerClaim, _remittance: &Remittance) {}
+
+    /// Version of the payer rules the next claim is adjudicated under, for an adjudicator that
+    /// follows the simulator's hot-reloaded rules
+    fn rules_version(&self) -> Option<u64> {
+        None
+    }
+}
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// `Adjudicator` with its future boxed, so adjudicators of any type can be shared behind a pointer
+trait DynAdjudicator: Send + Sync {
+    fn adjudicate<'a>(&'a self, claim: &'a PayerClaim) -> BoxFuture<'a, AdjudicationResult>;
+
+    fn retract(&self, claim: &PayerClaim, remittance: &Remittance);
+
+    fn rules_version(&self) -> Option<u64>;
+}
+
+impl<A: Adjudicator> DynAdjudicator for A {
+    fn adjudicate<'a>(&'a self, claim: &'a PayerClaim) -> BoxFuture<'a, AdjudicationResult> {
+        Box::pin(Adjudicator::adjudicate(self, claim))
+    }
+
+    fn retract(&self, claim: &PayerClaim, remittance: &Remittance) {
+        Adjudicator::retract(self, claim, remittance)
+    }
+
+    fn rules_version(&self) -> Option<u64> {
+        Adjudicator::rules_version(self)
+    }
+}
+
+/// An adjudicator shared by every worker of the payer running it
+#[derive(Clone)]
+pub struct SharedAdjudicator(Arc<dyn DynAdjudicator>);
+
+impl SharedAdjudicator {
+    pub fn new(adjudicator: impl Adjudicator) -> Self {
+        Self(Arc::new(adjudicator))
+    }
+
+    pub async fn adjudicate(&self, claim: &PayerClaim) -> AdjudicationResult {
+        self.0.adjudicate(claim).await
+    }
+
+    pub fn retract(&self, claim: &PayerClaim, remittance: &Remittance) {
+        self.0.retract(claim, remittance)
+    }
+
+    pub fn rules_version(&self) -> Option<u64> {
+        self.0.rules_version()
+    }
+}
+
+impl<A: Adjudicator> From<A> for SharedAdjudicator {
+    fn from(adjudicator: A) -> Self {
+        Self::new(adjudicator)
+    }
+}
+
+impl fmt::Debug for SharedAdjudicator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedAdjudicator")
+    }
+}
+
+/// Two handles are equal when they share one adjudicator
+impl PartialEq for SharedAdjudicator {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
@@ -0,0 +1,770 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::Sender;
+
+use crate::audit_log::{AuditAction, AuditLog};
+use crate::claim_notes::{ClaimNote, ClaimNotes, NoteAction};
+use crate::claim_search::{ClaimPage, ClaimQuery, ClaimSummary};
+use crate::control::{ComponentHandle, ComponentStatus, Controls, PayerSettings, RunState};
+use crate::held_remittances::HeldRemittances;
+use crate::history::History;
+use crate::ids::{ClaimId, ClaimKey, PayerId};
+use crate::inventory::{ClaimInventory, StateInventory};
+use crate::logging::log_claim_event;
+use crate::message::{ClaimMessage, ClaimStatus};
+use crate::payer::{PayerClaimStatus, PayerMetrics, PayerPortal};
+use crate::phi::Deidentification;
+use crate::rate_limiter::{RateLimiter, RateSettings};
+use crate::report_format::{MoneyFields, ReportFormat};
+use crate::supervisor::{ComponentHealth, Health, Supervisor};
+use crate::report_engine::{
+    PatientSummary, PayerAging, PayerLag, PayerReconciliation, PayerTurnaround, PriorityLatency, ProcedureSummary, ProviderSummary,
+    RemittanceException, ReportEngine, SplitClaim, StageLatency, TelehealthMix, TenantSummary,
+};
+
+type ApiResult<T> = Result<Json<T>, (StatusCode, String)>;
+
+/// Shared handles the HTTP API can inspect and control
+#[derive(Clone, Default)]
+pub struct ApiState {
+    pub rate_limiter: Option<RateLimiter>,
+    pub history: Option<History>,
+    pub controls: Controls,
+    /// Each payer's claim store, keyed by payer id, answering claim status inquiries
+    pub portals: BTreeMap<PayerId, PayerPortal>,
+    /// Clearinghouse claim inbox, taking cancellations on behalf of billers
+    pub claims: Option<Sender<ClaimMessage>>,
+    /// Remittances each tenant's biller holds for review, keyed by tenant id
+    pub held: BTreeMap<String, HeldRemittances>,
+    /// Units money amounts in JSON reports are scaled to
+    pub format: ReportFormat,
+    /// Records each claim a caller views or cancels
+    pub audit_log: Option<AuditLog>,
+    /// Runs the pipeline's components, reporting their health
+    pub supervisor: Option<Supervisor>,
+    /// Work history billing workers attach to claims
+    pub notes: ClaimNotes,
+    /// Claims in each lifecycle state, kept current by the clearinghouse
+    pub inventory: Option<ClaimInventory>,
+    /// How claims and reports name patients: by pseudonymous member id unless off
+    pub deidentification: Deidentification,
+}
+
+impl ApiState {
+    fn audit(&self, action: AuditAction, claim_id: &ClaimId) {
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.record("api", action, claim_id);
+        }
+    }
+}
+
+/// Answer to a claim status inquiry, as given by the payer the claim was routed to
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClaimInquiry {
+    pub claim_id: ClaimId,
+    pub payer_id: PayerId,
+    #[serde(flatten)]
+    pub status: PayerClaimStatus,
+}
+
+/// A claim as its history records it, with the notes billing workers have attached to it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClaimDetail {
+    #[serde(flatten)]
+    pub summary: ClaimSummary,
+    /// Oldest first
+    pub notes: Vec<ClaimNote>,
+}
+
+/// Note a billing worker attaches to a claim; stamped with the time it is received
+#[derive(Debug, Clone, Deserialize)]
+struct NewNote {
+    author: String,
+    #[serde(default)]
+    action: NoteAction,
+    text: String,
+}
+
+/// Tenant whose claim a claim id in the path means, needed only when several tenants used the id
+#[derive(Debug, Clone, Default, Deserialize)]
+struct TenantQuery {
+    tenant: Option<String>,
+}
+
+/// Page of a report's rows, for reports with a row per patient, provider, or claim; every row when absent
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+struct RowQuery {
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl RowQuery {
+    fn page<T>(&self, rows: Vec<T>) -> Vec<T> {
+        rows.into_iter().skip(self.offset).take(self.limit.unwrap_or(usize::MAX)).collect()
+    }
+}
+
+/// Lifecycle change requested for a component
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Action {
+    Pause,
+    Resume,
+    Stop,
+}
+
+/// Build the API routes
+///
+/// - `GET /rate`: current reader rate limit
+/// - `PUT /rate`: change claims/sec and burst at runtime
+/// - `GET /reports/...`: claim history reports as JSON (`ar-aging`, `patients`, `payer-turnaround`,
+///   `priority-latency`, `stage-latency`, `providers`, `procedures`, `tenants`); reports with money
+///   rows and `exceptions` take `offset` and `limit` to page their rows
+/// - `GET /reports/inventory`: claims and billed dollars in each lifecycle state right now
+/// - `GET /components`: run state of the clearinghouse, payers, and billers
+/// - `GET /health`: whether each supervised component is running, stalled, failed, or finished, and
+///   its restarts; 503 when any is stalled or failed
+/// - `POST /clearinghouse/{action}`, `/payers/{id}/{action}`, `/billers/{tenant}/{action}`: `pause`, `resume`, or `stop`
+/// - `GET /payers/{id}/settings`, `PUT /payers/{id}/settings`: payer response times and denial rate
+/// - `GET /payers/{id}/metrics`: payer intake queue depth and adjudicator utilization
+/// - `GET /payers/{id}/claims/{claim_id}`: a claim's status in the payer's own records
+/// - `GET /claims`: search the claim history by `tenant`, `patient_id`, `payer_id`, `status`
+///   (`pending`, `paid`, `denied`, `voided`), billed `min_amount` and `max_amount`, and date of service
+///   `from` and `to`, paged with `offset` and `limit`
+/// - `GET /claims/{claim_id}`: a claim's status, amounts, and notes
+/// - `POST /claims/{claim_id}/notes`: attach a note to a claim's work history, as `{"author", "action", "text"}`
+/// - `GET /claims/{claim_id}/status`: status inquiry, routed by the clearinghouse to the claim's payer
+///
+///   These three take `?tenant=` to pick one tenant's claim, and answer 409 without it when
+///   more than one tenant submitted a claim with the id
+/// - `POST /billers/{tenant}/claims/{claim_id}/cancel`: void a claim the tenant submitted, if not yet remitted
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/rate", get(get_rate).put(put_rate))
+        .route("/components", get(get_components))
+        .route("/health", get(get_health))
+        .route("/clearinghouse/{action}", post(post_clearinghouse_action))
+        .route("/payers/{payer_id}/settings", get(get_payer_settings).put(put_payer_settings))
+        .route("/payers/{payer_id}/{action}", post(post_payer_action))
+        .route("/payers/{payer_id}/metrics", get(get_payer_metrics))
+        .route("/payers/{payer_id}/claims/{claim_id}", get(get_payer_claim_status))
+        .route("/claims", get(get_claims))
+        .route("/claims/{claim_id}", get(get_claim_detail))
+        .route("/claims/{claim_id}/notes", post(post_claim_note))
+        .route("/claims/{claim_id}/status", get(get_claim_inquiry))
+        .route("/billers/{tenant_id}/{action}", post(post_biller_action))
+        .route("/billers/{tenant_id}/claims/{claim_id}/cancel", post(post_cancel_claim))
+        .route("/billers/{tenant_id}/claims/{claim_id}/release", post(post_release_remittance))
+        .route("/reports/inventory", get(get_inventory))
+        .route("/reports/ar-aging", get(get_ar_aging))
+        .route("/reports/patients", get(get_patients))
+        .route("/reports/payer-turnaround", get(get_payer_turnaround))
+        .route("/reports/payer-lag", get(get_payer_lag))
+        .route("/reports/priority-latency", get(get_priority_latency))
+        .route("/reports/stage-latency", get(get_stage_latency))
+        .route("/reports/providers", get(get_providers))
+        .route("/reports/procedures", get(get_procedures))
+        .route("/reports/telehealth", get(get_telehealth))
+        .route("/reports/tenants", get(get_tenants))
+        .route("/reports/exceptions", get(get_exceptions))
+        .route("/reports/reconciliation", get(get_reconciliation))
+        .route("/reports/split-claims", get(get_split_claims))
+        .with_state(state)
+}
+
+/// Serve the HTTP API on `addr` until the task is dropped
+pub async fn serve(addr: &str, state: ApiState, verbose: bool) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    if verbose {
+        log_claim_event("api", "-", "start", &format!("Listening on http://{}", listener.local_addr()?));
+    }
+    axum::serve(listener, router(state)).await?;
+    Ok(())
+}
+
+async fn get_rate(State(state): State<ApiState>) -> ApiResult<RateSettings> {
+    let limiter = rate_limiter(&state)?;
+    Ok(Json(limiter.settings().await))
+}
+
+async fn put_rate(
+    State(state): State<ApiState>,
+    Json(settings): Json<RateSettings>,
+) -> ApiResult<RateSettings> {
+    let limiter = rate_limiter(&state)?;
+    limiter
+        .set_settings(settings)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(Json(settings))
+}
+
+fn rate_limiter(state: &ApiState) -> Result<&RateLimiter, (StatusCode, String)> {
+    state
+        .rate_limiter
+        .as_ref()
+        .ok_or((StatusCode::NOT_FOUND, "Rate limiting is not enabled".to_string()))
+}
+
+async fn get_components(State(state): State<ApiState>) -> Json<Vec<ComponentStatus>> {
+    Json(state.controls.status())
+}
+
+async fn get_health(State(state): State<ApiState>) -> Result<(StatusCode, Json<Vec<ComponentHealth>>), (StatusCode, String)> {
+    let supervisor = state
+        .supervisor
+        .as_ref()
+        .ok_or((StatusCode::NOT_FOUND, "Supervision is not enabled".to_string()))?;
+    let health = supervisor.health();
+    let status = match health.iter().any(|component| matches!(component.health, Health::Stalled | Health::Failed)) {
+        true => StatusCode::SERVICE_UNAVAILABLE,
+        false => StatusCode::OK,
+    };
+    Ok((status, Json(health)))
+}
+
+async fn post_clearinghouse_action(
+    State(state): State<ApiState>,
+    Path(action): Path<Action>,
+) -> ApiResult<RunState> {
+    let handle = state
+        .controls
+        .clearinghouse
+        .as_ref()
+        .ok_or((StatusCode::NOT_FOUND, "Clearinghouse control is not available".to_string()))?;
+    Ok(Json(apply(handle, action)))
+}
+
+async fn post_payer_action(
+    State(state): State<ApiState>,
+    Path((payer_id, action)): Path<(String, Action)>,
+) -> ApiResult<RunState> {
+    let handle = component(&state.controls.payers, "payer", &payer_id)?;
+    Ok(Json(apply(handle, action)))
+}
+
+async fn post_biller_action(
+    State(state): State<ApiState>,
+    Path((tenant_id, action)): Path<(String, Action)>,
+) -> ApiResult<RunState> {
+    let handle = component(&state.controls.billers, "biller", &tenant_id)?;
+    Ok(Json(apply(handle, action)))
+}
+
+async fn get_payer_settings(
+    State(state): State<ApiState>,
+    Path(payer_id): Path<String>,
+) -> ApiResult<PayerSettings> {
+    let handle = component(&state.controls.payers, "payer", &payer_id)?;
+    Ok(Json(handle.settings()))
+}
+
+async fn put_payer_settings(
+    State(state): State<ApiState>,
+    Path(payer_id): Path<String>,
+    Json(settings): Json<PayerSettings>,
+) -> ApiResult<PayerSettings> {
+    let handle = component(&state.controls.payers, "payer", &payer_id)?;
+    handle
+        .reconfigure(settings)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(Json(settings))
+}
+
+async fn get_payer_metrics(State(state): State<ApiState>, Path(payer_id): Path<String>) -> ApiResult<PayerMetrics> {
+    state
+        .portals
+        .get(payer_id.as_str())
+        .map(|portal| Json(portal.metrics()))
+        .ok_or((StatusCode::NOT_FOUND, format!("Unknown payer: {}", payer_id)))
+}
+
+async fn get_payer_claim_status(
+    State(state): State<ApiState>,
+    Path((payer_id, claim_id)): Path<(String, String)>,
+) -> ApiResult<PayerClaimStatus> {
+    let portal = state
+        .portals
+        .get(payer_id.as_str())
+        .ok_or((StatusCode::NOT_FOUND, format!("Unknown payer: {}", payer_id)))?;
+    let status = portal
+        .claim_status(&claim_id)
+        .ok_or((StatusCode::NOT_FOUND, format!("Payer {} has no record of claim {}", payer_id, claim_id)))?;
+    state.audit(AuditAction::View, &claim_id.into());
+    Ok(Json(status))
+}
+
+async fn get_claims(State(state): State<ApiState>, Query(query): Query<ClaimQuery>) -> ApiResult<ClaimPage> {
+    let history = state
+        .history
+        .as_ref()
+        .ok_or((StatusCode::NOT_FOUND, "Claim history is not available".to_string()))?;
+    let page = history.search(&query, &state.deidentification).await;
+    for claim in &page.claims {
+        state.audit(AuditAction::View, &claim.claim_id);
+    }
+    Ok(Json(page))
+}
+
+/// The claim a path's claim id names: the named tenant's, or else the only claim with that id
+async fn find_claim(state: &ApiState, claim_id: String, tenant: Option<String>) -> Result<(ClaimKey, ClaimStatus), (StatusCode, String)> {
+    let history = state
+        .history
+        .as_ref()
+        .ok_or((StatusCode::NOT_FOUND, "Claim history is not available".to_string()))?;
+    let unknown = || (StatusCode::NOT_FOUND, format!("Unknown claim: {}", claim_id));
+    if let Some(tenant_id) = tenant {
+        let key = ClaimKey::new(tenant_id, claim_id.clone());
+        let status = history.get(&key).await.ok_or_else(unknown)?;
+        return Ok((key, status));
+    }
+    let mut found = history.find(&claim_id).await;
+    match found.len() {
+        0 => Err(unknown()),
+        1 => Ok(found.remove(0)),
+        _ => {
+            let tenants: Vec<String> = found.into_iter().map(|(key, _)| key.tenant_id).collect();
+            Err((
+                StatusCode::CONFLICT,
+                format!("Claim {} was submitted by tenants {}; name one with ?tenant=", claim_id, tenants.join(", ")),
+            ))
+        }
+    }
+}
+
+async fn get_claim_detail(
+    State(state): State<ApiState>,
+    Path(claim_id): Path<String>,
+    Query(query): Query<TenantQuery>,
+) -> ApiResult<ClaimDetail> {
+    let (key, status) = find_claim(&state, claim_id, query.tenant).await?;
+    state.audit(AuditAction::View, &key.claim_id);
+    Ok(Json(ClaimDetail { summary: ClaimSummary::new(&key.claim_id, &status, &state.deidentification), notes: state.notes.for_claim(&key) }))
+}
+
+/// Only claims in history take notes; the note is recorded in the audit log when there is one
+async fn post_claim_note(
+    State(state): State<ApiState>,
+    Path(claim_id): Path<String>,
+    Query(query): Query<TenantQuery>,
+    Json(note): Json<NewNote>,
+) -> Result<(StatusCode, Json<ClaimNote>), (StatusCode, String)> {
+    let (key, _) = find_claim(&state, claim_id, query.tenant).await?;
+    if note.author.trim().is_empty() || note.text.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "A note needs an author and text".to_string()));
+    }
+    let note = ClaimNote::new(note.author, note.action, note.text);
+    state.notes.add(&key, note.clone());
+    Ok((StatusCode::CREATED, Json(note)))
+}
+
+/// Look up which payer the claim was routed to, then ask that payer rather than relying on clearinghouse history
+///
+/// The payer is asked by the id the clearinghouse sent the claim under
+async fn get_claim_inquiry(
+    State(state): State<ApiState>,
+    Path(claim_id): Path<String>,
+    Query(query): Query<TenantQuery>,
+) -> ApiResult<ClaimInquiry> {
+    let (key, claim) = find_claim(&state, claim_id, query.tenant).await?;
+    let payer_id = claim.payer_id().clone();
+    let payer_claim_id = claim.payer_claim_id().to_string();
+    let Json(status) = get_payer_claim_status(State(state), Path((payer_id.to_string(), payer_claim_id))).await?;
+    Ok(Json(ClaimInquiry { claim_id: key.claim_id, payer_id, status }))
+}
+
+/// Cancellation is asynchronous: the biller learns the outcome from the remittance channel
+async fn post_cancel_claim(
+    State(state): State<ApiState>,
+    Path((tenant_id, claim_id)): Path<(String, String)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let claims = state
+        .claims
+        .as_ref()
+        .ok_or((StatusCode::NOT_FOUND, "Claim cancellation is not available".to_string()))?;
+    let claim_id = ClaimId::from(claim_id);
+    claims
+        .send(ClaimMessage::Cancel { claim_id: claim_id.clone(), tenant_id })
+        .await
+        .map_err(|_| (StatusCode::SERVICE_UNAVAILABLE, "Clearinghouse has stopped".to_string()))?;
+    state.audit(AuditAction::Cancel, &claim_id);
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn post_release_remittance(
+    State(state): State<ApiState>,
+    Path((tenant_id, claim_id)): Path<(String, String)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let held = state
+        .held
+        .get(&tenant_id)
+        .ok_or((StatusCode::NOT_FOUND, format!("Unknown biller: {}", tenant_id)))?;
+    let claim_id = ClaimId::from(claim_id);
+    if !held.release(&claim_id) {
+        return Err((StatusCode::NOT_FOUND, format!("No remittance held for claim {}", claim_id)));
+    }
+    state.audit(AuditAction::Release, &claim_id);
+    Ok(StatusCode::ACCEPTED)
+}
+
+fn component<'a, K: std::borrow::Borrow<str> + Ord, S>(
+    handles: &'a std::collections::BTreeMap<K, ComponentHandle<S>>,
+    kind: &str,
+    id: &str,
+) -> Result<&'a ComponentHandle<S>, (StatusCode, String)> {
+    handles
+        .get(id)
+        .ok_or((StatusCode::NOT_FOUND, format!("Unknown {}: {}", kind, id)))
+}
+
+fn apply<S: Clone>(handle: &ComponentHandle<S>, action: Action) -> RunState {
+    match action {
+        Action::Pause => handle.pause(),
+        Action::Resume => handle.resume(),
+        Action::Stop => handle.stop(),
+    }
+    handle.state()
+}
+
+async fn get_inventory(State(state): State<ApiState>) -> ApiResult<Vec<StateInventory>> {
+    let inventory = state
+        .inventory
+        .as_ref()
+        .ok_or((StatusCode::NOT_FOUND, "Claim inventory is not available".to_string()))?;
+    let mut rows = inventory.counts();
+    for row in &mut rows {
+        row.map_money(|amount| state.format.scale(amount));
+    }
+    Ok(Json(rows))
+}
+
+async fn get_ar_aging(State(state): State<ApiState>) -> ApiResult<Vec<PayerAging>> {
+    report(&state, |engine| engine.ar_aging()).await
+}
+
+async fn get_patients(State(state): State<ApiState>, Query(rows): Query<RowQuery>) -> ApiResult<Vec<PatientSummary>> {
+    money_report(&state, rows, |engine| engine.patient_summary()).await
+}
+
+async fn get_payer_turnaround(State(state): State<ApiState>) -> ApiResult<Vec<PayerTurnaround>> {
+    report(&state, |engine| engine.payer_turnaround()).await
+}
+
+async fn get_payer_lag(State(state): State<ApiState>) -> ApiResult<Vec<PayerLag>> {
+    report(&state, |engine| engine.payer_lag()).await
+}
+
+async fn get_priority_latency(State(state): State<ApiState>) -> ApiResult<Vec<PriorityLatency>> {
+    report(&state, |engine| engine.latency_by_priority()).await
+}
+
+async fn get_stage_latency(State(state): State<ApiState>) -> ApiResult<Vec<StageLatency>> {
+    report(&state, |engine| engine.stage_latency()).await
+}
+
+async fn get_providers(State(state): State<ApiState>, Query(rows): Query<RowQuery>) -> ApiResult<Vec<ProviderSummary>> {
+    money_report(&state, rows, |engine| engine.provider_summary()).await
+}
+
+async fn get_procedures(State(state): State<ApiState>, Query(rows): Query<RowQuery>) -> ApiResult<Vec<ProcedureSummary>> {
+    money_report(&state, rows, |engine| engine.procedure_summary()).await
+}
+
+async fn get_telehealth(State(state): State<ApiState>, Query(rows): Query<RowQuery>) -> ApiResult<Vec<TelehealthMix>> {
+    money_report(&state, rows, |engine| engine.telehealth_mix()).await
+}
+
+async fn get_split_claims(State(state): State<ApiState>, Query(rows): Query<RowQuery>) -> ApiResult<Vec<SplitClaim>> {
+    money_report(&state, rows, |engine| engine.split_claims()).await
+}
+
+async fn get_tenants(State(state): State<ApiState>, Query(rows): Query<RowQuery>) -> ApiResult<Vec<TenantSummary>> {
+    money_report(&state, rows, |engine| engine.tenant_summary()).await
+}
+
+async fn get_exceptions(State(state): State<ApiState>, Query(rows): Query<RowQuery>) -> ApiResult<Vec<RemittanceException>> {
+    report(&state, |engine| rows.page(engine.remittance_exceptions())).await
+}
+
+async fn get_reconciliation(State(state): State<ApiState>, Query(rows): Query<RowQuery>) -> ApiResult<Vec<PayerReconciliation>> {
+    money_report(&state, rows, |engine| engine.reconciliation()).await
+}
+
+/// Run a report whose rows carry money, with the page's amounts scaled to the report format's units
+async fn money_report<T: Serialize + MoneyFields>(
+    state: &ApiState,
+    page: RowQuery,
+    build: impl FnOnce(&ReportEngine) -> Vec<T>,
+) -> ApiResult<Vec<T>> {
+    let format = state.format;
+    report(state, |engine| {
+        let mut rows = page.page(build(engine));
+        for row in &mut rows {
+            row.map_money(|amount| format.scale(amount));
+        }
+        rows
+    })
+    .await
+}
+
+/// Run one report against a snapshot of claim history, so no shard is locked while aggregating
+async fn report<T: Serialize>(state: &ApiState, build: impl FnOnce(&ReportEngine) -> T) -> ApiResult<T> {
+    let history = state
+        .history
+        .as_ref()
+        .ok_or((StatusCode::NOT_FOUND, "Claim history is not available".to_string()))?;
+    let records = history.snapshot().await;
+    Ok(Json(build(&ReportEngine::new(&records).with_deidentification(state.deidentification.clone()))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use crate::message::{ClaimMetadata, ClaimStatus};
+    use std::collections::HashMap;
+    use crate::remittance::MismatchPolicy;
+    use tower::ServiceExt;
+
+    /// Test that the rate limit can be read and changed through the API.
+    /// Expected: PUT updates the limiter; GET returns the new settings.
+    #[tokio::test]
+    async fn test_rate_endpoints() {
+        let limiter = RateLimiter::new(RateSettings { claims_per_sec: 1.0, burst: 1 }).unwrap();
+        let app = router(ApiState {
+            rate_limiter: Some(limiter.clone()),
+            ..ApiState::default()
+        });
+        let request = Request::put("/rate")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"claims_per_sec": 25.0, "burst": 10}"#))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(limiter.settings().await, RateSettings { claims_per_sec: 25.0, burst: 10 });
+
+        let response = app.oneshot(Request::get("/rate").body(Body::empty()).unwrap()).await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let settings: RateSettings = serde_json::from_slice(&body).unwrap();
+        assert_eq!(settings.burst, 10);
+    }
+
+    /// Test that invalid settings are rejected without changing the limiter.
+    /// Expected: 400 Bad Request; settings unchanged.
+    #[tokio::test]
+    async fn test_put_rate_invalid() {
+        let original = RateSettings { claims_per_sec: 1.0, burst: 1 };
+        let limiter = RateLimiter::new(original).unwrap();
+        let app = router(ApiState {
+            rate_limiter: Some(limiter.clone()),
+            ..ApiState::default()
+        });
+        let request = Request::put("/rate")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"claims_per_sec": 0.0, "burst": 10}"#))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(limiter.settings().await, original);
+    }
+
+    /// Test that reports are served from shared claim history.
+    /// Expected: The provider report lists the claim's NPI, and a page past it is empty; without history the route is 404.
+    #[tokio::test]
+    async fn test_report_endpoints() {
+        use crate::test_utils::mock_claim;
+        use std::time::Instant;
+
+        let status = ClaimStatus::Submitted { claim: mock_claim().into(), tenant_id: "default".to_string(), submitted_at: Instant::now(), metadata: ClaimMetadata::default() };
+        let records = HashMap::from([(status.key(), status)]);
+        let app = router(ApiState {
+            history: Some(History::from_records(records)),
+            ..ApiState::default()
+        });
+        let response = app.clone().oneshot(Request::get("/reports/providers").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let providers: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(providers[0]["npi"], mock_claim().rendering_provider.npi.as_str());
+        assert_eq!(providers[0]["claims"], 1);
+
+        let response = app.oneshot(Request::get("/reports/providers?offset=1&limit=10").body(Body::empty()).unwrap()).await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&body).unwrap(), serde_json::json!([]));
+
+        let app = router(ApiState::default());
+        let response = app.oneshot(Request::get("/reports/ar-aging").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    /// Test that the claim history can be searched and paged through the API.
+    /// Expected: Filters by payer, status, and amount select the matching claims; pages split them in claim id order.
+    #[tokio::test]
+    async fn test_search_claims() {
+        use crate::remittance::{Remittance, RemittanceRecord};
+        use crate::schema::PayerClaim;
+        use crate::test_utils::mock_claim;
+        use std::time::Instant;
+
+        let claim = |claim_id: &str, payer_id: &str| {
+            let mut claim = mock_claim();
+            claim.claim_id = claim_id.into();
+            claim.insurance.payer_id = payer_id.into();
+            claim
+        };
+        let submitted = |claim: PayerClaim| ClaimStatus::Submitted { claim: claim.into(), tenant_id: "default".to_string(), submitted_at: Instant::now(), metadata: ClaimMetadata::default() };
+        let denied = claim("c3", "medicare");
+        let records: HashMap<_, _> = [
+            submitted(claim("c1", "medicare")),
+            submitted(claim("c2", "anthem")),
+            ClaimStatus::Remitted(RemittanceRecord::new(denied.clone(), Remittance::denied(&denied), Instant::now(), Instant::now())),
+        ]
+        .map(|status| (status.key(), status))
+        .into();
+        let app = router(ApiState {
+            history: Some(History::from_records(records)),
+            ..ApiState::default()
+        });
+        let search = async |uri: &str| {
+            let response = app.clone().oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        let page = search("/claims?payer_id=medicare&limit=1").await;
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["claims"][0]["claim_id"], "c1");
+        let page = search("/claims?payer_id=medicare&limit=1&offset=1").await;
+        assert_eq!(page["claims"][0]["claim_id"], "c3");
+        assert_eq!(page["claims"][0]["status"], "denied");
+        let page = search("/claims?status=pending").await;
+        assert_eq!(page["total"], 2);
+        let billed = mock_claim().total_charge();
+        assert_eq!(search(&format!("/claims?min_amount={}", billed + 1.0)).await["total"], 0);
+        assert_eq!(search("/claims?from=1900-01-01").await["total"], 0);
+    }
+
+    /// Test that a run pseudonymizing patients names them pseudonymously in claim searches and patient reports.
+    /// Expected: The claim and the patient report carry the pseudonymous member id, which the patient filter matches; the real one matches nothing.
+    #[tokio::test]
+    async fn test_search_claims_pseudonymized() {
+        use crate::pseudonym::Pseudonymizer;
+        use crate::remittance::{Remittance, RemittanceRecord};
+        use crate::test_utils::{mock_adjudication_date, mock_claim};
+        use std::time::Instant;
+
+        let claim = mock_claim();
+        let real = claim.insurance.patient_member_id.clone();
+        let pseudonymizer = Pseudonymizer::new("key");
+        let pseudonym = pseudonymizer.member_id(&real);
+        let status = ClaimStatus::Remitted(RemittanceRecord::new(claim.clone(), Remittance::from_claim(&claim, mock_adjudication_date()), Instant::now(), Instant::now()));
+        let app = router(ApiState {
+            history: Some(History::from_records([(status.key(), status)].into())),
+            deidentification: Deidentification::Pseudonymize(pseudonymizer),
+            ..ApiState::default()
+        });
+        let get = async |uri: &str| {
+            let response = app.clone().oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        let page = get(&format!("/claims?patient_id={}", pseudonym)).await;
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["claims"][0]["patient_id"], pseudonym.as_str());
+        assert_eq!(get(&format!("/claims?patient_id={}", real)).await["total"], 0);
+        assert_eq!(get("/reports/patients").await[0]["patient_id"], pseudonym.as_str());
+    }
+
+    /// Test that notes attached to a claim come back with its detail, oldest first.
+    /// Expected: Two notes are created and listed in order with their actions; notes on unknown claims are 404, empty notes 400.
+    /// Once another tenant submits a claim with the same id, the detail is 409 until a tenant is named.
+    #[tokio::test]
+    async fn test_claim_notes() {
+        use crate::test_utils::mock_claim;
+        use std::time::Instant;
+
+        let claim_id = mock_claim().claim_id;
+        let status = ClaimStatus::Submitted { claim: mock_claim().into(), tenant_id: "default".to_string(), submitted_at: Instant::now(), metadata: ClaimMetadata::default() };
+        let app_history = History::from_records(HashMap::from([(status.key(), status)]));
+        let app = router(ApiState {
+            history: Some(app_history.clone()),
+            ..ApiState::default()
+        });
+        let note = async |claim_id: &str, body: &'static str| {
+            let request = Request::post(format!("/claims/{}/notes", claim_id))
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap();
+            app.clone().oneshot(request).await.unwrap().status()
+        };
+
+        assert_eq!(note(&claim_id, r#"{"author": "denials:jsmith", "action": "payer_call", "text": "Payer says records missing"}"#).await, StatusCode::CREATED);
+        assert_eq!(note(&claim_id, r#"{"author": "denials:jsmith", "action": "appeal", "text": "Appealed with records"}"#).await, StatusCode::CREATED);
+        assert_eq!(note("unknown", r#"{"author": "denials:jsmith", "text": "?"}"#).await, StatusCode::NOT_FOUND);
+        assert_eq!(note(&claim_id, r#"{"author": "", "text": " "}"#).await, StatusCode::BAD_REQUEST);
+
+        let response = app.clone().oneshot(Request::get(format!("/claims/{}", claim_id)).body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let detail: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(detail["status"], "pending");
+        let actions: Vec<_> = detail["notes"].as_array().unwrap().iter().map(|note| note["action"].clone()).collect();
+        assert_eq!(actions, ["payer_call", "appeal"]);
+
+        let other = ClaimStatus::Submitted { claim: mock_claim().into(), tenant_id: "acme".to_string(), submitted_at: Instant::now(), metadata: ClaimMetadata::default() };
+        app_history.insert(other.key(), other).await;
+        let detail = async |uri: String| app.clone().oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap().status();
+        assert_eq!(detail(format!("/claims/{}", claim_id)).await, StatusCode::CONFLICT);
+        assert_eq!(detail(format!("/claims/{}?tenant=acme", claim_id)).await, StatusCode::OK);
+    }
+
+    /// Test that components can be paused and payers reconfigured through the API.
+    /// Expected: Pausing a payer is reflected in its handle; invalid settings are 400; unknown payers are 404.
+    #[tokio::test]
+    async fn test_component_endpoints() {
+        let payer = PayerSettings { min_response_time_secs: 1, max_response_time_secs: 2, ack_time_secs: None, denial_rate: 0.0, error_rate: 0.0, mismatch_policy: MismatchPolicy::Flag, telehealth_rate: 1.0 };
+        let handle = crate::control::PayerHandle::new(payer);
+        let mut controls = Controls::default();
+        controls.payers.insert("medicare".into(), handle.clone());
+        let app = router(ApiState { controls, ..ApiState::default() });
+
+        let response = app.clone().oneshot(Request::post("/payers/medicare/pause").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(handle.state(), RunState::Paused);
+
+        let request = Request::put("/payers/medicare/settings")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"min_response_time_secs": 1, "max_response_time_secs": 2, "denial_rate": 2.0}"#))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(handle.settings(), payer);
+
+        let response = app.oneshot(Request::post("/payers/anthem/stop").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    /// Test that a held remittance is released through the API exactly once.
+    /// Expected: The biller's hold fires on release; a second release and an unknown tenant are 404.
+    #[tokio::test]
+    async fn test_release_held_remittance() {
+        let held = HeldRemittances::new();
+        let released = held.hold(ClaimId::from("claim-1"));
+        let app = router(ApiState { held: BTreeMap::from([("acme".to_string(), held)]), ..ApiState::default() });
+        let release = async |uri: &str| app.clone().oneshot(Request::post(uri).body(Body::empty()).unwrap()).await.unwrap().status();
+
+        assert_eq!(release("/billers/acme/claims/claim-1/release").await, StatusCode::ACCEPTED);
+        assert!(released.await.is_ok());
+        assert_eq!(release("/billers/acme/claims/claim-1/release").await, StatusCode::NOT_FOUND);
+        assert_eq!(release("/billers/other/claims/claim-1/release").await, StatusCode::NOT_FOUND);
+    }
+}
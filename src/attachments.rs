@@ -0,0 +1,85 @@
+use anyhow::{Context, anyhow};
+use serde::Deserialize;
+
+use crate::outcomes::CodeRange;
+use crate::schema::PayerClaim;
+
+/// Procedures in the range are only adjudicated once the claim carries a document of this type
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AttachmentRule {
+    pub procedures: CodeRange,
+    /// Report type code the payer asks for, e.g. `OB` for an operative note
+    pub document_type: String,
+}
+
+/// A payer's rules for which procedures need supporting documentation
+///
+/// Every rule whose range contains a line's procedure code applies, so a line may need
+/// several documents. The default policy needs none
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct AttachmentPolicy(Vec<AttachmentRule>);
+
+impl AttachmentPolicy {
+    pub fn new(rules: Vec<AttachmentRule>) -> anyhow::Result<Self> {
+        if let Some(rule) = rules.iter().find(|rule| rule.document_type.trim().is_empty()) {
+            return Err(anyhow!("{}: an attachment rule must name a document type", rule.procedures));
+        }
+        Ok(Self(rules))
+    }
+
+    /// Read a JSON array of rules, e.g.
+    /// `[{"procedures": "27000-27899", "document_type": "OB"}]`
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path).with_context(|| format!("reading attachment rules {}", path))?;
+        let rules = serde_json::from_str(&contents).with_context(|| format!("parsing attachment rules {}", path))?;
+        Self::new(rules).with_context(|| format!("invalid attachment rules {}", path))
+    }
+
+    /// Document types the claim's procedures need but its attachments do not include, without repeats
+    pub fn missing(&self, claim: &PayerClaim) -> Vec<String> {
+        let mut missing: Vec<String> = Vec::new();
+        for line in &claim.service_lines {
+            let code = line.procedure_code.trim().to_uppercase();
+            for rule in self.0.iter().filter(|rule| rule.procedures.contains(&code)) {
+                let attached = claim
+                    .attachments
+                    .iter()
+                    .any(|attachment| attachment.document_type.eq_ignore_ascii_case(&rule.document_type));
+                if !attached && !missing.contains(&rule.document_type) {
+                    missing.push(rule.document_type.clone());
+                }
+            }
+        }
+        missing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Attachment;
+    use crate::test_utils::mock_claim;
+
+    /// Test that a procedure under a rule needs its document type until the claim carries it.
+    /// Expected: An orthopedic surgery line needs an operative note, an office visit needs nothing; a rule without a document type is refused.
+    #[test]
+    fn test_attachment_policy() {
+        let rules = serde_json::from_str(r#"[{"procedures": "27000-27899", "document_type": "OB"}]"#).unwrap();
+        let policy = AttachmentPolicy::new(rules).unwrap();
+        let mut claim = mock_claim();
+        assert!(policy.missing(&claim).is_empty());
+        claim.service_lines[0].procedure_code = "27447".to_string();
+        claim.service_lines.push(claim.service_lines[0].clone());
+        assert_eq!(policy.missing(&claim), vec!["OB".to_string()]);
+        claim.attachments.push(Attachment {
+            document_type: "ob".to_string(),
+            id: "att-1".to_string(),
+            payload_path: None,
+        });
+        assert!(policy.missing(&claim).is_empty());
+
+        let rules = serde_json::from_str(r#"[{"procedures": "27447", "document_type": " "}]"#).unwrap();
+        assert!(AttachmentPolicy::new(rules).is_err());
+    }
+}
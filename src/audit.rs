@@ -0,0 +1,92 @@
+use serde::Serialize;
+
+use crate::remittance::Remittance;
+use crate::schema::PayerClaim;
+
+/// Largest difference between a line's remitted total and its charge treated as rounding
+const BALANCE_TOLERANCE: f64 = 1e-2;
+
+/// One way a remittance failed to reconcile with the claim it answers
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Discrepancy {
+    /// Service line the discrepancy is on; `None` for the remittance as a whole
+    pub service_line_id: Option<String>,
+    pub reason: String,
+    /// Remitted total minus billed charge; zero when the discrepancy is not about amounts
+    pub variance: f64,
+}
+
+/// Check a remittance against the claim the clearinghouse submitted, independently of the payer's own check
+///
+/// Every billed line must be answered exactly once by id, in any order, in the claim's
+/// currency, and each line's shares must add up to its charge. A billed line left
+/// unanswered is short its whole charge; a remittance line for nothing billed is over by its total
+pub fn audit_remittance(remittance: &Remittance, claim: &PayerClaim) -> Vec<Discrepancy> {
+    let mut discrepancies = Vec::new();
+    if remittance.currency() != claim.currency() {
+        discrepancies.push(Discrepancy {
+            service_line_id: None,
+            reason: format!("remitted in {} but billed in {}", remittance.currency(), claim.currency()),
+            variance: 0.0,
+        });
+    }
+    let lines = remittance.match_lines(claim);
+    for line in &lines.missing {
+        discrepancies.push(Discrepancy {
+            service_line_id: Some(line.service_line_id.clone()),
+            reason: "billed line not remitted".to_string(),
+            variance: -(line.unit_charge_amount * line.units as f64),
+        });
+    }
+    for remit in &lines.extra {
+        discrepancies.push(Discrepancy {
+            service_line_id: Some(remit.service_line_id().to_string()),
+            reason: "remittance line not billed".to_string(),
+            variance: remit.total(),
+        });
+    }
+    for (remit, line) in lines.matched {
+        let billed = line.unit_charge_amount * line.units as f64;
+        let variance = remit.total() - billed;
+        if variance.abs() > BALANCE_TOLERANCE {
+            discrepancies.push(Discrepancy {
+                service_line_id: Some(line.service_line_id.clone()),
+                reason: format!("remitted {:.2} against {:.2} billed", remit.total(), billed),
+                variance,
+            });
+        }
+    }
+    discrepancies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{mock_claim, mock_adjudication_date};
+
+    /// Test that the audit passes a correct remittance and catches short and unmatched ones.
+    /// Expected: No discrepancies for a priced claim, even with its lines out of order; a mispriced one is short by its
+    /// not-allowed amount; a line left unanswered is reported short its charge.
+    #[test]
+    fn test_audit_remittance() {
+        let claim = mock_claim();
+        assert!(audit_remittance(&Remittance::from_claim(&claim, mock_adjudication_date()), &claim).is_empty());
+
+        let short = Remittance::from_claim(&claim, mock_adjudication_date()).with_miscalculation();
+        let discrepancies = audit_remittance(&short, &claim);
+        assert_eq!(discrepancies.len(), 1);
+        assert!((discrepancies[0].variance + 150.0 * 0.02).abs() < 1e-9);
+
+        let mut two_lines = claim.clone();
+        let mut second = claim.service_lines[0].clone();
+        second.service_line_id = "sl2".to_string();
+        two_lines.service_lines.push(second);
+        let remit = Remittance::from_claim(&two_lines, mock_adjudication_date());
+        two_lines.service_lines.reverse();
+        assert!(audit_remittance(&remit, &two_lines).is_empty());
+        let discrepancies = audit_remittance(&Remittance::from_claim(&claim, mock_adjudication_date()), &two_lines);
+        assert_eq!(discrepancies.len(), 1);
+        assert_eq!(discrepancies[0].service_line_id.as_deref(), Some("sl2"));
+        assert_eq!(discrepancies[0].variance, -150.0);
+    }
+}
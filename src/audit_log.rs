@@ -0,0 +1,342 @@
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use tokio::fs::OpenOptions;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::ids::ClaimId;
+
+/// What a component did with a claim record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// Clearinghouse accepted the claim and created its history record
+    Submit,
+    /// Clearinghouse disclosed the claim to its payer
+    Route,
+    /// Payer took the claim up for adjudication
+    Adjudicate,
+    /// Clearinghouse recorded the remittance in the claim's history
+    Remit,
+    /// Clearinghouse voided the claim's record at its biller's request
+    Void,
+    /// Biller posted the remittance to its ledger
+    Post,
+    /// API caller asked for the claim's cancellation
+    Cancel,
+    /// API caller released the claim's held remittance for posting
+    Release,
+    /// API caller viewed the claim's record
+    View,
+    /// A billing worker added a note to the claim's work history
+    Note,
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AuditAction::Submit => "submit",
+            AuditAction::Route => "route",
+            AuditAction::Adjudicate => "adjudicate",
+            AuditAction::Remit => "remit",
+            AuditAction::Void => "void",
+            AuditAction::Post => "post",
+            AuditAction::Cancel => "cancel",
+            AuditAction::Release => "release",
+            AuditAction::View => "view",
+            AuditAction::Note => "note",
+        })
+    }
+}
+
+/// One line of the audit log
+///
+/// Each entry's `hash` covers its own fields and the previous entry's hash, so editing,
+/// dropping, or reordering an entry breaks the chain from that point on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Monotonic sequence number, starting at 1 and continuing across appended runs
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    /// Who touched the record, e.g. `clearinghouse`, `payer:medicare`, `biller:acme`, `api`
+    pub component: String,
+    pub action: AuditAction,
+    pub claim_id: ClaimId,
+    /// What a `note` entry said
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Hash of the previous entry; empty for the first
+    pub prev_hash: String,
+    /// SHA-256 of this entry chained to `prev_hash`, hex encoded
+    pub hash: String,
+}
+
+impl AuditEntry {
+    fn new(seq: u64, component: String, action: AuditAction, claim_id: ClaimId, note: Option<String>, prev_hash: String) -> Self {
+        let mut entry = Self {
+            seq,
+            timestamp: Utc::now(),
+            component,
+            action,
+            claim_id,
+            note,
+            prev_hash,
+            hash: String::new(),
+        };
+        entry.hash = entry.expected_hash();
+        entry
+    }
+
+    fn expected_hash(&self) -> String {
+        let mut content = format!(
+            "{}|{}|{}|{}|{}|{}",
+            self.prev_hash,
+            self.seq,
+            self.timestamp.to_rfc3339(),
+            self.component,
+            self.action,
+            self.claim_id
+        );
+        // entries without a note hash as they did before notes existed
+        if let Some(note) = &self.note {
+            content.push('|');
+            content.push_str(note);
+        }
+        format!("{:x}", Sha256::digest(content.as_bytes()))
+    }
+}
+
+#[derive(Debug)]
+enum AuditCommand {
+    Record { component: String, action: AuditAction, claim_id: ClaimId, note: Option<String> },
+    Flush(oneshot::Sender<()>),
+}
+
+/// Append-only, hash-chained JSONL log of who accessed or modified each claim record
+///
+/// A single writer task assigns sequence numbers and chains hashes, so entries follow the
+/// order they are recorded
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    tx: mpsc::UnboundedSender<AuditCommand>,
+}
+
+impl AuditLog {
+    /// Open (or create) the log at `path` and start its writer task
+    ///
+    /// Existing entries are verified and kept, and new ones chained after them;
+    /// a log whose chain is already broken is refused
+    pub async fn open(path: &str) -> anyhow::Result<Self> {
+        let (mut seq, mut prev_hash) = match tokio::fs::try_exists(path).await? {
+            true => {
+                let entries = read_audit_log(path).await?;
+                verify_chain(&entries).map_err(|e| anyhow!("{}: {}", path, e))?;
+                entries.last().map_or((0, String::new()), |entry| (entry.seq, entry.hash.clone()))
+            }
+            false => (0, String::new()),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path).await?;
+        let mut writer = BufWriter::new(file);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let path = path.to_string();
+        tokio::spawn(async move {
+            while let Some(command) = rx.recv().await {
+                match command {
+                    AuditCommand::Record { component, action, claim_id, note } => {
+                        seq += 1;
+                        let entry = AuditEntry::new(seq, component, action, claim_id, note, prev_hash.clone());
+                        prev_hash = entry.hash.clone();
+                        if let Err(e) = write_entry(&mut writer, &entry).await {
+                            eprintln!("Failed to write audit entry {} to {}: {}", seq, path, e);
+                        }
+                    }
+                    AuditCommand::Flush(done) => {
+                        if let Err(e) = writer.flush().await {
+                            eprintln!("Failed to flush audit log {}: {}", path, e);
+                        }
+                        let _ = done.send(());
+                    }
+                }
+            }
+            let _ = writer.flush().await;
+        });
+        Ok(Self { tx })
+    }
+
+    /// Queue an entry for the log; never blocks the caller
+    pub fn record(&self, component: impl Into<String>, action: AuditAction, claim_id: &ClaimId) {
+        self.send(AuditCommand::Record {
+            component: component.into(),
+            action,
+            claim_id: claim_id.clone(),
+            note: None,
+        });
+    }
+
+    /// Queue a `note` entry carrying what the note said; never blocks the caller
+    pub fn record_note(&self, component: impl Into<String>, claim_id: &ClaimId, note: &str) {
+        self.send(AuditCommand::Record {
+            component: component.into(),
+            action: AuditAction::Note,
+            claim_id: claim_id.clone(),
+            note: Some(note.to_string()),
+        });
+    }
+
+    fn send(&self, command: AuditCommand) {
+        if self.tx.send(command).is_err() {
+            eprintln!("Audit log writer stopped");
+        }
+    }
+
+    /// Wait until every entry recorded so far is written to disk
+    pub async fn flush(&self) {
+        let (done_tx, done_rx) = oneshot::channel();
+        if self.tx.send(AuditCommand::Flush(done_tx)).is_ok() {
+            let _ = done_rx.await;
+        }
+    }
+}
+
+async fn write_entry<W: AsyncWriteExt + Unpin>(writer: &mut W, entry: &AuditEntry) -> anyhow::Result<()> {
+    let mut json = serde_json::to_string(entry)?;
+    json.push('\n');
+    writer.write_all(json.as_bytes()).await?;
+    Ok(())
+}
+
+/// Read every entry from an audit log file, in file order
+pub async fn read_audit_log(path: &str) -> anyhow::Result<Vec<AuditEntry>> {
+    let contents = tokio::fs::read_to_string(path).await?;
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|e| anyhow!("{}:{}: invalid audit entry: {}", path, index + 1, e))
+        })
+        .collect()
+}
+
+/// Check that entries are numbered in order and each hash chains to the one before,
+/// naming the first entry that does not
+pub fn verify_chain(entries: &[AuditEntry]) -> anyhow::Result<()> {
+    let mut prev_hash = "";
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.seq != index as u64 + 1 {
+            return Err(anyhow!("audit entry {} is out of sequence (expected {})", entry.seq, index + 1));
+        }
+        if entry.prev_hash != prev_hash || entry.hash != entry.expected_hash() {
+            return Err(anyhow!("audit entry {} has been altered", entry.seq));
+        }
+        prev_hash = &entry.hash;
+    }
+    Ok(())
+}
+
+/// File format of an audit log export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AuditExportFormat {
+    #[default]
+    Csv,
+    /// One JSON entry per line, as stored
+    Jsonl,
+}
+
+impl FromStr for AuditExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(AuditExportFormat::Csv),
+            "jsonl" | "json" => Ok(AuditExportFormat::Jsonl),
+            other => Err(anyhow!("Unknown audit export format: {} (expected csv or jsonl)", other)),
+        }
+    }
+}
+
+impl fmt::Display for AuditExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AuditExportFormat::Csv => "csv",
+            AuditExportFormat::Jsonl => "jsonl",
+        })
+    }
+}
+
+/// Write `entries` in `format`, CSV with a header row
+pub fn export<W: Write>(mut writer: W, entries: &[AuditEntry], format: AuditExportFormat) -> anyhow::Result<()> {
+    match format {
+        AuditExportFormat::Csv => {
+            writeln!(writer, "seq,timestamp,component,action,claim_id,hash,note")?;
+            for entry in entries {
+                // notes are free text, so they are always quoted
+                let note = entry.note.as_deref().map_or(String::new(), |note| format!("\"{}\"", note.replace('"', "\"\"")));
+                writeln!(
+                    writer,
+                    "{},{},{},{},{},{},{}",
+                    entry.seq,
+                    entry.timestamp.to_rfc3339(),
+                    entry.component,
+                    entry.action,
+                    entry.claim_id,
+                    entry.hash,
+                    note
+                )?;
+            }
+        }
+        AuditExportFormat::Jsonl => {
+            for entry in entries {
+                writeln!(writer, "{}", serde_json::to_string(entry)?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that recorded entries, notes included, chain across reopening and that editing one is detected.
+    /// Expected: Four entries with seq 1..=4 that verify and export as CSV, the note quoted; changing the note or the second entry's component fails verification there.
+    #[tokio::test]
+    async fn test_audit_log_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let path = path.to_str().unwrap();
+        let claim_id = ClaimId::new("abc123");
+
+        let log = AuditLog::open(path).await.unwrap();
+        log.record("clearinghouse", AuditAction::Submit, &claim_id);
+        log.record("payer:medicare", AuditAction::Adjudicate, &claim_id);
+        log.flush().await;
+        drop(log);
+        let reopened = AuditLog::open(path).await.unwrap();
+        reopened.record("api", AuditAction::View, &claim_id);
+        reopened.record_note("denials:jsmith", &claim_id, r#"appeal: sent "records""#);
+        reopened.flush().await;
+
+        let mut entries = read_audit_log(path).await.unwrap();
+        assert_eq!(entries.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        verify_chain(&entries).unwrap();
+        let mut csv = Vec::new();
+        export(&mut csv, &entries, AuditExportFormat::Csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 5);
+        assert!(csv.lines().nth(2).unwrap().contains(",payer:medicare,adjudicate,abc123,"));
+        assert!(csv.lines().nth(4).unwrap().ends_with(r#","appeal: sent ""records""""#));
+
+        entries[3].note = Some("appeal: sent nothing".to_string());
+        assert!(verify_chain(&entries).unwrap_err().to_string().contains("entry 4"));
+
+        entries[1].component = "payer:anthem".to_string();
+        let error = verify_chain(&entries).unwrap_err().to_string();
+        assert!(error.contains("entry 2"), "{}", error);
+    }
+}
@@ -0,0 +1,413 @@
+use anyhow::{Context, anyhow};
+use chrono::{Datelike, NaiveDate};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use crate::ids::MemberId;
+use crate::remittance::Remittance;
+use crate::schema::{CoverageKey, Insurance};
+
+/// Kind of service a procedure code bills, deciding whether a copay applies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceCategory {
+    /// Preventive medicine visits (99381-99429)
+    Preventive,
+    /// Office and outpatient evaluation and management (99202-99215)
+    OfficeVisit,
+    /// Emergency department visits (99281-99285)
+    Emergency,
+    /// Radiology (70010-79999)
+    Imaging,
+    /// Pathology and laboratory (80047-89398)
+    Lab,
+    /// Surgery (10004-69990)
+    Surgery,
+    Other,
+}
+
+impl ServiceCategory {
+    /// Category of a CPT procedure code; HCPCS and unknown codes are `Other`
+    pub fn of(procedure_code: &str) -> Self {
+        let code = procedure_code.trim();
+        let within = |first: &str, last: &str| code.len() == first.len() && first <= code && code <= last;
+        if within("99381", "99429") {
+            ServiceCategory::Preventive
+        } else if within("99202", "99215") {
+            ServiceCategory::OfficeVisit
+        } else if within("99281", "99285") {
+            ServiceCategory::Emergency
+        } else if within("70010", "79999") {
+            ServiceCategory::Imaging
+        } else if within("80047", "89398") {
+            ServiceCategory::Lab
+        } else if within("10004", "69990") {
+            ServiceCategory::Surgery
+        } else {
+            ServiceCategory::Other
+        }
+    }
+}
+
+/// Cost sharing of one health plan
+///
+/// A line in a category with a copay costs the patient the copay and nothing else;
+/// other lines go toward the deductible, then coinsurance. Once the patient has paid
+/// `out_of_pocket_max` in the plan year, the payer pays the whole allowed amount
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PlanDesign {
+    pub plan_id: String,
+    /// Month, 1 to 12, on whose first day each plan year starts and the patient's totals reset
+    #[serde(default = "default_plan_year_start")]
+    pub plan_year_start: u32,
+    /// Fraction of billed charges the payer's fee schedule allows; the rest is not allowed
+    #[serde(default = "default_fee_schedule")]
+    pub fee_schedule: f64,
+    pub deductible: f64,
+    pub out_of_pocket_max: f64,
+    /// Patient's fraction of the allowed amount once the deductible is met
+    pub coinsurance: f64,
+    /// Flat amount per line billed in these categories; a copay of zero covers the service in full
+    #[serde(default)]
+    pub copays: BTreeMap<ServiceCategory, f64>,
+}
+
+fn default_fee_schedule() -> f64 {
+    0.98
+}
+
+fn default_plan_year_start() -> u32 {
+    1
+}
+
+impl Default for PlanDesign {
+    fn default() -> Self {
+        Self {
+            plan_id: "standard".to_string(),
+            plan_year_start: default_plan_year_start(),
+            fee_schedule: default_fee_schedule(),
+            deductible: 500.0,
+            out_of_pocket_max: 4_000.0,
+            coinsurance: 0.20,
+            copays: BTreeMap::from([
+                (ServiceCategory::Preventive, 0.0),
+                (ServiceCategory::OfficeVisit, 25.0),
+                (ServiceCategory::Emergency, 250.0),
+            ]),
+        }
+    }
+}
+
+impl PlanDesign {
+    fn validate(&self) -> Result<(), String> {
+        if self.plan_id.trim().is_empty() {
+            return Err("a plan must have a plan_id".to_string());
+        }
+        if !(1..=12).contains(&self.plan_year_start) {
+            return Err(format!("{}: plan_year_start must be a month from 1 to 12", self.plan_id));
+        }
+        if self.fee_schedule <= 0.0 || !(0.0..=1.0).contains(&self.fee_schedule) {
+            return Err(format!("{}: fee_schedule must be above 0 and at most 1", self.plan_id));
+        }
+        if !(0.0..=1.0).contains(&self.coinsurance) {
+            return Err(format!("{}: coinsurance must be between 0 and 1", self.plan_id));
+        }
+        let mut amounts = [self.deductible, self.out_of_pocket_max].into_iter().chain(self.copays.values().copied());
+        if amounts.any(|amount| !amount.is_finite() || amount < 0.0) {
+            return Err(format!("{}: deductible, out-of-pocket maximum, and copays must be non-negative", self.plan_id));
+        }
+        if self.out_of_pocket_max < self.deductible {
+            return Err(format!("{}: out-of-pocket maximum is below the deductible", self.plan_id));
+        }
+        Ok(())
+    }
+
+    /// First day of the plan year containing `date`
+    pub fn plan_year_of(&self, date: NaiveDate) -> NaiveDate {
+        let year = if date.month() >= self.plan_year_start { date.year() } else { date.year() - 1 };
+        NaiveDate::from_ymd_opt(year, self.plan_year_start, 1).expect("plan year start is a validated month")
+    }
+
+    /// Patient's share of a line's allowed amount given what they have paid so far this plan year,
+    /// which is added to `accumulator`
+    ///
+    /// A waived copay is paid by the payer instead
+    pub fn patient_share(
+        &self,
+        category: ServiceCategory,
+        allowed: f64,
+        copay_waived: bool,
+        accumulator: &mut Accumulator,
+    ) -> PatientShare {
+        let mut remaining = (self.out_of_pocket_max - accumulator.out_of_pocket).max(0.0);
+        let mut owe = |amount: f64| {
+            let owed = amount.clamp(0.0, remaining);
+            remaining -= owed;
+            owed
+        };
+        let share = match self.copays.get(&category) {
+            Some(_) if copay_waived => PatientShare::default(),
+            Some(copay) => PatientShare {
+                copay: owe(copay.min(allowed)),
+                ..PatientShare::default()
+            },
+            None => {
+                let deductible = owe((self.deductible - accumulator.deductible_met).min(allowed));
+                let coinsurance = owe((allowed - deductible) * self.coinsurance);
+                PatientShare { deductible, copay: 0.0, coinsurance }
+            }
+        };
+        accumulator.deductible_met += share.deductible;
+        accumulator.out_of_pocket += share.total();
+        share
+    }
+}
+
+/// What a patient owes on one line, by kind
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PatientShare {
+    pub deductible: f64,
+    pub copay: f64,
+    pub coinsurance: f64,
+}
+
+impl PatientShare {
+    pub fn total(&self) -> f64 {
+        self.deductible + self.copay + self.coinsurance
+    }
+}
+
+/// What a patient has paid toward their plan's deductible and out-of-pocket maximum
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Accumulator {
+    /// First day of the plan year the totals count toward
+    pub plan_year: Option<NaiveDate>,
+    pub deductible_met: f64,
+    pub out_of_pocket: f64,
+}
+
+impl Accumulator {
+    /// Start the totals over if `plan_year` is later than the one they count toward
+    pub fn roll_over(&mut self, plan_year: NaiveDate) {
+        if self.plan_year.is_none_or(|current| current < plan_year) {
+            *self = Accumulator {
+                plan_year: Some(plan_year),
+                ..Accumulator::default()
+            };
+        }
+    }
+}
+
+/// Where a patient stands against their plan's limits in the current plan year
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenefitStatus {
+    pub coverage: CoverageKey,
+    pub plan_id: String,
+    pub plan_year: NaiveDate,
+    pub deductible_met: f64,
+    pub deductible: f64,
+    pub out_of_pocket: f64,
+    pub out_of_pocket_max: f64,
+}
+
+impl BenefitStatus {
+    /// Status on `date` of a patient with these totals, which count for nothing once their plan year is over
+    pub fn new(coverage: CoverageKey, plan: &PlanDesign, mut accumulator: Accumulator, date: NaiveDate) -> Self {
+        let plan_year = plan.plan_year_of(date);
+        accumulator.roll_over(plan_year);
+        Self {
+            coverage,
+            plan_id: plan.plan_id.clone(),
+            plan_year,
+            deductible_met: accumulator.deductible_met,
+            deductible: plan.deductible,
+            out_of_pocket: accumulator.out_of_pocket,
+            out_of_pocket_max: plan.out_of_pocket_max,
+        }
+    }
+
+    pub fn is_deductible_met(&self) -> bool {
+        self.deductible_met >= self.deductible - MET_TOLERANCE
+    }
+
+    pub fn is_out_of_pocket_met(&self) -> bool {
+        self.out_of_pocket >= self.out_of_pocket_max - MET_TOLERANCE
+    }
+}
+
+/// Shortfall from a limit still counted as meeting it, for rounding
+const MET_TOLERANCE: f64 = 1e-6;
+
+/// A payer's plan designs and the members enrolled in each
+///
+/// Members are enrolled by subscriber id, so dependents are on their subscriber's plan;
+/// anyone not enrolled is on the first plan. The default has only the standard plan
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenefitPlans {
+    plans: Vec<PlanDesign>,
+    /// Index into `plans` of each enrolled subscriber's plan
+    members: HashMap<MemberId, usize>,
+}
+
+impl Default for BenefitPlans {
+    fn default() -> Self {
+        Self {
+            plans: vec![PlanDesign::default()],
+            members: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct BenefitsFile {
+    plans: Vec<PlanDesign>,
+    #[serde(default)]
+    members: HashMap<MemberId, String>,
+}
+
+impl BenefitPlans {
+    /// Plans, the first of which covers anyone not enrolled, and the plan id of each enrolled subscriber
+    pub fn new(plans: Vec<PlanDesign>, members: HashMap<MemberId, String>) -> anyhow::Result<Self> {
+        if plans.is_empty() {
+            return Err(anyhow!("at least one plan is needed"));
+        }
+        for plan in &plans {
+            plan.validate().map_err(|e| anyhow!(e))?;
+        }
+        let members = members
+            .into_iter()
+            .map(|(member_id, plan_id)| match plans.iter().position(|plan| plan.plan_id == plan_id) {
+                Some(index) => Ok((member_id, index)),
+                None => Err(anyhow!("member {} is enrolled in unknown plan {}", member_id, plan_id)),
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { plans, members })
+    }
+
+    /// Read plans and enrollments from JSON, e.g.
+    /// `{"plans": [{"plan_id": "hdhp", "deductible": 3000, "out_of_pocket_max": 7000, "coinsurance": 0.3}], "members": {"sub123": "hdhp"}}`
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path).with_context(|| format!("reading benefit plans {}", path))?;
+        let file: BenefitsFile =
+            serde_json::from_str(&contents).with_context(|| format!("parsing benefit plans {}", path))?;
+        Self::new(file.plans, file.members).with_context(|| format!("invalid benefit plans {}", path))
+    }
+
+    /// Plan covering a claim's patient
+    pub fn plan(&self, insurance: &Insurance) -> &PlanDesign {
+        self.subscriber_plan(insurance.subscriber_id())
+    }
+
+    /// Plan a subscriber and their dependents are enrolled in
+    pub fn subscriber_plan(&self, subscriber_id: &MemberId) -> &PlanDesign {
+        let index = self.members.get(subscriber_id).copied().unwrap_or(0);
+        &self.plans[index]
+    }
+}
+
+/// Each patient's running totals against their plan, shared by a payer's adjudicators
+#[derive(Debug, Clone, Default)]
+pub struct Accumulators(Arc<Mutex<HashMap<CoverageKey, Accumulator>>>);
+
+impl Accumulators {
+    pub fn get(&self, key: &CoverageKey) -> Accumulator {
+        self.0.lock().unwrap().get(key).copied().unwrap_or_default()
+    }
+
+    /// Run `f` on a patient's accumulator with no other adjudicator touching it meanwhile
+    pub fn update<T>(&self, key: &CoverageKey, f: impl FnOnce(&mut Accumulator) -> T) -> T {
+        let mut accumulators = self.0.lock().unwrap();
+        f(accumulators.entry(key.clone()).or_default())
+    }
+
+    /// Every patient's totals, in coverage order
+    pub fn snapshot(&self) -> Vec<(CoverageKey, Accumulator)> {
+        let mut accumulators: Vec<_> = self.0.lock().unwrap().iter().map(|(key, accumulator)| (key.clone(), *accumulator)).collect();
+        accumulators.sort_by(|a, b| a.0.cmp(&b.0));
+        accumulators
+    }
+
+    /// Take back what a remittance added to a patient's totals, when it is priced again
+    pub fn refund(&self, key: &CoverageKey, remittance: &Remittance) {
+        let lines = remittance.service_line_remittances();
+        self.update(key, |accumulator| {
+            accumulator.deductible_met -= lines.iter().map(|line| line.deductible_amount()).sum::<f64>();
+            accumulator.out_of_pocket -= lines.iter().map(|line| line.patient_responsibility()).sum::<f64>();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::mock_claim;
+
+    /// Test that a plan charges the copay, deductible, and coinsurance in turn and stops at the out-of-pocket maximum.
+    /// Expected: An office visit costs its copay; surgery fills the deductible then 20% coinsurance; past the maximum the patient owes nothing.
+    #[test]
+    fn test_patient_share() {
+        let plan = PlanDesign {
+            out_of_pocket_max: 1_000.0,
+            ..PlanDesign::default()
+        };
+        let mut accumulator = Accumulator::default();
+        let visit = plan.patient_share(ServiceCategory::of("99213"), 147.0, false, &mut accumulator);
+        assert_eq!(visit, PatientShare { copay: 25.0, ..PatientShare::default() });
+        assert_eq!(plan.patient_share(ServiceCategory::OfficeVisit, 147.0, true, &mut accumulator).total(), 0.0);
+
+        let surgery = plan.patient_share(ServiceCategory::of("27447"), 1_500.0, false, &mut accumulator);
+        assert_eq!(surgery, PatientShare { deductible: 500.0, copay: 0.0, coinsurance: 200.0 });
+        let surgery = plan.patient_share(ServiceCategory::Surgery, 5_000.0, false, &mut accumulator);
+        assert_eq!(surgery.coinsurance, 275.0);
+        assert_eq!(accumulator, Accumulator { plan_year: None, deductible_met: 500.0, out_of_pocket: 1_000.0 });
+        assert_eq!(plan.patient_share(ServiceCategory::Lab, 50.0, false, &mut accumulator).total(), 0.0);
+    }
+
+    /// Test that members are matched to plans by subscriber id and that bad plans are refused.
+    /// Expected: An enrolled subscriber's dependent gets the enrolled plan, anyone else the first plan; an unknown plan id or coinsurance above 1 is an error.
+    #[test]
+    fn test_benefit_plans() {
+        let file: BenefitsFile = serde_json::from_str(
+            r#"{"plans": [{"plan_id": "ppo", "deductible": 250, "out_of_pocket_max": 3000, "coinsurance": 0.1},
+                          {"plan_id": "hdhp", "deductible": 3000, "out_of_pocket_max": 7000, "coinsurance": 0.3,
+                           "copays": {"preventive": 0}}],
+                "members": {"sub789": "hdhp"}}"#,
+        )
+        .unwrap();
+        let plans = BenefitPlans::new(file.plans, file.members).unwrap();
+        let mut claim = mock_claim();
+        assert_eq!(plans.plan(&claim.insurance).plan_id, "ppo");
+        claim.insurance.patient_member_id = "sub789".into();
+        assert_eq!(plans.plan(&claim.insurance).plan_id, "hdhp");
+
+        let members = HashMap::from([("sub789".into(), "gold".to_string())]);
+        assert!(BenefitPlans::new(vec![PlanDesign::default()], members).is_err());
+        let plan = PlanDesign { coinsurance: 1.5, ..PlanDesign::default() };
+        assert!(BenefitPlans::new(vec![plan], HashMap::new()).is_err());
+    }
+
+    /// Test that a patient's totals reset on the first day of a new plan year.
+    /// Expected: A July plan year counts March toward the previous July; the deductible met in June is no longer met on July 1st.
+    #[test]
+    fn test_plan_year_rollover() {
+        let plan = PlanDesign { plan_year_start: 7, ..PlanDesign::default() };
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(plan.plan_year_of(date(2025, 3, 1)), date(2024, 7, 1));
+        let mut accumulator = Accumulator::default();
+        accumulator.roll_over(plan.plan_year_of(date(2025, 6, 1)));
+        plan.patient_share(ServiceCategory::Surgery, 2_000.0, false, &mut accumulator);
+
+        let coverage = mock_claim().insurance.coverage_key();
+        let june = BenefitStatus::new(coverage.clone(), &plan, accumulator, date(2025, 6, 30));
+        assert!(june.is_deductible_met());
+        assert!(!june.is_out_of_pocket_met());
+        let july = BenefitStatus::new(coverage, &plan, accumulator, date(2025, 7, 1));
+        assert_eq!((july.plan_year, july.deductible_met), (date(2025, 7, 1), 0.0));
+        assert!(!july.is_deductible_met());
+
+        accumulator.roll_over(date(2025, 7, 1));
+        assert_eq!(accumulator.out_of_pocket, 0.0);
+        assert!(BenefitPlans::new(vec![PlanDesign { plan_year_start: 13, ..PlanDesign::default() }], HashMap::new()).is_err());
+    }
+}
@@ -1,14 +1,83 @@
+use tokio::sync::Mutex;
 use tokio::sync::mpsc::{Receiver, Sender};
-use tokio::time::Duration;
 
+use crate::audit_log::{AuditAction, AuditLog};
+use crate::claim_ids::ClaimIdGenerator;
 use crate::config::Config;
+use crate::control::BillerHandle;
+use crate::debugger::Debugger;
+use crate::held_remittances::HeldRemittances;
+use crate::ledger::Ledger;
 use crate::logging::log_claim_event;
-use crate::message::{ClaimEnvelope, ClaimMessage};
-use crate::schema::PayerClaim;
+use crate::message::{ClaimEnvelope, ClaimMessage, ClaimMetadata, ClaimOrigin, PatientMessage, PatientStatement, RemittanceMessage};
+use crate::posting::{self, WriteOffRules};
+use crate::remittance::{Balancing, Remittance};
+use crate::remittance_sink::SharedRemittanceSink;
+use crate::schema::{Attachment, PayerClaim};
+use crate::reader::ProcessedClaims;
+use crate::rejects::LineError;
+use crate::supervisor::Heartbeat;
+use crate::tenant::DEFAULT_TENANT;
+use crate::validation;
+use crate::ids::ClaimId;
 
 use std::sync::Arc;
+use std::time::Instant;
+use tracing::Instrument;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+/// Optional downstream integrations for the biller
+///
+/// When a ledger is present, remittances are posted to it on receipt.
+/// When a patient channel is present, a statement is sent for any patient balance
+/// left after the write-off rules are applied.
+/// A remittance held for review is not posted until it is released from `held`.
+/// Every remittance received is then passed to each sink.
+#[derive(Clone, Default)]
+pub struct BillerHooks {
+    pub ledger: Option<Arc<Mutex<Ledger>>>,
+    pub patient_tx: Option<Sender<PatientMessage>>,
+    pub write_off_rules: WriteOffRules,
+    /// Acknowledged once each claim taken off the input is submitted, so its reader can checkpoint it
+    pub processed: Option<ProcessedClaims>,
+    /// Billing organization this biller submits for (default: `tenant::DEFAULT_TENANT`)
+    pub tenant_id: Option<String>,
+    /// Runtime pause/resume/stop control over claim submission
+    pub control: Option<BillerHandle>,
+    /// Input file the claims come from, recorded in each claim's origin metadata
+    pub source_file: Option<String>,
+    /// Downstream systems told about each remittance
+    pub sinks: Vec<SharedRemittanceSink>,
+    /// Records each remittance posted to the ledger
+    pub audit_log: Option<AuditLog>,
+    /// Holds each claim taken off the input at `biller:<tenant>`, and each remittance
+    /// received at `biller:<tenant>:remittances`, while paused
+    pub debugger: Option<Debugger>,
+    /// Beaten each time a claim is submitted
+    pub heartbeat: Heartbeat,
+    /// Ids for the child claims of a claim split for its payer's line limit
+    pub claim_ids: ClaimIdGenerator,
+    /// Remittances held for review, each posted once a reviewer releases it
+    pub held: HeldRemittances,
+}
+
+/// Per-claim state shared with each remittance listener
+#[derive(Clone)]
+struct ListenerContext {
+    test_notify: Option<Sender<ClaimId>>,
+    verbose: bool,
+    remittances_received: Arc<AtomicUsize>,
+    expected_claims: Arc<AtomicUsize>,
+    shutdown_tx: Sender<()>,
+    hooks: BillerHooks,
+}
+
+impl ListenerContext {
+    fn tenant_id(&self) -> &str {
+        self.hooks.tenant_id.as_deref().unwrap_or(DEFAULT_TENANT)
+    }
+}
+
 /// Biller task that processes claims received over a PayerClaim channel.
 ///
 /// For each incoming claim:
@@ -17,55 +86,152 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 /// - Wraps the claim and response channel in a `ClaimEnvelope`.
 /// - Sends the envelope to the clearinghouse via the `ClaimMessage` channel.
 ///
-/// The ingest rate is controlled by the configured interval.
+/// Ingestion pacing is shaped upstream by the reader's rate limiter.
+/// Shutdown is signalled once `total_claims` remittances are received; pass 0 when
+/// the count is unknown (e.g. stdin input) to stop after the input ends instead.
 pub async fn run_biller(
+    config: Config,
+    rx: Receiver<PayerClaim>,
+    tx: Sender<ClaimMessage>,
+    test_notify: Option<Sender<ClaimId>>, //optional notification for remittance
+    total_claims: usize,
+    shutdown_tx: Sender<()>,
+) -> anyhow::Result<()> {
+    run_biller_with_hooks(config, rx, tx, test_notify, total_claims, shutdown_tx, BillerHooks::default()).await
+}
+
+/// Biller task with ledger posting and patient statements enabled via `hooks`
+pub(crate) async fn run_biller_with_hooks(
     config: Config,
     mut rx: Receiver<PayerClaim>,
     tx: Sender<ClaimMessage>,
-    test_notify: Option<Sender<String>>, //optional notification for remittance
+    test_notify: Option<Sender<ClaimId>>,
     total_claims: usize,
     shutdown_tx: Sender<()>,
+    hooks: BillerHooks,
 ) -> anyhow::Result<()> {
-    if config.ingest_rate == 0 {
-        return Err(anyhow::anyhow!("Config ingest_rate must be non-zero"));
-    }
-    let interval = Duration::from_secs(config.ingest_rate);
-    let mut ticker = tokio::time::interval(interval);
     let verbose = config.verbose;
     if verbose {
         log_claim_event("biller", "-", "start", "Starting biller task");
     }
-    let remittances_received = Arc::new(AtomicUsize::new(0));
+    let expected = if total_claims == 0 { usize::MAX } else { total_claims };
+    let mut control = hooks.control.as_ref().map(|handle| handle.subscribe());
+    let ctx = ListenerContext {
+        test_notify,
+        verbose,
+        remittances_received: Arc::new(AtomicUsize::new(0)),
+        expected_claims: Arc::new(AtomicUsize::new(expected)),
+        shutdown_tx,
+        hooks,
+    };
+    let mut claims_read = 0;
     let mut claims_sent = 0;
+    let mut stopped = false;
 
-    while let Some(claim) = rx.recv().await {
-        ticker.tick().await;
-        claims_sent += 1;
-        process_claim(
-            claim,
-            &tx,
-            &test_notify,
-            verbose,
-            remittances_received.clone(),
-            total_claims,
-            shutdown_tx.clone(),
-        ).await?;
-        if claims_sent == total_claims {
+    loop {
+        let claim = match &mut control {
+            Some(control) => {
+                if !control.proceed().await {
+                    stopped = true;
+                    break;
+                }
+                tokio::select! {
+                    claim = rx.recv() => claim,
+                    _ = control.changed() => continue,
+                }
+            }
+            None => rx.recv().await,
+        };
+        let Some(claim) = claim else {
+            break;
+        };
+        if let Some(debugger) = &ctx.hooks.debugger {
+            let channel = format!("biller:{}", ctx.tenant_id());
+            debugger
+                .checkpoint(&channel, || format!("claim {} for {}, {:.2} billed", claim.claim_id, claim.insurance.payer_id, claim.total_charge()))
+                .await;
+        }
+        claims_read += 1;
+        let claim_id = claim.claim_id.clone();
+        // the reader checks this too, but claims handed over in memory reach the biller unchecked
+        match validation::check_currency(claim, &config.currencies) {
+            Ok(claim) => {
+                let claims = match config.max_service_lines.get(&claim.insurance.payer_id) {
+                    Some(&max_lines) => split_claim(claim, max_lines, &ctx),
+                    None => vec![claim],
+                };
+                for claim in claims {
+                    claims_sent += 1;
+                    process_claim(claim, claims_read as u64, &tx, &ctx).await?;
+                }
+            }
+            Err(err) => skip_claim(&claim_id, err, &ctx).await,
+        }
+        if let Some(processed) = &ctx.hooks.processed {
+            processed.ack(&claim_id);
+        }
+        ctx.hooks.heartbeat.beat();
+        if claims_read == total_claims {
             break;
         }
     }
+    if stopped && verbose {
+        log_claim_event("biller", "-", "stopped", &format!("Stopped after submitting {} claims", claims_sent));
+    }
+    if total_claims == 0 || stopped {
+        // input ended: now we know how many remittances to wait for
+        ctx.expected_claims.store(claims_sent, Ordering::SeqCst);
+        if ctx.remittances_received.load(Ordering::SeqCst) == claims_sent {
+            let _ = ctx.shutdown_tx.send(()).await;
+        }
+    }
     Ok(())
 }
 
+/// Drop a claim that cannot be billed, no longer waiting for its remittance
+async fn skip_claim(claim_id: &ClaimId, err: LineError, ctx: &ListenerContext) {
+    eprintln!("Invalid claim skipped: {}: {}: {}", claim_id, err.pointer, err.reason);
+    let expected = ctx.expected_claims.load(Ordering::SeqCst);
+    if expected != usize::MAX {
+        let expected = ctx.expected_claims.fetch_sub(1, Ordering::SeqCst) - 1;
+        if ctx.remittances_received.load(Ordering::SeqCst) == expected {
+            let _ = ctx.shutdown_tx.send(()).await;
+        }
+    }
+}
+
+/// Split a claim longer than its payer's service line limit into child claims, each remitted on its own
+///
+/// With a known claim count, the biller waits for one more remittance per extra child
+fn split_claim(claim: PayerClaim, max_lines: usize, ctx: &ListenerContext) -> Vec<PayerClaim> {
+    let parent_id = claim.claim_id.clone();
+    let lines = claim.service_lines.len();
+    let children = claim.split(max_lines, &ctx.hooks.claim_ids);
+    if children.len() > 1 {
+        let expected = ctx.expected_claims.load(Ordering::SeqCst);
+        if expected != usize::MAX {
+            ctx.expected_claims.fetch_add(children.len() - 1, Ordering::SeqCst);
+        }
+        if ctx.verbose {
+            log_claim_event(
+                "biller",
+                &parent_id,
+                "split_claim",
+                &format!("Split {} service lines into {} claims of at most {}", lines, children.len(), max_lines),
+            );
+        }
+    }
+    children
+}
+
 async fn process_claim(
     claim: PayerClaim,
+    line: u64,
     tx: &Sender<ClaimMessage>,
-    test_notify: &Option<Sender<String>>,
-    verbose: bool,
-    remittances_received: Arc<AtomicUsize>,
-    total_claims: usize,
-    shutdown_tx: Sender<()>,
+    ctx: &ListenerContext,
 ) -> anyhow::Result<()> {
+    let ingested_at = Instant::now();
+    let verbose = ctx.verbose;
     if verbose {
         log_claim_event(
             "biller",
@@ -75,20 +241,27 @@ async fn process_claim(
         );
     }
     let (rem_tx, rem_rx) = tokio::sync::mpsc::channel(1);
-    let test_notify_opt = test_notify.clone();
     let claim_id = claim.claim_id.clone();
-    tokio::spawn(listen_for_remittance(
-        rem_rx,
-        claim_id.clone(),
-        test_notify_opt,
-        verbose,
-        remittances_received,
-        total_claims,
-        shutdown_tx.clone(),
-    ));
+    let tenant_id = ctx.tenant_id().to_string();
+    let mut metadata = ClaimMetadata::new(ClaimOrigin {
+        file: ctx.hooks.source_file.clone(),
+        line: Some(line),
+    })
+    .with_ingested_at(ingested_at);
+    metadata.span = tracing::info_span!(
+        "claim",
+        claim_id = %claim_id,
+        correlation_id = %metadata.correlation_id,
+        tenant_id = %tenant_id,
+        priority = ?claim.priority(),
+    );
+    let claim = Arc::new(claim);
+    tokio::spawn(listen_for_remittance(rem_rx, Arc::clone(&claim), tx.clone(), ctx.clone(), metadata.span.clone()));
     let envelope = ClaimEnvelope {
         claim,
         response_tx: rem_tx,
+        tenant_id,
+        metadata,
     };
     if verbose {
         log_claim_event(
@@ -98,46 +271,185 @@ async fn process_claim(
             &format!("Sending claim envelope to clearinghouse: {}", &claim_id),
         );
     }
-    if tx.send(ClaimMessage::NewClaim(envelope)).await.is_err() {
+    if tx.send(ClaimMessage::NewClaim(Box::new(envelope))).await.is_err() {
         eprintln!("Clearinghouse dropped");
         return Err(anyhow::anyhow!("Clearinghouse channel dropped"));
     }
     Ok(())
 }
 
+/// Wait for the claim's remittance, sending any attachments the payer asks for meanwhile
 async fn listen_for_remittance(
-    mut rem_rx: tokio::sync::mpsc::Receiver<crate::message::RemittanceMessage>,
-    claim_id: String,
-    test_notify_opt: Option<Sender<String>>,
-    verbose: bool,
-    remittances_received: Arc<AtomicUsize>,
-    total_claims: usize,
-    shutdown_tx: Sender<()>,
+    mut rem_rx: tokio::sync::mpsc::Receiver<RemittanceMessage>,
+    claim: Arc<PayerClaim>,
+    tx: Sender<ClaimMessage>,
+    ctx: ListenerContext,
+    span: tracing::Span,
 ) {
-    if let Some(_response) = rem_rx.recv().await {
-        if verbose {
-            log_claim_event(
-                "biller",
-                &claim_id,
-                "received_remittance",
-                &format!("Received remittance for claim: {}", &claim_id),
-            );
+    let claim_id = claim.claim_id.clone();
+    loop {
+        let Some(msg) = rem_rx.recv().await else {
+            return;
+        };
+        if let Some(debugger) = &ctx.hooks.debugger {
+            debugger.checkpoint(&format!("biller:{}:remittances", ctx.tenant_id()), || msg.summary()).await;
         }
-        if let Some(tx) = test_notify_opt {
-            let _ = tx.send(claim_id).await;
+        match msg {
+            RemittanceMessage::AttachmentRequest { document_types, .. } => {
+                send_attachments(&claim, document_types, &tx, &ctx).await;
+                continue;
+            }
+            // the clearinghouse keeps acknowledgments for its reports; the remittance is still to come
+            RemittanceMessage::Acknowledged { .. } => continue,
+            RemittanceMessage::Processed(remittance) => {
+                if ctx.verbose {
+                    log_claim_event(
+                        "biller",
+                        &claim_id,
+                        "received_remittance",
+                        &format!("Received remittance for claim: {}", &claim_id),
+                    );
+                }
+                if let Balancing::Held(reason) = remittance.balancing() {
+                    // Waits in the exceptions queue; posting it would book amounts that do not balance
+                    eprintln!("Remittance for claim {} held for review, not posted until released: {}", claim_id, reason);
+                    let release = ctx.hooks.held.hold(claim_id.clone());
+                    let (claim, remittance, ctx) = (claim.clone(), remittance.clone(), ctx.clone());
+                    tokio::spawn(async move {
+                        if release.await.is_ok() {
+                            post_and_bill_patient(&claim, &remittance, &ctx).await;
+                        }
+                    });
+                } else {
+                    post_and_bill_patient(&claim, &remittance, &ctx)
+                        .instrument(tracing::info_span!(parent: &span, "biller.post"))
+                        .await;
+                }
+                deliver_to_sinks(&claim, &remittance, &ctx).await;
+            }
+            RemittanceMessage::Duplicate(_) => {
+                // Already posted when the original submission was remitted
+                if ctx.verbose {
+                    log_claim_event(
+                        "biller",
+                        &claim_id,
+                        "duplicate_remittance",
+                        "Claim was a resubmission, remittance not posted again",
+                    );
+                }
+            }
+            RemittanceMessage::Rejected { reason, .. } => {
+                // Nothing to post; the claim still counts as settled so the biller can finish
+                if ctx.verbose {
+                    log_claim_event("biller", &claim_id, "claim_rejected", &format!("Claim rejected: {}", reason));
+                }
+            }
+            RemittanceMessage::Voided { .. } => {
+                // Cancelled before it was remitted: nothing was paid, so nothing to post
+                if ctx.verbose {
+                    log_claim_event("biller", &claim_id, "claim_voided", "Claim voided before adjudication");
+                }
+            }
+        }
+        break;
+    }
+    if let Some(tx) = ctx.test_notify {
+        let _ = tx.send(claim_id).await;
+    }
+    let count = ctx.remittances_received.fetch_add(1, Ordering::SeqCst) + 1;
+    if count == ctx.expected_claims.load(Ordering::SeqCst) {
+        let _ = ctx.shutdown_tx.send(()).await;
+    }
+}
+
+/// Tell each downstream sink about a remittance, logging those that fail
+async fn deliver_to_sinks(claim: &PayerClaim, remittance: &Remittance, ctx: &ListenerContext) {
+    let tenant_id = ctx.tenant_id();
+    for sink in &ctx.hooks.sinks {
+        if let Err(e) = sink.receive(tenant_id, claim, remittance).await {
+            eprintln!("Remittance sink failed for claim {}: {:#}", claim.claim_id, e);
         }
-        let count = remittances_received.fetch_add(1, Ordering::SeqCst) + 1;
-        if count == total_claims {
-                let _ = shutdown_tx.send(()).await;
+    }
+}
+
+/// Answer a payer's request with the provider's documentation for the claim (a 275)
+///
+/// The simulated provider always has the documents on file, numbered by claim and type
+async fn send_attachments(claim: &PayerClaim, document_types: Vec<String>, tx: &Sender<ClaimMessage>, ctx: &ListenerContext) {
+    let attachments: Vec<Attachment> = document_types
+        .into_iter()
+        .map(|document_type| Attachment {
+            id: format!("{}-{}", claim.claim_id, document_type),
+            document_type,
+            payload_path: None,
+        })
+        .collect();
+    if ctx.verbose {
+        log_claim_event(
+            "biller",
+            &claim.claim_id,
+            "sending_attachments",
+            &format!("Sending {} attachments requested by the payer", attachments.len()),
+        );
+    }
+    let message = ClaimMessage::Attachments {
+        claim_id: claim.claim_id.clone(),
+        tenant_id: ctx.hooks.tenant_id.clone().unwrap_or_else(|| DEFAULT_TENANT.to_string()),
+        attachments,
+    };
+    if tx.send(message).await.is_err() {
+        eprintln!("Clearinghouse dropped; attachments for claim {} not sent", claim.claim_id);
+    }
+}
+
+/// Post a remittance to the ledger and send the patient a statement for their share
+async fn post_and_bill_patient(claim: &PayerClaim, remittance: &Remittance, ctx: &ListenerContext) {
+    let rules = &ctx.hooks.write_off_rules;
+    let patient_share = match &ctx.hooks.ledger {
+        Some(ledger) => {
+            let mut ledger = ledger.lock().await;
+            // the payer took back the original's payment; undo its postings before booking the correction
+            if let Some(original_id) = claim.replaces() {
+                let reversed = ledger.reverse_claim(original_id);
+                if ctx.verbose {
+                    log_claim_event(
+                        "biller",
+                        &claim.claim_id,
+                        "original_reversed",
+                        &format!("Reversed {} postings of replaced claim {}", reversed, original_id),
+                    );
+                }
             }
+            if let Some(audit_log) = &ctx.hooks.audit_log {
+                let tenant_id = ctx.tenant_id();
+                audit_log.record(format!("biller:{}", tenant_id), AuditAction::Post, &claim.claim_id);
+            }
+            posting::post_remittance(&mut ledger, claim, remittance, rules)
+        }
+        None => {
+            let share = posting::patient_responsibility(remittance);
+            if rules.is_small_balance(share) { 0.0 } else { share }
+        }
+    };
+    if let Some(patient_tx) = &ctx.hooks.patient_tx
+        && patient_share > 0.0
+    {
+        let statement = PatientStatement {
+            claim_id: claim.claim_id.clone(),
+            patient_id: claim.insurance.patient_member_id.clone(),
+            payer_id: claim.insurance.payer_id.clone(),
+            amount: patient_share,
+        };
+        if patient_tx.send(PatientMessage::Statement(statement)).await.is_err() {
+            eprintln!("Patient payer dropped");
         }
     }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::message::RemittanceMessage;
-    use crate::{remittance::mock_remittance, schema::mock_claim};
+    use crate::test_utils::{ClaimBuilder, mock_claim, mock_remittance};
 
     /// Test that the biller task processes a claim, sends it to the clearinghouse, and receives a remittance notification.
     /// Expected: The claim is sent, remittance is received, and notification channel receives the correct claim ID.
@@ -148,6 +460,7 @@ mod tests {
             file_path: "mock_path.json".to_string(),
             ingest_rate: 1,
             verbose: true,
+            ..Config::default()
         };
 
         // input channel for claims
@@ -159,9 +472,12 @@ mod tests {
         // notifcation channel
         let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel(1);
 
+        // shutdown channel
+        let (shutdown_tx, _shutdown_rx) = tokio::sync::mpsc::channel(1);
+
         // spawn biller task
         tokio::spawn(async move {
-            let _ = run_biller(mock_config, claim_rx, out_tx, Some(notify_tx), 1, None).await;
+            let _ = run_biller(mock_config, claim_rx, out_tx, Some(notify_tx), 1, shutdown_tx).await;
         });
 
         // send a mock claim
@@ -197,13 +513,15 @@ mod tests {
             file_path: "mock_path.json".to_string(),
             ingest_rate: 1,
             verbose: false,
+            ..Config::default()
         };
         let (claim_tx, claim_rx) = tokio::sync::mpsc::channel(1);
         let (out_tx, _out_rx) = tokio::sync::mpsc::channel(1);
         let (notify_tx, _notify_rx) = tokio::sync::mpsc::channel(1);
+        let (shutdown_tx, _shutdown_rx) = tokio::sync::mpsc::channel(1);
         // Spawn biller task, then drop the output channel to simulate clearinghouse down
         let biller_handle = tokio::spawn(async move {
-            run_biller(mock_config, claim_rx, out_tx, Some(notify_tx), 1, None).await
+            run_biller(mock_config, claim_rx, out_tx, Some(notify_tx), 1, shutdown_tx).await
         });
         // Drop the output channel after spawning
         // (out_tx is moved into the spawned task, so we can't drop it here)
@@ -226,12 +544,14 @@ mod tests {
             file_path: "mock_path.json".to_string(),
             ingest_rate: 1,
             verbose: false,
+            ..Config::default()
         };
         let (claim_tx, claim_rx) = tokio::sync::mpsc::channel(1);
         let (out_tx, mut out_rx) = tokio::sync::mpsc::channel(1);
         let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel(1);
+        let (shutdown_tx, _shutdown_rx) = tokio::sync::mpsc::channel(1);
         tokio::spawn(async move {
-            let _ = run_biller(mock_config, claim_rx, out_tx, Some(notify_tx), 1, None).await;
+            let _ = run_biller(mock_config, claim_rx, out_tx, Some(notify_tx), 1, shutdown_tx).await;
         });
         let mock_claim = mock_claim();
         claim_tx.send(mock_claim.clone()).await.unwrap();
@@ -249,26 +569,52 @@ mod tests {
         assert_eq!(notified_id, mock_claim.claim_id);
     }
 
-    /// Test that the biller returns an error with a clear message if the config ingest_rate is zero.
-    /// Expected: run_biller returns an error containing "ingest_rate must be non-zero".
+    /// Test that the biller leaves pacing to the reader and does not look at ingest_rate.
+    /// Expected: A biller configured with ingest_rate 0 still forwards the claim to the clearinghouse.
     #[tokio::test]
-    async fn test_biller_invalid_config() {
+    async fn test_biller_ignores_ingest_rate() {
         let mock_config = Config {
             file_path: "mock_path.json".to_string(),
-            ingest_rate: 0, // Invalid: zero interval
+            ingest_rate: 0,
             verbose: false,
+            ..Config::default()
         };
-        let (_claim_tx, claim_rx) = tokio::sync::mpsc::channel(1);
-        let (out_tx, _out_rx) = tokio::sync::mpsc::channel(1);
+        let (claim_tx, claim_rx) = tokio::sync::mpsc::channel(1);
+        let (out_tx, mut out_rx) = tokio::sync::mpsc::channel(1);
         let (notify_tx, _notify_rx) = tokio::sync::mpsc::channel(1);
-        let result = run_biller(mock_config, claim_rx, out_tx, Some(notify_tx), 1, None).await;
-        assert!(result.is_err(), "Expected error with invalid ingest_rate");
-        let err_msg = format!("{}", result.unwrap_err());
-        assert!(
-            err_msg.contains("ingest_rate must be non-zero"),
-            "Unexpected error message: {}",
-            err_msg
-        );
+        let (shutdown_tx, _shutdown_rx) = tokio::sync::mpsc::channel(1);
+        tokio::spawn(async move {
+            let _ = run_biller(mock_config, claim_rx, out_tx, Some(notify_tx), 1, shutdown_tx).await;
+        });
+        let mock_claim = mock_claim();
+        claim_tx.send(mock_claim.clone()).await.unwrap();
+        let Some(ClaimMessage::NewClaim(envelope)) = out_rx.recv().await else {
+            panic!("Expected the claim to be forwarded");
+        };
+        assert_eq!(envelope.claim.claim_id, mock_claim.claim_id);
+    }
+
+    /// Test that a claim handed over in memory in a currency not accepted is skipped rather than billed.
+    /// Expected: Only the USD claim reaches the clearinghouse, and its remittance alone completes the run.
+    #[tokio::test]
+    async fn test_biller_skips_unaccepted_currency() {
+        let (claim_tx, claim_rx) = tokio::sync::mpsc::channel(2);
+        let (out_tx, mut out_rx) = tokio::sync::mpsc::channel(2);
+        let (shutdown_tx, mut shutdown_rx) = tokio::sync::mpsc::channel(1);
+        tokio::spawn(async move {
+            let _ = run_biller(Config::default(), claim_rx, out_tx, None, 2, shutdown_tx).await;
+        });
+        let mut euros = ClaimBuilder::new().claim_id("eur1").build();
+        euros.service_lines[0].unit_charge_currency = "EUR".to_string();
+        claim_tx.send(euros).await.unwrap();
+        claim_tx.send(ClaimBuilder::new().claim_id("usd1").build()).await.unwrap();
+        let Some(ClaimMessage::NewClaim(envelope)) = out_rx.recv().await else {
+            panic!("Expected the USD claim to be forwarded");
+        };
+        assert_eq!(envelope.claim.claim_id.as_str(), "usd1");
+        let _ = envelope.response_tx.send(RemittanceMessage::Processed(mock_remittance())).await;
+        assert!(shutdown_rx.recv().await.is_some());
+        assert!(out_rx.try_recv().is_err());
     }
 
     /// Test that the biller can process two claims with the same claim ID.
@@ -279,12 +625,14 @@ mod tests {
             file_path: "mock_path.json".to_string(),
             ingest_rate: 1,
             verbose: false,
+            ..Config::default()
         };
         let (claim_tx, claim_rx) = tokio::sync::mpsc::channel(2);
         let (out_tx, mut out_rx) = tokio::sync::mpsc::channel(2);
         let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel(2);
+        let (shutdown_tx, _shutdown_rx) = tokio::sync::mpsc::channel(1);
         tokio::spawn(async move {
-            let _ = run_biller(mock_config, claim_rx, out_tx, Some(notify_tx), 2, None).await;
+            let _ = run_biller(mock_config, claim_rx, out_tx, Some(notify_tx), 2, shutdown_tx).await;
         });
         let claim1 = mock_claim();
         let mut claim2 = mock_claim();
@@ -316,56 +664,30 @@ mod tests {
     /// Expected: The claim is sent, remittance is received, and notification channel receives the claim ID.
     #[tokio::test]
     async fn test_biller_empty_claim() {
-        use crate::schema::{Insurance, Organization, Patient, PayerClaim, Provider, ServiceLine};
+        use crate::schema::{Gender, Organization, Patient, Provider, ServiceLine};
         let mock_config = Config {
             file_path: "mock_path.json".to_string(),
             ingest_rate: 1,
             verbose: false,
+            ..Config::default()
         };
         let (claim_tx, claim_rx) = tokio::sync::mpsc::channel(1);
         let (out_tx, mut out_rx) = tokio::sync::mpsc::channel(1);
         let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel(1);
+        let (shutdown_tx, _shutdown_rx) = tokio::sync::mpsc::channel(1);
         tokio::spawn(async move {
-            let _ = run_biller(mock_config, claim_rx, out_tx, Some(notify_tx), 1, None).await;
+            let _ = run_biller(mock_config, claim_rx, out_tx, Some(notify_tx), 1, shutdown_tx).await;
         });
-        let empty_claim = PayerClaim {
-            claim_id: "empty1".to_string(),
-            place_of_service_code: 0,
-            insurance: Insurance {
-                payer_id: "".to_string(),
-                patient_member_id: "".to_string(),
-            },
-            patient: Patient {
-                first_name: "".to_string(),
-                last_name: "".to_string(),
-                gender: "".to_string(),
-                dob: "".to_string(),
-                email: None,
-                address: None,
-            },
-            organization: Organization {
-                name: "".to_string(),
-                billing_npi: None,
-                ein: None,
-                contact: None,
-                address: None,
-            },
-            rendering_provider: Provider {
-                first_name: "".to_string(),
-                last_name: "".to_string(),
-                npi: "".to_string(),
-            },
-            service_lines: vec![ServiceLine {
-                service_line_id: "".to_string(),
-                procedure_code: "".to_string(),
-                units: 0,
-                details: "".to_string(),
-                unit_charge_currency: "".to_string(),
-                unit_charge_amount: 0.0,
-                modifiers: None,
-                do_not_bill: None,
-            }],
-        };
+        // built whole, then emptied the way a sparse file reads, since the builder refuses empty fields
+        let mut empty_claim = ClaimBuilder::new().claim_id("empty1").build();
+        empty_claim.insurance.payer_id = "".into();
+        empty_claim.insurance.patient_member_id = "".into();
+        empty_claim.patient = Patient::new("", "", Gender::Unknown);
+        empty_claim.organization = Organization::new("");
+        empty_claim.rendering_provider = Provider::new("", "", "");
+        // still in an accepted currency, which the biller checks
+        empty_claim.service_lines = vec![ServiceLine::new("", "", 0, 0.0)];
+        empty_claim.diagnosis_codes.clear();
         claim_tx.send(empty_claim.clone()).await.unwrap();
         if let Some(ClaimMessage::NewClaim(envelope)) = out_rx.recv().await {
             let mock_remittance = mock_remittance();
@@ -380,4 +702,121 @@ mod tests {
             .expect("Expected remittance notification");
         assert_eq!(notified_id, empty_claim.claim_id);
     }
+
+    /// Test that a received remittance is posted to the ledger and a patient statement is issued.
+    /// Expected: Ledger holds charge, payer payment, and patient responsibility; statement amount matches.
+    #[tokio::test]
+    async fn test_biller_posts_remittance_and_issues_statement() {
+        use crate::ledger::EntryKind;
+        let mock_config = Config {
+            file_path: "mock_path.json".to_string(),
+            ingest_rate: 1,
+            verbose: false,
+            ..Config::default()
+        };
+        let (claim_tx, claim_rx) = tokio::sync::mpsc::channel(1);
+        let (out_tx, mut out_rx) = tokio::sync::mpsc::channel(1);
+        let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel(1);
+        let (shutdown_tx, _shutdown_rx) = tokio::sync::mpsc::channel(1);
+        let (patient_tx, mut patient_rx) = tokio::sync::mpsc::channel(1);
+        let ledger = Arc::new(Mutex::new(Ledger::new()));
+        let hooks = BillerHooks {
+            ledger: Some(ledger.clone()),
+            patient_tx: Some(patient_tx),
+            ..BillerHooks::default()
+        };
+        tokio::spawn(async move {
+            let _ = run_biller_with_hooks(mock_config, claim_rx, out_tx, Some(notify_tx), 1, shutdown_tx, hooks).await;
+        });
+        claim_tx.send(mock_claim()).await.unwrap();
+        if let Some(ClaimMessage::NewClaim(envelope)) = out_rx.recv().await {
+            let _ = envelope
+                .response_tx
+                .send(RemittanceMessage::Processed(mock_remittance()))
+                .await;
+        }
+        notify_rx.recv().await.expect("Expected remittance notification");
+
+        // mock_remittance: copay 10 + coinsurance 35 + deductible 5
+        let Some(PatientMessage::Statement(statement)) = patient_rx.recv().await else {
+            panic!("Expected patient statement");
+        };
+        assert_eq!(statement.patient_id, "pmid456");
+        assert!((statement.amount - 50.0).abs() < 1e-9);
+
+        let ledger = ledger.lock().await;
+        let kinds: Vec<_> = ledger.entries().iter().map(|e| e.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![EntryKind::Charge, EntryKind::PayerPayment, EntryKind::PatientResponsibility]
+        );
+    }
+
+    /// Test that a remittance held for review is posted only once a reviewer releases it.
+    /// Expected: The ledger is empty while held; after release the charge and payment are posted, and a second release is refused.
+    #[tokio::test]
+    async fn test_biller_posts_held_remittance_on_release() {
+        let (claim_tx, claim_rx) = tokio::sync::mpsc::channel(1);
+        let (out_tx, mut out_rx) = tokio::sync::mpsc::channel(1);
+        let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel(1);
+        let (shutdown_tx, _shutdown_rx) = tokio::sync::mpsc::channel(1);
+        let ledger = Arc::new(Mutex::new(Ledger::new()));
+        let held = HeldRemittances::new();
+        let hooks = BillerHooks {
+            ledger: Some(ledger.clone()),
+            held: held.clone(),
+            ..BillerHooks::default()
+        };
+        tokio::spawn(async move {
+            let _ = run_biller_with_hooks(Config::default(), claim_rx, out_tx, Some(notify_tx), 1, shutdown_tx, hooks).await;
+        });
+        claim_tx.send(mock_claim()).await.unwrap();
+        let Some(ClaimMessage::NewClaim(envelope)) = out_rx.recv().await else {
+            panic!("Expected claim envelope");
+        };
+        let remittance = mock_remittance().with_balancing(Balancing::Held("does not balance".to_string()));
+        envelope.response_tx.send(RemittanceMessage::Processed(remittance)).await.unwrap();
+        let claim_id = notify_rx.recv().await.expect("Expected remittance notification");
+
+        assert_eq!(held.claim_ids(), vec![claim_id.clone()]);
+        assert!(ledger.lock().await.entries().is_empty());
+
+        assert!(held.release(&claim_id));
+        assert!(!held.release(&claim_id));
+        let posted = async {
+            while ledger.lock().await.entries().is_empty() {
+                tokio::task::yield_now().await;
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(1), posted).await.expect("held remittance was not posted");
+        assert!(held.claim_ids().is_empty());
+    }
+
+    /// Test that an unknown claim count (0) shuts down once the input ends and all remittances arrive.
+    /// Expected: Shutdown is signalled after the single remittance for a closed input stream.
+    #[tokio::test]
+    async fn test_biller_shutdown_after_input_ends() {
+        let mock_config = Config {
+            file_path: "-".to_string(),
+            ..Config::default()
+        };
+        let (claim_tx, claim_rx) = tokio::sync::mpsc::channel(1);
+        let (out_tx, mut out_rx) = tokio::sync::mpsc::channel(1);
+        let (shutdown_tx, mut shutdown_rx) = tokio::sync::mpsc::channel(1);
+        tokio::spawn(async move {
+            let _ = run_biller(mock_config, claim_rx, out_tx, None, 0, shutdown_tx).await;
+        });
+        claim_tx.send(mock_claim()).await.unwrap();
+        drop(claim_tx);
+        if let Some(ClaimMessage::NewClaim(envelope)) = out_rx.recv().await {
+            let _ = envelope
+                .response_tx
+                .send(RemittanceMessage::Processed(mock_remittance()))
+                .await;
+        }
+        tokio::time::timeout(tokio::time::Duration::from_secs(2), shutdown_rx.recv())
+            .await
+            .expect("Timeout waiting for shutdown")
+            .expect("Expected shutdown signal");
+    }
 }
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use rand::Rng;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::time::sleep;
+
+/// How often the chaos layer misbehaves, per message relayed
+///
+/// The default never misbehaves
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChaosSettings {
+    /// Chance the channel closes instead of delivering the message, losing it
+    pub close_probability: f64,
+    /// Chance the message is held back for up to `max_delay`
+    pub delay_probability: f64,
+    pub max_delay: Duration,
+    /// Chance the message is delivered twice
+    pub duplicate_probability: f64,
+}
+
+impl ChaosSettings {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (name, probability) in [
+            ("close_probability", self.close_probability),
+            ("delay_probability", self.delay_probability),
+            ("duplicate_probability", self.duplicate_probability),
+        ] {
+            if !(0.0..=1.0).contains(&probability) {
+                return Err(anyhow::anyhow!("{} must be between 0 and 1", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Running totals of what the chaos layer did, shared by every channel it relays
+#[derive(Debug, Clone, Default)]
+pub struct ChaosStats {
+    closed: Arc<AtomicU64>,
+    delayed: Arc<AtomicU64>,
+    duplicated: Arc<AtomicU64>,
+}
+
+impl ChaosStats {
+    /// Channels closed, each losing the message that closed it
+    pub fn closed(&self) -> u64 {
+        self.closed.load(Ordering::Relaxed)
+    }
+
+    pub fn delayed(&self) -> u64 {
+        self.delayed.load(Ordering::Relaxed)
+    }
+
+    pub fn duplicated(&self) -> u64 {
+        self.duplicated.load(Ordering::Relaxed)
+    }
+}
+
+/// Injects failures into the channels between pipeline components
+#[derive(Debug, Clone)]
+pub struct ChaosLayer {
+    settings: ChaosSettings,
+    stats: ChaosStats,
+}
+
+impl ChaosLayer {
+    pub fn new(settings: ChaosSettings) -> anyhow::Result<Self> {
+        settings.validate()?;
+        Ok(Self {
+            settings,
+            stats: ChaosStats::default(),
+        })
+    }
+
+    pub fn stats(&self) -> ChaosStats {
+        self.stats.clone()
+    }
+
+    /// Bounded channel whose messages pass through a relay task that misbehaves per the settings
+    ///
+    /// Once the relay closes the channel, senders get errors and the receiver sees it end,
+    /// exactly as if the component at either end had stopped
+    pub fn channel<T: Clone + Send + 'static>(&self, capacity: usize) -> (Sender<T>, Receiver<T>) {
+        let (tx, mut relay_rx) = mpsc::channel::<T>(capacity);
+        let (relay_tx, rx) = mpsc::channel(capacity);
+        let settings = self.settings;
+        let stats = self.stats.clone();
+        tokio::spawn(async move {
+            while let Some(msg) = relay_rx.recv().await {
+                let (close, delay, duplicate) = {
+                    let mut rng = rand::rng();
+                    let delay = rng
+                        .random_bool(settings.delay_probability)
+                        .then(|| rng.random_range(Duration::ZERO..=settings.max_delay));
+                    (
+                        rng.random_bool(settings.close_probability),
+                        delay,
+                        rng.random_bool(settings.duplicate_probability),
+                    )
+                };
+                if close {
+                    stats.closed.fetch_add(1, Ordering::Relaxed);
+                    break;
+                }
+                if let Some(delay) = delay {
+                    stats.delayed.fetch_add(1, Ordering::Relaxed);
+                    sleep(delay).await;
+                }
+                if duplicate {
+                    stats.duplicated.fetch_add(1, Ordering::Relaxed);
+                    if relay_tx.send(msg.clone()).await.is_err() {
+                        break;
+                    }
+                }
+                if relay_tx.send(msg).await.is_err() {
+                    break;
+                }
+            }
+        });
+        (tx, rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invariants::{Invariant, InvariantChecker};
+    use crate::simulation::{BillerSpec, PayerSpec, Simulation};
+    use crate::test_utils::mock_claim;
+    use tokio::time::timeout;
+
+    fn claims(n: usize) -> Vec<crate::schema::PayerClaim> {
+        (0..n)
+            .map(|i| {
+                let mut claim = mock_claim();
+                claim.claim_id = format!("chaos-{}", i).into();
+                claim
+            })
+            .collect()
+    }
+
+    /// Test that the relay duplicates every message when told to, and closes on the first message when told to.
+    /// Expected: Each message arrives twice; after a close the receiver ends and senders fail.
+    #[tokio::test]
+    async fn test_chaos_channel() {
+        let layer = ChaosLayer::new(ChaosSettings { duplicate_probability: 1.0, ..ChaosSettings::default() }).unwrap();
+        let (tx, mut rx) = layer.channel(4);
+        tx.send(7).await.unwrap();
+        assert_eq!(rx.recv().await, Some(7));
+        assert_eq!(rx.recv().await, Some(7));
+        assert_eq!(layer.stats().duplicated(), 1);
+
+        let layer = ChaosLayer::new(ChaosSettings { close_probability: 1.0, ..ChaosSettings::default() }).unwrap();
+        let (tx, mut rx) = layer.channel(4);
+        tx.send(7).await.unwrap();
+        assert_eq!(rx.recv().await, None);
+        assert!(tx.send(8).await.is_err());
+        assert!(ChaosLayer::new(ChaosSettings { delay_probability: 2.0, ..ChaosSettings::default() }).is_err());
+    }
+
+    /// Test that the pipeline tolerates delayed and duplicated payer traffic.
+    /// Expected: Every claim is remitted once and posted once; duplicates are discarded by the clearinghouse.
+    #[tokio::test]
+    async fn test_pipeline_survives_duplicates_and_delays() {
+        let mut sim = Simulation::builder()
+            .payer(PayerSpec::new("medicare", 0, 0))
+            .claim_source(claims(20))
+            .chaos(ChaosSettings {
+                delay_probability: 0.5,
+                max_delay: Duration::from_millis(20),
+                duplicate_probability: 0.5,
+                ..ChaosSettings::default()
+            })
+            .build()
+            .unwrap();
+        timeout(Duration::from_secs(10), sim.wait()).await.expect("remittances delayed, not lost");
+        let violations = InvariantChecker::default().check_finished(&sim.history(), sim.ledgers()).await;
+        assert!(violations.is_empty(), "{:?}", violations);
+        assert!(sim.chaos_stats().unwrap().duplicated() > 0);
+    }
+
+    /// Test that claims lost to a closed channel do not vanish silently.
+    /// Expected: The run never finishes, and the invariant checker reports every lost claim as pending.
+    #[tokio::test]
+    async fn test_closed_channel_surfaces_lost_claims() {
+        let mut sim = Simulation::builder()
+            .payer(PayerSpec::new("medicare", 0, 0))
+            .biller(BillerSpec::default().with_source(claims(5)))
+            .chaos(ChaosSettings { close_probability: 1.0, ..ChaosSettings::default() })
+            .build()
+            .unwrap();
+        assert!(timeout(Duration::from_millis(500), sim.wait()).await.is_err());
+        let violations = InvariantChecker::default().check_finished(&sim.history(), sim.ledgers()).await;
+        assert_eq!(violations.len(), 5);
+        assert!(violations.iter().all(|v| v.invariant == Invariant::Terminal));
+        assert!(sim.chaos_stats().unwrap().closed() > 0);
+    }
+}
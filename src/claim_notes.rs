@@ -0,0 +1,112 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::audit_log::AuditLog;
+use crate::ids::ClaimKey;
+use crate::inventory::ClaimInventory;
+
+/// What a billing worker did about a claim when they wrote a note
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteAction {
+    /// A remark, with no action taken
+    #[default]
+    Note,
+    /// Called the payer about the claim
+    PayerCall,
+    /// Appealed the payer's decision
+    Appeal,
+    /// Corrected and resubmitted the claim
+    Correction,
+    /// Wrote off the claim's open balance
+    WriteOff,
+    /// Passed the claim to someone else to work
+    Escalation,
+}
+
+impl fmt::Display for NoteAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NoteAction::Note => "note",
+            NoteAction::PayerCall => "payer_call",
+            NoteAction::Appeal => "appeal",
+            NoteAction::Correction => "correction",
+            NoteAction::WriteOff => "write_off",
+            NoteAction::Escalation => "escalation",
+        })
+    }
+}
+
+/// A timestamped entry in a claim's work history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClaimNote {
+    pub timestamp: DateTime<Utc>,
+    /// Who wrote it, e.g. `denials:jsmith`
+    pub author: String,
+    #[serde(default)]
+    pub action: NoteAction,
+    pub text: String,
+}
+
+impl ClaimNote {
+    /// Note written now
+    pub fn new(author: impl Into<String>, action: NoteAction, text: impl Into<String>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            author: author.into(),
+            action,
+            text: text.into(),
+        }
+    }
+}
+
+/// Work history of every claim: the notes and actions billing workers attach to it
+///
+/// With an audit log, each note is also recorded there as a `note` entry naming its author, so
+/// the work history is kept in the tamper-evident trail beside every other touch of the claim.
+/// With a claim inventory, an appeal note moves a denied claim to appealed
+#[derive(Debug, Clone, Default)]
+pub struct ClaimNotes {
+    notes: Arc<Mutex<HashMap<ClaimKey, Vec<ClaimNote>>>>,
+    audit_log: Option<AuditLog>,
+    inventory: Option<ClaimInventory>,
+}
+
+impl ClaimNotes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record each note in `audit_log` as well
+    pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Count each claim appealed in `inventory`
+    pub fn with_inventory(mut self, inventory: ClaimInventory) -> Self {
+        self.inventory = Some(inventory);
+        self
+    }
+
+    /// Attach a note to a claim's work history
+    pub fn add(&self, key: &ClaimKey, note: ClaimNote) {
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.record_note(note.author.clone(), &key.claim_id, &format!("{}: {}", note.action, note.text));
+        }
+        if let Some(inventory) = &self.inventory
+            && note.action == NoteAction::Appeal
+        {
+            inventory.appeal(key);
+        }
+        self.notes.lock().unwrap().entry(key.clone()).or_default().push(note);
+    }
+
+    /// A claim's notes, oldest first
+    pub fn for_claim(&self, key: &ClaimKey) -> Vec<ClaimNote> {
+        self.notes.lock().unwrap().get(key).cloned().unwrap_or_default()
+    }
+}
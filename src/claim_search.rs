@@ -0,0 +1,174 @@
+use anyhow::anyhow;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ids::{ClaimId, MemberId, PayerId};
+use crate::message::ClaimStatus;
+use crate::phi::Deidentification;
+
+/// Claims returned per page unless a query asks for fewer
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Most claims returned in one page
+pub const MAX_PAGE_SIZE: usize = 500;
+
+/// Where a claim stands, as searched for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimState {
+    /// Submitted and waiting on its payer
+    Pending,
+    /// Remitted with at least part of it paid or owed by the patient
+    Paid,
+    /// Remitted as denied
+    Denied,
+    /// Cancelled by its biller before it was remitted
+    Voided,
+}
+
+impl FromStr for ClaimState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pending" => Ok(ClaimState::Pending),
+            "paid" => Ok(ClaimState::Paid),
+            "denied" => Ok(ClaimState::Denied),
+            "voided" => Ok(ClaimState::Voided),
+            other => Err(anyhow!("Unknown claim state: {} (expected pending, paid, denied, or voided)", other)),
+        }
+    }
+}
+
+impl fmt::Display for ClaimState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ClaimState::Pending => "pending",
+            ClaimState::Paid => "paid",
+            ClaimState::Denied => "denied",
+            ClaimState::Voided => "voided",
+        })
+    }
+}
+
+/// Filters over the claim history, all optional, and the page of matches wanted
+///
+/// Amounts are the claim's billed charge; dates are its date of service, both bounds
+/// inclusive. Claims without a date of service never match a date range
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ClaimQuery {
+    pub tenant: Option<String>,
+    pub patient_id: Option<MemberId>,
+    pub payer_id: Option<PayerId>,
+    pub status: Option<ClaimState>,
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// Matches to skip, in claim id order
+    pub offset: usize,
+    /// Matches to return, at most `MAX_PAGE_SIZE`
+    pub limit: usize,
+}
+
+impl Default for ClaimQuery {
+    fn default() -> Self {
+        Self {
+            tenant: None,
+            patient_id: None,
+            payer_id: None,
+            status: None,
+            min_amount: None,
+            max_amount: None,
+            from: None,
+            to: None,
+            offset: 0,
+            limit: DEFAULT_PAGE_SIZE,
+        }
+    }
+}
+
+impl ClaimQuery {
+    /// Summary of the claim if it passes every filter, a patient id filter matching the member id
+    /// as `deidentification` writes it
+    pub fn matches(&self, claim_id: &ClaimId, status: &ClaimStatus, deidentification: &Deidentification) -> Option<ClaimSummary> {
+        let summary = ClaimSummary::new(claim_id, status, deidentification);
+        let claim = status.claim();
+        let in_range = |bound: Option<NaiveDate>, keep: fn(&NaiveDate, &NaiveDate) -> bool| match bound {
+            Some(bound) => claim.service_date.is_some_and(|date| keep(&date, &bound)),
+            None => true,
+        };
+        let passes = self.tenant.as_ref().is_none_or(|tenant| *tenant == summary.tenant_id)
+            && self.patient_id.as_ref().is_none_or(|patient_id| *patient_id == summary.patient_id)
+            && self.payer_id.as_ref().is_none_or(|payer_id| *payer_id == summary.payer_id)
+            && self.status.is_none_or(|state| state == summary.status)
+            && self.min_amount.is_none_or(|min| summary.billed >= min)
+            && self.max_amount.is_none_or(|max| summary.billed <= max)
+            && in_range(self.from, NaiveDate::ge)
+            && in_range(self.to, NaiveDate::le);
+        passes.then_some(summary)
+    }
+
+    /// The requested page of `matches`, sorted by claim id
+    pub fn page(&self, mut matches: Vec<ClaimSummary>) -> ClaimPage {
+        matches.sort_by(|a, b| a.claim_id.cmp(&b.claim_id));
+        let total = matches.len();
+        let limit = self.limit.min(MAX_PAGE_SIZE);
+        let claims = matches.into_iter().skip(self.offset).take(limit).collect();
+        ClaimPage { total, offset: self.offset, limit, claims }
+    }
+}
+
+/// One claim found by a search
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClaimSummary {
+    pub claim_id: ClaimId,
+    pub tenant_id: String,
+    pub patient_id: MemberId,
+    pub payer_id: PayerId,
+    pub status: ClaimState,
+    pub billed: f64,
+    /// What the payer paid, once remitted
+    pub payer_paid: Option<f64>,
+    pub service_date: Option<NaiveDate>,
+}
+
+impl ClaimSummary {
+    /// Summary naming the patient by member id as `deidentification` writes it
+    pub fn new(claim_id: &ClaimId, status: &ClaimStatus, deidentification: &Deidentification) -> Self {
+        let claim = status.claim();
+        let (state, payer_paid) = match status {
+            ClaimStatus::Submitted { .. } => (ClaimState::Pending, None),
+            ClaimStatus::Voided { .. } => (ClaimState::Voided, None),
+            ClaimStatus::Remitted(record) => {
+                let remittance = record.remittance();
+                let state = if remittance.is_denied() { ClaimState::Denied } else { ClaimState::Paid };
+                (state, Some(remittance.payer_paid()))
+            }
+        };
+        Self {
+            claim_id: claim_id.clone(),
+            tenant_id: status.tenant_id().to_string(),
+            patient_id: deidentification.member_id(&claim.insurance.patient_member_id),
+            payer_id: status.payer_id().clone(),
+            status: state,
+            billed: claim.total_charge(),
+            payer_paid,
+            service_date: claim.service_date,
+        }
+    }
+}
+
+/// A page of search results and how many claims matched in all
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClaimPage {
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub claims: Vec<ClaimSummary>,
+}
+
+
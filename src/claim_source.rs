@@ -0,0 +1,405 @@
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::time::Duration;
+
+use axum::Router;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::post;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::time::sleep;
+
+use crate::claim_ids::ClaimIdGenerator;
+use crate::json_faker::fake_payer_claim_with_ids;
+use crate::logging::log_claim_event;
+use crate::reader::{self, ReaderOptions};
+use crate::schema::PayerClaim;
+
+/// How often a watched directory is scanned for new files unless configured otherwise
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where a biller's claims come from
+///
+/// A source sends claims into the biller's channel until it runs out of claims or the biller
+/// stops listening, so the biller consumes every source alike. Implement it to feed a
+/// simulation from anywhere; `Vec<PayerClaim>` and `mpsc::Receiver<PayerClaim>` are sources too
+pub trait ClaimSource: Send + 'static {
+    fn stream(self, tx: Sender<PayerClaim>, verbose: bool) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    /// Claims the source will send, when known before it starts; the biller then signals
+    /// completion after that many remittances instead of when the source ends
+    fn expected_claims(&self) -> Option<usize> {
+        None
+    }
+
+    /// File the claims are read from, beside which rejected lines are written by default
+    fn source_file(&self) -> Option<&str> {
+        None
+    }
+}
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// `ClaimSource` with its future boxed, so billers can hold sources of any type
+trait DynClaimSource: Send {
+    fn stream(self: Box<Self>, tx: Sender<PayerClaim>, verbose: bool) -> BoxFuture<anyhow::Result<()>>;
+
+    fn expected_claims(&self) -> Option<usize>;
+
+    fn source_file(&self) -> Option<&str>;
+}
+
+impl<S: ClaimSource> DynClaimSource for S {
+    fn stream(self: Box<Self>, tx: Sender<PayerClaim>, verbose: bool) -> BoxFuture<anyhow::Result<()>> {
+        Box::pin(ClaimSource::stream(*self, tx, verbose))
+    }
+
+    fn expected_claims(&self) -> Option<usize> {
+        ClaimSource::expected_claims(self)
+    }
+
+    fn source_file(&self) -> Option<&str> {
+        ClaimSource::source_file(self)
+    }
+}
+
+/// A claim source of any type, as a biller holds it
+pub(crate) struct BoxedClaimSource(Box<dyn DynClaimSource>);
+
+impl BoxedClaimSource {
+    pub(crate) fn new(source: impl ClaimSource) -> Self {
+        Self(Box::new(source))
+    }
+
+    pub(crate) async fn stream(self, tx: Sender<PayerClaim>, verbose: bool) -> anyhow::Result<()> {
+        self.0.stream(tx, verbose).await
+    }
+
+    pub(crate) fn expected_claims(&self) -> Option<usize> {
+        self.0.expected_claims()
+    }
+
+    pub(crate) fn source_file(&self) -> Option<&str> {
+        self.0.source_file()
+    }
+}
+
+/// Fixed list of claims, e.g. for tests
+impl ClaimSource for Vec<PayerClaim> {
+    async fn stream(self, tx: Sender<PayerClaim>, _verbose: bool) -> anyhow::Result<()> {
+        for claim in self {
+            if tx.send(claim).await.is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn expected_claims(&self) -> Option<usize> {
+        Some(self.len())
+    }
+}
+
+/// Claims pushed by the caller
+impl ClaimSource for Receiver<PayerClaim> {
+    async fn stream(mut self, tx: Sender<PayerClaim>, _verbose: bool) -> anyhow::Result<()> {
+        while let Some(claim) = self.recv().await {
+            if tx.send(claim).await.is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// JSONL claims file (or `-` for stdin), optionally gzip or zstd compressed
+#[derive(Debug, Clone)]
+pub struct JsonlFile {
+    path: String,
+    options: ReaderOptions,
+}
+
+impl JsonlFile {
+    /// Read claims from `path` with default reader options
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            options: ReaderOptions::default(),
+        }
+    }
+
+    /// Checkpointing, reject capture, rate limiting, and validation
+    pub fn with_options(mut self, options: ReaderOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl ClaimSource for JsonlFile {
+    async fn stream(self, tx: Sender<PayerClaim>, verbose: bool) -> anyhow::Result<()> {
+        reader::stream_claims_with_options(&self.path, &self.options, tx, verbose).await
+    }
+
+    fn source_file(&self) -> Option<&str> {
+        Some(&self.path)
+    }
+}
+
+/// Directory watched for claims files, each read once as it appears
+///
+/// Files are read in name order, those present at the start first. Move files into the
+/// directory once complete, since one is read as soon as a scan finds it. Watches until the
+/// biller stops listening
+#[derive(Debug, Clone)]
+pub struct DirectoryWatcher {
+    dir: PathBuf,
+    poll_interval: Duration,
+    options: ReaderOptions,
+}
+
+impl DirectoryWatcher {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            options: ReaderOptions::default(),
+        }
+    }
+
+    /// Time between scans for new files
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Reader options applied to every file
+    pub fn with_options(mut self, options: ReaderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Claims files in the directory not yet read, in name order
+    async fn scan(&self, read: &BTreeSet<PathBuf>) -> anyhow::Result<BTreeSet<PathBuf>> {
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        let mut found = BTreeSet::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let is_claims = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| [".jsonl", ".jsonl.gz", ".jsonl.zst"].iter().any(|ext| name.ends_with(ext)));
+            if is_claims && entry.file_type().await?.is_file() && !read.contains(&path) {
+                found.insert(path);
+            }
+        }
+        Ok(found)
+    }
+}
+
+impl ClaimSource for DirectoryWatcher {
+    async fn stream(self, tx: Sender<PayerClaim>, verbose: bool) -> anyhow::Result<()> {
+        if verbose {
+            log_claim_event("watcher", "-", "start", &format!("Watching {} for claims files", self.dir.display()));
+        }
+        let mut read = BTreeSet::new();
+        while !tx.is_closed() {
+            for path in self.scan(&read).await? {
+                if let Err(e) = reader::stream_claims_with_options(&path.to_string_lossy(), &self.options, tx.clone(), verbose).await {
+                    eprintln!("Failed to read claims file {}: {:?}", path.display(), e);
+                }
+                read.insert(path);
+            }
+            tokio::select! {
+                _ = tx.closed() => break,
+                _ = sleep(self.poll_interval) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Random but valid claims, as `json_faker` writes them
+#[derive(Debug, Clone, Default)]
+pub struct ClaimGenerator {
+    count: Option<usize>,
+    interval: Duration,
+    ids: ClaimIdGenerator,
+}
+
+impl ClaimGenerator {
+    /// Generate claims without end, as fast as the biller takes them
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop after this many claims
+    pub fn with_count(mut self, count: usize) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Wait this long between claims
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Identify claims from `ids` rather than with the default prefix
+    pub fn with_claim_ids(mut self, ids: ClaimIdGenerator) -> Self {
+        self.ids = ids;
+        self
+    }
+}
+
+impl ClaimSource for ClaimGenerator {
+    async fn stream(self, tx: Sender<PayerClaim>, verbose: bool) -> anyhow::Result<()> {
+        let mut generated = 0;
+        while self.count.is_none_or(|count| generated < count) {
+            let claim = fake_payer_claim_with_ids(&self.ids);
+            if verbose {
+                log_claim_event("generator", &claim.claim_id, "claim_generated", "Generated claim");
+            }
+            if tx.send(claim).await.is_err() {
+                break;
+            }
+            generated += 1;
+            if !self.interval.is_zero() {
+                sleep(self.interval).await;
+            }
+        }
+        Ok(())
+    }
+
+    fn expected_claims(&self) -> Option<usize> {
+        self.count
+    }
+}
+
+/// HTTP endpoint taking claims one at a time: `POST /claims` with a claim as JSON
+///
+/// Answers `202 Accepted` once the biller has the claim, and `422 Unprocessable Entity` with
+/// the reason for a claim the reader would reject. Serves until the biller stops listening
+#[derive(Debug)]
+pub struct HttpSource {
+    listener: TcpListener,
+    options: ReaderOptions,
+}
+
+impl HttpSource {
+    /// Listen on `addr`; port 0 picks a free port, reported by `local_addr`
+    pub async fn bind(addr: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            options: ReaderOptions::default(),
+        })
+    }
+
+    pub fn local_addr(&self) -> anyhow::Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Validation and allowed currencies applied to each claim
+    pub fn with_options(mut self, options: ReaderOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+#[derive(Clone)]
+struct HttpSourceState {
+    tx: Sender<PayerClaim>,
+    options: ReaderOptions,
+    source: String,
+}
+
+impl ClaimSource for HttpSource {
+    async fn stream(self, tx: Sender<PayerClaim>, verbose: bool) -> anyhow::Result<()> {
+        let source = format!("http://{}/claims", self.listener.local_addr()?);
+        if verbose {
+            log_claim_event("http_source", "-", "start", &format!("Taking claims at {}", source));
+        }
+        let state = HttpSourceState { tx: tx.clone(), options: self.options, source };
+        let router = Router::new().route("/claims", post(post_claim)).with_state(state);
+        axum::serve(self.listener, router)
+            .with_graceful_shutdown(async move { tx.closed().await })
+            .await?;
+        Ok(())
+    }
+}
+
+async fn post_claim(State(state): State<HttpSourceState>, body: String) -> Result<StatusCode, (StatusCode, String)> {
+    let claim = reader::check_claim(&body, &state.options, &state.source, 1).map_err(|err| {
+        let pointer = if err.pointer.is_empty() { "<document>" } else { &err.pointer };
+        (StatusCode::UNPROCESSABLE_ENTITY, format!("{}: {}", pointer, err.reason))
+    })?;
+    state
+        .tx
+        .send(claim)
+        .await
+        .map_err(|_| (StatusCode::SERVICE_UNAVAILABLE, "Biller has stopped".to_string()))?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::ClaimId;
+    use crate::test_utils::mock_claim;
+    use tokio::sync::mpsc;
+    use tokio::time::timeout;
+
+    /// Test that a watched directory's claims files are read once each, including files added later.
+    /// Expected: Claims from the file present at the start, then from the file added, and nothing from other files.
+    #[tokio::test]
+    async fn test_directory_watcher() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, claim_id: &str| {
+            let mut claim = mock_claim();
+            claim.claim_id = claim_id.into();
+            std::fs::write(dir.path().join(name), serde_json::to_string(&claim).unwrap() + "\n").unwrap();
+        };
+        write("a.jsonl", "c1");
+        write("notes.txt", "c0");
+        let (tx, mut rx) = mpsc::channel(4);
+        let watcher = DirectoryWatcher::new(dir.path()).with_poll_interval(Duration::from_millis(10));
+        let task = tokio::spawn(watcher.stream(tx, false));
+        let next = async |rx: &mut mpsc::Receiver<PayerClaim>| timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert_eq!(next(&mut rx).await.claim_id, ClaimId::new("c1"));
+        write("b.jsonl", "c2");
+        assert_eq!(next(&mut rx).await.claim_id, ClaimId::new("c2"));
+        drop(rx);
+        timeout(Duration::from_secs(5), task).await.unwrap().unwrap().unwrap();
+    }
+
+    /// Test that the HTTP source passes valid claims to the biller and rejects invalid ones.
+    /// Expected: A valid claim gets 202 and reaches the channel; malformed JSON gets 422.
+    #[tokio::test]
+    async fn test_http_source() {
+        let source = HttpSource::bind("127.0.0.1:0").await.unwrap();
+        let addr = source.local_addr().unwrap();
+        let (tx, mut rx) = mpsc::channel(4);
+        tokio::spawn(source.stream(tx, false));
+
+        let post = async |body: String| {
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            let request = format!(
+                "POST /claims HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                addr,
+                body.len(),
+                body
+            );
+            tokio::io::AsyncWriteExt::write_all(&mut stream, request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            tokio::io::AsyncReadExt::read_to_string(&mut stream, &mut response).await.unwrap();
+            response
+        };
+        let claim = mock_claim();
+        assert!(post(serde_json::to_string(&claim).unwrap()).await.starts_with("HTTP/1.1 202"));
+        assert_eq!(rx.recv().await.unwrap().claim_id, claim.claim_id);
+        assert!(post("{not json".to_string()).await.starts_with("HTTP/1.1 422"));
+    }
+}
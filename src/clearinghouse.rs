@@ -1,69 +1,277 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Instant;
-use tokio::sync::{
-    Mutex,
-    mpsc::{Receiver, Sender},
-};
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::task::JoinSet;
+use tracing::Instrument;
 
+use crate::audit;
+use crate::audit_log::{AuditAction, AuditLog};
+use crate::control::{ClearinghouseHandle, ControlReceiver, RunState};
+use crate::debugger::Debugger;
+use crate::edits::{EditRates, EditStats};
+use crate::event_log::{Event, EventLog};
+use crate::history::History;
+use crate::idempotency::content_hash;
+use crate::inventory::{ClaimInventory, ClaimState};
 use crate::logging::log_claim_event;
-use crate::message::{ClaimEnvelope, ClaimMessage, ClaimStatus, PayerMessage, RemittanceMessage};
+use crate::message::{ClaimEnvelope, ClaimMessage, ClaimStatus, FrontEndVerdict, PayerMessage, RemittanceMessage, Stage};
+use crate::priority::{Priority, PriorityQueue};
 use crate::remittance::{Remittance, RemittanceRecord};
+use crate::remittance_files::RemittanceFiles;
+use crate::schema::{Attachment, PayerClaim};
+use crate::supervisor::Heartbeat;
+use crate::ids::{ClaimId, ClaimKey, PayerId};
+
+/// Worker shards the clearinghouse runs unless configured otherwise
+pub const DEFAULT_CLEARINGHOUSE_SHARDS: usize = 4;
+
+/// Joins a claim id to its tenant in the id a claim is sent to its payer under when the
+/// plain id is taken; tenant ids never contain it
+const PAYER_CLAIM_ID_SEPARATOR: char = '~';
+
+/// Id to send a claim to its payer under when its own id can't be used
+fn surrogate_claim_id(key: &ClaimKey) -> ClaimId {
+    ClaimId::new(format!("{}{}{}", key.claim_id, PAYER_CLAIM_ID_SEPARATOR, key.tenant_id))
+}
+
+/// The biller's claim id a payer-facing id was made from, which picks the claim's shard
+fn biller_claim_id(payer_claim_id: &str) -> &str {
+    payer_claim_id
+        .rsplit_once(PAYER_CLAIM_ID_SEPARATOR)
+        .map_or(payer_claim_id, |(claim_id, _)| claim_id)
+}
 
 /// Central routing hub for claim processing workflow
 /// 
 /// Routes claims from billers to appropriate payers and remittances back to billers
 /// Maintains claim history and tracks processing status
+///
+/// A routing front-end hands each claim and remittance to one of several worker shards,
+/// chosen by a hash of the claim id, one per shard of `history`. Each shard owns its slice
+/// of history, its duplicate tracking, and the biller return channels of its claims, so shards work in parallel without sharing locks;
+/// a claim and its remittance always go to the same shard.
+///
+/// Claims are kept by tenant and claim id, since billers pick their own ids. Payers know
+/// claims by id alone, so a claim whose id another tenant's claim already has is sent to
+/// its payer as `<claim id>~<tenant id>`, and its remittance translated back before the
+/// biller sees it.
+///
+/// Before a new claim is routed it runs the front-end edits, and a claim failing one is
+/// rejected back to its biller with the edit's code without reaching the payer
 pub struct Clearinghouse {
     claim_rx: Receiver<ClaimMessage>,
-    payer_txs: HashMap<String, Sender<PayerMessage>>,
+    payer_txs: HashMap<PayerId, Sender<PayerMessage>>,
     remittance_rx: Receiver<RemittanceMessage>,
-    biller_txs: Arc<Mutex<HashMap<String, Sender<RemittanceMessage>>>>,
-    history: Arc<Mutex<HashMap<String, ClaimStatus>>>,
+    history: History,
+    edits: EditRates,
+    edit_stats: EditStats,
+    inventory: ClaimInventory,
+    event_log: Option<EventLog>,
+    audit_log: Option<AuditLog>,
+    remittance_files: Option<RemittanceFiles>,
+    debugger: Option<Debugger>,
+    heartbeat: Heartbeat,
+    handle: ClearinghouseHandle,
+    control: ControlReceiver<()>,
+    verbose: bool,
+}
+
+/// Inbound channels of one worker shard
+struct ShardInbox {
+    claim_tx: Sender<ClaimMessage>,
+    remittance_tx: Sender<RemittanceMessage>,
+}
+
+/// One clearinghouse worker: routes the claims whose ids hash to its slice of history
+struct ClearinghouseShard {
+    index: usize,
+    claim_rx: Receiver<ClaimMessage>,
+    remittance_rx: Receiver<RemittanceMessage>,
+    payer_txs: HashMap<PayerId, Sender<PayerMessage>>,
+    /// Biller return channel of every claim this shard is waiting on a remittance for
+    biller_txs: HashMap<ClaimKey, Sender<RemittanceMessage>>,
+    /// Claim each id this shard sent to a payer stands for, until the payer remits it
+    payer_claims: HashMap<ClaimId, ClaimKey>,
+    history: History,
+    /// Tenants with a claim under each claim id in this shard's slice of history, kept as
+    /// claims are added to it; claims stay in history once added, so ids are never dropped
+    claim_id_tenants: HashMap<ClaimId, HashSet<String>>,
+    /// Resubmissions waiting on the original claim's remittance
+    duplicate_txs: HashMap<ClaimKey, Vec<Sender<RemittanceMessage>>>,
+    edits: EditRates,
+    edit_stats: EditStats,
+    inventory: ClaimInventory,
+    event_log: Option<EventLog>,
+    audit_log: Option<AuditLog>,
+    remittance_files: Option<RemittanceFiles>,
+    debugger: Option<Debugger>,
+    heartbeat: Heartbeat,
     verbose: bool,
 }
 
 impl Clearinghouse {
     /// Create a new clearinghouse with routing channels and shared state
+    ///
+    /// Runs one worker shard per shard of `history`
     pub fn new(
         claim_rx: Receiver<ClaimMessage>,
-        payer_txs: HashMap<String, Sender<PayerMessage>>,
+        payer_txs: HashMap<PayerId, Sender<PayerMessage>>,
         remittance_rx: Receiver<RemittanceMessage>,
-        biller_txs: Arc<Mutex<HashMap<String, Sender<RemittanceMessage>>>>,
-        history: Arc<Mutex<HashMap<String, ClaimStatus>>>,
+        history: History,
         verbose: bool,
     ) -> Self {
+        let handle = ClearinghouseHandle::new(());
+        let inventory = ClaimInventory::new(history.shard_count());
         Self {
             claim_rx,
             payer_txs,
             remittance_rx,
-            biller_txs,
             history,
+            edits: EditRates::default(),
+            edit_stats: EditStats::default(),
+            inventory,
+            event_log: None,
+            audit_log: None,
+            remittance_files: None,
+            debugger: None,
+            heartbeat: Heartbeat::default(),
+            control: handle.subscribe(),
+            handle,
             verbose,
         }
     }
 
+    /// Reject this fraction of new claims at each level of front-end edits
+    pub fn with_edits(mut self, edits: EditRates) -> Self {
+        self.edits = edits;
+        self
+    }
+
+    /// Record submissions, routing, and remittances to an event log
+    pub fn with_event_log(mut self, event_log: EventLog) -> Self {
+        self.event_log = Some(event_log);
+        self
+    }
+
+    /// Record each claim record the clearinghouse creates, discloses to a payer, remits, or voids
+    pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Write each remittance recorded to its payer's file for the simulated day
+    pub fn with_remittance_files(mut self, remittance_files: RemittanceFiles) -> Self {
+        self.remittance_files = Some(remittance_files);
+        self
+    }
+
+    /// Hold each claim and remittance a shard takes up at `clearinghouse:claims` or
+    /// `clearinghouse:remittances` while the debugger is paused
+    pub fn with_debugger(mut self, debugger: Debugger) -> Self {
+        self.debugger = Some(debugger);
+        self
+    }
+
+    /// Beat `heartbeat` each time the front-end or a shard handles a claim or remittance
+    pub fn with_heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Counts of claims rejected at each level of front-end edits
+    pub fn edit_stats(&self) -> EditStats {
+        self.edit_stats.clone()
+    }
+
+    /// Claims in each lifecycle state, as of each change the clearinghouse sees
+    pub fn inventory(&self) -> ClaimInventory {
+        self.inventory.clone()
+    }
+
+    /// Handle to pause, resume, or stop this clearinghouse while it runs
+    pub fn handle(&self) -> ClearinghouseHandle {
+        self.handle.clone()
+    }
+
+    /// Start one task per shard into `tasks`, returning the channels that feed them
+    fn spawn_shards(&self, tasks: &mut JoinSet<()>) -> Vec<ShardInbox> {
+        (0..self.history.shard_count())
+            .map(|index| {
+                let (claim_tx, claim_rx) = mpsc::channel(self.claim_rx.max_capacity());
+                let (remittance_tx, remittance_rx) = mpsc::channel(self.remittance_rx.max_capacity());
+                let shard = ClearinghouseShard {
+                    index,
+                    claim_rx,
+                    remittance_rx,
+                    payer_txs: self.payer_txs.clone(),
+                    biller_txs: HashMap::new(),
+                    payer_claims: HashMap::new(),
+                    history: self.history.clone(),
+                    claim_id_tenants: HashMap::new(),
+                    duplicate_txs: HashMap::new(),
+                    edits: self.edits,
+                    edit_stats: self.edit_stats.clone(),
+                    inventory: self.inventory.clone(),
+                    event_log: self.event_log.clone(),
+                    audit_log: self.audit_log.clone(),
+                    remittance_files: self.remittance_files.clone(),
+                    debugger: self.debugger.clone(),
+                    heartbeat: self.heartbeat.clone(),
+                    verbose: self.verbose,
+                };
+                tasks.spawn(shard.run());
+                ShardInbox { claim_tx, remittance_tx }
+            })
+            .collect()
+    }
+
     /// Main processing loop for claim routing and remittance handling
     /// 
     /// Handles incoming claims and remittances concurrently
-    /// Routes claims to payers and remittances back to originating billers
+    /// Hands claims, high priority first, and remittances to the shard owning their claim id
+    /// Neither claims nor remittances are taken while paused; the loop exits once stopped,
+    /// and each shard exits once it has finished what it was handed. A shard that panics
+    /// takes the front-end down with it, so the failure is seen where the clearinghouse runs
     pub async fn run(mut self) {
         if self.verbose {
             log_claim_event("clearinghouse", "-", "start", "Starting clearinghouse task");
         }
+        let mut shard_tasks = JoinSet::new();
+        let shards = self.spawn_shards(&mut shard_tasks);
+        let mut remittances_open = true;
         loop {
+            // paused or stopped, claims wait but remittances for claims already routed still reach
+            // their billers; once stopped, the front-end exits when the payers are done
+            let state = self.control.state();
+            if state == RunState::Stopped && !remittances_open {
+                break;
+            }
             tokio::select! {
-                Some(msg) = self.claim_rx.recv() => {
-                    self.process_claim_message(msg).await;
+                // a pause or stop takes effect before any claim already waiting
+                biased;
+                _ = self.control.changed() => {}
+                // shards outlive the front-end's inputs, so they must not keep it from exiting
+                Some(Err(e)) = shard_tasks.join_next(), if !(self.claim_rx.is_closed() && self.remittance_rx.is_closed()) => {
+                    if e.is_panic() {
+                        std::panic::resume_unwind(e.into_panic());
+                    }
                 }
-                Some(msg) = self.remittance_rx.recv() => {
-                    self.process_remittance_message(msg).await;
+                Some(msg) = self.claim_rx.recv(), if state == RunState::Running => {
+                    self.dispatch_claim_backlog(msg, &shards).await;
                 }
+                msg = self.remittance_rx.recv(), if remittances_open => match msg {
+                    Some(msg) => self.dispatch_remittance(msg, &shards).await,
+                    None => remittances_open = false,
+                },
                 else => {
                     break;
                 }
             }
+            self.heartbeat.beat();
         }
+        // shards finish what they were handed after the front-end exits
+        shard_tasks.detach_all();
         if self.verbose {
             log_claim_event(
                 "clearinghouse",
@@ -74,20 +282,193 @@ impl Clearinghouse {
         }
     }
 
+    /// Drain all claims already waiting and hand them to their shards high priority first
+    async fn dispatch_claim_backlog(&mut self, first: ClaimMessage, shards: &[ShardInbox]) {
+        let mut queue = PriorityQueue::new();
+        let mut next = Some(first);
+        while let Some(mut msg) = next {
+            let priority = match &mut msg {
+                ClaimMessage::NewClaim(envelope) => {
+                    envelope.metadata.record_hop(Stage::Received);
+                    let key = ClaimKey::new(envelope.tenant_id.clone(), envelope.claim.claim_id.clone());
+                    self.inventory.scrub(&key, envelope.claim.total_charge());
+                    envelope.claim.priority()
+                }
+                // never overtakes the claim it refers to, which is at least normal priority
+                ClaimMessage::Cancel { .. } | ClaimMessage::Attachments { .. } | ClaimMessage::Resubmit { .. } => Priority::Normal,
+            };
+            queue.push(priority, msg);
+            next = self.claim_rx.try_recv().ok();
+        }
+        while let Some(msg) = queue.pop() {
+            let shard = &shards[self.history.shard_of(msg.claim_id())];
+            if shard.claim_tx.send(msg).await.is_err() {
+                eprintln!("Clearinghouse shard stopped; claim not routed");
+            }
+        }
+    }
+
+    async fn dispatch_remittance(&self, msg: RemittanceMessage, shards: &[ShardInbox]) {
+        let shard = &shards[self.history.shard_of(biller_claim_id(msg.claim_id()))];
+        if shard.remittance_tx.send(msg).await.is_err() {
+            eprintln!("Clearinghouse shard stopped; remittance not handled");
+        }
+    }
+}
+
+impl ClearinghouseShard {
+    fn record(&self, event: Event) {
+        if let Some(event_log) = &self.event_log {
+            event_log.record(event);
+        }
+    }
+
+    fn audit(&self, action: AuditAction, claim_id: &ClaimId) {
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.record("clearinghouse", action, claim_id);
+        }
+    }
+
+    async fn checkpoint(&self, channel: &str, message: impl FnOnce() -> String) {
+        if let Some(debugger) = &self.debugger {
+            debugger.checkpoint(channel, message).await;
+        }
+    }
+
+    /// Handle claims and remittances until the front-end drops this shard's inbox
+    async fn run(mut self) {
+        loop {
+            tokio::select! {
+                Some(msg) = self.claim_rx.recv() => {
+                    self.checkpoint("clearinghouse:claims", || msg.summary()).await;
+                    self.process_claim_message(msg).await;
+                }
+                Some(msg) = self.remittance_rx.recv() => {
+                    self.checkpoint("clearinghouse:remittances", || msg.summary()).await;
+                    self.process_remittance_message(msg).await;
+                }
+                else => break,
+            }
+            self.heartbeat.beat();
+        }
+    }
+
     async fn process_claim_message(&mut self, msg: ClaimMessage) {
-        let ClaimMessage::NewClaim(envelope) = msg;
+        let envelope = match msg {
+            ClaimMessage::NewClaim(envelope) => *envelope,
+            ClaimMessage::Cancel { claim_id, tenant_id } => {
+                if self.verbose {
+                    log_claim_event("clearinghouse", &claim_id, "handle_cancel", &format!("Handling cancellation of claim: {}", &claim_id));
+                }
+                self.handle_cancel(claim_id, &tenant_id).await;
+                return;
+            }
+            ClaimMessage::Attachments { claim_id, tenant_id, attachments } => {
+                self.forward_attachments(claim_id, &tenant_id, attachments).await;
+                return;
+            }
+            ClaimMessage::Resubmit { claim_id, tenant_id } => {
+                self.resubmit(ClaimKey::new(tenant_id, claim_id)).await;
+                return;
+            }
+        };
         if self.verbose {
             log_claim_event("clearinghouse", &envelope.claim.claim_id, "handle_new_claim", &format!("Handling new claim: {}", &envelope.claim.claim_id));
         }
-        self.handle_claim(envelope).await;
+        let span = tracing::info_span!(
+            parent: &envelope.metadata.span,
+            "clearinghouse.route",
+            claim_id = %envelope.claim.claim_id,
+            payer_id = %envelope.claim.insurance.payer_id,
+        );
+        self.handle_claim(envelope).instrument(span).await;
     }
 
     async fn process_remittance_message(&mut self, msg: RemittanceMessage) {
-        let RemittanceMessage::Processed(remittance) = msg;
+        let remittance = match msg {
+            RemittanceMessage::Processed(remittance) => remittance,
+            RemittanceMessage::AttachmentRequest { claim_id, document_types } => {
+                match self.payer_claims.get(&claim_id).cloned() {
+                    Some(key) => self.forward_attachment_request(key, document_types).await,
+                    None => eprintln!("No biller waiting on claim {} to request attachments from", claim_id),
+                }
+                return;
+            }
+            RemittanceMessage::Acknowledged { claim_id } => {
+                match self.payer_claims.get(&claim_id).cloned() {
+                    Some(key) => self.record_acknowledgment(key).await,
+                    None => eprintln!("Claim {} acknowledged but not waiting on its payer", claim_id),
+                }
+                return;
+            }
+            RemittanceMessage::Duplicate(remittance) => {
+                eprintln!("Unexpected duplicate remittance from payer for claim {}", remittance.claim_id());
+                return;
+            }
+            RemittanceMessage::Rejected { claim_id, reason } => {
+                match self.payer_claims.remove(&claim_id) {
+                    Some(key) => self.handle_payer_rejection(key, reason).await,
+                    None => eprintln!("Claim {} rejected by its payer but not waiting on it", claim_id),
+                }
+                return;
+            }
+            RemittanceMessage::Voided { claim_id } => {
+                eprintln!("Unexpected void from payer for claim {}", claim_id);
+                return;
+            }
+        };
         if self.verbose {
-            log_claim_event("clearinghouse", &remittance.claim_id, "handle_remittance", &format!("Handling remittance for claim: {}", &remittance.claim_id));
+            log_claim_event("clearinghouse", remittance.claim_id(), "handle_remittance", &format!("Handling remittance for claim: {}", &remittance.claim_id()));
+        }
+        let Some(key) = self.payer_claims.remove(remittance.claim_id()) else {
+            eprintln!("Claim {} not found in history", remittance.claim_id());
+            if self.verbose {
+                log_claim_event("clearinghouse", remittance.claim_id(), "remittance_not_found", "Claim not found in history");
+            }
+            return;
+        };
+        let payer_claim_id = remittance.claim_id().clone();
+        let remittance = remittance.with_claim_id(key.claim_id.clone());
+        self.handle_remittance(key, payer_claim_id, remittance).await;
+    }
+
+    /// Id to send a new claim to its payer under: its own, unless another tenant's claim
+    /// with the same id is already in history
+    ///
+    /// Every tenant's claims with one id live in this shard, so its own index of them is enough
+    fn assign_payer_claim_id(&self, key: &ClaimKey) -> Option<ClaimId> {
+        let taken = key.claim_id.as_str().contains(PAYER_CLAIM_ID_SEPARATOR)
+            || self
+                .claim_id_tenants
+                .get(&key.claim_id)
+                .is_some_and(|tenants| tenants.iter().any(|tenant_id| *tenant_id != key.tenant_id));
+        taken.then(|| surrogate_claim_id(key))
+    }
+
+    /// The claim as its payer sees it: under the id it was sent with, and for a replacement,
+    /// naming the original by the id the payer knows the original by
+    ///
+    /// The original may live in another shard's slice of history; it is only read here. The
+    /// biller's claim is shared unless one of its ids had to change
+    async fn payer_claim(&self, claim: &Arc<PayerClaim>, tenant_id: &str, payer_claim_id: Option<&ClaimId>) -> Arc<PayerClaim> {
+        let mut original_payer_claim_id = None;
+        if let Some(original_id) = claim.replaces()
+            && let Some(original) = self.history.get(&ClaimKey::new(tenant_id, original_id.clone())).await
+            && original.payer_claim_id() != original_id
+        {
+            original_payer_claim_id = Some(original.payer_claim_id().clone());
         }
-        self.handle_remittance(remittance).await;
+        if payer_claim_id.is_none() && original_payer_claim_id.is_none() {
+            return Arc::clone(claim);
+        }
+        let mut claim = PayerClaim::clone(claim);
+        if let Some(payer_claim_id) = payer_claim_id {
+            claim.claim_id = payer_claim_id.clone();
+        }
+        if let Some(original_payer_claim_id) = original_payer_claim_id {
+            claim.original_claim_id = Some(original_payer_claim_id);
+        }
+        Arc::new(claim)
     }
 
     /// Process a new claim from a biller
@@ -97,24 +478,59 @@ impl Clearinghouse {
     async fn handle_claim(&mut self, envelope: ClaimEnvelope) {
         let claim = envelope.claim;
         let response_tx = envelope.response_tx;
+        let tenant_id = envelope.tenant_id;
+        let mut metadata = envelope.metadata;
         let claim_id = claim.claim_id.clone();
         let payer_id = claim.insurance.payer_id.clone();
 
-        //TODO: any faster way than locks here?
-        // Track response channel for later
-        self.biller_txs
-            .lock()
-            .await
-            .insert(claim_id.clone(), response_tx);
+        // the content includes the claim id, so a resubmission is the claim this shard already holds
+        // under the same tenant and id; identical content from different tenants is not a duplicate
+        let key = ClaimKey::new(tenant_id.clone(), claim_id.clone());
+        if self.is_resubmission(&key, &claim).await {
+            tracing::info!(original_claim_id = %claim_id, "duplicate submission");
+            metadata.report_verdict(&claim_id, FrontEndVerdict::Accepted);
+            self.record(Event::DuplicateSubmitted { claim_id: claim_id.clone(), original_claim_id: claim_id });
+            self.handle_duplicate(key, response_tx).await;
+            return;
+        }
+        // a rejected claim is not remembered, so its corrected resubmission is taken as new
+        let edited = self.edits.check(&claim, &mut rand::rng());
+        if let Err(rejection) = edited {
+            tracing::info!(code = rejection.code, "front-end rejection");
+            self.edit_stats.record(rejection.level);
+            self.inventory.reject(&key);
+            if self.verbose {
+                log_claim_event("clearinghouse", &claim_id, "edit_rejected", &format!("Claim rejected by {}", rejection));
+            }
+            self.record(Event::ClaimRejected { claim_id: claim_id.clone(), code: rejection.code.to_string(), reason: rejection.to_string() });
+            let reason = rejection.to_string();
+            metadata.report_verdict(&claim_id, FrontEndVerdict::Rejected { code: Some(rejection.code), reason: reason.clone() });
+            if let Err(e) = response_tx.send(RemittanceMessage::Rejected { claim_id: claim_id.clone(), reason }).await {
+                eprintln!("Failed to answer rejected claim {}: {}", claim_id, e);
+            }
+            return;
+        }
+        // checked last, since a claim passing it is linked to its original for good
+        if let Some(original_id) = claim.replaces()
+            && let Err(reason) = self.claim_original(&claim, original_id, &tenant_id).await
+        {
+            eprintln!("Replacement claim {} rejected: {}", claim_id, reason);
+            metadata.report_verdict(&claim_id, FrontEndVerdict::Rejected { code: None, reason: reason.clone() });
+            self.inventory.reject(&key);
+            if let Err(e) = response_tx.send(RemittanceMessage::Rejected { claim_id: claim_id.clone(), reason }).await {
+                eprintln!("Failed to answer replacement claim {}: {}", claim_id, e);
+            }
+            return;
+        }
+        metadata.report_verdict(&claim_id, FrontEndVerdict::Accepted);
+        self.record(Event::ClaimSubmitted { tenant_id: tenant_id.clone(), claim: Arc::clone(&claim) });
+        self.audit(AuditAction::Submit, &claim_id);
 
-        // Track for AR aging
-        self.history.lock().await.insert(
-            claim_id.clone(),
-            ClaimStatus::Submitted {
-                claim: claim.clone(), //TODO: is it okay to clone claims and remittance like this?
-                submitted_at: Instant::now(),
-            },
-        );
+        // Track response channel for later
+        self.biller_txs.insert(key.clone(), response_tx);
+        metadata.payer_claim_id = self.assign_payer_claim_id(&key);
+        let payer_claim_id = metadata.payer_claim_id.clone().unwrap_or_else(|| claim_id.clone());
+        self.payer_claims.insert(payer_claim_id, key.clone());
 
         if self.verbose {
             log_claim_event(
@@ -124,37 +540,391 @@ impl Clearinghouse {
                 &format!("Forwarding claim to payer {}", &payer_id),
             );
         }
-        // Forward claim to payer
+        // Forward claim to payer; its remittance is only handled after this returns,
+        // so the history entry below is always in place first
         if let Some(payer_tx) = self.payer_txs.get(&payer_id) {
-            if let Err(e) = payer_tx.send(PayerMessage::Adjudicate(claim)).await {
-                eprintln!(
+            self.record(Event::ClaimRouted { claim_id: claim_id.clone(), payer_id: payer_id.clone() });
+            self.audit(AuditAction::Route, &claim_id);
+            let payer_claim = self.payer_claim(&claim, &tenant_id, metadata.payer_claim_id.as_ref()).await;
+            match payer_tx.send(PayerMessage::Adjudicate(payer_claim, metadata.span.clone())).await {
+                Ok(()) => metadata.record_hop(Stage::Routed),
+                Err(e) => eprintln!(
                     "Failed to forward claim {} to payer {}: {}",
                     claim_id, payer_id, e
-                );
+                ),
             }
         } else {
             eprintln!("Unknown payer ID: {}", payer_id);
         }
+
+        // Track for AR aging
+        let status = ClaimStatus::Submitted {
+            claim,
+            tenant_id,
+            submitted_at: Instant::now(),
+            metadata,
+        };
+        let mut history = self.history.shard(self.index).await;
+        self.history.publish(&key, &status);
+        history.insert(key.clone(), status);
+        drop(history);
+        self.claim_id_tenants.entry(key.claim_id.clone()).or_default().insert(key.tenant_id.clone());
+        self.inventory.advance(&key, ClaimState::Submitted);
+    }
+
+    /// A replacement is accepted only for a remitted claim of the same tenant and payer that no
+    /// other claim has replaced, since the payer reverses the original's remittance when it
+    /// adjudicates the replacement
+    ///
+    /// The original may live in another shard's slice of history. Its slice stays locked from the
+    /// check until it is linked to the replacement, so two replacements of one claim cannot both pass
+    async fn claim_original(&self, claim: &Arc<PayerClaim>, original_id: &ClaimId, tenant_id: &str) -> Result<(), String> {
+        let key = ClaimKey::new(tenant_id, original_id.clone());
+        let mut history = self.history.shard(self.history.shard_of(original_id)).await;
+        let record = match history.get(&key) {
+            Some(ClaimStatus::Remitted(record)) => record,
+            Some(ClaimStatus::Submitted { .. }) => {
+                return Err(format!("original claim {} is still with its payer; cancel it instead", original_id));
+            }
+            Some(ClaimStatus::Voided { .. }) => return Err(format!("original claim {} was voided", original_id)),
+            // another tenant's claim with this id is not the original
+            None => return Err(format!("unknown original claim {}", original_id)),
+        };
+        if record.payer_id() != &claim.insurance.payer_id {
+            return Err(format!(
+                "original claim {} was adjudicated by payer {}, not {}",
+                original_id,
+                record.payer_id(),
+                claim.insurance.payer_id
+            ));
+        }
+        if let Some(replacement) = record.replaced_by() {
+            return Err(format!("original claim {} was already replaced by claim {}", original_id, replacement));
+        }
+        let linked = ClaimStatus::Remitted(record.clone().with_replaced_by(Some(Arc::clone(claim))));
+        self.history.publish(&key, &linked);
+        history.insert(key, linked);
+        Ok(())
+    }
+
+    /// Close a claim its payer refused to adjudicate, passing the refusal to its biller and to any
+    /// resubmissions waiting on it
+    ///
+    /// The claim stays in history as voided, having been closed unpaid; a refused replacement
+    /// leaves its original free to be replaced again
+    async fn handle_payer_rejection(&mut self, key: ClaimKey, reason: String) {
+        let claim_id = key.claim_id.clone();
+        let mut history = self.history.shard(self.index).await;
+        let Some(ClaimStatus::Submitted { claim, .. }) = history.get(&key) else {
+            eprintln!("Claim {} rejected by its payer but not waiting on it", claim_id);
+            return;
+        };
+        let claim = Arc::clone(claim);
+        let voided = ClaimStatus::Voided { claim: Arc::clone(&claim), tenant_id: key.tenant_id.clone(), voided_at: Instant::now() };
+        self.history.publish(&key, &voided);
+        history.insert(key.clone(), voided);
+        drop(history);
+        if let Some(original_id) = claim.replaces() {
+            self.release_original(&ClaimKey::new(key.tenant_id.clone(), original_id.clone()), &claim_id).await;
+        }
+        self.inventory.remove(&key);
+        self.record(Event::ClaimVoided { tenant_id: key.tenant_id.clone(), claim_id: claim_id.clone() });
+        eprintln!("Claim {} rejected by its payer: {}", claim_id, reason);
+
+        let waiting = self
+            .biller_txs
+            .remove(&key)
+            .into_iter()
+            .chain(self.duplicate_txs.remove(&key).unwrap_or_default());
+        for tx in waiting {
+            let rejected = RemittanceMessage::Rejected { claim_id: claim_id.clone(), reason: reason.clone() };
+            if let Err(e) = tx.send(rejected).await {
+                eprintln!("Failed to answer rejected claim {}: {}", claim_id, e);
+            }
+        }
+    }
+
+    /// Unlink an original from a replacement that was refused
+    async fn release_original(&self, original: &ClaimKey, replacement: &ClaimId) {
+        let mut history = self.history.shard(self.history.shard_of(&original.claim_id)).await;
+        if let Some(ClaimStatus::Remitted(record)) = history.get(original)
+            && record.replaced_by() == Some(replacement)
+        {
+            let released = ClaimStatus::Remitted(record.clone().with_replaced_by(None));
+            self.history.publish(original, &released);
+            history.insert(original.clone(), released);
+        }
+    }
+
+    /// Whether history already holds this exact claim, so only claims resubmitted under an id
+    /// already taken are hashed, and nothing beyond history is kept to recognize them
+    async fn is_resubmission(&self, key: &ClaimKey, claim: &PayerClaim) -> bool {
+        let history = self.history.shard(self.index).await;
+        history.get(key).is_some_and(|original| content_hash(original.claim()) == content_hash(claim))
+    }
+
+    /// Answer a resubmitted claim with the original claim's status instead of re-adjudicating
+    ///
+    /// Already remitted: the stored remittance is returned immediately.
+    /// Still with the payer: the resubmission is answered when the original remits.
+    /// Voided: the void is returned. With no original in history it is rejected.
+    async fn handle_duplicate(&mut self, original: ClaimKey, response_tx: Sender<RemittanceMessage>) {
+        let original_id = &original.claim_id;
+        if self.verbose {
+            log_claim_event(
+                "clearinghouse",
+                original_id,
+                "duplicate_claim",
+                "Claim content already submitted, skipping adjudication",
+            );
+        }
+        let history = self.history.shard(self.index).await;
+        match history.get(&original) {
+            Some(ClaimStatus::Remitted(record)) => {
+                let remittance = record.remittance().clone();
+                drop(history);
+                if let Err(e) = response_tx.send(RemittanceMessage::Duplicate(remittance)).await {
+                    eprintln!("Failed to answer duplicate of claim {}: {}", original_id, e);
+                }
+            }
+            Some(ClaimStatus::Submitted { .. }) => {
+                self.duplicate_txs
+                    .entry(original.clone())
+                    .or_default()
+                    .push(response_tx);
+            }
+            Some(ClaimStatus::Voided { .. }) => {
+                drop(history);
+                let voided = RemittanceMessage::Voided { claim_id: original_id.clone() };
+                if let Err(e) = response_tx.send(voided).await {
+                    eprintln!("Failed to answer duplicate of claim {}: {}", original_id, e);
+                }
+            }
+            None => {
+                drop(history);
+                let reason = format!("original claim {} has no history entry", original_id);
+                eprintln!("Duplicate of claim {} rejected: {}", original_id, reason);
+                if let Err(e) = response_tx.send(RemittanceMessage::Rejected { claim_id: original_id.clone(), reason }).await {
+                    eprintln!("Failed to answer duplicate of claim {}: {}", original_id, e);
+                }
+            }
+        }
+    }
+
+    /// Void a claim its biller cancelled
+    ///
+    /// Only a claim still with its payer can be voided: the payer is told to drop it,
+    /// history marks it voided, and the biller and any resubmissions waiting on it are
+    /// answered so they stop waiting for a remittance. A remitted claim can no longer be cancelled
+    async fn handle_cancel(&mut self, claim_id: ClaimId, tenant_id: &str) {
+        let key = ClaimKey::new(tenant_id, claim_id.clone());
+        let mut history = self.history.shard(self.index).await;
+        let (claim, payer_claim_id) = match history.get(&key) {
+            Some(status @ ClaimStatus::Submitted { claim, .. }) => (claim.clone(), status.payer_claim_id().clone()),
+            Some(ClaimStatus::Remitted(_)) => {
+                eprintln!("Claim {} already remitted, too late to cancel", claim_id);
+                return;
+            }
+            Some(ClaimStatus::Voided { .. }) => {
+                if self.verbose {
+                    log_claim_event("clearinghouse", &claim_id, "cancel_repeated", "Claim already voided");
+                }
+                return;
+            }
+            None => {
+                eprintln!("Tenant {} cannot cancel unknown claim {}", tenant_id, claim_id);
+                return;
+            }
+        };
+        let payer_id = claim.insurance.payer_id.clone();
+        let voided = ClaimStatus::Voided {
+            claim,
+            tenant_id: tenant_id.to_string(),
+            voided_at: Instant::now(),
+        };
+        self.history.publish(&key, &voided);
+        history.insert(key.clone(), voided);
+        drop(history);
+        self.inventory.remove(&key);
+        self.record(Event::ClaimVoided { tenant_id: tenant_id.to_string(), claim_id: claim_id.clone() });
+        self.audit(AuditAction::Void, &claim_id);
+
+        if let Some(payer_tx) = self.payer_txs.get(&payer_id)
+            && let Err(e) = payer_tx.send(PayerMessage::Void(payer_claim_id)).await
+        {
+            eprintln!("Failed to forward cancellation of claim {} to payer {}: {}", claim_id, payer_id, e);
+        }
+        let waiting = self
+            .biller_txs
+            .remove(&key)
+            .into_iter()
+            .chain(self.duplicate_txs.remove(&key).unwrap_or_default());
+        for tx in waiting {
+            if let Err(e) = tx.send(RemittanceMessage::Voided { claim_id: claim_id.clone() }).await {
+                eprintln!("Failed to confirm cancellation of claim {}: {}", claim_id, e);
+            }
+        }
+        if self.verbose {
+            log_claim_event("clearinghouse", &claim_id, "claim_voided", "Claim voided and payer notified");
+        }
+    }
+
+    /// Pass a payer's request for documentation on to the biller that submitted the claim
+    ///
+    /// The biller's return channel stays in place for the remittance that follows
+    async fn forward_attachment_request(&mut self, key: ClaimKey, document_types: Vec<String>) {
+        let claim_id = key.claim_id.clone();
+        if self.verbose {
+            log_claim_event(
+                "clearinghouse",
+                &claim_id,
+                "attachment_requested",
+                &format!("Payer requested attachments: {}", document_types.join(", ")),
+            );
+        }
+        let Some(biller_tx) = self.biller_txs.get(&key) else {
+            eprintln!("No biller waiting on claim {} to request attachments from", claim_id);
+            return;
+        };
+        self.inventory.advance(&key, ClaimState::Pended);
+        let request = RemittanceMessage::AttachmentRequest { claim_id: claim_id.clone(), document_types };
+        if let Err(e) = biller_tx.send(request).await {
+            eprintln!("Failed to request attachments for claim {}: {}", claim_id, e);
+        }
+    }
+
+    /// Note when the payer acknowledged a claim still waiting on it, once per claim
+    async fn record_acknowledgment(&mut self, key: ClaimKey) {
+        let mut history = self.history.shard(self.index).await;
+        let Some(status) = history.get_mut(&key) else { return };
+        let ClaimStatus::Submitted { claim, metadata, .. } = &mut *status else { return };
+        if metadata.time_to(Stage::Acknowledged).is_some() {
+            return;
+        }
+        metadata.record_hop(Stage::Acknowledged);
+        let payer_id = claim.insurance.payer_id.clone();
+        self.history.publish(&key, status);
+        drop(history);
+        self.record(Event::ClaimAcknowledged { claim_id: key.claim_id.clone(), payer_id });
+        if self.verbose {
+            log_claim_event("clearinghouse", &key.claim_id, "acknowledged", "Payer acknowledged claim");
+        }
+    }
+
+    /// Route a biller's attachments to the payer holding the claim
+    async fn forward_attachments(&mut self, claim_id: ClaimId, tenant_id: &str, attachments: Vec<Attachment>) {
+        let key = ClaimKey::new(tenant_id, claim_id.clone());
+        let (payer_id, payer_claim_id) = match self.history.shard(self.index).await.get(&key) {
+            Some(status @ ClaimStatus::Submitted { claim, .. }) => (claim.insurance.payer_id.clone(), status.payer_claim_id().clone()),
+            Some(_) => {
+                eprintln!("Claim {} is no longer with its payer; attachments not forwarded", claim_id);
+                return;
+            }
+            None => {
+                eprintln!("Tenant {} sent attachments for unknown claim {}", tenant_id, claim_id);
+                return;
+            }
+        };
+        if self.verbose {
+            log_claim_event(
+                "clearinghouse",
+                &claim_id,
+                "forward_attachments",
+                &format!("Forwarding {} attachments to payer {}", attachments.len(), payer_id),
+            );
+        }
+        self.inventory.advance(&key, ClaimState::Submitted);
+        if let Some(payer_tx) = self.payer_txs.get(&payer_id)
+            && let Err(e) = payer_tx.send(PayerMessage::Attachments(payer_claim_id, attachments)).await
+        {
+            eprintln!("Failed to forward attachments for claim {} to payer {}: {}", claim_id, payer_id, e);
+        }
+    }
+
+    /// Send a claim still waiting on its payer to that payer again
+    ///
+    /// The biller's return channel and the claim's history entry stay as they are, so the
+    /// remittance is handled like any other; a claim remitted or voided meanwhile is left alone
+    async fn resubmit(&mut self, key: ClaimKey) {
+        let claim_id = key.claim_id.clone();
+        let (claim, payer_claim_id, span) = match self.history.shard(self.index).await.get(&key) {
+            Some(ClaimStatus::Submitted { claim, metadata, .. }) => (claim.clone(), metadata.payer_claim_id.clone(), metadata.span.clone()),
+            Some(_) => {
+                if self.verbose {
+                    log_claim_event("clearinghouse", &claim_id, "resubmit_skipped", "Claim no longer with its payer");
+                }
+                return;
+            }
+            None => {
+                eprintln!("Cannot resubmit unknown claim {}", claim_id);
+                return;
+            }
+        };
+        let payer_id = claim.insurance.payer_id.clone();
+        let Some(payer_tx) = self.payer_txs.get(&payer_id) else {
+            eprintln!("Unknown payer ID: {}", payer_id);
+            return;
+        };
+        self.record(Event::ClaimRouted { claim_id: claim_id.clone(), payer_id: payer_id.clone() });
+        self.audit(AuditAction::Route, &claim_id);
+        let claim = self.payer_claim(&claim, &key.tenant_id, payer_claim_id.as_ref()).await;
+        if let Err(e) = payer_tx.send(PayerMessage::Adjudicate(claim, span)).await {
+            eprintln!("Failed to resubmit claim {} to payer {}: {}", claim_id, payer_id, e);
+        } else if self.verbose {
+            log_claim_event("clearinghouse", &claim_id, "resubmitted", &format!("Claim resubmitted to payer {}", payer_id));
+        }
     }
 
     /// Process a remittance response from a payer
     /// 
     /// Updates claim status and forwards remittance to originating biller
-    /// Validates claim exists and is in correct state
-    async fn handle_remittance(&mut self, remittance: Remittance) {
+    /// Validates claim exists and is in correct state, and audits the remittance
+    /// against the stored claim, recording any discrepancies in history
+    async fn handle_remittance(&mut self, key: ClaimKey, payer_claim_id: ClaimId, remittance: Remittance) {
         // println!("ATTEMPTING TO HANDLE REMITTANCE CLEARINGHOUSE ------");
-        let claim_id = remittance.claim_id.clone();
+        let claim_id = key.claim_id.clone();
 
         // lock history and try to remove claim
-        let mut history = self.history.lock().await;
-        match history.remove(&claim_id) {
+        let mut history = self.history.shard(self.index).await;
+        let claim = match history.remove(&key) {
             Some(ClaimStatus::Submitted {
                 claim,
+                tenant_id,
                 submitted_at,
+                mut metadata,
             }) => {
+                if let Some(adjudicated_at) = remittance.adjudicated_at() {
+                    metadata.record_hop_at(Stage::Adjudicated, adjudicated_at);
+                }
+                metadata.record_hop(Stage::Remitted);
+                let discrepancies = audit::audit_remittance(&remittance, &claim);
+                for discrepancy in &discrepancies {
+                    eprintln!(
+                        "Remittance for claim {} fails audit: {}: {}",
+                        claim_id,
+                        discrepancy.service_line_id.as_deref().unwrap_or("<claim>"),
+                        discrepancy.reason
+                    );
+                }
+                tracing::info!(parent: &metadata.span, discrepancies = discrepancies.len(), "remitted");
+                // History outlives the claim; drop the span so the trace can close
+                metadata.span = tracing::Span::none();
                 let record =
-                    RemittanceRecord::new(claim, remittance.clone(), submitted_at, Instant::now());
-                history.insert(claim_id.clone(), ClaimStatus::Remitted(record));
+                    RemittanceRecord::new(Arc::clone(&claim), remittance.clone(), submitted_at, Instant::now())
+                        .with_tenant(&tenant_id)
+                        .with_metadata(metadata)
+                        .with_discrepancies(discrepancies);
+                let state = if record.remittance().is_denied() { ClaimState::Denied } else { ClaimState::Paid };
+                self.inventory.advance(&key, state);
+                let remitted = ClaimStatus::Remitted(record);
+                self.history.publish(&key, &remitted);
+                history.insert(key.clone(), remitted);
+                self.record(Event::ClaimRemitted {
+                    tenant_id: key.tenant_id.clone(),
+                    claim_id: claim_id.clone(),
+                    payer_claim_id: (payer_claim_id != claim_id).then_some(payer_claim_id),
+                });
+                self.audit(AuditAction::Remit, &claim_id);
                 if self.verbose {
                     log_claim_event(
                         "clearinghouse",
@@ -163,12 +933,24 @@ impl Clearinghouse {
                         "Remittance recorded in history",
                     );
                 }
+                claim
+            }
+            Some(status @ ClaimStatus::Voided { .. }) => {
+                // remitted before the payer saw the cancellation; the payer reverses it
+                history.insert(key.clone(), status);
+                if self.verbose {
+                    log_claim_event(
+                        "clearinghouse",
+                        &claim_id,
+                        "remittance_for_voided",
+                        "Claim was voided, remittance discarded",
+                    );
+                }
+                return;
             }
             Some(status) => {
-                eprintln!(
-                    "Claim {} found in history but not in Submitted state: {:?}",
-                    claim_id, status
-                );
+                eprintln!("Claim {} found in history but not in Submitted state: already remitted", claim_id);
+                history.insert(key.clone(), status);
                 if self.verbose {
                     log_claim_event(
                         "clearinghouse",
@@ -191,12 +973,21 @@ impl Clearinghouse {
                 }
                 return;
             }
+        };
+        drop(history);
+        if let Some(files) = &self.remittance_files {
+            files.write(&key.tenant_id, &claim, &remittance);
+        }
+
+        // Settle resubmissions that arrived while the original was pending
+        for tx in self.duplicate_txs.remove(&key).unwrap_or_default() {
+            if let Err(e) = tx.send(RemittanceMessage::Duplicate(remittance.clone())).await {
+                eprintln!("Failed to answer duplicate of claim {}: {}", claim_id, e);
+            }
         }
-        drop(history); // Explicitly drop the lock before locking biller_txs
 
         // Forward remittance to originating biller
-        let mut biller_txs = self.biller_txs.lock().await;
-        match biller_txs.remove(&claim_id) {
+        match self.biller_txs.remove(&key) {
             Some(tx) => {
                 if let Err(e) = tx.send(RemittanceMessage::Processed(remittance)).await {
                     eprintln!("Failed to send remittance for claim {}: {}", claim_id, e);
@@ -227,10 +1018,14 @@ impl Clearinghouse {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{remittance::mock_remittance, schema::mock_claim};
+    use crate::message::ClaimMetadata;
+    use crate::schema::ClaimFrequency;
+    use crate::tenant::DEFAULT_TENANT;
+    use crate::test_utils::{mock_claim, mock_remittance, mock_adjudication_date};
+    use std::time::Duration;
 
     /// Test that a claim is received, routed to the correct payer, and remittance is returned to the biller.
-    /// Expected: Claim is forwarded, remittance is received, and history is updated.
+    /// Expected: Claim is forwarded, remittance is received, and history is updated; the payer and history share the biller's claim rather than copies.
     #[tokio::test]
     async fn test_run_clearinghouse() {
         // input channel for claims from biller
@@ -244,18 +1039,16 @@ mod tests {
 
         // Create payer channels map
         let mut payer_txs = HashMap::new();
-        payer_txs.insert("medicare".to_string(), payer_tx);
+        payer_txs.insert("medicare".into(), payer_tx);
 
         // Create shared state
-        let biller_txs = Arc::new(Mutex::new(HashMap::new()));
-        let claim_timestamps = Arc::new(Mutex::new(HashMap::new()));
+        let claim_timestamps = History::default();
 
         // spawn clearinghouse task
         let clearinghouse = Clearinghouse::new(
             claim_rx,
             payer_txs,
             remittance_rx,
-            biller_txs.clone(),
             claim_timestamps.clone(),
             true,
         );
@@ -264,23 +1057,26 @@ mod tests {
         });
 
         // Create a mock claim envelope
-        let mock_claim = mock_claim();
+        let mock_claim = Arc::new(mock_claim());
         let (response_tx, mut response_rx) = tokio::sync::mpsc::channel(1);
         let envelope = ClaimEnvelope {
-            claim: mock_claim,
+            claim: Arc::clone(&mock_claim),
             response_tx,
+            tenant_id: DEFAULT_TENANT.to_string(),
+            metadata: ClaimMetadata::default(),
         };
 
         // Send claim envelope to clearinghouse
         claim_tx
-            .send(ClaimMessage::NewClaim(envelope))
+            .send(ClaimMessage::NewClaim(Box::new(envelope)))
             .await
             .unwrap();
 
         // Verify claim was forwarded to payer
-        if let Some(PayerMessage::Adjudicate(claim)) = payer_rx.recv().await {
+        if let Some(PayerMessage::Adjudicate(claim, _)) = payer_rx.recv().await {
             assert_eq!(claim.claim_id, "abc123");
             assert_eq!(claim.insurance.payer_id, "medicare");
+            assert!(Arc::ptr_eq(&claim, &mock_claim));
         } else {
             panic!("Expected PayerMessage::Adjudicate");
         }
@@ -294,10 +1090,14 @@ mod tests {
 
         // Verify remittance was forwarded to biller
         if let Some(RemittanceMessage::Processed(remittance)) = response_rx.recv().await {
-            assert_eq!(remittance.claim_id, "abc123");
+            assert_eq!(remittance.claim_id(), "abc123");
         } else {
             panic!("Expected RemittanceMessage::Processed");
         }
+        match claim_timestamps.get(&ClaimKey::new(DEFAULT_TENANT, "abc123")).await {
+            Some(ClaimStatus::Remitted(record)) => assert!(Arc::ptr_eq(record.shared_claim(), &mock_claim)),
+            _ => panic!("Expected the claim remitted in history"),
+        }
     }
 
     /// Test that claims with unknown payer IDs are handled gracefully.
@@ -308,14 +1108,12 @@ mod tests {
         let (_remittance_tx, remittance_rx) = tokio::sync::mpsc::channel(1);
         let (payer_tx, mut payer_rx) = tokio::sync::mpsc::channel(1);
         let mut payer_txs = HashMap::new();
-        payer_txs.insert("medicare".to_string(), payer_tx);
-        let biller_txs = Arc::new(Mutex::new(HashMap::new()));
-        let history = Arc::new(Mutex::new(HashMap::new()));
+        payer_txs.insert("medicare".into(), payer_tx);
+        let history = History::default();
         let clearinghouse = Clearinghouse::new(
             claim_rx,
             payer_txs,
             remittance_rx,
-            biller_txs.clone(),
             history.clone(),
             false,
         );
@@ -323,14 +1121,16 @@ mod tests {
             clearinghouse.run().await;
         });
         let mut mock_claim = mock_claim();
-        mock_claim.insurance.payer_id = "unknown_payer".to_string();
+        mock_claim.insurance.payer_id = "unknown_payer".into();
         let (response_tx, _response_rx) = tokio::sync::mpsc::channel(1);
         let envelope = ClaimEnvelope {
-            claim: mock_claim,
+            claim: mock_claim.into(),
             response_tx,
+            tenant_id: DEFAULT_TENANT.to_string(),
+            metadata: ClaimMetadata::default(),
         };
         claim_tx
-            .send(ClaimMessage::NewClaim(envelope))
+            .send(ClaimMessage::NewClaim(Box::new(envelope)))
             .await
             .unwrap();
         // Should not receive any message from payer
@@ -345,24 +1145,22 @@ mod tests {
         let (remittance_tx, remittance_rx) = tokio::sync::mpsc::channel(1);
         let (payer_tx, _payer_rx) = tokio::sync::mpsc::channel(1);
         let mut payer_txs = HashMap::new();
-        payer_txs.insert("medicare".to_string(), payer_tx);
-        let biller_txs = Arc::new(Mutex::new(HashMap::new()));
-        let history = Arc::new(Mutex::new(HashMap::new()));
+        payer_txs.insert("medicare".into(), payer_tx);
+        let history = History::default();
         let clearinghouse = Clearinghouse::new(
             claim_rx,
             payer_txs,
             remittance_rx,
-            biller_txs.clone(),
             history.clone(),
             false,
         );
         tokio::spawn(async move {
             clearinghouse.run().await;
         });
-        let mut mock_remittance = mock_remittance();
-        mock_remittance.claim_id = "unknown_claim".to_string();
+        let mut unknown_claim = mock_claim();
+        unknown_claim.claim_id = "unknown_claim".into();
         remittance_tx
-            .send(RemittanceMessage::Processed(mock_remittance))
+            .send(RemittanceMessage::Processed(Remittance::from_claim(&unknown_claim, mock_adjudication_date())))
             .await
             .unwrap();
         // Should not panic, just log error
@@ -376,14 +1174,12 @@ mod tests {
         let (remittance_tx, remittance_rx) = tokio::sync::mpsc::channel(1);
         let (payer_tx, _payer_rx) = tokio::sync::mpsc::channel(1);
         let mut payer_txs = HashMap::new();
-        payer_txs.insert("medicare".to_string(), payer_tx);
-        let biller_txs = Arc::new(Mutex::new(HashMap::new()));
-        let history = Arc::new(Mutex::new(HashMap::new()));
+        payer_txs.insert("medicare".into(), payer_tx);
+        let history = History::default();
         let clearinghouse = Clearinghouse::new(
             claim_rx,
             payer_txs,
             remittance_rx,
-            biller_txs.clone(),
             history.clone(),
             false,
         );
@@ -393,18 +1189,17 @@ mod tests {
         let mock_claim = mock_claim();
         let mock_remittance = mock_remittance();
         // Manually insert a remitted claim to simulate wrong state
-        {
-            let mut history = history.lock().await;
-            history.insert(
-                mock_claim.claim_id.clone(),
+        history
+            .insert(
+                ClaimKey::new(DEFAULT_TENANT, mock_claim.claim_id.clone()),
                 ClaimStatus::Remitted(RemittanceRecord::new(
                     mock_claim,
                     mock_remittance.clone(),
                     Instant::now(),
                     Instant::now(),
                 )),
-            );
-        }
+            )
+            .await;
         remittance_tx
             .send(RemittanceMessage::Processed(mock_remittance))
             .await
@@ -420,14 +1215,12 @@ mod tests {
         let (remittance_tx, remittance_rx) = tokio::sync::mpsc::channel(1);
         let (payer_tx, _payer_rx) = tokio::sync::mpsc::channel(1);
         let mut payer_txs = HashMap::new();
-        payer_txs.insert("medicare".to_string(), payer_tx);
-        let biller_txs = Arc::new(Mutex::new(HashMap::new()));
-        let history = Arc::new(Mutex::new(HashMap::new()));
+        payer_txs.insert("medicare".into(), payer_tx);
+        let history = History::default();
         let clearinghouse = Clearinghouse::new(
             claim_rx,
             payer_txs,
             remittance_rx,
-            biller_txs.clone(),
             history.clone(),
             false,
         );
@@ -437,16 +1230,17 @@ mod tests {
         let mock_claim = mock_claim();
         let mock_remittance = mock_remittance();
         // Manually insert claim in history but don't add biller channel
-        {
-            let mut history = history.lock().await;
-            history.insert(
-                mock_claim.claim_id.clone(),
+        history
+            .insert(
+                ClaimKey::new(DEFAULT_TENANT, mock_claim.claim_id.clone()),
                 ClaimStatus::Submitted {
-                    claim: mock_claim,
+                    claim: mock_claim.into(),
+                    tenant_id: DEFAULT_TENANT.to_string(),
+                    metadata: ClaimMetadata::default(),
                     submitted_at: Instant::now(),
                 },
-            );
-        }
+            )
+            .await;
         remittance_tx
             .send(RemittanceMessage::Processed(mock_remittance))
             .await
@@ -462,14 +1256,12 @@ mod tests {
         let (remittance_tx, remittance_rx) = tokio::sync::mpsc::channel(2);
         let (payer_tx, mut payer_rx) = tokio::sync::mpsc::channel(2);
         let mut payer_txs = HashMap::new();
-        payer_txs.insert("medicare".to_string(), payer_tx);
-        let biller_txs = Arc::new(Mutex::new(HashMap::new()));
-        let history = Arc::new(Mutex::new(HashMap::new()));
+        payer_txs.insert("medicare".into(), payer_tx);
+        let history = History::new(2);
         let clearinghouse = Clearinghouse::new(
             claim_rx,
             payer_txs,
             remittance_rx,
-            biller_txs.clone(),
             history.clone(),
             false,
         );
@@ -478,35 +1270,38 @@ mod tests {
         });
         let claim1 = mock_claim();
         let mut claim2 = mock_claim();
-        claim2.claim_id = "claim2".to_string();
+        claim2.claim_id = "claim2".into();
         let (response_tx1, mut response_rx1) = tokio::sync::mpsc::channel(1);
         let (response_tx2, mut response_rx2) = tokio::sync::mpsc::channel(1);
         let envelope1 = ClaimEnvelope {
-            claim: claim1.clone(),
+            claim: claim1.clone().into(),
             response_tx: response_tx1,
+            tenant_id: DEFAULT_TENANT.to_string(),
+            metadata: ClaimMetadata::default(),
         };
         let envelope2 = ClaimEnvelope {
-            claim: claim2.clone(),
+            claim: claim2.clone().into(),
             response_tx: response_tx2,
+            tenant_id: DEFAULT_TENANT.to_string(),
+            metadata: ClaimMetadata::default(),
         };
         claim_tx
-            .send(ClaimMessage::NewClaim(envelope1))
+            .send(ClaimMessage::NewClaim(Box::new(envelope1)))
             .await
             .unwrap();
         claim_tx
-            .send(ClaimMessage::NewClaim(envelope2))
+            .send(ClaimMessage::NewClaim(Box::new(envelope2)))
             .await
             .unwrap();
         // Verify both claims were sent to payer
         for _ in 0..2 {
-            if let Some(PayerMessage::Adjudicate(claim)) = payer_rx.recv().await {
+            if let Some(PayerMessage::Adjudicate(claim, _)) = payer_rx.recv().await {
                 assert!(claim.claim_id == "abc123" || claim.claim_id == "claim2");
             }
         }
         // Send remittances back
         let remittance1 = mock_remittance();
-        let mut remittance2 = mock_remittance();
-        remittance2.claim_id = "claim2".to_string();
+        let remittance2 = Remittance::from_claim(&claim2, mock_adjudication_date());
         remittance_tx
             .send(RemittanceMessage::Processed(remittance1))
             .await
@@ -521,4 +1316,159 @@ mod tests {
         assert!(matches!(response1, RemittanceMessage::Processed(_)));
         assert!(matches!(response2, RemittanceMessage::Processed(_)));
     }
+
+    /// Test that resubmitting identical claim content is answered with the original remittance.
+    /// Expected: Payer adjudicates once; pending and later duplicates receive Duplicate remittances.
+    #[tokio::test]
+    async fn test_duplicate_claim_not_readjudicated() {
+        let (claim_tx, claim_rx) = tokio::sync::mpsc::channel(4);
+        let (remittance_tx, remittance_rx) = tokio::sync::mpsc::channel(1);
+        let (payer_tx, mut payer_rx) = tokio::sync::mpsc::channel(4);
+        let mut payer_txs = HashMap::new();
+        payer_txs.insert("medicare".into(), payer_tx);
+        let clearinghouse = Clearinghouse::new(
+            claim_rx,
+            payer_txs,
+            remittance_rx,
+            History::default(),
+            false,
+        );
+        tokio::spawn(clearinghouse.run());
+
+        let submit = |claim_tx: Sender<ClaimMessage>| async move {
+            let (response_tx, response_rx) = tokio::sync::mpsc::channel(1);
+            claim_tx
+                .send(ClaimMessage::NewClaim(Box::new(ClaimEnvelope { claim: mock_claim().into(), response_tx, tenant_id: DEFAULT_TENANT.to_string(), metadata: ClaimMetadata::default() })))
+                .await
+                .unwrap();
+            response_rx
+        };
+
+        let mut original_rx = submit(claim_tx.clone()).await;
+        let mut pending_dup_rx = submit(claim_tx.clone()).await;
+        assert!(payer_rx.recv().await.is_some());
+
+        remittance_tx
+            .send(RemittanceMessage::Processed(mock_remittance()))
+            .await
+            .unwrap();
+        assert!(matches!(original_rx.recv().await, Some(RemittanceMessage::Processed(_))));
+        assert!(matches!(pending_dup_rx.recv().await, Some(RemittanceMessage::Duplicate(_))));
+
+        let mut late_dup_rx = submit(claim_tx.clone()).await;
+        assert!(matches!(late_dup_rx.recv().await, Some(RemittanceMessage::Duplicate(_))));
+        assert!(payer_rx.try_recv().is_err());
+    }
+
+    /// Test that a stopped clearinghouse takes no new claims but still passes remittances for claims it routed.
+    /// Expected: After stopping, the routed claim's remittance reaches its biller and a claim sent afterwards never reaches the payer.
+    #[tokio::test]
+    async fn test_stopped_clearinghouse_forwards_remittances() {
+        let (claim_tx, claim_rx) = tokio::sync::mpsc::channel(4);
+        let (remittance_tx, remittance_rx) = tokio::sync::mpsc::channel(1);
+        let (payer_tx, mut payer_rx) = tokio::sync::mpsc::channel(4);
+        let payer_txs = HashMap::from([("medicare".into(), payer_tx)]);
+        let clearinghouse = Clearinghouse::new(claim_rx, payer_txs, remittance_rx, History::default(), false);
+        let handle = clearinghouse.handle();
+        tokio::spawn(clearinghouse.run());
+
+        let (response_tx, mut response_rx) = tokio::sync::mpsc::channel(2);
+        let submit = |claim: PayerClaim| ClaimMessage::NewClaim(Box::new(ClaimEnvelope {
+            claim: claim.into(),
+            response_tx: response_tx.clone(),
+            tenant_id: DEFAULT_TENANT.to_string(),
+            metadata: ClaimMetadata::default(),
+        }));
+        claim_tx.send(submit(mock_claim())).await.unwrap();
+        assert!(payer_rx.recv().await.is_some());
+
+        handle.stop();
+        claim_tx.send(submit(PayerClaim { claim_id: "late".into(), ..mock_claim() })).await.unwrap();
+        remittance_tx.send(RemittanceMessage::Processed(mock_remittance())).await.unwrap();
+        let remitted = tokio::time::timeout(Duration::from_secs(1), response_rx.recv()).await;
+        assert!(matches!(remitted, Ok(Some(RemittanceMessage::Processed(_)))));
+        assert!(tokio::time::timeout(Duration::from_millis(100), payer_rx.recv()).await.is_err());
+    }
+
+    /// Test that an original is replaced once, and is free again after its payer refuses the replacement.
+    /// Expected: A replacement the payer refuses is rejected to its biller and voided; the next replacement reaches the payer,
+    /// and a third replacement of the same original is rejected without reaching it.
+    #[tokio::test]
+    async fn test_claim_replaced_once() {
+        let (claim_tx, claim_rx) = tokio::sync::mpsc::channel(4);
+        let (remittance_tx, remittance_rx) = tokio::sync::mpsc::channel(1);
+        let (payer_tx, mut payer_rx) = tokio::sync::mpsc::channel(4);
+        let payer_txs = HashMap::from([("medicare".into(), payer_tx)]);
+        let history = History::default();
+        tokio::spawn(Clearinghouse::new(claim_rx, payer_txs, remittance_rx, history.clone(), false).run());
+
+        let (response_tx, mut response_rx) = tokio::sync::mpsc::channel(4);
+        let submit = |claim: PayerClaim| ClaimMessage::NewClaim(Box::new(ClaimEnvelope {
+            claim: claim.into(),
+            response_tx: response_tx.clone(),
+            tenant_id: DEFAULT_TENANT.to_string(),
+            metadata: ClaimMetadata::default(),
+        }));
+        let replacement = |claim_id: &str| {
+            let mut claim = mock_claim();
+            claim.claim_id = claim_id.into();
+            claim.claim_frequency_code = ClaimFrequency::Replacement;
+            claim.original_claim_id = Some(mock_claim().claim_id);
+            claim
+        };
+        claim_tx.send(submit(mock_claim())).await.unwrap();
+        assert!(payer_rx.recv().await.is_some());
+        remittance_tx.send(RemittanceMessage::Processed(mock_remittance())).await.unwrap();
+        assert!(matches!(response_rx.recv().await, Some(RemittanceMessage::Processed(_))));
+
+        claim_tx.send(submit(replacement("refused"))).await.unwrap();
+        assert!(payer_rx.recv().await.is_some());
+        let refusal = RemittanceMessage::Rejected { claim_id: "refused".into(), reason: "nothing to reverse".to_string() };
+        remittance_tx.send(refusal).await.unwrap();
+        assert!(matches!(response_rx.recv().await, Some(RemittanceMessage::Rejected { .. })));
+        let refused = history.get(&ClaimKey::new(DEFAULT_TENANT, "refused")).await;
+        assert!(matches!(refused, Some(ClaimStatus::Voided { .. })));
+
+        claim_tx.send(submit(replacement("accepted"))).await.unwrap();
+        let Some(PayerMessage::Adjudicate(sent, _)) = payer_rx.recv().await else {
+            panic!("Expected the second replacement at the payer");
+        };
+        assert_eq!(sent.claim_id, "accepted");
+
+        claim_tx.send(submit(replacement("again"))).await.unwrap();
+        let Some(RemittanceMessage::Rejected { claim_id, reason }) = response_rx.recv().await else {
+            panic!("Expected RemittanceMessage::Rejected");
+        };
+        assert_eq!(claim_id, "again");
+        assert!(reason.contains("already replaced"), "{}", reason);
+        assert!(payer_rx.try_recv().is_err());
+    }
+
+    /// Test that a claim failing a front-end edit is rejected back to its biller without reaching the payer.
+    /// Expected: The biller receives a rejection naming a syntax edit code, the payer receives nothing, the claim is not in history, and the rejection is counted.
+    #[tokio::test]
+    async fn test_front_end_edit_rejects_claim() {
+        let (claim_tx, claim_rx) = tokio::sync::mpsc::channel(1);
+        let (_remittance_tx, remittance_rx) = tokio::sync::mpsc::channel(1);
+        let (payer_tx, mut payer_rx) = tokio::sync::mpsc::channel(1);
+        let payer_txs = HashMap::from([("medicare".into(), payer_tx)]);
+        let history = History::default();
+        let clearinghouse = Clearinghouse::new(claim_rx, payer_txs, remittance_rx, history.clone(), false)
+            .with_edits(EditRates { syntax: 1.0, ..EditRates::default() });
+        let edit_stats = clearinghouse.edit_stats();
+        tokio::spawn(clearinghouse.run());
+
+        let (response_tx, mut response_rx) = tokio::sync::mpsc::channel(1);
+        let envelope = ClaimEnvelope { claim: mock_claim().into(), response_tx, tenant_id: DEFAULT_TENANT.to_string(), metadata: ClaimMetadata::default() };
+        claim_tx.send(ClaimMessage::NewClaim(Box::new(envelope))).await.unwrap();
+
+        let Some(RemittanceMessage::Rejected { claim_id, reason }) = response_rx.recv().await else {
+            panic!("Expected RemittanceMessage::Rejected");
+        };
+        assert_eq!(claim_id, "abc123");
+        assert!(reason.starts_with("syntax edit SYN"), "{}", reason);
+        assert!(payer_rx.try_recv().is_err());
+        assert!(!history.contains(&ClaimKey::new(DEFAULT_TENANT, claim_id)).await);
+        assert_eq!((edit_stats.rejected(crate::edits::EditLevel::Syntax), edit_stats.total()), (1, 1));
+    }
 }
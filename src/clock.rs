@@ -0,0 +1,98 @@
+use chrono::{Datelike, Duration as ChronoDuration, NaiveDate, NaiveDateTime};
+use std::time::{Duration, Instant};
+
+/// Virtual simulation clock mapping wall-clock time onto simulated calendar time
+///
+/// The simulation runs faster than real life: by default one simulated day
+/// passes every two real seconds, so a simulated month takes about a minute
+#[derive(Debug, Clone, Copy)]
+pub struct SimClock {
+    origin: Instant,
+    start: NaiveDateTime,
+    real_secs_per_day: f64,
+}
+
+impl Default for SimClock {
+    /// Clock running at real speed from midnight today
+    fn default() -> Self {
+        Self::new(chrono::Utc::now().date_naive(), 86_400.0)
+    }
+}
+
+impl SimClock {
+    /// Start a clock at `start` (midnight) that advances one day every `real_secs_per_day` seconds
+    pub fn new(start: NaiveDate, real_secs_per_day: f64) -> Self {
+        Self::starting_at(Instant::now(), start, real_secs_per_day)
+    }
+
+    /// Clock whose simulated `start` corresponds to the real instant `origin`
+    pub fn starting_at(origin: Instant, start: NaiveDate, real_secs_per_day: f64) -> Self {
+        Self {
+            origin,
+            start: start.and_hms_opt(0, 0, 0).expect("midnight is a valid time"),
+            real_secs_per_day,
+        }
+    }
+
+    /// Current simulated time
+    pub fn now(&self) -> NaiveDateTime {
+        self.at(Instant::now())
+    }
+
+    /// Simulated time of a real instant
+    pub fn at(&self, instant: Instant) -> NaiveDateTime {
+        let real_secs = instant.saturating_duration_since(self.origin).as_secs_f64();
+        let sim_secs = real_secs / self.real_secs_per_day * 86_400.0;
+        self.start + ChronoDuration::milliseconds((sim_secs * 1000.0) as i64)
+    }
+
+    /// Simulated days that pass in a real `elapsed`
+    pub fn days(&self, elapsed: Duration) -> f64 {
+        elapsed.as_secs_f64() / self.real_secs_per_day
+    }
+
+    /// Simulated days from the clock's start to the real instant `now`
+    pub fn elapsed_days(&self, now: Instant) -> f64 {
+        self.days(now.saturating_duration_since(self.origin))
+    }
+
+    /// Real instant at which the simulated time `datetime` is reached
+    pub fn instant_of(&self, datetime: NaiveDateTime) -> Instant {
+        let sim_secs = (datetime - self.start).num_milliseconds().max(0) as f64 / 1000.0;
+        self.origin + Duration::from_secs_f64(sim_secs / 86_400.0 * self.real_secs_per_day)
+    }
+}
+
+/// First day of the month containing `date`
+pub fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).expect("day 1 exists in every month")
+}
+
+/// First day of the month after the one containing `date`
+pub fn next_month_start(date: NaiveDate) -> NaiveDate {
+    let (year, month) = match date.month() {
+        12 => (date.year() + 1, 1),
+        month => (date.year(), month + 1),
+    };
+    NaiveDate::from_ymd_opt(year, month, 1).expect("day 1 exists in every month")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that real elapsed time maps to simulated days and back.
+    /// Expected: 4 real seconds at 2 s/day is 2 simulated days; the inverse maps back.
+    #[test]
+    fn test_sim_clock_mapping() {
+        let origin = Instant::now();
+        let start = NaiveDate::from_ymd_opt(2025, 1, 30).unwrap();
+        let clock = SimClock::starting_at(origin, start, 2.0);
+        let later = origin + Duration::from_secs(4);
+        assert_eq!(clock.at(later).date(), NaiveDate::from_ymd_opt(2025, 2, 1).unwrap());
+        let feb = next_month_start(start).and_hms_opt(0, 0, 0).unwrap();
+        assert_eq!(clock.instant_of(feb), later);
+        assert_eq!(next_month_start(NaiveDate::from_ymd_opt(2025, 12, 5).unwrap()), NaiveDate::from_ymd_opt(2026, 1, 1).unwrap());
+        assert_eq!(month_start(start), NaiveDate::from_ymd_opt(2025, 1, 1).unwrap());
+    }
+}
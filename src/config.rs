@@ -1,33 +1,1373 @@
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::audit_log::AuditExportFormat;
+use crate::clearinghouse::DEFAULT_CLEARINGHOUSE_SHARDS;
+use crate::edits::EditRates;
+use crate::file_drop::FileDropSettings;
+use crate::ids::{ClaimId, PayerId};
+use crate::loadtest::LoadTestOptions;
+use crate::monte_carlo::MonteCarloOptions;
+use crate::patient_payer::{CollectionsAction, CollectionsPolicy};
+use crate::payer::DEFAULT_PAYER_WORKERS;
+use crate::payer_rules::RulePaths;
+use crate::queue::OverflowPolicy;
+use crate::reader;
+use crate::rejects;
+use crate::report_format::{Locale, ReportFormat, Units};
+use crate::remittance::MismatchPolicy;
+use crate::remittance_sink::SinkSpec;
+use crate::logging::{ClaimLogSpec, LogSinks};
+use crate::run_dir::DEFAULT_KEEP_RUNS;
+use crate::remittance_files::REMITTANCE_FILES_DIR;
+use crate::roster::RosterOptions;
+use crate::simulation::{DEFAULT_CHANNEL_CAPACITY, PayerSpec};
+use crate::sla::PayerSla;
+use crate::stop::StopCondition;
+use crate::supervisor::SupervisorSettings;
+use crate::validation::{AllowedCurrencies, Strictness};
+use crate::watchdog::StuckAction;
+use crate::what_if::ContractPaths;
+use crate::workqueue::StaffMember;
+use crate::tenant::{self, TenantConfig};
+
+/// Directory the `scenarios` command looks for scenarios in, one subdirectory each
+pub const SCENARIOS_DIR: &str = "scenarios";
 
 /// Application configuration for claim processing simulation
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct Config {
     pub file_path: String,
     pub ingest_rate: u64,
     pub verbose: bool,
+    pub checkpoint_path: Option<String>,
+    pub rejects_path: Option<String>,
+    pub burst: u32,
+    pub api_addr: Option<String>,
+    pub event_log_path: Option<String>,
+    /// Billing organizations with their own claim streams; empty runs a single
+    /// default tenant reading `file_path`
+    pub tenants: Vec<TenantConfig>,
+    /// Fake claims generated for a tenant with no claim file of its own
+    pub fake_claims: usize,
+    pub procedure_csv_path: Option<String>,
+    /// Real seconds per simulated day on the virtual clock
+    pub secs_per_day: f64,
+    /// OTLP gRPC collector receiving claim lifecycle spans (requires the `otel` feature)
+    pub otlp_endpoint: Option<String>,
+    /// Capacity of the pipeline's internal channels
+    pub channel_capacity: usize,
+    /// What the clearinghouse's and payers' claim inboxes do when full
+    pub overflow_policy: OverflowPolicy,
+    /// How the reader treats claims with invalid NPIs or EINs
+    pub validation: Strictness,
+    /// Currencies claims may be billed in
+    pub currencies: AllowedCurrencies,
+    /// What payers do with remittances that do not balance against their claim
+    pub mismatch_policy: MismatchPolicy,
+    /// Fraction of remittances payers price wrong, to exercise the mismatch policy
+    pub remit_error_rate: f64,
+    /// JSON table of adjudication outcome weights by procedure code range
+    pub outcomes_path: Option<String>,
+    /// JSON medical necessity rules linking procedure code ranges to the diagnoses that justify them
+    pub coverage_path: Option<String>,
+    /// JSON rules naming the documents payers require for procedure code ranges
+    pub attachments_path: Option<String>,
+    /// JSON plan designs and the members enrolled in each
+    pub benefits_path: Option<String>,
+    /// Reload the outcome, coverage, attachment, and benefit files when they change, applying them to running payers
+    pub watch_rules: bool,
+    /// Generate a member roster per payer, churning this fraction of members each month
+    pub roster_churn: Option<f64>,
+    /// Members on each generated roster at the start
+    pub roster_members: usize,
+    /// JSON share of fake claims billed to each payer and the reimbursement rate each is expected to pay
+    pub payer_mix_path: Option<String>,
+    /// Seed of the rosters and of the payer and member each fake claim is drawn for
+    pub fake_seed: Option<u64>,
+    /// Statements sent for a patient balance before it goes to collections
+    pub statements: u32,
+    /// What happens to a patient balance unpaid after the last statement
+    pub collections_action: CollectionsAction,
+    /// Claims each payer adjudicates at once
+    pub payer_workers: usize,
+    /// Clearinghouse workers, each owning the claims whose id hashes to it
+    pub clearinghouse_shards: usize,
+    /// Check simulation invariants while running, treating claims pending this many seconds as lost
+    pub invariant_max_pending_secs: Option<u64>,
+    /// Locale and units of report amounts on the console, in CSV, and in the API's JSON
+    pub report_format: ReportFormat,
+    /// SQLite database the run's claims, denials, and payments are stored in at shutdown
+    pub run_db_path: Option<String>,
+    /// Payer ids routed to external gRPC payers, with each one's endpoint
+    pub grpc_payers: BTreeMap<PayerId, String>,
+    /// Downstream systems every biller tells about each remittance it receives
+    pub remittance_sinks: Vec<SinkSpec>,
+    /// Where each component's claim events are logged: stdout, files, rolling files, or syslog
+    pub log_sinks: LogSinks,
+    /// Hash-chained log of each component that touches a claim record
+    pub audit_log_path: Option<String>,
+    /// Mask patient demographics in the event log, rejects, and messages
+    pub redact_phi: bool,
+    /// Secret key file; when given, patients are replaced by stable synthetic identities instead of masked
+    pub pseudonym_key_path: Option<String>,
+    /// Start paused under a debugger driven from stdin, stepping messages one at a time
+    pub debug: bool,
+    /// Seconds a component may make no progress while work waits for it before it is stalled; 0 never
+    pub stall_secs: u64,
+    /// Times a failed payer or reporter is restarted before it is left failed
+    pub max_restarts: u32,
+    /// Watch for claims waiting on their payer this many seconds; `None` never
+    pub stuck_after_secs: Option<u64>,
+    /// What the watchdog does about a stuck claim beyond alerting
+    pub stuck_action: StuckAction,
+    /// Service levels payers are held to, tracked by the reporter
+    pub slas: BTreeMap<PayerId, PayerSla>,
+    /// Forecast payer cash expected over this many simulated days in the reporter
+    pub forecast_days: Option<f64>,
+    /// Billing staff who work denials, underpayments, and remittance exceptions; none leaves them unworked
+    pub staff: Vec<StaffMember>,
+    /// Fraction of claims the clearinghouse rejects at each level of front-end edits, and whether
+    /// payers' companion guides are enforced
+    pub edit_rates: EditRates,
+    /// Fraction of the in-person rate each payer pays for telehealth; payers left out pay at parity
+    pub telehealth_rates: BTreeMap<PayerId, f64>,
+    /// Most service lines each payer accepts on a claim; the biller splits longer claims. Payers left out take any number
+    pub max_service_lines: BTreeMap<PayerId, usize>,
+    /// Range of seconds each payer takes to acknowledge a claim, in place of its default
+    pub ack_delays: BTreeMap<PayerId, (u64, u64)>,
+    /// Where the single default tenant's fake claims are written before it reads them
+    pub generated_claims_path: String,
+    /// Collect the run's outputs in a `runs/<timestamp>[-<name>]/` directory
+    pub run_dir: bool,
+    /// Name the run directory carries after its timestamp
+    pub run_name: Option<String>,
+    /// Run directories kept, newest first, before older ones are removed; 0 keeps all
+    pub keep_runs: usize,
+    /// End the run early once any of these is met
+    pub stop_conditions: Vec<StopCondition>,
+    /// Exchange claims and remittances between billers and the clearinghouse as files in a
+    /// polled directory rather than as messages
+    pub file_drop: Option<FileDropSettings>,
+    /// Directory the clearinghouse writes a remittance file per payer per simulated day to
+    pub remittance_files_dir: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            file_path: "fake_claims.jsonl".to_string(),
+            ingest_rate: 1,
+            verbose: false,
+            checkpoint_path: None,
+            rejects_path: None,
+            burst: 1,
+            api_addr: None,
+            event_log_path: None,
+            tenants: Vec::new(),
+            fake_claims: 10,
+            procedure_csv_path: None,
+            secs_per_day: 2.0,
+            otlp_endpoint: None,
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            overflow_policy: OverflowPolicy::Block,
+            validation: Strictness::Warn,
+            currencies: AllowedCurrencies::default(),
+            mismatch_policy: MismatchPolicy::Flag,
+            remit_error_rate: 0.0,
+            outcomes_path: None,
+            coverage_path: None,
+            attachments_path: None,
+            benefits_path: None,
+            watch_rules: false,
+            roster_churn: None,
+            roster_members: RosterOptions::default().members,
+            payer_mix_path: None,
+            fake_seed: None,
+            statements: CollectionsPolicy::default().statements,
+            collections_action: CollectionsAction::default(),
+            payer_workers: DEFAULT_PAYER_WORKERS,
+            clearinghouse_shards: DEFAULT_CLEARINGHOUSE_SHARDS,
+            invariant_max_pending_secs: None,
+            report_format: ReportFormat::default(),
+            run_db_path: None,
+            grpc_payers: BTreeMap::new(),
+            remittance_sinks: Vec::new(),
+            log_sinks: LogSinks::default(),
+            audit_log_path: None,
+            redact_phi: false,
+            pseudonym_key_path: None,
+            debug: false,
+            stall_secs: SupervisorSettings::default().stall_after.map_or(0, |after| after.as_secs()),
+            max_restarts: SupervisorSettings::default().max_restarts,
+            stuck_after_secs: None,
+            stuck_action: StuckAction::default(),
+            slas: BTreeMap::new(),
+            forecast_days: None,
+            staff: Vec::new(),
+            edit_rates: EditRates::default(),
+            telehealth_rates: BTreeMap::new(),
+            max_service_lines: BTreeMap::new(),
+            ack_delays: BTreeMap::new(),
+            generated_claims_path: "fake_claims.jsonl".to_string(),
+            run_dir: false,
+            run_name: None,
+            keep_runs: DEFAULT_KEEP_RUNS,
+            stop_conditions: Vec::new(),
+            file_drop: None,
+            remittance_files_dir: None,
+        }
+    }
+}
+
+impl Config {
+    /// Tenants to simulate: the configured list, or one default tenant reading `file_path`
+    pub fn tenant_configs(&self) -> Vec<TenantConfig> {
+        if self.tenants.is_empty() {
+            vec![TenantConfig {
+                tenant_id: tenant::DEFAULT_TENANT.to_string(),
+                file_path: self.file_path.clone(),
+            }]
+        } else {
+            self.tenants.clone()
+        }
+    }
+
+    /// Files the payers' outcome, coverage, attachment, and benefit rules are read from
+    pub fn rule_paths(&self) -> RulePaths {
+        RulePaths {
+            outcomes: self.outcomes_path.clone(),
+            coverage: self.coverage_path.clone(),
+            attachments: self.attachments_path.clone(),
+            benefits: self.benefits_path.clone(),
+        }
+    }
+
+    /// Check the configuration before a run starts with these simulated payers, reporting every
+    /// problem found in one error rather than the first
+    ///
+    /// Parsing already drops values it cannot read; this catches what parses but cannot run, such
+    /// as zero-sized channels, a payer whose minimum response time exceeds its maximum, payer ids
+    /// in `--grpc-payers`, `--sla`, `--telehealth-rates`, `--max-service-lines`, or `--ack-delays` that name none
+    /// of `payers`, and rule files that do not exist
+    pub fn validate(&self, payers: &[PayerSpec]) -> anyhow::Result<()> {
+        let mut problems = Vec::new();
+        let mut at_least_one = |value: usize, what: &str| {
+            if value == 0 {
+                problems.push(format!("{} must be at least 1", what));
+            }
+        };
+        at_least_one(self.ingest_rate as usize, "ingest_rate (seconds between claims)");
+        at_least_one(self.burst as usize, "--burst");
+        at_least_one(self.fake_claims, "--fake-claims");
+        at_least_one(self.channel_capacity, "--channel-capacity");
+        at_least_one(self.payer_workers, "--payer-workers");
+        at_least_one(self.clearinghouse_shards, "--clearinghouse-shards");
+        if !(self.secs_per_day.is_finite() && self.secs_per_day > 0.0) {
+            problems.push(format!("--secs-per-day must be positive, not {}", self.secs_per_day));
+        }
+        if !(0.0..=1.0).contains(&self.remit_error_rate) {
+            problems.push(format!("--remit-error-rate must be between 0 and 1, not {}", self.remit_error_rate));
+        }
+
+        if payers.is_empty() {
+            problems.push("no payers to route claims to".to_string());
+        }
+        let mut known = HashSet::new();
+        for payer in payers {
+            if !known.insert(&payer.payer_id) {
+                problems.push(format!("payer {} is configured twice", payer.payer_id));
+            }
+            if let Err(e) = payer.settings().validate() {
+                problems.push(format!("payer {}: {}", payer.payer_id, e));
+            }
+        }
+        let known_list = payers.iter().map(|payer| payer.payer_id.as_str()).collect::<Vec<_>>().join(", ");
+        let referenced: [(&str, Vec<&PayerId>); 5] = [
+            ("--grpc-payers", self.grpc_payers.keys().collect()),
+            ("--sla", self.slas.keys().collect()),
+            ("--telehealth-rates", self.telehealth_rates.keys().collect()),
+            ("--max-service-lines", self.max_service_lines.keys().collect()),
+            ("--ack-delays", self.ack_delays.keys().collect()),
+        ];
+        for (flag, payer_ids) in referenced {
+            for payer_id in payer_ids.into_iter().filter(|payer_id| !known.contains(payer_id)) {
+                problems.push(format!("{} names unknown payer {} (known payers: {})", flag, payer_id, known_list));
+            }
+        }
+
+        let mut tenant_ids = HashSet::new();
+        for tenant in &self.tenants {
+            if !tenant_ids.insert(&tenant.tenant_id) {
+                problems.push(format!("--tenants lists tenant {} twice", tenant.tenant_id));
+            }
+            if let Err(e) = tenant::check_tenant_id(&tenant.tenant_id) {
+                problems.push(format!("--tenants: {}", e));
+            }
+        }
+        let inputs = [
+            ("--outcomes", &self.outcomes_path),
+            ("--coverage", &self.coverage_path),
+            ("--attachments", &self.attachments_path),
+            ("--benefits", &self.benefits_path),
+            ("--payer-mix", &self.payer_mix_path),
+            ("--pseudonym-key", &self.pseudonym_key_path),
+        ];
+        for (flag, path) in inputs {
+            if let Some(path) = path
+                && !Path::new(path).exists()
+            {
+                problems.push(format!("{} file {} does not exist", flag, path));
+            }
+        }
+        if self.watch_rules && self.rule_paths().is_empty() {
+            problems.push("--watch-rules needs at least one of --outcomes, --coverage, --attachments, or --benefits".to_string());
+        }
+        // the debug console reads its commands from stdin
+        if self.debug && self.tenant_configs().iter().any(|tenant| tenant.file_path == reader::STDIN_PATH) {
+            problems.push("--debug reads commands from stdin, so claims cannot be read from it".to_string());
+        }
+
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(anyhow::anyhow!("Invalid configuration:\n  - {}", problems.join("\n  - "))),
+        }
+    }
+}
+
+/// Top-level command selected on the command line
+pub enum Command {
+    /// Run the claim processing simulation
+    Simulate(Box<Config>),
+    /// Write fake claims as JSONL to stdout, e.g. `generate 100 | simulate -`
+    Generate { count: usize },
+    /// Rebuild reports from a recorded event log, optionally paced at `speed`x
+    /// and filtered to one tenant
+    Replay { event_log_path: String, speed: Option<f64>, tenant: Option<String>, format: ReportFormat },
+    /// Push fake claims through the pipeline with no delays and report throughput
+    LoadTest(LoadTestOptions, ReportFormat),
+    /// Query a run database stored with `--run-db`, optionally for one tenant
+    Report {
+        query: String,
+        db_path: String,
+        /// Date AR is aged to; the run's last simulated day when absent
+        as_of: Option<NaiveDate>,
+        tenant: Option<String>,
+        format: ReportFormat,
+    },
+    /// Adjudicate a stored claim set offline under current and proposed contract terms
+    WhatIf {
+        claims_path: String,
+        baseline: ContractPaths,
+        /// Terms being considered; any file not given is the baseline's
+        proposed: ContractPaths,
+        seed: u64,
+        format: ReportFormat,
+    },
+    /// Run a claim set through the simulated payers many times with different seeds and
+    /// summarize the spread of the results
+    MonteCarlo {
+        claims_path: String,
+        options: MonteCarloOptions,
+        terms: ContractPaths,
+        denial_rate: f64,
+        error_rate: f64,
+        format: ReportFormat,
+    },
+    /// Verify an audit log's hash chain and export its entries, optionally for one claim
+    AuditExport {
+        audit_log_path: String,
+        format: AuditExportFormat,
+        claim_id: Option<ClaimId>,
+        /// File the export is written to; stdout when absent
+        output_path: Option<String>,
+    },
+    /// Run every scenario under a directory, or the one named, and compare its outputs to the
+    /// golden files, rewriting them instead when `update` is set
+    Scenarios { dir: String, name: Option<String>, update: bool },
+    /// Post the remittance files written with `--remittance-files` to fresh ledgers and report
+    /// them, optionally for one tenant
+    PostRemittances { dir: String, tenant: Option<String>, format: ReportFormat },
+}
+
+/// Parse command line arguments into a command
+///
+/// `generate [count]` writes fake claims to stdout (default: 10);
+/// `replay <event_log> [--speed <factor>] [--tenant <id>]` replays a recorded run (default log: events.jsonl);
+/// `loadtest [--claims <n>] [--payers <n>] [--payer-workers <n>] [--clearinghouse-shards <n>] [--channel-capacity <n>]`
+/// measures pipeline throughput (default: 10000 claims over 3 payers);
+/// `report <ar-aging|denials|payments> [run_db] [--as-of <YYYY-MM-DD>] [--tenant <id>]` queries a
+/// run stored with `--run-db` (default database: run.db);
+/// all three also take the simulation's `--locale`, `--units`, and `--report-rows` report formatting flags;
+/// `what-if <claims> [--benefits <path>] [--outcomes <path>] [--coverage <path>] [--proposed-benefits <path>]
+/// [--proposed-outcomes <path>] [--proposed-coverage <path>] [--seed <n>]` compares revenue of a claims file
+/// or event log under current and proposed contract terms (default seed: 0);
+/// `monte-carlo <claims> [--runs <n>] [--parallel <n>] [--seed <n>] [--secs-per-day <secs>] [--denial-rate <rate>]
+/// [--remit-error-rate <rate>] [--benefits <path>] [--outcomes <path>] [--coverage <path>]` runs a claims file
+/// or event log through the simulation once per seed (default: 20 runs from seed 0, 10 at once);
+/// `audit-export [audit_log] [--format <csv|jsonl>] [--claim <id>] [--output <path>]` verifies and
+/// exports an audit log (default log: audit.jsonl, format: csv, output: stdout);
+/// `scenarios [dir] [--name <scenario>] [--update]` runs golden-file scenarios and reports any whose
+/// events or reports changed, or rewrites their golden files with `--update` (default dir: scenarios);
+/// `post-remittances [dir] [--tenant <id>]` posts the remittance files of a run to ledgers and prints
+/// their reports (default dir: remittances);
+/// anything else is treated as simulation arguments
+pub fn command() -> anyhow::Result<Command> {
+    parse_command(env::args().skip(1).collect())
+}
+
+pub fn parse_command(args: Vec<String>) -> anyhow::Result<Command> {
+    let command = match args.first().map(String::as_str) {
+        Some("generate") => Command::Generate {
+            count: args.get(1).and_then(|s| s.parse().ok()).unwrap_or(10),
+        },
+        Some("replay") => {
+            let (args, flags) = split_flags(args.into_iter().skip(1).collect())?;
+            Command::Replay {
+                event_log_path: args.first().cloned().unwrap_or_else(|| "events.jsonl".to_string()),
+                speed: flags.get("speed").and_then(|s| s.parse().ok()),
+                tenant: flags.get("tenant").cloned(),
+                format: report_format(&flags),
+            }
+        }
+        Some("loadtest") => {
+            let (_, flags) = split_flags(args.into_iter().skip(1).collect())?;
+            let defaults = LoadTestOptions::default();
+            let count = |name: &str, default: usize| {
+                flags
+                    .get(name)
+                    .and_then(|s| s.parse::<usize>().ok())
+                    .filter(|n| *n > 0)
+                    .unwrap_or(default)
+            };
+            let options = LoadTestOptions {
+                claims: count("claims", defaults.claims),
+                payers: count("payers", defaults.payers),
+                payer_workers: count("payer-workers", defaults.payer_workers),
+                clearinghouse_shards: count("clearinghouse-shards", defaults.clearinghouse_shards),
+                channel_capacity: count("channel-capacity", defaults.channel_capacity),
+            };
+            Command::LoadTest(options, report_format(&flags))
+        }
+        Some("report") => {
+            let (args, flags) = split_flags(args.into_iter().skip(1).collect())?;
+            Command::Report {
+                query: args.first().cloned().unwrap_or_default(),
+                db_path: args.get(1).cloned().unwrap_or_else(|| "run.db".to_string()),
+                as_of: flags.get("as-of").and_then(|s| s.parse().ok()),
+                tenant: flags.get("tenant").cloned(),
+                format: report_format(&flags),
+            }
+        }
+        Some("what-if") => {
+            let (args, flags) = split_flags(args.into_iter().skip(1).collect())?;
+            let contract = |prefix: &str| ContractPaths {
+                benefits: flags.get(&format!("{}benefits", prefix)).cloned(),
+                outcomes: flags.get(&format!("{}outcomes", prefix)).cloned(),
+                coverage: flags.get(&format!("{}coverage", prefix)).cloned(),
+            };
+            let baseline = contract("");
+            Command::WhatIf {
+                claims_path: args.first().cloned().unwrap_or_else(|| "fake_claims.jsonl".to_string()),
+                proposed: contract("proposed-").or(&baseline),
+                baseline,
+                seed: flags.get("seed").and_then(|s| s.parse().ok()).unwrap_or_default(),
+                format: report_format(&flags),
+            }
+        }
+        Some("monte-carlo") => {
+            let (args, flags) = split_flags(args.into_iter().skip(1).collect())?;
+            let defaults = MonteCarloOptions::default();
+            let count = |name: &str, default: usize| {
+                flags
+                    .get(name)
+                    .and_then(|s| s.parse::<usize>().ok())
+                    .filter(|n| *n > 0)
+                    .unwrap_or(default)
+            };
+            let rate = |name: &str| {
+                flags
+                    .get(name)
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .filter(|rate| (0.0..=1.0).contains(rate))
+                    .unwrap_or(0.0)
+            };
+            Command::MonteCarlo {
+                claims_path: args.first().cloned().unwrap_or_else(|| "fake_claims.jsonl".to_string()),
+                options: MonteCarloOptions {
+                    runs: count("runs", defaults.runs),
+                    parallel: count("parallel", defaults.parallel),
+                    seed: flags.get("seed").and_then(|s| s.parse().ok()).unwrap_or(defaults.seed),
+                    secs_per_day: flags
+                        .get("secs-per-day")
+                        .and_then(|s| s.parse::<f64>().ok())
+                        .filter(|secs| *secs > 0.0)
+                        .unwrap_or(defaults.secs_per_day),
+                },
+                terms: ContractPaths {
+                    benefits: flags.get("benefits").cloned(),
+                    outcomes: flags.get("outcomes").cloned(),
+                    coverage: flags.get("coverage").cloned(),
+                },
+                denial_rate: rate("denial-rate"),
+                error_rate: rate("remit-error-rate"),
+                format: report_format(&flags),
+            }
+        }
+        Some("audit-export") => {
+            let (args, flags) = split_flags(args.into_iter().skip(1).collect())?;
+            Command::AuditExport {
+                audit_log_path: args.first().cloned().unwrap_or_else(|| "audit.jsonl".to_string()),
+                format: flags.get("format").and_then(|s| s.parse().ok()).unwrap_or_default(),
+                claim_id: flags.get("claim").map(ClaimId::new),
+                output_path: flags.get("output").cloned(),
+            }
+        }
+        Some("scenarios") => {
+            let (args, flags) = split_flags(args.into_iter().skip(1).collect())?;
+            Command::Scenarios {
+                dir: args.first().cloned().unwrap_or_else(|| SCENARIOS_DIR.to_string()),
+                name: flags.get("name").cloned(),
+                update: flags.contains_key("update"),
+            }
+        }
+        Some("post-remittances") => {
+            let (args, flags) = split_flags(args.into_iter().skip(1).collect())?;
+            Command::PostRemittances {
+                dir: args.first().cloned().unwrap_or_else(|| REMITTANCE_FILES_DIR.to_string()),
+                tenant: flags.get("tenant").cloned(),
+                format: report_format(&flags),
+            }
+        }
+        _ => Command::Simulate(Box::new(parse_args(args)?)),
+    };
+    Ok(command)
 }
 
 /// Parse command line arguments to create application configuration
-/// 
-/// Args: [file_path] [ingest_rate] [verbose_flag]
-/// - file_path: JSONL file with claims (default: fake_claims.jsonl)
+///
+/// Args: [file_path] [ingest_rate] [verbose_flag] [--checkpoint <path>] [--rejects <path>] [--burst <n>] [--api <addr>] [--event-log <path>] [--tenants <name=path,...>] [--fake-claims <n>] [--procedure-csv <path>] [--secs-per-day <secs>] [--otlp-endpoint <url>] [--channel-capacity <n>] [--overflow <policy>] [--validation <level>] [--currencies <codes>] [--mismatch <policy>] [--remit-error-rate <rate>] [--outcomes <path>] [--coverage <path>] [--attachments <path>] [--benefits <path>] [--watch-rules] [--roster-churn <rate>] [--roster-members <n>] [--payer-mix <path>] [--fake-seed <n>] [--statements <n>] [--collections <action>] [--payer-workers <n>] [--clearinghouse-shards <n>] [--check-invariants <secs>] [--locale <locale>] [--units <units>] [--report-rows <n>] [--run-db <path>] [--grpc-payers <id=url,...>] [--remittance-sinks <kind=target,...>] [--log-sinks <component=sink,...>] [--claim-logs <dir>] [--claim-log-format <format>] [--audit-log <path>] [--redact-phi] [--pseudonym-key <path>] [--debug] [--stall-secs <secs>] [--max-restarts <n>] [--stuck-after <secs>] [--stuck-action <action>] [--sla <id=percent@days,...>] [--forecast-days <days>] [--staff <name=per_day/capacity,...>] [--edit-rates <level=rate,...>] [--companion-guides] [--telehealth-rates <id=rate,...>] [--max-service-lines <id=lines,...>] [--ack-delays <id=min-max,...>] [--run-dir] [--run-name <name>] [--keep-runs <n>] [--stop-when <condition,...>] [--file-drop <dir>] [--file-drop-poll <secs>] [--remittance-files <dir>]
+/// - file_path: JSONL file with claims, or `-` for stdin (default: fake_claims.jsonl)
 /// - ingest_rate: seconds between claim processing (default: 1)
 /// - verbose: enable detailed logging (default: false)
-pub fn config() -> Config {
-    let args: Vec<String> = env::args().skip(1).collect();
+/// - --checkpoint: file recording reader progress so a restart resumes (default: none)
+/// - --rejects: sidecar JSONL for invalid input lines (default: rejects.jsonl beside the input)
+/// - --burst: claims the reader may send back-to-back before throttling (default: 1)
+/// - --api: address for the HTTP control API, e.g. 127.0.0.1:8080 (default: disabled)
+/// - --event-log: append-only JSONL log of every claim event in the run (default: disabled)
+/// - --tenants: billing organizations as `name=path,...`, each with its own claim stream (default: single tenant)
+/// - --fake-claims: fake claims generated for each tenant without a claim file (default: 10)
+/// - --procedure-csv: write revenue by procedure code to this CSV file at shutdown (default: disabled)
+/// - --secs-per-day: real seconds per simulated day, driving month-end close (default: 2)
+/// - --otlp-endpoint: export claim lifecycle spans to an OTLP collector, e.g. http://localhost:4317 (default: disabled)
+/// - --channel-capacity: messages each internal channel holds before it is full (default: 100)
+/// - --overflow: full clearinghouse and payer inbox policy, one of `block`, `drop-oldest`, `reject`; anything else is an error (default: block)
+/// - --validation: NPI check digit and EIN format checks, one of `off`, `warn`, `strict` (default: warn)
+/// - --currencies: comma-separated currency codes claims may be billed in, e.g. USD,CAD (default: USD)
+/// - --mismatch: unbalanced remittance policy, one of `regenerate`, `flag`, `exception` (default: flag)
+/// - --remit-error-rate: fraction of remittances payers price wrong, from 0 to 1 (default: 0)
+/// - --outcomes: JSON table of pay/deny/downcode weights by procedure code range (default: pay every line)
+/// - --coverage: JSON medical necessity rules; lines without a supporting diagnosis are denied (default: none)
+/// - --attachments: JSON rules of documents payers request before adjudicating procedure code ranges (default: none)
+/// - --benefits: JSON plan designs and member enrollments setting patient cost sharing (default: one standard plan)
+/// - --watch-rules: reload the outcome, coverage, attachment, and benefit files when they change and apply them to running payers as a new rules version, recorded on each adjudication in the event log; takes no value (default: disabled)
+/// - --roster-churn: generate per-payer member rosters losing this fraction of members monthly, from 0 to 1; fake claims are drawn from them (default: no rosters)
+/// - --roster-members: members on each generated roster at the start (default: 500)
+/// - --payer-mix: JSON percent of fake claims billed to each payer and the share of charges each is expected to reimburse, compared with what it paid at the end of the run (default: payers equally likely)
+/// - --fake-seed: seed of the generated rosters and of the payer and roster member each fake claim is drawn for, so a run bills the same mix again (default: fresh each run)
+/// - --statements: statements sent for a patient balance, a statement cycle apart, before the collections action (default: 3)
+/// - --collections: what happens to a patient balance unpaid after the last statement, `transfer` to an agency or `write-off` (default: transfer)
+/// - --payer-workers: adjudicators per payer; further claims wait in the payer's intake queue (default: 16)
+/// - --clearinghouse-shards: clearinghouse workers, claims routed by id (default: 4)
+/// - --check-invariants: fail the run if an invariant breaks; claims pending this many seconds count as lost (default: disabled)
+/// - --locale: number and currency notation of reports, one of `en-US`, `de-DE`, `fr-FR` (default: en-US)
+/// - --units: money units of reports, `dollars` or `thousands` (default: dollars)
+/// - --report-rows: rows console reports list per patient, provider, or claim, the rest summed into one row; 0 lists all (default: 25)
+/// - --run-db: store the run's claims, denials, and payments in this SQLite file at shutdown, for `report` (default: disabled)
+/// - --grpc-payers: payer ids adjudicated by external gRPC `PayerService`s as `id=url,...`; requires the `grpc` feature (default: none)
+/// - --remittance-sinks: downstream systems told about each remittance as `file=<path>`, `webhook=<url>`, or `sqlite=<path>`, comma-separated (default: none)
+/// - --log-sinks: where each component's claim events go as `component=sink,...`, `*` for the rest; sinks are `stdout`, `file:<path>`, `rolling:<path>`, `syslog`, or `off` (default: stdout)
+/// - --claim-logs: also gather each claim's events under this directory, whatever the component sinks (default: disabled)
+/// - --claim-log-format: `files`, one `<claim_id>.log` per claim, or `ndjson`, one `claims.ndjson` keyed by claim id (default: files)
+/// - --audit-log: append-only, hash-chained JSONL record of each component that creates, reads, or changes a claim record, for `audit-export` (default: disabled)
+/// - --redact-phi: mask patient and subscriber names, dates of birth, emails, and addresses in everything the run writes, keeping ids; takes no value (default: disabled)
+/// - --pseudonym-key: file holding a secret key; patients in everything the run writes are replaced by synthetic identities and member ids by pseudonymous ids, the same for a given key across runs (default: disabled)
+/// - --debug: start paused, releasing messages one at a time on `step` commands read from stdin; takes no value (default: disabled)
+/// - --stall-secs: seconds a component may make no progress while work waits for it before it is reported stalled and, if it can be, restarted; 0 never (default: 60)
+/// - --max-restarts: times a payer or the reporter is restarted after panicking or stalling before it is left failed (default: 3)
+/// - --stuck-after: seconds a claim may wait on its payer before the watchdog alerts on it (default: disabled)
+/// - --stuck-action: what the watchdog does about a stuck claim besides alerting, alert, inquire (ask its payer), or resubmit (inquire, and resend claims the payer has no record of) (default: alert)
+/// - --sla: service levels as `payer=percent@days,...`, e.g. `medicare=95@14` for 95% of claims adjudicated within 14 simulated days; the reporter tracks compliance and alerts on breaches (default: none)
+/// - --forecast-days: forecast the payer cash expected on open claims over this many simulated days from past remittances, in the reporter (default: disabled)
+/// - --staff: billing staff as `name=per_day/capacity,...`, e.g. `jsmith=20/10` for 20 items resolved per simulated day with up to 10 held at once; they work denials, underpayments, and remittance exceptions, and the reporter shows backlog and throughput (default: none)
+/// - --edit-rates: fraction of claims the clearinghouse rejects back to the biller with an edit code at each level of its front-end edits, as `level=rate,...` over syntax, payer, and duplicate, e.g. `syntax=0.02,payer=0.05`; each rate applies to claims that passed the levels before (default: none)
+/// - --companion-guides: reject claims at the clearinghouse's payer edits when they break a rule of their payer's companion guide, such as Medicare requiring the rendering provider's taxonomy code; takes no value (default: disabled)
+/// - --telehealth-rates: fraction of the in-person allowed amount each payer pays for telehealth lines, as `payer=rate,...`, e.g. `anthem=0.8`; a line is telehealth at place of service 02 or 10 or with modifier 95 (default: every payer at parity)
+/// - --max-service-lines: most service lines each payer accepts on a claim, as `payer=lines,...`, e.g. `medicare=50`; the biller splits a longer professional claim into child claims `<claim_id>-1`, `<claim_id>-2`, ... that the reporter rolls back up to the parent (default: no limit)
+/// - --ack-delays: seconds each payer takes to acknowledge a claim it takes in, as `payer=min-max,...`, e.g. `anthem=1-4`, apart from the adjudication delay before it pays (default: each simulated payer's own quick range)
+/// - --run-dir: collect the run's generated claims, logs, exports, and a config snapshot in `runs/<timestamp>/` (default: disabled)
+/// - --run-name: name appended to the run directory, `runs/<timestamp>-<name>/`; implies --run-dir (default: none)
+/// - --keep-runs: run directories kept under `runs/`, newest first, older ones removed when a run starts; 0 keeps all (default: 10)
+/// - --stop-when: end the run early once any condition is met, as `condition,...` over `wall=<secs>` of real time, `days=<days>` of simulated time, `adjudicated=<dollars>` of billed charges remitted, and `steady[=<tolerance>]` once claims remitted per second stay within the tolerance of their mean (default 0.1) for 5 seconds; invalid conditions are skipped (default: run until every claim is remitted)
+/// - --file-drop: submit claims as batch files written to `<dir>/<tenant>/outbound` and polled by the clearinghouse, which answers with remittance files in `<dir>/<tenant>/inbound` polled by the biller; picked-up files move to `archive` (default: disabled)
+/// - --file-drop-poll: seconds between scans of a drop directory, and longest a message waits to be written in a partial batch (default: 1)
+/// - --remittance-files: directory the clearinghouse writes each payer's remittances to, a `<payer>/<YYYY-MM-DD>.jsonl` file per simulated day (default: `remittances` in the run directory with `--run-dir`, otherwise disabled)
+pub fn config() -> anyhow::Result<Config> {
+    parse_args(env::args().skip(1).collect())
+}
+
+/// Build a configuration from raw arguments (excluding the program name)
+///
+/// Fails only on a flag missing its value; values that cannot be read fall back to their defaults
+pub fn parse_args(args: Vec<String>) -> anyhow::Result<Config> {
+    let (args, flags) = split_flags(args)?;
+    let defaults = Config::default();
 
-    let file_path = args.get(0).cloned().unwrap_or_else(|| "fake_claims.jsonl".to_string());
+    let file_path = args.first().cloned().unwrap_or(defaults.file_path);
 
     let ingest_rate = args.get(1)
         .and_then(|s| s.parse::<u64>().ok())
-        .unwrap_or(1);
+        .unwrap_or(defaults.ingest_rate);
 
     let verbose = args.iter().any(|arg| arg == "verbose" || arg == "v");
 
-    Config {
+    let checkpoint_path = flags.get("checkpoint").cloned();
+
+    let rejects_path = flags
+        .get("rejects")
+        .cloned()
+        .unwrap_or_else(|| rejects::default_rejects_path(&file_path));
+
+    let burst = flags
+        .get("burst")
+        .and_then(|s| s.parse::<u32>().ok())
+        .unwrap_or(defaults.burst);
+
+    let api_addr = flags.get("api").cloned();
+
+    let event_log_path = flags.get("event-log").cloned();
+
+    let remittance_files_dir = flags.get("remittance-files").cloned();
+
+    let tenants = flags
+        .get("tenants")
+        .map(|spec| tenant::parse_tenants(spec))
+        .unwrap_or_default();
+
+    let fake_claims = flags
+        .get("fake-claims")
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(defaults.fake_claims);
+
+    let procedure_csv_path = flags.get("procedure-csv").cloned();
+
+    let secs_per_day = flags
+        .get("secs-per-day")
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|secs| secs.is_finite() && *secs > 0.0)
+        .unwrap_or(defaults.secs_per_day);
+
+    let otlp_endpoint = flags.get("otlp-endpoint").cloned();
+
+    let channel_capacity = flags
+        .get("channel-capacity")
+        .and_then(|s| s.parse::<usize>().ok())
+        .filter(|capacity| *capacity > 0)
+        .unwrap_or(defaults.channel_capacity);
+
+    // a policy mistyped would otherwise quietly block, hiding the overflow being studied
+    let overflow_policy = match flags.get("overflow") {
+        Some(s) => s.parse::<OverflowPolicy>()?,
+        None => defaults.overflow_policy,
+    };
+
+    let validation = flags
+        .get("validation")
+        .and_then(|s| s.parse::<Strictness>().ok())
+        .unwrap_or(defaults.validation);
+
+    let currencies = flags
+        .get("currencies")
+        .and_then(|s| s.parse::<AllowedCurrencies>().ok())
+        .unwrap_or(defaults.currencies);
+
+    let mismatch_policy = flags
+        .get("mismatch")
+        .and_then(|s| s.parse::<MismatchPolicy>().ok())
+        .unwrap_or(defaults.mismatch_policy);
+
+    let remit_error_rate = flags
+        .get("remit-error-rate")
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|rate| (0.0..=1.0).contains(rate))
+        .unwrap_or(defaults.remit_error_rate);
+
+    let outcomes_path = flags.get("outcomes").cloned();
+
+    let coverage_path = flags.get("coverage").cloned();
+
+    let attachments_path = flags.get("attachments").cloned();
+
+    let benefits_path = flags.get("benefits").cloned();
+
+    let watch_rules = flags.contains_key("watch-rules");
+
+    let roster_churn = flags
+        .get("roster-churn")
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|rate| (0.0..=1.0).contains(rate));
+
+    let roster_members = flags
+        .get("roster-members")
+        .and_then(|s| s.parse::<usize>().ok())
+        .filter(|members| *members > 0)
+        .unwrap_or(defaults.roster_members);
+
+    let payer_mix_path = flags.get("payer-mix").cloned();
+    let fake_seed = flags.get("fake-seed").and_then(|s| s.parse::<u64>().ok());
+
+    let statements = flags
+        .get("statements")
+        .and_then(|s| s.parse::<u32>().ok())
+        .filter(|statements| *statements > 0)
+        .unwrap_or(defaults.statements);
+
+    let collections_action = flags
+        .get("collections")
+        .and_then(|s| s.parse::<CollectionsAction>().ok())
+        .unwrap_or(defaults.collections_action);
+
+    let payer_workers = flags
+        .get("payer-workers")
+        .and_then(|s| s.parse::<usize>().ok())
+        .filter(|workers| *workers > 0)
+        .unwrap_or(defaults.payer_workers);
+
+    let clearinghouse_shards = flags
+        .get("clearinghouse-shards")
+        .and_then(|s| s.parse::<usize>().ok())
+        .filter(|shards| *shards > 0)
+        .unwrap_or(defaults.clearinghouse_shards);
+
+    let invariant_max_pending_secs = flags
+        .get("check-invariants")
+        .and_then(|s| s.parse::<u64>().ok());
+
+    let run_db_path = flags.get("run-db").cloned();
+
+    let grpc_payers = flags
+        .get("grpc-payers")
+        .map(|spec| parse_grpc_payers(spec))
+        .unwrap_or_default();
+
+    let remittance_sinks = flags
+        .get("remittance-sinks")
+        .map(|spec| parse_remittance_sinks(spec))
+        .unwrap_or_default();
+
+    let log_sinks: LogSinks = flags
+        .get("log-sinks")
+        .and_then(|spec| match spec.parse() {
+            Ok(sinks) => Some(sinks),
+            Err(e) => {
+                eprintln!("Ignoring --log-sinks: {}", e);
+                None
+            }
+        })
+        .unwrap_or_default();
+    let log_sinks = match flags.get("claim-logs") {
+        Some(dir) => log_sinks.with_claim_log(ClaimLogSpec {
+            dir: dir.clone(),
+            format: flags
+                .get("claim-log-format")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_default(),
+        }),
+        None => log_sinks,
+    };
+
+    let audit_log_path = flags.get("audit-log").cloned();
+
+    let redact_phi = flags.contains_key("redact-phi");
+
+    let pseudonym_key_path = flags.get("pseudonym-key").cloned();
+
+    let debug = flags.contains_key("debug");
+
+    let stall_secs = flags
+        .get("stall-secs")
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(defaults.stall_secs);
+
+    let max_restarts = flags
+        .get("max-restarts")
+        .and_then(|s| s.parse::<u32>().ok())
+        .unwrap_or(defaults.max_restarts);
+
+    let stuck_after_secs = flags
+        .get("stuck-after")
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|secs| *secs > 0);
+
+    let stuck_action = flags
+        .get("stuck-action")
+        .and_then(|s| s.parse::<StuckAction>().ok())
+        .unwrap_or(defaults.stuck_action);
+
+    let slas = flags
+        .get("sla")
+        .map(|spec| parse_slas(spec))
+        .unwrap_or_default();
+
+    let forecast_days = flags
+        .get("forecast-days")
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|days| *days > 0.0 && days.is_finite());
+
+    let staff = flags
+        .get("staff")
+        .map(|spec| parse_staff(spec))
+        .unwrap_or_default();
+
+    let mut edit_rates = flags
+        .get("edit-rates")
+        .and_then(|s| s.parse::<EditRates>().ok())
+        .unwrap_or(defaults.edit_rates);
+    edit_rates.companion_guides = flags.contains_key("companion-guides");
+
+    let telehealth_rates = flags
+        .get("telehealth-rates")
+        .map(|spec| parse_telehealth_rates(spec))
+        .unwrap_or_default();
+
+    let max_service_lines = flags
+        .get("max-service-lines")
+        .map(|spec| parse_max_service_lines(spec))
+        .unwrap_or_default();
+
+    let ack_delays = flags
+        .get("ack-delays")
+        .map(|spec| parse_ack_delays(spec))
+        .unwrap_or_default();
+
+    // naming a run implies collecting it
+    let run_name = flags.get("run-name").cloned();
+    let run_dir = flags.contains_key("run-dir") || run_name.is_some();
+    let keep_runs = flags
+        .get("keep-runs")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_KEEP_RUNS);
+
+    let stop_conditions = flags
+        .get("stop-when")
+        .map(|spec| spec.split(',').filter_map(|condition| condition.parse().ok()).collect())
+        .unwrap_or_default();
+
+    let file_drop = flags.get("file-drop").map(|dir| {
+        let settings = FileDropSettings::new(dir.as_str());
+        match flags.get("file-drop-poll").and_then(|s| s.parse::<f64>().ok()).filter(|secs| secs.is_finite() && *secs > 0.0) {
+            Some(secs) => settings.with_poll_interval(Duration::from_secs_f64(secs)),
+            None => settings,
+        }
+    });
+
+    Ok(Config {
         file_path,
         ingest_rate,
         verbose,
+        checkpoint_path,
+        rejects_path: Some(rejects_path),
+        burst,
+        api_addr,
+        event_log_path,
+        tenants,
+        fake_claims,
+        procedure_csv_path,
+        secs_per_day,
+        otlp_endpoint,
+        channel_capacity,
+        overflow_policy,
+        validation,
+        currencies,
+        mismatch_policy,
+        remit_error_rate,
+        outcomes_path,
+        coverage_path,
+        attachments_path,
+        benefits_path,
+        watch_rules,
+        roster_churn,
+        roster_members,
+        payer_mix_path,
+        fake_seed,
+        statements,
+        collections_action,
+        payer_workers,
+        clearinghouse_shards,
+        invariant_max_pending_secs,
+        report_format: report_format(&flags),
+        run_db_path,
+        grpc_payers,
+        remittance_sinks,
+        log_sinks,
+        audit_log_path,
+        redact_phi,
+        pseudonym_key_path,
+        debug,
+        stall_secs,
+        max_restarts,
+        stuck_after_secs,
+        stuck_action,
+        slas,
+        forecast_days,
+        staff,
+        edit_rates,
+        telehealth_rates,
+        max_service_lines,
+        ack_delays,
+        generated_claims_path: Config::default().generated_claims_path,
+        run_dir,
+        run_name,
+        keep_runs,
+        stop_conditions,
+        file_drop,
+        remittance_files_dir,
+    })
+}
+
+/// Parse `id=percent@days,...` into payer ids and their SLAs, skipping invalid entries
+fn parse_slas(spec: &str) -> BTreeMap<PayerId, PayerSla> {
+    spec.split(',')
+        .filter_map(|entry| entry.trim().split_once('='))
+        .filter_map(|(payer_id, sla)| Some((PayerId::new(payer_id), sla.parse().ok()?)))
+        .collect()
+}
+
+/// Parse `id=rate,...` into payer ids and their telehealth rates, skipping rates outside 0 to 1
+fn parse_telehealth_rates(spec: &str) -> BTreeMap<PayerId, f64> {
+    spec.split(',')
+        .filter_map(|entry| entry.trim().split_once('='))
+        .filter_map(|(payer_id, rate)| Some((PayerId::new(payer_id), rate.trim().parse::<f64>().ok()?)))
+        .filter(|(_, rate)| (0.0..=1.0).contains(rate))
+        .collect()
+}
+
+/// Parse `id=lines,...` into payer ids and their service line limits, skipping limits that are not positive
+fn parse_max_service_lines(spec: &str) -> BTreeMap<PayerId, usize> {
+    spec.split(',')
+        .filter_map(|entry| entry.trim().split_once('='))
+        .filter_map(|(payer_id, lines)| Some((PayerId::new(payer_id), lines.trim().parse::<usize>().ok()?)))
+        .filter(|(_, lines)| *lines > 0)
+        .collect()
+}
+
+/// Parse `id=min-max,...` into payer ids and their acknowledgment delay ranges in seconds,
+/// skipping ranges whose minimum exceeds their maximum
+fn parse_ack_delays(spec: &str) -> BTreeMap<PayerId, (u64, u64)> {
+    spec.split(',')
+        .filter_map(|entry| entry.trim().split_once('='))
+        .filter_map(|(payer_id, range)| {
+            let (min, max) = range.trim().split_once('-')?;
+            Some((PayerId::new(payer_id), (min.trim().parse::<u64>().ok()?, max.trim().parse::<u64>().ok()?)))
+        })
+        .filter(|(_, (min, max))| min <= max)
+        .collect()
+}
+
+/// Parse `name=per_day/capacity,...` into billing staff, skipping invalid entries
+fn parse_staff(spec: &str) -> Vec<StaffMember> {
+    spec.split(',')
+        .filter_map(|entry| match entry.trim().parse() {
+            Ok(member) => Some(member),
+            Err(e) => {
+                eprintln!("Ignoring staff member: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Parse `id=url,...` into payer ids and their endpoints, skipping entries without a url
+fn parse_grpc_payers(spec: &str) -> BTreeMap<PayerId, String> {
+    spec.split(',')
+        .filter_map(|entry| entry.trim().split_once('='))
+        .map(|(payer_id, endpoint)| (PayerId::new(payer_id), endpoint.to_string()))
+        .collect()
+}
+
+/// Parse `kind=target,...` into remittance sinks, skipping entries that name no known kind
+fn parse_remittance_sinks(spec: &str) -> Vec<SinkSpec> {
+    spec.split(',')
+        .filter_map(|entry| match entry.trim().parse() {
+            Ok(sink) => Some(sink),
+            Err(e) => {
+                eprintln!("Ignoring remittance sink: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Report format from `--locale`, `--units`, and `--report-rows`, each falling back to its default when absent or unknown
+///
+/// `--report-rows 0` lists every row
+fn report_format(flags: &HashMap<String, String>) -> ReportFormat {
+    let defaults = ReportFormat::default();
+    ReportFormat {
+        max_rows: match flags.get("report-rows").and_then(|s| s.parse::<usize>().ok()) {
+            Some(0) => None,
+            Some(rows) => Some(rows),
+            None => defaults.max_rows,
+        },
+        locale: flags
+            .get("locale")
+            .and_then(|s| s.parse::<Locale>().ok())
+            .unwrap_or(defaults.locale),
+        units: flags
+            .get("units")
+            .and_then(|s| s.parse::<Units>().ok())
+            .unwrap_or(defaults.units),
+    }
+}
+
+/// Flags that take no value
+const SWITCHES: [&str; 6] = ["redact-phi", "debug", "run-dir", "watch-rules", "companion-guides", "update"];
+
+/// Separate `--name value` / `--name=value` flags from positional arguments
+///
+/// A flag other than a switch must have a value; the next argument does not count as one when it is
+/// missing or is itself a flag, so `--checkpoint --debug` is an error rather than a checkpoint named `--debug`
+fn split_flags(args: Vec<String>) -> anyhow::Result<(Vec<String>, HashMap<String, String>)> {
+    let mut positional = Vec::new();
+    let mut flags = HashMap::new();
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        match arg.strip_prefix("--") {
+            Some(flag) => match flag.split_once('=') {
+                Some((name, value)) => {
+                    flags.insert(name.to_string(), value.to_string());
+                }
+                None if SWITCHES.contains(&flag) => {
+                    flags.insert(flag.to_string(), String::new());
+                }
+                None => match iter.next() {
+                    Some(value) if !value.starts_with("--") => {
+                        flags.insert(flag.to_string(), value);
+                    }
+                    _ => return Err(anyhow::anyhow!("--{} needs a value", flag)),
+                },
+            },
+            None => positional.push(arg),
+        }
+    }
+    Ok((positional, flags))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::{ClaimLogFormat, LogSink};
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// Test that positional arguments keep working alongside flags in any order.
+    /// Expected: file path, ingest rate, verbose, and checkpoint are all parsed.
+    #[test]
+    fn test_parse_args_with_flags() {
+        let config = parse_args(args(&["--checkpoint", "ckpt.json", "claims.jsonl", "3", "v"])).unwrap();
+        assert_eq!(config.file_path, "claims.jsonl");
+        assert_eq!(config.ingest_rate, 3);
+        assert!(config.verbose);
+        assert_eq!(config.checkpoint_path.as_deref(), Some("ckpt.json"));
+        let config = parse_args(args(&["--grpc-payers", "acme=http://localhost:50051, bogus"])).unwrap();
+        assert_eq!(config.grpc_payers.len(), 1);
+        assert_eq!(config.grpc_payers["acme"], "http://localhost:50051");
+        let config = parse_args(args(&["--remittance-sinks", "file=remits.jsonl,webhook=http://localhost:9000/remits"])).unwrap();
+        assert_eq!(
+            config.remittance_sinks,
+            [SinkSpec::File("remits.jsonl".to_string()), SinkSpec::Webhook("http://localhost:9000/remits".to_string())]
+        );
+        let config = parse_args(args(&["--log-sinks", "*=rolling:sim.log,payer=syslog"])).unwrap();
+        assert_eq!(config.log_sinks.sink("biller"), &LogSink::Rolling("sim.log".to_string()));
+        assert_eq!(config.log_sinks.sink("payer"), &LogSink::Syslog);
+        let config = parse_args(args(&["--claim-logs", "run/claims", "--claim-log-format", "ndjson"])).unwrap();
+        assert_eq!(config.log_sinks.claim_log.unwrap().format, ClaimLogFormat::Ndjson);
+        let config = parse_args(args(&["--redact-phi", "--debug", "claims.jsonl", "--stall-secs", "5", "--max-restarts=1"])).unwrap();
+        assert!(config.redact_phi && config.debug);
+        assert_eq!((config.stall_secs, config.max_restarts), (5, 1));
+        assert_eq!(config.file_path, "claims.jsonl");
+        let config = parse_args(args(&["--stuck-after", "30", "--stuck-action", "resubmit"])).unwrap();
+        assert_eq!((config.stuck_after_secs, config.stuck_action), (Some(30), StuckAction::Resubmit));
+        let config = parse_args(args(&["--sla", "medicare=95@14,anthem=90%@2.5,bogus=150@1"])).unwrap();
+        assert_eq!(config.slas.len(), 2);
+        assert_eq!(config.slas["anthem"], PayerSla { target: 0.9, within_days: 2.5 });
+        assert_eq!(parse_args(args(&["--forecast-days", "30"])).unwrap().forecast_days, Some(30.0));
+        let config = parse_args(args(&["--staff", "jsmith=20/10,bogus=0/1,akim=7.5/3"])).unwrap();
+        assert_eq!(config.staff.iter().map(|member| (member.name.as_str(), member.per_day, member.capacity)).collect::<Vec<_>>(), [("jsmith", 20.0, 10), ("akim", 7.5, 3)]);
+        let config = parse_args(args(&["--edit-rates", "syntax=0.02, duplicate=0.01"])).unwrap();
+        assert_eq!(config.edit_rates, EditRates { syntax: 0.02, duplicate: 0.01, ..EditRates::default() });
+        assert!(parse_args(args(&["--companion-guides"])).unwrap().edit_rates.companion_guides);
+        let config = parse_args(args(&["--telehealth-rates", "anthem=0.8,medicare=1,bogus=1.5"])).unwrap();
+        assert_eq!(config.telehealth_rates, BTreeMap::from([(PayerId::new("anthem"), 0.8), (PayerId::new("medicare"), 1.0)]));
+        let config = parse_args(args(&["--max-service-lines", "medicare=50,anthem=0,bogus=x"])).unwrap();
+        assert_eq!(config.max_service_lines, BTreeMap::from([(PayerId::new("medicare"), 50)]));
+        let config = parse_args(args(&["--ack-delays", "anthem=1-4,medicare=0-0,bogus=5-2"])).unwrap();
+        assert_eq!(config.ack_delays, BTreeMap::from([(PayerId::new("anthem"), (1, 4)), (PayerId::new("medicare"), (0, 0))]));
+        assert_eq!(parse_args(args(&["--edit-rates", "payer=2"])).unwrap().edit_rates, EditRates::default());
+        let config = parse_args(args(&["--run-name", "nightly", "--keep-runs", "3"])).unwrap();
+        assert!(config.run_dir);
+        assert_eq!((config.run_name.as_deref(), config.keep_runs), (Some("nightly"), 3));
+        let config = parse_args(args(&["--watch-rules", "--benefits", "plans.json"])).unwrap();
+        assert!(config.watch_rules);
+        assert_eq!(config.rule_paths().paths().collect::<Vec<_>>(), ["plans.json"]);
+        let config = parse_args(args(&["--stop-when", "wall=600,steady,claims=10"])).unwrap();
+        assert_eq!(config.stop_conditions, [StopCondition::WallClock(std::time::Duration::from_secs(600)), StopCondition::SteadyState(0.1)]);
+        let config = parse_args(args(&["--file-drop", "sftp", "--file-drop-poll", "0.5"])).unwrap();
+        assert_eq!(config.file_drop, Some(FileDropSettings::new("sftp").with_poll_interval(Duration::from_millis(500))));
+        let config = parse_args(args(&["--remittance-files", "835s"])).unwrap();
+        assert_eq!(config.remittance_files_dir.as_deref(), Some("835s"));
+    }
+
+    /// Test that the channel capacity and overflow policy are parsed, and an unknown policy is refused.
+    /// Expected: `drop-oldest` and a capacity of 8 are read; `--overflow sideways` is an error rather than falling back to block.
+    #[test]
+    fn test_parse_overflow() {
+        let config = parse_args(args(&["--overflow", "drop-oldest", "--channel-capacity=8"])).unwrap();
+        assert_eq!(config.overflow_policy, OverflowPolicy::DropOldest);
+        assert_eq!(config.channel_capacity, 8);
+        assert_eq!(parse_args(args(&["--overflow", "reject"])).unwrap().overflow_policy, OverflowPolicy::Reject);
+        let err = parse_args(args(&["--overflow", "sideways"])).unwrap_err();
+        assert!(err.to_string().contains("sideways"), "{}", err);
+    }
+
+    /// Test that accepted currencies are read as codes.
+    /// Expected: Lowercase codes are accepted and stored upper case.
+    #[test]
+    fn test_parse_currencies() {
+        let config = parse_args(args(&["--currencies", "usd,cad"])).unwrap();
+        assert!(config.currencies.contains("CAD"));
+    }
+
+    /// Test that the remittance mismatch policy and error rate are parsed.
+    /// Expected: `exception` and a rate of 0.1 are read.
+    #[test]
+    fn test_parse_mismatch() {
+        let config = parse_args(args(&["--mismatch", "exception", "--remit-error-rate", "0.1"])).unwrap();
+        assert_eq!(config.mismatch_policy, MismatchPolicy::Exception);
+        assert_eq!(config.remit_error_rate, 0.1);
+    }
+
+    /// Test that the fake claim seed is read as a number.
+    /// Expected: `--fake-seed 7` seeds the generator; an unparsable seed leaves it unseeded.
+    #[test]
+    fn test_parse_fake_seed() {
+        let config = parse_args(args(&["claims.jsonl", "1", "--fake-seed", "7"])).unwrap();
+        assert_eq!(config.fake_seed, Some(7));
+        let config = parse_args(args(&["claims.jsonl", "1", "--fake-seed", "seven"])).unwrap();
+        assert_eq!(config.fake_seed, None);
+    }
+
+    /// Test that missing arguments fall back to defaults.
+    /// Expected: Default file path and ingest rate, no checkpoint.
+    #[test]
+    fn test_parse_args_defaults() {
+        let config = parse_args(vec![]).unwrap();
+        assert_eq!(config.file_path, "fake_claims.jsonl");
+        assert_eq!(config.ingest_rate, 1);
+        assert!(!config.verbose);
+        assert!(config.checkpoint_path.is_none());
+    }
+
+    /// Test that `generate` is recognized as a subcommand and `-` as a stdin file path.
+    /// Expected: Generate carries the count; stdin path is passed through to the config.
+    #[test]
+    fn test_parse_command() {
+        assert!(matches!(parse_command(args(&["generate", "25"])).unwrap(), Command::Generate { count: 25 }));
+        match parse_command(args(&["-", "2"])).unwrap() {
+            Command::Simulate(config) => assert_eq!(config.file_path, "-"),
+            _ => panic!("Expected simulate command"),
+        }
+    }
+
+    /// Test that a flag needing a value does not take the next flag as its value.
+    /// Expected: A value flag followed by another flag or by nothing is an error naming it; switches need no value.
+    #[test]
+    fn test_flag_without_value() {
+        let message = parse_args(args(&["--checkpoint", "--debug"])).unwrap_err().to_string();
+        assert_eq!(message, "--checkpoint needs a value");
+        assert!(parse_args(args(&["claims.jsonl", "--rejects"])).is_err());
+        assert!(parse_command(args(&["replay", "run.jsonl", "--speed"])).is_err());
+        assert!(parse_args(args(&["--debug", "--run-dir"])).unwrap().debug);
+    }
+
+    /// Test that validation reports every problem with a configuration at once.
+    /// Expected: Defaults pass; zero channels, a payer with min above max response time, an SLA for an unknown payer,
+    /// a missing outcomes file, and debugging from stdin are each listed in one error.
+    #[test]
+    fn test_validate() {
+        let payers = [PayerSpec::new("medicare", 10, 30), PayerSpec::new("anthem", 60, 100)];
+        Config::default().validate(&payers).unwrap();
+        assert!(Config { watch_rules: true, ..Config::default() }.validate(&payers).is_err());
+
+        let config = parse_args(args(&["-", "--sla", "medicare=95@14,aetna=90@7", "--outcomes", "no/such/outcomes.json", "--debug"])).unwrap();
+        let config = Config { channel_capacity: 0, ..config };
+        let bad_payer = [PayerSpec::new("medicare", 30, 10), PayerSpec::new("anthem", 60, 100)];
+        let message = config.validate(&bad_payer).unwrap_err().to_string();
+        assert!(message.starts_with("Invalid configuration:"));
+        assert_eq!(message.lines().count(), 6, "{}", message);
+        assert!(message.contains("--channel-capacity must be at least 1"));
+        assert!(message.contains("payer medicare: min_response_time_secs must not exceed max_response_time_secs"));
+        assert!(message.contains("--sla names unknown payer aetna (known payers: medicare, anthem)"));
+        assert!(message.contains("--outcomes file no/such/outcomes.json does not exist"));
+        assert!(message.contains("--debug reads commands from stdin"));
+    }
+
+    /// Test that tenant ids and the fake claim count are checked before a run.
+    /// Expected: `--fake-claims` is parsed; a tenant id with a path separator and a zero fake claim count are both reported.
+    #[test]
+    fn test_validate_tenants() {
+        let payers = [PayerSpec::new("medicare", 10, 30)];
+        let config = parse_args(args(&["--tenants", "acme=a.jsonl,beta=b.jsonl", "--fake-claims", "25"])).unwrap();
+        assert_eq!(config.fake_claims, 25);
+        config.validate(&payers).unwrap();
+
+        let config = parse_args(args(&["--tenants", "acme/east=a.jsonl", "--fake-claims", "0"])).unwrap();
+        let message = config.validate(&payers).unwrap_err().to_string();
+        assert!(message.contains("--fake-claims must be at least 1"), "{}", message);
+        assert!(message.contains("--tenants: tenant id acme/east may only use"), "{}", message);
+    }
+
+    /// Test that `replay` takes the event log path and an optional speed flag.
+    /// Expected: Path and speed are parsed; speed is absent when not given.
+    #[test]
+    fn test_parse_replay_command() {
+        match parse_command(args(&["replay", "run.jsonl", "--speed", "10"])).unwrap() {
+            Command::Replay { event_log_path, speed, .. } => {
+                assert_eq!(event_log_path, "run.jsonl");
+                assert_eq!(speed, Some(10.0));
+            }
+            _ => panic!("Expected replay command"),
+        }
+        assert!(matches!(parse_command(args(&["replay"])).unwrap(), Command::Replay { speed: None, .. }));
+    }
+
+    /// Test that `loadtest` reads its counts from flags and ignores invalid ones.
+    /// Expected: Claims and payers are parsed; a zero shard count falls back to the default.
+    #[test]
+    fn test_parse_loadtest_command() {
+        match parse_command(args(&["loadtest", "--claims", "500", "--payers=5", "--clearinghouse-shards", "0"])).unwrap() {
+            Command::LoadTest(options, _) => {
+                assert_eq!(options.claims, 500);
+                assert_eq!(options.payers, 5);
+                assert_eq!(options.clearinghouse_shards, DEFAULT_CLEARINGHOUSE_SHARDS);
+            }
+            _ => panic!("Expected loadtest command"),
+        }
+    }
+
+    /// Test that `report` takes a query, an optional database path, and an as-of date.
+    /// Expected: The query and date are parsed; the database defaults to run.db.
+    #[test]
+    fn test_parse_report_command() {
+        match parse_command(args(&["report", "ar-aging", "--as-of", "2025-03-31"])).unwrap() {
+            Command::Report { query, db_path, as_of, tenant, .. } => {
+                assert_eq!(query, "ar-aging");
+                assert_eq!(db_path, "run.db");
+                assert_eq!(as_of, NaiveDate::from_ymd_opt(2025, 3, 31));
+                assert!(tenant.is_none());
+            }
+            _ => panic!("Expected report command"),
+        }
+    }
+
+    /// Test that `what-if` reads baseline terms and proposed overrides.
+    /// Expected: Proposed terms keep the baseline's files except those overridden.
+    #[test]
+    fn test_parse_what_if_command() {
+        match parse_command(args(&["what-if", "events.jsonl", "--benefits", "plans.json", "--outcomes", "rules.json", "--proposed-benefits", "new-plans.json"])).unwrap() {
+            Command::WhatIf { claims_path, baseline, proposed, seed, .. } => {
+                assert_eq!(claims_path, "events.jsonl");
+                assert_eq!(baseline.benefits.as_deref(), Some("plans.json"));
+                assert_eq!(proposed.benefits.as_deref(), Some("new-plans.json"));
+                assert_eq!(proposed.outcomes.as_deref(), Some("rules.json"));
+                assert_eq!(seed, 0);
+            }
+            _ => panic!("Expected what-if command"),
+        }
+    }
+
+    /// Test that `monte-carlo` reads its run count, seed, and payer behavior.
+    /// Expected: Given values are parsed; unset ones keep their defaults and an out-of-range rate is ignored.
+    #[test]
+    fn test_parse_monte_carlo_command() {
+        match parse_command(args(&["monte-carlo", "claims.jsonl", "--runs", "50", "--seed", "9", "--denial-rate", "0.1", "--remit-error-rate", "2"])).unwrap() {
+            Command::MonteCarlo { claims_path, options, denial_rate, error_rate, terms, .. } => {
+                assert_eq!(claims_path, "claims.jsonl");
+                assert_eq!((options.runs, options.seed), (50, 9));
+                assert_eq!(options.parallel, MonteCarloOptions::default().parallel);
+                assert_eq!((denial_rate, error_rate), (0.1, 0.0));
+                assert_eq!(terms, ContractPaths::default());
+            }
+            _ => panic!("Expected monte-carlo command"),
+        }
+    }
+
+    /// Test that `audit-export` takes a log path, format, claim filter, and output file.
+    /// Expected: The given values are parsed; with no arguments the log is audit.jsonl exported as CSV to stdout.
+    #[test]
+    fn test_parse_audit_export_command() {
+        match parse_command(args(&["audit-export", "run-audit.jsonl", "--format", "jsonl", "--claim", "abc123"])).unwrap() {
+            Command::AuditExport { audit_log_path, format, claim_id, output_path } => {
+                assert_eq!(audit_log_path, "run-audit.jsonl");
+                assert_eq!(format, AuditExportFormat::Jsonl);
+                assert_eq!(claim_id, Some(ClaimId::new("abc123")));
+                assert!(output_path.is_none());
+            }
+            _ => panic!("Expected audit-export command"),
+        }
+        assert!(matches!(
+            parse_command(args(&["audit-export"])).unwrap(),
+            Command::AuditExport { format: AuditExportFormat::Csv, .. }
+        ));
+    }
+
+    /// Test that `scenarios` takes a directory, a scenario name, and the update switch.
+    /// Expected: `--update` takes no value, so the directory after it stays positional; the directory defaults to scenarios.
+    #[test]
+    fn test_parse_scenarios_command() {
+        match parse_command(args(&["scenarios", "--update", "my-scenarios", "--name", "denials"])).unwrap() {
+            Command::Scenarios { dir, name, update } => {
+                assert_eq!(dir, "my-scenarios");
+                assert_eq!(name.as_deref(), Some("denials"));
+                assert!(update);
+            }
+            _ => panic!("Expected scenarios command"),
+        }
+        assert!(matches!(
+            parse_command(args(&["scenarios"])).unwrap(),
+            Command::Scenarios { ref dir, name: None, update: false } if dir == SCENARIOS_DIR
+        ));
+    }
+
+    /// Test that `post-remittances` takes a remittance files directory and a tenant.
+    /// Expected: Both are read, and the directory defaults to the one a run directory writes.
+    #[test]
+    fn test_parse_post_remittances_command() {
+        match parse_command(args(&["post-remittances", "runs/nightly/remittances", "--tenant", "acme"])).unwrap() {
+            Command::PostRemittances { dir, tenant, .. } => {
+                assert_eq!(dir, "runs/nightly/remittances");
+                assert_eq!(tenant.as_deref(), Some("acme"));
+            }
+            _ => panic!("Expected post-remittances command"),
+        }
+        assert!(matches!(
+            parse_command(args(&["post-remittances"])).unwrap(),
+            Command::PostRemittances { ref dir, tenant: None, .. } if dir == REMITTANCE_FILES_DIR
+        ));
     }
 }
@@ -0,0 +1,295 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+use crate::ids::PayerId;
+use crate::remittance::MismatchPolicy;
+
+/// Lifecycle state a component has been asked to be in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunState {
+    Running,
+    /// Not taking new input; queued messages wait in the channel
+    Paused,
+    /// Stopped taking input for good; work already in flight still completes
+    Stopped,
+}
+
+impl fmt::Display for RunState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunState::Running => write!(f, "running"),
+            RunState::Paused => write!(f, "paused"),
+            RunState::Stopped => write!(f, "stopped"),
+        }
+    }
+}
+
+/// Telehealth rate of a payer paying telehealth the same as in-person care
+pub const TELEHEALTH_PARITY: f64 = 1.0;
+
+fn default_telehealth_rate() -> f64 {
+    TELEHEALTH_PARITY
+}
+
+/// Adjudication behavior of a payer that can be changed while it runs
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PayerSettings {
+    pub min_response_time_secs: u64,
+    pub max_response_time_secs: u64,
+    /// Range of seconds from taking a claim in to acknowledging it, however long it then waits to
+    /// be adjudicated; a payer without one never acknowledges claims
+    #[serde(default)]
+    pub ack_time_secs: Option<(u64, u64)>,
+    /// Fraction of claims denied outright (0.0 to 1.0)
+    #[serde(default)]
+    pub denial_rate: f64,
+    /// Fraction of remittances priced wrong so they do not balance (0.0 to 1.0)
+    #[serde(default)]
+    pub error_rate: f64,
+    /// What happens to a remittance that does not balance against its claim
+    #[serde(default)]
+    pub mismatch_policy: MismatchPolicy,
+    /// Fraction of the in-person allowed amount paid for a telehealth line (0.0 to 1.0, 1.0 at parity)
+    #[serde(default = "default_telehealth_rate")]
+    pub telehealth_rate: f64,
+}
+
+impl PayerSettings {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.min_response_time_secs > self.max_response_time_secs {
+            return Err(anyhow::anyhow!("min_response_time_secs must not exceed max_response_time_secs"));
+        }
+        if self.ack_time_secs.is_some_and(|(min, max)| min > max) {
+            return Err(anyhow::anyhow!("ack_time_secs minimum must not exceed its maximum"));
+        }
+        if !(0.0..=1.0).contains(&self.denial_rate) {
+            return Err(anyhow::anyhow!("denial_rate must be between 0 and 1"));
+        }
+        if !(0.0..=1.0).contains(&self.error_rate) {
+            return Err(anyhow::anyhow!("error_rate must be between 0 and 1"));
+        }
+        if !(0.0..=1.0).contains(&self.telehealth_rate) {
+            return Err(anyhow::anyhow!("telehealth_rate must be between 0 and 1"));
+        }
+        Ok(())
+    }
+}
+
+/// Runtime control over one component: pause, resume, stop, and (for payers) reconfigure
+///
+/// Drives the component from the HTTP API or a test while its task is running
+#[derive(Debug)]
+pub struct ComponentHandle<S> {
+    state: Arc<watch::Sender<RunState>>,
+    settings: Arc<watch::Sender<S>>,
+}
+
+impl<S> Clone for ComponentHandle<S> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            settings: self.settings.clone(),
+        }
+    }
+}
+
+pub type ClearinghouseHandle = ComponentHandle<()>;
+pub type BillerHandle = ComponentHandle<()>;
+pub type PayerHandle = ComponentHandle<PayerSettings>;
+
+impl<S: Clone> ComponentHandle<S> {
+    pub fn new(settings: S) -> Self {
+        Self {
+            state: Arc::new(watch::Sender::new(RunState::Running)),
+            settings: Arc::new(watch::Sender::new(settings)),
+        }
+    }
+
+    pub fn state(&self) -> RunState {
+        *self.state.borrow()
+    }
+
+    pub fn settings(&self) -> S {
+        self.settings.borrow().clone()
+    }
+
+    /// Stop taking new input until resumed; has no effect once stopped
+    pub fn pause(&self) {
+        self.transition(RunState::Paused);
+    }
+
+    /// Continue taking input after a pause; has no effect once stopped
+    pub fn resume(&self) {
+        self.transition(RunState::Running);
+    }
+
+    /// Stop taking input for good, letting in-flight work finish
+    pub fn stop(&self) {
+        self.state.send_replace(RunState::Stopped);
+    }
+
+    fn transition(&self, next: RunState) {
+        self.state.send_if_modified(|state| {
+            if *state == RunState::Stopped || *state == next {
+                return false;
+            }
+            *state = next;
+            true
+        });
+    }
+
+    /// Receiver side held by the component task
+    pub(crate) fn subscribe(&self) -> ControlReceiver<S> {
+        ControlReceiver {
+            state: self.state.subscribe(),
+            settings: self.settings.subscribe(),
+        }
+    }
+}
+
+impl PayerHandle {
+    /// Change response times and denial rate; applies to claims adjudicated from now on
+    pub fn reconfigure(&self, settings: PayerSettings) -> anyhow::Result<()> {
+        settings.validate()?;
+        self.settings.send_replace(settings);
+        Ok(())
+    }
+}
+
+/// Component side of a `ComponentHandle`
+#[derive(Debug)]
+pub(crate) struct ControlReceiver<S> {
+    state: watch::Receiver<RunState>,
+    settings: watch::Receiver<S>,
+}
+
+impl<S: Clone> ControlReceiver<S> {
+    /// Wait while paused; true when the component should keep running, false once stopped
+    pub(crate) async fn proceed(&mut self) -> bool {
+        loop {
+            match *self.state.borrow_and_update() {
+                RunState::Running => return true,
+                RunState::Stopped => return false,
+                RunState::Paused => {}
+            }
+            if self.state.changed().await.is_err() {
+                // every handle is gone, so nothing can resume us
+                return false;
+            }
+        }
+    }
+
+    /// Current run state, for components that keep taking some input while paused or stopped
+    pub(crate) fn state(&mut self) -> RunState {
+        *self.state.borrow_and_update()
+    }
+
+    /// Resolve on the next state change, for racing against a component's input
+    pub(crate) async fn changed(&mut self) {
+        if self.state.changed().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    pub(crate) fn settings(&self) -> S {
+        self.settings.borrow().clone()
+    }
+}
+
+/// Handles for every component of a running simulation
+#[derive(Debug, Clone, Default)]
+pub struct Controls {
+    pub clearinghouse: Option<ClearinghouseHandle>,
+    /// Keyed by payer id
+    pub payers: BTreeMap<PayerId, PayerHandle>,
+    /// Keyed by tenant id
+    pub billers: BTreeMap<String, BillerHandle>,
+}
+
+/// Current state of one component, as reported by the HTTP API
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComponentStatus {
+    pub component: &'static str,
+    pub id: String,
+    pub state: RunState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settings: Option<PayerSettings>,
+}
+
+impl Controls {
+    pub fn status(&self) -> Vec<ComponentStatus> {
+        let mut status = Vec::new();
+        if let Some(clearinghouse) = &self.clearinghouse {
+            status.push(ComponentStatus {
+                component: "clearinghouse",
+                id: "clearinghouse".to_string(),
+                state: clearinghouse.state(),
+                settings: None,
+            });
+        }
+        for (payer_id, payer) in &self.payers {
+            status.push(ComponentStatus {
+                component: "payer",
+                id: payer_id.to_string(),
+                state: payer.state(),
+                settings: Some(payer.settings()),
+            });
+        }
+        for (tenant_id, biller) in &self.billers {
+            status.push(ComponentStatus {
+                component: "biller",
+                id: tenant_id.clone(),
+                state: biller.state(),
+                settings: None,
+            });
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    /// Test that a paused component waits until resumed and that stop is final.
+    /// Expected: proceed() blocks while paused, returns true on resume, false after stop; resume after stop is ignored.
+    #[tokio::test]
+    async fn test_pause_resume_stop() {
+        let handle = ClearinghouseHandle::new(());
+        let mut control = handle.subscribe();
+        assert!(control.proceed().await);
+
+        handle.pause();
+        assert!(timeout(Duration::from_millis(50), control.proceed()).await.is_err());
+        handle.resume();
+        assert!(control.proceed().await);
+
+        handle.stop();
+        handle.resume();
+        assert_eq!(handle.state(), RunState::Stopped);
+        assert!(!control.proceed().await);
+    }
+
+    /// Test that payer settings are validated before being applied.
+    /// Expected: Inverted response or acknowledgment times and out-of-range denial or telehealth rates are rejected; valid settings apply.
+    #[test]
+    fn test_payer_reconfigure() {
+        let original = PayerSettings { min_response_time_secs: 1, max_response_time_secs: 2, ack_time_secs: Some((0, 1)), denial_rate: 0.0, error_rate: 0.0, mismatch_policy: MismatchPolicy::Flag, telehealth_rate: TELEHEALTH_PARITY };
+        let handle = PayerHandle::new(original);
+        assert!(handle.reconfigure(PayerSettings { min_response_time_secs: 5, ..original }).is_err());
+        assert!(handle.reconfigure(PayerSettings { ack_time_secs: Some((2, 1)), ..original }).is_err());
+        assert!(handle.reconfigure(PayerSettings { denial_rate: 1.5, ..original }).is_err());
+        assert!(handle.reconfigure(PayerSettings { telehealth_rate: 1.2, ..original }).is_err());
+        assert_eq!(handle.settings(), original);
+
+        let updated = PayerSettings { denial_rate: 0.25, ..original };
+        handle.reconfigure(updated).unwrap();
+        assert_eq!(handle.subscribe().settings(), updated);
+    }
+}
@@ -0,0 +1,259 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tokio::sync::mpsc::{Receiver, Sender, WeakSender};
+
+/// A message held at a checkpoint, waiting to be stepped past
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HeldMessage {
+    /// Order the message reached its checkpoint in; steps release the lowest first
+    pub seq: u64,
+    pub channel: String,
+    pub message: String,
+}
+
+/// One channel as the debugger sees it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChannelView {
+    pub channel: String,
+    /// Messages sent but not yet taken off the channel, where its depth is known
+    pub queued: Option<usize>,
+    /// Messages taken off the channel and held at its checkpoint
+    pub held: usize,
+    /// The message stepping this channel would release
+    pub next: Option<String>,
+}
+
+/// Everything the debugger can show about the pipeline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DebugSnapshot {
+    pub paused: bool,
+    /// Messages released one at a time so far
+    pub steps: u64,
+    /// The message the next step releases
+    pub next: Option<HeldMessage>,
+    /// Sorted by name
+    pub channels: Vec<ChannelView>,
+}
+
+#[derive(Debug, Default)]
+struct DebugState {
+    paused: bool,
+    steps: u64,
+    next_seq: u64,
+    held: BTreeMap<u64, HeldMessage>,
+    /// Held messages a step has released whose components have not yet moved on
+    released: Vec<u64>,
+}
+
+type DepthProbe = Box<dyn Fn() -> Option<usize> + Send + Sync>;
+
+struct Inner {
+    state: Mutex<DebugState>,
+    changed: Notify,
+    probes: Mutex<BTreeMap<String, DepthProbe>>,
+}
+
+/// Pauses the whole pipeline at its checkpoints and lets messages through one at a time
+///
+/// Each component checks in with the debugger after taking a message off a channel and
+/// before handling it. While paused, the message is held there until a step releases it,
+/// oldest first, or the pipeline resumes; while running, checkpoints cost one lock
+#[derive(Clone)]
+pub struct Debugger {
+    inner: Arc<Inner>,
+}
+
+impl Debugger {
+    /// Debugger that starts paused, so nothing moves until it is stepped or resumed
+    pub fn paused() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                state: Mutex::new(DebugState { paused: true, ..DebugState::default() }),
+                changed: Notify::new(),
+                probes: Mutex::new(BTreeMap::new()),
+            }),
+        }
+    }
+
+    pub fn pause(&self) {
+        self.inner.state.lock().unwrap().paused = true;
+    }
+
+    /// Let every held message through and stop holding new ones
+    pub fn resume(&self) {
+        self.inner.state.lock().unwrap().paused = false;
+        self.inner.changed.notify_waiters();
+    }
+
+    /// Release the message held longest, returning it, or `None` if nothing is held
+    pub fn step(&self) -> Option<HeldMessage> {
+        let mut state = self.inner.state.lock().unwrap();
+        let (seq, held) = state.held.pop_first()?;
+        state.released.push(seq);
+        state.steps += 1;
+        drop(state);
+        self.inner.changed.notify_waiters();
+        Some(held)
+    }
+
+    /// Report the depth of `tx`'s channel as `channel`, without keeping the channel open
+    pub fn watch_channel<T: Send + 'static>(&self, channel: impl Into<String>, tx: &Sender<T>) {
+        let weak: WeakSender<T> = tx.downgrade();
+        let probe: DepthProbe = Box::new(move || weak.upgrade().map(|tx| tx.max_capacity() - tx.capacity()));
+        self.inner.probes.lock().unwrap().insert(channel.into(), probe);
+    }
+
+    pub fn snapshot(&self) -> DebugSnapshot {
+        let state = self.inner.state.lock().unwrap();
+        let mut channels: BTreeMap<String, ChannelView> = BTreeMap::new();
+        for (channel, probe) in self.inner.probes.lock().unwrap().iter() {
+            channels.insert(channel.clone(), ChannelView { channel: channel.clone(), queued: probe(), held: 0, next: None });
+        }
+        for held in state.held.values() {
+            let view = channels.entry(held.channel.clone()).or_insert_with(|| ChannelView {
+                channel: held.channel.clone(),
+                queued: None,
+                held: 0,
+                next: None,
+            });
+            view.held += 1;
+            view.next.get_or_insert_with(|| held.message.clone());
+        }
+        DebugSnapshot {
+            paused: state.paused,
+            steps: state.steps,
+            next: state.held.values().next().cloned(),
+            channels: channels.into_values().collect(),
+        }
+    }
+
+    /// Hold `message`, just taken off `channel`, while paused until a step releases it
+    pub(crate) async fn checkpoint(&self, channel: &str, message: impl FnOnce() -> String) {
+        let seq = {
+            let mut state = self.inner.state.lock().unwrap();
+            if !state.paused {
+                return;
+            }
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.held.insert(seq, HeldMessage { seq, channel: channel.to_string(), message: message() });
+            seq
+        };
+        loop {
+            // registered before checking, so a step between the check and the wait is not missed
+            let changed = self.inner.changed.notified();
+            {
+                let mut state = self.inner.state.lock().unwrap();
+                if let Some(index) = state.released.iter().position(|released| *released == seq) {
+                    state.released.swap_remove(index);
+                    return;
+                }
+                if !state.paused {
+                    state.held.remove(&seq);
+                    return;
+                }
+            }
+            changed.await;
+        }
+    }
+}
+
+/// Drive a debugger from text commands, one per line, until the lines end or `quit`
+///
+/// `step [n]` releases the next n messages (default 1), `continue` resumes, `pause` pauses,
+/// and `inspect` shows the queues; each command prints what it did
+pub async fn run_console(debugger: Debugger, mut lines: Receiver<String>) {
+    println!("Debugger paused. Commands: step [n], inspect, continue, pause, quit");
+    while let Some(line) = lines.recv().await {
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("step" | "s"), count) => {
+                let count = count.and_then(|n| n.parse().ok()).unwrap_or(1);
+                for _ in 0..count {
+                    // components reach their next checkpoint as soon as they can
+                    let mut released = debugger.step();
+                    for _ in 0..20 {
+                        if released.is_some() {
+                            break;
+                        }
+                        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                        released = debugger.step();
+                    }
+                    match released {
+                        Some(held) => println!("#{} {}: {}", held.seq, held.channel, held.message),
+                        None => {
+                            println!("No message waiting");
+                            break;
+                        }
+                    }
+                }
+            }
+            (Some("inspect" | "i"), _) => crate::reporter::print_debug_snapshot(&debugger.snapshot()),
+            (Some("continue" | "c"), _) => {
+                debugger.resume();
+                println!("Running");
+            }
+            (Some("pause" | "p"), _) => {
+                debugger.pause();
+                println!("Paused");
+            }
+            (Some("quit" | "q"), _) => break,
+            (None, _) => {}
+            (Some(other), _) => println!("Unknown command: {} (expected step [n], inspect, continue, pause, quit)", other),
+        }
+    }
+    // nothing can step the pipeline any more
+    debugger.resume();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{BillerSpec, PayerSpec, Simulation};
+    use crate::test_utils::mock_claim;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    /// Test stepping one claim through its whole lifecycle under a paused debugger.
+    /// Expected: Nothing is posted until stepped; five steps release the claim at the biller, clearinghouse, payer, clearinghouse, and biller in order; then it is remitted.
+    #[tokio::test]
+    async fn test_step_claim_lifecycle() {
+        let debugger = Debugger::paused();
+        let mut sim = Simulation::builder()
+            .payer(PayerSpec::new("medicare", 0, 0))
+            .biller(BillerSpec::new("acme"))
+            .claim_source(vec![mock_claim()])
+            .debugger(debugger.clone())
+            .build()
+            .unwrap();
+
+        let mut channels = Vec::new();
+        while channels.len() < 5 {
+            let held = timeout(Duration::from_secs(5), async {
+                loop {
+                    if let Some(held) = debugger.snapshot().next {
+                        return held;
+                    }
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+            })
+            .await
+            .expect("the claim should reach its next checkpoint");
+            assert_eq!(debugger.snapshot().channels.iter().map(|view| view.held).sum::<usize>(), 1);
+            assert!(sim.ledger("acme").unwrap().lock().await.entries().is_empty());
+            assert_eq!(debugger.step(), Some(held.clone()));
+            channels.push(held.channel);
+        }
+        assert_eq!(
+            channels,
+            vec!["biller:acme", "clearinghouse:claims", "payer:medicare", "clearinghouse:remittances", "biller:acme:remittances"]
+        );
+        timeout(Duration::from_secs(5), sim.wait()).await.expect("claim should be remitted after its last step");
+        assert_eq!(debugger.snapshot().steps, 5);
+        let payer = debugger.snapshot().channels.into_iter().find(|view| view.channel == "payer:medicare").unwrap();
+        assert_eq!((payer.queued, payer.held), (Some(0), 0));
+        sim.shutdown();
+    }
+}
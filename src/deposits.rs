@@ -0,0 +1,223 @@
+use chrono::{Days, NaiveDate};
+use rand::Rng;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use crate::ids::{ClaimId, ClaimKey, PayerId};
+use crate::message::ClaimStatus;
+
+/// Smallest payment change worth issuing, below which a claim is treated as fully paid
+const PAYMENT_TOLERANCE: f64 = 1e-2;
+
+/// How a payer's payment reaches the billing organization's bank
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentMethod {
+    /// Electronic funds transfer, deposited the day it is issued
+    Eft,
+    /// Paper check, deposited once it clears
+    Check,
+}
+
+impl fmt::Display for PaymentMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaymentMethod::Eft => write!(f, "eft"),
+            PaymentMethod::Check => write!(f, "check"),
+        }
+    }
+}
+
+/// A claim's part of a payment: positive when paid, negative when the payer recoups an earlier payment
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepositLine {
+    pub claim_id: ClaimId,
+    pub amount: f64,
+}
+
+/// One payer payment to one billing organization as it lands in the bank
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deposit {
+    /// EFT trace or check number the payer's remittance advice quotes
+    pub trace_number: String,
+    pub tenant_id: String,
+    pub payer_id: PayerId,
+    pub method: PaymentMethod,
+    pub issued_on: NaiveDate,
+    pub deposited_on: NaiveDate,
+    /// Claims the remittance advice lists under the trace number
+    pub lines: Vec<DepositLine>,
+}
+
+impl Deposit {
+    pub fn amount(&self) -> f64 {
+        self.lines.iter().map(|line| line.amount).sum()
+    }
+}
+
+/// When payers pay and by which method
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepositSettings {
+    /// Simulated days between payment runs
+    pub cycle_days: u32,
+    /// Chance a payment is made by check rather than EFT
+    pub check_share: f64,
+    /// Simulated days a check takes to clear
+    pub check_clearing_days: u32,
+}
+
+impl Default for DepositSettings {
+    fn default() -> Self {
+        Self {
+            cycle_days: 7,
+            check_share: 0.2,
+            check_clearing_days: 3,
+        }
+    }
+}
+
+impl DepositSettings {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.cycle_days == 0 {
+            return Err(anyhow::anyhow!("a payment cycle must be at least one day"));
+        }
+        if !(0.0..=1.0).contains(&self.check_share) {
+            return Err(anyhow::anyhow!("check share must be between 0 and 1"));
+        }
+        Ok(())
+    }
+}
+
+/// Simulated bank feed of payer payments
+///
+/// Each payment run batches what every payer owes on its remittances since the last run into
+/// one payment per billing organization. A claim superseded by a replacement is recouped
+#[derive(Debug)]
+pub struct DepositFeed {
+    settings: DepositSettings,
+    /// What each claim has been paid so far, net of recoupments
+    paid: HashMap<ClaimKey, f64>,
+    deposits: Vec<Deposit>,
+}
+
+impl DepositFeed {
+    pub fn new(settings: DepositSettings) -> anyhow::Result<Self> {
+        settings.validate()?;
+        Ok(Self {
+            settings,
+            paid: HashMap::new(),
+            deposits: Vec::new(),
+        })
+    }
+
+    pub fn settings(&self) -> &DepositSettings {
+        &self.settings
+    }
+
+    /// Issue the payments owed on remitted claims as of `issued_on`, returning how many were issued
+    pub fn issue(&mut self, records: &HashMap<ClaimKey, ClaimStatus>, issued_on: NaiveDate, rng: &mut impl Rng) -> usize {
+        let replaced: HashSet<ClaimKey> = records
+            .values()
+            .filter_map(|status| match status {
+                ClaimStatus::Submitted { claim, .. } => claim.replaces(),
+                ClaimStatus::Remitted(record) => record.claim().replaces(),
+                ClaimStatus::Voided { .. } => None,
+            }
+            .map(|original_id| ClaimKey::new(status.tenant_id(), original_id.clone())))
+            .collect();
+        let mut batches: BTreeMap<(&str, &PayerId), Vec<DepositLine>> = BTreeMap::new();
+        for (key, status) in records {
+            let ClaimStatus::Remitted(record) = status else {
+                continue;
+            };
+            let claim_id = record.remittance().claim_id();
+            let owed = match replaced.contains(key) {
+                true => 0.0,
+                false => record.remittance().payer_paid(),
+            };
+            let amount = owed - self.paid.get(key).copied().unwrap_or_default();
+            if amount.abs() < PAYMENT_TOLERANCE {
+                continue;
+            }
+            *self.paid.entry(key.clone()).or_default() += amount;
+            batches
+                .entry((record.tenant_id(), record.payer_id()))
+                .or_default()
+                .push(DepositLine { claim_id: claim_id.clone(), amount });
+        }
+        let issued = batches.len();
+        for ((tenant_id, payer_id), mut lines) in batches {
+            lines.sort_by(|a, b| a.claim_id.cmp(&b.claim_id));
+            let (method, clearing_days) = match rng.random_bool(self.settings.check_share) {
+                true => (PaymentMethod::Check, self.settings.check_clearing_days),
+                false => (PaymentMethod::Eft, 0),
+            };
+            self.deposits.push(Deposit {
+                trace_number: format!("{}-{:06}", payer_id, self.deposits.len() + 1),
+                tenant_id: tenant_id.to_string(),
+                payer_id: payer_id.clone(),
+                method,
+                issued_on,
+                deposited_on: issued_on + Days::new(clearing_days.into()),
+                lines,
+            });
+        }
+        issued
+    }
+
+    /// Every payment issued so far, oldest first
+    pub fn deposits(&self) -> &[Deposit] {
+        &self.deposits
+    }
+
+    /// A billing organization's payments that have reached its bank by `date`
+    pub fn deposited_by<'a>(&'a self, tenant_id: &'a str, date: NaiveDate) -> impl Iterator<Item = &'a Deposit> {
+        self.deposits
+            .iter()
+            .filter(move |deposit| deposit.tenant_id == tenant_id && deposit.deposited_on <= date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tenant::DEFAULT_TENANT;
+    use crate::remittance::{Remittance, RemittanceRecord};
+    use crate::schema::{ClaimFrequency, PayerClaim};
+    use crate::test_utils::{mock_claim, mock_adjudication_date};
+    use std::time::Instant;
+
+    /// Test that a payment run batches each payer's remittances once and recoups a replaced claim on the next run.
+    /// Expected: One EFT deposit for the original claim; after a replacement the next deposit pays it and takes back the original.
+    #[test]
+    fn test_deposit_feed_batches_and_recoups() {
+        let now = Instant::now();
+        let original = mock_claim();
+        let mut records = HashMap::new();
+        let remitted = |claim: &PayerClaim| {
+            ClaimStatus::Remitted(RemittanceRecord::new(claim.clone(), Remittance::from_claim(claim, mock_adjudication_date()), now, now))
+        };
+        records.insert(ClaimKey::new(DEFAULT_TENANT, original.claim_id.clone()), remitted(&original));
+        let settings = DepositSettings { check_share: 0.0, ..DepositSettings::default() };
+        let mut feed = DepositFeed::new(settings).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2025, 3, 3).unwrap();
+        assert_eq!(feed.issue(&records, monday, &mut rand::rng()), 1);
+        let paid = Remittance::from_claim(&original, mock_adjudication_date()).payer_paid();
+        let [deposit] = feed.deposits() else { panic!("Expected one deposit") };
+        assert_eq!(deposit.method, PaymentMethod::Eft);
+        assert_eq!(deposit.deposited_on, monday);
+        assert_eq!(deposit.amount(), paid);
+        assert_eq!(feed.issue(&records, monday, &mut rand::rng()), 0);
+
+        let mut replacement = mock_claim();
+        replacement.claim_id = "replacement".into();
+        replacement.claim_frequency_code = ClaimFrequency::Replacement;
+        replacement.original_claim_id = Some(original.claim_id.clone());
+        records.insert(ClaimKey::new(DEFAULT_TENANT, replacement.claim_id.clone()), remitted(&replacement));
+        feed.issue(&records, monday + Days::new(7), &mut rand::rng());
+        let recouped = &feed.deposits()[1];
+        assert_eq!(recouped.lines.len(), 2);
+        assert!(recouped.lines.iter().any(|line| line.claim_id == original.claim_id && line.amount == -paid));
+        assert!(recouped.amount().abs() < PAYMENT_TOLERANCE);
+        assert_eq!(feed.deposited_by("someone-else", monday + Days::new(7)).count(), 0);
+    }
+}
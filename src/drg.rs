@@ -0,0 +1,90 @@
+use crate::schema::PayerClaim;
+
+/// Payment for a relative weight of 1.0, the hospital's base rate for an inpatient stay
+pub const BASE_RATE: f64 = 6500.0;
+
+/// MS-DRG relative weights payers price inpatient stays by, with each group's description
+const WEIGHTS: [(&str, f64, &str); 8] = [
+    ("194", 0.9, "simple pneumonia and pleurisy with CC"),
+    ("291", 1.34, "heart failure and shock with MCC"),
+    ("292", 0.89, "heart failure and shock with CC"),
+    ("392", 0.74, "esophagitis, gastroenteritis and miscellaneous digestive disorders without MCC"),
+    ("470", 1.9, "major hip and knee joint replacement without MCC"),
+    ("683", 0.87, "renal failure with CC"),
+    ("775", 0.61, "vaginal delivery without complicating diagnoses"),
+    ("871", 1.87, "septicemia without mechanical ventilation over 96 hours with MCC"),
+];
+
+/// Procedures that group a stay to a surgical DRG whatever the diagnosis: total knee and hip replacement
+const PROCEDURE_GROUPS: [(&str, &str); 2] = [("27447", "470"), ("27130", "470")];
+
+/// ICD-10-CM categories of the principal diagnosis that group a medical stay
+const DIAGNOSIS_GROUPS: [(&str, &str); 8] = [
+    ("A41", "871"),
+    ("I50", "291"),
+    ("I11", "292"),
+    ("J18", "194"),
+    ("K21", "392"),
+    ("K52", "392"),
+    ("N17", "683"),
+    ("O80", "775"),
+];
+
+/// Relative weight of a DRG, or `None` for a group the payers do not price
+///
+/// Accepts the code with or without an `MS-` prefix or leading zeros
+pub fn weight(drg: &str) -> Option<f64> {
+    let code = drg.trim().trim_start_matches("MS-").trim_start_matches('0');
+    WEIGHTS
+        .iter()
+        .find(|(known, _, _)| *known == code)
+        .map(|(_, weight, _)| *weight)
+}
+
+/// What a payer pays for an inpatient stay grouped to `drg`, whatever the hospital charged
+pub fn payment(drg: &str) -> Option<f64> {
+    weight(drg).map(|weight| weight * BASE_RATE)
+}
+
+/// Principal diagnoses that group a stay to a DRG, for generating stays the grouper can place
+pub fn groupable_diagnoses() -> impl Iterator<Item = &'static str> {
+    DIAGNOSIS_GROUPS.iter().map(|(category, _)| *category)
+}
+
+/// Group a stay to a DRG the way a payer's grouper would, from the billed services alone
+///
+/// A stub of the MS-DRG logic: a billed procedure with a surgical group wins, else the
+/// category of the principal (first) diagnosis decides. `None` when neither matches
+pub fn group(claim: &PayerClaim) -> Option<&'static str> {
+    let surgical = claim.service_lines.iter().find_map(|line| {
+        let code = line.procedure_code.trim();
+        PROCEDURE_GROUPS.iter().find(|(procedure, _)| *procedure == code).map(|(_, drg)| *drg)
+    });
+    surgical.or_else(|| {
+        let principal = claim.diagnosis_codes.first()?.trim().to_uppercase();
+        DIAGNOSIS_GROUPS
+            .iter()
+            .find(|(category, _)| principal.starts_with(category))
+            .map(|(_, drg)| *drg)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::mock_claim;
+
+    /// Test that the grouper prefers a surgical procedure and otherwise groups by principal diagnosis.
+    /// Expected: A knee replacement groups to 470 whatever the diagnosis; heart failure groups to 291 and an unmatched diagnosis to none.
+    #[test]
+    fn test_group() {
+        let mut claim = mock_claim();
+        assert_eq!(group(&claim), None);
+        claim.diagnosis_codes = vec!["i50.9".to_string(), "A41.9".to_string()];
+        assert_eq!(group(&claim), Some("291"));
+        assert_eq!(payment("291"), Some(1.34 * BASE_RATE));
+        claim.service_lines[0].procedure_code = "27447".to_string();
+        assert_eq!(group(&claim), Some("470"));
+        assert!(DIAGNOSIS_GROUPS.iter().chain(&PROCEDURE_GROUPS).all(|(_, drg)| weight(drg).is_some()));
+    }
+}
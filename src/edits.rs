@@ -0,0 +1,251 @@
+use rand::Rng;
+use rand::seq::IndexedRandom;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::schema::PayerClaim;
+
+/// A level of the clearinghouse's front-end edits, run in the order listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditLevel {
+    /// Format and required-field checks every claim must pass
+    Syntax,
+    /// Rules particular to the claim's payer
+    Payer,
+    /// Checks against claims already accepted
+    Duplicate,
+}
+
+impl EditLevel {
+    pub const ALL: [EditLevel; 3] = [EditLevel::Syntax, EditLevel::Payer, EditLevel::Duplicate];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Edit codes a claim can fail this level with, and what each means
+    fn codes(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            EditLevel::Syntax => &[
+                ("SYN001", "billing provider NPI missing or invalid"),
+                ("SYN002", "service date not a valid date"),
+                ("SYN003", "diagnosis pointer refers to no diagnosis"),
+                ("SYN004", "subscriber name missing"),
+            ],
+            EditLevel::Payer => &[
+                ("PAY101", "procedure requires a modifier for this payer"),
+                ("PAY102", "member id format not accepted by this payer"),
+                ("PAY103", "prior authorization number required"),
+                ("PAY104", "place of service not covered by this payer"),
+            ],
+            EditLevel::Duplicate => &[
+                ("DUP201", "same patient, provider, and date of service as an accepted claim"),
+                ("DUP202", "same service lines as an accepted claim still with the payer"),
+            ],
+        }
+    }
+}
+
+impl FromStr for EditLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "syntax" => Ok(EditLevel::Syntax),
+            "payer" => Ok(EditLevel::Payer),
+            "duplicate" => Ok(EditLevel::Duplicate),
+            _ => Err(anyhow::anyhow!("Unknown edit level: {} (expected syntax, payer, or duplicate)", s)),
+        }
+    }
+}
+
+impl fmt::Display for EditLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditLevel::Syntax => write!(f, "syntax"),
+            EditLevel::Payer => write!(f, "payer"),
+            EditLevel::Duplicate => write!(f, "duplicate"),
+        }
+    }
+}
+
+/// A requirement a payer's companion guide adds to the standard claim format
+struct GuideRule {
+    payer_id: &'static str,
+    code: &'static str,
+    message: &'static str,
+    passes: fn(&PayerClaim) -> bool,
+}
+
+/// Companion-guide rules of the simulated payers, checked in order
+const COMPANION_GUIDES: [GuideRule; 4] = [
+    GuideRule {
+        payer_id: "medicare",
+        code: "MCR001",
+        message: "rendering provider taxonomy code required",
+        passes: |claim| claim.rendering_provider.taxonomy_code.is_some(),
+    },
+    GuideRule {
+        payer_id: "medicare",
+        code: "MCR002",
+        message: "billing provider address required",
+        passes: |claim| claim.billing_address().is_some(),
+    },
+    GuideRule {
+        payer_id: "united_health_group",
+        code: "UHC001",
+        message: "billing provider taxonomy code required",
+        passes: |claim| claim.billing_taxonomy().is_some(),
+    },
+    GuideRule {
+        payer_id: "anthem",
+        code: "ANT001",
+        message: "an individual billing provider must be the rendering provider",
+        passes: |claim| claim.billing_provider.as_ref().is_none_or(|billing| billing.npi == claim.rendering_provider.npi),
+    },
+];
+
+/// Why the clearinghouse turned a claim back to its biller before it reached the payer
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditRejection {
+    pub level: EditLevel,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for EditRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} edit {}: {}", self.level, self.code, self.message)
+    }
+}
+
+/// Fraction of claims failing each level of front-end edits
+///
+/// Each rate applies to the claims that passed the levels before it. With companion guides,
+/// the payer level also rejects every claim breaking one of its payer's published rules. All
+/// zero and off by default, so every claim passes
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EditRates {
+    pub syntax: f64,
+    pub payer: f64,
+    pub duplicate: f64,
+    pub companion_guides: bool,
+}
+
+impl EditRates {
+    pub fn rate(&self, level: EditLevel) -> f64 {
+        match level {
+            EditLevel::Syntax => self.syntax,
+            EditLevel::Payer => self.payer,
+            EditLevel::Duplicate => self.duplicate,
+        }
+    }
+
+    /// Run a claim through every level, stopping at the first it fails
+    pub fn check(&self, claim: &PayerClaim, rng: &mut impl Rng) -> Result<(), EditRejection> {
+        for level in EditLevel::ALL {
+            if level == EditLevel::Payer
+                && self.companion_guides
+                && let Some(rejection) = companion_guide_rejection(claim)
+            {
+                return Err(rejection);
+            }
+            if rng.random_bool(self.rate(level)) {
+                let (code, message) = level.codes().choose(rng).copied().expect("every level has codes");
+                let message = match level {
+                    EditLevel::Payer => format!("{} ({})", message, claim.insurance.payer_id),
+                    EditLevel::Syntax | EditLevel::Duplicate => message.to_string(),
+                };
+                return Err(EditRejection { level, code, message });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The first rule of the claim's payer's companion guide the claim breaks
+fn companion_guide_rejection(claim: &PayerClaim) -> Option<EditRejection> {
+    let payer_id = claim.insurance.payer_id.as_str();
+    COMPANION_GUIDES
+        .iter()
+        .find(|rule| rule.payer_id == payer_id && !(rule.passes)(claim))
+        .map(|rule| EditRejection {
+            level: EditLevel::Payer,
+            code: rule.code,
+            message: format!("{} ({} companion guide)", rule.message, payer_id),
+        })
+}
+
+impl FromStr for EditRates {
+    type Err = anyhow::Error;
+
+    /// Parse `<level>=<rate>,...`, e.g. `syntax=0.02,payer=0.05`; levels left out never reject
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rates = EditRates::default();
+        for entry in s.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let invalid = || anyhow::anyhow!("Invalid edit rate: {} (expected <level>=<rate> with a rate from 0 to 1)", entry);
+            let (level, rate) = entry.split_once('=').ok_or_else(invalid)?;
+            let rate: f64 = rate.trim().parse().map_err(|_| invalid())?;
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(invalid());
+            }
+            match level.trim().parse()? {
+                EditLevel::Syntax => rates.syntax = rate,
+                EditLevel::Payer => rates.payer = rate,
+                EditLevel::Duplicate => rates.duplicate = rate,
+            }
+        }
+        Ok(rates)
+    }
+}
+
+/// Claims rejected at each level of front-end edits
+#[derive(Debug, Clone, Default)]
+pub struct EditStats {
+    rejected: Arc<[AtomicU64; 3]>,
+}
+
+impl EditStats {
+    pub fn record(&self, level: EditLevel) {
+        self.rejected[level.index()].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn rejected(&self, level: EditLevel) -> u64 {
+        self.rejected[level.index()].load(Ordering::Relaxed)
+    }
+
+    pub fn total(&self) -> u64 {
+        EditLevel::ALL.iter().map(|level| self.rejected(*level)).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::PayerId;
+    use crate::test_utils::mock_claim;
+
+    /// Test that companion guides hold claims to their own payer's rules only, and only when enabled.
+    /// Expected: A medicare claim without a rendering taxonomy fails MCR001 with guides on and passes with them off; anthem rejects a solo billing provider who did not render the service.
+    #[test]
+    fn test_companion_guide_rules() {
+        let guides = EditRates { companion_guides: true, ..EditRates::default() };
+        let mut rng = rand::rng();
+        assert_eq!(guides.check(&mock_claim(), &mut rng), Ok(()));
+
+        let mut claim = mock_claim();
+        claim.rendering_provider.taxonomy_code = None;
+        let rejection = guides.check(&claim, &mut rng).unwrap_err();
+        assert_eq!((rejection.level, rejection.code), (EditLevel::Payer, "MCR001"));
+        assert_eq!(EditRates::default().check(&claim, &mut rng), Ok(()));
+
+        let mut solo = mock_claim();
+        solo.insurance.payer_id = PayerId::from("anthem");
+        solo.billing_provider = Some(solo.rendering_provider.clone());
+        assert_eq!(guides.check(&solo, &mut rng), Ok(()));
+        solo.billing_provider.as_mut().unwrap().npi = "1987654328".into();
+        assert_eq!(guides.check(&solo, &mut rng).unwrap_err().code, "ANT001");
+    }
+}
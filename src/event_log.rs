@@ -0,0 +1,221 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::fs::OpenOptions;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::phi::Deidentification;
+use crate::remittance::Remittance;
+use crate::schema::PayerClaim;
+use crate::tenant::DEFAULT_TENANT;
+use crate::ids::{ClaimId, PayerId};
+
+/// Something that happened to a claim during a simulation run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// Clearinghouse accepted a new claim from a biller
+    ClaimSubmitted {
+        #[serde(default = "default_tenant")]
+        tenant_id: String,
+        claim: Arc<PayerClaim>,
+    },
+    /// Clearinghouse recognized a resubmission of an earlier claim's content
+    DuplicateSubmitted { claim_id: ClaimId, original_claim_id: ClaimId },
+    /// Clearinghouse rejected a new claim back to its biller at a front-end edit
+    ClaimRejected { claim_id: ClaimId, code: String, reason: String },
+    /// Clearinghouse forwarded a claim to its payer
+    ClaimRouted { claim_id: ClaimId, payer_id: PayerId },
+    /// Payer acknowledged taking a claim in for adjudication
+    ClaimAcknowledged { claim_id: ClaimId, payer_id: PayerId },
+    /// Payer finished adjudication and produced a remittance
+    ClaimAdjudicated {
+        payer_id: PayerId,
+        remittance: Remittance,
+        /// Version of the payer rules the claim was adjudicated under, for simulated payers
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rules_version: Option<u64>,
+    },
+    /// Clearinghouse recorded the remittance in history and returned it to the biller
+    ClaimRemitted {
+        #[serde(default = "default_tenant")]
+        tenant_id: String,
+        claim_id: ClaimId,
+        /// Id the payer adjudicated the claim under, when not the claim id
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        payer_claim_id: Option<ClaimId>,
+    },
+    /// Clearinghouse voided a claim its biller cancelled before it was remitted
+    ClaimVoided {
+        #[serde(default = "default_tenant")]
+        tenant_id: String,
+        claim_id: ClaimId,
+    },
+    /// Watchdog found a claim still waiting on its payer past the allowed time
+    ClaimStuck { claim_id: ClaimId, payer_id: PayerId, pending_secs: u64 },
+    /// Changed rule files were reloaded and applied to running payers as a new version
+    RulesApplied { version: u64 },
+}
+
+fn default_tenant() -> String {
+    DEFAULT_TENANT.to_string()
+}
+
+/// One line of the event log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRecord {
+    /// Monotonic sequence number, starting at 1 and continuing across appended runs
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    /// Milliseconds since the log was opened by this run, used to pace replays
+    pub elapsed_ms: u64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+#[derive(Debug)]
+enum LogCommand {
+    Record(Event),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Append-only JSONL log of simulation events
+///
+/// A single writer task assigns sequence numbers, so they follow the order events are recorded
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    tx: mpsc::UnboundedSender<LogCommand>,
+    deidentification: Deidentification,
+}
+
+impl EventLog {
+    /// Open (or create) the log at `path` and start its writer task
+    ///
+    /// Existing events are kept and new ones appended after them,
+    /// continuing the sequence numbers of the previous run
+    pub async fn open(path: &str) -> anyhow::Result<Self> {
+        let mut seq = match tokio::fs::try_exists(path).await? {
+            true => read_event_log(path).await?.last().map_or(0, |record| record.seq),
+            false => 0,
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path).await?;
+        let mut writer = BufWriter::new(file);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let path = path.to_string();
+        tokio::spawn(async move {
+            let started = Instant::now();
+            while let Some(command) = rx.recv().await {
+                match command {
+                    LogCommand::Record(event) => {
+                        seq += 1;
+                        let record = EventRecord {
+                            seq,
+                            timestamp: Utc::now(),
+                            elapsed_ms: started.elapsed().as_millis() as u64,
+                            event,
+                        };
+                        if let Err(e) = write_record(&mut writer, &record).await {
+                            eprintln!("Failed to write event {} to {}: {}", seq, path, e);
+                        }
+                    }
+                    LogCommand::Flush(done) => {
+                        if let Err(e) = writer.flush().await {
+                            eprintln!("Failed to flush event log {}: {}", path, e);
+                        }
+                        let _ = done.send(());
+                    }
+                }
+            }
+            let _ = writer.flush().await;
+        });
+        Ok(Self { tx, deidentification: Deidentification::Off })
+    }
+
+    /// De-identify patient demographics in submitted claims before they are logged
+    pub fn with_deidentification(mut self, deidentification: Deidentification) -> Self {
+        self.deidentification = deidentification;
+        self
+    }
+
+    /// Queue an event for the log; never blocks the caller
+    pub fn record(&self, mut event: Event) {
+        // the claim is shared with the pipeline, so only the logged copy is de-identified
+        if let Event::ClaimSubmitted { claim, .. } = &mut event
+            && !matches!(self.deidentification, Deidentification::Off)
+        {
+            self.deidentification.claim(Arc::make_mut(claim));
+        }
+        if self.tx.send(LogCommand::Record(event)).is_err() {
+            eprintln!("Event log writer stopped");
+        }
+    }
+
+    /// Wait until every event recorded so far is written to disk
+    pub async fn flush(&self) {
+        let (done_tx, done_rx) = oneshot::channel();
+        if self.tx.send(LogCommand::Flush(done_tx)).is_ok() {
+            let _ = done_rx.await;
+        }
+    }
+}
+
+async fn write_record<W: AsyncWriteExt + Unpin>(writer: &mut W, record: &EventRecord) -> anyhow::Result<()> {
+    let mut json = serde_json::to_string(record)?;
+    json.push('\n');
+    writer.write_all(json.as_bytes()).await?;
+    Ok(())
+}
+
+/// Read every record from an event log file, in file order
+pub async fn read_event_log(path: &str) -> anyhow::Result<Vec<EventRecord>> {
+    let contents = tokio::fs::read_to_string(path).await?;
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| anyhow::anyhow!("{}:{}: invalid event: {}", path, index + 1, e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{mock_claim, mock_remittance};
+
+    /// Test that recorded events are written in order with increasing sequence numbers.
+    /// Expected: Three records read back with seq 1..=3 and the original events; reopening continues at 4.
+    #[tokio::test]
+    async fn test_event_log_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let path = path.to_str().unwrap();
+
+        let log = EventLog::open(path).await.unwrap();
+        let events = vec![
+            Event::ClaimSubmitted { tenant_id: DEFAULT_TENANT.to_string(), claim: Arc::new(mock_claim()) },
+            Event::ClaimAdjudicated { payer_id: "medicare".into(), remittance: mock_remittance(), rules_version: None },
+            Event::ClaimRemitted { tenant_id: DEFAULT_TENANT.to_string(), claim_id: "abc123".into(), payer_claim_id: None },
+        ];
+        for event in &events {
+            log.clone().record(event.clone());
+        }
+        log.flush().await;
+
+        let records = read_event_log(path).await.unwrap();
+        assert_eq!(records.iter().map(|r| r.seq).collect::<Vec<_>>(), vec![1, 2, 3]);
+        drop(log);
+
+        let reopened = EventLog::open(path).await.unwrap();
+        reopened.record(Event::ClaimRemitted { tenant_id: DEFAULT_TENANT.to_string(), claim_id: "def456".into(), payer_claim_id: None });
+        reopened.flush().await;
+        assert_eq!(read_event_log(path).await.unwrap().last().unwrap().seq, 4);
+        for (record, event) in records.iter().zip(&events) {
+            assert_eq!(serde_json::to_value(&record.event).unwrap(), serde_json::to_value(event).unwrap());
+        }
+    }
+}
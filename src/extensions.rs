@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+
+use crate::rejects::LineError;
+use crate::schema::{PayerClaim, Provider};
+use crate::validation;
+
+/// Key of the ambulance transport segment, required with ambulance codes
+pub const AMBULANCE: &str = "ambulance";
+
+/// Key of the durable medical equipment segment, required with DME codes
+pub const DME: &str = "dme";
+
+/// Key of the provider who ordered the services, required with DME codes
+pub const ORDERING_PROVIDER: &str = "ordering_provider";
+
+/// HCPCS code billed per loaded mile of ground ambulance transport
+pub const AMBULANCE_MILEAGE_CODE: &str = "A0425";
+
+/// Longest capped rental Medicare pays before the patient owns the equipment
+pub const MAX_RENTAL_MONTHS: u32 = 13;
+
+/// Ambulance trip details, as in the 837P CR1 segment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AmbulanceTransport {
+    /// Loaded miles from pickup to destination
+    pub miles: f64,
+    /// Why the patient was transported, e.g. `A` for to the nearest facility
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason_code: Option<String>,
+}
+
+/// Whether durable medical equipment is rented month to month or bought outright
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DmeAcquisition {
+    Rental,
+    Purchase,
+}
+
+/// Durable medical equipment details, as in the 837P CR3 and SV5 segments
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DmeItem {
+    pub acquisition: DmeAcquisition,
+    /// Months of rental billed so far, rentals only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rental_months: Option<u32>,
+}
+
+/// Service ranges whose claims need a specialty segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SpecialtyRange {
+    /// Ambulance transport (HCPCS A0021-A0999)
+    Ambulance,
+    /// Durable medical equipment (HCPCS E0100-E8002)
+    Dme,
+}
+
+impl SpecialtyRange {
+    fn of(procedure_code: &str) -> Option<Self> {
+        let code = procedure_code.trim().to_uppercase();
+        let within = |first: &str, last: &str| code.len() == first.len() && first <= code.as_str() && code.as_str() <= last;
+        if within("A0021", "A0999") {
+            Some(SpecialtyRange::Ambulance)
+        } else if within("E0100", "E8002") {
+            Some(SpecialtyRange::Dme)
+        } else {
+            None
+        }
+    }
+}
+
+/// Every problem with the specialty segments the claim's services call for
+///
+/// Segments are only checked when a line falls in their range, so a claim may carry
+/// extensions the simulator does not know. Ambulance codes need a transport with positive
+/// miles, and a mileage line must bill them rounded up; DME codes need the equipment's
+/// acquisition, a rental its months, and an ordering provider with a valid NPI
+pub fn validate(claim: &PayerClaim) -> Vec<LineError> {
+    let mut errors = Vec::new();
+    let ranges: Vec<Option<SpecialtyRange>> = claim.service_lines.iter().map(|line| SpecialtyRange::of(&line.procedure_code)).collect();
+    let error = |key: &str, reason: String| LineError {
+        pointer: format!("/extensions/{}", key),
+        reason,
+    };
+    if ranges.contains(&Some(SpecialtyRange::Ambulance)) {
+        match claim.extension::<AmbulanceTransport>(AMBULANCE) {
+            None => errors.push(error(AMBULANCE, "ambulance transport billed without an ambulance segment".to_string())),
+            Some(Err(e)) => errors.push(error(AMBULANCE, format!("unreadable ambulance segment: {}", e))),
+            Some(Ok(transport)) if !transport.miles.is_finite() || transport.miles <= 0.0 => {
+                errors.push(error(AMBULANCE, format!("transport miles {} must be positive", transport.miles)))
+            }
+            Some(Ok(transport)) => {
+                let billed_miles = transport.miles.ceil() as u32;
+                for (i, line) in claim.service_lines.iter().enumerate() {
+                    if line.procedure_code.trim().eq_ignore_ascii_case(AMBULANCE_MILEAGE_CODE) && line.units != billed_miles {
+                        errors.push(LineError {
+                            pointer: format!("/service_lines/{}/units", i),
+                            reason: format!("{} units {} do not match {} transport miles", AMBULANCE_MILEAGE_CODE, line.units, transport.miles),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    if ranges.contains(&Some(SpecialtyRange::Dme)) {
+        match claim.extension::<DmeItem>(DME) {
+            None => errors.push(error(DME, "durable medical equipment billed without a dme segment".to_string())),
+            Some(Err(e)) => errors.push(error(DME, format!("unreadable dme segment: {}", e))),
+            Some(Ok(DmeItem { acquisition: DmeAcquisition::Rental, rental_months })) => match rental_months {
+                Some(1..=MAX_RENTAL_MONTHS) => {}
+                Some(months) => errors.push(error(DME, format!("rental month {} outside 1 to {}", months, MAX_RENTAL_MONTHS))),
+                None => errors.push(error(DME, "rental without rental_months".to_string())),
+            },
+            Some(Ok(DmeItem { acquisition: DmeAcquisition::Purchase, .. })) => {}
+        }
+        match claim.extension::<Provider>(ORDERING_PROVIDER) {
+            None => errors.push(error(ORDERING_PROVIDER, "durable medical equipment billed without an ordering provider".to_string())),
+            Some(Err(e)) => errors.push(error(ORDERING_PROVIDER, format!("unreadable ordering provider: {}", e))),
+            Some(Ok(provider)) if !validation::is_valid_npi(&provider.npi) => {
+                errors.push(error(ORDERING_PROVIDER, format!("invalid NPI {}", provider.npi)))
+            }
+            Some(Ok(_)) => {}
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::mock_claim;
+    use serde_json::json;
+
+    /// Test that specialty segments are required and checked only for lines in their ranges.
+    /// Expected: An office visit passes with unknown extensions; ambulance mileage must match its segment's miles, and a DME
+    /// rental needs its months and a valid ordering provider.
+    #[test]
+    fn test_specialty_segments() {
+        let mut claim = mock_claim();
+        claim.extensions.insert("vision".to_string(), json!({"lens": "bifocal"}));
+        assert!(validate(&claim).is_empty());
+
+        let mut ambulance = mock_claim();
+        ambulance.service_lines[0].procedure_code = AMBULANCE_MILEAGE_CODE.to_string();
+        ambulance.service_lines[0].units = 12;
+        assert_eq!(validate(&ambulance)[0].pointer, "/extensions/ambulance");
+        ambulance.extensions.insert(AMBULANCE.to_string(), json!({"miles": 11.2}));
+        assert!(validate(&ambulance).is_empty());
+        ambulance.service_lines[0].units = 11;
+        assert_eq!(validate(&ambulance)[0].pointer, "/service_lines/0/units");
+
+        let mut dme = mock_claim();
+        dme.service_lines[0].procedure_code = "e0601".to_string();
+        dme.extensions.insert(DME.to_string(), json!({"acquisition": "rental"}));
+        let pointers: Vec<String> = validate(&dme).into_iter().map(|error| error.pointer).collect();
+        assert_eq!(pointers, ["/extensions/dme", "/extensions/ordering_provider"]);
+        dme.extensions.insert(DME.to_string(), json!({"acquisition": "rental", "rental_months": 3}));
+        dme.extensions.insert(ORDERING_PROVIDER.to_string(), serde_json::to_value(&dme.rendering_provider).unwrap());
+        assert!(validate(&dme).is_empty());
+    }
+}
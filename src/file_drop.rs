@@ -0,0 +1,622 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, sleep, sleep_until};
+
+use crate::functional_ack::FunctionalAck;
+use crate::ids::ClaimId;
+use crate::message::{ClaimEnvelope, ClaimMessage, ClaimMetadata, FrontEndVerdict, RemittanceMessage};
+use crate::phi::Deidentification;
+use crate::schema::{Attachment, PayerClaim};
+
+/// Directory under a tenant's drop area that its biller writes claim batches to
+pub const OUTBOUND_DIR: &str = "outbound";
+
+/// Directory under a tenant's drop area that the clearinghouse writes remittance files to
+pub const INBOUND_DIR: &str = "inbound";
+
+/// Directory under `OUTBOUND_DIR` and `INBOUND_DIR` that files are moved to once picked up
+pub const ARCHIVE_DIR: &str = "archive";
+
+/// Time between scans of a drop directory (default for `--file-drop-poll`)
+pub const DEFAULT_DROP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Most messages written to one file
+pub const DEFAULT_DROP_BATCH_SIZE: usize = 100;
+
+/// Files of a batch still being written end in this, so the side polling skips them
+const PARTIAL_SUFFIX: &str = ".part";
+
+/// Where billers and the clearinghouse exchange files when claims are submitted by file drop
+///
+/// Each tenant gets `<dir>/<tenant>/outbound` for claim batches and `<dir>/<tenant>/inbound` for
+/// remittance files, as an SFTP mailbox would have
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDropSettings {
+    pub dir: String,
+    /// Time between scans by the side picking files up, and longest a message waits to be
+    /// written in a partial batch
+    pub poll_interval: Duration,
+    pub batch_size: usize,
+}
+
+impl FileDropSettings {
+    pub fn new(dir: impl Into<String>) -> Self {
+        Self {
+            dir: dir.into(),
+            poll_interval: DEFAULT_DROP_POLL_INTERVAL,
+            batch_size: DEFAULT_DROP_BATCH_SIZE,
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+}
+
+/// One line of a claim batch file; the tenant is the one whose mailbox it was dropped in
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BatchLine {
+    Claim { claim: Arc<PayerClaim> },
+    Cancel { claim_id: ClaimId },
+    Attachments { claim_id: ClaimId, attachments: Vec<Attachment> },
+    Resubmit { claim_id: ClaimId },
+}
+
+/// One line of a remittance file, naming the claim it answers since a duplicate's remittance
+/// carries the original claim's id
+#[derive(Debug, Serialize, Deserialize)]
+struct RemittanceLine {
+    claim_id: ClaimId,
+    message: RemittanceMessage,
+}
+
+/// What a claim leaves behind in memory when it goes out as a file: the biller's channel for
+/// its remittance, and its tracing metadata and the claim as billed until the clearinghouse
+/// picks it up
+struct Submitted {
+    response_tx: Sender<RemittanceMessage>,
+    metadata: Option<ClaimMetadata>,
+    /// Kept when the batch carries the claim de-identified
+    billed: Option<Arc<PayerClaim>>,
+}
+
+type InFlight = Arc<Mutex<HashMap<ClaimId, Submitted>>>;
+
+/// Relay a tenant's traffic with the clearinghouse through files, returning the sender its
+/// biller submits to in place of the clearinghouse's
+///
+/// The biller's messages are written to claim batches in `OUTBOUND_DIR`, which the
+/// clearinghouse side polls for and sends on to `claim_tx`. Its answers are written to
+/// remittance files in `INBOUND_DIR`, which the biller side polls for in turn. Picked-up files
+/// are moved to `ARCHIVE_DIR` as they are taken, so the directories record every exchange, and
+/// each claim batch's 999 is written beside it there as `<batch>.999`
+///
+/// Under `deidentification`, the biller's batches carry its claims de-identified, the claims as
+/// billed passing to the clearinghouse side in memory, and claims dropped by anyone else are
+/// de-identified in the archived copy, so no claim is left on disk as billed
+pub fn spawn(
+    settings: &FileDropSettings,
+    deidentification: &Deidentification,
+    tenant_id: &str,
+    claim_tx: Sender<ClaimMessage>,
+    capacity: usize,
+    tasks: &mut Vec<JoinHandle<()>>,
+) -> anyhow::Result<Sender<ClaimMessage>> {
+    let mailbox = Path::new(&settings.dir).join(tenant_id);
+    let (outbound, inbound) = (mailbox.join(OUTBOUND_DIR), mailbox.join(INBOUND_DIR));
+    for dir in [&outbound, &inbound] {
+        let archive = dir.join(ARCHIVE_DIR);
+        std::fs::create_dir_all(&archive).with_context(|| format!("creating drop directory {}", archive.display()))?;
+    }
+    let in_flight = InFlight::default();
+    let (submit_tx, submit_rx) = mpsc::channel(capacity);
+    let (remit_tx, remit_rx) = mpsc::channel(capacity);
+
+    let submitted = in_flight.clone();
+    let written_as = deidentification.clone();
+    tasks.push(tokio::spawn(write_batches(submit_rx, outbound.clone(), "claims", settings.clone(), move |message| {
+        match message {
+            ClaimMessage::NewClaim(envelope) => {
+                let ClaimEnvelope { claim, response_tx, metadata, .. } = *envelope;
+                let (claim, billed) = match written_as.is_off() {
+                    true => (claim, None),
+                    false => (Arc::new(deidentified(&claim, &written_as)), Some(claim)),
+                };
+                let submitted_claim = Submitted { response_tx, metadata: Some(metadata), billed };
+                submitted.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).insert(claim.claim_id.clone(), submitted_claim);
+                BatchLine::Claim { claim }
+            }
+            ClaimMessage::Cancel { claim_id, .. } => BatchLine::Cancel { claim_id },
+            ClaimMessage::Attachments { claim_id, attachments, .. } => BatchLine::Attachments { claim_id, attachments },
+            ClaimMessage::Resubmit { claim_id, .. } => BatchLine::Resubmit { claim_id },
+        }
+    })));
+    tasks.push(tokio::spawn(ingest_claims(
+        outbound,
+        tenant_id.to_string(),
+        claim_tx,
+        remit_tx,
+        in_flight.clone(),
+        deidentification.clone(),
+        settings.poll_interval,
+    )));
+    tasks.push(tokio::spawn(write_batches(remit_rx, inbound.clone(), "remittances", settings.clone(), |(claim_id, message)| {
+        RemittanceLine { claim_id, message }
+    })));
+    tasks.push(tokio::spawn(pick_up_remittances(inbound, in_flight, settings.poll_interval)));
+    Ok(submit_tx)
+}
+
+/// Write messages from `rx` as JSONL files in `dir`, `settings.batch_size` to a file, writing a
+/// partial batch once its first message has waited `settings.poll_interval`
+///
+/// Files are named `<prefix>-<start>-<n>.jsonl` so they sort in the order written, and appear
+/// under that name only once complete
+async fn write_batches<M, L: Serialize>(
+    mut rx: Receiver<M>,
+    dir: PathBuf,
+    prefix: &'static str,
+    settings: FileDropSettings,
+    mut to_line: impl FnMut(M) -> L,
+) {
+    let started = chrono::Utc::now().format("%Y%m%dT%H%M%S").to_string();
+    let mut written = 0u64;
+    let mut batch = String::new();
+    let mut lines = 0;
+    let mut deadline: Option<Instant> = None;
+    loop {
+        // `None` once the partial batch's wait is up, `Some(None)` once the sender is gone
+        let received = match deadline {
+            Some(at) => tokio::select! {
+                message = rx.recv() => Some(message),
+                _ = sleep_until(at) => None,
+            },
+            None => Some(rx.recv().await),
+        };
+        let closed = matches!(received, Some(None));
+        if let Some(Some(message)) = received {
+            match serde_json::to_string(&to_line(message)) {
+                Ok(line) => {
+                    batch.push_str(&line);
+                    batch.push('\n');
+                    lines += 1;
+                }
+                Err(e) => eprintln!("Failed to write a {} line: {}", prefix, e),
+            }
+            deadline.get_or_insert(Instant::now() + settings.poll_interval);
+        }
+        let due = lines >= settings.batch_size || deadline.is_some_and(|at| Instant::now() >= at) || closed;
+        if due && lines > 0 {
+            written += 1;
+            let path = dir.join(format!("{}-{}-{:06}.jsonl", prefix, started, written));
+            match write_file(&path, &batch).await {
+                Ok(()) => {
+                    batch.clear();
+                    lines = 0;
+                    deadline = None;
+                }
+                // the batch is kept and tried again after another wait
+                Err(e) => {
+                    eprintln!("Failed to write {}: {:#}", path.display(), e);
+                    deadline = Some(Instant::now() + settings.poll_interval);
+                }
+            }
+        }
+        if closed {
+            break;
+        }
+    }
+}
+
+/// Write `contents` under a partial name, then rename it, so a reader never sees half a file
+async fn write_file(path: &Path, contents: &str) -> anyhow::Result<()> {
+    let partial = path.with_extension(format!("jsonl{}", PARTIAL_SUFFIX));
+    tokio::fs::write(&partial, contents).await?;
+    tokio::fs::rename(&partial, path).await?;
+    Ok(())
+}
+
+/// Complete JSONL files waiting in `dir`, in name order
+async fn waiting_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return files;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "jsonl") && entry.file_type().await.is_ok_and(|kind| kind.is_file()) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Lines of a picked-up file, skipping those that do not parse
+async fn read_file<L: DeserializeOwned>(path: &Path) -> Vec<L> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(contents) => parse_lines(path, &contents),
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+fn parse_lines<L: DeserializeOwned>(path: &Path, contents: &str) -> Vec<L> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| eprintln!("Skipping {}:{}: {}", path.display(), index + 1, e))
+                .ok()
+        })
+        .collect()
+}
+
+/// Take a waiting file by moving it into the archive beside it, returning where it now is
+///
+/// Moving it first means a file is picked up once, even if handling its lines is cut short
+async fn pick_up(path: &Path) -> Option<PathBuf> {
+    let archived = path.parent()?.join(ARCHIVE_DIR).join(path.file_name()?);
+    match tokio::fs::rename(path, &archived).await {
+        Ok(()) => Some(archived),
+        Err(e) => {
+            eprintln!("Failed to pick up {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Clearinghouse side: pick up claim batches and submit their messages to the clearinghouse
+///
+/// Each claim's answers come back on a channel of its own, forwarded to the remittance file
+/// writer with the claim's id. Each batch with claims is answered with a 999 beside it in the
+/// archive once the front end has ruled on every claim
+async fn ingest_claims(
+    outbound: PathBuf,
+    tenant_id: String,
+    claim_tx: Sender<ClaimMessage>,
+    remit_tx: Sender<(ClaimId, RemittanceMessage)>,
+    in_flight: InFlight,
+    deidentification: Deidentification,
+    poll_interval: Duration,
+) {
+    let mut control_number = 0;
+    loop {
+        for path in waiting_files(&outbound).await {
+            let Some((path, lines)) = pick_up_batch(&path, &in_flight, &deidentification).await else {
+                continue;
+            };
+            let claims = lines.iter().filter(|line| matches!(line, BatchLine::Claim { .. })).count();
+            let (verdict_tx, verdict_rx) = mpsc::channel(claims.max(1));
+            let mut claim_ids = Vec::with_capacity(claims);
+            for line in lines {
+                let tenant_id = tenant_id.clone();
+                let message = match line {
+                    BatchLine::Claim { claim } => {
+                        // claims dropped by someone other than the biller have no metadata yet
+                        let (metadata, billed) = match in_flight.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get_mut(&claim.claim_id) {
+                            Some(submitted) => (submitted.metadata.take(), submitted.billed.take()),
+                            None => (None, None),
+                        };
+                        let (mut metadata, claim) = (metadata.unwrap_or_default(), billed.unwrap_or(claim));
+                        metadata.verdict_tx = Some(verdict_tx.clone());
+                        claim_ids.push(claim.claim_id.clone());
+                        let (response_tx, response_rx) = mpsc::channel(1);
+                        tokio::spawn(forward_answers(claim.claim_id.clone(), response_rx, remit_tx.clone()));
+                        ClaimMessage::NewClaim(Box::new(ClaimEnvelope { claim, response_tx, tenant_id, metadata }))
+                    }
+                    BatchLine::Cancel { claim_id } => ClaimMessage::Cancel { claim_id, tenant_id },
+                    BatchLine::Attachments { claim_id, attachments } => ClaimMessage::Attachments { claim_id, tenant_id, attachments },
+                    BatchLine::Resubmit { claim_id } => ClaimMessage::Resubmit { claim_id, tenant_id },
+                };
+                if claim_tx.send(message).await.is_err() {
+                    return;
+                }
+            }
+            drop(verdict_tx);
+            if !claim_ids.is_empty() {
+                control_number += 1;
+                let ack = FunctionalAck { tenant_id: tenant_id.clone(), control_number, verdicts: Vec::new() };
+                tokio::spawn(acknowledge_batch(path.with_extension("999"), ack, claim_ids, verdict_rx));
+            }
+        }
+        sleep(poll_interval).await;
+    }
+}
+
+/// Take a waiting claim batch, returning where it is archived and its lines
+///
+/// Without de-identification the batch is moved into the archive beside it. Otherwise a copy
+/// with every claim the biller did not already de-identify is written to the archive under a
+/// partial name and renamed, and the batch is then removed, so the archive never holds a claim
+/// as billed. Either way the batch is gone before its lines are handled, so it is picked up
+/// once even if handling them is cut short
+async fn pick_up_batch(path: &Path, in_flight: &InFlight, deidentification: &Deidentification) -> Option<(PathBuf, Vec<BatchLine>)> {
+    if deidentification.is_off() {
+        let archived = pick_up(path).await?;
+        let lines = read_file(&archived).await;
+        return Some((archived, lines));
+    }
+    let archived = path.parent()?.join(ARCHIVE_DIR).join(path.file_name()?);
+    let contents = match tokio::fs::read_to_string(path).await {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    let mut copy = String::with_capacity(contents.len());
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        // lines that do not parse are masked whole or field by field, as rejects files are
+        let line = match serde_json::from_str::<BatchLine>(line) {
+            Ok(BatchLine::Claim { claim }) if !billed_kept(in_flight, &claim.claim_id) => {
+                let claim = Arc::new(deidentified(&claim, deidentification));
+                serde_json::to_string(&BatchLine::Claim { claim }).unwrap_or_else(|_| deidentification.line(line))
+            }
+            Ok(_) => line.to_string(),
+            Err(_) => deidentification.line(line),
+        };
+        copy.push_str(&line);
+        copy.push('\n');
+    }
+    if let Err(e) = write_file(&archived, &copy).await {
+        eprintln!("Failed to archive {}: {:#}", path.display(), e);
+        return None;
+    }
+    if let Err(e) = tokio::fs::remove_file(path).await {
+        eprintln!("Failed to pick up {}: {}", path.display(), e);
+        return None;
+    }
+    Some((archived, parse_lines(path, &contents)))
+}
+
+/// Whether the biller kept the claim as billed, having written it to its batch de-identified
+fn billed_kept(in_flight: &InFlight, claim_id: &ClaimId) -> bool {
+    let in_flight = in_flight.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    in_flight.get(claim_id).is_some_and(|submitted| submitted.billed.is_some())
+}
+
+fn deidentified(claim: &PayerClaim, deidentification: &Deidentification) -> PayerClaim {
+    let mut claim = claim.clone();
+    deidentification.claim(&mut claim);
+    claim
+}
+
+/// Write the 999 answering a batch once every claim in `claim_ids` has a verdict
+///
+/// A claim whose verdict never comes, because the clearinghouse stopped before ruling on it, is
+/// acknowledged as rejected
+async fn acknowledge_batch(
+    path: PathBuf,
+    mut ack: FunctionalAck,
+    claim_ids: Vec<ClaimId>,
+    mut verdict_rx: Receiver<(ClaimId, FrontEndVerdict)>,
+) {
+    let mut verdicts: Vec<Option<FrontEndVerdict>> = vec![None; claim_ids.len()];
+    let mut waiting = claim_ids.len();
+    while waiting > 0
+        && let Some((claim_id, verdict)) = verdict_rx.recv().await
+    {
+        // a batch may hold one claim id twice; verdicts come in the order the claims were sent
+        let unanswered = claim_ids.iter().zip(&verdicts).position(|(id, verdict)| *id == claim_id && verdict.is_none());
+        if let Some(index) = unanswered {
+            verdicts[index] = Some(verdict);
+            waiting -= 1;
+        }
+    }
+    ack.verdicts = claim_ids
+        .into_iter()
+        .zip(verdicts)
+        .map(|(claim_id, verdict)| {
+            let reason = "not ruled on before the clearinghouse stopped".to_string();
+            (claim_id, verdict.unwrap_or(FrontEndVerdict::Rejected { code: None, reason }))
+        })
+        .collect();
+    if let Err(e) = tokio::fs::write(&path, ack.to_x12(chrono::Utc::now())).await {
+        eprintln!("Failed to write {}: {}", path.display(), e);
+    }
+}
+
+async fn forward_answers(claim_id: ClaimId, mut rx: Receiver<RemittanceMessage>, remit_tx: Sender<(ClaimId, RemittanceMessage)>) {
+    while let Some(message) = rx.recv().await {
+        if remit_tx.send((claim_id.clone(), message)).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Biller side: pick up remittance files and hand each answer to the biller waiting on its claim
+async fn pick_up_remittances(inbound: PathBuf, in_flight: InFlight, poll_interval: Duration) {
+    loop {
+        for path in waiting_files(&inbound).await {
+            let Some(path) = pick_up(&path).await else {
+                continue;
+            };
+            for RemittanceLine { claim_id, message } in read_file(&path).await {
+                let response_tx = {
+                    let mut in_flight = in_flight.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    match message {
+                        // the remittance follows once the documents are sent, or the claim is adjudicated
+                        RemittanceMessage::AttachmentRequest { .. } | RemittanceMessage::Acknowledged { .. } => in_flight.get(&claim_id).map(|submitted| submitted.response_tx.clone()),
+                        _ => in_flight.remove(&claim_id).map(|submitted| submitted.response_tx),
+                    }
+                };
+                match response_tx {
+                    Some(response_tx) => {
+                        let _ = response_tx.send(message).await;
+                    }
+                    None => eprintln!("No claim {} waiting for the answer in {}", claim_id, path.display()),
+                }
+            }
+        }
+        sleep(poll_interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edits::EditRates;
+    use crate::simulation::{BillerSpec, PayerSpec, Simulation};
+    use crate::test_utils::ClaimBuilder;
+    use std::fs;
+    use tokio::time::timeout;
+
+    /// Test that a run submitting by file drop remits every claim through batch and remittance files.
+    /// Expected: All claims are remitted and posted; each direction's files end up archived, the claims in one batch
+    /// acknowledged by an accepting 999, and nothing is left waiting in either mailbox.
+    #[tokio::test]
+    async fn test_file_drop_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let claims: Vec<PayerClaim> = (0..3).map(|n| ClaimBuilder::new().claim_id(format!("drop-{}", n)).build()).collect();
+        let settings = FileDropSettings::new(dir.path().to_string_lossy()).with_poll_interval(Duration::from_millis(20));
+        let mut sim = Simulation::builder()
+            .payer(PayerSpec::new("medicare", 0, 0))
+            .biller(BillerSpec::new("acme").with_source(claims))
+            .file_drop(settings)
+            .build()
+            .unwrap();
+        timeout(Duration::from_secs(10), sim.wait()).await.unwrap();
+        assert_eq!(sim.metrics().await.remitted, 3);
+        assert!(sim.ledgers()["acme"].lock().await.entries().len() >= 3);
+
+        let files = |dir: PathBuf| fs::read_dir(dir).unwrap().filter(|entry| entry.as_ref().unwrap().path().is_file()).count();
+        let mailbox = dir.path().join("acme");
+        let archive = mailbox.join(OUTBOUND_DIR).join(ARCHIVE_DIR);
+        assert_eq!(files(archive.clone()), 2);
+        let batch = waiting_files(&archive).await.pop().unwrap();
+        let ack = fs::read_to_string(batch.with_extension("999")).unwrap();
+        assert!(ack.contains("AK9*A*3*3*3~"), "{}", ack);
+        assert!(files(mailbox.join(INBOUND_DIR).join(ARCHIVE_DIR)) >= 1);
+        assert_eq!(files(mailbox.join(OUTBOUND_DIR)) + files(mailbox.join(INBOUND_DIR)), 0);
+        sim.shutdown();
+    }
+
+    /// Test that a batch whose claims all fail a front-end edit is answered with a rejecting 999.
+    /// Expected: Both claims are rejected back to the biller; the 999 beside the archived batch rejects the group
+    /// and carries each claim's id and syntax edit code in its error segments.
+    #[tokio::test]
+    async fn test_file_drop_999_rejects() {
+        let dir = tempfile::tempdir().unwrap();
+        let claims: Vec<PayerClaim> = (0..2).map(|n| ClaimBuilder::new().claim_id(format!("bad-{}", n)).build()).collect();
+        let settings = FileDropSettings::new(dir.path().to_string_lossy()).with_poll_interval(Duration::from_millis(20));
+        let mut sim = Simulation::builder()
+            .payer(PayerSpec::new("medicare", 0, 0))
+            .biller(BillerSpec::new("acme").with_source(claims))
+            .edits(EditRates { syntax: 1.0, ..EditRates::default() })
+            .file_drop(settings)
+            .build()
+            .unwrap();
+        timeout(Duration::from_secs(10), sim.wait()).await.unwrap();
+        assert_eq!(sim.metrics().await.edit_rejected, 2);
+
+        let archive = dir.path().join("acme").join(OUTBOUND_DIR).join(ARCHIVE_DIR);
+        let batch = waiting_files(&archive).await.pop().unwrap();
+        let ack = fs::read_to_string(batch.with_extension("999")).unwrap();
+        assert!(ack.contains("AK9*R*2*2*0~"), "{}", ack);
+        for claim_id in ["bad-0", "bad-1"] {
+            assert!(ack.contains(&format!("CTX*CLM01:{}~", claim_id)), "{}", ack);
+        }
+        assert_eq!(ack.matches("IK4*1*1028*I12*SYN").count(), 2, "{}", ack);
+        sim.shutdown();
+    }
+
+    /// Test that a run redacting PHI leaves no patient demographics anywhere under the drop directory.
+    /// Expected: The biller's claims and a batch dropped by someone else are all remitted, and no file in the
+    /// mailbox, archived batches included, holds the patient's name or date of birth.
+    #[tokio::test]
+    async fn test_file_drop_redacts_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let claims: Vec<PayerClaim> = (0..2).map(|n| ClaimBuilder::new().claim_id(format!("phi-{}", n)).build()).collect();
+        let outbound = dir.path().join("acme").join(OUTBOUND_DIR);
+        fs::create_dir_all(&outbound).unwrap();
+        let dropped = BatchLine::Claim { claim: Arc::new(ClaimBuilder::new().claim_id("dropped").build()) };
+        fs::write(outbound.join("claims-0-000001.jsonl"), serde_json::to_string(&dropped).unwrap() + "\n").unwrap();
+        let settings = FileDropSettings::new(dir.path().to_string_lossy()).with_poll_interval(Duration::from_millis(20));
+        let mut sim = Simulation::builder()
+            .payer(PayerSpec::new("medicare", 0, 0))
+            .biller(BillerSpec::new("acme").with_source(claims))
+            .file_drop(settings)
+            .deidentification(Deidentification::redact())
+            .build()
+            .unwrap();
+        timeout(Duration::from_secs(10), sim.wait()).await.unwrap();
+        timeout(Duration::from_secs(10), async {
+            while sim.metrics().await.remitted < 3 {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        let mut dirs = vec![dir.path().to_path_buf()];
+        let mut checked = 0;
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let contents = fs::read_to_string(&path).unwrap();
+                for phi in ["Jane", "Doe", "1990-01-01", "pmid456"] {
+                    assert!(!contents.contains(phi), "{} holds {}", path.display(), phi);
+                }
+                checked += 1;
+            }
+        }
+        // both batches, their 999s, and at least one remittance file
+        assert!(checked >= 5, "{}", checked);
+        sim.shutdown();
+    }
+
+    /// Test that the biller writes its batches de-identified, keeping the claims as billed in memory.
+    /// Expected: The waiting batch holds the claim without the patient's name or date of birth.
+    #[tokio::test]
+    async fn test_file_drop_outbound_deidentified() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = FileDropSettings::new(dir.path().to_string_lossy()).with_poll_interval(Duration::from_secs(60)).with_batch_size(1);
+        let (claim_tx, _claim_rx) = mpsc::channel(1);
+        let mut tasks = Vec::new();
+        let submit_tx = spawn(&settings, &Deidentification::redact(), "acme", claim_tx, 1, &mut tasks).unwrap();
+        let (response_tx, _response_rx) = mpsc::channel(1);
+        let claim = Arc::new(ClaimBuilder::new().claim_id("billed").build());
+        let envelope = ClaimEnvelope { claim, response_tx, tenant_id: "acme".to_string(), metadata: ClaimMetadata::default() };
+        submit_tx.send(ClaimMessage::NewClaim(Box::new(envelope))).await.unwrap();
+
+        let outbound = dir.path().join("acme").join(OUTBOUND_DIR);
+        let batch = timeout(Duration::from_secs(10), async {
+            loop {
+                // the clearinghouse side may already have picked the batch up into the archive
+                if let Some(batch) = waiting_files(&outbound).await.pop().or(waiting_files(&outbound.join(ARCHIVE_DIR)).await.pop()) {
+                    return batch;
+                }
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let contents = fs::read_to_string(batch).unwrap();
+        assert!(contents.contains("billed") && !contents.contains("Jane") && !contents.contains("1990-01-01") && !contents.contains("pmid456"), "{}", contents);
+        tasks.iter().for_each(JoinHandle::abort);
+    }
+}
@@ -0,0 +1,156 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::clock::SimClock;
+use crate::ids::PayerId;
+use crate::message::ClaimStatus;
+use crate::report_engine::{HistoryTotals, LatencyStats};
+
+/// Cash a payer is expected to remit on its open claims within the forecast horizon
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PayerForecast {
+    pub payer_id: PayerId,
+    pub open_claims: u32,
+    /// Billed on the open claims
+    pub open_ar: f64,
+    pub expected: f64,
+    /// Open claims with no remittance history at all to forecast from, left out of `expected`
+    pub unforecast: u32,
+}
+
+/// What remitted claims show about how much a payer pays and how long it takes
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PaymentHistory {
+    billed: f64,
+    paid: f64,
+    /// Submission to remittance
+    turnaround: LatencyStats,
+}
+
+impl PaymentHistory {
+    /// Add (`sign` 1) or take out (-1) a remitted claim
+    pub(crate) fn tally(&mut self, billed: f64, paid: f64, turnaround: Duration, sign: i32) {
+        self.billed += sign as f64 * billed;
+        self.paid += sign as f64 * paid;
+        self.turnaround.tally(turnaround, sign);
+    }
+
+    fn merge(&mut self, other: &PaymentHistory) {
+        self.billed += other.billed;
+        self.paid += other.paid;
+        self.turnaround.merge(&other.turnaround);
+    }
+
+    fn paid_ratio(&self) -> f64 {
+        if self.billed > 0.0 { self.paid / self.billed } else { 0.0 }
+    }
+
+    /// Chance a claim open for `age` simulated days is remitted within `horizon` more days
+    ///
+    /// Of the past claims still open at that age, the share remitted within the horizon; a claim
+    /// older than any past one is already overdue and expected within the horizon
+    fn remit_chance(&self, age: f64, horizon: f64, clock: &SimClock) -> f64 {
+        let still_open = self.turnaround.count_where(|elapsed| clock.days(elapsed) > age);
+        if still_open == 0 {
+            return 1.0;
+        }
+        let remitted = self.turnaround.count_where(|elapsed| {
+            let days = clock.days(elapsed);
+            days > age && days <= age + horizon
+        });
+        remitted as f64 / still_open as f64
+    }
+}
+
+/// Expected payer cash over the next `horizon_days` simulated days from the claims open at `now`, per payer
+///
+/// Each open claim is expected to pay its charge times the share of billed charges its payer
+/// has paid so far, weighted by the chance its payer's past turnaround puts its remittance inside
+/// the horizon. Payers with no remitted claims yet are forecast from every payer's history.
+/// Patient payments are not forecast
+pub fn forecast_collections(totals: &HistoryTotals, clock: &SimClock, now: Instant, horizon_days: f64) -> Vec<PayerForecast> {
+    let history = totals.payment_histories();
+    let mut overall = PaymentHistory::default();
+    for payer in history.values() {
+        overall.merge(payer);
+    }
+
+    let mut forecasts: BTreeMap<PayerId, PayerForecast> = BTreeMap::new();
+    for (_, status) in totals.pending() {
+        let ClaimStatus::Submitted { claim, submitted_at, .. } = status else {
+            continue;
+        };
+        let payer_id = &claim.insurance.payer_id;
+        let forecast = forecasts.entry(payer_id.clone()).or_insert_with(|| PayerForecast {
+            payer_id: payer_id.clone(),
+            ..PayerForecast::default()
+        });
+        let charge = claim.total_charge();
+        forecast.open_claims += 1;
+        forecast.open_ar += charge;
+        let basis = history.get(payer_id).unwrap_or(&overall);
+        if basis.turnaround.is_empty() {
+            forecast.unforecast += 1;
+            continue;
+        }
+        let age = clock.days(now.saturating_duration_since(*submitted_at));
+        forecast.expected += charge * basis.paid_ratio() * basis.remit_chance(age, horizon_days, clock);
+    }
+    forecasts.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::ClaimMetadata;
+    use crate::remittance::RemittanceRecord;
+    use crate::schema::PayerClaim;
+    use crate::ids::ClaimKey;
+    use crate::tenant::DEFAULT_TENANT;
+    use crate::test_utils::{mock_claim, mock_remittance};
+    use std::collections::HashMap;
+
+    /// Test that open claims are forecast from their payer's paid share and turnaround.
+    /// Expected: Half of medicare's past claims took longer than the horizon, so an open medicare claim is expected at half its paid share; anthem, with no history of its own, uses medicare's; nothing is forecast without any history.
+    #[test]
+    fn test_forecast_collections() {
+        let now = Instant::now();
+        let clock = SimClock::new(chrono::Utc::now().date_naive(), 1.0);
+        let claim = |claim_id: &str, payer_id: &str| {
+            let mut claim = PayerClaim { claim_id: claim_id.into(), ..mock_claim() };
+            claim.insurance.payer_id = PayerId::from(payer_id);
+            claim
+        };
+        let remitted = |claim_id: &str, days: u64| {
+            let submitted_at = now - Duration::from_secs(10);
+            let record = RemittanceRecord::new(claim(claim_id, "medicare"), mock_remittance(), submitted_at, submitted_at + Duration::from_secs(days));
+            let status = ClaimStatus::Remitted(record);
+            (status.key(), status)
+        };
+        let open = |claim_id: &str, payer_id: &str| {
+            let status = ClaimStatus::Submitted {
+                claim: claim(claim_id, payer_id).into(),
+                tenant_id: DEFAULT_TENANT.to_string(),
+                submitted_at: now,
+                metadata: ClaimMetadata::default(),
+            };
+            (status.key(), status)
+        };
+
+        let mut records: HashMap<ClaimKey, ClaimStatus> = [remitted("a", 1), remitted("b", 5), open("c", "medicare"), open("d", "anthem")].into();
+        let forecast = forecast_collections(&HistoryTotals::from_records(&records), &clock, now, 2.0);
+        let paid_share = mock_remittance().payer_paid() / mock_claim().total_charge();
+        let expected = mock_claim().total_charge() * paid_share * 0.5;
+        assert!(expected > 0.0);
+        assert_eq!(forecast.iter().map(|payer| payer.payer_id.as_str()).collect::<Vec<_>>(), vec!["anthem", "medicare"]);
+        for payer in &forecast {
+            assert_eq!((payer.open_claims, payer.unforecast), (1, 0));
+            assert!((payer.expected - expected).abs() < 1e-9, "{:?}", payer);
+        }
+
+        records.retain(|_, status| matches!(status, ClaimStatus::Submitted { .. }));
+        let forecast = forecast_collections(&HistoryTotals::from_records(&records), &clock, now, 2.0);
+        assert!(forecast.iter().all(|payer| payer.unforecast == 1 && payer.expected == 0.0));
+    }
+}
@@ -0,0 +1,140 @@
+use chrono::{DateTime, Utc};
+
+use crate::ids::ClaimId;
+use crate::message::FrontEndVerdict;
+
+/// Interchange id the clearinghouse sends acknowledgments under
+const CLEARINGHOUSE_ID: &str = "HEALTHTECHSIM";
+
+/// Implementation guide of the 837 professional claims being acknowledged
+const CLAIM_GUIDE: &str = "005010X222A1";
+
+/// Implementation guide of the 999 itself
+const ACK_GUIDE: &str = "005010X231A1";
+
+/// A 999 implementation acknowledgment answering one claim batch
+///
+/// A batch is JSON rather than an 837, so it is acknowledged as one functional group with each
+/// claim its own transaction set, numbered by its place among the batch's claims. A rejected
+/// claim's errors point at its CLM segment and carry the failed front-end edit's code
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionalAck {
+    /// The submitter the acknowledgment goes back to
+    pub tenant_id: String,
+    /// Interchange and group control number, unique per tenant's run
+    pub control_number: u32,
+    /// Each claim of the batch and the front end's verdict on it, in batch order
+    pub verdicts: Vec<(ClaimId, FrontEndVerdict)>,
+}
+
+impl FunctionalAck {
+    pub fn accepted(&self) -> usize {
+        self.verdicts.iter().filter(|(_, verdict)| *verdict == FrontEndVerdict::Accepted).count()
+    }
+
+    /// AK9 code for the group: `A` all accepted, `R` all rejected, `P` partly accepted
+    pub fn group_code(&self) -> char {
+        match self.accepted() {
+            accepted if accepted == self.verdicts.len() => 'A',
+            0 => 'R',
+            _ => 'P',
+        }
+    }
+
+    /// The acknowledgment as X12 segments, one per line, dated `at`
+    pub fn to_x12(&self, at: DateTime<Utc>) -> String {
+        let receiver: String = element(&self.tenant_id).chars().take(15).collect();
+        let control = self.control_number;
+        let mut set = vec![
+            format!("ST*999*0001*{}", ACK_GUIDE),
+            format!("AK1*HC*{}*{}", control, CLAIM_GUIDE),
+        ];
+        for (index, (claim_id, verdict)) in self.verdicts.iter().enumerate() {
+            set.push(format!("AK2*837*{:04}*{}", index + 1, CLAIM_GUIDE));
+            match verdict {
+                FrontEndVerdict::Accepted => set.push("IK5*A".to_string()),
+                FrontEndVerdict::Rejected { code, .. } => {
+                    set.push("IK3*CLM*1**8".to_string());
+                    set.push(format!("CTX*CLM01:{}", element(claim_id.as_str())));
+                    if let Some(code) = code {
+                        set.push(format!("IK4*1*1028*I12*{}", element(code)));
+                    }
+                    set.push("IK5*R*I5".to_string());
+                }
+            }
+        }
+        let claims = self.verdicts.len();
+        set.push(format!("AK9*{}*{}*{}*{}", self.group_code(), claims, claims, self.accepted()));
+        set.push(format!("SE*{}*0001", set.len() + 1));
+
+        let mut segments = vec![
+            format!(
+                "ISA*00*          *00*          *ZZ*{:<15}*ZZ*{:<15}*{}*{}*^*00501*{:09}*0*P*:",
+                CLEARINGHOUSE_ID,
+                receiver,
+                at.format("%y%m%d"),
+                at.format("%H%M"),
+                control
+            ),
+            format!(
+                "GS*FA*{}*{}*{}*{}*{}*X*{}",
+                CLEARINGHOUSE_ID,
+                receiver,
+                at.format("%Y%m%d"),
+                at.format("%H%M"),
+                control,
+                ACK_GUIDE
+            ),
+        ];
+        segments.extend(set);
+        segments.push(format!("GE*1*{}", control));
+        segments.push(format!("IEA*1*{:09}", control));
+        segments.iter().map(|segment| format!("{}~\n", segment)).collect()
+    }
+}
+
+/// `value` with the 999's separators replaced, so it fits in one data element
+fn element(value: &str) -> String {
+    value.replace(['*', '~', '^', ':'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that a batch with an accepted claim and an edit-rejected claim is acknowledged as partly accepted.
+    /// Expected: AK9 reports P with 2 received and 1 accepted; only the rejected claim gets error segments,
+    /// naming its claim id and edit code; the SE count covers ST through SE.
+    #[test]
+    fn test_functional_ack() {
+        let ack = FunctionalAck {
+            tenant_id: "acme".to_string(),
+            control_number: 7,
+            verdicts: vec![
+                ("ok-1".into(), FrontEndVerdict::Accepted),
+                ("bad-1".into(), FrontEndVerdict::Rejected { code: Some("SYN002"), reason: "syntax edit SYN002: bad".to_string() }),
+            ],
+        };
+        let x12 = ack.to_x12(Utc::now());
+        let segments: Vec<&str> = x12.lines().map(|line| line.trim_end_matches('~')).collect();
+        assert!(segments[0].starts_with("ISA*00*"));
+        assert!(segments[0].contains("*ZZ*acme           *"));
+        let set = &segments[segments.iter().position(|s| s.starts_with("ST*")).unwrap()..segments.len() - 2];
+        assert_eq!(
+            &set[1..set.len() - 1],
+            [
+                "AK1*HC*7*005010X222A1",
+                "AK2*837*0001*005010X222A1",
+                "IK5*A",
+                "AK2*837*0002*005010X222A1",
+                "IK3*CLM*1**8",
+                "CTX*CLM01:bad-1",
+                "IK4*1*1028*I12*SYN002",
+                "IK5*R*I5",
+                "AK9*P*2*2*1",
+            ]
+        );
+        assert_eq!(set.last().unwrap(), &format!("SE*{}*0001", set.len()));
+        assert_eq!(segments[segments.len() - 2..], ["GE*1*7", "IEA*1*000000007"]);
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tonic::client::Grpc;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::{Channel, Endpoint};
+use tonic_prost::ProstCodec;
+
+use crate::ids::{ClaimId, PayerId};
+use crate::logging::log_claim_event;
+use crate::message::{PayerMessage, RemittanceMessage};
+use crate::remittance::Remittance;
+use crate::schema::PayerClaim;
+
+/// Messages of `proto/payer.proto`, written out by hand so building needs no `protoc`
+pub mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PayerRequest {
+        #[prost(oneof = "payer_request::Request", tags = "1, 2")]
+        pub request: Option<payer_request::Request>,
+    }
+
+    pub mod payer_request {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Request {
+            /// Claim to adjudicate, as JSON in the claim file format
+            #[prost(string, tag = "1")]
+            ClaimJson(String),
+            /// Claim the biller voided
+            #[prost(string, tag = "2")]
+            VoidClaimId(String),
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PayerRemittance {
+        #[prost(string, tag = "1")]
+        pub claim_id: String,
+        #[prost(string, tag = "2")]
+        pub remittance_json: String,
+    }
+}
+
+use proto::payer_request::Request;
+use proto::{PayerRemittance, PayerRequest};
+
+/// Fully qualified name of the gRPC service external payers implement
+pub const SERVICE_NAME: &str = "healthtechsim.payer.v1.PayerService";
+
+/// Path of the bidirectional `Adjudicate` stream
+const ADJUDICATE_PATH: &str = "/healthtechsim.payer.v1.PayerService/Adjudicate";
+
+/// Stands in for a simulated payer, relaying its claims to an external `PayerService` over gRPC
+///
+/// The clearinghouse routes to it exactly as to a simulated payer. Claims the external payer
+/// cannot be reached for, or has not answered when its stream ends, are denied so no biller
+/// waits forever. External payers do not request attachments; any the biller sends are dropped
+pub struct GrpcPayer {
+    payer_id: PayerId,
+    endpoint: String,
+    tx: Sender<RemittanceMessage>,
+    rx: Receiver<PayerMessage>,
+    verbose: bool,
+}
+
+impl GrpcPayer {
+    pub fn new(
+        payer_id: impl Into<PayerId>,
+        endpoint: impl Into<String>,
+        tx: Sender<RemittanceMessage>,
+        rx: Receiver<PayerMessage>,
+        verbose: bool,
+    ) -> Self {
+        Self {
+            payer_id: payer_id.into(),
+            endpoint: endpoint.into(),
+            tx,
+            rx,
+            verbose,
+        }
+    }
+
+    /// Relay claims until the clearinghouse closes the channel and every claim is answered
+    pub async fn run(mut self) {
+        let mut pending = HashMap::new();
+        if let Err(e) = self.relay(&mut pending).await {
+            eprintln!("External payer {} at {} failed: {:#}", self.payer_id, self.endpoint, e);
+        }
+        // deny what the external payer never answered, then anything still to come
+        for claim in pending.into_values() {
+            self.deny(&claim).await;
+        }
+        while let Some(msg) = self.rx.recv().await {
+            if let PayerMessage::Adjudicate(claim, _) = msg {
+                self.deny(&claim).await;
+            }
+        }
+    }
+
+    /// Stream claims to the external payer and its remittances back, until both sides are done
+    async fn relay(&mut self, pending: &mut HashMap<ClaimId, Arc<PayerClaim>>) -> anyhow::Result<()> {
+        let channel = Endpoint::from_shared(self.endpoint.clone())?.connect().await?;
+        let mut client: Grpc<Channel> = Grpc::new(channel);
+        client.ready().await?;
+        let (out_tx, out_rx) = mpsc::channel::<PayerRequest>(1);
+        let outbound = futures::stream::unfold(out_rx, |mut rx| async move { rx.recv().await.map(|request| (request, rx)) });
+        let mut responses = client
+            .streaming(
+                tonic::Request::new(outbound),
+                PathAndQuery::from_static(ADJUDICATE_PATH),
+                ProstCodec::<PayerRequest, PayerRemittance>::default(),
+            )
+            .await?
+            .into_inner();
+        if self.verbose {
+            println!("[payer {}] Connected to external payer at {}", self.payer_id, self.endpoint);
+        }
+        let mut out_tx = Some(out_tx);
+        while out_tx.is_some() || !pending.is_empty() {
+            tokio::select! {
+                msg = self.rx.recv(), if out_tx.is_some() => {
+                    let request = match msg {
+                        Some(PayerMessage::Adjudicate(claim, _)) => {
+                            let request = Request::ClaimJson(serde_json::to_string(&claim)?);
+                            pending.insert(claim.claim_id.clone(), claim);
+                            request
+                        }
+                        Some(PayerMessage::Void(claim_id)) => {
+                            pending.remove(&claim_id);
+                            Request::VoidClaimId(claim_id.to_string())
+                        }
+                        Some(PayerMessage::Attachments(claim_id, _)) => {
+                            eprintln!("External payer {} does not take attachments; dropped those for claim {}", self.payer_id, claim_id);
+                            continue;
+                        }
+                        None => {
+                            // closing the request stream tells the external payer no more claims are coming
+                            out_tx = None;
+                            continue;
+                        }
+                    };
+                    if let Some(sender) = &out_tx
+                        && sender.send(PayerRequest { request: Some(request) }).await.is_err()
+                    {
+                        return Err(anyhow::anyhow!("request stream closed"));
+                    }
+                }
+                response = responses.message() => match response? {
+                    Some(response) => self.remit(response, pending).await,
+                    None if pending.is_empty() => break,
+                    None => return Err(anyhow::anyhow!("stream ended with {} claims unanswered", pending.len())),
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Pass an external remittance on to the clearinghouse, if it answers a claim still pending
+    async fn remit(&self, response: PayerRemittance, pending: &mut HashMap<ClaimId, Arc<PayerClaim>>) {
+        let remittance = match serde_json::from_str::<Remittance>(&response.remittance_json) {
+            Ok(remittance) if *remittance.claim_id() == *response.claim_id => remittance,
+            Ok(remittance) => {
+                eprintln!("External payer {} answered claim {} with a remittance for {}", self.payer_id, response.claim_id, remittance.claim_id());
+                return;
+            }
+            Err(e) => {
+                eprintln!("External payer {} sent an unreadable remittance for claim {}: {}", self.payer_id, response.claim_id, e);
+                return;
+            }
+        };
+        if pending.remove(remittance.claim_id()).is_none() {
+            eprintln!("External payer {} remitted claim {}, which is not pending", self.payer_id, response.claim_id);
+            return;
+        }
+        if self.verbose {
+            log_claim_event("payer", remittance.claim_id(), "external_remittance", &format!("Remittance from external payer {}", self.payer_id));
+        }
+        let _ = self.tx.send(RemittanceMessage::Processed(remittance.with_adjudicated_at(Instant::now()))).await;
+    }
+
+    async fn deny(&self, claim: &PayerClaim) {
+        eprintln!("Denying claim {}: external payer {} did not answer", claim.claim_id, self.payer_id);
+        let remittance = Remittance::denied(claim).with_adjudicated_at(Instant::now());
+        let _ = self.tx.send(RemittanceMessage::Processed(remittance)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{mock_claim, mock_adjudication_date};
+    use std::convert::Infallible;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+    use tokio::time::timeout;
+    use tonic::body::Body;
+    use tonic::codegen::{BoxFuture, http};
+    use tonic::server::NamedService;
+    use tonic::transport::server::TcpIncoming;
+    use tonic::{Status, Streaming};
+
+    /// External payer that pays every claim in full, as `Remittance::from_claim` prices it
+    #[derive(Clone)]
+    struct PayingPayer;
+
+    impl NamedService for PayingPayer {
+        const NAME: &'static str = SERVICE_NAME;
+    }
+
+    impl tower::Service<http::Request<Body>> for PayingPayer {
+        type Response = http::Response<Body>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+            let adjudicate = tower::service_fn(|request: tonic::Request<Streaming<PayerRequest>>| async move {
+                let remittances = futures::stream::unfold(request.into_inner(), |mut requests| async move {
+                    while let Ok(Some(request)) = requests.message().await {
+                        if let Some(Request::ClaimJson(json)) = request.request {
+                            let claim: PayerClaim = serde_json::from_str(&json).unwrap();
+                            let remittance = PayerRemittance {
+                                claim_id: claim.claim_id.to_string(),
+                                remittance_json: serde_json::to_string(&Remittance::from_claim(&claim, mock_adjudication_date())).unwrap(),
+                            };
+                            return Some((Ok::<_, Status>(remittance), requests));
+                        }
+                    }
+                    None
+                });
+                Ok::<_, Status>(tonic::Response::new(Box::pin(remittances)))
+            });
+            Box::pin(async move {
+                let mut grpc = tonic::server::Grpc::new(ProstCodec::<PayerRemittance, PayerRequest>::default());
+                Ok(grpc.streaming(adjudicate, request).await)
+            })
+        }
+    }
+
+    /// Test that claims are adjudicated by an external gRPC payer, and denied when it cannot be reached.
+    /// Expected: The external payer's remittance pays the claim; with no server listening the claim is denied.
+    #[tokio::test]
+    async fn test_grpc_payer() {
+        let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = incoming.local_addr().unwrap();
+        tokio::spawn(tonic::transport::Server::builder().add_service(PayingPayer).serve_with_incoming(incoming));
+
+        let run = |endpoint: String| {
+            let (payer_tx, payer_rx) = mpsc::channel(4);
+            let (remit_tx, remit_rx) = mpsc::channel(4);
+            tokio::spawn(GrpcPayer::new("external", endpoint, remit_tx, payer_rx, false).run());
+            (payer_tx, remit_rx)
+        };
+        let claim = mock_claim();
+        let (payer_tx, mut remit_rx) = run(format!("http://{}", addr));
+        payer_tx.send(PayerMessage::Adjudicate(Arc::new(claim.clone()), tracing::Span::none())).await.unwrap();
+        match timeout(Duration::from_secs(5), remit_rx.recv()).await {
+            Ok(Some(RemittanceMessage::Processed(remittance))) => {
+                assert_eq!(remittance.claim_id(), &claim.claim_id);
+                assert_eq!(remittance.payer_paid(), Remittance::from_claim(&claim, mock_adjudication_date()).payer_paid());
+                assert!(remittance.adjudicated_at().is_some());
+            }
+            other => panic!("Expected the external payer's remittance, got {:?}", other),
+        }
+
+        let (payer_tx, mut remit_rx) = run("http://127.0.0.1:1".to_string());
+        payer_tx.send(PayerMessage::Adjudicate(Arc::new(claim.clone()), tracing::Span::none())).await.unwrap();
+        match timeout(Duration::from_secs(5), remit_rx.recv()).await {
+            Ok(Some(RemittanceMessage::Processed(remittance))) => assert!(remittance.is_denied()),
+            other => panic!("Expected a denial, got {:?}", other),
+        }
+    }
+}
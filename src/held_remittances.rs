@@ -0,0 +1,40 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+use crate::ids::ClaimId;
+
+/// A tenant's remittances held for review because they did not balance against their claims
+///
+/// Each waits, unposted, until a reviewer releases it; the biller then posts it as it would
+/// any other remittance. A remittance is released at most once.
+#[derive(Debug, Clone, Default)]
+pub struct HeldRemittances {
+    held: Arc<Mutex<BTreeMap<ClaimId, oneshot::Sender<()>>>>,
+}
+
+impl HeldRemittances {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hold a claim's remittance; the receiver fires once a reviewer releases it
+    pub fn hold(&self, claim_id: ClaimId) -> oneshot::Receiver<()> {
+        let (release_tx, release_rx) = oneshot::channel();
+        self.held.lock().unwrap().insert(claim_id, release_tx);
+        release_rx
+    }
+
+    /// Claims whose remittances are still held, in claim id order
+    pub fn claim_ids(&self) -> Vec<ClaimId> {
+        self.held.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Release a claim's held remittance for posting; false if none is held or its biller is gone
+    pub fn release(&self, claim_id: &ClaimId) -> bool {
+        match self.held.lock().unwrap().remove(claim_id) {
+            Some(release_tx) => release_tx.send(()).is_ok(),
+            None => false,
+        }
+    }
+}
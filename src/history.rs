@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError, error::TryRecvError};
+use tokio::sync::{Mutex, MutexGuard};
+
+use crate::claim_search::{ClaimPage, ClaimQuery};
+use crate::ids::ClaimKey;
+use crate::message::ClaimStatus;
+use crate::phi::Deidentification;
+use crate::report_engine::HistoryTotals;
+
+/// Updates a subscriber may fall behind by before it must resync from a snapshot
+pub const UPDATE_BUFFER: usize = 4096;
+
+/// A claim's new status, as published to history's subscribers
+pub type ClaimUpdate = (ClaimKey, ClaimStatus);
+
+/// Which of `shards` slices a claim id falls in, for state split the way history is
+pub(crate) fn shard_index(claim_id: &str, shards: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    claim_id.hash(&mut hasher);
+    (hasher.finish() % shards as u64) as usize
+}
+
+/// Claim status by tenant and claim id, split into shards by a hash of the claim id
+///
+/// Each clearinghouse shard owns one slice and is its only writer, so shards never
+/// wait on each other; readers lock one slice at a time. Every tenant's claims with
+/// the same id share a slice. Every change of status is also broadcast, so readers
+/// that follow the updates (see `LiveTotals`) need not lock the slices at all
+#[derive(Debug, Clone)]
+pub struct History {
+    shards: Arc<[Mutex<HashMap<ClaimKey, ClaimStatus>>]>,
+    updates: broadcast::Sender<ClaimUpdate>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl History {
+    /// Empty history split into `shards` slices (at least one)
+    pub fn new(shards: usize) -> Self {
+        Self {
+            shards: (0..shards.max(1)).map(|_| Mutex::default()).collect(),
+            updates: broadcast::channel(UPDATE_BUFFER).0,
+        }
+    }
+
+    /// History holding `records`, in a single shard
+    pub fn from_records(records: HashMap<ClaimKey, ClaimStatus>) -> Self {
+        Self {
+            shards: Arc::new([Mutex::new(records)]),
+            updates: broadcast::channel(UPDATE_BUFFER).0,
+        }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Shard a claim's status lives in; stable for the life of the process
+    pub fn shard_of(&self, claim_id: &str) -> usize {
+        shard_index(claim_id, self.shards.len())
+    }
+
+    /// Lock one shard's slice
+    pub async fn shard(&self, index: usize) -> MutexGuard<'_, HashMap<ClaimKey, ClaimStatus>> {
+        self.shards[index].lock().await
+    }
+
+    /// Copy of one claim's status
+    pub async fn get(&self, key: &ClaimKey) -> Option<ClaimStatus> {
+        self.shard(self.shard_of(&key.claim_id)).await.get(key).cloned()
+    }
+
+    pub async fn contains(&self, key: &ClaimKey) -> bool {
+        self.shard(self.shard_of(&key.claim_id)).await.contains_key(key)
+    }
+
+    /// Every tenant's claim with this id, by tenant id
+    pub async fn find(&self, claim_id: &str) -> Vec<(ClaimKey, ClaimStatus)> {
+        let mut found: Vec<(ClaimKey, ClaimStatus)> = self
+            .shard(self.shard_of(claim_id))
+            .await
+            .iter()
+            .filter(|(key, _)| key.claim_id == claim_id)
+            .map(|(key, status)| (key.clone(), status.clone()))
+            .collect();
+        found.sort_by(|(a, _), (b, _)| a.cmp(b));
+        found
+    }
+
+    /// Record a claim's status in its shard
+    pub async fn insert(&self, key: ClaimKey, status: ClaimStatus) {
+        let mut shard = self.shard(self.shard_of(&key.claim_id)).await;
+        self.publish(&key, &status);
+        shard.insert(key, status);
+    }
+
+    /// Tell subscribers a claim's status changed
+    ///
+    /// Writers that change a shard through its guard call this with the shard still
+    /// locked, so subscribers see each claim's updates in the order they were made
+    pub fn publish(&self, key: &ClaimKey, status: &ClaimStatus) {
+        if self.updates.receiver_count() > 0 {
+            // only fails when every subscriber has gone since the check
+            let _ = self.updates.send((key.clone(), status.clone()));
+        }
+    }
+
+    /// Every status change from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<ClaimUpdate> {
+        self.updates.subscribe()
+    }
+
+    /// Visit every claim, locking one shard at a time
+    pub async fn for_each(&self, mut visit: impl FnMut(&ClaimKey, &ClaimStatus)) {
+        for index in 0..self.shards.len() {
+            for (key, status) in self.shard(index).await.iter() {
+                visit(key, status);
+            }
+        }
+    }
+
+    /// Claims passing the query's filters, one page at a time in claim id order, patients named
+    /// as `deidentification` writes them
+    pub async fn search(&self, query: &ClaimQuery, deidentification: &Deidentification) -> ClaimPage {
+        let mut matches = Vec::new();
+        self.for_each(|key, status| matches.extend(query.matches(&key.claim_id, status, deidentification))).await;
+        query.page(matches)
+    }
+
+    /// Copy of every claim's status, for building reports without holding any lock
+    pub async fn snapshot(&self) -> HashMap<ClaimKey, ClaimStatus> {
+        let mut records = HashMap::new();
+        self.for_each(|key, status| {
+            records.insert(key.clone(), status.clone());
+        })
+        .await;
+        records
+    }
+}
+
+/// Report totals of claim history (see `HistoryTotals`) kept current from its updates, for
+/// readers that must not contend with the clearinghouse for the shard locks
+///
+/// Only claims still pending are kept whole. Only a reader that falls more than
+/// `UPDATE_BUFFER` updates behind locks history again, to take a fresh snapshot
+#[derive(Debug)]
+pub struct LiveTotals {
+    history: History,
+    updates: broadcast::Receiver<ClaimUpdate>,
+    totals: HistoryTotals,
+    /// Updates were missed, so the totals must be resynced before they are read
+    stale: bool,
+}
+
+impl LiveTotals {
+    /// Follow `history`, starting from totals of a snapshot of it
+    pub async fn new(history: History) -> Self {
+        // subscribe first, so nothing changed during the snapshot is missed
+        let updates = history.subscribe();
+        let totals = HistoryTotals::from_records(&history.snapshot().await);
+        Self { history, updates, totals, stale: false }
+    }
+
+    /// Wait for the next update and apply it; never returns once history is dropped
+    ///
+    /// Safe to cancel, e.g. in a `select!`, without losing an update
+    pub async fn follow(&mut self) {
+        match self.updates.recv().await {
+            Ok((key, status)) => self.totals.apply(key, status),
+            Err(RecvError::Lagged(_)) => self.stale = true,
+            Err(RecvError::Closed) => std::future::pending().await,
+        }
+    }
+
+    /// Totals as of now: updates not yet applied are applied first, and the totals are
+    /// recounted from a snapshot if any were missed
+    pub async fn totals(&mut self) -> &HistoryTotals {
+        loop {
+            match self.updates.try_recv() {
+                Ok((key, status)) => self.totals.apply(key, status),
+                Err(TryRecvError::Lagged(_)) => self.stale = true,
+                Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+            }
+        }
+        if self.stale {
+            self.updates = self.updates.resubscribe();
+            self.totals = HistoryTotals::from_records(&self.history.snapshot().await);
+            self.stale = false;
+        }
+        &self.totals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::ClaimMetadata;
+    use crate::test_utils::mock_claim;
+    use std::time::Instant;
+
+    fn submitted(tenant_id: &str, claim_id: &str) -> (ClaimKey, ClaimStatus) {
+        let mut claim = mock_claim();
+        claim.claim_id = claim_id.into();
+        let status = ClaimStatus::Submitted {
+            claim: claim.into(),
+            tenant_id: tenant_id.to_string(),
+            submitted_at: Instant::now(),
+            metadata: ClaimMetadata::default(),
+        };
+        (ClaimKey::new(tenant_id, claim_id), status)
+    }
+
+    /// Test that claims land in the shard their id hashes to and are all visible in a snapshot.
+    /// Expected: Each claim is found by id and in its own shard; the snapshot holds all of them.
+    #[tokio::test]
+    async fn test_sharded_history() {
+        let history = History::new(4);
+        for i in 0..20 {
+            let (key, status) = submitted("default", &format!("claim{}", i));
+            history.insert(key, status).await;
+        }
+        let key = ClaimKey::new("default", "claim7");
+        assert!(history.contains(&key).await);
+        let index = history.shard_of("claim7");
+        assert!(history.shard(index).await.contains_key(&key));
+        assert_eq!(history.snapshot().await.len(), 20);
+    }
+
+    /// Test that two tenants' claims with the same id are kept apart.
+    /// Expected: Both are in history under their own tenant, and `find` returns both in tenant order.
+    #[tokio::test]
+    async fn test_duplicate_claim_ids_across_tenants() {
+        let history = History::new(4);
+        for tenant_id in ["beta", "acme"] {
+            let (key, status) = submitted(tenant_id, "abc123");
+            history.insert(key, status).await;
+        }
+        assert_eq!(history.snapshot().await.len(), 2);
+        let tenants: Vec<String> = history.find("abc123").await.into_iter().map(|(key, _)| key.tenant_id).collect();
+        assert_eq!(tenants, ["acme", "beta"]);
+        assert_eq!(history.get(&ClaimKey::new("beta", "abc123")).await.map(|status| status.tenant_id().to_string()), Some("beta".to_string()));
+    }
+
+    /// Test that live totals follow history's updates and recount after falling too far behind.
+    /// Expected: The totals start from what history held, apply later inserts, and after missing updates
+    /// still count every claim.
+    #[tokio::test]
+    async fn test_live_totals_follow_updates() {
+        let history = History::new(4);
+        let (key, status) = submitted("default", "before");
+        history.insert(key, status).await;
+        let mut live = LiveTotals::new(history.clone()).await;
+        let (key, status) = submitted("default", "after");
+        history.insert(key.clone(), status).await;
+        live.follow().await;
+        assert!(live.totals.pending().any(|(pending, _)| *pending == key));
+
+        for i in 0..UPDATE_BUFFER + 10 {
+            let (key, status) = submitted("default", &format!("claim{}", i));
+            history.insert(key, status).await;
+        }
+        assert_eq!(live.totals().await.pending().count(), UPDATE_BUFFER + 12);
+    }
+}
@@ -0,0 +1,31 @@
+use sha2::{Digest, Sha256};
+
+use crate::schema::PayerClaim;
+
+/// SHA-256 of the claim's canonical JSON, hex encoded
+///
+/// Identical claims hash identically regardless of which run or file they came
+/// from, so a resubmission can be recognized without trusting the claim id alone
+pub(crate) fn content_hash(claim: &PayerClaim) -> String {
+    let json = serde_json::to_vec(claim).expect("claim serializes to JSON");
+    format!("{:x}", Sha256::digest(&json))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::mock_claim;
+
+    /// Test that equal claims share a hash and any content change alters it.
+    /// Expected: Same hash for clones; different hash after changing a charge.
+    #[test]
+    fn test_content_hash() {
+        let claim = mock_claim();
+        let hash = content_hash(&claim);
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, content_hash(&claim.clone()));
+        let mut changed = claim;
+        changed.service_lines[0].unit_charge_amount += 1.0;
+        assert_ne!(hash, content_hash(&changed));
+    }
+}
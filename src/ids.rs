@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::Deref;
+
+/// Declare a string identifier that cannot be mixed up with the other kinds
+///
+/// Each id serializes as a bare string, derefs to `&str` for display and logging,
+/// and borrows as `str` so maps keyed by it can be looked up with a `&str`
+macro_rules! string_id {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn new(id: impl Into<String>) -> Self {
+                Self(id.into())
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Borrow<str> for $name {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> Self {
+                Self(id.to_string())
+            }
+        }
+
+        impl From<&$name> for $name {
+            fn from(id: &$name) -> Self {
+                id.clone()
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+    };
+}
+
+string_id!(
+    /// Claim identifier assigned by the submitting provider
+    ClaimId
+);
+
+string_id!(
+    /// Insurance payer a claim is routed to
+    PayerId
+);
+
+string_id!(
+    /// National Provider Identifier of a billing or rendering provider
+    Npi
+);
+
+string_id!(
+    /// Patient's member id with their insurance payer
+    MemberId
+);
+
+/// A claim's identity within a run: the billing organization that submitted it and its claim id
+///
+/// Providers assign claim ids, so two organizations may submit claims with the same one.
+/// Each organization runs a single biller, so the tenant also names the biller
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ClaimKey {
+    pub tenant_id: String,
+    pub claim_id: ClaimId,
+}
+
+impl ClaimKey {
+    pub fn new(tenant_id: impl Into<String>, claim_id: impl Into<ClaimId>) -> Self {
+        Self { tenant_id: tenant_id.into(), claim_id: claim_id.into() }
+    }
+}
+
+impl fmt::Display for ClaimKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.tenant_id, self.claim_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Test that ids serialize as bare strings and key maps that can be queried by `&str`.
+    /// Expected: `"abc"` round-trips through serde; a map keyed by ClaimId finds the entry by string slice.
+    #[test]
+    fn test_id_serde_and_lookup() {
+        let id: ClaimId = serde_json::from_str("\"abc\"").unwrap();
+        assert_eq!(id, "abc");
+        assert_eq!(serde_json::to_string(&id).unwrap(), "\"abc\"");
+
+        let map = HashMap::from([(id.clone(), 1)]);
+        assert_eq!(map.get("abc"), Some(&1));
+        assert_eq!(id.to_string(), "abc");
+    }
+}
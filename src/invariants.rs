@@ -0,0 +1,295 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::history::History;
+use crate::ids::{ClaimId, ClaimKey};
+use crate::ledger::{AdjustmentReason, EntryKind, Ledger, LedgerEntry};
+use crate::message::ClaimStatus;
+use crate::posting::WriteOffRules;
+
+/// How long a claim may stay with the payer before it counts as lost
+pub const DEFAULT_MAX_PENDING: Duration = Duration::from_secs(600);
+
+/// How often a live run is checked
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Largest difference between a claim's charge and its postings treated as rounding
+const BALANCE_TOLERANCE: f64 = 1e-2;
+
+/// Property that must hold across the whole simulation at any point of a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Invariant {
+    /// Every submitted claim is remitted within the allowed time
+    Terminal,
+    /// A claim's postings account for its whole charge, and its patient pays no more than their share
+    Balanced,
+    /// No claim's remittance is posted more than once
+    SinglePosting,
+}
+
+impl fmt::Display for Invariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Invariant::Terminal => write!(f, "terminal"),
+            Invariant::Balanced => write!(f, "balanced"),
+            Invariant::SinglePosting => write!(f, "single_posting"),
+        }
+    }
+}
+
+/// One claim breaking an invariant
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub invariant: Invariant,
+    pub tenant_id: String,
+    pub claim_id: ClaimId,
+    pub detail: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] claim {} from {}: {}", self.invariant, self.claim_id, self.tenant_id, self.detail)
+    }
+}
+
+/// Checks simulation-wide invariants against claim history and the tenant ledgers
+///
+/// Claims whose remittance the payer flagged as unbalanced or the clearinghouse audit
+/// rejected are not checked for balance; those are already reported as exceptions
+#[derive(Debug, Clone)]
+pub struct InvariantChecker {
+    max_pending: Duration,
+    contractual_adjustments: bool,
+}
+
+impl Default for InvariantChecker {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_PENDING)
+    }
+}
+
+impl InvariantChecker {
+    pub fn new(max_pending: Duration) -> Self {
+        Self {
+            max_pending,
+            contractual_adjustments: true,
+        }
+    }
+
+    /// Match the billers' write-off rules; without contractual adjustments the
+    /// not-allowed amount is never posted, so charges are not expected to balance
+    pub fn with_write_off_rules(mut self, rules: &WriteOffRules) -> Self {
+        self.contractual_adjustments = rules.contractual_adjustments;
+        self
+    }
+
+    /// Violations in the current state of a running simulation
+    pub async fn check(&self, history: &History, ledgers: &BTreeMap<String, Arc<Mutex<Ledger>>>) -> Vec<Violation> {
+        self.check_with(history, ledgers, self.max_pending).await
+    }
+
+    /// Violations once every biller has all its remittances: any claim still pending is lost
+    pub async fn check_finished(&self, history: &History, ledgers: &BTreeMap<String, Arc<Mutex<Ledger>>>) -> Vec<Violation> {
+        self.check_with(history, ledgers, Duration::ZERO).await
+    }
+
+    async fn check_with(
+        &self,
+        history: &History,
+        ledgers: &BTreeMap<String, Arc<Mutex<Ledger>>>,
+        max_pending: Duration,
+    ) -> Vec<Violation> {
+        // ledgers first: history records a remittance before the biller posts it
+        let mut entries = Vec::new();
+        for (tenant_id, ledger) in ledgers {
+            entries.push((tenant_id, ledger.lock().await.entries().to_vec()));
+        }
+        let records = history.snapshot().await;
+        let mut violations = pending_violations(&records, Instant::now(), max_pending);
+        for (tenant_id, entries) in entries {
+            violations.extend(self.ledger_violations(&records, tenant_id, &entries));
+        }
+        violations
+    }
+
+    /// Violations in a recorded history and one tenant's ledger, with claims pending longer than `max_pending` at `now` lost
+    pub fn check_at(&self, records: &HashMap<ClaimKey, ClaimStatus>, tenant_id: &str, ledger: &Ledger, now: Instant) -> Vec<Violation> {
+        let mut violations = pending_violations(records, now, self.max_pending);
+        violations.extend(self.ledger_violations(records, tenant_id, ledger.entries()));
+        violations
+    }
+
+    /// Check every `interval` until an invariant breaks, returning what broke
+    pub async fn watch(
+        self,
+        history: History,
+        ledgers: BTreeMap<String, Arc<Mutex<Ledger>>>,
+        interval: Duration,
+    ) -> Vec<Violation> {
+        loop {
+            tokio::time::sleep(interval).await;
+            let violations = self.check(&history, &ledgers).await;
+            if !violations.is_empty() {
+                return violations;
+            }
+        }
+    }
+
+    fn ledger_violations(&self, records: &HashMap<ClaimKey, ClaimStatus>, tenant_id: &str, entries: &[LedgerEntry]) -> Vec<Violation> {
+        let mut postings: BTreeMap<&ClaimId, Postings> = BTreeMap::new();
+        for entry in entries {
+            postings.entry(&entry.claim_id).or_default().add(entry);
+        }
+        let mut violations = Vec::new();
+        for (claim_id, posted) in postings {
+            let violation = |invariant, detail| Violation {
+                invariant,
+                tenant_id: tenant_id.to_string(),
+                claim_id: claim_id.clone(),
+                detail,
+            };
+            if posted.charges > 1 {
+                violations.push(violation(Invariant::SinglePosting, format!("remittance posted {} times", posted.charges)));
+                continue;
+            }
+            // a replaced claim's postings net to zero, but what the patient paid on it stays as a credit
+            if posted.reversed {
+                continue;
+            }
+            if posted.patient_paid - posted.patient_share > BALANCE_TOLERANCE {
+                violations.push(violation(
+                    Invariant::Balanced,
+                    format!("patient paid {:.2} against a {:.2} share", posted.patient_paid, posted.patient_share),
+                ));
+            }
+            let flagged = match records.get(&ClaimKey::new(tenant_id, claim_id.clone())) {
+                Some(ClaimStatus::Remitted(record)) => {
+                    !record.remittance().balancing().is_balanced() || !record.discrepancies().is_empty()
+                }
+                _ => false,
+            };
+            if !self.contractual_adjustments || flagged {
+                continue;
+            }
+            let accounted = posted.payer_paid + posted.patient_share + posted.contractual;
+            if (posted.charge - accounted).abs() > BALANCE_TOLERANCE {
+                violations.push(violation(
+                    Invariant::Balanced,
+                    format!("charged {:.2} but posted {:.2}", posted.charge, accounted),
+                ));
+            }
+        }
+        violations
+    }
+}
+
+/// One claim's ledger postings, summed by kind
+#[derive(Debug, Default)]
+struct Postings {
+    charges: usize,
+    /// Offset by a replacement claim
+    reversed: bool,
+    charge: f64,
+    payer_paid: f64,
+    patient_share: f64,
+    patient_paid: f64,
+    contractual: f64,
+}
+
+impl Postings {
+    fn add(&mut self, entry: &LedgerEntry) {
+        match &entry.kind {
+            EntryKind::Charge if entry.amount < 0.0 => {
+                self.reversed = true;
+                self.charge += entry.amount;
+            }
+            EntryKind::Charge => {
+                self.charges += 1;
+                self.charge += entry.amount;
+            }
+            EntryKind::PayerPayment => self.payer_paid += entry.amount,
+            EntryKind::PatientResponsibility => self.patient_share += entry.amount,
+            EntryKind::PatientPayment => self.patient_paid += entry.amount,
+            EntryKind::Adjustment(AdjustmentReason::Contractual) => self.contractual += entry.amount,
+            EntryKind::Adjustment(_) => {}
+        }
+    }
+}
+
+/// Claims submitted more than `max_pending` before `now` and still not remitted
+fn pending_violations(records: &HashMap<ClaimKey, ClaimStatus>, now: Instant, max_pending: Duration) -> Vec<Violation> {
+    let mut violations: Vec<Violation> = records
+        .iter()
+        .filter_map(|(key, status)| match status {
+            ClaimStatus::Submitted { submitted_at, .. } => {
+                let pending = now.saturating_duration_since(*submitted_at);
+                (pending >= max_pending).then(|| Violation {
+                    invariant: Invariant::Terminal,
+                    tenant_id: key.tenant_id.clone(),
+                    claim_id: key.claim_id.clone(),
+                    detail: format!("still pending after {:.1}s", pending.as_secs_f64()),
+                })
+            }
+            ClaimStatus::Remitted(_) | ClaimStatus::Voided { .. } => None,
+        })
+        .collect();
+    violations.sort_by(|a, b| (&a.claim_id, &a.tenant_id).cmp(&(&b.claim_id, &b.tenant_id)));
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::ClaimMetadata;
+    use crate::posting::post_remittance;
+    use crate::remittance::{Remittance, RemittanceRecord};
+    use crate::tenant::DEFAULT_TENANT;
+    use crate::test_utils::{mock_claim, mock_adjudication_date};
+
+    /// Test that a posted claim passes and that lost claims, double postings, and overpayments are caught.
+    /// Expected: No violations for one remittance posted once; each broken invariant is reported by name.
+    #[test]
+    fn test_invariant_violations() {
+        let claim = mock_claim();
+        let remittance = Remittance::from_claim(&claim, mock_adjudication_date());
+        let now = Instant::now();
+        let mut records = HashMap::new();
+        records.insert(
+            ClaimKey::new(DEFAULT_TENANT, claim.claim_id.clone()),
+            ClaimStatus::Remitted(RemittanceRecord::new(claim.clone(), remittance.clone(), now, now)),
+        );
+        let mut ledger = Ledger::new();
+        post_remittance(&mut ledger, &claim, &remittance, &WriteOffRules::default());
+        let checker = InvariantChecker::new(Duration::from_secs(60));
+        assert!(checker.check_at(&records, DEFAULT_TENANT, &ledger, now).is_empty());
+
+        let mut lost = claim.clone();
+        lost.claim_id = "lost".into();
+        records.insert(
+            ClaimKey::new(DEFAULT_TENANT, lost.claim_id.clone()),
+            ClaimStatus::Submitted {
+                claim: lost.into(),
+                tenant_id: DEFAULT_TENANT.to_string(),
+                submitted_at: now,
+                metadata: ClaimMetadata::default(),
+            },
+        );
+        assert!(checker.check_at(&records, DEFAULT_TENANT, &ledger, now).is_empty());
+        let violations = checker.check_at(&records, DEFAULT_TENANT, &ledger, now + Duration::from_secs(61));
+        assert_eq!(violations[0].invariant, Invariant::Terminal);
+        assert_eq!(violations[0].claim_id, ClaimId::new("lost"));
+
+        post_remittance(&mut ledger, &claim, &remittance, &WriteOffRules::default());
+        let violations = checker.check_at(&records, DEFAULT_TENANT, &ledger, now);
+        assert_eq!(violations[0].invariant, Invariant::SinglePosting);
+
+        let mut ledger = Ledger::new();
+        post_remittance(&mut ledger, &claim, &remittance, &WriteOffRules::default());
+        ledger.post(LedgerEntry::new(claim.claim_id.clone(), "p", "medicare", EntryKind::PatientPayment, 1_000.0));
+        let violations = checker.check_at(&records, DEFAULT_TENANT, &ledger, now);
+        assert_eq!(violations[0].invariant, Invariant::Balanced);
+    }
+}
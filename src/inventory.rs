@@ -0,0 +1,224 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::history::shard_index;
+use crate::ids::ClaimKey;
+use crate::report_format::MoneyFields;
+
+/// Where a claim stands in the revenue cycle, in pipeline order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimState {
+    /// Taken in by the clearinghouse and waiting on its front-end edits
+    Scrubbing,
+    /// Accepted and with its payer
+    Submitted,
+    /// Held by its payer until the biller sends the documents requested
+    Pended,
+    /// Remitted with nothing paid
+    Denied,
+    /// Remitted with a payment
+    Paid,
+    /// Denied, and a billing worker has appealed the denial
+    Appealed,
+}
+
+impl ClaimState {
+    pub const ALL: [ClaimState; 6] = [
+        ClaimState::Scrubbing,
+        ClaimState::Submitted,
+        ClaimState::Pended,
+        ClaimState::Denied,
+        ClaimState::Paid,
+        ClaimState::Appealed,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl fmt::Display for ClaimState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ClaimState::Scrubbing => "scrubbing",
+            ClaimState::Submitted => "submitted",
+            ClaimState::Pended => "pended",
+            ClaimState::Denied => "denied",
+            ClaimState::Paid => "paid",
+            ClaimState::Appealed => "appealed",
+        })
+    }
+}
+
+/// Claims currently in one state and the dollars they billed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct StateInventory {
+    pub state: ClaimState,
+    pub claims: u32,
+    pub billed: f64,
+}
+
+impl MoneyFields for StateInventory {
+    fn map_money(&mut self, f: impl Fn(f64) -> f64) {
+        self.billed = f(self.billed);
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// State and billed amount of every claim in the pipeline
+    claims: HashMap<ClaimKey, (ClaimState, f64)>,
+    /// Claims and dollars per state, by `ClaimState::index`
+    totals: [(u32, f64); 6],
+}
+
+impl Inner {
+    fn set(&mut self, key: &ClaimKey, state: ClaimState, billed: f64) {
+        self.take(key);
+        self.claims.insert(key.clone(), (state, billed));
+        let total = &mut self.totals[state.index()];
+        total.0 += 1;
+        total.1 += billed;
+    }
+
+    fn take(&mut self, key: &ClaimKey) -> Option<(ClaimState, f64)> {
+        let (state, billed) = self.claims.remove(key)?;
+        let total = &mut self.totals[state.index()];
+        total.0 -= 1;
+        total.1 -= billed;
+        Some((state, billed))
+    }
+
+    fn state(&self, key: &ClaimKey) -> Option<ClaimState> {
+        self.claims.get(key).map(|(state, _)| *state)
+    }
+}
+
+/// Claim counts and billed dollars by lifecycle state, moved claim by claim as the
+/// clearinghouse sees each change, so reading it never scans claim history
+///
+/// Split into shards by claim id the way history is, so each clearinghouse shard moves only
+/// its own claims and never waits on another; reading sums the shards' totals. Rejected and
+/// voided claims leave the inventory
+#[derive(Debug, Clone)]
+pub struct ClaimInventory {
+    shards: Arc<[Mutex<Inner>]>,
+}
+
+impl Default for ClaimInventory {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl ClaimInventory {
+    /// Empty inventory split into `shards` slices (at least one)
+    pub fn new(shards: usize) -> Self {
+        Self { shards: (0..shards.max(1)).map(|_| Mutex::default()).collect() }
+    }
+
+    fn shard(&self, key: &ClaimKey) -> MutexGuard<'_, Inner> {
+        self.shards[shard_index(&key.claim_id, self.shards.len())].lock().unwrap()
+    }
+
+    /// Start a new claim off in scrubbing; a claim already in the pipeline, e.g. a
+    /// resubmission of it, stays where it is
+    pub fn scrub(&self, key: &ClaimKey, billed: f64) {
+        let mut inner = self.shard(key);
+        if inner.state(key).is_none() {
+            inner.set(key, ClaimState::Scrubbing, billed);
+        }
+    }
+
+    /// Drop a claim rejected before it was accepted
+    pub fn reject(&self, key: &ClaimKey) {
+        let mut inner = self.shard(key);
+        if inner.state(key) == Some(ClaimState::Scrubbing) {
+            inner.take(key);
+        }
+    }
+
+    /// Move a claim in the pipeline to `state`
+    pub fn advance(&self, key: &ClaimKey, state: ClaimState) {
+        let mut inner = self.shard(key);
+        if let Some((_, billed)) = inner.claims.get(key).copied() {
+            inner.set(key, state, billed);
+        }
+    }
+
+    /// Mark a denied claim appealed; claims in any other state are left alone
+    pub fn appeal(&self, key: &ClaimKey) {
+        let mut inner = self.shard(key);
+        if let Some((ClaimState::Denied, billed)) = inner.claims.get(key).copied() {
+            inner.set(key, ClaimState::Appealed, billed);
+        }
+    }
+
+    /// Drop a claim voided by its biller
+    pub fn remove(&self, key: &ClaimKey) {
+        self.shard(key).take(key);
+    }
+
+    pub fn state(&self, key: &ClaimKey) -> Option<ClaimState> {
+        self.shard(key).state(key)
+    }
+
+    /// Claims and billed dollars in every state, in pipeline order
+    pub fn counts(&self) -> Vec<StateInventory> {
+        let mut totals = [(0, 0.0); 6];
+        for shard in self.shards.iter() {
+            let inner = shard.lock().unwrap();
+            for (total, (claims, billed)) in totals.iter_mut().zip(inner.totals) {
+                total.0 += claims;
+                total.1 += billed;
+            }
+        }
+        ClaimState::ALL
+            .into_iter()
+            .map(|state| {
+                let (claims, billed) = totals[state.index()];
+                StateInventory { state, claims, billed }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that claims move between states and the totals follow them.
+    /// Expected: Scrubbed claims count until rejected or accepted; a pended claim returns to submitted; only a
+    /// denied claim can be appealed; a resubmission does not reset its claim; voided claims drop out; counts sum every shard.
+    #[test]
+    fn test_claim_inventory() {
+        let inventory = ClaimInventory::new(4);
+        let (a, b, c) = (ClaimKey::new("default", "a"), ClaimKey::new("default", "b"), ClaimKey::new("default", "c"));
+        inventory.scrub(&a, 100.0);
+        inventory.scrub(&b, 50.0);
+        inventory.scrub(&c, 25.0);
+        inventory.reject(&c);
+        inventory.advance(&a, ClaimState::Submitted);
+        inventory.advance(&b, ClaimState::Pended);
+        let rows = |inventory: &ClaimInventory| -> Vec<(ClaimState, u32, f64)> {
+            inventory.counts().iter().filter(|row| row.claims > 0).map(|row| (row.state, row.claims, row.billed)).collect()
+        };
+        assert_eq!(rows(&inventory), vec![(ClaimState::Submitted, 1, 100.0), (ClaimState::Pended, 1, 50.0)]);
+
+        inventory.advance(&b, ClaimState::Submitted);
+        inventory.advance(&a, ClaimState::Paid);
+        inventory.appeal(&a);
+        inventory.scrub(&a, 100.0);
+        inventory.advance(&b, ClaimState::Denied);
+        inventory.appeal(&b);
+        assert_eq!(rows(&inventory), vec![(ClaimState::Paid, 1, 100.0), (ClaimState::Appealed, 1, 50.0)]);
+        assert_eq!(inventory.counts().len(), ClaimState::ALL.len());
+
+        inventory.remove(&a);
+        inventory.advance(&c, ClaimState::Paid);
+        assert_eq!(rows(&inventory), vec![(ClaimState::Appealed, 1, 50.0)]);
+    }
+}
@@ -1,4 +1,11 @@
+use crate::claim_ids::ClaimIdGenerator;
+use crate::drg;
+use crate::ids::{MemberId, Npi, PayerId};
+use crate::payer_mix::PayerMix;
+use crate::place_of_service::PlaceOfService;
+use crate::roster::Roster;
 use crate::schema::PayerClaim;
+use crate::validation;
 use chrono::NaiveDate;
 use fake::faker::address::en::*;
 use fake::faker::boolean::en::*;
@@ -8,91 +15,235 @@ use fake::faker::lorem::en::Word;
 use fake::faker::name::en::*;
 use fake::faker::number::en::*;
 use fake::{Fake, Faker};
+use rand::Rng;
 use rand::seq::IndexedRandom;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{BufWriter, Write};
+use std::sync::Arc;
+
+/// Places of service drawn for fake claims, repeated to weight them toward the office
+const COMMON_PLACES_OF_SERVICE: [PlaceOfService; 10] = [
+    PlaceOfService::OFFICE,
+    PlaceOfService::OFFICE,
+    PlaceOfService::OFFICE,
+    PlaceOfService::OFFICE,
+    PlaceOfService::TELEHEALTH_HOME,
+    PlaceOfService::TELEHEALTH,
+    PlaceOfService::OUTPATIENT_HOSPITAL,
+    PlaceOfService::INPATIENT_HOSPITAL,
+    PlaceOfService::EMERGENCY_ROOM,
+    PlaceOfService::URGENT_CARE,
+];
+
+/// Modifier sets drawn for fake service lines, mostly none; `95` bills an audio-video visit from the office
+const MODIFIERS: [&[&str]; 11] = [&[], &[], &[], &[], &["25"], &["59"], &["26"], &["TC"], &["50"], &["51", "LT"], &["95"]];
+
+/// Share of fake claims whose patient is a dependent on someone else's policy
+const DEPENDENT_PERCENT: u8 = 30;
+
+/// NUCC taxonomy codes drawn for rendering providers: family, internal, and pediatric medicine,
+/// orthopaedic surgery, psychiatry, and family nurse practitioner
+const PROVIDER_TAXONOMIES: [&str; 6] = ["207Q00000X", "207R00000X", "208000000X", "207X00000X", "2084P0800X", "363LF0000X"];
+
+/// NUCC taxonomy codes drawn for billing organizations: primary care clinic, multi-specialty group, and acute care hospital
+const ORGANIZATION_TAXONOMIES: [&str; 3] = ["261QP2300X", "193200000X", "282N00000X"];
+
+/// Share of fake claims billed by a solo practitioner under their own NPI rather than by the organization
+const SOLO_PRACTITIONER_PERCENT: u8 = 15;
+
+/// Share of fake rendering providers seeing patients at a site other than the organization's address
+const SATELLITE_SITE_PERCENT: u8 = 30;
+
+/// NUCC taxonomy code of the acute care hospitals billing fake institutional claims
+const HOSPITAL_TAXONOMY: &str = "282N00000X";
+
+/// Type of bill of fake inpatient claims: a hospital stay billed admission through discharge
+const INPATIENT_BILL_TYPE: &str = "0111";
+
+/// Type of bill of fake hospital outpatient claims, admission through discharge
+const OUTPATIENT_BILL_TYPE: &str = "0131";
+
+/// Share of fake hospital outpatient visits billed by the hospital on its own institutional claim
+const OUTPATIENT_INSTITUTIONAL_PERCENT: u8 = 50;
+
+/// Revenue codes drawn for fake hospital outpatient lines: pharmacy, laboratory, radiology, CT, and clinic
+const OUTPATIENT_REVENUE_CODES: [&str; 5] = ["0250", "0300", "0320", "0350", "0510"];
+
+/// Common ICD-10-CM diagnoses drawn for fake claims
+const DIAGNOSES: [&str; 10] = ["I10", "E11.9", "J06.9", "M54.50", "Z00.00", "F41.1", "R51.9", "N39.0", "E78.5", "K21.9"];
 
 /// Generate a realistic fake healthcare claim for testing
 /// 
 /// Creates claims with random but valid patient, provider, and billing data
 /// Uses common payer IDs and realistic procedure codes
 pub fn fake_payer_claim() -> PayerClaim {
+    fake_payer_claim_with_ids(&ClaimIdGenerator::default())
+}
+
+/// Fake claim identified by `ids`, unique across the run
+pub fn fake_payer_claim_with_ids(ids: &ClaimIdGenerator) -> PayerClaim {
     use crate::schema::*;
     let mut rng = rand::rng();
-    PayerClaim {
-        claim_id: Faker.fake(),
-        place_of_service_code: NumberWithFormat("##")
-            .fake::<String>()
-            .parse()
-            .unwrap_or(11),
-        insurance: Insurance {
-            payer_id: ["medicare", "united_health_group", "anthem"]
-                .choose(&mut rng)
-                .unwrap()
-                .to_string(),
-            patient_member_id: Faker.fake(),
-        },
-        patient: Patient {
-            first_name: FirstName().fake(),
-            last_name: LastName().fake(),
-            gender: ["m", "f", "o"].choose(&mut rng).unwrap().to_string(),
+    let payer_id = ["medicare", "united_health_group", "anthem"].choose(&mut rng).copied().unwrap();
+    let line = ServiceLine {
+        units: (1..5).fake(),
+        modifiers: Some(MODIFIERS.choose(&mut rng).unwrap().iter().map(|m| m.to_string()).collect()),
+        do_not_bill: Some(Boolean(50).fake()),
+        ..ServiceLine::new(Faker.fake::<String>(), NumberWithFormat("#####").fake::<String>(), 1, (50.0..500.0).fake())
+            .with_details(format!("{} {}", Word().fake::<String>(), Word().fake::<String>()))
+    };
+    let builder = PayerClaim::builder()
+        .claim_id(ids.generate())
+        .place_of_service(*COMMON_PLACES_OF_SERVICE.choose(&mut rng).unwrap())
+        .payer(payer_id)
+        .member(MemberId::new(Faker.fake::<String>()))
+        .patient(Patient {
             dob: NaiveDate::from_ymd_opt(
                 *((1950..=2010).collect::<Vec<_>>().choose(&mut rng).unwrap()),
                 *((1..=12).collect::<Vec<_>>().choose(&mut rng).unwrap()),
                 *((1..=28).collect::<Vec<_>>().choose(&mut rng).unwrap()),
-            )
-            .unwrap()
-            .to_string(),
+            ),
             email: Some(FreeEmail().fake()),
-            address: Some(Address {
-                street: Some(StreetName().fake()),
-                city: Some(CityName().fake()),
-                state: Some(StateAbbr().fake()),
-                zip: Some(PostCode().fake()),
-                country: Some("USA".to_string()),
-            }),
-        },
-        organization: Organization {
-            name: CompanyName().fake(),
-            billing_npi: Some(NumberWithFormat("##########").fake()),
-            ein: Some(format!(
-                "{}-{}",
-                NumberWithFormat("##").fake::<String>(),
-                NumberWithFormat("######").fake::<String>()
-            )),
+            address: Some(fake_address()),
+            ..Patient::new(
+                FirstName().fake::<String>(),
+                LastName().fake::<String>(),
+                *[Gender::Male, Gender::Female, Gender::Other].choose(&mut rng).unwrap(),
+            )
+        })
+        .organization(Organization {
+            billing_npi: Some(fake_npi()),
+            ein: Some(fake_ein()),
             contact: Some(Contact {
                 first_name: Some(FirstName().fake()),
                 last_name: Some(LastName().fake()),
                 phone_number: Some(format!("555-{:04}", (0..10000).fake::<u16>())),
             }),
-            address: Some(Address {
-                street: Some(StreetName().fake()),
-                city: Some(CityName().fake()),
-                state: Some(StateAbbr().fake()),
-                zip: Some(PostCode().fake()),
-                country: Some("USA".to_string()),
-            }),
-        },
-        rendering_provider: Provider {
-            first_name: FirstName().fake(),
-            last_name: LastName().fake(),
-            npi: NumberWithFormat("##########").fake(),
-        },
-        service_lines: vec![ServiceLine {
-            service_line_id: Faker.fake(),
-            procedure_code: NumberWithFormat("#####").fake(),
-            units: (1..5).fake(),
-            details: format!("{} {}", Word().fake::<String>(), Word().fake::<String>()),
-            unit_charge_currency: "USD".to_string(),
-            unit_charge_amount: (50.0..500.0).fake(),
-            modifiers: Some(vec![
-                (0..2)
-                    .map(|_| Word().fake::<String>())
-                    .collect::<Vec<_>>()
-                    .join(""),
-            ]),
-            do_not_bill: Some(Boolean(50).fake()),
-        }],
+            address: Some(fake_address()),
+            taxonomy_code: ORGANIZATION_TAXONOMIES.choose(&mut rng).map(|code| code.to_string()),
+            ..Organization::new(CompanyName().fake::<String>())
+        })
+        .rendering_provider(Provider {
+            taxonomy_code: PROVIDER_TAXONOMIES.choose(&mut rng).map(|code| code.to_string()),
+            address: Boolean(SATELLITE_SITE_PERCENT).fake::<bool>().then(fake_address),
+            ..Provider::new(FirstName().fake::<String>(), LastName().fake::<String>(), fake_npi())
+        })
+        .line(line)
+        .urgent(Boolean(10).fake());
+    let mut claim = DIAGNOSES
+        .choose_multiple(&mut rng, (1..=3).fake())
+        .fold(builder, |builder, code| builder.diagnosis(*code))
+        .build()
+        .expect("fake claims are complete");
+    if claim.place_of_service_code == PlaceOfService::INPATIENT_HOSPITAL {
+        make_inpatient(&mut claim, &mut rng);
+    } else if claim.place_of_service_code == PlaceOfService::OUTPATIENT_HOSPITAL
+        && Boolean(OUTPATIENT_INSTITUTIONAL_PERCENT).fake()
+    {
+        make_outpatient(&mut claim, &mut rng);
+    } else if Boolean(SOLO_PRACTITIONER_PERCENT).fake() {
+        // the practitioner bills under their own NPI; the organization is only their practice name
+        claim.billing_provider = Some(claim.rendering_provider.clone());
+        claim.organization.billing_npi = None;
+    }
+    if Boolean(DEPENDENT_PERCENT).fake() {
+        make_dependent(&mut claim, &mut rng);
+    }
+    claim.total_charge_amount = Some(claim.total_charge());
+    claim
+}
+
+/// Bill an inpatient claim as the hospital's own UB-04 for the stay: a recent admission of up
+/// to a week for a principal diagnosis the payers' grouper places, coded with the DRG the
+/// hospital expects, with a room and board line per night
+fn make_inpatient(claim: &mut PayerClaim, rng: &mut impl rand::Rng) {
+    use crate::schema::{ClaimType, InstitutionalClaim};
+    use chrono::Days;
+    let nights = (1..=7).fake::<u32>();
+    let admission_date = chrono::Utc::now().date_naive() - Days::new((nights + (1..30).fake::<u32>()).into());
+    let principal = drg::groupable_diagnoses().collect::<Vec<_>>().choose(rng).unwrap().to_string();
+    claim.diagnosis_codes.insert(0, principal);
+    claim.claim_type = ClaimType::Institutional(InstitutionalClaim {
+        bill_type: INPATIENT_BILL_TYPE.to_string(),
+        admission_date,
+        discharge_date: Some(admission_date + Days::new(nights.into())),
+        drg: drg::group(claim).map(String::from),
+    });
+    claim.organization.taxonomy_code = Some(HOSPITAL_TAXONOMY.to_string());
+    for line in &mut claim.service_lines {
+        line.revenue_code = Some("0120".to_string());
+        line.details = "room and board, semi-private".to_string();
+        line.units = nights;
+        line.unit_charge_amount = (1500.0..3000.0).fake();
+        line.modifiers = None;
+    }
+}
+
+/// Bill a hospital outpatient visit as the hospital's own UB-04, paid line by line, each line
+/// under the revenue code of a department
+fn make_outpatient(claim: &mut PayerClaim, rng: &mut impl rand::Rng) {
+    use crate::schema::{ClaimType, InstitutionalClaim};
+    let visit_date = chrono::Utc::now().date_naive() - chrono::Days::new((1..30).fake());
+    claim.claim_type = ClaimType::Institutional(InstitutionalClaim {
+        bill_type: OUTPATIENT_BILL_TYPE.to_string(),
+        admission_date: visit_date,
+        discharge_date: Some(visit_date),
+        drg: None,
+    });
+    claim.organization.taxonomy_code = Some(HOSPITAL_TAXONOMY.to_string());
+    for line in &mut claim.service_lines {
+        line.revenue_code = OUTPATIENT_REVENUE_CODES.choose(rng).map(|code| code.to_string());
+    }
+}
+
+/// Put the patient on a spouse's or parent's policy: the subscriber's member id plus a
+/// dependent suffix, as printed on family ID cards
+fn make_dependent(claim: &mut PayerClaim, rng: &mut impl rand::Rng) {
+    use crate::schema::{Relationship, Subscriber};
+    use chrono::Datelike;
+    let relationship = *[Relationship::Spouse, Relationship::Child].choose(rng).unwrap();
+    let years_older = match relationship {
+        Relationship::Child => (20..=40).fake::<i32>(),
+        _ => (-5..=5).fake::<i32>(),
+    };
+    let subscriber_id = MemberId::new(Faker.fake::<String>());
+    claim.insurance.patient_member_id = MemberId::new(format!("{}-{:02}", subscriber_id, (1..=4).fake::<u8>()));
+    claim.insurance.subscriber = Some(Subscriber {
+        member_id: subscriber_id,
+        first_name: FirstName().fake(),
+        last_name: claim.patient.last_name.clone(),
+        dob: claim.patient.dob.and_then(|dob| dob.with_year(dob.year() - years_older)),
+        relationship,
+    });
+}
+
+/// Random US street address
+fn fake_address() -> crate::schema::Address {
+    crate::schema::Address {
+        street: Some(StreetName().fake()),
+        city: Some(CityName().fake()),
+        state: Some(StateAbbr().fake()),
+        zip: Some(PostCode().fake()),
+        country: Some("USA".to_string()),
+    }
+}
+
+/// Random NPI with a valid check digit
+fn fake_npi() -> Npi {
+    // individual providers start with 1, organizations with 2
+    let first_nine = format!("{}{}", (1..=2).fake::<u8>(), NumberWithFormat("########").fake::<String>());
+    let check = validation::npi_check_digit(&first_nine).expect("nine digits");
+    Npi::new(format!("{}{}", first_nine, check))
+}
+
+/// Random EIN in `NN-NNNNNNN` form with a prefix the IRS assigns
+fn fake_ein() -> String {
+    loop {
+        let ein = format!("{}-{}", NumberWithFormat("##").fake::<String>(), NumberWithFormat("#######").fake::<String>());
+        if validation::is_valid_ein(&ein) {
+            return ein;
+        }
     }
 }
 
@@ -100,13 +251,83 @@ pub fn fake_payer_claim() -> PayerClaim {
 /// 
 /// Creates n claims and writes them as JSON lines to the specified path
 /// Used to generate test data for the claim processing simulation
-pub fn write_fake_claims_jsonl(path: &str, n: usize) -> std::io::Result<()> {
+pub fn write_fake_claims_jsonl(path: &str, n: usize, ids: &ClaimIdGenerator) -> std::io::Result<()> {
     let file = File::create(path)?;
-    let mut writer = BufWriter::new(file);
+    write_fake_claims(BufWriter::new(file), n, ids)
+}
+
+/// Write n fake claims as JSON lines to any writer (e.g. stdout for pipelines)
+pub fn write_fake_claims<W: Write>(writer: W, n: usize, ids: &ClaimIdGenerator) -> std::io::Result<()> {
+    write_claims(writer, n, || fake_payer_claim_with_ids(ids))
+}
+
+/// Fake claim billed to a payer drawn from `mix` with `rng`
+pub(crate) fn fake_mixed_claim(mix: &PayerMix, ids: &ClaimIdGenerator, rng: &mut impl Rng) -> PayerClaim {
+    let mut claim = fake_payer_claim_with_ids(ids);
+    claim.insurance.payer_id = mix.choose(rng).clone();
+    claim
+}
+
+/// Write n fake claims billed to payers in the proportions of `mix` to a JSONL file, drawing
+/// each claim's payer with `rng`
+pub fn write_mixed_claims_jsonl(path: &str, n: usize, mix: &PayerMix, ids: &ClaimIdGenerator, rng: &mut impl Rng) -> std::io::Result<()> {
+    let file = File::create(path)?;
+    write_claims(BufWriter::new(file), n, || fake_mixed_claim(mix, ids, rng))
+}
+
+/// Fake claim billed to one of the rosters' payers for a member it covers on `date`
+///
+/// The member is the patient and subscriber. As the simulated months pass some of these
+/// members leave their payer, so later claims for them are denied as not eligible. With a
+/// `mix` the payer is drawn from it, otherwise each roster's payer is equally likely; the
+/// payer and member are drawn with `rng`
+pub(crate) fn fake_roster_claim(
+    rosters: &BTreeMap<PayerId, Arc<Roster>>,
+    mix: Option<&PayerMix>,
+    date: NaiveDate,
+    ids: &ClaimIdGenerator,
+    rng: &mut impl Rng,
+) -> PayerClaim {
+    let mut claim = fake_payer_claim_with_ids(ids);
+    let payer = match mix {
+        Some(mix) => {
+            let payer_id = mix.choose(rng);
+            claim.insurance.payer_id = payer_id.clone();
+            rosters.get_key_value(payer_id)
+        }
+        None => rosters.iter().collect::<Vec<_>>().choose(rng).copied(),
+    };
+    let Some((payer_id, roster)) = payer else {
+        return claim;
+    };
+    if let Some(member) = roster.eligible_on(date).collect::<Vec<_>>().choose(rng) {
+        claim.insurance.payer_id = payer_id.clone();
+        claim.insurance.patient_member_id = member.member_id.clone();
+        claim.insurance.subscriber = None;
+    }
+    claim
+}
+
+/// Write n fake claims for members the rosters cover today to a JSONL file, billed to payers
+/// in the proportions of `mix` when there is one, drawing each claim's payer and member with `rng`
+pub fn write_roster_claims_jsonl(
+    path: &str,
+    n: usize,
+    rosters: &BTreeMap<PayerId, Arc<Roster>>,
+    mix: Option<&PayerMix>,
+    ids: &ClaimIdGenerator,
+    rng: &mut impl Rng,
+) -> std::io::Result<()> {
+    let today = chrono::Utc::now().date_naive();
+    let file = File::create(path)?;
+    write_claims(BufWriter::new(file), n, || fake_roster_claim(rosters, mix, today, ids, rng))
+}
+
+fn write_claims<W: Write>(mut writer: W, n: usize, mut fake: impl FnMut() -> PayerClaim) -> std::io::Result<()> {
     for _ in 0..n {
-        let claim = fake_payer_claim();
+        let claim = fake();
         let json = serde_json::to_string(&claim).unwrap();
         writeln!(writer, "{}", json)?;
     }
-    Ok(())
+    writer.flush()
 }
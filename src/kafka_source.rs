@@ -0,0 +1,77 @@
+use rdkafka::ClientConfig;
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::message::Message;
+use tokio::sync::mpsc::Sender;
+
+use crate::claim_source::ClaimSource;
+use crate::logging::log_claim_event;
+use crate::reader::{self, ReaderOptions};
+use crate::schema::PayerClaim;
+
+/// Kafka topic of claims, one claim as JSON per message
+///
+/// Joins a consumer group, so several simulations can share a topic's partitions, and starts
+/// from the earliest offset the group has not committed. Messages that are not valid claims are
+/// skipped. Consumes until the biller stops listening
+#[derive(Debug, Clone)]
+pub struct KafkaSource {
+    brokers: String,
+    topic: String,
+    group_id: String,
+    options: ReaderOptions,
+}
+
+impl KafkaSource {
+    /// Consume `topic` from the comma-separated `brokers` as consumer group `group_id`
+    pub fn new(brokers: impl Into<String>, topic: impl Into<String>, group_id: impl Into<String>) -> Self {
+        Self {
+            brokers: brokers.into(),
+            topic: topic.into(),
+            group_id: group_id.into(),
+            options: ReaderOptions::default(),
+        }
+    }
+
+    /// Validation and allowed currencies applied to each claim
+    pub fn with_options(mut self, options: ReaderOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl ClaimSource for KafkaSource {
+    async fn stream(self, tx: Sender<PayerClaim>, verbose: bool) -> anyhow::Result<()> {
+        let consumer: StreamConsumer = ClientConfig::new()
+            .set("bootstrap.servers", &self.brokers)
+            .set("group.id", &self.group_id)
+            .set("auto.offset.reset", "earliest")
+            .create()?;
+        consumer.subscribe(&[&self.topic])?;
+        if verbose {
+            log_claim_event("kafka", "-", "start", &format!("Consuming claims from {} at {}", self.topic, self.brokers));
+        }
+        loop {
+            let message = tokio::select! {
+                _ = tx.closed() => return Ok(()),
+                message = consumer.recv() => message?,
+            };
+            let source = format!("{}[{}]", message.topic(), message.partition());
+            let offset = message.offset().max(0) as u64;
+            let checked = match message.payload_view::<str>() {
+                Some(Ok(payload)) => reader::check_claim(payload, &self.options, &source, offset)
+                    .map_err(|err| format!("{}: {}", if err.pointer.is_empty() { "<document>" } else { &err.pointer }, err.reason)),
+                Some(Err(e)) => Err(format!("payload is not UTF-8: {}", e)),
+                None => Err("message has no payload".to_string()),
+            };
+            drop(message);
+            match checked {
+                Ok(claim) => {
+                    if tx.send(claim).await.is_err() {
+                        return Ok(());
+                    }
+                }
+                Err(reason) => eprintln!("Invalid claim skipped: {}@{}: {}", source, offset, reason),
+            }
+        }
+    }
+}
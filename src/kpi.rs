@@ -0,0 +1,134 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+use crate::clock::SimClock;
+use crate::ids::ClaimKey;
+use crate::ledger::{AdjustmentReason, EntryKind, Ledger};
+use crate::message::ClaimStatus;
+use crate::report_engine::HistoryTotals;
+
+/// Standard revenue-cycle KPIs over the run so far, on the simulated calendar
+///
+/// Rates are `None` until there is something to divide by
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RevenueCycleKpis {
+    /// Simulated days since the run started
+    pub days: f64,
+    /// Billed on every claim accepted, less claims voided or replaced by a corrected claim
+    pub gross_charges: f64,
+    /// Payer and patient cash posted to the ledgers
+    pub payments: f64,
+    pub contractual_adjustments: f64,
+    /// Charges not yet paid or adjusted off, with or without a remittance
+    pub ar_balance: f64,
+    /// AR balance over the average charges billed per simulated day
+    pub days_in_ar: Option<f64>,
+    /// Payments over gross charges
+    pub gross_collection_rate: Option<f64>,
+    /// Payments over gross charges less contractual adjustments, i.e. over what could be collected
+    pub net_collection_rate: Option<f64>,
+    /// Share of remitted claims paid on their only submission: not denied, not corrected, and not a correction
+    pub first_pass_resolution_rate: Option<f64>,
+}
+
+/// KPIs of the claims `totals` counts and the money posted to `ledgers`, as of `now`
+///
+/// Charges come from claim history, so claims still with their payer count toward AR;
+/// payments and adjustments come from the ledgers, net of reversals for replaced claims
+pub fn revenue_cycle_kpis<'a>(
+    totals: &HistoryTotals,
+    ledgers: impl IntoIterator<Item = &'a Ledger>,
+    clock: &SimClock,
+    now: Instant,
+) -> RevenueCycleKpis {
+    // a corrected claim bills in place of the original it replaced
+    let mut kpis = RevenueCycleKpis { days: clock.elapsed_days(now), gross_charges: totals.gross_charges(), ..RevenueCycleKpis::default() };
+    let (remitted, first_pass) = totals.first_pass();
+
+    let mut other_adjustments = 0.0;
+    for ledger in ledgers {
+        for entry in ledger.entries() {
+            match entry.kind {
+                EntryKind::PayerPayment | EntryKind::PatientPayment => kpis.payments += entry.amount,
+                EntryKind::Adjustment(AdjustmentReason::Contractual) => kpis.contractual_adjustments += entry.amount,
+                EntryKind::Adjustment(_) => other_adjustments += entry.amount,
+                // charges are taken from history; the patient's share moves AR, it doesn't reduce it
+                EntryKind::Charge | EntryKind::PatientResponsibility => {}
+            }
+        }
+    }
+    kpis.ar_balance = kpis.gross_charges - kpis.payments - kpis.contractual_adjustments - other_adjustments;
+
+    let ratio = |numerator: f64, denominator: f64| (denominator > 0.0).then(|| numerator / denominator);
+    kpis.days_in_ar = ratio(kpis.days, kpis.gross_charges).map(|days_per_dollar| kpis.ar_balance * days_per_dollar);
+    kpis.gross_collection_rate = ratio(kpis.payments, kpis.gross_charges);
+    kpis.net_collection_rate = ratio(kpis.payments, kpis.gross_charges - kpis.contractual_adjustments);
+    kpis.first_pass_resolution_rate = ratio(first_pass as f64, remitted as f64);
+    kpis
+}
+
+/// Originals in `records` that a corrected claim has replaced
+pub(crate) fn replaced_claims(records: &HashMap<ClaimKey, ClaimStatus>) -> HashSet<ClaimKey> {
+    records
+        .values()
+        .filter_map(|status| match status {
+            ClaimStatus::Submitted { claim, .. } => claim.replaces(),
+            ClaimStatus::Remitted(record) => record.claim().replaces(),
+            ClaimStatus::Voided { .. } => None,
+        }
+        .map(|original_id| ClaimKey::new(status.tenant_id(), original_id.clone())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::LedgerEntry;
+    use crate::message::ClaimMetadata;
+    use crate::remittance::{Remittance, RemittanceRecord};
+    use crate::tenant::DEFAULT_TENANT;
+    use crate::test_utils::{ClaimBuilder, mock_remittance};
+    use std::time::Duration;
+
+    /// Test that the KPIs combine charges from history with cash and adjustments from the ledger over simulated days.
+    /// Expected: Three claims are billed over 10 simulated days, the voided one left out; with half a claim's charge
+    /// collected and a tenth adjusted off, 2.4 charges remain in AR, 8 days' worth; one of two remitted claims passed first time.
+    #[test]
+    fn test_revenue_cycle_kpis() {
+        let clock = SimClock::new(chrono::Utc::now().date_naive(), 1.0);
+        let now = Instant::now() + Duration::from_secs(10);
+        let claim = |claim_id: &str| ClaimBuilder::new().claim_id(claim_id).build();
+        let charge = claim("a").total_charge();
+        let paid = RemittanceRecord::new(claim("a"), mock_remittance(), now, now);
+        let denied = RemittanceRecord::new(claim("b"), Remittance::denied(&claim("b")), now, now);
+        let pending = ClaimStatus::Submitted {
+            claim: claim("c").into(),
+            tenant_id: DEFAULT_TENANT.to_string(),
+            submitted_at: now,
+            metadata: ClaimMetadata::default(),
+        };
+        let voided = ClaimStatus::Voided { claim: claim("d").into(), tenant_id: DEFAULT_TENANT.to_string(), voided_at: now };
+        let records: HashMap<ClaimKey, ClaimStatus> = [ClaimStatus::Remitted(paid), ClaimStatus::Remitted(denied), pending, voided]
+            .into_iter()
+            .map(|status| (status.key(), status))
+            .collect();
+        let mut ledger = Ledger::new();
+        let post = |kind, amount| LedgerEntry::new("a", "p1", "medicare", kind, amount);
+        ledger.post(post(EntryKind::PayerPayment, charge * 0.4));
+        ledger.post(post(EntryKind::PatientPayment, charge * 0.1));
+        ledger.post(post(EntryKind::Adjustment(AdjustmentReason::Contractual), charge * 0.1));
+
+        let kpis = revenue_cycle_kpis(&HistoryTotals::from_records(&records), [&ledger], &clock, now);
+        assert!((kpis.days - 10.0).abs() < 0.1, "{:?}", kpis);
+        assert!((kpis.gross_charges - charge * 3.0).abs() < 1e-9);
+        assert!((kpis.ar_balance - charge * 2.4).abs() < 1e-9);
+        assert!((kpis.days_in_ar.unwrap() - kpis.days * 0.8).abs() < 1e-9);
+        assert!((kpis.gross_collection_rate.unwrap() - 0.5 / 3.0).abs() < 1e-9);
+        assert!((kpis.net_collection_rate.unwrap() - 0.5 / 2.9).abs() < 1e-9);
+        assert_eq!(kpis.first_pass_resolution_rate, Some(0.5));
+
+        let empty = revenue_cycle_kpis(&HistoryTotals::default(), [], &clock, now);
+        assert_eq!((empty.days_in_ar, empty.gross_collection_rate, empty.first_pass_resolution_rate), (None, None, None));
+    }
+}
@@ -0,0 +1,298 @@
+use chrono::NaiveDate;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::Instant;
+use crate::clock::SimClock;
+use crate::ids::{ClaimId, MemberId, PayerId};
+
+/// Why a balance was adjusted off the books
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AdjustmentReason {
+    /// Amount above the payer's allowed amount, written off per contract
+    Contractual,
+    /// Patient balance too small to be worth sending a statement
+    SmallBalance,
+    /// Patient balance left unpaid after every statement, written off
+    BadDebt,
+    /// Patient balance left unpaid after every statement, handed to a collections agency
+    Collections,
+}
+
+impl fmt::Display for AdjustmentReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdjustmentReason::Contractual => write!(f, "contractual"),
+            AdjustmentReason::SmallBalance => write!(f, "small_balance"),
+            AdjustmentReason::BadDebt => write!(f, "bad_debt"),
+            AdjustmentReason::Collections => write!(f, "collections"),
+        }
+    }
+}
+
+/// Kind of financial transaction posted against a claim
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntryKind {
+    /// Gross amount billed to the payer
+    Charge,
+    /// Amount paid by the payer on the remittance
+    PayerPayment,
+    /// Copay, coinsurance, and deductible transferred to the patient
+    PatientResponsibility,
+    /// Amount collected from the patient
+    PatientPayment,
+    /// Amount written off, with the reason for the adjustment
+    Adjustment(AdjustmentReason),
+}
+
+/// Single transaction in the biller's accounts receivable ledger
+#[derive(Debug, Clone)]
+pub struct LedgerEntry {
+    pub claim_id: ClaimId,
+    pub patient_id: MemberId,
+    pub payer_id: PayerId,
+    pub kind: EntryKind,
+    pub amount: f64,
+    pub posted_at: Instant,
+}
+
+impl LedgerEntry {
+    pub fn new(
+        claim_id: impl Into<ClaimId>,
+        patient_id: impl Into<MemberId>,
+        payer_id: impl Into<PayerId>,
+        kind: EntryKind,
+        amount: f64,
+    ) -> Self {
+        Self {
+            claim_id: claim_id.into(),
+            patient_id: patient_id.into(),
+            payer_id: payer_id.into(),
+            kind,
+            amount,
+            posted_at: Instant::now(),
+        }
+    }
+}
+
+/// Patient AR position: what was transferred to the patient vs what they paid
+#[derive(Debug, Default, Clone)]
+pub struct PatientBalance {
+    pub responsibility: f64,
+    pub collected: f64,
+    pub written_off: f64,
+    /// Transferred to a collections agency, no longer billed to the patient
+    pub in_collections: f64,
+}
+
+impl PatientBalance {
+    pub fn outstanding(&self) -> f64 {
+        self.responsibility - self.collected - self.written_off - self.in_collections
+    }
+}
+
+/// Labels for the patient aging buckets, in simulated days since the balance was billed
+pub const PATIENT_AGING_BUCKET_LABELS: [&str; 4] = ["0-30 days", "31-60 days", "61-90 days", "90+ days"];
+
+/// A patient's outstanding balances by age, and what has gone to collections
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PatientAging {
+    pub patient_id: MemberId,
+    pub buckets: [f64; 4],
+    pub in_collections: f64,
+}
+
+impl PatientAging {
+    /// Outstanding balance still billed to the patient
+    pub fn total(&self) -> f64 {
+        self.buckets.iter().sum()
+    }
+}
+
+/// Append-only record of all charges, payments, and patient balances
+#[derive(Debug, Default)]
+pub struct Ledger {
+    entries: Vec<LedgerEntry>,
+    /// Claims whose postings were already offset, so a repeated reversal posts nothing
+    reversed: HashSet<ClaimId>,
+}
+
+impl Ledger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn post(&mut self, entry: LedgerEntry) {
+        self.entries.push(entry);
+    }
+
+    pub fn entries(&self) -> &[LedgerEntry] {
+        &self.entries
+    }
+
+    /// Post an offsetting entry for each of a claim's postings, e.g. when a replacement supersedes it
+    ///
+    /// Patient payments are not reversed: money collected stays as a credit to the patient.
+    /// A claim is reversed at most once. Returns the number of entries reversed
+    pub fn reverse_claim(&mut self, claim_id: &str) -> usize {
+        if !self.reversed.insert(ClaimId::from(claim_id)) {
+            return 0;
+        }
+        let reversals: Vec<LedgerEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.claim_id == claim_id && entry.kind != EntryKind::PatientPayment)
+            .map(|entry| LedgerEntry {
+                amount: -entry.amount,
+                posted_at: Instant::now(),
+                ..entry.clone()
+            })
+            .collect();
+        let count = reversals.len();
+        self.entries.extend(reversals);
+        count
+    }
+
+    /// Summarize patient responsibility and collections by patient id
+    pub fn patient_balances(&self) -> HashMap<MemberId, PatientBalance> {
+        let mut balances: HashMap<MemberId, PatientBalance> = HashMap::new();
+        for entry in &self.entries {
+            match entry.kind {
+                EntryKind::PatientResponsibility => {
+                    balances.entry(entry.patient_id.clone()).or_default().responsibility += entry.amount;
+                }
+                EntryKind::PatientPayment => {
+                    balances.entry(entry.patient_id.clone()).or_default().collected += entry.amount;
+                }
+                EntryKind::Adjustment(AdjustmentReason::SmallBalance | AdjustmentReason::BadDebt) => {
+                    balances.entry(entry.patient_id.clone()).or_default().written_off += entry.amount;
+                }
+                EntryKind::Adjustment(AdjustmentReason::Collections) => {
+                    balances.entry(entry.patient_id.clone()).or_default().in_collections += entry.amount;
+                }
+                _ => {}
+            }
+        }
+        balances
+    }
+
+    /// Outstanding patient balances by patient, each claim's balance aged by simulated days since
+    /// it was transferred to the patient; balances sent to collections are totalled apart
+    pub fn patient_aging(&self, clock: &SimClock, today: NaiveDate) -> Vec<PatientAging> {
+        let mut claims: HashMap<&ClaimId, (&MemberId, Option<Instant>, PatientBalance)> = HashMap::new();
+        for entry in &self.entries {
+            let (_, billed_at, balance) = claims.entry(&entry.claim_id).or_insert((&entry.patient_id, None, PatientBalance::default()));
+            match entry.kind {
+                EntryKind::PatientResponsibility => {
+                    billed_at.get_or_insert(entry.posted_at);
+                    balance.responsibility += entry.amount;
+                }
+                EntryKind::PatientPayment => balance.collected += entry.amount,
+                EntryKind::Adjustment(AdjustmentReason::SmallBalance | AdjustmentReason::BadDebt) => balance.written_off += entry.amount,
+                EntryKind::Adjustment(AdjustmentReason::Collections) => balance.in_collections += entry.amount,
+                _ => {}
+            }
+        }
+        let mut aging: HashMap<&MemberId, PatientAging> = HashMap::new();
+        for (patient_id, billed_at, balance) in claims.into_values() {
+            let Some(billed_at) = billed_at else {
+                continue;
+            };
+            let row = aging.entry(patient_id).or_insert_with(|| PatientAging {
+                patient_id: patient_id.clone(),
+                ..PatientAging::default()
+            });
+            row.in_collections += balance.in_collections;
+            let outstanding = balance.outstanding();
+            if outstanding > 0.0 {
+                let days = (today - clock.at(billed_at).date()).num_days();
+                let bucket = match days {
+                    ..=30 => 0,
+                    31..=60 => 1,
+                    61..=90 => 2,
+                    _ => 3,
+                };
+                row.buckets[bucket] += outstanding;
+            }
+        }
+        let mut aging: Vec<PatientAging> = aging
+            .into_values()
+            .filter(|row| row.total() > 0.0 || row.in_collections > 0.0)
+            .collect();
+        aging.sort_by(|a, b| a.patient_id.cmp(&b.patient_id));
+        aging
+    }
+
+    /// Total adjustments by payer and reason
+    pub fn write_off_summary(&self) -> HashMap<PayerId, HashMap<AdjustmentReason, f64>> {
+        let mut summary: HashMap<PayerId, HashMap<AdjustmentReason, f64>> = HashMap::new();
+        for entry in &self.entries {
+            if let EntryKind::Adjustment(reason) = entry.kind {
+                *summary
+                    .entry(entry.payer_id.clone())
+                    .or_default()
+                    .entry(reason)
+                    .or_default() += entry.amount;
+            }
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that patient balances net payments against responsibility, and that reversing a claim keeps its collections.
+    /// Expected: Outstanding equals responsibility minus collected, per patient; after reversal the payment is a credit.
+    #[test]
+    fn test_patient_balances() {
+        let mut ledger = Ledger::new();
+        ledger.post(LedgerEntry::new("c1", "p1", "medicare", EntryKind::Charge, 100.0));
+        ledger.post(LedgerEntry::new("c1", "p1", "medicare", EntryKind::PatientResponsibility, 20.0));
+        ledger.post(LedgerEntry::new("c1", "p1", "medicare", EntryKind::PatientPayment, 15.0));
+        ledger.post(LedgerEntry::new("c2", "p2", "anthem", EntryKind::PatientResponsibility, 10.0));
+        let balances = ledger.patient_balances();
+        assert_eq!(balances.len(), 2);
+        assert!((balances["p1"].outstanding() - 5.0).abs() < 1e-9);
+        assert!((balances["p2"].outstanding() - 10.0).abs() < 1e-9);
+        assert_eq!(balances["p2"].collected, 0.0);
+
+        assert_eq!(ledger.reverse_claim("c1"), 2);
+        let balances = ledger.patient_balances();
+        assert!((balances["p1"].outstanding() + 15.0).abs() < 1e-9);
+    }
+
+    /// Test that reversing a claim a second time posts nothing.
+    /// Expected: The second reversal returns 0 and the claim's postings net to zero.
+    #[test]
+    fn test_reverse_claim_once() {
+        let mut ledger = Ledger::new();
+        ledger.post(LedgerEntry::new("c1", "p1", "medicare", EntryKind::Charge, 100.0));
+        ledger.post(LedgerEntry::new("c1", "p1", "medicare", EntryKind::PayerPayment, 80.0));
+        assert_eq!(ledger.reverse_claim("c1"), 2);
+        assert_eq!(ledger.reverse_claim("c1"), 0);
+        let net: f64 = ledger.entries().iter().map(|entry| entry.amount).sum();
+        assert!(net.abs() < 1e-9);
+    }
+
+    /// Test that outstanding patient balances age by simulated days since billing and collections are totalled apart.
+    /// Expected: A balance billed 45 simulated days ago is 31-60 days old; a paid one is gone; a collections transfer is not aged.
+    #[test]
+    fn test_patient_aging() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 3).unwrap();
+        let clock = SimClock::new(today - chrono::Days::new(45), 1.0);
+        let mut ledger = Ledger::new();
+        ledger.post(LedgerEntry::new("c1", "p1", "medicare", EntryKind::PatientResponsibility, 20.0));
+        ledger.post(LedgerEntry::new("c2", "p1", "medicare", EntryKind::PatientResponsibility, 30.0));
+        ledger.post(LedgerEntry::new("c2", "p1", "medicare", EntryKind::PatientPayment, 30.0));
+        ledger.post(LedgerEntry::new("c3", "p2", "anthem", EntryKind::PatientResponsibility, 10.0));
+        ledger.post(LedgerEntry::new("c3", "p2", "anthem", EntryKind::Adjustment(AdjustmentReason::Collections), 10.0));
+
+        let aging = ledger.patient_aging(&clock, today);
+        assert_eq!(aging.len(), 2);
+        assert_eq!(aging[0].buckets, [0.0, 20.0, 0.0, 0.0]);
+        assert_eq!(aging[1].total(), 0.0);
+        assert_eq!(aging[1].in_collections, 10.0);
+        assert_eq!(ledger.patient_balances()["p2"].outstanding(), 0.0);
+    }
+}
@@ -1,11 +1,88 @@
+pub mod adjudicator;
+pub mod api;
+pub mod attachments;
+pub mod audit;
+pub mod audit_log;
+pub mod benefits;
 pub mod biller;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod claim_ids;
+pub mod claim_notes;
+pub mod claim_search;
+pub mod claim_source;
 pub mod clearinghouse;
+pub mod clock;
 pub mod config;
+pub mod control;
+pub mod debugger;
+pub mod drg;
+pub mod edits;
+pub mod deposits;
+pub mod event_log;
+pub mod extensions;
+pub mod file_drop;
+pub mod forecast;
+pub mod functional_ack;
+#[cfg(feature = "grpc")]
+pub mod grpc_payer;
+pub mod held_remittances;
+pub mod history;
+pub mod idempotency;
+pub mod inventory;
+pub mod invariants;
+pub mod ids;
 pub mod json_faker;
+pub mod kpi;
+#[cfg(feature = "kafka")]
+pub mod kafka_source;
+pub mod ledger;
+pub mod loadtest;
 pub mod logging;
+pub mod medical_necessity;
 pub mod message;
+pub mod modifier;
+pub mod monte_carlo;
+pub mod orchestrator;
+pub mod outcomes;
+pub mod patient_payer;
 pub mod payer;
+pub mod payer_mix;
+pub mod payer_rules;
+pub mod phi;
+pub mod period_close;
+pub mod place_of_service;
+pub mod posting;
+pub mod prelude;
+pub mod priority;
+pub mod pseudonym;
+pub mod queue;
+pub mod rate_limiter;
 pub mod reader;
+pub mod reconciliation;
+pub mod rejects;
+pub mod roster;
+pub mod run_db;
+pub mod run_dir;
 pub mod remittance;
+pub mod remittance_files;
+pub mod remittance_sink;
+pub mod replay;
+pub mod report_engine;
+pub mod report_format;
 pub mod reporter;
+#[cfg(feature = "scenario")]
+pub mod scenario;
 pub mod schema;
+pub mod simulation;
+pub mod sla;
+pub mod stop;
+pub mod supervisor;
+pub mod telemetry;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+pub mod tenant;
+pub mod validation;
+pub mod watchdog;
+pub mod what_if;
+pub mod workqueue;
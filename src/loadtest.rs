@@ -0,0 +1,131 @@
+use std::time::{Duration, Instant};
+
+use crate::claim_ids::ClaimIdGenerator;
+use crate::clearinghouse::DEFAULT_CLEARINGHOUSE_SHARDS;
+use crate::config::Config;
+use crate::json_faker;
+use crate::payer::DEFAULT_PAYER_WORKERS;
+use crate::report_engine::{ReportEngine, StageLatency};
+use crate::simulation::{BillerSpec, DEFAULT_CHANNEL_CAPACITY, PayerSpec, Simulation};
+
+/// Shape of a load test run
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadTestOptions {
+    pub claims: usize,
+    pub payers: usize,
+    pub payer_workers: usize,
+    pub clearinghouse_shards: usize,
+    pub channel_capacity: usize,
+}
+
+impl Default for LoadTestOptions {
+    fn default() -> Self {
+        Self {
+            claims: 10_000,
+            payers: 3,
+            payer_workers: DEFAULT_PAYER_WORKERS,
+            clearinghouse_shards: DEFAULT_CLEARINGHOUSE_SHARDS,
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+        }
+    }
+}
+
+/// Throughput, memory, and latency measured by one load test run
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadTestReport {
+    pub claims: usize,
+    pub remitted: usize,
+    pub elapsed: Duration,
+    /// Peak resident set size of the process in KiB, where the platform reports it
+    pub peak_memory_kib: Option<u64>,
+    pub stage_latency: Vec<StageLatency>,
+}
+
+impl LoadTestReport {
+    /// Claims remitted per second of wall-clock time
+    pub fn throughput(&self) -> f64 {
+        match self.elapsed.as_secs_f64() {
+            secs if secs > 0.0 => self.remitted as f64 / secs,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Push fake claims through the whole pipeline as fast as it will take them
+///
+/// Payers adjudicate with no delay, there is no ingest rate limit, and no patient
+/// payer runs, so the run measures the pipeline itself. Claims are spread round-robin
+/// over `payers` payers named `payer-1`, `payer-2`, ...
+pub async fn run(options: &LoadTestOptions) -> anyhow::Result<LoadTestReport> {
+    if options.claims == 0 || options.payers == 0 {
+        return Err(anyhow::anyhow!("load test needs at least one claim and one payer"));
+    }
+    let payer_ids: Vec<String> = (1..=options.payers).map(|n| format!("payer-{}", n)).collect();
+    let ids = ClaimIdGenerator::new("loadtest");
+    let claims: Vec<_> = (0..options.claims)
+        .map(|n| {
+            let mut claim = json_faker::fake_payer_claim_with_ids(&ids);
+            claim.insurance.payer_id = payer_ids[n % payer_ids.len()].as_str().into();
+            claim
+        })
+        .collect();
+
+    let mut builder = Simulation::builder()
+        .config(Config::default())
+        .channel_capacity(options.channel_capacity)
+        .clearinghouse_shards(options.clearinghouse_shards)
+        .biller(BillerSpec::default().with_source(claims));
+    for payer_id in &payer_ids {
+        builder = builder.payer(PayerSpec::new(payer_id.as_str(), 0, 0).with_workers(options.payer_workers));
+    }
+
+    let started = Instant::now();
+    let mut sim = builder.build()?;
+    sim.wait().await;
+    let elapsed = started.elapsed();
+
+    let records = sim.history().snapshot().await;
+    let engine = ReportEngine::new(&records);
+    let report = LoadTestReport {
+        claims: options.claims,
+        remitted: sim.metrics().await.remitted,
+        elapsed,
+        peak_memory_kib: peak_memory_kib(),
+        stage_latency: engine.stage_latency(),
+    };
+    sim.shutdown();
+    Ok(report)
+}
+
+/// High-water mark of resident memory from `/proc/self/status` (Linux only)
+fn peak_memory_kib() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that a small load test remits every claim and measures each pipeline stage.
+    /// Expected: All claims remitted, positive throughput, and a latency row per stage transition.
+    #[tokio::test]
+    async fn test_load_test_run() {
+        let options = LoadTestOptions {
+            claims: 200,
+            payers: 2,
+            ..LoadTestOptions::default()
+        };
+        let report = tokio::time::timeout(Duration::from_secs(10), run(&options))
+            .await
+            .expect("load test should finish quickly with no payer delay")
+            .unwrap();
+        assert_eq!(report.remitted, 200);
+        assert!(report.throughput() > 0.0);
+        assert!(!report.stage_latency.is_empty());
+        assert!(run(&LoadTestOptions { payers: 0, ..options }).await.is_err());
+    }
+}
@@ -1,13 +1,460 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use anyhow::anyhow;
+use serde::Serialize;
+
+/// Size a rolling log file reaches before it is rolled over
+pub const ROLL_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Rolled-over files a rolling log keeps, `<path>.1` the newest, before dropping the oldest
+pub const ROLL_KEEP: usize = 5;
+
+/// Component name in `--log-sinks` that sets the sink of every component not named
+pub const ANY_COMPONENT: &str = "*";
+
+/// File of a claim log in NDJSON form, under its directory
+pub const CLAIM_LOG_NDJSON: &str = "claims.ndjson";
+
+/// Socket syslog and journald take local messages on
+#[cfg(unix)]
+const SYSLOG_SOCKET: &str = "/dev/log";
+
+/// The logger every component writes through, once `init` has run; stdout until then
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Where a component's claim events are written
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum LogSink {
+    /// The console, as a blank-line separated stream
+    #[default]
+    Stdout,
+    /// Appended to one file, which grows without bound
+    File(String),
+    /// Appended to a file rolled over at `ROLL_BYTES`, keeping `ROLL_KEEP` old files
+    Rolling(String),
+    /// The local syslog daemon, or journald where it owns `/dev/log`
+    Syslog,
+    /// Dropped
+    Off,
+}
+
+impl FromStr for LogSink {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().split_once(':') {
+            Some(("file", path)) if !path.is_empty() => Ok(LogSink::File(path.to_string())),
+            Some(("rolling", path)) if !path.is_empty() => Ok(LogSink::Rolling(path.to_string())),
+            None if s.trim() == "stdout" => Ok(LogSink::Stdout),
+            None if s.trim() == "syslog" => Ok(LogSink::Syslog),
+            None if s.trim() == "off" => Ok(LogSink::Off),
+            _ => Err(anyhow!("Unknown log sink: {} (expected stdout, file:<path>, rolling:<path>, syslog, or off)", s)),
+        }
+    }
+}
+
+impl fmt::Display for LogSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogSink::Stdout => write!(f, "stdout"),
+            LogSink::File(path) => write!(f, "file:{}", path),
+            LogSink::Rolling(path) => write!(f, "rolling:{}", path),
+            LogSink::Syslog => write!(f, "syslog"),
+            LogSink::Off => write!(f, "off"),
+        }
+    }
+}
+
+/// How a claim log keeps each claim's events apart
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClaimLogFormat {
+    /// One `<claim_id>.log` file per claim
+    #[default]
+    Files,
+    /// One `claims.ndjson` stream, each line keyed by its claim id
+    Ndjson,
+}
+
+impl FromStr for ClaimLogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "files" => Ok(ClaimLogFormat::Files),
+            "ndjson" => Ok(ClaimLogFormat::Ndjson),
+            _ => Err(anyhow!("Unknown claim log format: {} (expected files or ndjson)", s)),
+        }
+    }
+}
+
+impl fmt::Display for ClaimLogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClaimLogFormat::Files => write!(f, "files"),
+            ClaimLogFormat::Ndjson => write!(f, "ndjson"),
+        }
+    }
+}
+
+/// Directory every event about a claim is also written to, whatever its component's sink
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClaimLogSpec {
+    pub dir: String,
+    pub format: ClaimLogFormat,
+}
+
+/// The sink of each component, by the component name it logs under, e.g. `clearinghouse`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogSinks {
+    /// Sink of components not named in `components`
+    pub default: LogSink,
+    pub components: BTreeMap<String, LogSink>,
+    /// Where each claim's events are gathered, apart from the rest of the run's
+    pub claim_log: Option<ClaimLogSpec>,
+}
+
+impl LogSinks {
+    pub fn with_component(mut self, component: impl Into<String>, sink: LogSink) -> Self {
+        self.components.insert(component.into(), sink);
+        self
+    }
+
+    pub fn with_claim_log(mut self, claim_log: ClaimLogSpec) -> Self {
+        self.claim_log = Some(claim_log);
+        self
+    }
+
+    pub fn sink(&self, component: &str) -> &LogSink {
+        self.components.get(component).unwrap_or(&self.default)
+    }
+}
+
+impl FromStr for LogSinks {
+    type Err = anyhow::Error;
+
+    /// `component=sink,...`, with `*` naming every other component
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut sinks = LogSinks::default();
+        for entry in s.split(',').filter(|entry| !entry.trim().is_empty()) {
+            let (component, sink) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Log sink {} is not component=sink", entry))?;
+            let sink = sink.parse()?;
+            match component.trim() {
+                ANY_COMPONENT => sinks.default = sink,
+                component => {
+                    sinks.components.insert(component.to_string(), sink);
+                }
+            }
+        }
+        Ok(sinks)
+    }
+}
+
+/// A file that starts over at `max_bytes`, shifting old contents to `<path>.1`, `<path>.2`, ...
+#[derive(Debug)]
+struct RollingFile {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl RollingFile {
+    fn open(path: impl Into<PathBuf>, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        let path = path.into();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self { path, file, written, max_bytes, keep })
+    }
+
+    fn rolled(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        path.into()
+    }
+
+    fn roll(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                if self.rolled(n).exists() {
+                    fs::rename(self.rolled(n), self.rolled(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rolled(1))?;
+            self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        }
+        self.written = 0;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.written > 0 && self.written + len > self.max_bytes {
+            self.roll()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.written += len;
+        Ok(())
+    }
+}
+
+/// An open sink, shared by every component writing to the same place
+#[derive(Debug)]
+enum Writer {
+    Stdout,
+    File(Mutex<File>),
+    Rolling(Mutex<RollingFile>),
+    #[cfg(unix)]
+    Syslog(std::os::unix::net::UnixDatagram),
+    Off,
+}
+
+impl Writer {
+    fn open(sink: &LogSink) -> anyhow::Result<Self> {
+        Ok(match sink {
+            LogSink::Stdout => Writer::Stdout,
+            LogSink::File(path) => Writer::File(Mutex::new(OpenOptions::new().create(true).append(true).open(path)?)),
+            LogSink::Rolling(path) => Writer::Rolling(Mutex::new(RollingFile::open(path, ROLL_BYTES, ROLL_KEEP)?)),
+            #[cfg(unix)]
+            LogSink::Syslog => {
+                let socket = std::os::unix::net::UnixDatagram::unbound()?;
+                socket
+                    .connect(SYSLOG_SOCKET)
+                    .map_err(|e| anyhow!("Cannot reach syslog at {}: {}", SYSLOG_SOCKET, e))?;
+                Writer::Syslog(socket)
+            }
+            #[cfg(not(unix))]
+            LogSink::Syslog => return Err(anyhow!("Syslog logging is only available on Unix")),
+            LogSink::Off => Writer::Off,
+        })
+    }
+
+    fn write(&self, line: &str) -> io::Result<()> {
+        match self {
+            Writer::Stdout => {
+                println!("{}\n", line);
+                Ok(())
+            }
+            Writer::File(file) => {
+                let stamp = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z");
+                writeln!(file.lock().unwrap(), "{} {}", stamp, line)
+            }
+            Writer::Rolling(file) => {
+                let stamp = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z");
+                file.lock().unwrap().write_line(&format!("{} {}", stamp, line))
+            }
+            // facility user, severity info; the daemon stamps the time
+            #[cfg(unix)]
+            Writer::Syslog(socket) => socket
+                .send(format!("<14>healthtechsim[{}]: {}", std::process::id(), line).as_bytes())
+                .map(|_| ()),
+            Writer::Off => Ok(()),
+        }
+    }
+}
+
+/// A claim's event, as a line of an NDJSON claim log
+#[derive(Debug, Serialize)]
+struct ClaimLogLine<'a> {
+    timestamp: String,
+    claim_id: &'a str,
+    component: &'a str,
+    event: &'a str,
+    message: &'a str,
+}
+
+/// Gathers the events of each claim, so one claim's history can be read without the run's
+#[derive(Debug)]
+enum ClaimLog {
+    Files(PathBuf),
+    Ndjson(Mutex<File>),
+}
+
+impl ClaimLog {
+    fn open(spec: &ClaimLogSpec) -> anyhow::Result<Self> {
+        fs::create_dir_all(&spec.dir).map_err(|e| anyhow!("Cannot create claim log directory {}: {}", spec.dir, e))?;
+        let dir = PathBuf::from(&spec.dir);
+        Ok(match spec.format {
+            ClaimLogFormat::Files => ClaimLog::Files(dir),
+            ClaimLogFormat::Ndjson => ClaimLog::Ndjson(Mutex::new(
+                OpenOptions::new().create(true).append(true).open(dir.join(CLAIM_LOG_NDJSON))?,
+            )),
+        })
+    }
+
+    /// File of one claim's events, its id made safe to use as a file name
+    fn claim_path(dir: &Path, claim_id: &str) -> PathBuf {
+        let name: String = claim_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || "-_.~".contains(c) { c } else { '_' })
+            .collect();
+        dir.join(format!("{}.log", name.trim_start_matches('.')))
+    }
+
+    fn write(&self, component: &str, claim_id: &str, event: &str, message: &str) -> anyhow::Result<()> {
+        let timestamp = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z").to_string();
+        match self {
+            // each event is one append, so concurrent components never interleave within a line
+            ClaimLog::Files(dir) => {
+                let line = format!("{} [{}][{}] {}\n", timestamp, component, event, message);
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(Self::claim_path(dir, claim_id))?
+                    .write_all(line.as_bytes())?;
+            }
+            ClaimLog::Ndjson(file) => {
+                let line = serde_json::to_string(&ClaimLogLine { timestamp, claim_id, component, event, message })?;
+                writeln!(file.lock().unwrap(), "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes each component's claim events to its configured sink
+#[derive(Debug)]
+pub struct Logger {
+    default: Arc<Writer>,
+    components: BTreeMap<String, Arc<Writer>>,
+    claim_log: Option<ClaimLog>,
+}
+
+impl Logger {
+    /// Open every sink named, once each however many components share it
+    pub fn open(sinks: &LogSinks) -> anyhow::Result<Self> {
+        let mut opened: BTreeMap<String, Arc<Writer>> = BTreeMap::new();
+        let mut open = |sink: &LogSink| -> anyhow::Result<Arc<Writer>> {
+            if let Some(writer) = opened.get(&sink.to_string()) {
+                return Ok(writer.clone());
+            }
+            let writer = Arc::new(Writer::open(sink).map_err(|e| anyhow!("Cannot open log sink {}: {}", sink, e))?);
+            opened.insert(sink.to_string(), writer.clone());
+            Ok(writer)
+        };
+        let default = open(&sinks.default)?;
+        let components = sinks
+            .components
+            .iter()
+            .map(|(component, sink)| Ok((component.clone(), open(sink)?)))
+            .collect::<anyhow::Result<_>>()?;
+        let claim_log = sinks.claim_log.as_ref().map(ClaimLog::open).transpose()?;
+        Ok(Self { default, components, claim_log })
+    }
+
+    pub fn log(&self, component: &str, claim_id: &str, event: &str, message: &str) {
+        let writer = self.components.get(component).unwrap_or(&self.default);
+        let line = format!("[{}][claim:{}][{}] {}", component, claim_id, event, message);
+        if let Err(e) = writer.write(&line) {
+            eprintln!("Failed to write {} log: {}", component, e);
+        }
+        // `-` marks events about no claim in particular, such as a component starting
+        if let Some(claim_log) = &self.claim_log
+            && claim_id != "-"
+            && let Err(e) = claim_log.write(component, claim_id, event, message)
+        {
+            eprintln!("Failed to write claim log of {}: {}", claim_id, e);
+        }
+    }
+}
+
+/// Send every component's claim events to the sinks configured for it, for the rest of the run
+pub fn init(sinks: &LogSinks) -> anyhow::Result<()> {
+    let logger = Logger::open(sinks)?;
+    LOGGER.set(logger).map_err(|_| anyhow!("Logging is already initialized"))
+}
+
 /// Log a claim processing event with standardized format
-/// 
+///
 /// Used by all components to track claim lifecycle events
 /// Format: [component][claim:claim_id][event] message
+///
+/// Goes to the component's sink once `init` has run, and to stdout before
 pub fn log_claim_event(component: &str, claim_id: &str, event: &str, message: &str) {
-    println!(
-        "[{}][claim:{}][{}] {}\n",
-        component,
-        claim_id,
-        event,
-        message
-    );
-} 
\ No newline at end of file
+    match LOGGER.get() {
+        Some(logger) => logger.log(component, claim_id, event, message),
+        None => println!(
+            "[{}][claim:{}][{}] {}\n",
+            component,
+            claim_id,
+            event,
+            message
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that each component's events reach its own sink and a rolling file rolls over at its size.
+    /// Expected: The clearinghouse's events land in its file and the payer's are dropped; a rolling file keeps two old files and drops the oldest.
+    #[test]
+    fn test_log_sinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clearinghouse.log").to_string_lossy().to_string();
+        let sinks: LogSinks = format!("clearinghouse=file:{}, *=off", path).parse().unwrap();
+        assert_eq!(sinks.sink("payer"), &LogSink::Off);
+        let logger = Logger::open(&sinks).unwrap();
+        logger.log("clearinghouse", "c1", "routed", "to medicare");
+        logger.log("payer", "c1", "adjudicated", "paid");
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 1);
+        assert!(contents.ends_with("[clearinghouse][claim:c1][routed] to medicare\n"));
+
+        let rolling = dir.path().join("payer.log");
+        let mut file = RollingFile::open(&rolling, 10, 2).unwrap();
+        for line in ["first", "second", "third", "fourth"] {
+            file.write_line(line).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&rolling).unwrap(), "fourth\n");
+        assert_eq!(fs::read_to_string(file.rolled(1)).unwrap(), "third\n");
+        assert_eq!(fs::read_to_string(file.rolled(2)).unwrap(), "second\n");
+        assert!(!file.rolled(3).exists());
+        assert!("rolling:".parse::<LogSink>().is_err());
+    }
+
+    /// Test that a claim log gathers each claim's events apart, as files or as one keyed stream, whatever the component sinks.
+    /// Expected: Two events of c1 land in its own file and one of c/2 in a safely named one; NDJSON lines carry their claim id; events about no claim are left out.
+    #[test]
+    fn test_claim_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = dir.path().join("files").to_string_lossy().to_string();
+        let ndjson = dir.path().join("ndjson").to_string_lossy().to_string();
+        for (path, format) in [(&files, ClaimLogFormat::Files), (&ndjson, ClaimLogFormat::Ndjson)] {
+            let sinks = LogSinks {
+                default: LogSink::Off,
+                ..LogSinks::default()
+            }
+            .with_claim_log(ClaimLogSpec { dir: path.clone(), format });
+            let logger = Logger::open(&sinks).unwrap();
+            logger.log("clearinghouse", "-", "start", "Starting clearinghouse task");
+            logger.log("clearinghouse", "c1", "routed", "to medicare");
+            logger.log("payer", "c/2", "adjudicated", "paid");
+            logger.log("payer", "c1", "adjudicated", "denied");
+        }
+
+        let c1 = fs::read_to_string(Path::new(&files).join("c1.log")).unwrap();
+        let events: Vec<&str> = c1.lines().map(|line| line.split_once(' ').unwrap().1).collect();
+        assert_eq!(events, ["[clearinghouse][routed] to medicare", "[payer][adjudicated] denied"]);
+        assert!(Path::new(&files).join("c_2.log").exists());
+        assert_eq!(fs::read_dir(&files).unwrap().count(), 2);
+
+        let stream = fs::read_to_string(Path::new(&ndjson).join(CLAIM_LOG_NDJSON)).unwrap();
+        let lines: Vec<serde_json::Value> = stream.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1]["claim_id"], "c/2");
+        assert_eq!(lines[2]["event"], "adjudicated");
+        assert_eq!("ndjson".parse::<ClaimLogFormat>().unwrap(), ClaimLogFormat::Ndjson);
+    }
+}
@@ -1,17 +1,31 @@
-use std::collections::HashMap;
-use std::sync::Arc;
-
 use anyhow::Result;
-use tokio::sync::{Mutex, mpsc};
+use chrono::NaiveDate;
 
-use healthtechsim::biller;
-use healthtechsim::clearinghouse;
+use healthtechsim::audit_log::{self, AuditExportFormat};
+use healthtechsim::claim_ids::ClaimIdGenerator;
+use healthtechsim::clock::SimClock;
 use healthtechsim::config;
+use healthtechsim::event_log;
+use healthtechsim::ids::ClaimId;
 use healthtechsim::json_faker;
-use healthtechsim::payer;
-use healthtechsim::reader;
+use healthtechsim::loadtest;
+use healthtechsim::logging;
+use healthtechsim::monte_carlo;
+use healthtechsim::orchestrator;
+use healthtechsim::phi::Deidentification;
+use healthtechsim::posting::WriteOffRules;
+use healthtechsim::remittance_files;
+use healthtechsim::replay;
+use healthtechsim::run_db::{RunDb, RunQuery};
+use healthtechsim::run_dir::{self, RunDir};
+use healthtechsim::report_format::ReportFormat;
+use healthtechsim::report_engine::HistoryTotals;
 use healthtechsim::reporter;
-use healthtechsim::schema;
+#[cfg(feature = "scenario")]
+use healthtechsim::scenario::{self, GoldenCheck, Scenario};
+use healthtechsim::simulation::PayerSpec;
+use healthtechsim::telemetry;
+use healthtechsim::what_if;
 
 /// Healthcare claim processing simulation
 ///
@@ -19,158 +33,211 @@ use healthtechsim::schema;
 #[tokio::main]
 async fn main() -> Result<()> {
     // parse CLI args
+    let mut config = match config::command()? {
+        config::Command::Simulate(config) => *config,
+        config::Command::Generate { count } => {
+            json_faker::write_fake_claims(std::io::stdout().lock(), count, &ClaimIdGenerator::default())?;
+            return Ok(());
+        }
+        config::Command::Replay { event_log_path, speed, tenant, format } => {
+            return run_replay(&event_log_path, speed, tenant.as_deref(), &format).await;
+        }
+        config::Command::Report { query, db_path, as_of, tenant, format } => {
+            return run_report(&query, &db_path, as_of, tenant.as_deref(), &format);
+        }
+        config::Command::WhatIf { claims_path, baseline, proposed, seed, format } => {
+            let claims = what_if::load_claims(&claims_path)?;
+            println!("Comparing contract terms over {} claims from {}", claims.len(), claims_path);
+            let impacts = what_if::compare(&claims, &baseline.load()?, &proposed.load()?, seed);
+            reporter::print_what_if_report(&impacts, &format);
+            return Ok(());
+        }
+        config::Command::MonteCarlo { claims_path, options, terms, denial_rate, error_rate, format } => {
+            let claims = what_if::load_claims(&claims_path)?;
+            let terms = terms.load()?;
+            let payers: Vec<PayerSpec> = orchestrator::simulated_payers()
+                .into_iter()
+                .map(|payer| {
+                    payer
+                        .with_denial_rate(denial_rate)
+                        .with_error_rate(error_rate)
+                        .with_outcomes(terms.outcomes.clone())
+                        .with_coverage(terms.coverage.clone())
+                        .with_benefits(terms.benefits.clone())
+                })
+                .collect();
+            println!("Running {} claims from {} {} times, {} at once", claims.len(), claims_path, options.runs, options.parallel);
+            reporter::print_monte_carlo_report(&monte_carlo::run(&claims, &payers, &options).await?, &format);
+            return Ok(());
+        }
+        config::Command::AuditExport { audit_log_path, format, claim_id, output_path } => {
+            return run_audit_export(&audit_log_path, format, claim_id.as_ref(), output_path.as_deref()).await;
+        }
+        config::Command::Scenarios { dir, name, update } => {
+            // each scenario runs on its own runtime, which cannot be started from this one
+            return tokio::task::spawn_blocking(move || run_scenarios(&dir, name.as_deref(), update)).await?;
+        }
+        config::Command::PostRemittances { dir, tenant, format } => {
+            return run_post_remittances(&dir, tenant.as_deref(), &format);
+        }
+        config::Command::LoadTest(options, format) => {
+            println!("Load testing {} claims across {} payers", options.claims, options.payers);
+            reporter::print_load_test_report(&loadtest::run(&options).await?, &format);
+            return Ok(());
+        }
+    };
+    config.validate(&orchestrator::simulated_payers())?;
+    let run_dir = match config.run_dir {
+        true => Some(start_run_dir(&mut config)?),
+        false => None,
+    };
+    println!("Config settings: file_path={}, ingest_rate={}, verbose={}, checkpoint={:?}, tenants={}", config.file_path, config.ingest_rate, config.verbose, config.checkpoint_path, config.tenant_configs().len());
 
-    // for simulation
-    let claims = 10;
-    json_faker::write_fake_claims_jsonl("fake_claims.jsonl", claims)
-        .expect("Failed to write fake claims");
-    println!("Wrote {} fake claims to fake_claims.jsonl", claims);
-
-    let config = config::config();
-    println!("Config settings: file_path={}, ingest_rate={}, verbose={}", config.file_path, config.ingest_rate, config.verbose);
+    logging::init(&config.log_sinks)?;
 
-    // channels
-    let (claim_input_tx, claim_input_rx) = mpsc::channel::<schema::PayerClaim>(100);
-    let (claim_tx, claim_rx) = mpsc::channel::<healthtechsim::message::ClaimMessage>(100);
-    let (payer1_tx, payer1_rx) = mpsc::channel::<healthtechsim::message::PayerMessage>(100);
-    let (payer2_tx, payer2_rx) = mpsc::channel::<healthtechsim::message::PayerMessage>(100);
-    let (payer3_tx, payer3_rx) = mpsc::channel::<healthtechsim::message::PayerMessage>(100);
-    let payer_txs = HashMap::from([
-        ("medicare".to_string(), payer1_tx),
-        ("united_health_group".to_string(), payer2_tx),
-        ("anthem".to_string(), payer3_tx),
-    ]);
-    let (remit_tx, remit_rx) = mpsc::channel::<healthtechsim::message::RemittanceMessage>(100);
-    let biller_txs = Arc::new(Mutex::new(HashMap::new()));
-    let remittance_history = Arc::new(Mutex::new(HashMap::new()));
+    // spans are exported until the guard drops at the end of main
+    let _telemetry = telemetry::init(config.otlp_endpoint.as_deref())?;
 
-    let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1); //TODO: change into pattern that guarantees only one sender
+    let mut handles = orchestrator::start(&config, orchestrator::simulated_payers()).await?;
+    handles.wait().await?;
+    handles.finish().await?;
+    if let Some(run_dir) = &run_dir {
+        println!("Run artifacts are in {}", run_dir.path().display());
+    }
+    Ok(())
+}
 
-    // setup and spawn tasks
-    setup_biller_task(
-        config.clone(),
-        claim_input_rx,
-        claim_tx.clone(),
-        claims,
-        shutdown_tx,
-    );
-    setup_clearinghouse_task(
-        claim_rx,
-        payer_txs,
-        remit_rx,
-        biller_txs.clone(),
-        remittance_history.clone(),
-        config.verbose,
-    );
-    setup_reporter_task(remittance_history.clone(), config.verbose);
-    setup_payer_tasks(
-        remit_tx.clone(),
-        payer1_rx,
-        payer2_rx,
-        payer3_rx,
-        config.verbose,
-    );
-    setup_reader_task(&config.file_path, claim_input_tx, config.verbose);
+/// Make this run's directory, move its outputs into it, snapshot its config, and drop old runs
+fn start_run_dir(config: &mut config::Config) -> Result<RunDir> {
+    let run_dir = RunDir::create(run_dir::RUNS_ROOT, config.run_name.as_deref(), chrono::Local::now())?;
+    run_dir.place(config);
+    run_dir.write_config(&std::env::args().collect::<Vec<_>>(), config)?;
+    for removed in run_dir::prune(run_dir::RUNS_ROOT, config.keep_runs)? {
+        println!("Removed old run {}", removed.display());
+    }
+    println!("Writing run artifacts to {}", run_dir.path().display());
+    Ok(run_dir)
+}
 
-    // shutdown
-    tokio::select! {
-        _ = shutdown_rx.recv() => {
-            println!("All remittances received. Shutting down.");
-        }
-        _ = tokio::signal::ctrl_c() => {
-            println!("Shutdown signal received.");
-        }
+/// Replay a recorded event log and print the reports it reconstructs
+async fn run_replay(event_log_path: &str, speed: Option<f64>, tenant_id: Option<&str>, format: &ReportFormat) -> Result<()> {
+    let records = event_log::read_event_log(event_log_path).await?;
+    println!("Replaying {} events from {}", records.len(), event_log_path);
+    let options = replay::ReplayOptions {
+        speed,
+        ..replay::ReplayOptions::default()
+    };
+    let mut run = replay::replay(&records, &options).await?;
+    if let Some(tenant_id) = tenant_id {
+        run.retain_tenant(tenant_id);
     }
+    // a de-identified run logged its claims de-identified already
+    reporter::print_history_reports(&HistoryTotals::from_records(&run.history), format, &Deidentification::Off);
+    // the ledger is rebuilt now, so replayed patient balances all age from today
+    reporter::print_tenant_ledger_reports(
+        run.ledgers.iter().map(|(id, ledger)| (id.as_str(), ledger)),
+        &SimClock::default(),
+        format,
+        &Deidentification::Off,
+    );
     Ok(())
 }
 
-fn setup_biller_task(
-    config: config::Config,
-    claim_input_rx: mpsc::Receiver<schema::PayerClaim>,
-    claim_tx: mpsc::Sender<healthtechsim::message::ClaimMessage>,
-    total_claims: usize,
-    shutdown_tx: mpsc::Sender<()>,
-) {
-    tokio::spawn(biller::run_biller(config, claim_input_rx, claim_tx, None, total_claims, shutdown_tx));
+/// Post the remittance files under `dir` to a ledger per tenant and print the ledger reports
+fn run_post_remittances(dir: &str, tenant_id: Option<&str>, format: &ReportFormat) -> Result<()> {
+    let mut entries = remittance_files::load(dir)?;
+    if let Some(tenant_id) = tenant_id {
+        entries.retain(|entry| entry.tenant_id == tenant_id);
+    }
+    println!("Posting {} remittances from {}", entries.len(), dir);
+    let ledgers = remittance_files::post(&entries, &WriteOffRules::default());
+    // as with a replay, patient balances age from today, and member ids are as the files hold them
+    reporter::print_tenant_ledger_reports(
+        ledgers.iter().map(|(id, ledger)| (id.as_str(), ledger)),
+        &SimClock::default(),
+        format,
+        &Deidentification::Off,
+    );
+    Ok(())
 }
 
-fn setup_clearinghouse_task(
-    claim_rx: mpsc::Receiver<healthtechsim::message::ClaimMessage>,
-    payer_txs: HashMap<String, mpsc::Sender<healthtechsim::message::PayerMessage>>,
-    remit_rx: mpsc::Receiver<healthtechsim::message::RemittanceMessage>,
-    biller_txs: Arc<
-        Mutex<HashMap<String, mpsc::Sender<healthtechsim::message::RemittanceMessage>>>,
-    >,
-    remittance_history: Arc<Mutex<HashMap<String, healthtechsim::message::ClaimStatus>>>,
-    verbose: bool,
-) {
-    let clearinghouse = clearinghouse::Clearinghouse::new(
-        claim_rx,
-        payer_txs,
-        remit_rx,
-        biller_txs,
-        remittance_history,
-        verbose,
-    );
-    tokio::spawn(async move {
-        clearinghouse.run().await;
-    });
+/// Run the scenarios under `dir`, or the one named, and compare each to its golden files,
+/// failing if any differ
+#[cfg(feature = "scenario")]
+fn run_scenarios(dir: &str, name: Option<&str>, update: bool) -> Result<()> {
+    let dirs: Vec<_> = scenario::discover(dir)?
+        .into_iter()
+        .filter(|path| name.is_none_or(|name| path.ends_with(name)))
+        .collect();
+    if dirs.is_empty() {
+        return Err(anyhow::anyhow!("No scenario {}under {}", name.map(|name| format!("{} ", name)).unwrap_or_default(), dir));
+    }
+    let mut differing = 0;
+    for path in &dirs {
+        let scenario = Scenario::load(path)?;
+        match scenario.check(&scenario.run()?, update)? {
+            GoldenCheck::Matched => println!("ok       {}", scenario.name),
+            GoldenCheck::Written => println!("written  {}", scenario.name),
+            GoldenCheck::Differs { file, line, expected, actual } => {
+                differing += 1;
+                println!("DIFFERS  {}: {} line {}", scenario.name, file.display(), line);
+                println!("  expected: {}", expected);
+                println!("  actual:   {}", actual);
+            }
+        }
+    }
+    match differing {
+        0 => Ok(()),
+        n => Err(anyhow::anyhow!(
+            "{} of {} scenarios differ from their golden files; rerun with --update to accept the changes",
+            n,
+            dirs.len()
+        )),
+    }
 }
 
-fn setup_reporter_task(
-    remittance_history: Arc<Mutex<HashMap<String, healthtechsim::message::ClaimStatus>>>,
-    verbose: bool,
-) {
-    tokio::spawn(async move {
-        reporter::run_reporter(remittance_history, verbose).await;
-    });
+#[cfg(not(feature = "scenario"))]
+fn run_scenarios(_dir: &str, _name: Option<&str>, _update: bool) -> Result<()> {
+    Err(anyhow::anyhow!("Scenarios require building with `--features scenario`"))
 }
 
-fn setup_payer_tasks(
-    remit_tx: mpsc::Sender<healthtechsim::message::RemittanceMessage>,
-    payer1_rx: mpsc::Receiver<healthtechsim::message::PayerMessage>,
-    payer2_rx: mpsc::Receiver<healthtechsim::message::PayerMessage>,
-    payer3_rx: mpsc::Receiver<healthtechsim::message::PayerMessage>,
-    verbose: bool,
-) {
-    let payer1 = payer::Payer::new(
-        "medicare".into(),
-        10,
-        30,
-        remit_tx.clone(),
-        payer1_rx,
-        verbose,
-    );
-    let payer2 = payer::Payer::new(
-        "united_health_group".into(),
-        5,
-        6,
-        remit_tx.clone(),
-        payer2_rx,
-        verbose,
-    );
-    let payer3 = payer::Payer::new(
-        "anthem".into(),
-         60,
-          100,
-           remit_tx.clone(), 
-           payer3_rx, 
-           verbose
-    );
-    tokio::spawn(async move { payer1.run().await });
-    tokio::spawn(async move { payer2.run().await });
-    tokio::spawn(async move { payer3.run().await });
+/// Verify an audit log's hash chain, then export its entries, those of one claim if given
+async fn run_audit_export(
+    audit_log_path: &str,
+    format: AuditExportFormat,
+    claim_id: Option<&ClaimId>,
+    output_path: Option<&str>,
+) -> Result<()> {
+    let mut entries = audit_log::read_audit_log(audit_log_path).await?;
+    audit_log::verify_chain(&entries).map_err(|e| anyhow::anyhow!("{}: {}", audit_log_path, e))?;
+    eprintln!("Verified {} audit entries in {}", entries.len(), audit_log_path);
+    if let Some(claim_id) = claim_id {
+        entries.retain(|entry| entry.claim_id == *claim_id);
+    }
+    match output_path {
+        Some(path) => {
+            audit_log::export(std::io::BufWriter::new(std::fs::File::create(path)?), &entries, format)?;
+            eprintln!("Exported {} audit entries to {}", entries.len(), path);
+        }
+        None => audit_log::export(std::io::stdout().lock(), &entries, format)?,
+    }
+    Ok(())
 }
 
-fn setup_reader_task(
-    file_path: &str,
-    claim_input_tx: mpsc::Sender<schema::PayerClaim>,
-    verbose: bool,
-) {
-    let file_path = file_path.to_string();
-    tokio::spawn(async move {
-        if let Err(e) = reader::stream_claims(&file_path, claim_input_tx, verbose).await {
-            eprintln!("Claim stream failed: {:?}", e);
+/// Run one SQL report against a stored run database
+fn run_report(query: &str, db_path: &str, as_of: Option<NaiveDate>, tenant_id: Option<&str>, format: &ReportFormat) -> Result<()> {
+    let query: RunQuery = query.parse()?;
+    let db = RunDb::open(db_path)?;
+    match query {
+        RunQuery::ArAging => {
+            let as_of = match as_of {
+                Some(as_of) => as_of,
+                None => db.ended_on()?,
+            };
+            reporter::print_run_ar_aging_report(as_of, &db.ar_aging(as_of, tenant_id)?, format);
         }
-    });
+        RunQuery::Denials => reporter::print_denials_report(&db.denials_by_reason(tenant_id)?, format),
+        RunQuery::Payments => reporter::print_weekly_payments_report(&db.payments_by_week(tenant_id)?, format),
+    }
+    Ok(())
 }
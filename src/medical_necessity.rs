@@ -0,0 +1,85 @@
+use anyhow::{Context, anyhow};
+use serde::Deserialize;
+
+use crate::outcomes::CodeRange;
+
+/// LCD/NCD-style coverage rule: procedures in the range are medically necessary
+/// only when the claim carries one of the listed diagnoses
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CoverageRule {
+    pub procedures: CodeRange,
+    /// ICD-10-CM codes or category prefixes, e.g. `E11` covers `E11.9`
+    pub diagnoses: Vec<String>,
+}
+
+impl CoverageRule {
+    fn supported_by(&self, diagnosis_code: &str) -> bool {
+        let code = normalize(diagnosis_code);
+        self.diagnoses.iter().any(|prefix| code.starts_with(&normalize(prefix)))
+    }
+}
+
+/// Compare ICD-10 codes without case or the optional dot after the category
+fn normalize(code: &str) -> String {
+    code.trim().replace('.', "").to_uppercase()
+}
+
+/// A payer's medical necessity rules linking procedures to the diagnoses that justify them
+///
+/// The first rule whose range contains a line's procedure code decides whether the
+/// line is covered; procedures no rule covers need no particular diagnosis.
+/// The default policy covers everything
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct CoveragePolicy(Vec<CoverageRule>);
+
+impl CoveragePolicy {
+    pub fn new(rules: Vec<CoverageRule>) -> anyhow::Result<Self> {
+        if let Some(rule) = rules.iter().find(|rule| rule.diagnoses.is_empty()) {
+            return Err(anyhow!("{}: a coverage rule must list at least one diagnosis", rule.procedures));
+        }
+        Ok(Self(rules))
+    }
+
+    /// Read a JSON array of rules, e.g.
+    /// `[{"procedures": "82947-82962", "diagnoses": ["E08", "E11", "R73"]}]`
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path).with_context(|| format!("reading coverage policy {}", path))?;
+        let rules = serde_json::from_str(&contents).with_context(|| format!("parsing coverage policy {}", path))?;
+        Self::new(rules).with_context(|| format!("invalid coverage policy {}", path))
+    }
+
+    /// Whether a procedure is medically necessary given the claim's diagnoses
+    pub fn is_covered(&self, procedure_code: &str, diagnosis_codes: &[String]) -> bool {
+        let code = procedure_code.trim().to_uppercase();
+        match self.0.iter().find(|rule| rule.procedures.contains(&code)) {
+            Some(rule) => diagnosis_codes.iter().any(|diagnosis| rule.supported_by(diagnosis)),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// Test that a procedure under a rule needs a listed diagnosis, matched by category prefix.
+    /// Expected: A glucose test is covered for E11.9 and R73.03 but not for J06.9 alone; an office visit needs nothing; a rule without diagnoses is refused.
+    #[test]
+    fn test_coverage_policy() {
+        let rules = serde_json::from_str(r#"[{"procedures": "82947-82962", "diagnoses": ["E11", "r73.0"]}]"#).unwrap();
+        let policy = CoveragePolicy::new(rules).unwrap();
+        assert!(policy.is_covered("82947", &codes(&["J06.9", "E11.9"])));
+        assert!(policy.is_covered("82950", &codes(&["R7303"])));
+        assert!(!policy.is_covered("82947", &codes(&["J06.9"])));
+        assert!(!policy.is_covered("82947", &[]));
+        assert!(policy.is_covered("99213", &[]));
+
+        let rules = serde_json::from_str(r#"[{"procedures": "82947", "diagnoses": []}]"#).unwrap();
+        assert!(CoveragePolicy::new(rules).is_err());
+    }
+}
@@ -1,42 +1,428 @@
+use crate::queue::{Overflow, OverflowPolicy};
 use crate::remittance::{Remittance, RemittanceRecord};
-use crate::schema::PayerClaim;
-use std::time::Instant;
+use crate::schema::{Attachment, PayerClaim};
+use crate::ids::{ClaimId, ClaimKey, MemberId, PayerId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::Sender;
 
 /// Wraps a claim with a response channel for remittance processing
 /// 
-/// Used by biller to track which claim a remittance response belongs to
+/// Used by biller to track which claim a remittance response belongs to. The claim is
+/// shared, not copied, with the history entry and payer message made from it
 #[derive(Debug)]
 pub struct ClaimEnvelope {
-    pub claim: PayerClaim,
+    pub claim: Arc<PayerClaim>,
     pub response_tx: Sender<RemittanceMessage>,
+    /// Billing organization that submitted the claim
+    pub tenant_id: String,
+    pub metadata: ClaimMetadata,
+}
+
+/// Where a claim entered the simulation
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ClaimOrigin {
+    /// Input file the biller's claims were read from, if any
+    pub file: Option<String>,
+    /// 1-based position in the biller's input: the JSONL line number unless
+    /// earlier lines were rejected or skipped by a checkpoint
+    pub line: Option<u64>,
+}
+
+/// Point in the pipeline a claim has reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Taken off the biller's input
+    Ingested,
+    /// Sent by the biller
+    Submitted,
+    /// Taken off the clearinghouse inbox
+    Received,
+    /// Handed to the payer
+    Routed,
+    /// Payer acknowledged taking the claim in for adjudication
+    Acknowledged,
+    /// Payer finished adjudicating
+    Adjudicated,
+    /// Remittance back at the clearinghouse
+    Remitted,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Ingested => write!(f, "ingested"),
+            Stage::Submitted => write!(f, "submitted"),
+            Stage::Received => write!(f, "received"),
+            Stage::Routed => write!(f, "routed"),
+            Stage::Acknowledged => write!(f, "acknowledged"),
+            Stage::Adjudicated => write!(f, "adjudicated"),
+            Stage::Remitted => write!(f, "remitted"),
+        }
+    }
+}
+
+/// A stage a claim reached and when
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hop {
+    pub stage: Stage,
+    pub at: Instant,
+}
+
+/// Tracing data carried with a claim from the biller through the clearinghouse
+#[derive(Debug, Clone)]
+pub struct ClaimMetadata {
+    /// Unique per submission, unlike the claim id which a resubmission reuses
+    pub correlation_id: String,
+    /// Wall-clock time the biller submitted the claim
+    pub submitted_at: DateTime<Utc>,
+    pub origin: ClaimOrigin,
+    /// Stages reached so far, in order
+    pub hops: Vec<Hop>,
+    /// Root `claim` span the pipeline's spans nest under; disabled until the biller opens it
+    pub span: tracing::Span,
+    /// Id the clearinghouse sent the claim to its payer under, when another tenant's claim
+    /// already had the claim's id
+    pub payer_claim_id: Option<ClaimId>,
+    /// Where to report the front end's verdict on the claim, for claims whose submitter
+    /// acknowledges them, such as file-drop batches answered with a 999
+    pub verdict_tx: Option<Sender<(ClaimId, FrontEndVerdict)>>,
+}
+
+/// How the clearinghouse front end ruled on a new claim before sending it on to its payer
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrontEndVerdict {
+    Accepted,
+    /// `code` is the failed edit's, when a front-end edit turned the claim back
+    Rejected { code: Option<&'static str>, reason: String },
+}
+
+impl ClaimMetadata {
+    /// Metadata for a claim being submitted now, starting at `Stage::Submitted`
+    pub fn new(origin: ClaimOrigin) -> Self {
+        Self {
+            correlation_id: format!("{:016x}", rand::random::<u64>()),
+            submitted_at: Utc::now(),
+            origin,
+            hops: vec![Hop { stage: Stage::Submitted, at: Instant::now() }],
+            span: tracing::Span::none(),
+            payer_claim_id: None,
+            verdict_tx: None,
+        }
+    }
+
+    /// Start the hops with the time the biller took the claim off its input
+    pub fn with_ingested_at(mut self, at: Instant) -> Self {
+        self.hops.insert(0, Hop { stage: Stage::Ingested, at });
+        self
+    }
+
+    /// Report the front end's verdict to whoever asked for it; only the first verdict is reported
+    pub fn report_verdict(&mut self, claim_id: &ClaimId, verdict: FrontEndVerdict) {
+        if let Some(verdict_tx) = self.verdict_tx.take() {
+            // the channel has room for every claim it was handed to
+            let _ = verdict_tx.try_send((claim_id.clone(), verdict));
+        }
+    }
+
+    /// Append a hop for `stage` reached now
+    pub fn record_hop(&mut self, stage: Stage) {
+        self.record_hop_at(stage, Instant::now());
+    }
+
+    /// Append a hop for `stage` reached at `at`, for stages timed by another component
+    pub fn record_hop_at(&mut self, stage: Stage, at: Instant) {
+        self.hops.push(Hop { stage, at });
+    }
+
+    /// Time from the biller submitting the claim to it first reaching `stage`, if it has
+    pub fn time_to(&self, stage: Stage) -> Option<Duration> {
+        let at = |stage| self.hops.iter().find(|hop| hop.stage == stage).map(|hop| hop.at);
+        Some(at(stage)?.saturating_duration_since(at(Stage::Submitted)?))
+    }
+
+    /// Time between each pair of consecutive hops, as (from, to, elapsed)
+    pub fn stage_durations(&self) -> Vec<(Stage, Stage, Duration)> {
+        self.hops
+            .windows(2)
+            .map(|pair| (pair[0].stage, pair[1].stage, pair[1].at.saturating_duration_since(pair[0].at)))
+            .collect()
+    }
+}
+
+impl Default for ClaimMetadata {
+    fn default() -> Self {
+        Self::new(ClaimOrigin::default())
+    }
 }
 
 /// Message sent from Biller to Clearinghouse
 #[derive(Debug)]
 pub enum ClaimMessage {
-    NewClaim(ClaimEnvelope),
+    NewClaim(Box<ClaimEnvelope>),
+    /// Void a claim the biller submitted earlier, if its payer has not yet remitted it
+    Cancel { claim_id: ClaimId, tenant_id: String },
+    /// Documents the claim's payer asked for (a 275 in reply to the payer's request)
+    Attachments { claim_id: ClaimId, tenant_id: String, attachments: Vec<Attachment> },
+    /// Send a claim still waiting on its payer to the payer again, as if the first submission was lost
+    Resubmit { claim_id: ClaimId, tenant_id: String },
+}
+
+impl ClaimMessage {
+    pub fn claim_id(&self) -> &ClaimId {
+        match self {
+            ClaimMessage::NewClaim(envelope) => &envelope.claim.claim_id,
+            ClaimMessage::Cancel { claim_id, .. }
+            | ClaimMessage::Attachments { claim_id, .. }
+            | ClaimMessage::Resubmit { claim_id, .. } => claim_id,
+        }
+    }
+
+    /// One-line description, as the debugger shows it
+    pub fn summary(&self) -> String {
+        match self {
+            ClaimMessage::NewClaim(envelope) => format!(
+                "new claim {} from {} for {}, {:.2} billed",
+                envelope.claim.claim_id,
+                envelope.tenant_id,
+                envelope.claim.insurance.payer_id,
+                envelope.claim.total_charge()
+            ),
+            ClaimMessage::Cancel { claim_id, tenant_id } => format!("cancel claim {} from {}", claim_id, tenant_id),
+            ClaimMessage::Attachments { claim_id, attachments, .. } => {
+                format!("{} attachments for claim {}", attachments.len(), claim_id)
+            }
+            ClaimMessage::Resubmit { claim_id, tenant_id } => format!("resubmit claim {} from {}", claim_id, tenant_id),
+        }
+    }
+}
+
+impl Overflow for ClaimMessage {
+    type Context = ();
+
+    /// Answer the biller with a rejection so it does not wait for a remittance
+    fn overflowed(self, policy: OverflowPolicy, _context: &()) {
+        let mut envelope = match self {
+            ClaimMessage::NewClaim(envelope) => envelope,
+            ClaimMessage::Cancel { claim_id, .. } => {
+                eprintln!("Cancellation of claim {} lost to a full clearinghouse queue", claim_id);
+                return;
+            }
+            ClaimMessage::Attachments { claim_id, .. } => {
+                eprintln!("Attachments for claim {} lost to a full clearinghouse queue", claim_id);
+                return;
+            }
+            ClaimMessage::Resubmit { claim_id, .. } => {
+                eprintln!("Resubmission of claim {} lost to a full clearinghouse queue", claim_id);
+                return;
+            }
+        };
+        tracing::info!(parent: &envelope.metadata.span, %policy, "clearinghouse queue overflow");
+        let reason = match policy {
+            OverflowPolicy::DropOldest => "dropped from full clearinghouse queue",
+            OverflowPolicy::Block | OverflowPolicy::Reject => "clearinghouse queue full",
+        };
+        let claim_id = envelope.claim.claim_id.clone();
+        envelope.metadata.report_verdict(&claim_id, FrontEndVerdict::Rejected { code: None, reason: reason.to_string() });
+        // the response channel belongs to this claim alone, so it has room
+        let _ = envelope.response_tx.try_send(RemittanceMessage::Rejected {
+            claim_id: envelope.claim.claim_id.clone(),
+            reason: reason.to_string(),
+        });
+    }
 }
 
 /// Message sent from Clearinghouse to Payer
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum PayerMessage {
-    Adjudicate(PayerClaim),
+    /// Claim to adjudicate, with the claim's root span to adjudicate under
+    Adjudicate(Arc<PayerClaim>, tracing::Span),
+    /// Stop adjudicating a claim, or reverse its payment if already remitted
+    Void(ClaimId),
+    /// Documents the biller sent for a claim held awaiting them
+    Attachments(ClaimId, Vec<Attachment>),
+}
+
+impl PayerMessage {
+    /// One-line description, as the debugger shows it
+    pub fn summary(&self) -> String {
+        match self {
+            PayerMessage::Adjudicate(claim, _) => {
+                format!("adjudicate claim {}, {} lines, {:.2} billed", claim.claim_id, claim.service_lines.len(), claim.total_charge())
+            }
+            PayerMessage::Void(claim_id) => format!("void claim {}", claim_id),
+            PayerMessage::Attachments(claim_id, attachments) => format!("{} attachments for claim {}", attachments.len(), claim_id),
+        }
+    }
+}
+
+impl Overflow for PayerMessage {
+    /// The clearinghouse's remittance inbox, which the payer would have answered on
+    type Context = Sender<RemittanceMessage>;
+
+    /// Answer the clearinghouse with a rejection as the payer would, so it closes the claim
+    /// and tells the biller instead of waiting for a remittance
+    fn overflowed(self, policy: OverflowPolicy, remittance_tx: &Sender<RemittanceMessage>) {
+        let (claim, span) = match self {
+            PayerMessage::Adjudicate(claim, span) => (claim, span),
+            PayerMessage::Void(claim_id) => {
+                eprintln!("Void of claim {} lost to a full payer queue", claim_id);
+                return;
+            }
+            PayerMessage::Attachments(claim_id, _) => {
+                eprintln!("Attachments for claim {} lost to a full payer queue", claim_id);
+                return;
+            }
+        };
+        tracing::info!(parent: &span, %policy, "payer queue overflow");
+        let reason = match policy {
+            OverflowPolicy::DropOldest => "dropped from full payer queue",
+            OverflowPolicy::Block | OverflowPolicy::Reject => "payer queue full",
+        };
+        let rejected = RemittanceMessage::Rejected { claim_id: claim.claim_id.clone(), reason: reason.to_string() };
+        // the remittance inbox may be full too, and the relay must not wait on it
+        let remittance_tx = remittance_tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = remittance_tx.send(rejected).await {
+                eprintln!("Failed to reject claim {} from a full payer queue: {}", claim.claim_id, e);
+            }
+        });
+    }
 }
 
 /// Message sent from Payer to Clearinghouse
 /// and from Clearinghouse to Biller
-#[derive(Debug)]
+///
+/// Serializable so it can travel in a remittance file when billers submit by file drop
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum RemittanceMessage {
     Processed(Remittance),
+    /// Claim content was already submitted; carries the original remittance
+    /// so the biller can settle without posting it a second time
+    Duplicate(Remittance),
+    /// Claim was refused before adjudication, e.g. its queue was full and overflowed
+    Rejected { claim_id: ClaimId, reason: String },
+    /// Claim was voided at the biller's request before it was remitted
+    Voided { claim_id: ClaimId },
+    /// Payer holds the claim until the biller sends these document types (a 277 request for a 275);
+    /// the remittance follows once they arrive
+    AttachmentRequest { claim_id: ClaimId, document_types: Vec<String> },
+    /// Payer accepted the claim for adjudication (a 277CA), well before it pays; the
+    /// clearinghouse records it rather than passing it on
+    Acknowledged { claim_id: ClaimId },
 }
 
-/// Claim status: submitted or remitted
-#[derive(Debug)]
+impl RemittanceMessage {
+    pub fn claim_id(&self) -> &ClaimId {
+        match self {
+            RemittanceMessage::Processed(remittance) | RemittanceMessage::Duplicate(remittance) => remittance.claim_id(),
+            RemittanceMessage::Rejected { claim_id, .. }
+            | RemittanceMessage::Voided { claim_id }
+            | RemittanceMessage::AttachmentRequest { claim_id, .. }
+            | RemittanceMessage::Acknowledged { claim_id } => claim_id,
+        }
+    }
+
+    /// One-line description, as the debugger shows it
+    pub fn summary(&self) -> String {
+        match self {
+            RemittanceMessage::Processed(remittance) => match remittance.is_denied() {
+                true => format!("remittance for claim {}: denied", remittance.claim_id()),
+                false => format!("remittance for claim {}: {:.2} paid", remittance.claim_id(), remittance.payer_paid()),
+            },
+            RemittanceMessage::Duplicate(remittance) => format!("duplicate of remitted claim {}", remittance.claim_id()),
+            RemittanceMessage::Rejected { claim_id, reason } => format!("claim {} rejected: {}", claim_id, reason),
+            RemittanceMessage::Voided { claim_id } => format!("claim {} voided", claim_id),
+            RemittanceMessage::AttachmentRequest { claim_id, document_types } => {
+                format!("claim {} needs documents {}", claim_id, document_types.join(", "))
+            }
+            RemittanceMessage::Acknowledged { claim_id } => format!("claim {} acknowledged", claim_id),
+        }
+    }
+}
+
+/// Claim status: submitted, remitted, or voided
+#[derive(Debug, Clone)]
 pub enum ClaimStatus {
     Submitted {
-        claim: PayerClaim,
+        claim: Arc<PayerClaim>,
+        tenant_id: String,
         submitted_at: Instant,
+        metadata: ClaimMetadata,
     },
     Remitted(RemittanceRecord),
+    /// Cancelled by the biller before its payer remitted it
+    Voided {
+        claim: Arc<PayerClaim>,
+        tenant_id: String,
+        voided_at: Instant,
+    },
+}
+
+impl ClaimStatus {
+    /// The claim as its biller submitted it
+    pub fn claim(&self) -> &PayerClaim {
+        match self {
+            ClaimStatus::Submitted { claim, .. } | ClaimStatus::Voided { claim, .. } => claim,
+            ClaimStatus::Remitted(record) => record.claim(),
+        }
+    }
+
+    pub fn claim_id(&self) -> &ClaimId {
+        match self {
+            ClaimStatus::Submitted { claim, .. } | ClaimStatus::Voided { claim, .. } => &claim.claim_id,
+            ClaimStatus::Remitted(record) => &record.claim().claim_id,
+        }
+    }
+
+    /// Tenant and claim id the claim is kept under in history
+    pub fn key(&self) -> ClaimKey {
+        ClaimKey::new(self.tenant_id(), self.claim_id().clone())
+    }
+
+    /// Id the claim's payer knows it by, the claim id unless the clearinghouse sent it under another
+    pub fn payer_claim_id(&self) -> &ClaimId {
+        let metadata = match self {
+            ClaimStatus::Submitted { metadata, .. } => Some(metadata),
+            ClaimStatus::Remitted(record) => record.metadata(),
+            ClaimStatus::Voided { .. } => None,
+        };
+        metadata.and_then(|metadata| metadata.payer_claim_id.as_ref()).unwrap_or(self.claim_id())
+    }
+
+    /// Billing organization the claim belongs to
+    pub fn tenant_id(&self) -> &str {
+        match self {
+            ClaimStatus::Submitted { tenant_id, .. } | ClaimStatus::Voided { tenant_id, .. } => tenant_id,
+            ClaimStatus::Remitted(record) => record.tenant_id(),
+        }
+    }
+
+    /// Payer the claim was routed to
+    pub fn payer_id(&self) -> &PayerId {
+        match self {
+            ClaimStatus::Submitted { claim, .. } | ClaimStatus::Voided { claim, .. } => &claim.insurance.payer_id,
+            ClaimStatus::Remitted(record) => record.payer_id(),
+        }
+    }
+}
+
+/// Patient statement issued by the biller after a remittance is posted
+#[derive(Debug, Clone)]
+pub struct PatientStatement {
+    pub claim_id: ClaimId,
+    pub patient_id: MemberId,
+    pub payer_id: PayerId,
+    pub amount: f64,
+}
+
+/// Message sent from Biller to PatientPayer
+#[derive(Debug)]
+pub enum PatientMessage {
+    Statement(PatientStatement),
 }
@@ -0,0 +1,43 @@
+/// Procedure code modifiers that change what a payer allows for a service line
+///
+/// Factors scale the amount the payer would otherwise allow. A bilateral procedure is
+/// allowed 150% of the single-side amount, though never more than the line bills
+const PRICING: [(&str, f64, &str); 5] = [
+    ("26", 0.40, "professional component"),
+    ("TC", 0.60, "technical component"),
+    ("50", 1.50, "bilateral procedure"),
+    ("51", 0.50, "multiple procedures"),
+    ("52", 0.50, "reduced services"),
+];
+
+/// Modifier marking a service delivered by synchronous audio-video telehealth
+pub const TELEHEALTH: &str = "95";
+
+/// Whether a modifier marks a telehealth service, with or without a leading dash
+pub fn is_telehealth(modifier: &str) -> bool {
+    modifier.trim().trim_start_matches('-') == TELEHEALTH
+}
+
+/// How a modifier changes the allowed amount of a line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModifierPricing {
+    pub modifier: &'static str,
+    /// Fraction of the otherwise allowed amount the payer allows
+    pub factor: f64,
+    pub description: &'static str,
+}
+
+/// Pricing effect of a modifier, or `None` for modifiers that do not change payment
+///
+/// Accepts the bare code or with a leading dash, in any case
+pub fn pricing(modifier: &str) -> Option<ModifierPricing> {
+    let code = modifier.trim().trim_start_matches('-');
+    PRICING
+        .iter()
+        .find(|(known, _, _)| known.eq_ignore_ascii_case(code))
+        .map(|(modifier, factor, description)| ModifierPricing {
+            modifier,
+            factor: *factor,
+            description,
+        })
+}
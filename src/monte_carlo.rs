@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::config::Config;
+use crate::ledger::{AdjustmentReason, EntryKind};
+use crate::message::ClaimStatus;
+use crate::schema::PayerClaim;
+use crate::simulation::{BillerSpec, PayerSpec, Simulation};
+
+/// How many runs of a scenario to make, and how many at once
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonteCarloOptions {
+    pub runs: usize,
+    /// Runs in flight at once
+    pub parallel: usize,
+    /// Seed of the first run; run `n` is seeded `seed + n`
+    pub seed: u64,
+    /// Real seconds per simulated day, converting payer delays into days in AR
+    pub secs_per_day: f64,
+}
+
+impl Default for MonteCarloOptions {
+    fn default() -> Self {
+        Self {
+            runs: 20,
+            // runs spend most of their time waiting on payer delays, not on a CPU
+            parallel: 10,
+            seed: 0,
+            secs_per_day: Config::default().secs_per_day,
+        }
+    }
+}
+
+/// Key results of one run
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunMetrics {
+    pub seed: u64,
+    pub remitted: usize,
+    /// Gross charges less contractual adjustments, across every tenant's ledger
+    pub net_revenue: f64,
+    /// Average simulated days from submission to remittance
+    pub days_in_ar: f64,
+    /// Share of remitted claims the payer paid nothing on
+    pub denial_rate: f64,
+}
+
+/// Summary statistics and percentile bands of one metric across runs
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Distribution {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub p5: f64,
+    pub p25: f64,
+    pub p50: f64,
+    pub p75: f64,
+    pub p95: f64,
+    pub max: f64,
+}
+
+impl Distribution {
+    /// Distribution of `values`, percentiles interpolated between the nearest ranks
+    pub fn of(values: &[f64]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let mean = sorted.iter().sum::<f64>() / sorted.len() as f64;
+        let variance = sorted.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / sorted.len() as f64;
+        let percentile = |p: f64| {
+            let rank = p / 100.0 * (sorted.len() - 1) as f64;
+            let (low, high) = (sorted[rank.floor() as usize], sorted[rank.ceil() as usize]);
+            low + (high - low) * rank.fract()
+        };
+        Self {
+            mean,
+            std_dev: variance.sqrt(),
+            min: sorted[0],
+            p5: percentile(5.0),
+            p25: percentile(25.0),
+            p50: percentile(50.0),
+            p75: percentile(75.0),
+            p95: percentile(95.0),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// Every run's results and the distribution of each metric across them
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonteCarloReport {
+    /// In seed order
+    pub runs: Vec<RunMetrics>,
+    pub net_revenue: Distribution,
+    pub days_in_ar: Distribution,
+    pub denial_rate: Distribution,
+}
+
+/// Run the same claims through the same payers `options.runs` times, each with its own seed
+///
+/// Each run is a full simulation under one default-tenant biller. The seed decides which claims
+/// payers deny, each line's outcome, how long each claim takes, and which remittances are priced
+/// wrong; patient payments and the rest of the pipeline are not seeded
+pub async fn run(claims: &[PayerClaim], payers: &[PayerSpec], options: &MonteCarloOptions) -> anyhow::Result<MonteCarloReport> {
+    if options.runs == 0 || claims.is_empty() || payers.is_empty() {
+        return Err(anyhow::anyhow!("Monte Carlo needs at least one run, one claim, and one payer"));
+    }
+    let slots = Arc::new(Semaphore::new(options.parallel.max(1)));
+    let mut tasks = JoinSet::new();
+    for seed in (0..options.runs as u64).map(|n| options.seed.wrapping_add(n)) {
+        // claims are copied only once a run can start, bounding memory to the runs in flight
+        let slot = slots.clone().acquire_owned().await?;
+        let (claims, payers, secs_per_day) = (claims.to_vec(), payers.to_vec(), options.secs_per_day);
+        tasks.spawn(async move {
+            let metrics = run_once(claims, payers, seed, secs_per_day).await;
+            drop(slot);
+            metrics
+        });
+    }
+    let mut runs = Vec::with_capacity(options.runs);
+    while let Some(metrics) = tasks.join_next().await {
+        runs.push(metrics??);
+    }
+    runs.sort_by_key(|metrics| metrics.seed);
+    let distribution = |metric: fn(&RunMetrics) -> f64| Distribution::of(&runs.iter().map(metric).collect::<Vec<_>>());
+    Ok(MonteCarloReport {
+        net_revenue: distribution(|metrics| metrics.net_revenue),
+        days_in_ar: distribution(|metrics| metrics.days_in_ar),
+        denial_rate: distribution(|metrics| metrics.denial_rate),
+        runs,
+    })
+}
+
+async fn run_once(claims: Vec<PayerClaim>, payers: Vec<PayerSpec>, seed: u64, secs_per_day: f64) -> anyhow::Result<RunMetrics> {
+    let config = Config { secs_per_day, ..Config::default() };
+    let mut builder = Simulation::builder().config(config).biller(BillerSpec::default().with_source(claims));
+    for payer in payers {
+        builder = builder.payer(payer.with_seed(seed));
+    }
+    let mut sim = builder.build()?;
+    sim.wait().await;
+
+    let records = sim.history().snapshot().await;
+    let (mut remitted, mut denied, mut days) = (0, 0, 0.0);
+    for status in records.values() {
+        if let ClaimStatus::Remitted(record) = status {
+            remitted += 1;
+            denied += usize::from(record.remittance().is_denied());
+            days += record.elapsed().as_secs_f64() / secs_per_day;
+        }
+    }
+    let mut net_revenue = 0.0;
+    for ledger in sim.ledgers().values() {
+        for entry in ledger.lock().await.entries() {
+            // as a period close counts it
+            match entry.kind {
+                EntryKind::Charge => net_revenue += entry.amount,
+                EntryKind::Adjustment(AdjustmentReason::Contractual) => net_revenue -= entry.amount,
+                _ => {}
+            }
+        }
+    }
+    sim.shutdown();
+    let per_claim = |total: f64, count: usize| if count == 0 { 0.0 } else { total / count as f64 };
+    Ok(RunMetrics {
+        seed,
+        remitted,
+        net_revenue,
+        days_in_ar: per_claim(days, remitted),
+        denial_rate: per_claim(denied as f64, remitted),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::ClaimId;
+    use crate::test_utils::mock_claim;
+
+    /// Test that seeded runs are summarized and repeat exactly under the same seeds.
+    /// Expected: One result per seed, in order, with ordered percentile bands; a second batch with the same seeds matches; percentiles interpolate.
+    #[tokio::test]
+    async fn test_monte_carlo_runs() {
+        let claims: Vec<PayerClaim> = (0..20)
+            .map(|n| PayerClaim { claim_id: ClaimId::new(format!("mc-{}", n)), ..mock_claim() })
+            .collect();
+        let payers = [PayerSpec::new("medicare", 0, 0).with_denial_rate(0.5)];
+        let options = MonteCarloOptions { runs: 4, parallel: 2, seed: 7, secs_per_day: 1.0 };
+
+        let report = run(&claims, &payers, &options).await.unwrap();
+        assert_eq!(report.runs.iter().map(|run| run.seed).collect::<Vec<_>>(), vec![7, 8, 9, 10]);
+        assert!(report.runs.iter().all(|run| run.remitted == 20));
+        let rates = report.denial_rate;
+        assert!(rates.min <= rates.p5 && rates.p5 <= rates.p50 && rates.p50 <= rates.p95 && rates.p95 <= rates.max);
+        assert!(rates.max > 0.0 && rates.min < 1.0, "{:?}", rates);
+        let again = run(&claims, &payers, &options).await.unwrap();
+        assert_eq!(again.denial_rate, report.denial_rate);
+        assert_eq!(again.net_revenue, report.net_revenue);
+
+        let distribution = Distribution::of(&[4.0, 1.0, 3.0, 2.0, 5.0]);
+        assert_eq!((distribution.mean, distribution.p50, distribution.p25), (3.0, 3.0, 2.0));
+        assert!(run(&claims, &payers, &MonteCarloOptions { runs: 0, ..options }).await.is_err());
+    }
+}
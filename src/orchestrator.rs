@@ -0,0 +1,579 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use tokio::sync::Mutex;
+
+use crate::api::{self, ApiState};
+use crate::audit_log::AuditLog;
+use crate::claim_ids::ClaimIdGenerator;
+use crate::claim_notes::ClaimNotes;
+use crate::claim_source::JsonlFile;
+use crate::clock::SimClock;
+use crate::config::Config;
+use crate::control::TELEHEALTH_PARITY;
+use crate::debugger::{self, Debugger};
+use crate::deposits::{DepositFeed, DepositSettings};
+use crate::edits::EditLevel;
+use crate::event_log::EventLog;
+use crate::history::History;
+use crate::ids::PayerId;
+use crate::invariants::{self, InvariantChecker, Violation};
+use crate::json_faker;
+use crate::kpi;
+use crate::ledger::Ledger;
+use crate::patient_payer::CollectionsPolicy;
+use crate::payer_mix::PayerMix;
+use crate::payer_rules::{RULES_POLL_INTERVAL, RuleBook, RuleWatcher};
+use crate::period_close;
+use crate::phi::Deidentification;
+use crate::pseudonym::Pseudonymizer;
+use crate::rate_limiter::{RateLimiter, RateSettings};
+use crate::reader;
+use crate::reconciliation;
+use crate::report_engine::HistoryTotals;
+use crate::remittance_sink::SinkSpec;
+use crate::report_format::ReportFormat;
+use crate::reporter::{self, ReportSources};
+use crate::roster::{Roster, RosterOptions};
+use crate::run_db::RunDb;
+use crate::simulation::{BillerSpec, PayerSpec, Simulation};
+use crate::sla::SlaMonitor;
+use crate::stop::{StopMonitor, Stopped};
+use crate::supervisor::{Supervisor, SupervisorSettings};
+use crate::tenant::{self, TenantConfig};
+use crate::watchdog::{Watchdog, WatchdogSettings};
+use crate::workqueue::Workqueue;
+
+/// Payers the simulation runs, as fake claims name them
+pub const PAYER_IDS: [&str; 3] = ["medicare", "united_health_group", "anthem"];
+
+/// Each payer's adjudication delay range in seconds, in `PAYER_IDS` order
+pub const PAYER_DELAY_SECS: [(u64, u64); 3] = [(10, 30), (5, 6), (60, 100)];
+
+/// Each payer's acknowledgment delay range in seconds, in `PAYER_IDS` order: payers ack within
+/// seconds however long they take to pay
+pub const PAYER_ACK_DELAY_SECS: [(u64, u64); 3] = [(1, 3), (0, 1), (2, 5)];
+
+/// The payers the simulation runs, before the run's settings are applied to them
+pub fn simulated_payers() -> Vec<PayerSpec> {
+    PAYER_IDS
+        .into_iter()
+        .zip(PAYER_DELAY_SECS)
+        .zip(PAYER_ACK_DELAY_SECS)
+        .map(|((payer_id, (min_delay_secs, max_delay_secs)), (min_ack_secs, max_ack_secs))| {
+            PayerSpec::new(payer_id, min_delay_secs, max_delay_secs).with_ack_delay(min_ack_secs, max_ack_secs)
+        })
+        .collect()
+}
+
+/// Why a run ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunEnd {
+    /// Every tenant received all its remittances
+    Completed,
+    /// A `--stop-when` condition was met; the billers were stopped
+    Stopped(Stopped),
+    /// Ctrl-C was pressed
+    Interrupted,
+}
+
+/// Everything a started simulation run is made of: the pipeline, the tasks around it, and the
+/// logs, clock, and shared state they write to
+///
+/// `start` returns it with every task running; `wait` until the run ends, then `finish` to
+/// write its exports
+pub struct SimulationHandles {
+    pub sim: Simulation,
+    pub clock: SimClock,
+    pub history: History,
+    pub ledgers: BTreeMap<String, Arc<Mutex<Ledger>>>,
+    pub supervisor: Supervisor,
+    pub rate_limiter: RateLimiter,
+    pub rules: RuleBook,
+    pub notes: ClaimNotes,
+    pub event_log: Option<EventLog>,
+    pub audit_log: Option<AuditLog>,
+    pub deidentification: Deidentification,
+    invariants: Option<InvariantChecker>,
+    payer_mix: Option<PayerMix>,
+    stop: Option<StopMonitor>,
+    config: Config,
+}
+
+/// Wire up and start a run of `config` with these payers, as the `healthtechsim` binary does
+///
+/// Builds the pipeline with a biller per tenant, then starts the rule watcher, debugger console,
+/// HTTP API, period close, bank reconciliation, workqueue, reporter, and watchdog as `config`
+/// asks. The payers take the run's workers, error rate, mismatch policy, telehealth rates,
+/// acknowledgment delays, rules, and rosters; those routed to gRPC payers are left out
+pub async fn start(config: &Config, payers: Vec<PayerSpec>) -> Result<SimulationHandles> {
+    let deidentification = match (&config.pseudonym_key_path, config.redact_phi) {
+        (Some(path), _) => Deidentification::Pseudonymize(Pseudonymizer::load(path)?),
+        (None, true) => Deidentification::redact(),
+        (None, false) => Deidentification::Off,
+    };
+    let event_log = match &config.event_log_path {
+        Some(path) => Some(EventLog::open(path).await?.with_deidentification(deidentification.clone())),
+        None => None,
+    };
+    let audit_log = match &config.audit_log_path {
+        Some(path) => Some(AuditLog::open(path).await?),
+        None => None,
+    };
+    // ingestion rate is shared across tenants
+    let rate_limiter = RateLimiter::new(RateSettings {
+        claims_per_sec: 1.0 / config.ingest_rate.max(1) as f64,
+        burst: config.burst,
+    })?;
+
+    // every simulated payer reads one rule book, replaced as a whole when the rule files change
+    let rules = RuleBook::new(config.rule_paths().load()?);
+    if config.watch_rules {
+        let mut watcher = RuleWatcher::new(config.rule_paths(), rules.clone());
+        if let Some(event_log) = &event_log {
+            watcher = watcher.with_event_log(event_log.clone());
+        }
+        println!("Watching payer rules for changes: {}", config.rule_paths().paths().collect::<Vec<_>>().join(", "));
+        tokio::spawn(watcher.run(RULES_POLL_INTERVAL));
+    }
+    let payer_ids: Vec<PayerId> = payers.iter().map(|payer| payer.payer_id.clone()).collect();
+    let mut fake_rng = match config.fake_seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_rng(&mut rand::rng()),
+    };
+    let rosters = generate_rosters(config, &payer_ids, &mut fake_rng)?;
+    let payer_mix = load_payer_mix(config, &payer_ids)?;
+    let payer = |payer: PayerSpec| {
+        let telehealth_rate = config.telehealth_rates.get(&payer.payer_id).copied().unwrap_or(TELEHEALTH_PARITY);
+        let roster = rosters.get(&payer.payer_id).cloned().unwrap_or_default();
+        let payer = match config.ack_delays.get(&payer.payer_id) {
+            Some(&(min_secs, max_secs)) => payer.with_ack_delay(min_secs, max_secs),
+            None => payer,
+        };
+        payer
+            .with_workers(config.payer_workers)
+            .with_error_rate(config.remit_error_rate)
+            .with_mismatch_policy(config.mismatch_policy)
+            .with_telehealth_rate(telehealth_rate)
+            .with_rules(rules.clone())
+            .with_roster(roster)
+    };
+    let mut builder = Simulation::builder()
+        .config(config.clone())
+        .channel_capacity(config.channel_capacity)
+        .overflow_policy(config.overflow_policy)
+        .clearinghouse_shards(config.clearinghouse_shards)
+        .edits(config.edit_rates);
+    // payers routed to external gRPC services are not simulated
+    for spec in payers {
+        if !config.grpc_payers.contains_key(&spec.payer_id) {
+            builder = builder.payer(payer(spec));
+        }
+    }
+    #[cfg(feature = "grpc")]
+    for (payer_id, endpoint) in &config.grpc_payers {
+        builder = builder.grpc_payer(payer_id.clone(), endpoint.clone());
+    }
+    #[cfg(not(feature = "grpc"))]
+    if !config.grpc_payers.is_empty() {
+        return Err(anyhow::anyhow!("External payers require building with `--features grpc`"));
+    }
+    if let Some(event_log) = &event_log {
+        builder = builder.event_log(event_log.clone());
+    }
+    if let Some(audit_log) = &audit_log {
+        builder = builder.audit_log(audit_log.clone());
+    }
+    builder = builder.deidentification(deidentification.clone());
+    if let Some(file_drop) = &config.file_drop {
+        builder = builder.file_drop(file_drop.clone());
+    }
+    if let Some(dir) = &config.remittance_files_dir {
+        builder = builder.remittance_files(dir.as_str());
+    }
+    // downstream sinks are shared, each delivery naming its tenant
+    let sinks = config.remittance_sinks.iter().map(SinkSpec::open).collect::<Result<Vec<_>>>()?;
+    // each tenant gets its own reader, biller, ledger, and patient payer
+    for tenant in &config.tenant_configs() {
+        let biller = tenant_biller(config, tenant, rate_limiter.clone(), &rosters, payer_mix.as_ref(), &mut fake_rng, &deidentification).await;
+        builder = builder.biller(sinks.iter().cloned().fold(biller, BillerSpec::with_sink));
+    }
+    let supervisor = Supervisor::new(SupervisorSettings {
+        // a paused debugger holds components on purpose
+        stall_after: (config.stall_secs > 0 && !config.debug).then(|| Duration::from_secs(config.stall_secs)),
+        max_restarts: config.max_restarts,
+    });
+    builder = builder.supervisor(supervisor.clone());
+    let debugger = config.debug.then(Debugger::paused);
+    if let Some(debugger) = &debugger {
+        builder = builder.debugger(debugger.clone());
+    }
+    let sim = builder.build()?;
+    if let Some(debugger) = debugger {
+        setup_debug_console(debugger);
+    }
+
+    // work history billing workers attach to claims, kept in the audit trail too
+    let mut notes = ClaimNotes::new().with_inventory(sim.inventory().clone());
+    if let Some(audit_log) = &audit_log {
+        notes = notes.with_audit_log(audit_log.clone());
+    }
+
+    if let Some(addr) = &config.api_addr {
+        let state = ApiState {
+            rate_limiter: Some(rate_limiter.clone()),
+            history: Some(sim.history()),
+            controls: sim.controls().clone(),
+            portals: sim.portals().clone(),
+            claims: Some(sim.claim_inbox()),
+            held: sim.held_remittances().clone(),
+            format: config.report_format,
+            audit_log: audit_log.clone(),
+            supervisor: Some(supervisor.clone()),
+            notes: notes.clone(),
+            inventory: Some(sim.inventory().clone()),
+            deidentification: deidentification.clone(),
+        };
+        setup_api_task(addr, state, config.verbose);
+    }
+
+    // simulated calendar starting today, driving month-end close, payment runs, and plan years
+    let clock = sim.clock();
+    for (tenant_id, ledger) in sim.ledgers() {
+        setup_period_close_task(tenant_id, ledger.clone(), clock, config.report_format, config.verbose);
+    }
+    let feed = DepositFeed::new(DepositSettings::default())?;
+    setup_bank_reconciliation_task(feed, sim.history(), sim.ledgers().clone(), clock, config.report_format, config.verbose);
+    // billing staff working the denials, underpayments, and exceptions remittances turn up
+    let workqueue = (!config.staff.is_empty()).then(|| Workqueue::new(config.staff.clone()).with_notes(notes.clone()));
+    if let Some(workqueue) = &workqueue {
+        tokio::spawn(workqueue.clone().run(sim.history(), clock, invariants::CHECK_INTERVAL));
+    }
+    let sources = ReportSources {
+        history: sim.history(),
+        ledgers: sim.ledgers().clone(),
+        portals: sim.portals().clone(),
+        inventory: sim.inventory().clone(),
+        workqueue,
+        deidentification: deidentification.clone(),
+    };
+    let sla = SlaMonitor::new(config.slas.clone());
+    setup_reporter_task(&supervisor, sources, sla, config.forecast_days, clock, config.report_format, config.verbose);
+    if let Some(secs) = config.stuck_after_secs {
+        let settings = WatchdogSettings { stuck_after: Duration::from_secs(secs), action: config.stuck_action };
+        let mut watchdog = Watchdog::new(settings, sim.history())
+            .with_portals(sim.portals().clone())
+            .with_claim_inbox(sim.claim_inbox());
+        if let Some(event_log) = &event_log {
+            watchdog = watchdog.with_event_log(event_log.clone());
+        }
+        tokio::spawn(watchdog.run(invariants::CHECK_INTERVAL));
+    }
+
+    let invariants = config
+        .invariant_max_pending_secs
+        .map(|secs| InvariantChecker::new(Duration::from_secs(secs)));
+    let (history, ledgers) = (sim.history(), sim.ledgers().clone());
+    let stop = StopMonitor::new(config.stop_conditions.clone(), history.clone(), clock);
+    Ok(SimulationHandles {
+        sim,
+        clock,
+        history,
+        ledgers,
+        supervisor,
+        rate_limiter,
+        rules,
+        notes,
+        event_log,
+        audit_log,
+        deidentification,
+        invariants,
+        payer_mix,
+        stop: Some(stop),
+        config: config.clone(),
+    })
+}
+
+impl SimulationHandles {
+    /// Wait until every tenant has received all its remittances, a stop condition is met, or
+    /// Ctrl-C is pressed; an invariant violation ends the run with an error
+    pub async fn wait(&mut self) -> Result<RunEnd> {
+        let stop = self.stop.take().ok_or_else(|| anyhow::anyhow!("The run has already ended"))?;
+        let (history, ledgers) = (self.history.clone(), self.ledgers.clone());
+        tokio::select! {
+            _ = self.sim.wait() => {
+                println!("All remittances received. Shutting down.");
+                if let Some(checker) = &self.invariants {
+                    fail_on_violations(&checker.check_finished(&history, &ledgers).await)?;
+                }
+                Ok(RunEnd::Completed)
+            }
+            violations = watch_invariants(self.invariants.clone(), history.clone(), ledgers.clone()) => {
+                fail_on_violations(&violations)?;
+                Ok(RunEnd::Completed)
+            }
+            stopped = stop.run(invariants::CHECK_INTERVAL) => {
+                println!("Stop condition met: {}. Shutting down.", stopped);
+                // no new claims enter while the run is written up
+                for biller in self.sim.controls().billers.values() {
+                    biller.stop();
+                }
+                Ok(RunEnd::Stopped(stopped))
+            }
+            _ = tokio::signal::ctrl_c() => {
+                println!("Shutdown signal received.");
+                Ok(RunEnd::Interrupted)
+            }
+        }
+    }
+
+    /// Report overflow and edit rejections, the run's final revenue-cycle KPIs, and expected vs actual
+    /// reimbursement under the payer mix if there is one; flush the logs and remittance files, and write
+    /// the procedure CSV and run database
+    pub async fn finish(self) -> Result<()> {
+        let config = &self.config;
+        let metrics = self.sim.metrics().await;
+        if metrics.dropped + metrics.rejected > 0 {
+            println!(
+                "Claim inboxes overflowed ({}): {} claims dropped, {} rejected",
+                config.overflow_policy, metrics.dropped, metrics.rejected
+            );
+        }
+        if metrics.edit_rejected > 0 {
+            let levels: Vec<String> =
+                EditLevel::ALL.iter().map(|level| format!("{} {}", self.sim.edit_stats().rejected(*level), level)).collect();
+            println!("Clearinghouse edits rejected {} claims ({})", metrics.edit_rejected, levels.join(", "));
+        }
+        if let Some(event_log) = &self.event_log {
+            event_log.flush().await;
+        }
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.flush().await;
+        }
+        if let Some(files) = self.sim.remittance_files() {
+            files.flush().await;
+        }
+        let mut ledgers = Vec::new();
+        for ledger in self.ledgers.values() {
+            ledgers.push(ledger.lock().await);
+        }
+        let records = self.history.snapshot().await;
+        let kpis = kpi::revenue_cycle_kpis(&HistoryTotals::from_records(&records), ledgers.iter().map(|ledger| &**ledger), &self.clock, Instant::now());
+        drop(ledgers);
+        reporter::print_kpi_report(&kpis, &config.report_format);
+        if let Some(payer_mix) = &self.payer_mix {
+            reporter::print_reimbursement_report(&payer_mix.reimbursement(&records), &config.report_format);
+        }
+        if let Some(path) = &config.procedure_csv_path {
+            let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+            reporter::write_procedure_csv(file, &self.history.snapshot().await, &config.report_format)?;
+            println!("Wrote procedure code revenue to {}", path);
+        }
+        if let Some(path) = &config.run_db_path {
+            let mut db = RunDb::create(path, self.clock.now().date())?.with_deidentification(self.deidentification.clone());
+            db.store_claims(&self.history.snapshot().await, &self.clock)?;
+            for (tenant_id, ledger) in &self.ledgers {
+                db.store_ledger(tenant_id, &*ledger.lock().await, &self.clock)?;
+            }
+            println!("Stored run in {}", path);
+        }
+        Ok(())
+    }
+}
+
+/// Member rosters of `payer_ids`, when `--roster-churn` asks for them, drawn with `rng`
+fn generate_rosters(config: &Config, payer_ids: &[PayerId], rng: &mut StdRng) -> Result<BTreeMap<PayerId, Arc<Roster>>> {
+    let Some(monthly_churn) = config.roster_churn else {
+        return Ok(BTreeMap::new());
+    };
+    let options = RosterOptions {
+        members: config.roster_members,
+        monthly_churn,
+        ..RosterOptions::default()
+    };
+    let mut rosters = BTreeMap::new();
+    for payer_id in payer_ids {
+        let roster = Roster::generate(payer_id, &options, rng)?;
+        rosters.insert(payer_id.clone(), Arc::new(roster));
+    }
+    Ok(rosters)
+}
+
+/// The `--payer-mix` table, which may only name payers among `payer_ids`
+fn load_payer_mix(config: &Config, payer_ids: &[PayerId]) -> Result<Option<PayerMix>> {
+    let Some(path) = &config.payer_mix_path else {
+        return Ok(None);
+    };
+    let mix = PayerMix::load(path)?;
+    if let Some(unknown) = mix.payer_ids().find(|payer_id| !payer_ids.contains(payer_id)) {
+        let known = payer_ids.iter().map(|payer_id| payer_id.as_str()).collect::<Vec<_>>().join(", ");
+        return Err(anyhow::anyhow!("--payer-mix names unknown payer {} (known payers: {})", unknown, known));
+    }
+    Ok(Some(mix))
+}
+
+/// Describe one billing organization's biller: its reader input, claim count, and patient payer
+///
+/// With explicit tenants, checkpoint and rejects files are scoped per tenant; with rosters,
+/// fake claims are for roster members, and with a payer mix they are billed in its proportions;
+/// `fake_rng` draws each fake claim's payer and member
+async fn tenant_biller(
+    config: &Config,
+    tenant: &TenantConfig,
+    rate_limiter: RateLimiter,
+    rosters: &BTreeMap<PayerId, Arc<Roster>>,
+    payer_mix: Option<&PayerMix>,
+    fake_rng: &mut StdRng,
+    deidentification: &Deidentification,
+) -> BillerSpec {
+    let scoped = !config.tenants.is_empty();
+    let scope = |path: &String| match scoped {
+        true => tenant::scoped_path(path, &tenant.tenant_id),
+        false => path.clone(),
+    };
+    let checkpoint_path = config.checkpoint_path.as_ref().map(scope);
+    let rejects_path = config.rejects_path.as_ref().map(scope);
+
+    // claims already streamed by a previous run, if resuming from a checkpoint
+    let resumed_lines = match &checkpoint_path {
+        Some(checkpoint_path) => reader::load_checkpoint(checkpoint_path, &tenant.file_path).await as usize,
+        None => 0,
+    };
+
+    // each billing organization's claims are prefixed with its tenant id
+    let claim_ids = match scoped {
+        true => ClaimIdGenerator::new(&tenant.tenant_id),
+        false => ClaimIdGenerator::default(),
+    };
+
+    let mut claims = config.fake_claims;
+    let mut write_fake_claims = |path: &str| match (rosters.is_empty(), payer_mix) {
+        (false, _) => json_faker::write_roster_claims_jsonl(path, claims, rosters, payer_mix, &claim_ids, fake_rng),
+        (true, Some(payer_mix)) => json_faker::write_mixed_claims_jsonl(path, claims, payer_mix, &claim_ids, fake_rng),
+        (true, None) => json_faker::write_fake_claims_jsonl(path, claims, &claim_ids),
+    };
+    if tenant.file_path == reader::STDIN_PATH {
+        // unknown count: run until stdin is exhausted
+        claims = 0;
+    } else if resumed_lines > 0 {
+        claims = claims.saturating_sub(resumed_lines);
+        println!("[{}] Resuming with {} claims left to process", tenant.tenant_id, claims);
+    } else if !scoped {
+        write_fake_claims(&config.generated_claims_path)
+            .expect("Failed to write fake claims");
+        println!("Wrote {} fake claims to {}", claims, config.generated_claims_path);
+    } else if !std::path::Path::new(&tenant.file_path).exists() {
+        write_fake_claims(&tenant.file_path)
+            .expect("Failed to write fake claims");
+        println!("[{}] Wrote {} fake claims to {}", tenant.tenant_id, claims, tenant.file_path);
+    } else {
+        // existing tenant file of unknown length: run until it is exhausted
+        claims = 0;
+    }
+
+    // a resumed run reads again any claim the biller had not submitted
+    let processed = checkpoint_path.is_some().then(reader::ProcessedClaims::new);
+    let options = reader::ReaderOptions {
+        checkpoint_path,
+        rejects_path,
+        rate_limiter: Some(rate_limiter),
+        validation: config.validation,
+        currencies: config.currencies.clone(),
+        deidentification: deidentification.clone(),
+        processed: processed.clone(),
+    };
+    let biller = BillerSpec::new(&tenant.tenant_id)
+        .with_source(JsonlFile::new(tenant.file_path.clone()).with_options(options))
+        .with_total_claims(claims)
+        .with_claim_ids(claim_ids)
+        // 70% of patients pay each statement, 5-20 seconds after it is issued
+        .with_patient_payer(0.7, 5, 20)
+        .with_collections(CollectionsPolicy {
+            statements: config.statements,
+            action: config.collections_action,
+            ..CollectionsPolicy::default()
+        });
+    match processed {
+        Some(processed) => biller.with_processed_claims(processed),
+        None => biller,
+    }
+}
+
+/// Violations once an invariant breaks; never resolves when checking is disabled
+async fn watch_invariants(
+    checker: Option<InvariantChecker>,
+    history: History,
+    ledgers: BTreeMap<String, Arc<Mutex<Ledger>>>,
+) -> Vec<Violation> {
+    match checker {
+        Some(checker) => checker.watch(history, ledgers, invariants::CHECK_INTERVAL).await,
+        None => std::future::pending().await,
+    }
+}
+
+fn fail_on_violations(violations: &[Violation]) -> Result<()> {
+    if violations.is_empty() {
+        return Ok(());
+    }
+    for violation in violations {
+        eprintln!("Invariant violated: {}", violation);
+    }
+    Err(anyhow::anyhow!("{} simulation invariant violations", violations.len()))
+}
+
+fn setup_reporter_task(
+    supervisor: &Supervisor,
+    sources: ReportSources,
+    sla: SlaMonitor,
+    forecast_days: Option<f64>,
+    clock: SimClock,
+    format: ReportFormat,
+    verbose: bool,
+) {
+    // everything the reporter reads is shared, so a restarted reporter picks up where it left off
+    let heartbeat = supervisor.register("reporter", "reporter");
+    supervisor.supervise(&heartbeat.clone(), move || {
+        reporter::run_reporter(sources.clone(), sla.clone(), forecast_days, clock, format, heartbeat.clone(), verbose)
+    });
+}
+
+fn setup_period_close_task(tenant_id: &str, ledger: Arc<Mutex<Ledger>>, clock: SimClock, format: ReportFormat, verbose: bool) {
+    tokio::spawn(period_close::run_period_close(tenant_id.to_string(), ledger, clock, format, verbose));
+}
+
+fn setup_bank_reconciliation_task(
+    feed: DepositFeed,
+    history: History,
+    ledgers: BTreeMap<String, Arc<Mutex<Ledger>>>,
+    clock: SimClock,
+    format: ReportFormat,
+    verbose: bool,
+) {
+    tokio::spawn(reconciliation::run_bank_reconciliation(feed, history, ledgers, clock, format, verbose));
+}
+
+fn setup_debug_console(debugger: Debugger) {
+    let (tx, rx) = tokio::sync::mpsc::channel(16);
+    // a blocking read on tokio's stdin would hold up shutdown until the next line is entered
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines().map_while(Result::ok) {
+            if tx.blocking_send(line).is_err() {
+                break;
+            }
+        }
+    });
+    tokio::spawn(debugger::run_console(debugger, rx));
+}
+
+fn setup_api_task(addr: &str, state: ApiState, verbose: bool) {
+    let addr = addr.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = api::serve(&addr, state, verbose).await {
+            eprintln!("HTTP API failed: {:?}", e);
+        }
+    });
+}
@@ -0,0 +1,171 @@
+use anyhow::{Context, anyhow};
+use rand::Rng;
+use serde::Deserialize;
+use std::fmt;
+
+/// How a payer rules on one service line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Outcome {
+    /// Priced as billed
+    #[default]
+    Pay,
+    /// Denied as not medically necessary (CO-50)
+    DenyMedicalNecessity,
+    /// Denied for lack of prior authorization (CO-197)
+    DenyAuthorization,
+    /// Paid at a lower level of service than billed (CO-150)
+    Downcode,
+}
+
+/// Inclusive range of procedure codes, e.g. `70010-79999`, or a single code
+///
+/// Codes are compared as strings, so both ends should have the same length
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct CodeRange {
+    first: String,
+    last: String,
+}
+
+impl CodeRange {
+    pub fn contains(&self, code: &str) -> bool {
+        self.first.as_str() <= code && code <= self.last.as_str()
+    }
+}
+
+impl TryFrom<String> for CodeRange {
+    type Error = String;
+
+    fn try_from(spec: String) -> Result<Self, Self::Error> {
+        let (first, last) = spec.split_once('-').unwrap_or((&spec, &spec));
+        let (first, last) = (first.trim().to_uppercase(), last.trim().to_uppercase());
+        if first.is_empty() || first.len() != last.len() || first > last {
+            return Err(format!("invalid procedure code range: {}", spec));
+        }
+        Ok(CodeRange { first, last })
+    }
+}
+
+impl fmt::Display for CodeRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.first == self.last {
+            write!(f, "{}", self.first)
+        } else {
+            write!(f, "{}-{}", self.first, self.last)
+        }
+    }
+}
+
+/// Relative weights of each outcome for the procedure codes in a range
+///
+/// Weights need not sum to one; they are normalized when an outcome is drawn
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct OutcomeRule {
+    pub codes: CodeRange,
+    #[serde(default)]
+    pub pay: f64,
+    #[serde(default)]
+    pub deny_medical_necessity: f64,
+    #[serde(default)]
+    pub deny_auth: f64,
+    #[serde(default)]
+    pub downcode: f64,
+}
+
+impl OutcomeRule {
+    fn weights(&self) -> [(Outcome, f64); 4] {
+        [
+            (Outcome::Pay, self.pay),
+            (Outcome::DenyMedicalNecessity, self.deny_medical_necessity),
+            (Outcome::DenyAuthorization, self.deny_auth),
+            (Outcome::Downcode, self.downcode),
+        ]
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let weights = self.weights();
+        if weights.iter().any(|(_, weight)| !weight.is_finite() || *weight < 0.0) {
+            return Err(format!("{}: outcome weights must be non-negative numbers", self.codes));
+        }
+        if weights.iter().map(|(_, weight)| weight).sum::<f64>() <= 0.0 {
+            return Err(format!("{}: at least one outcome weight must be positive", self.codes));
+        }
+        Ok(())
+    }
+
+    fn draw(&self, rng: &mut impl Rng) -> Outcome {
+        let weights = self.weights();
+        let mut roll = rng.random_range(0.0..weights.iter().map(|(_, weight)| weight).sum::<f64>());
+        for (outcome, weight) in weights {
+            if roll < weight {
+                return outcome;
+            }
+            roll -= weight;
+        }
+        Outcome::Pay
+    }
+}
+
+/// Specialty-specific adjudication outcomes by procedure code range
+///
+/// The first rule whose range contains a line's procedure code decides the line;
+/// codes no rule covers are paid. The default table pays everything
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct OutcomeTable(Vec<OutcomeRule>);
+
+impl OutcomeTable {
+    pub fn new(rules: Vec<OutcomeRule>) -> anyhow::Result<Self> {
+        for rule in &rules {
+            rule.validate().map_err(|e| anyhow!(e))?;
+        }
+        Ok(Self(rules))
+    }
+
+    /// Read a JSON array of rules, e.g.
+    /// `[{"codes": "70010-79999", "pay": 0.7, "deny_auth": 0.2, "downcode": 0.1}]`
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path).with_context(|| format!("reading outcome table {}", path))?;
+        let rules = serde_json::from_str(&contents).with_context(|| format!("parsing outcome table {}", path))?;
+        Self::new(rules).with_context(|| format!("invalid outcome table {}", path))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Rule covering a procedure code, if any
+    pub fn rule(&self, procedure_code: &str) -> Option<&OutcomeRule> {
+        let code = procedure_code.trim().to_uppercase();
+        self.0.iter().find(|rule| rule.codes.contains(&code))
+    }
+
+    /// Draw the outcome of a line billed with this procedure code
+    pub fn outcome(&self, procedure_code: &str, rng: &mut impl Rng) -> Outcome {
+        self.rule(procedure_code).map(|rule| rule.draw(rng)).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that a table parses from JSON, matches codes by range, and draws only weighted outcomes.
+    /// Expected: Radiology codes are always denied for authorization, other codes are paid; a rule with no weight is refused.
+    #[test]
+    fn test_outcome_table() {
+        let rules = serde_json::from_str(r#"[{"codes": "70010-79999", "deny_auth": 1.0}, {"codes": "99213", "pay": 1.0}]"#).unwrap();
+        let table = OutcomeTable::new(rules).unwrap();
+        let mut rng = rand::rng();
+        for _ in 0..20 {
+            assert_eq!(table.outcome("71046", &mut rng), Outcome::DenyAuthorization);
+            assert_eq!(table.outcome("99213", &mut rng), Outcome::Pay);
+            assert_eq!(table.outcome("27447", &mut rng), Outcome::Pay);
+        }
+        assert_eq!(table.rule("71046").unwrap().codes.to_string(), "70010-79999");
+
+        let rules = serde_json::from_str(r#"[{"codes": "99213"}]"#).unwrap();
+        assert!(OutcomeTable::new(rules).is_err());
+        assert!(serde_json::from_str::<CodeRange>(r#""99999-10000""#).is_err());
+    }
+}
@@ -0,0 +1,288 @@
+use chrono::Days;
+use rand::Rng;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::sync::mpsc::Receiver;
+use tokio::time::{sleep, sleep_until};
+
+use crate::clock::SimClock;
+use crate::ledger::{AdjustmentReason, EntryKind, Ledger, LedgerEntry};
+use crate::logging::log_claim_event;
+use crate::message::{PatientMessage, PatientStatement};
+
+/// What happens to a patient balance still unpaid after the last statement
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CollectionsAction {
+    /// Hand the balance to a collections agency
+    #[default]
+    Transfer,
+    /// Write the balance off as bad debt
+    WriteOff,
+}
+
+impl FromStr for CollectionsAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "transfer" => Ok(CollectionsAction::Transfer),
+            "write-off" => Ok(CollectionsAction::WriteOff),
+            _ => Err(anyhow::anyhow!("Unknown collections action: {} (expected transfer or write-off)", s)),
+        }
+    }
+}
+
+impl fmt::Display for CollectionsAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CollectionsAction::Transfer => write!(f, "transfer"),
+            CollectionsAction::WriteOff => write!(f, "write-off"),
+        }
+    }
+}
+
+/// Statement cycle for patient balances: how many statements are sent, how far apart, and what follows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CollectionsPolicy {
+    pub statements: u32,
+    /// Simulated days between statements
+    pub cycle_days: u32,
+    pub action: CollectionsAction,
+}
+
+impl Default for CollectionsPolicy {
+    fn default() -> Self {
+        Self {
+            statements: 3,
+            cycle_days: 30,
+            action: CollectionsAction::default(),
+        }
+    }
+}
+
+/// Simulates patients paying their self-pay balances
+///
+/// Each statement is paid in full with the configured probability after a random delay,
+/// and the payment is posted to the ledger. A statement left unpaid is followed by another
+/// each statement cycle on the virtual clock; once the last goes unpaid the balance is sent
+/// to collections or written off
+pub struct PatientPayer {
+    payment_probability: f64,
+    min_delay_secs: u64,
+    max_delay_secs: u64,
+    collections: CollectionsPolicy,
+    clock: SimClock,
+    rx: Receiver<PatientMessage>,
+    ledger: Arc<Mutex<Ledger>>,
+    verbose: bool,
+}
+
+impl PatientPayer {
+    /// Create a new patient payer with a payment probability (0.0-1.0) and delay range
+    pub fn new(
+        payment_probability: f64,
+        min_delay_secs: u64,
+        max_delay_secs: u64,
+        rx: Receiver<PatientMessage>,
+        ledger: Arc<Mutex<Ledger>>,
+        verbose: bool,
+    ) -> Self {
+        Self {
+            payment_probability: payment_probability.clamp(0.0, 1.0),
+            min_delay_secs,
+            max_delay_secs,
+            collections: CollectionsPolicy::default(),
+            clock: SimClock::default(),
+            rx,
+            ledger,
+            verbose,
+        }
+    }
+
+    pub fn with_collections(mut self, collections: CollectionsPolicy) -> Self {
+        self.collections = collections;
+        self
+    }
+
+    /// Space statements by this clock's simulated days
+    pub fn with_clock(mut self, clock: SimClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Main processing loop for patient statements
+    ///
+    /// Works each balance through its statement cycle asynchronously, posting
+    /// the payment once the patient pays or the collections action after the last statement
+    pub async fn run(mut self) {
+        if self.verbose {
+            log_claim_event("patient_payer", "-", "start", "Starting patient payer task");
+        }
+        while let Some(msg) = self.rx.recv().await {
+            self.handle_patient_message(msg);
+        }
+        if self.verbose {
+            log_claim_event("patient_payer", "-", "shutdown", "Shutting down patient payer task");
+        }
+    }
+
+    fn handle_patient_message(&self, msg: PatientMessage) {
+        let PatientMessage::Statement(statement) = msg;
+        let delays: Vec<Option<Duration>> = (0..self.collections.statements)
+            .map(|_| rand::rng().random_bool(self.payment_probability).then(|| self.random_delay()))
+            .collect();
+        let cycle = StatementCycle {
+            collections: self.collections,
+            clock: self.clock,
+            ledger: self.ledger.clone(),
+            verbose: self.verbose,
+        };
+        tokio::spawn(cycle.run(statement, delays));
+    }
+
+    /// Generate a random payment delay within configured range
+    fn random_delay(&self) -> Duration {
+        let mut rng = rand::rng();
+        let secs = rng.random_range(self.min_delay_secs..=self.max_delay_secs);
+        Duration::from_secs(secs)
+    }
+}
+
+/// One balance's statements, sent until the patient pays or they run out
+struct StatementCycle {
+    collections: CollectionsPolicy,
+    clock: SimClock,
+    ledger: Arc<Mutex<Ledger>>,
+    verbose: bool,
+}
+
+impl StatementCycle {
+    /// Send each statement in turn; `delays` holds, per statement, how long the patient takes to pay it, if they do
+    async fn run(self, statement: PatientStatement, delays: Vec<Option<Duration>>) {
+        let mut sent_on = self.clock.now().date();
+        for (n, delay) in delays.into_iter().enumerate() {
+            if let Some(delay) = delay {
+                return self.pay_statement(statement, delay).await;
+            }
+            if self.verbose {
+                log_claim_event(
+                    "patient_payer",
+                    &statement.claim_id,
+                    "statement_unpaid",
+                    &format!("Patient {} did not pay statement {} of ${:.2}", &statement.patient_id, n + 1, statement.amount),
+                );
+            }
+            sent_on = sent_on + Days::new(self.collections.cycle_days.into());
+            let next = sent_on.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+            sleep_until(self.clock.instant_of(next).into()).await;
+        }
+        let (reason, event) = match self.collections.action {
+            CollectionsAction::Transfer => (AdjustmentReason::Collections, "sent_to_collections"),
+            CollectionsAction::WriteOff => (AdjustmentReason::BadDebt, "written_off"),
+        };
+        self.ledger.lock().await.post(LedgerEntry::new(
+            &statement.claim_id,
+            &statement.patient_id,
+            &statement.payer_id,
+            EntryKind::Adjustment(reason),
+            statement.amount,
+        ));
+        if self.verbose {
+            log_claim_event(
+                "patient_payer",
+                &statement.claim_id,
+                event,
+                &format!("Patient {} left ${:.2} unpaid after {} statements", &statement.patient_id, statement.amount, self.collections.statements),
+            );
+        }
+    }
+
+    async fn pay_statement(&self, statement: PatientStatement, delay: Duration) {
+        sleep(delay).await;
+        self.ledger.lock().await.post(LedgerEntry::new(
+            &statement.claim_id,
+            &statement.patient_id,
+            &statement.payer_id,
+            EntryKind::PatientPayment,
+            statement.amount,
+        ));
+        if self.verbose {
+            log_claim_event(
+                "patient_payer",
+                &statement.claim_id,
+                "statement_paid",
+                &format!("Patient {} paid ${:.2}", &statement.patient_id, statement.amount),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::timeout;
+
+    fn statement() -> PatientStatement {
+        PatientStatement {
+            claim_id: "abc123".into(),
+            patient_id: "pmid456".into(),
+            payer_id: "medicare".into(),
+            amount: 27.0,
+        }
+    }
+
+    /// Test that a patient who always pays has the payment posted to the ledger.
+    /// Expected: A PatientPayment entry for the full statement amount.
+    #[tokio::test]
+    async fn test_patient_payer_pays() {
+        let (tx, rx) = tokio::sync::mpsc::channel(1);
+        let ledger = Arc::new(Mutex::new(Ledger::new()));
+        let patient_payer = PatientPayer::new(1.0, 0, 0, rx, ledger.clone(), false);
+        let handle = tokio::spawn(patient_payer.run());
+        tx.send(PatientMessage::Statement(statement())).await.unwrap();
+        drop(tx);
+        timeout(Duration::from_secs(1), handle).await.unwrap().unwrap();
+        sleep(Duration::from_millis(50)).await;
+        let ledger = ledger.lock().await;
+        let balances = ledger.patient_balances();
+        assert_eq!(balances["pmid456"].collected, 27.0);
+    }
+
+    /// Test that a patient who never pays leaves the ledger untouched.
+    /// Expected: No entries are posted.
+    #[tokio::test]
+    async fn test_patient_payer_never_pays() {
+        let (tx, rx) = tokio::sync::mpsc::channel(1);
+        let ledger = Arc::new(Mutex::new(Ledger::new()));
+        let patient_payer = PatientPayer::new(0.0, 0, 0, rx, ledger.clone(), false);
+        let handle = tokio::spawn(patient_payer.run());
+        tx.send(PatientMessage::Statement(statement())).await.unwrap();
+        drop(tx);
+        timeout(Duration::from_secs(1), handle).await.unwrap().unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert!(ledger.lock().await.entries().is_empty());
+    }
+
+    /// Test that a balance unpaid after every statement cycle is handed to collections, or written off.
+    /// Expected: After two statements a day apart on a fast clock, a collections adjustment for the full amount; with write-off, bad debt.
+    #[tokio::test]
+    async fn test_patient_payer_collections() {
+        for (action, reason) in [(CollectionsAction::Transfer, AdjustmentReason::Collections), (CollectionsAction::WriteOff, AdjustmentReason::BadDebt)] {
+            let (tx, rx) = tokio::sync::mpsc::channel(1);
+            let ledger = Arc::new(Mutex::new(Ledger::new()));
+            let clock = SimClock::new(chrono::Utc::now().date_naive(), 0.01);
+            let policy = CollectionsPolicy { statements: 2, cycle_days: 1, action };
+            let patient_payer = PatientPayer::new(0.0, 0, 0, rx, ledger.clone(), false).with_collections(policy).with_clock(clock);
+            tokio::spawn(patient_payer.run());
+            tx.send(PatientMessage::Statement(statement())).await.unwrap();
+            sleep(Duration::from_millis(200)).await;
+            let ledger = ledger.lock().await;
+            let [entry] = ledger.entries() else { panic!("Expected one adjustment") };
+            assert_eq!(entry.kind, EntryKind::Adjustment(reason));
+            assert_eq!(entry.amount, 27.0);
+        }
+    }
+}
@@ -1,49 +1,465 @@
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::sync::{Mutex as AsyncMutex, OwnedSemaphorePermit, Semaphore};
 use tokio::time::sleep;
+use tracing::Instrument;
 
+use crate::adjudicator::{AdjudicationResult, Adjudicator, SharedAdjudicator};
+use crate::attachments::AttachmentPolicy;
+use crate::benefits::{Accumulator, Accumulators, BenefitPlans, BenefitStatus};
+use crate::clock::SimClock;
+use crate::control::{ControlReceiver, PayerHandle, PayerSettings, TELEHEALTH_PARITY};
+use crate::debugger::Debugger;
+use crate::audit_log::{AuditAction, AuditLog};
+use crate::event_log::{Event, EventLog};
+use crate::ids::{ClaimId, PayerId};
 use crate::logging::log_claim_event;
+use crate::medical_necessity::CoveragePolicy;
 use crate::message::{PayerMessage, RemittanceMessage};
-use crate::remittance::Remittance;
+use crate::outcomes::{Outcome, OutcomeTable};
+use crate::payer_rules::RuleBook;
+use crate::priority::{Priority, PriorityQueue};
+use crate::roster::Roster;
+use crate::supervisor::Heartbeat;
+use crate::remittance::{Balancing, DenialReason, MismatchPolicy, Remittance};
+use crate::schema::{CoverageKey, PayerClaim};
+use crate::validation;
+
+/// Adjudicators a payer runs at once unless configured otherwise
+pub const DEFAULT_PAYER_WORKERS: usize = 16;
 
 /// Simulates an insurance payer for claim adjudication
 /// 
-/// Processes claims asynchronously with configurable response times
-/// Generates remittances with payment breakdowns
+/// Claims wait in an intake queue, high priority first, for one of a fixed pool of
+/// adjudicators, each working one claim at a time with configurable response times.
+/// Generates remittances with payment breakdowns, the patient's share set by their plan and what
+/// they have already paid toward it, denying a configurable share of claims
+/// and ruling on each line by its procedure code's outcome table. Lines whose diagnoses do not
+/// meet the payer's medical necessity rules are denied, as are claims for members its roster
+/// shows were not covered on the date of service. Claims for procedures that need
+/// documentation are held while the biller is asked for it once; a claim still missing it is denied.
+/// Its rules can be replaced while it runs, each claim decided under the version then in force
+///
+/// A custom `Adjudicator` can decide claims in place of the simulated one
 pub struct Payer {
-    payer_id: String,
-    min_response_time_secs: u64,
-    max_response_time_secs: u64,
-    rx: Receiver<PayerMessage>,
+    payer_id: PayerId,
+    handle: PayerHandle,
+    control: ControlReceiver<PayerSettings>,
+    /// Shared with any replacement, which takes up the claims still waiting in it
+    rx: Arc<AsyncMutex<Receiver<PayerMessage>>>,
     tx: Sender<RemittanceMessage>,
+    event_log: Option<EventLog>,
+    audit_log: Option<AuditLog>,
+    debugger: Option<Debugger>,
+    heartbeat: Heartbeat,
+    roster: Arc<Roster>,
+    adjudicator: Option<SharedAdjudicator>,
+    portal: PayerPortal,
     verbose: bool,
 }
 
+/// Where a claim stands in a payer's own records
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PayerClaimStatus {
+    /// Received and queued for adjudication
+    Received,
+    /// Held until the biller sends the documents requested
+    AwaitingAttachments { document_types: Vec<String> },
+    /// Being adjudicated
+    Pending,
+    /// Adjudicated and remitted
+    Finished { payer_paid: f64, denied: bool },
+    /// Cancelled by the biller; `reversed_payment` is what was taken back if it had already been paid
+    Voided { reversed_payment: f64 },
+    /// Superseded by a corrected claim; its payment was reversed
+    Replaced { replacement: ClaimId, reversed_payment: f64 },
+}
+
+/// Snapshot of a payer's capacity: its intake queue and how busy its adjudicators are
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PayerMetrics {
+    pub workers: usize,
+    /// Adjudicators working a claim
+    pub busy: usize,
+    /// Claims in the intake queue waiting for an adjudicator
+    pub queued: usize,
+    /// Claims adjudicated and remitted so far
+    pub adjudicated: u64,
+    /// Fraction of adjudicators busy, from 0 to 1
+    pub utilization: f64,
+}
+
+#[derive(Debug)]
+struct PoolStats {
+    workers: AtomicUsize,
+    busy: AtomicUsize,
+    queued: AtomicUsize,
+    adjudicated: AtomicU64,
+}
+
+impl Default for PoolStats {
+    fn default() -> Self {
+        Self {
+            workers: AtomicUsize::new(DEFAULT_PAYER_WORKERS),
+            busy: AtomicUsize::new(0),
+            queued: AtomicUsize::new(0),
+            adjudicated: AtomicU64::new(0),
+        }
+    }
+}
+
+/// The payer's internal store of the claims it has received, and its capacity metrics,
+/// queryable while the payer runs
+#[derive(Debug, Clone, Default)]
+pub struct PayerPortal {
+    claims: Arc<Mutex<HashMap<ClaimId, PayerClaimStatus>>>,
+    pool: Arc<PoolStats>,
+    /// Outcome, coverage, attachment, and benefit rules, which may be swapped while the payer runs
+    rules: RuleBook,
+    accumulators: Accumulators,
+    clock: SimClock,
+    /// Seeds each claim's random draws when set; see `claim_rng`
+    seed: Option<u64>,
+}
+
+impl PayerPortal {
+    pub fn metrics(&self) -> PayerMetrics {
+        let workers = self.pool.workers.load(Ordering::Relaxed);
+        let busy = self.pool.busy.load(Ordering::Relaxed);
+        PayerMetrics {
+            workers,
+            busy,
+            queued: self.pool.queued.load(Ordering::Relaxed),
+            adjudicated: self.pool.adjudicated.load(Ordering::Relaxed),
+            utilization: if workers == 0 { 0.0 } else { busy as f64 / workers as f64 },
+        }
+    }
+
+    /// Status of a claim, or `None` if this payer never received it
+    pub fn claim_status(&self, claim_id: &str) -> Option<PayerClaimStatus> {
+        self.claims.lock().unwrap().get(claim_id).cloned()
+    }
+
+    /// What a patient has paid toward their plan's limits
+    pub fn accumulator(&self, coverage: &CoverageKey) -> Accumulator {
+        self.accumulators.get(coverage)
+    }
+
+    /// Every patient's standing against their plan's limits on today's simulated date,
+    /// with totals from a plan year that has ended reset
+    pub fn benefit_statuses(&self) -> Vec<BenefitStatus> {
+        let today = self.clock.now().date();
+        let rules = self.rules.current();
+        self.accumulators
+            .snapshot()
+            .into_iter()
+            .map(|(coverage, accumulator)| {
+                let plan = rules.benefits.subscriber_plan(&coverage.subscriber_id);
+                BenefitStatus::new(coverage, plan, accumulator, today)
+            })
+            .collect()
+    }
+
+    /// Price a claim under its patient's plan on today's simulated date, adding their share to the patient's totals
+    ///
+    /// Claims for one patient are priced one at a time, so each sees what the last one added
+    fn price(&self, claim: &PayerClaim, benefits: &BenefitPlans, outcomes: &[Outcome], telehealth_rate: f64) -> Remittance {
+        let plan = benefits.plan(&claim.insurance);
+        let today = self.clock.now().date();
+        self.accumulators.update(&claim.insurance.coverage_key(), |accumulator| {
+            Remittance::adjudicated(claim, outcomes, plan, telehealth_rate, accumulator, today)
+        })
+    }
+
+    fn record(&self, claim_id: &ClaimId, status: PayerClaimStatus) {
+        self.claims.lock().unwrap().insert(claim_id.clone(), status);
+    }
+
+    /// Record a finished adjudication, unless the claim was voided meanwhile
+    fn finish(&self, claim_id: &ClaimId, status: PayerClaimStatus) -> bool {
+        let mut claims = self.claims.lock().unwrap();
+        if matches!(claims.get(claim_id), Some(PayerClaimStatus::Voided { .. })) {
+            return false;
+        }
+        claims.insert(claim_id.clone(), status);
+        true
+    }
+
+    fn is_voided(&self, claim_id: &ClaimId) -> bool {
+        matches!(self.claims.lock().unwrap().get(claim_id), Some(PayerClaimStatus::Voided { .. }))
+    }
+
+    /// Void a claim, reversing its payment if it was already remitted
+    ///
+    /// Returns the new status, or `None` for a claim never received, already voided, or already reversed by a replacement
+    fn void(&self, claim_id: &ClaimId) -> Option<PayerClaimStatus> {
+        let mut claims = self.claims.lock().unwrap();
+        let status = claims.get_mut(claim_id)?;
+        let reversed_payment = match status {
+            PayerClaimStatus::Voided { .. } | PayerClaimStatus::Replaced { .. } => return None,
+            PayerClaimStatus::Finished { payer_paid, .. } => *payer_paid,
+            PayerClaimStatus::Received | PayerClaimStatus::AwaitingAttachments { .. } | PayerClaimStatus::Pending => 0.0,
+        };
+        *status = PayerClaimStatus::Voided { reversed_payment };
+        Some(status.clone())
+    }
+
+    /// Reverse a remitted claim's payment because a replacement supersedes it
+    ///
+    /// Returns the payment reversed, or `None` for a claim not yet remitted or already superseded
+    fn replace(&self, original_id: &ClaimId, replacement: &ClaimId) -> Option<f64> {
+        let mut claims = self.claims.lock().unwrap();
+        let status = claims.get_mut(original_id)?;
+        let PayerClaimStatus::Finished { payer_paid, .. } = *status else {
+            return None;
+        };
+        *status = PayerClaimStatus::Replaced { replacement: replacement.clone(), reversed_payment: payer_paid };
+        Some(payer_paid)
+    }
+}
+
+/// The built-in payer: denies claims for members not covered, claims missing documentation, and a
+/// random share of the rest, then rules on each line and prices it under the member's plan
+struct SimulatedAdjudicator {
+    handle: PayerHandle,
+    roster: Arc<Roster>,
+    portal: PayerPortal,
+}
+
+/// Each line's outcome: denied if its procedure is not medically necessary for the claim's
+/// diagnoses, otherwise drawn from the outcome table
+pub(crate) fn line_outcomes(
+    claim: &PayerClaim,
+    coverage: &CoveragePolicy,
+    outcomes: &OutcomeTable,
+    rng: &mut impl Rng,
+) -> Vec<Outcome> {
+    claim
+        .service_lines
+        .iter()
+        .map(|line| match coverage.is_covered(&line.procedure_code, &claim.diagnosis_codes) {
+            true => outcomes.outcome(&line.procedure_code, rng),
+            false => Outcome::DenyMedicalNecessity,
+        })
+        .collect()
+}
+
+/// Random number generator for one claim's draws of kind `stream`
+///
+/// With a seed, the draws depend only on the seed and the claim id, not on the order claims
+/// happen to be adjudicated in, so a run can be repeated; without one they are fresh each time
+pub(crate) fn claim_rng(seed: Option<u64>, stream: &str, claim_id: &ClaimId) -> StdRng {
+    let Some(seed) = seed else {
+        return StdRng::from_rng(&mut rand::rng());
+    };
+    let digest = Sha256::digest(format!("{}|{}", stream, claim_id).as_bytes());
+    StdRng::seed_from_u64(seed ^ u64::from_be_bytes(digest[..8].try_into().expect("a SHA-256 digest has 8 bytes")))
+}
+
+impl Adjudicator for SimulatedAdjudicator {
+    async fn adjudicate(&self, claim: &PayerClaim) -> AdjudicationResult {
+        let date_of_service = claim.service_date.unwrap_or_else(|| self.portal.clock.now().date());
+        let mut rng = claim_rng(self.portal.seed, "adjudicate", &claim.claim_id);
+        let rules = self.portal.rules.current();
+        if validation::validate_claim_total(claim).is_some() {
+            // a claim that contradicts itself cannot be priced
+            AdjudicationResult::Deny(Some(DenialReason::ClaimError))
+        } else if !self.roster.is_eligible(claim.insurance.subscriber_id(), date_of_service) {
+            AdjudicationResult::Deny(Some(DenialReason::NotEligible))
+        } else if !rules.attachments.missing(claim).is_empty() {
+            AdjudicationResult::Deny(Some(DenialReason::ClaimError))
+        } else if rng.random_bool(self.handle.settings().denial_rate) {
+            AdjudicationResult::Deny(None)
+        } else {
+            let outcomes = line_outcomes(claim, &rules.coverage, &rules.outcomes, &mut rng);
+            AdjudicationResult::Remit(self.portal.price(claim, &rules.benefits, &outcomes, self.handle.settings().telehealth_rate))
+        }
+    }
+
+    /// Take the patient's share of a discarded remittance back off their totals
+    fn retract(&self, claim: &PayerClaim, remittance: &Remittance) {
+        self.portal.accumulators.refund(&claim.insurance.coverage_key(), remittance);
+    }
+
+    fn rules_version(&self) -> Option<u64> {
+        Some(self.portal.rules.version())
+    }
+}
+
 impl Payer {
     /// Create a new payer with specified response time range
     pub fn new(
-        payer_id: String,
+        payer_id: impl Into<PayerId>,
         min_response_time_secs: u64,
         max_response_time_secs: u64,
         tx: Sender<RemittanceMessage>,
         rx: Receiver<PayerMessage>,
         verbose: bool,
     ) -> Self {
-        Self {
-            payer_id,
+        let handle = PayerHandle::new(PayerSettings {
             min_response_time_secs,
-            max_response_time_secs,
+            max_response_time_secs: max_response_time_secs.max(min_response_time_secs),
+            ack_time_secs: None,
+            denial_rate: 0.0,
+            error_rate: 0.0,
+            mismatch_policy: MismatchPolicy::default(),
+            telehealth_rate: TELEHEALTH_PARITY,
+        });
+        Self {
+            payer_id: payer_id.into(),
+            control: handle.subscribe(),
+            handle,
             tx,
-            rx,
+            rx: Arc::new(AsyncMutex::new(rx)),
+            event_log: None,
+            audit_log: None,
+            debugger: None,
+            heartbeat: Heartbeat::default(),
+            roster: Arc::default(),
+            adjudicator: None,
+            portal: PayerPortal::default(),
             verbose,
         }
     }
 
+    /// Adjudicate at most this many claims at once; the rest wait in the intake queue
+    pub fn with_workers(self, workers: usize) -> Self {
+        self.portal.pool.workers.store(workers.max(1), Ordering::Relaxed);
+        self
+    }
+
+    /// Deny, downcode, or pay each line according to its procedure code
+    pub fn with_outcomes(self, outcomes: Arc<OutcomeTable>) -> Self {
+        self.portal.rules.edit(|rules| rules.outcomes = outcomes);
+        self
+    }
+
+    /// Record each adjudication result to an event log
+    pub fn with_event_log(mut self, event_log: EventLog) -> Self {
+        self.event_log = Some(event_log);
+        self
+    }
+
+    /// Record each claim this payer takes up for adjudication to an audit log
+    pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Hold each message this payer takes in at `payer:<id>` while the debugger is paused
+    pub fn with_debugger(mut self, debugger: Debugger) -> Self {
+        self.debugger = Some(debugger);
+        self
+    }
+
+    /// Beat `heartbeat` each time a message is taken in or a claim handed to an adjudicator
+    pub fn with_heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Deny lines whose procedure is not medically necessary for the claim's diagnoses (CO-50)
+    pub fn with_coverage(self, coverage: Arc<CoveragePolicy>) -> Self {
+        self.portal.rules.edit(|rules| rules.coverage = coverage);
+        self
+    }
+
+    /// Hold claims for procedures that need documentation until the biller sends it
+    pub fn with_attachments(self, attachments: Arc<AttachmentPolicy>) -> Self {
+        self.portal.rules.edit(|rules| rules.attachments = attachments);
+        self
+    }
+
+    /// Deny claims for members the roster shows were not covered on the date of service (CO-27)
+    pub fn with_roster(mut self, roster: Arc<Roster>) -> Self {
+        self.roster = roster;
+        self
+    }
+
+    /// Price claims under the plan each member is enrolled in
+    pub fn with_benefits(self, benefits: Arc<BenefitPlans>) -> Self {
+        self.portal.rules.edit(|rules| rules.benefits = benefits);
+        self
+    }
+
+    /// Adjudicate under rules shared with other payers, which a `RuleWatcher` may replace while
+    /// they run; each claim is decided under the version in force when it is taken up. Replaces
+    /// any outcomes, coverage, attachments, or benefits set before
+    pub fn with_rules(mut self, rules: RuleBook) -> Self {
+        self.portal.rules = rules;
+        self
+    }
+
+    /// Date claims by the virtual clock, so plan years pass as the simulation runs
+    pub fn with_clock(mut self, clock: SimClock) -> Self {
+        self.portal.clock = clock;
+        self
+    }
+
+    /// Decide claims with a custom adjudicator instead of the simulated one, which the outcome,
+    /// coverage, roster, and benefit settings configure
+    pub fn with_adjudicator(mut self, adjudicator: impl Into<SharedAdjudicator>) -> Self {
+        self.adjudicator = Some(adjudicator.into());
+        self
+    }
+
+    /// Draw denials, line outcomes, delays, and pricing errors from `seed` and each claim's id,
+    /// so the same claims are decided the same way and take as long in every run with this seed
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.portal.seed = Some(seed);
+        self
+    }
+
+    /// Status of a claim in this payer's own records
+    pub fn claim_status(&self, claim_id: &str) -> Option<PayerClaimStatus> {
+        self.portal.claim_status(claim_id)
+    }
+
+    /// Shared view of this payer's claim store, answering status inquiries after `run` takes the payer
+    pub fn portal(&self) -> PayerPortal {
+        self.portal.clone()
+    }
+
+    /// Handle to pause, resume, stop, or reconfigure this payer while it runs
+    pub fn handle(&self) -> PayerHandle {
+        self.handle.clone()
+    }
+
+    /// A payer to take over if this one fails, sharing its inbox, claim store, and controls
+    ///
+    /// Claims the failed payer had taken in but not yet handed to an adjudicator are lost with it;
+    /// those being adjudicated still complete
+    pub fn replacement(&self) -> Self {
+        Self {
+            payer_id: self.payer_id.clone(),
+            handle: self.handle.clone(),
+            control: self.handle.subscribe(),
+            rx: self.rx.clone(),
+            tx: self.tx.clone(),
+            event_log: self.event_log.clone(),
+            audit_log: self.audit_log.clone(),
+            debugger: self.debugger.clone(),
+            heartbeat: self.heartbeat.clone(),
+            roster: self.roster.clone(),
+            adjudicator: self.adjudicator.clone(),
+            portal: self.portal.clone(),
+            verbose: self.verbose,
+        }
+    }
+
     /// Main processing loop for claim adjudication
     /// 
     /// Receives claims, processes them asynchronously with random delays
     /// Generates and validates remittances before sending responses
+    /// Stops taking claims while paused, and exits once stopped; claims already being adjudicated still complete
     pub async fn run(mut self) {
         if self.verbose {
             log_claim_event(
@@ -53,8 +469,78 @@ impl Payer {
                 &format!("Starting payer task for {}", &self.payer_id),
             );
         }
-        while let Some(msg) = self.rx.recv().await {
-            self.handle_payer_message(msg);
+        let adjudicator = self.adjudicator.clone().unwrap_or_else(|| {
+            SharedAdjudicator::new(SimulatedAdjudicator {
+                handle: self.handle.clone(),
+                roster: self.roster.clone(),
+                portal: self.portal.clone(),
+            })
+        });
+        let workers = Arc::new(Semaphore::new(self.portal.pool.workers.load(Ordering::Relaxed)));
+        let mut queue = PriorityQueue::new();
+        // claims waiting on the biller's attachments, and those already asked for them once
+        let mut awaiting: HashMap<ClaimId, (Arc<PayerClaim>, tracing::Span)> = HashMap::new();
+        let mut requested: HashSet<ClaimId> = HashSet::new();
+        let inbox = self.rx.clone();
+        // released if this task fails, so a replacement can take over the inbox
+        let mut rx = inbox.lock().await;
+        let mut input_open = true;
+        while (input_open || !queue.is_empty()) && self.control.proceed().await {
+            // take in everything already sent before handing the most urgent claim to a free adjudicator
+            tokio::select! {
+                biased;
+                _ = self.control.changed() => continue,
+                msg = rx.recv(), if input_open => {
+                    if let (Some(debugger), Some(msg)) = (&self.debugger, &msg) {
+                        debugger.checkpoint(&format!("payer:{}", self.payer_id), || msg.summary()).await;
+                    }
+                    match msg {
+                        Some(PayerMessage::Adjudicate(claim, span)) => match claim.replaces() {
+                            // with no payment to take back, a replacement is refused rather than paid as a new claim
+                            Some(original_id) if !self.reverse_replaced(original_id, &claim.claim_id) => {
+                                let reason = format!("payer {} has no remitted claim {} to replace", self.payer_id, original_id);
+                                self.reject(&claim.claim_id, reason).await;
+                            }
+                            _ => {
+                                self.portal.record(&claim.claim_id, PayerClaimStatus::Received);
+                                self.acknowledge(&claim.claim_id);
+                                queue.push(claim.priority(), (claim, span));
+                            }
+                        },
+                        Some(PayerMessage::Void(claim_id)) => {
+                            awaiting.remove(&claim_id);
+                            self.void(&claim_id);
+                        }
+                        Some(PayerMessage::Attachments(claim_id, attachments)) => match awaiting.remove(&claim_id) {
+                            Some((mut claim, span)) => {
+                                Arc::make_mut(&mut claim).attachments.extend(attachments);
+                                self.portal.record(&claim_id, PayerClaimStatus::Received);
+                                queue.push(claim.priority(), (claim, span));
+                            }
+                            None => eprintln!("Payer {} got attachments for claim {} it is not holding", self.payer_id, claim_id),
+                        },
+                        None => input_open = false,
+                    }
+                }
+                permit = workers.clone().acquire_owned(), if !queue.is_empty() => {
+                    let permit = permit.expect("worker pool is never closed");
+                    // voided claims stay queued until their turn, then are dropped
+                    if let Some((claim, span)) = queue.pop()
+                        && !self.portal.is_voided(&claim.claim_id)
+                    {
+                        let missing = self.portal.rules.current().attachments.missing(&claim);
+                        if missing.is_empty() || requested.remove(&claim.claim_id) {
+                            self.adjudicate(claim, &adjudicator, span, permit);
+                        } else {
+                            self.request_attachments(&claim.claim_id, missing).await;
+                            requested.insert(claim.claim_id.clone());
+                            awaiting.insert(claim.claim_id.clone(), (claim, span));
+                        }
+                    }
+                }
+            }
+            self.portal.pool.queued.store(queue.len(), Ordering::Relaxed);
+            self.heartbeat.beat();
         }
         if self.verbose {
             log_claim_event(
@@ -66,8 +552,89 @@ impl Payer {
         }
     }
 
-    fn handle_payer_message(&self, msg: PayerMessage) {
-        let PayerMessage::Adjudicate(claim) = msg;
+    /// Stop a cancelled claim from being remitted, or reverse it if it already was
+    fn void(&self, claim_id: &ClaimId) {
+        match self.portal.void(claim_id) {
+            Some(PayerClaimStatus::Voided { reversed_payment }) if reversed_payment > 0.0 => {
+                log_claim_event(
+                    "payer",
+                    claim_id,
+                    "payment_reversed",
+                    &format!("Claim voided after remittance, reversed payment of {:.2}", reversed_payment),
+                );
+            }
+            Some(_) => {
+                if self.verbose {
+                    log_claim_event("payer", claim_id, "claim_voided", "Claim voided before remittance");
+                }
+            }
+            None => eprintln!("Payer {} cannot void claim {}: not received or already voided", self.payer_id, claim_id),
+        }
+    }
+
+    /// Acknowledge a claim taken in once a random delay in the acknowledgment range passes,
+    /// however long it waits for an adjudicator, unless it was remitted or voided first
+    fn acknowledge(&self, claim_id: &ClaimId) {
+        let Some((min_secs, max_secs)) = self.control.settings().ack_time_secs else {
+            return;
+        };
+        let mut rng = claim_rng(self.portal.seed, "ack", claim_id);
+        let delay = Duration::from_secs(rng.random_range(min_secs..=max_secs));
+        let (claim_id, portal, tx) = (claim_id.clone(), self.portal.clone(), self.tx.clone());
+        tokio::spawn(async move {
+            sleep(delay).await;
+            let open = matches!(
+                portal.claim_status(&claim_id),
+                Some(PayerClaimStatus::Received | PayerClaimStatus::AwaitingAttachments { .. } | PayerClaimStatus::Pending)
+            );
+            if open && let Err(e) = tx.send(RemittanceMessage::Acknowledged { claim_id: claim_id.clone() }).await {
+                eprintln!("Failed to acknowledge claim {}: {}", claim_id, e);
+            }
+        });
+    }
+
+    /// Ask the biller, through the clearinghouse, for the documents a held claim needs
+    async fn request_attachments(&self, claim_id: &ClaimId, document_types: Vec<String>) {
+        if self.verbose {
+            log_claim_event(
+                "payer",
+                claim_id,
+                "attachments_requested",
+                &format!("Holding claim for attachments: {}", document_types.join(", ")),
+            );
+        }
+        self.portal.record(claim_id, PayerClaimStatus::AwaitingAttachments { document_types: document_types.clone() });
+        let request = RemittanceMessage::AttachmentRequest { claim_id: claim_id.clone(), document_types };
+        if let Err(e) = self.tx.send(request).await {
+            eprintln!("Failed to request attachments for claim {}: {}", claim_id, e);
+        }
+    }
+
+    /// Take back the original's payment before adjudicating its replacement from scratch
+    ///
+    /// False when the original was never remitted here or was already replaced
+    fn reverse_replaced(&self, original_id: &ClaimId, replacement: &ClaimId) -> bool {
+        let Some(reversed_payment) = self.portal.replace(original_id, replacement) else {
+            return false;
+        };
+        log_claim_event(
+            "payer",
+            original_id,
+            "payment_reversed",
+            &format!("Replaced by claim {}, reversed payment of {:.2}", replacement, reversed_payment),
+        );
+        true
+    }
+
+    /// Refuse a claim without adjudicating it
+    async fn reject(&self, claim_id: &ClaimId, reason: String) {
+        eprintln!("Payer {} rejected claim {}: {}", self.payer_id, claim_id, reason);
+        if let Err(e) = self.tx.send(RemittanceMessage::Rejected { claim_id: claim_id.clone(), reason }).await {
+            eprintln!("Failed to send rejection of claim {}: {}", claim_id, e);
+        }
+    }
+
+    fn adjudicate(&self, claim: Arc<PayerClaim>, adjudicator: &SharedAdjudicator, span: tracing::Span, worker: OwnedSemaphorePermit) {
         if self.verbose {
             log_claim_event(
                 "payer",
@@ -82,15 +649,89 @@ impl Payer {
                 &format!("Adjudicating claim: {}", &claim.claim_id),
             );
         }
-        let delay = self.random_delay();
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.record(format!("payer:{}", self.payer_id), AuditAction::Adjudicate, &claim.claim_id);
+        }
+        let settings = self.control.settings();
+        let adjudicator = adjudicator.clone();
         let tx = self.tx.clone();
+        let event_log = self.event_log.clone().map(|log| (self.payer_id.clone(), log));
+        let portal = self.portal.clone();
+        portal.record(&claim.claim_id, PayerClaimStatus::Pending);
+        portal.pool.busy.fetch_add(1, Ordering::Relaxed);
         let verbose = self.verbose;
-        tokio::spawn(Self::adjudicate_and_send_remittance(claim, tx, delay, verbose));
+        let span = tracing::info_span!(
+            parent: &span,
+            "payer.adjudicate",
+            claim_id = %claim.claim_id,
+            payer_id = %self.payer_id,
+            denied = tracing::field::Empty,
+        );
+        tokio::spawn(
+            async move {
+                Self::adjudicate_and_send_remittance(claim, adjudicator, tx, settings, portal, event_log, verbose).await;
+                drop(worker);
+            }
+            .instrument(span),
+        );
     }
 
-    async fn adjudicate_and_send_remittance(claim: crate::schema::PayerClaim, tx: Sender<RemittanceMessage>, delay: std::time::Duration, verbose: bool) {
-        sleep(delay).await;
-        let remittance = Remittance::from_claim(&claim);
+    async fn adjudicate_and_send_remittance(
+        claim: Arc<PayerClaim>,
+        adjudicator: SharedAdjudicator,
+        tx: Sender<RemittanceMessage>,
+        settings: PayerSettings,
+        portal: PayerPortal,
+        event_log: Option<(PayerId, EventLog)>,
+        verbose: bool,
+    ) {
+        let mut rng = claim_rng(portal.seed, "remit", &claim.claim_id);
+        sleep(random_delay(&settings, claim.priority(), &mut rng)).await;
+        let rules_version = adjudicator.rules_version();
+        let mut remittance = adjudicator.adjudicate(&claim).await.into_remittance(&claim);
+        if rng.random_bool(settings.error_rate) {
+            remittance = remittance.with_miscalculation();
+        }
+        if let Err(e) = remittance.validate_against_claim(&claim) {
+            remittance = match settings.mismatch_policy {
+                MismatchPolicy::Regenerate => {
+                    eprintln!("Remittance validation error, regenerating: {}", e);
+                    adjudicator.retract(&claim, &remittance);
+                    let mut regenerated = adjudicator.adjudicate(&claim).await.into_remittance(&claim);
+                    // priced again, it is as prone to the payer's pricing error as the first attempt
+                    if rng.random_bool(settings.error_rate) {
+                        regenerated = regenerated.with_miscalculation();
+                    }
+                    match regenerated.validate_against_claim(&claim) {
+                        Ok(()) => regenerated,
+                        Err(e) => {
+                            eprintln!("Regenerated remittance still does not balance: {}", e);
+                            regenerated.with_balancing(Balancing::Flagged(e))
+                        }
+                    }
+                }
+                MismatchPolicy::Flag => {
+                    eprintln!("Remittance validation error: {}", e);
+                    remittance.with_balancing(Balancing::Flagged(e))
+                }
+                MismatchPolicy::Exception => {
+                    eprintln!("Remittance validation error, held for review: {}", e);
+                    remittance.with_balancing(Balancing::Held(e))
+                }
+            };
+        } else if verbose {
+            log_claim_event(
+                "payer",
+                &claim.claim_id,
+                "remittance_valid",
+                "Remittance is valid!",
+            );
+        }
+        let remittance = remittance.with_adjudicated_at(std::time::Instant::now());
+        tracing::Span::current().record("denied", remittance.is_denied());
+        if let Some((payer_id, event_log)) = event_log {
+            event_log.record(Event::ClaimAdjudicated { payer_id, remittance: remittance.clone(), rules_version });
+        }
         if verbose {
             log_claim_event(
                 "payer",
@@ -105,36 +746,44 @@ impl Payer {
                 &format!("Sending remittance for claim: {}", &claim.claim_id),
             );
         }
-        match remittance.validate_against_claim(&claim) {
-            Ok(()) => {
-                if verbose {
-                    log_claim_event(
-                        "payer",
-                        &claim.claim_id,
-                        "remittance_valid",
-                        "Remittance is valid!",
-                    );
-                }
-            }
-            Err(e) => {
-                eprintln!("Remittance validation error: {}", e);
+        let finished = PayerClaimStatus::Finished {
+            payer_paid: remittance.payer_paid(),
+            denied: remittance.is_denied(),
+        };
+        portal.pool.busy.fetch_sub(1, Ordering::Relaxed);
+        // a claim voided while it was being adjudicated is never remitted
+        if !portal.finish(&claim.claim_id, finished) {
+            if verbose {
+                log_claim_event("payer", &claim.claim_id, "voided_during_adjudication", "Claim voided, remittance not sent");
             }
+            return;
         }
+        portal.pool.adjudicated.fetch_add(1, Ordering::Relaxed);
         let _ = tx.send(RemittanceMessage::Processed(remittance)).await;
     }
 
-    /// Generate a random processing delay within configured range
-    fn random_delay(&self) -> Duration {
-        let mut rng = rand::rng();
-        let secs = rng.random_range(self.min_response_time_secs..=self.max_response_time_secs);
-        Duration::from_secs(secs)
-    }
+}
+
+/// Generate a random processing delay within the configured range
+///
+/// High priority claims are expedited into the faster half of the range
+fn random_delay(settings: &PayerSettings, priority: Priority, rng: &mut impl Rng) -> Duration {
+    let max = match priority {
+        Priority::High => (settings.min_response_time_secs + settings.max_response_time_secs) / 2,
+        Priority::Normal => settings.max_response_time_secs,
+    };
+    let secs = rng.random_range(settings.min_response_time_secs..=max);
+    Duration::from_secs(secs)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::schema::mock_claim;
+    use tracing::Span;
+    use crate::payer_rules::PayerRules;
+    use crate::roster::Enrollment;
+    use crate::schema::{Attachment, ClaimFrequency};
+    use crate::test_utils::{mock_claim, mock_adjudication_date};
     use tokio::time::timeout;
 
     /// Test that a claim is adjudicated and remittance is sent.
@@ -165,7 +814,7 @@ mod tests {
 
         // Send claim to payer for adjudication
         payer_tx
-            .send(PayerMessage::Adjudicate(mock_claim.clone()))
+            .send(PayerMessage::Adjudicate(Arc::new(mock_claim.clone()), Span::none()))
             .await
             .unwrap();
 
@@ -174,39 +823,41 @@ mod tests {
         match timeout(timeout_duration, remittance_rx.recv()).await {
             Ok(Some(RemittanceMessage::Processed(remittance))) => {
                 // Verify the remittance matches the claim
-                assert_eq!(remittance.claim_id, mock_claim.claim_id);
+                assert_eq!(remittance.claim_id(), &mock_claim.claim_id);
                 assert_eq!(
-                    remittance.service_line_remittances.len(),
+                    remittance.service_line_remittances().len(),
                     mock_claim.service_lines.len()
                 );
 
                 // Verify service line remittances correspond to service lines
                 for (i, service_line) in mock_claim.service_lines.iter().enumerate() {
-                    let remittance_line = &remittance.service_line_remittances[i];
+                    let remittance_line = &remittance.service_line_remittances()[i];
                     assert_eq!(
-                        remittance_line.service_line_id,
+                        remittance_line.service_line_id(),
                         service_line.service_line_id
                     );
 
-                    // Verify amounts are calculated (should be non-zero for our mock calculation)
+                    // An office visit under the standard plan costs the patient only its copay
                     let total_charge = service_line.unit_charge_amount * service_line.units as f64;
-                    assert!(remittance_line.payer_paid_amount > 0.0);
-                    assert!(remittance_line.coinsurance_amount > 0.0);
-                    assert!(remittance_line.copay_amount > 0.0);
-                    assert!(remittance_line.deductible_amount > 0.0);
-                    assert!(remittance_line.not_allowed_amount > 0.0);
+                    assert!(remittance_line.payer_paid_amount() > 0.0);
+                    assert!(remittance_line.copay_amount() > 0.0);
+                    assert_eq!(remittance_line.patient_responsibility(), remittance_line.copay_amount());
+                    assert!(remittance_line.not_allowed_amount() > 0.0);
 
                     // Verify total amounts add up to approximately the total charge
-                    let total_remitted = remittance_line.payer_paid_amount
-                        + remittance_line.coinsurance_amount
-                        + remittance_line.copay_amount
-                        + remittance_line.deductible_amount
-                        + remittance_line.not_allowed_amount;
+                    let total_remitted = remittance_line.payer_paid_amount()
+                        + remittance_line.coinsurance_amount()
+                        + remittance_line.copay_amount()
+                        + remittance_line.deductible_amount()
+                        + remittance_line.not_allowed_amount();
 
                     // Allow for small floating point differences
                     assert!((total_remitted - total_charge).abs() < 0.01);
                 }
             }
+            Ok(Some(RemittanceMessage::Duplicate(_) | RemittanceMessage::Rejected { .. } | RemittanceMessage::Voided { .. } | RemittanceMessage::AttachmentRequest { .. } | RemittanceMessage::Acknowledged { .. })) => {
+                panic!("Payer should never answer with a duplicate");
+            }
             Ok(None) => {
                 panic!("Expected remittance response but got None");
             }
@@ -244,11 +895,11 @@ mod tests {
         let claim2 = mock_claim(); // This will have the same ID, but that's okay for testing
 
         payer_tx
-            .send(PayerMessage::Adjudicate(claim1.clone()))
+            .send(PayerMessage::Adjudicate(Arc::new(claim1.clone()), Span::none()))
             .await
             .unwrap();
         payer_tx
-            .send(PayerMessage::Adjudicate(claim2.clone()))
+            .send(PayerMessage::Adjudicate(Arc::new(claim2.clone()), Span::none()))
             .await
             .unwrap();
 
@@ -258,7 +909,7 @@ mod tests {
         // First remittance
         match timeout(timeout_duration, remittance_rx.recv()).await {
             Ok(Some(RemittanceMessage::Processed(remittance1))) => {
-                assert_eq!(remittance1.claim_id, claim1.claim_id);
+                assert_eq!(remittance1.claim_id(), &claim1.claim_id);
             }
             _ => panic!("Timeout or error waiting for first remittance"),
         }
@@ -266,7 +917,7 @@ mod tests {
         // Second remittance
         match timeout(timeout_duration, remittance_rx.recv()).await {
             Ok(Some(RemittanceMessage::Processed(remittance2))) => {
-                assert_eq!(remittance2.claim_id, claim2.claim_id);
+                assert_eq!(remittance2.claim_id(), &claim2.claim_id);
             }
             _ => panic!("Timeout or error waiting for second remittance"),
         }
@@ -289,26 +940,267 @@ mod tests {
             service_line.units = 0;
         }
         payer_tx
-            .send(PayerMessage::Adjudicate(invalid_claim.clone()))
+            .send(PayerMessage::Adjudicate(Arc::new(invalid_claim.clone()), Span::none()))
             .await
             .unwrap();
         let timeout_duration = Duration::from_secs(5);
         match timeout(timeout_duration, remittance_rx.recv()).await {
             Ok(Some(RemittanceMessage::Processed(remittance))) => {
-                assert_eq!(remittance.claim_id, invalid_claim.claim_id);
+                assert_eq!(remittance.claim_id(), &invalid_claim.claim_id);
                 // Verify all amounts are zero
-                for remittance_line in &remittance.service_line_remittances {
-                    assert_eq!(remittance_line.payer_paid_amount, 0.0);
-                    assert_eq!(remittance_line.coinsurance_amount, 0.0);
-                    assert_eq!(remittance_line.copay_amount, 0.0);
-                    assert_eq!(remittance_line.deductible_amount, 0.0);
-                    assert_eq!(remittance_line.not_allowed_amount, 0.0);
+                for remittance_line in remittance.service_line_remittances() {
+                    assert_eq!(remittance_line.payer_paid_amount(), 0.0);
+                    assert_eq!(remittance_line.coinsurance_amount(), 0.0);
+                    assert_eq!(remittance_line.copay_amount(), 0.0);
+                    assert_eq!(remittance_line.deductible_amount(), 0.0);
+                    assert_eq!(remittance_line.not_allowed_amount(), 0.0);
                 }
             }
             _ => panic!("Expected remittance response"),
         }
     }
 
+    /// Test that mispriced remittances follow the payer's mismatch policy.
+    /// Expected: Under `exception` the remittance is held with a reason; under `regenerate` a payer that
+    /// misprices every claim misprices the repricing too, so it is flagged rather than passed as balanced.
+    #[tokio::test]
+    async fn test_payer_mismatch_policy() {
+        for (policy, held) in [(MismatchPolicy::Exception, true), (MismatchPolicy::Regenerate, false)] {
+            let (payer_tx, payer_rx) = tokio::sync::mpsc::channel(1);
+            let (remittance_tx, mut remittance_rx) = tokio::sync::mpsc::channel(1);
+            let payer = Payer::new("medicare", 0, 0, remittance_tx, payer_rx, false);
+            let handle = payer.handle();
+            handle
+                .reconfigure(PayerSettings { error_rate: 1.0, mismatch_policy: policy, ..handle.settings() })
+                .unwrap();
+            tokio::spawn(payer.run());
+            payer_tx.send(PayerMessage::Adjudicate(Arc::new(mock_claim()), Span::none())).await.unwrap();
+            match timeout(Duration::from_secs(5), remittance_rx.recv()).await {
+                Ok(Some(RemittanceMessage::Processed(remittance))) => {
+                    assert_eq!(remittance.balancing().is_held(), held, "{}", policy);
+                    assert!(!remittance.balancing().is_balanced(), "{}", policy);
+                }
+                _ => panic!("Expected remittance response"),
+            }
+        }
+    }
+
+    /// Test that a replacement takes over a payer whose task was aborted.
+    /// Expected: The replacement adjudicates a claim sent to the original's inbox, recording it in the original's portal.
+    #[tokio::test]
+    async fn test_payer_replacement_takes_over_inbox() {
+        let (payer_tx, payer_rx) = tokio::sync::mpsc::channel(1);
+        let (remittance_tx, mut remittance_rx) = tokio::sync::mpsc::channel(1);
+        let payer = Payer::new("medicare", 0, 0, remittance_tx, payer_rx, false);
+        let (portal, replacement) = (payer.portal(), payer.replacement());
+        let original = tokio::spawn(payer.run());
+        original.abort();
+        assert!(original.await.unwrap_err().is_cancelled());
+
+        tokio::spawn(replacement.run());
+        payer_tx.send(PayerMessage::Adjudicate(Arc::new(mock_claim()), Span::none())).await.unwrap();
+        match timeout(Duration::from_secs(5), remittance_rx.recv()).await {
+            Ok(Some(RemittanceMessage::Processed(_))) => {}
+            _ => panic!("Expected remittance response"),
+        }
+        assert!(matches!(portal.claim_status(&mock_claim().claim_id), Some(PayerClaimStatus::Finished { .. })));
+    }
+
+    /// Test that a replacement for a claim the payer never remitted is refused rather than adjudicated.
+    /// Expected: The payer answers with a rejection naming the original and never records the replacement.
+    #[tokio::test]
+    async fn test_payer_refuses_replacement_without_original() {
+        let (payer_tx, payer_rx) = tokio::sync::mpsc::channel(1);
+        let (remittance_tx, mut remittance_rx) = tokio::sync::mpsc::channel(1);
+        let payer = Payer::new("medicare", 0, 0, remittance_tx, payer_rx, false);
+        let portal = payer.portal();
+        tokio::spawn(payer.run());
+        let mut claim = mock_claim();
+        claim.claim_id = "replacement".into();
+        claim.claim_frequency_code = ClaimFrequency::Replacement;
+        claim.original_claim_id = Some("never-remitted".into());
+        payer_tx.send(PayerMessage::Adjudicate(Arc::new(claim), Span::none())).await.unwrap();
+        match timeout(Duration::from_secs(5), remittance_rx.recv()).await {
+            Ok(Some(RemittanceMessage::Rejected { claim_id, reason })) => {
+                assert_eq!(claim_id, "replacement");
+                assert!(reason.contains("never-remitted"), "{}", reason);
+            }
+            _ => panic!("Expected a rejection"),
+        }
+        assert!(portal.claim_status("replacement").is_none());
+    }
+
+    /// Test that a claim whose header total disagrees with its lines is denied as a claim error.
+    /// Expected: Nothing is paid and the denial carries CO-16.
+    #[tokio::test]
+    async fn test_payer_denies_unbalanced_header() {
+        let (payer_tx, payer_rx) = tokio::sync::mpsc::channel(1);
+        let (remittance_tx, mut remittance_rx) = tokio::sync::mpsc::channel(1);
+        tokio::spawn(Payer::new("medicare", 0, 0, remittance_tx, payer_rx, false).run());
+        let mut claim = mock_claim();
+        claim.total_charge_amount = Some(claim.total_charge() + 10.0);
+        payer_tx.send(PayerMessage::Adjudicate(Arc::new(claim), Span::none())).await.unwrap();
+        match timeout(Duration::from_secs(5), remittance_rx.recv()).await {
+            Ok(Some(RemittanceMessage::Processed(remittance))) => {
+                assert!(remittance.is_denied());
+                assert_eq!(remittance.denial_reason().map(|r| r.code()), Some("CO-16"));
+            }
+            _ => panic!("Expected remittance response"),
+        }
+    }
+
+    /// Test that a claim needing documentation is held until attachments arrive, and denied if they still fall short.
+    /// Expected: The payer requests an operative note first; with it the claim is paid, with the wrong document it is denied CO-16.
+    #[tokio::test]
+    async fn test_payer_requests_attachments() {
+        let rules = serde_json::from_str(r#"[{"procedures": "99213", "document_type": "OB"}]"#).unwrap();
+        let policy = Arc::new(AttachmentPolicy::new(rules).unwrap());
+        for (document_type, denied) in [("OB", false), ("09", true)] {
+            let (payer_tx, payer_rx) = tokio::sync::mpsc::channel(1);
+            let (remittance_tx, mut remittance_rx) = tokio::sync::mpsc::channel(1);
+            let payer = Payer::new("medicare", 0, 0, remittance_tx, payer_rx, false).with_attachments(policy.clone());
+            let portal = payer.portal();
+            tokio::spawn(payer.run());
+            let claim_id = mock_claim().claim_id;
+            payer_tx.send(PayerMessage::Adjudicate(Arc::new(mock_claim()), Span::none())).await.unwrap();
+            match timeout(Duration::from_secs(5), remittance_rx.recv()).await {
+                Ok(Some(RemittanceMessage::AttachmentRequest { document_types, .. })) => assert_eq!(document_types, vec!["OB"]),
+                _ => panic!("Expected an attachment request"),
+            }
+            assert!(matches!(portal.claim_status(&claim_id), Some(PayerClaimStatus::AwaitingAttachments { .. })));
+
+            let attachment = Attachment { document_type: document_type.to_string(), id: "att-1".to_string(), payload_path: None };
+            payer_tx.send(PayerMessage::Attachments(claim_id.clone(), vec![attachment])).await.unwrap();
+            match timeout(Duration::from_secs(5), remittance_rx.recv()).await {
+                Ok(Some(RemittanceMessage::Processed(remittance))) => {
+                    assert_eq!(remittance.is_denied(), denied, "{}", document_type);
+                    if denied {
+                        assert_eq!(remittance.denial_reason().map(|r| r.code()), Some("CO-16"));
+                    }
+                }
+                _ => panic!("Expected remittance response"),
+            }
+        }
+    }
+
+    /// Test that rules applied while a payer runs decide the claims after them, and each adjudication records its rules version.
+    /// Expected: The first claim is paid under version 1; after version 2 denies its procedure for lack of authorization the
+    /// next claim pays nothing, and the event log records versions 1 and 2 on the two adjudications.
+    #[tokio::test]
+    async fn test_payer_applies_reloaded_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl").to_string_lossy().into_owned();
+        let event_log = EventLog::open(&path).await.unwrap();
+        let (payer_tx, payer_rx) = tokio::sync::mpsc::channel(1);
+        let (remittance_tx, mut remittance_rx) = tokio::sync::mpsc::channel(1);
+        let rules = RuleBook::default();
+        let payer = Payer::new("medicare", 0, 0, remittance_tx, payer_rx, false)
+            .with_rules(rules.clone())
+            .with_event_log(event_log.clone());
+        tokio::spawn(payer.run());
+
+        let mut paid = Vec::new();
+        for claim_id in ["abc123", "abc124"] {
+            let claim = PayerClaim { claim_id: claim_id.into(), ..mock_claim() };
+            payer_tx.send(PayerMessage::Adjudicate(Arc::new(claim), Span::none())).await.unwrap();
+            match timeout(Duration::from_secs(5), remittance_rx.recv()).await {
+                Ok(Some(RemittanceMessage::Processed(remittance))) => paid.push(remittance.payer_paid()),
+                _ => panic!("Expected remittance response"),
+            }
+            let denials = serde_json::from_str(r#"[{"codes": "99213", "deny_auth": 1}]"#).unwrap();
+            rules.apply(PayerRules { outcomes: Arc::new(OutcomeTable::new(denials).unwrap()), ..PayerRules::default() });
+        }
+        assert!(paid[0] > 0.0);
+        assert_eq!(paid[1], 0.0);
+
+        event_log.flush().await;
+        let versions: Vec<Option<u64>> = crate::event_log::read_event_log(&path)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|record| match record.event {
+                Event::ClaimAdjudicated { rules_version, .. } => Some(rules_version),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(versions, [Some(1), Some(2)]);
+    }
+
+    /// Test that a claim for a member the roster shows had left before the date of service is denied.
+    /// Expected: Covered on the date of service the claim is paid; a day after termination it is denied CO-27.
+    #[tokio::test]
+    async fn test_payer_denies_ineligible_member() {
+        let last_day = chrono::NaiveDate::from_ymd_opt(2025, 6, 30).unwrap();
+        let member_id = mock_claim().insurance.subscriber_id().clone();
+        let roster = Arc::new(Roster::new(vec![Enrollment {
+            member_id,
+            enrolled: chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            terminated: Some(last_day),
+        }]));
+        for (date_of_service, denied) in [(last_day, false), (last_day.succ_opt().unwrap(), true)] {
+            let (payer_tx, payer_rx) = tokio::sync::mpsc::channel(1);
+            let (remittance_tx, mut remittance_rx) = tokio::sync::mpsc::channel(1);
+            let payer = Payer::new("medicare", 0, 0, remittance_tx, payer_rx, false).with_roster(roster.clone());
+            tokio::spawn(payer.run());
+            let mut claim = mock_claim();
+            claim.service_date = Some(date_of_service);
+            payer_tx.send(PayerMessage::Adjudicate(Arc::new(claim), Span::none())).await.unwrap();
+            match timeout(Duration::from_secs(5), remittance_rx.recv()).await {
+                Ok(Some(RemittanceMessage::Processed(remittance))) => {
+                    assert_eq!(remittance.is_denied(), denied, "{}", date_of_service);
+                    if denied {
+                        assert_eq!(remittance.denial_reason().map(|r| r.code()), Some("CO-27"));
+                    }
+                }
+                _ => panic!("Expected remittance response"),
+            }
+        }
+    }
+
+    /// Payer that pays claims in full unless a line needs authorization it does not have
+    struct AuthorizingPayer {
+        needs_authorization: HashSet<String>,
+    }
+
+    impl Adjudicator for AuthorizingPayer {
+        async fn adjudicate(&self, claim: &PayerClaim) -> AdjudicationResult {
+            if claim.service_lines.iter().any(|line| self.needs_authorization.contains(&line.procedure_code)) {
+                AdjudicationResult::Deny(Some(DenialReason::NoAuthorization))
+            } else {
+                AdjudicationResult::Remit(Remittance::from_claim(claim, mock_adjudication_date()))
+            }
+        }
+    }
+
+    /// Test that a custom adjudicator decides claims in place of the simulated payer.
+    /// Expected: A claim with no line needing authorization is paid in full; otherwise it is denied CO-197 and recorded as denied.
+    #[tokio::test]
+    async fn test_custom_adjudicator() {
+        let claim = mock_claim();
+        let procedure_code = claim.service_lines[0].procedure_code.clone();
+        for (needs_authorization, denied) in [(HashSet::new(), false), (HashSet::from([procedure_code.clone()]), true)] {
+            let (payer_tx, payer_rx) = tokio::sync::mpsc::channel(1);
+            let (remittance_tx, mut remittance_rx) = tokio::sync::mpsc::channel(1);
+            let payer = Payer::new("medicare", 0, 0, remittance_tx, payer_rx, false)
+                .with_adjudicator(AuthorizingPayer { needs_authorization });
+            let portal = payer.portal();
+            tokio::spawn(payer.run());
+            payer_tx.send(PayerMessage::Adjudicate(Arc::new(claim.clone()), Span::none())).await.unwrap();
+            match timeout(Duration::from_secs(5), remittance_rx.recv()).await {
+                Ok(Some(RemittanceMessage::Processed(remittance))) => {
+                    assert_eq!(remittance.is_denied(), denied);
+                    if denied {
+                        assert_eq!(remittance.denial_reason().map(|r| r.code()), Some("CO-197"));
+                    } else {
+                        assert_eq!(remittance.payer_paid(), Remittance::from_claim(&claim, mock_adjudication_date()).payer_paid());
+                    }
+                }
+                _ => panic!("Expected remittance response"),
+            }
+            assert!(matches!(portal.claim_status(&claim.claim_id), Some(PayerClaimStatus::Finished { denied: d, .. }) if d == denied));
+        }
+    }
+
     /// Test that payer exits gracefully if the channel is closed.
     /// Expected: Task exits without panic.
     #[tokio::test]
@@ -339,14 +1231,14 @@ mod tests {
         let mut empty_claim = mock_claim();
         empty_claim.service_lines.clear();
         payer_tx
-            .send(PayerMessage::Adjudicate(empty_claim.clone()))
+            .send(PayerMessage::Adjudicate(Arc::new(empty_claim.clone()), Span::none()))
             .await
             .unwrap();
         let timeout_duration = Duration::from_secs(5);
         match timeout(timeout_duration, remittance_rx.recv()).await {
             Ok(Some(RemittanceMessage::Processed(remittance))) => {
-                assert_eq!(remittance.claim_id, empty_claim.claim_id);
-                assert_eq!(remittance.service_line_remittances.len(), 0);
+                assert_eq!(remittance.claim_id(), &empty_claim.claim_id);
+                assert_eq!(remittance.service_line_remittances().len(), 0);
             }
             _ => panic!("Expected remittance response"),
         }
@@ -368,21 +1260,21 @@ mod tests {
             service_line.units = 10;
         }
         payer_tx
-            .send(PayerMessage::Adjudicate(large_claim.clone()))
+            .send(PayerMessage::Adjudicate(Arc::new(large_claim.clone()), Span::none()))
             .await
             .unwrap();
         let timeout_duration = Duration::from_secs(5);
         match timeout(timeout_duration, remittance_rx.recv()).await {
             Ok(Some(RemittanceMessage::Processed(remittance))) => {
-                assert_eq!(remittance.claim_id, large_claim.claim_id);
+                assert_eq!(remittance.claim_id(), &large_claim.claim_id);
                 for (i, service_line) in large_claim.service_lines.iter().enumerate() {
-                    let remittance_line = &remittance.service_line_remittances[i];
+                    let remittance_line = &remittance.service_line_remittances()[i];
                     let total_charge = service_line.unit_charge_amount * service_line.units as f64;
-                    let total_remitted = remittance_line.payer_paid_amount
-                        + remittance_line.coinsurance_amount
-                        + remittance_line.copay_amount
-                        + remittance_line.deductible_amount
-                        + remittance_line.not_allowed_amount;
+                    let total_remitted = remittance_line.payer_paid_amount()
+                        + remittance_line.coinsurance_amount()
+                        + remittance_line.copay_amount()
+                        + remittance_line.deductible_amount()
+                        + remittance_line.not_allowed_amount();
                     assert!((total_remitted - total_charge).abs() < 0.01);
                 }
             }
@@ -410,7 +1302,7 @@ mod tests {
         let claim = mock_claim();
         let start_time = std::time::Instant::now();
         payer_tx
-            .send(PayerMessage::Adjudicate(claim))
+            .send(PayerMessage::Adjudicate(Arc::new(claim), Span::none()))
             .await
             .unwrap();
         let _remittance = remittance_rx.recv().await.expect("Expected remittance");
@@ -420,4 +1312,163 @@ mod tests {
         // Should not take more than 3 seconds (max + buffer)
         assert!(elapsed <= Duration::from_secs(3));
     }
+
+    /// Test that a paused payer holds claims until resumed, and that reconfigured denial rates apply.
+    /// Expected: No remittance while paused; after resuming with a 100% denial rate the claim is denied.
+    #[tokio::test]
+    async fn test_payer_pause_and_reconfigure() {
+        let (payer_tx, payer_rx) = tokio::sync::mpsc::channel(1);
+        let (remittance_tx, mut remittance_rx) = tokio::sync::mpsc::channel(1);
+        let payer = Payer::new("medicare".to_string(), 0, 0, remittance_tx, payer_rx, false);
+        let handle = payer.handle();
+        handle.pause();
+        tokio::spawn(payer.run());
+
+        payer_tx.send(PayerMessage::Adjudicate(Arc::new(mock_claim()), Span::none())).await.unwrap();
+        assert!(timeout(Duration::from_millis(200), remittance_rx.recv()).await.is_err());
+
+        handle
+            .reconfigure(PayerSettings { denial_rate: 1.0, ..handle.settings() })
+            .unwrap();
+        handle.resume();
+        match timeout(Duration::from_secs(2), remittance_rx.recv()).await {
+            Ok(Some(RemittanceMessage::Processed(remittance))) => {
+                assert!(remittance.is_denied());
+                assert!(remittance.validate_against_claim(&mock_claim()).is_ok());
+            }
+            other => panic!("Expected a processed remittance, got {:?}", other.map(|msg| msg.is_some())),
+        }
+    }
+
+    /// Test that the payer's own store tracks a claim from receipt to remittance.
+    /// Expected: Unknown before it is sent, pending while adjudicated, finished and paid once remitted.
+    #[tokio::test]
+    async fn test_claim_status() {
+        let (payer_tx, payer_rx) = tokio::sync::mpsc::channel(1);
+        let (remittance_tx, mut remittance_rx) = tokio::sync::mpsc::channel(1);
+        let payer = Payer::new("medicare", 1, 1, remittance_tx, payer_rx, false);
+        assert_eq!(payer.claim_status("abc123"), None);
+        let portal = payer.portal();
+        tokio::spawn(payer.run());
+
+        payer_tx.send(PayerMessage::Adjudicate(Arc::new(mock_claim()), Span::none())).await.unwrap();
+        sleep(Duration::from_millis(200)).await;
+        assert_eq!(portal.claim_status("abc123"), Some(PayerClaimStatus::Pending));
+
+        timeout(Duration::from_secs(5), remittance_rx.recv()).await.unwrap().unwrap();
+        match portal.claim_status("abc123") {
+            Some(PayerClaimStatus::Finished { payer_paid, denied }) => {
+                assert!(payer_paid > 0.0);
+                assert!(!denied);
+            }
+            other => panic!("expected finished, got {:?}", other),
+        }
+    }
+
+    /// Test that claims beyond the worker pool wait in the intake queue.
+    /// Expected: With one adjudicator and two claims, one is busy and one queued at full utilization; both are eventually adjudicated.
+    #[tokio::test]
+    async fn test_worker_pool() {
+        let (payer_tx, payer_rx) = tokio::sync::mpsc::channel(2);
+        let (remittance_tx, mut remittance_rx) = tokio::sync::mpsc::channel(2);
+        let payer = Payer::new("medicare", 1, 1, remittance_tx, payer_rx, false).with_workers(1);
+        let portal = payer.portal();
+        tokio::spawn(payer.run());
+
+        let mut second = mock_claim();
+        second.claim_id = "def456".into();
+        payer_tx.send(PayerMessage::Adjudicate(Arc::new(mock_claim()), Span::none())).await.unwrap();
+        payer_tx.send(PayerMessage::Adjudicate(Arc::new(second), Span::none())).await.unwrap();
+        sleep(Duration::from_millis(200)).await;
+        let metrics = portal.metrics();
+        assert_eq!((metrics.workers, metrics.busy, metrics.queued), (1, 1, 1));
+        assert_eq!(metrics.utilization, 1.0);
+
+        for _ in 0..2 {
+            timeout(Duration::from_secs(5), remittance_rx.recv()).await.unwrap().unwrap();
+        }
+        assert_eq!(portal.metrics().adjudicated, 2);
+    }
+
+    /// Test that queued claims are taken by priority when an adjudicator frees up, not as they arrive.
+    /// Expected: With one adjudicator and three claims waiting, the urgent claim sent last is remitted first and the other two in arrival order.
+    #[tokio::test]
+    async fn test_worker_takes_most_urgent() {
+        let (payer_tx, payer_rx) = tokio::sync::mpsc::channel(3);
+        let (remittance_tx, mut remittance_rx) = tokio::sync::mpsc::channel(3);
+        let payer = Payer::new("medicare", 0, 0, remittance_tx, payer_rx, false).with_workers(1);
+        for (claim_id, urgent) in [("first", false), ("second", false), ("urgent", true)] {
+            let mut claim = mock_claim();
+            claim.claim_id = claim_id.into();
+            claim.urgent = Some(urgent);
+            payer_tx.send(PayerMessage::Adjudicate(Arc::new(claim), Span::none())).await.unwrap();
+        }
+        tokio::spawn(payer.run());
+
+        let mut order = Vec::new();
+        for _ in 0..3 {
+            match timeout(Duration::from_secs(5), remittance_rx.recv()).await.unwrap().unwrap() {
+                RemittanceMessage::Processed(remittance) => order.push(remittance.claim_id().to_string()),
+                other => panic!("expected a remittance, got {}", other.summary()),
+            }
+        }
+        assert_eq!(order, ["urgent", "first", "second"]);
+    }
+
+    /// Test that a patient's deductible counts toward the plan year it was paid in and resets when the virtual clock passes into the next.
+    /// Expected: A surgery claim on December 31st meets the deductible; a day later the patient's status shows a new plan year with nothing met.
+    #[tokio::test]
+    async fn test_plan_year_rollover() {
+        let (payer_tx, payer_rx) = tokio::sync::mpsc::channel(1);
+        let (remittance_tx, mut remittance_rx) = tokio::sync::mpsc::channel(1);
+        let new_years_eve = chrono::NaiveDate::from_ymd_opt(2025, 12, 31).unwrap();
+        let payer = Payer::new("medicare", 0, 0, remittance_tx, payer_rx, false).with_clock(SimClock::new(new_years_eve, 1.0));
+        let portal = payer.portal();
+        tokio::spawn(payer.run());
+
+        let mut claim = mock_claim();
+        claim.service_lines[0].procedure_code = "27447".to_string();
+        claim.service_lines[0].unit_charge_amount = 2_000.0;
+        payer_tx.send(PayerMessage::Adjudicate(Arc::new(claim), Span::none())).await.unwrap();
+        timeout(Duration::from_secs(5), remittance_rx.recv()).await.unwrap().unwrap();
+        let [status] = portal.benefit_statuses().try_into().unwrap();
+        assert_eq!(status.plan_year, chrono::NaiveDate::from_ymd_opt(2025, 1, 1).unwrap());
+        assert!(status.is_deductible_met());
+
+        sleep(Duration::from_millis(1_100)).await;
+        let [status] = portal.benefit_statuses().try_into().unwrap();
+        assert_eq!(status.plan_year, chrono::NaiveDate::from_ymd_opt(2026, 1, 1).unwrap());
+        assert_eq!(status.deductible_met, 0.0);
+        assert!(!status.is_deductible_met());
+    }
+
+    /// Test that a payer with an acknowledgment delay acks each claim it takes in before paying it.
+    /// Expected: With one adjudicator, both claims are acknowledged right away, the queued one included, and remitted a second apart afterwards.
+    #[tokio::test]
+    async fn test_payer_acknowledges_before_paying() {
+        let (payer_tx, payer_rx) = tokio::sync::mpsc::channel(2);
+        let (remittance_tx, mut remittance_rx) = tokio::sync::mpsc::channel(4);
+        let payer = Payer::new("medicare", 1, 1, remittance_tx, payer_rx, false).with_workers(1);
+        let handle = payer.handle();
+        handle.reconfigure(PayerSettings { ack_time_secs: Some((0, 0)), ..handle.settings() }).unwrap();
+        tokio::spawn(payer.run());
+
+        let mut second = mock_claim();
+        second.claim_id = "def456".into();
+        payer_tx.send(PayerMessage::Adjudicate(Arc::new(mock_claim()), Span::none())).await.unwrap();
+        payer_tx.send(PayerMessage::Adjudicate(Arc::new(second), Span::none())).await.unwrap();
+        let mut received = Vec::new();
+        for _ in 0..4 {
+            received.push(timeout(Duration::from_secs(5), remittance_rx.recv()).await.unwrap().unwrap());
+        }
+        let acked: HashSet<_> = received[..2]
+            .iter()
+            .map(|msg| match msg {
+                RemittanceMessage::Acknowledged { claim_id } => claim_id.as_str(),
+                other => panic!("Expected acknowledgments first, got {}", other.summary()),
+            })
+            .collect();
+        assert_eq!(acked, HashSet::from(["abc123", "def456"]));
+        assert!(received[2..].iter().all(|msg| matches!(msg, RemittanceMessage::Processed(_))));
+    }
 }
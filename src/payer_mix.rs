@@ -0,0 +1,214 @@
+use anyhow::{Context, anyhow};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::ids::{ClaimKey, PayerId};
+use crate::kpi;
+use crate::message::ClaimStatus;
+use crate::report_format::MoneyFields;
+
+/// One payer's share of generated claims and the fraction of billed charges it is expected to pay
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PayerShare {
+    pub payer_id: PayerId,
+    /// Percent of claims billed to this payer
+    pub percent: f64,
+    /// Average payer payment over billed charges, from 0 to 1
+    pub reimbursement_rate: f64,
+}
+
+impl PayerShare {
+    fn validate(&self) -> Result<(), String> {
+        if !(self.percent.is_finite() && self.percent > 0.0) {
+            return Err(format!("payer {}: percent must be positive, not {}", self.payer_id, self.percent));
+        }
+        if !(0.0..=1.0).contains(&self.reimbursement_rate) {
+            return Err(format!(
+                "payer {}: reimbursement_rate must be between 0 and 1, not {}",
+                self.payer_id, self.reimbursement_rate
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The payers fake claims are billed to, in what proportions, and what each is expected to pay
+///
+/// Percents must sum to 100. Claims are drawn from the mix in proportion to each payer's percent
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "Vec<PayerShare>")]
+pub struct PayerMix(Vec<PayerShare>);
+
+impl TryFrom<Vec<PayerShare>> for PayerMix {
+    type Error = String;
+
+    fn try_from(shares: Vec<PayerShare>) -> Result<Self, String> {
+        Self::new(shares).map_err(|e| e.to_string())
+    }
+}
+
+/// A payer's expected reimbursement under the mix against what its remittances paid
+///
+/// Expected and actual are over remitted claims, less originals replaced by a corrected claim
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReimbursementVariance {
+    pub payer_id: PayerId,
+    /// Percent of claims the mix bills to this payer
+    pub mix_percent: f64,
+    /// Percent of the run's claims billed to this payer
+    pub actual_percent: f64,
+    pub remitted: u32,
+    pub billed: f64,
+    /// Billed charges of remitted claims at the payer's reimbursement rate
+    pub expected: f64,
+    /// Paid by the payer on those claims
+    pub actual: f64,
+    /// Actual less expected; negative when the payer paid less than assumed
+    pub variance: f64,
+}
+
+impl MoneyFields for ReimbursementVariance {
+    fn map_money(&mut self, f: impl Fn(f64) -> f64) {
+        self.billed = f(self.billed);
+        self.expected = f(self.expected);
+        self.actual = f(self.actual);
+        self.variance = f(self.variance);
+    }
+}
+
+impl PayerMix {
+    pub fn new(shares: Vec<PayerShare>) -> anyhow::Result<Self> {
+        if shares.is_empty() {
+            return Err(anyhow!("payer mix names no payers"));
+        }
+        let mut seen = HashSet::new();
+        for share in &shares {
+            share.validate().map_err(|e| anyhow!(e))?;
+            if !seen.insert(&share.payer_id) {
+                return Err(anyhow!("payer {} is listed twice", share.payer_id));
+            }
+        }
+        let total: f64 = shares.iter().map(|share| share.percent).sum();
+        if (total - 100.0).abs() > 0.01 {
+            return Err(anyhow!("payer percents sum to {}, not 100", total));
+        }
+        Ok(Self(shares))
+    }
+
+    /// Read a JSON array of payer shares, e.g.
+    /// `[{"payer_id": "medicare", "percent": 60, "reimbursement_rate": 0.45}]`
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path).with_context(|| format!("reading payer mix {}", path))?;
+        let shares = serde_json::from_str(&contents).with_context(|| format!("parsing payer mix {}", path))?;
+        Self::new(shares).with_context(|| format!("invalid payer mix {}", path))
+    }
+
+    pub fn payer_ids(&self) -> impl Iterator<Item = &PayerId> {
+        self.0.iter().map(|share| &share.payer_id)
+    }
+
+    /// Draw the payer of the next claim
+    pub fn choose(&self, rng: &mut impl Rng) -> &PayerId {
+        let mut roll = rng.random::<f64>() * 100.0;
+        for share in &self.0 {
+            if roll < share.percent {
+                return &share.payer_id;
+            }
+            roll -= share.percent;
+        }
+        // rounding can leave the roll just past the last share
+        &self.0[self.0.len() - 1].payer_id
+    }
+
+    /// Expected against actual reimbursement of each payer in the mix, in mix order
+    pub fn reimbursement(&self, records: &HashMap<ClaimKey, ClaimStatus>) -> Vec<ReimbursementVariance> {
+        let replaced = kpi::replaced_claims(records);
+        let mut claims: BTreeMap<&PayerId, u32> = BTreeMap::new();
+        let mut remitted: HashMap<&PayerId, (u32, f64, f64)> = HashMap::new();
+        for (key, status) in records {
+            let (payer_id, record) = match status {
+                ClaimStatus::Submitted { claim, .. } => (&claim.insurance.payer_id, None),
+                ClaimStatus::Remitted(record) => (record.payer_id(), Some(record)),
+                ClaimStatus::Voided { .. } => continue,
+            };
+            if replaced.contains(key) {
+                continue;
+            }
+            *claims.entry(payer_id).or_default() += 1;
+            if let Some(record) = record {
+                let totals = remitted.entry(payer_id).or_default();
+                totals.0 += 1;
+                totals.1 += record.claim().total_charge();
+                totals.2 += record.remittance().payer_paid();
+            }
+        }
+        let total_claims: u32 = claims.values().sum();
+        self.0
+            .iter()
+            .map(|share| {
+                let (remitted, billed, actual) = remitted.get(&share.payer_id).copied().unwrap_or_default();
+                let expected = billed * share.reimbursement_rate;
+                let payer_claims = claims.get(&share.payer_id).copied().unwrap_or_default();
+                ReimbursementVariance {
+                    payer_id: share.payer_id.clone(),
+                    mix_percent: share.percent,
+                    actual_percent: match total_claims {
+                        0 => 0.0,
+                        total => payer_claims as f64 * 100.0 / total as f64,
+                    },
+                    remitted,
+                    billed,
+                    expected,
+                    actual,
+                    variance: actual - expected,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remittance::{Remittance, RemittanceRecord};
+    use crate::test_utils::{ClaimBuilder, mock_remittance};
+    use std::time::Instant;
+
+    fn share(payer_id: &str, percent: f64, reimbursement_rate: f64) -> PayerShare {
+        PayerShare { payer_id: payer_id.into(), percent, reimbursement_rate }
+    }
+
+    /// Test that claims are drawn in the mix's proportions and each payer's payments are compared to its assumed rate.
+    /// Expected: About 80% of draws are medicare; percents not summing to 100 are refused; medicare's paid claim is
+    /// measured against half its charge and anthem's denial against 60% of its charge, both with half the claims.
+    #[test]
+    fn test_payer_mix() {
+        assert!(PayerMix::new(vec![share("medicare", 80.0, 0.5), share("anthem", 10.0, 0.6)]).is_err());
+        assert!(PayerMix::new(vec![share("medicare", 80.0, 1.5), share("anthem", 20.0, 0.6)]).is_err());
+        let mix = PayerMix::new(vec![share("medicare", 80.0, 0.5), share("anthem", 20.0, 0.6)]).unwrap();
+        let mut rng = rand::rng();
+        let medicare = (0..10_000).filter(|_| mix.choose(&mut rng).as_str() == "medicare").count();
+        assert!((7_500..8_500).contains(&medicare), "{}", medicare);
+
+        let now = Instant::now();
+        let paid = ClaimBuilder::new().claim_id("a").payer("medicare").build();
+        let denied = ClaimBuilder::new().claim_id("b").payer("anthem").build();
+        let (charge, payment) = (paid.total_charge(), mock_remittance().payer_paid());
+        let records: HashMap<ClaimKey, ClaimStatus> = [
+            RemittanceRecord::new(paid, mock_remittance(), now, now),
+            RemittanceRecord::new(denied.clone(), Remittance::denied(&denied), now, now),
+        ]
+        .into_iter()
+        .map(ClaimStatus::Remitted)
+        .map(|status| (status.key(), status))
+        .collect();
+
+        let rows = mix.reimbursement(&records);
+        assert_eq!(rows.iter().map(|row| (row.payer_id.as_str(), row.remitted)).collect::<Vec<_>>(), [("medicare", 1), ("anthem", 1)]);
+        assert!((rows[0].expected - charge * 0.5).abs() < 1e-9);
+        assert!((rows[0].variance - (payment - charge * 0.5)).abs() < 1e-9);
+        assert!((rows[1].variance + denied.total_charge() * 0.6).abs() < 1e-9);
+        assert_eq!((rows[0].actual_percent, rows[1].actual_percent), (50.0, 50.0));
+    }
+}
@@ -0,0 +1,216 @@
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use crate::attachments::AttachmentPolicy;
+use crate::benefits::BenefitPlans;
+use crate::event_log::{Event, EventLog};
+use crate::logging::log_claim_event;
+use crate::medical_necessity::CoveragePolicy;
+use crate::outcomes::OutcomeTable;
+
+/// How often watched rule files are checked for changes
+pub const RULES_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The rules simulated payers adjudicate under: line outcomes, medical necessity, documentation,
+/// and the plans whose fee schedules and cost sharing price each claim
+///
+/// `version` starts at 1 and counts up each time new rules are applied to running payers
+#[derive(Debug, Clone)]
+pub struct PayerRules {
+    pub version: u64,
+    pub outcomes: Arc<OutcomeTable>,
+    pub coverage: Arc<CoveragePolicy>,
+    pub attachments: Arc<AttachmentPolicy>,
+    pub benefits: Arc<BenefitPlans>,
+}
+
+impl Default for PayerRules {
+    fn default() -> Self {
+        Self {
+            version: 1,
+            outcomes: Arc::default(),
+            coverage: Arc::default(),
+            attachments: Arc::default(),
+            benefits: Arc::default(),
+        }
+    }
+}
+
+/// Files payer rules are read from; each absent file keeps the simulator's default
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RulePaths {
+    pub outcomes: Option<String>,
+    pub coverage: Option<String>,
+    pub attachments: Option<String>,
+    pub benefits: Option<String>,
+}
+
+impl RulePaths {
+    pub fn is_empty(&self) -> bool {
+        self.paths().next().is_none()
+    }
+
+    /// The files given, in the order rules are read
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        [&self.outcomes, &self.coverage, &self.attachments, &self.benefits].into_iter().flatten().map(String::as_str)
+    }
+
+    pub fn load(&self) -> anyhow::Result<PayerRules> {
+        Ok(PayerRules {
+            version: 1,
+            outcomes: Arc::new(self.outcomes.as_deref().map(OutcomeTable::load).transpose()?.unwrap_or_default()),
+            coverage: Arc::new(self.coverage.as_deref().map(CoveragePolicy::load).transpose()?.unwrap_or_default()),
+            attachments: Arc::new(self.attachments.as_deref().map(AttachmentPolicy::load).transpose()?.unwrap_or_default()),
+            benefits: Arc::new(self.benefits.as_deref().map(BenefitPlans::load).transpose()?.unwrap_or_default()),
+        })
+    }
+}
+
+/// The payer rules in force, shared by every payer reading them
+///
+/// Payers take the current rules once per claim, so a claim is adjudicated under one version
+/// from start to finish while new rules apply to the claims after it
+#[derive(Debug, Clone, Default)]
+pub struct RuleBook {
+    current: Arc<RwLock<Arc<PayerRules>>>,
+}
+
+impl RuleBook {
+    pub fn new(rules: PayerRules) -> Self {
+        Self { current: Arc::new(RwLock::new(Arc::new(rules))) }
+    }
+
+    pub fn current(&self) -> Arc<PayerRules> {
+        self.current.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    pub fn version(&self) -> u64 {
+        self.current().version
+    }
+
+    /// Put `rules` in force as the next version, returning its number
+    pub fn apply(&self, rules: PayerRules) -> u64 {
+        let mut current = self.current.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let version = current.version + 1;
+        *current = Arc::new(PayerRules { version, ..rules });
+        version
+    }
+
+    /// Change the rules in force without counting a new version, while a payer is being set up
+    pub(crate) fn edit(&self, change: impl FnOnce(&mut PayerRules)) {
+        let mut current = self.current.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        change(Arc::make_mut(&mut current));
+    }
+}
+
+/// Two handles are equal when they share one book
+impl PartialEq for RuleBook {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.current, &other.current)
+    }
+}
+
+/// Size and modification time of each watched file, `None` for one that cannot be read
+type Stamps = Vec<Option<(SystemTime, u64)>>;
+
+/// Reloads payer rules when their files change, applying them to running payers
+///
+/// Files are polled rather than subscribed to, so edits made by replacing a file are seen too.
+/// Rules that fail to load are reported and the version in force is kept
+#[derive(Debug)]
+pub struct RuleWatcher {
+    paths: RulePaths,
+    book: RuleBook,
+    stamps: Stamps,
+    event_log: Option<EventLog>,
+}
+
+impl RuleWatcher {
+    /// Watch `paths`, taking the files as they are now to be the rules `book` holds
+    pub fn new(paths: RulePaths, book: RuleBook) -> Self {
+        let stamps = stamps(&paths);
+        Self { paths, book, stamps, event_log: None }
+    }
+
+    /// Record each version applied to an event log
+    pub fn with_event_log(mut self, event_log: EventLog) -> Self {
+        self.event_log = Some(event_log);
+        self
+    }
+
+    /// Reload the rules if any file changed since the last check, returning the version applied
+    pub fn check(&mut self) -> Option<u64> {
+        let stamps = stamps(&self.paths);
+        if stamps == self.stamps {
+            return None;
+        }
+        self.stamps = stamps;
+        match self.paths.load() {
+            Ok(rules) => {
+                let version = self.book.apply(rules);
+                log_claim_event("rules", "-", "rules_applied", &format!("Applied payer rules version {}", version));
+                if let Some(event_log) = &self.event_log {
+                    event_log.record(Event::RulesApplied { version });
+                }
+                Some(version)
+            }
+            Err(e) => {
+                eprintln!("Keeping payer rules version {}: {:#}", self.book.version(), e);
+                None
+            }
+        }
+    }
+
+    /// Check for changes every `interval` until the task is dropped
+    pub async fn run(mut self, interval: Duration) {
+        let mut ticks = tokio::time::interval(interval);
+        ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            self.check();
+        }
+    }
+}
+
+fn stamps(paths: &RulePaths) -> Stamps {
+    paths
+        .paths()
+        .map(|path| std::fs::metadata(path).ok().and_then(|meta| Some((meta.modified().ok()?, meta.len()))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Test that editing a watched rule file applies the new rules as the next version.
+    /// Expected: An unchanged file applies nothing, an edit applies version 2 to every handle on the book,
+    /// and a file that no longer parses keeps version 2 in force.
+    #[test]
+    fn test_rule_watcher_reloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let outcomes = dir.path().join("outcomes.json");
+        fs::write(&outcomes, r#"[{"codes": "70010-79999", "pay": 1}]"#).unwrap();
+        let paths = RulePaths { outcomes: Some(outcomes.to_string_lossy().into_owned()), ..RulePaths::default() };
+        let book = RuleBook::new(paths.load().unwrap());
+        let payer_view = book.clone();
+        let mut watcher = RuleWatcher::new(paths, book);
+        assert_eq!(watcher.check(), None);
+
+        let touch = |contents: &str, secs: u64| {
+            fs::write(&outcomes, contents).unwrap();
+            let file = fs::File::options().write(true).open(&outcomes).unwrap();
+            file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)).unwrap();
+        };
+        touch(r#"[{"codes": "70010-79999", "deny_auth": 1}]"#, 1_000);
+        assert_eq!(watcher.check(), Some(2));
+        let rules = payer_view.current();
+        assert_eq!(rules.version, 2);
+        assert!(rules.outcomes.rule("70450").is_some_and(|rule| rule.deny_auth == 1.0));
+
+        touch("not json", 2_000);
+        assert_eq!(watcher.check(), None);
+        assert_eq!(payer_view.version(), 2);
+    }
+}
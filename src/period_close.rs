@@ -0,0 +1,154 @@
+use chrono::NaiveDate;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Mutex;
+use tokio::time::sleep_until;
+
+use crate::clock::{self, SimClock};
+use crate::ledger::{AdjustmentReason, EntryKind, Ledger};
+use crate::report_format::ReportFormat;
+use crate::reporter;
+
+/// Frozen financial results for one closed accounting period
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeriodReport {
+    pub period_start: NaiveDate,
+    /// Exclusive: first day of the next period
+    pub period_end: NaiveDate,
+    pub opening_ar: f64,
+    pub gross_charges: f64,
+    pub contractual_adjustments: f64,
+    pub other_adjustments: f64,
+    pub payer_cash: f64,
+    pub patient_cash: f64,
+    pub ending_ar: f64,
+}
+
+impl PeriodReport {
+    /// Gross charges less contractual adjustments
+    pub fn net_revenue(&self) -> f64 {
+        self.gross_charges - self.contractual_adjustments
+    }
+
+    pub fn cash_collected(&self) -> f64 {
+        self.payer_cash + self.patient_cash
+    }
+}
+
+/// Closes accounting periods against a ledger, rolling ending AR forward
+///
+/// Each close covers the ledger entries posted since the previous close and
+/// before the cutoff; closed entries are never counted again
+#[derive(Debug, Default)]
+pub struct PeriodCloser {
+    next_entry: usize,
+    opening_ar: f64,
+    closed: Vec<PeriodReport>,
+}
+
+impl PeriodCloser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Close the period `[period_start, period_end)` with entries posted before `cutoff`
+    pub fn close(&mut self, ledger: &Ledger, period_start: NaiveDate, period_end: NaiveDate, cutoff: Instant) -> PeriodReport {
+        let mut report = PeriodReport {
+            period_start,
+            period_end,
+            opening_ar: self.opening_ar,
+            gross_charges: 0.0,
+            contractual_adjustments: 0.0,
+            other_adjustments: 0.0,
+            payer_cash: 0.0,
+            patient_cash: 0.0,
+            ending_ar: 0.0,
+        };
+        let entries = &ledger.entries()[self.next_entry..];
+        let in_period = entries.iter().take_while(|entry| entry.posted_at < cutoff);
+        let mut count = 0;
+        for entry in in_period {
+            count += 1;
+            match entry.kind {
+                EntryKind::Charge => report.gross_charges += entry.amount,
+                EntryKind::PayerPayment => report.payer_cash += entry.amount,
+                EntryKind::PatientPayment => report.patient_cash += entry.amount,
+                EntryKind::Adjustment(AdjustmentReason::Contractual) => report.contractual_adjustments += entry.amount,
+                EntryKind::Adjustment(_) => report.other_adjustments += entry.amount,
+                // moves balance from payer to patient AR; total AR is unchanged
+                EntryKind::PatientResponsibility => {}
+            }
+        }
+        self.next_entry += count;
+        report.ending_ar = report.opening_ar + report.net_revenue() - report.other_adjustments - report.cash_collected();
+        self.opening_ar = report.ending_ar;
+        self.closed.push(report.clone());
+        report
+    }
+
+    /// All periods closed so far, oldest first
+    pub fn closed(&self) -> &[PeriodReport] {
+        &self.closed
+    }
+}
+
+/// Close each simulated month as the virtual clock passes its end and print the period report
+pub async fn run_period_close(
+    tenant_id: String,
+    ledger: Arc<Mutex<Ledger>>,
+    clock: SimClock,
+    format: ReportFormat,
+    verbose: bool,
+) {
+    if verbose {
+        println!("[period_close] Starting month-end close for tenant {}", tenant_id);
+    }
+    let mut closer = PeriodCloser::new();
+    let mut period_start = clock::month_start(clock.now().date());
+    loop {
+        let period_end = clock::next_month_start(period_start);
+        let cutoff = clock.instant_of(period_end.and_hms_opt(0, 0, 0).expect("midnight is a valid time"));
+        sleep_until(cutoff.into()).await;
+        let report = closer.close(&*ledger.lock().await, period_start, period_end, cutoff);
+        reporter::print_period_close_report(&tenant_id, &report, &format);
+        period_start = period_end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::LedgerEntry;
+    use std::time::Duration;
+
+    /// Test that a close freezes the period totals and rolls ending AR into the next period.
+    /// Expected: First period ends with AR 20; entries after the cutoff land in the second period.
+    #[test]
+    fn test_period_close_rolls_forward() {
+        let jan = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let feb = NaiveDate::from_ymd_opt(2025, 2, 1).unwrap();
+        let mar = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        let mut ledger = Ledger::new();
+        ledger.post(LedgerEntry::new("c1", "p1", "medicare", EntryKind::Charge, 100.0));
+        ledger.post(LedgerEntry::new("c1", "p1", "medicare", EntryKind::PayerPayment, 70.0));
+        ledger.post(LedgerEntry::new("c1", "p1", "medicare", EntryKind::Adjustment(AdjustmentReason::Contractual), 10.0));
+        ledger.post(LedgerEntry::new("c1", "p1", "medicare", EntryKind::PatientResponsibility, 20.0));
+        let cutoff = Instant::now() + Duration::from_millis(1);
+        std::thread::sleep(Duration::from_millis(2));
+        ledger.post(LedgerEntry::new("c1", "p1", "medicare", EntryKind::PatientPayment, 15.0));
+
+        let mut closer = PeriodCloser::new();
+        let january = closer.close(&ledger, jan, feb, cutoff);
+        assert_eq!(january.gross_charges, 100.0);
+        assert_eq!(january.net_revenue(), 90.0);
+        assert_eq!(january.cash_collected(), 70.0);
+        assert_eq!(january.ending_ar, 20.0);
+
+        let february = closer.close(&ledger, feb, mar, Instant::now());
+        assert_eq!(february.opening_ar, 20.0);
+        assert_eq!(february.gross_charges, 0.0);
+        assert_eq!(february.patient_cash, 15.0);
+        assert_eq!(february.ending_ar, 5.0);
+        assert_eq!(closer.closed().len(), 2);
+    }
+}
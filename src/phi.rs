@@ -0,0 +1,189 @@
+use serde_json::Value;
+
+use crate::ids::MemberId;
+use crate::pseudonym::Pseudonymizer;
+use crate::rejects::LineError;
+use crate::schema::{Address, PayerClaim};
+
+/// What masked names and addresses read in redacted output
+pub const REDACTED: &str = "[redacted]";
+
+/// JSON pointers of the patient and subscriber demographics redaction masks
+const PHI_POINTERS: [&str; 8] = [
+    "/patient/first_name",
+    "/patient/last_name",
+    "/patient/dob",
+    "/patient/email",
+    "/patient/address",
+    "/insurance/subscriber/first_name",
+    "/insurance/subscriber/last_name",
+    "/insurance/subscriber/dob",
+];
+
+/// JSON pointers of the member ids redaction swaps for pseudonymous ones
+const MEMBER_ID_POINTERS: [&str; 2] = ["/insurance/patient_member_id", "/insurance/subscriber/member_id"];
+
+/// How patient demographics are de-identified in what a run writes
+#[derive(Debug, Clone, Default)]
+pub enum Deidentification {
+    /// Written as received
+    #[default]
+    Off,
+    /// Masked by `redact_claim`, member ids pseudonymized under a key drawn for the run
+    Redact(Pseudonymizer),
+    /// Replaced by stable synthetic identities; lines that are not valid claims are masked
+    Pseudonymize(Pseudonymizer),
+}
+
+impl Deidentification {
+    /// Redaction whose pseudonymous member ids link up within the run but not across runs, and
+    /// cannot be traced back to the member once the run is over
+    pub fn redact() -> Self {
+        Deidentification::Redact(Pseudonymizer::new(rand::random::<[u8; 32]>()))
+    }
+
+    pub fn is_off(&self) -> bool {
+        matches!(self, Deidentification::Off)
+    }
+
+    pub fn claim(&self, claim: &mut PayerClaim) {
+        match self {
+            Deidentification::Off => {}
+            Deidentification::Redact(pseudonymizer) => redact_claim(claim, pseudonymizer),
+            Deidentification::Pseudonymize(pseudonymizer) => pseudonymizer.pseudonymize_claim(claim),
+        }
+    }
+
+    /// Member id as written
+    pub fn member_id(&self, member_id: &MemberId) -> MemberId {
+        match self {
+            Deidentification::Off => member_id.clone(),
+            Deidentification::Redact(pseudonymizer) | Deidentification::Pseudonymize(pseudonymizer) => pseudonymizer.member_id(member_id),
+        }
+    }
+
+    pub fn line(&self, line: &str) -> String {
+        match self {
+            Deidentification::Off => line.to_string(),
+            Deidentification::Redact(pseudonymizer) | Deidentification::Pseudonymize(pseudonymizer) => redact_line(line, pseudonymizer),
+        }
+    }
+
+    pub fn line_error(&self, err: LineError) -> LineError {
+        match self {
+            Deidentification::Off => err,
+            _ => redact_line_error(err),
+        }
+    }
+}
+
+/// Mask the patient's and subscriber's names, dates of birth, email, and street address, and
+/// swap their member ids for `pseudonymizer`'s
+///
+/// Claim and provider ids are kept, and a member keeps one pseudonymous id, so redacted output
+/// still links up. Addresses keep their state and the first three digits of the ZIP code, as
+/// HIPAA Safe Harbor allows
+pub fn redact_claim(claim: &mut PayerClaim, pseudonymizer: &Pseudonymizer) {
+    let patient = &mut claim.patient;
+    patient.first_name = REDACTED.to_string();
+    patient.last_name = REDACTED.to_string();
+    patient.dob = None;
+    patient.email = None;
+    if let Some(address) = &mut patient.address {
+        redact_address(address);
+    }
+    claim.insurance.patient_member_id = pseudonymizer.member_id(&claim.insurance.patient_member_id);
+    if let Some(subscriber) = &mut claim.insurance.subscriber {
+        subscriber.first_name = REDACTED.to_string();
+        subscriber.last_name = REDACTED.to_string();
+        subscriber.dob = None;
+        subscriber.member_id = pseudonymizer.member_id(&subscriber.member_id);
+    }
+}
+
+fn redact_address(address: &mut Address) {
+    address.street = address.street.as_ref().map(|_| REDACTED.to_string());
+    address.city = address.city.as_ref().map(|_| REDACTED.to_string());
+    address.zip = address.zip.as_ref().map(|zip| zip.chars().take(3).collect());
+}
+
+/// Mask the same fields as `redact_claim` in a claim's JSON, which need not be a valid claim
+///
+/// A line that is not JSON at all cannot be picked apart and is masked whole; a member id that is
+/// not a string is masked rather than pseudonymized
+pub fn redact_line(line: &str, pseudonymizer: &Pseudonymizer) -> String {
+    let Ok(mut value) = serde_json::from_str::<Value>(line) else {
+        return REDACTED.to_string();
+    };
+    for pointer in MEMBER_ID_POINTERS {
+        match value.pointer_mut(pointer) {
+            Some(Value::String(member_id)) => *member_id = pseudonymizer.member_id(&MemberId::new(member_id.as_str())).to_string(),
+            Some(Value::Null) | None => {}
+            Some(field) => *field = Value::from(REDACTED),
+        }
+    }
+    for pointer in PHI_POINTERS {
+        let Some(field) = value.pointer_mut(pointer) else {
+            continue;
+        };
+        match field {
+            Value::Null => {}
+            Value::Object(address) => {
+                for key in ["street", "city"] {
+                    if let Some(part) = address.get_mut(key).filter(|part| !part.is_null()) {
+                        *part = Value::from(REDACTED);
+                    }
+                }
+                if let Some(Value::String(zip)) = address.get_mut("zip") {
+                    *zip = zip.chars().take(3).collect();
+                }
+            }
+            _ if pointer.ends_with("name") => *field = Value::from(REDACTED),
+            _ => *field = Value::Null,
+        }
+    }
+    value.to_string()
+}
+
+/// Mask the reason of an error about a demographic field, which may quote its value
+pub(crate) fn redact_line_error(mut err: LineError) -> LineError {
+    if PHI_POINTERS.iter().chain(&MEMBER_ID_POINTERS).any(|pointer| err.pointer.starts_with(pointer)) {
+        err.reason = format!("invalid value {}", REDACTED);
+    }
+    err
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::mock_claim;
+
+    /// Test that a claim and its raw JSON are redacted alike, keeping claim ids and swapping member ids.
+    /// Expected: Names masked, DOB and email dropped, ZIP cut to 3 digits, member id pseudonymous in both; errors about the DOB
+    /// or a member id lose their reason.
+    #[test]
+    fn test_redact_claim_and_line() {
+        let pseudonymizer = Pseudonymizer::new("run key");
+        let mut claim = mock_claim();
+        let line = serde_json::to_string(&claim).unwrap();
+        let member_id = claim.insurance.patient_member_id.clone();
+        redact_claim(&mut claim, &pseudonymizer);
+        assert_eq!(claim.insurance.patient_member_id, pseudonymizer.member_id(&member_id));
+        assert_eq!(claim.patient.first_name, REDACTED);
+        assert_eq!(claim.patient.dob, None);
+        assert_eq!(claim.patient.email, None);
+
+        let redacted: PayerClaim = serde_json::from_str(&redact_line(&line, &pseudonymizer)).unwrap();
+        assert_eq!(redacted.patient.last_name, REDACTED);
+        assert_eq!(redacted.patient.dob, None);
+        assert_eq!(redacted.insurance.patient_member_id, claim.insurance.patient_member_id);
+        assert_eq!(serde_json::to_value(&redacted).unwrap(), serde_json::to_value(&claim).unwrap());
+        assert_eq!(redacted.patient.address.and_then(|address| address.zip).as_deref(), Some("123"));
+        assert_eq!(redact_line("not json, Jane Doe", &pseudonymizer), REDACTED);
+
+        let err = LineError { pointer: "/patient/dob".to_string(), reason: "invalid date 1990-13-01".to_string() };
+        assert!(!redact_line_error(err).reason.contains("1990"));
+        let err = LineError { pointer: "/insurance/patient_member_id".to_string(), reason: format!("invalid id {}", member_id) };
+        assert!(!redact_line_error(err).reason.contains(member_id.as_str()));
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// CMS place-of-service code: where the services on a claim were rendered
+///
+/// Only codes CMS has assigned are accepted; the code serializes as a bare number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u32", into = "u32")]
+pub struct PlaceOfService(u8);
+
+/// Codes CMS has assigned, with their short descriptions
+const ASSIGNED: [(u8, &str); 52] = [
+    (1, "Pharmacy"),
+    (2, "Telehealth other than in patient's home"),
+    (3, "School"),
+    (4, "Homeless shelter"),
+    (5, "Indian Health Service free-standing facility"),
+    (6, "Indian Health Service provider-based facility"),
+    (7, "Tribal 638 free-standing facility"),
+    (8, "Tribal 638 provider-based facility"),
+    (9, "Prison/correctional facility"),
+    (10, "Telehealth in patient's home"),
+    (11, "Office"),
+    (12, "Home"),
+    (13, "Assisted living facility"),
+    (14, "Group home"),
+    (15, "Mobile unit"),
+    (16, "Temporary lodging"),
+    (17, "Walk-in retail health clinic"),
+    (18, "Place of employment"),
+    (19, "Off campus outpatient hospital"),
+    (20, "Urgent care facility"),
+    (21, "Inpatient hospital"),
+    (22, "On campus outpatient hospital"),
+    (23, "Emergency room"),
+    (24, "Ambulatory surgical center"),
+    (25, "Birthing center"),
+    (26, "Military treatment facility"),
+    (27, "Outreach site/street"),
+    (31, "Skilled nursing facility"),
+    (32, "Nursing facility"),
+    (33, "Custodial care facility"),
+    (34, "Hospice"),
+    (41, "Ambulance - land"),
+    (42, "Ambulance - air or water"),
+    (49, "Independent clinic"),
+    (50, "Federally qualified health center"),
+    (51, "Inpatient psychiatric facility"),
+    (52, "Psychiatric facility partial hospitalization"),
+    (53, "Community mental health center"),
+    (54, "Intermediate care facility/individuals with intellectual disabilities"),
+    (55, "Residential substance abuse treatment facility"),
+    (56, "Psychiatric residential treatment center"),
+    (57, "Non-residential substance abuse treatment facility"),
+    (58, "Non-residential opioid treatment facility"),
+    (60, "Mass immunization center"),
+    (61, "Comprehensive inpatient rehabilitation facility"),
+    (62, "Comprehensive outpatient rehabilitation facility"),
+    (65, "End-stage renal disease treatment facility"),
+    (66, "Programs of all-inclusive care for the elderly"),
+    (71, "Public health clinic"),
+    (72, "Rural health clinic"),
+    (81, "Independent laboratory"),
+    (99, "Other place of service"),
+];
+
+impl PlaceOfService {
+    pub const TELEHEALTH: PlaceOfService = PlaceOfService(2);
+    pub const TELEHEALTH_HOME: PlaceOfService = PlaceOfService(10);
+    pub const OFFICE: PlaceOfService = PlaceOfService(11);
+    pub const HOME: PlaceOfService = PlaceOfService(12);
+    pub const URGENT_CARE: PlaceOfService = PlaceOfService(20);
+    pub const INPATIENT_HOSPITAL: PlaceOfService = PlaceOfService(21);
+    pub const OUTPATIENT_HOSPITAL: PlaceOfService = PlaceOfService(22);
+    pub const EMERGENCY_ROOM: PlaceOfService = PlaceOfService(23);
+    pub const AMBULATORY_SURGICAL_CENTER: PlaceOfService = PlaceOfService(24);
+
+    /// The code, or `None` if CMS has not assigned it
+    pub fn new(code: u32) -> Option<PlaceOfService> {
+        let code = u8::try_from(code).ok()?;
+        ASSIGNED
+            .iter()
+            .any(|(assigned, _)| *assigned == code)
+            .then_some(PlaceOfService(code))
+    }
+
+    pub fn code(&self) -> u32 {
+        self.0 as u32
+    }
+
+    pub fn description(&self) -> &'static str {
+        ASSIGNED
+            .iter()
+            .find(|(code, _)| *code == self.0)
+            .map(|(_, description)| *description)
+            .unwrap_or_default()
+    }
+
+    /// Hospitals and surgical centers bill the facility fee separately, so the
+    /// professional service is paid at the lower facility rate
+    pub fn is_facility(&self) -> bool {
+        matches!(self.0, 19 | 21..=24 | 31 | 51 | 52 | 56 | 61)
+    }
+
+    pub fn is_telehealth(&self) -> bool {
+        *self == Self::TELEHEALTH || *self == Self::TELEHEALTH_HOME
+    }
+}
+
+impl Default for PlaceOfService {
+    fn default() -> Self {
+        Self::OFFICE
+    }
+}
+
+impl TryFrom<u32> for PlaceOfService {
+    type Error = String;
+
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        PlaceOfService::new(code).ok_or_else(|| format!("unassigned place of service code {:02}", code))
+    }
+}
+
+impl From<PlaceOfService> for u32 {
+    fn from(pos: PlaceOfService) -> Self {
+        pos.code()
+    }
+}
+
+impl fmt::Display for PlaceOfService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that assigned codes parse from JSON numbers and unassigned ones are refused.
+    /// Expected: 11 is the office and 2 telehealth, displayed zero-padded; 0, 59, and 300 fail.
+    #[test]
+    fn test_place_of_service_codes() {
+        let office: PlaceOfService = serde_json::from_str("11").unwrap();
+        assert_eq!(office, PlaceOfService::OFFICE);
+        assert_eq!(office.description(), "Office");
+        assert_eq!(PlaceOfService::TELEHEALTH.to_string(), "02");
+        assert_eq!(serde_json::to_string(&PlaceOfService::INPATIENT_HOSPITAL).unwrap(), "21");
+        assert!(PlaceOfService::INPATIENT_HOSPITAL.is_facility());
+        for code in ["0", "59", "300"] {
+            assert!(serde_json::from_str::<PlaceOfService>(code).is_err(), "{}", code);
+        }
+    }
+}
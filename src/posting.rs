@@ -0,0 +1,147 @@
+use crate::ids::{ClaimId, MemberId, PayerId};
+use crate::ledger::{AdjustmentReason, EntryKind, Ledger, LedgerEntry};
+use crate::remittance::Remittance;
+use crate::schema::PayerClaim;
+
+/// Automatic write-off rules applied when a remittance is posted
+#[derive(Debug, Clone)]
+pub struct WriteOffRules {
+    /// Patient balances below this amount are written off instead of billed
+    pub small_balance_threshold: f64,
+    /// Write off the payer's not-allowed amount as a contractual adjustment
+    pub contractual_adjustments: bool,
+}
+
+impl Default for WriteOffRules {
+    fn default() -> Self {
+        Self {
+            small_balance_threshold: 5.0,
+            contractual_adjustments: true,
+        }
+    }
+}
+
+impl WriteOffRules {
+    /// Whether a patient balance falls under the small-balance write-off threshold
+    pub fn is_small_balance(&self, amount: f64) -> bool {
+        amount > 0.0 && amount < self.small_balance_threshold
+    }
+}
+
+/// Post a received remittance to the ledger
+///
+/// Records the gross charge, the payer payment, and the patient's share,
+/// then applies the write-off rules as adjustment entries
+/// Returns the patient balance left to bill after write-offs
+pub fn post_remittance(
+    ledger: &mut Ledger,
+    claim: &PayerClaim,
+    remittance: &Remittance,
+    rules: &WriteOffRules,
+) -> f64 {
+    let insurance = &claim.insurance;
+    post_payment(ledger, &claim.claim_id, &insurance.patient_member_id, &insurance.payer_id, claim.total_charge(), remittance, rules)
+}
+
+/// Post a remittance to the ledger as `post_remittance` does, from only the claim's ids and
+/// billed charge, as a remittance file records them
+pub fn post_payment(
+    ledger: &mut Ledger,
+    claim_id: &ClaimId,
+    patient_id: &MemberId,
+    payer_id: &PayerId,
+    charge: f64,
+    remittance: &Remittance,
+    rules: &WriteOffRules,
+) -> f64 {
+    let payer_paid: f64 = remittance
+        .service_line_remittances()
+        .iter()
+        .map(|line| line.payer_paid_amount())
+        .sum();
+    let not_allowed: f64 = remittance
+        .service_line_remittances()
+        .iter()
+        .map(|line| line.not_allowed_amount())
+        .sum();
+    let patient_share = patient_responsibility(remittance);
+
+    ledger.post(LedgerEntry::new(claim_id, patient_id, payer_id, EntryKind::Charge, charge));
+    ledger.post(LedgerEntry::new(claim_id, patient_id, payer_id, EntryKind::PayerPayment, payer_paid));
+    if rules.contractual_adjustments && not_allowed > 0.0 {
+        ledger.post(LedgerEntry::new(
+            claim_id,
+            patient_id,
+            payer_id,
+            EntryKind::Adjustment(AdjustmentReason::Contractual),
+            not_allowed,
+        ));
+    }
+    if patient_share > 0.0 {
+        ledger.post(LedgerEntry::new(
+            claim_id,
+            patient_id,
+            payer_id,
+            EntryKind::PatientResponsibility,
+            patient_share,
+        ));
+    }
+    if rules.is_small_balance(patient_share) {
+        ledger.post(LedgerEntry::new(
+            claim_id,
+            patient_id,
+            payer_id,
+            EntryKind::Adjustment(AdjustmentReason::SmallBalance),
+            patient_share,
+        ));
+        return 0.0;
+    }
+    patient_share
+}
+
+/// Sum of copay, coinsurance, and deductible across all service lines
+pub fn patient_responsibility(remittance: &Remittance) -> f64 {
+    remittance
+        .service_line_remittances()
+        .iter()
+        .map(|line| line.patient_responsibility())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remittance::ServiceLineRemittance;
+    use crate::test_utils::{mock_claim, mock_remittance};
+
+    /// Test that a patient balance under the threshold is written off instead of billed.
+    /// Expected: Nothing left to bill; a small-balance adjustment is recorded for the payer.
+    #[test]
+    fn test_small_balance_write_off() {
+        let mut ledger = Ledger::new();
+        let rules = WriteOffRules {
+            small_balance_threshold: 100.0,
+            contractual_adjustments: true,
+        };
+        let to_bill = post_remittance(&mut ledger, &mock_claim(), &mock_remittance(), &rules);
+        assert_eq!(to_bill, 0.0);
+        let summary = ledger.write_off_summary();
+        assert_eq!(summary["medicare"][&AdjustmentReason::SmallBalance], 50.0);
+        assert_eq!(ledger.patient_balances()["pmid456"].outstanding(), 0.0);
+    }
+
+    /// Test that the not-allowed amount is posted as a contractual adjustment.
+    /// Expected: Contractual adjustment equals the remittance's not-allowed total.
+    #[test]
+    fn test_contractual_adjustment() {
+        let mut ledger = Ledger::new();
+        let claim = mock_claim();
+        let line = ServiceLineRemittance::new("sl1", 87.5, 30.0, 15.0, 5.0, 12.5).unwrap();
+        let remittance = Remittance::new(&claim, vec![line]).unwrap();
+        let to_bill = post_remittance(&mut ledger, &claim, &remittance, &WriteOffRules::default());
+        assert_eq!(to_bill, 50.0);
+        let summary = ledger.write_off_summary();
+        assert_eq!(summary["medicare"][&AdjustmentReason::Contractual], 12.5);
+        assert!(!summary["medicare"].contains_key(&AdjustmentReason::SmallBalance));
+    }
+}
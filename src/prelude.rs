@@ -0,0 +1,31 @@
+//! What embedding the simulator takes, in one `use healthtechsim::prelude::*;`: the builder and
+//! orchestrator, the extension traits and built-in implementations, claim and message types, and
+//! report rows. Everything else stays reachable through its module
+
+pub use crate::adjudicator::{AdjudicationResult, Adjudicator};
+pub use crate::claim_source::{ClaimGenerator, ClaimSource, DirectoryWatcher, HttpSource, JsonlFile};
+pub use crate::clock::SimClock;
+pub use crate::config::Config;
+pub use crate::event_log::{Event, EventLog, EventRecord};
+pub use crate::history::History;
+pub use crate::ids::{ClaimId, ClaimKey, MemberId, Npi, PayerId};
+pub use crate::inventory::{ClaimInventory, ClaimState, StateInventory};
+pub use crate::invariants::{Invariant, InvariantChecker, Violation};
+pub use crate::kpi::RevenueCycleKpis;
+pub use crate::message::{ClaimEnvelope, ClaimMessage, ClaimMetadata, ClaimStatus, PayerMessage, RemittanceMessage, Stage};
+pub use crate::orchestrator::{RunEnd, SimulationHandles};
+pub use crate::payer_mix::{PayerMix, PayerShare, ReimbursementVariance};
+pub use crate::payer_rules::{PayerRules, RuleBook};
+pub use crate::remittance::{Adjustment, DenialReason, Remittance, RemittanceBuilder, RemittanceRecord, ServiceLineRemittance};
+pub use crate::remittance_sink::{DatabaseSink, FileSink, RemittanceSink, WebhookSink};
+pub use crate::report_engine::{
+    CareSetting, PatientSummary, PayerAging, PayerLag, PayerReconciliation, PayerTurnaround, PriorityLatency, ProcedureSummary,
+    ProviderSummary, RemittanceException, ReportEngine, SlaCompliance, SplitClaim, StageLatency, TelehealthMix,
+    TenantSummary,
+};
+pub use crate::schema::{
+    ClaimFrequency, ClaimType, Gender, Organization, Patient, PayerClaim, PayerClaimBuilder, Provider, ServiceLine,
+};
+pub use crate::simulation::{BillerSpec, PayerSpec, Simulation, SimulationBuilder, SimulationMetrics};
+pub use crate::stop::{StopCondition, Stopped};
+pub use crate::tenant::DEFAULT_TENANT;
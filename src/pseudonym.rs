@@ -0,0 +1,181 @@
+use anyhow::anyhow;
+use chrono::{Days, NaiveDate};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::ids::MemberId;
+use crate::schema::{Address, PayerClaim};
+
+const FIRST_NAMES: [&str; 32] = [
+    "Avery", "Blake", "Cameron", "Dana", "Elliot", "Finley", "Gray", "Harper", "Indigo", "Jordan", "Kai", "Logan",
+    "Morgan", "Noel", "Oakley", "Parker", "Quinn", "Reese", "Sage", "Taylor", "Umber", "Val", "Wren", "Xen", "Yael",
+    "Zion", "Ash", "Briar", "Casey", "Drew", "Emery", "Frankie",
+];
+
+const LAST_NAMES: [&str; 32] = [
+    "Alder", "Birch", "Cedar", "Dune", "Elm", "Fern", "Glen", "Hollow", "Isle", "Juniper", "Kestrel", "Linden",
+    "Maple", "North", "Orchard", "Pine", "Quarry", "Ridge", "Stone", "Thorn", "Upton", "Vale", "Willow", "Yarrow",
+    "Ashby", "Brook", "Cove", "Dell", "Everly", "Field", "Grove", "Heath",
+];
+
+const CITIES: [&str; 16] = [
+    "Fairview", "Riverside", "Franklin", "Greenville", "Springfield", "Clinton", "Madison", "Georgetown", "Salem",
+    "Arlington", "Ashland", "Dover", "Milton", "Newport", "Oxford", "Burlington",
+];
+
+const STREETS: [&str; 8] = ["Main St", "Oak Ave", "Park Rd", "Cedar Ln", "Lake Dr", "Hill St", "Mill Rd", "Elm Ct"];
+
+/// Dates of birth are shifted by up to this many days either way
+const MAX_DOB_SHIFT_DAYS: u64 = 182;
+
+/// Replaces patients and subscribers with synthetic identities derived from a secret key
+///
+/// The same person (by name and date of birth) always gets the same synthetic name, date of
+/// birth, email, and address, and the same member id always gets the same pseudonymous id,
+/// for as long as the key stays the same. Without the key the mapping cannot be reversed or
+/// recomputed, so de-identified output from many runs can be joined without exposing anyone
+#[derive(Clone)]
+pub struct Pseudonymizer {
+    key: Arc<[u8]>,
+}
+
+/// Synthetic demographics standing in for one person
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntheticIdentity {
+    pub first_name: String,
+    pub last_name: String,
+    /// Real date of birth shifted by up to six months, so ages stay about right
+    pub dob: Option<NaiveDate>,
+    pub email: String,
+    digest: [u8; 32],
+}
+
+impl Pseudonymizer {
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        Self { key: key.as_ref().into() }
+    }
+
+    /// Read the key from a file, ignoring surrounding whitespace
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let contents = std::fs::read(path).map_err(|e| anyhow!("Failed to read pseudonym key {}: {}", path, e))?;
+        let key = contents.trim_ascii();
+        if key.is_empty() {
+            return Err(anyhow!("Pseudonym key {} is empty", path));
+        }
+        Ok(Self::new(key))
+    }
+
+    fn digest(&self, domain: &str, value: &str) -> [u8; 32] {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(domain.as_bytes());
+        mac.update(&[0]);
+        mac.update(value.as_bytes());
+        mac.finalize().into_bytes().into()
+    }
+
+    /// Pseudonymous stand-in for a member id
+    pub fn member_id(&self, member_id: &MemberId) -> MemberId {
+        let digest = self.digest("member", member_id.as_str());
+        MemberId::new(format!("PSN{}", hex(&digest[..6])))
+    }
+
+    /// Synthetic identity of the person with this name and date of birth
+    pub fn identity(&self, first_name: &str, last_name: &str, dob: Option<NaiveDate>) -> SyntheticIdentity {
+        let person = format!(
+            "{}|{}|{}",
+            first_name.trim().to_lowercase(),
+            last_name.trim().to_lowercase(),
+            dob.map(|dob| dob.to_string()).unwrap_or_default()
+        );
+        let digest = self.digest("person", &person);
+        let first_name = FIRST_NAMES[digest[0] as usize % FIRST_NAMES.len()].to_string();
+        let last_name = LAST_NAMES[digest[1] as usize % LAST_NAMES.len()].to_string();
+        let shift = u64::from(u16::from_be_bytes([digest[2], digest[3]])) % (2 * MAX_DOB_SHIFT_DAYS + 1);
+        let dob = dob.and_then(|dob| match shift.checked_sub(MAX_DOB_SHIFT_DAYS) {
+            Some(later) => dob.checked_add_days(Days::new(later)),
+            None => dob.checked_sub_days(Days::new(MAX_DOB_SHIFT_DAYS - shift)),
+        });
+        let email = format!(
+            "{}.{}.{}@example.com",
+            first_name.to_lowercase(),
+            last_name.to_lowercase(),
+            hex(&digest[4..6])
+        );
+        SyntheticIdentity { first_name, last_name, dob, email, digest }
+    }
+
+    /// Swap the claim's patient and subscriber for their synthetic identities and their member
+    /// ids for pseudonymous ones
+    pub fn pseudonymize_claim(&self, claim: &mut PayerClaim) {
+        let patient = &mut claim.patient;
+        let identity = self.identity(&patient.first_name, &patient.last_name, patient.dob);
+        if let Some(address) = &mut patient.address {
+            identity.apply_to_address(address);
+        }
+        patient.email = patient.email.as_ref().map(|_| identity.email.clone());
+        patient.dob = identity.dob;
+        patient.first_name = identity.first_name;
+        patient.last_name = identity.last_name;
+        claim.insurance.patient_member_id = self.member_id(&claim.insurance.patient_member_id);
+        if let Some(subscriber) = &mut claim.insurance.subscriber {
+            let identity = self.identity(&subscriber.first_name, &subscriber.last_name, subscriber.dob);
+            subscriber.first_name = identity.first_name;
+            subscriber.last_name = identity.last_name;
+            subscriber.dob = identity.dob;
+            subscriber.member_id = self.member_id(&subscriber.member_id);
+        }
+    }
+}
+
+impl SyntheticIdentity {
+    /// Replace the street and city, keeping the state and the first three digits of the ZIP code
+    fn apply_to_address(&self, address: &mut Address) {
+        let number = u16::from_be_bytes([self.digest[6], self.digest[7]]) % 9900 + 100;
+        let street = STREETS[self.digest[8] as usize % STREETS.len()];
+        address.street = address.street.as_ref().map(|_| format!("{} {}", number, street));
+        address.city = address.city.as_ref().map(|_| CITIES[self.digest[9] as usize % CITIES.len()].to_string());
+        address.zip = address
+            .zip
+            .as_ref()
+            .map(|zip| format!("{}{:02}", zip.chars().take(3).collect::<String>(), self.digest[10] % 100));
+    }
+}
+
+impl fmt::Debug for Pseudonymizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Pseudonymizer")
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::mock_claim;
+
+    /// Test that pseudonyms are stable for a key, differ across keys, and replace the real identity.
+    /// Expected: Two pseudonymizations under one key agree; neither keeps the real name or member id; another key maps elsewhere.
+    #[test]
+    fn test_pseudonymize_claim() {
+        let real = mock_claim();
+        let pseudonymize = |key: &str| {
+            let mut claim = real.clone();
+            Pseudonymizer::new(key).pseudonymize_claim(&mut claim);
+            claim
+        };
+        let (first, second, other) = (pseudonymize("secret"), pseudonymize("secret"), pseudonymize("other"));
+
+        assert_eq!(serde_json::to_value(&first).unwrap(), serde_json::to_value(&second).unwrap());
+        assert_ne!(first.patient.first_name, real.patient.first_name);
+        assert_ne!(first.insurance.patient_member_id, real.insurance.patient_member_id);
+        assert!(first.insurance.patient_member_id.as_str().starts_with("PSN"));
+        assert_ne!(first.insurance.patient_member_id, other.insurance.patient_member_id);
+        let shift = (first.patient.dob.unwrap() - real.patient.dob.unwrap()).num_days().unsigned_abs();
+        assert!(shift <= MAX_DOB_SHIFT_DAYS);
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::sync::mpsc::{self, Receiver, Sender, error::TrySendError};
+
+/// What a bounded queue does with a message that arrives while it is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Wait for room, pushing backpressure onto the sender
+    #[default]
+    Block,
+    /// Make room by discarding the longest-waiting message
+    DropOldest,
+    /// Turn the new message away
+    Reject,
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(OverflowPolicy::Block),
+            "drop-oldest" => Ok(OverflowPolicy::DropOldest),
+            "reject" => Ok(OverflowPolicy::Reject),
+            _ => Err(anyhow::anyhow!("Unknown overflow policy: {} (expected block, drop-oldest, or reject)", s)),
+        }
+    }
+}
+
+impl fmt::Display for OverflowPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OverflowPolicy::Block => write!(f, "block"),
+            OverflowPolicy::DropOldest => write!(f, "drop-oldest"),
+            OverflowPolicy::Reject => write!(f, "reject"),
+        }
+    }
+}
+
+/// A message that can be turned away by a full queue
+pub trait Overflow: Send + 'static {
+    /// What the queue needs to tell whoever is waiting on a message turned away, e.g. the sender it answers on
+    type Context: Send + 'static;
+
+    /// Let whoever is waiting on this message know it will not be delivered
+    fn overflowed(self, policy: OverflowPolicy, context: &Self::Context);
+}
+
+/// Counts of messages a queue turned away, shared with whoever reports on it
+#[derive(Debug, Clone, Default)]
+pub struct QueueStats {
+    dropped: Arc<AtomicU64>,
+    rejected: Arc<AtomicU64>,
+}
+
+impl QueueStats {
+    /// Messages discarded to make room under `OverflowPolicy::DropOldest`
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Messages turned away under `OverflowPolicy::Reject`
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+}
+
+/// Bounded channel holding at most `capacity` waiting messages, applying `policy` when full
+///
+/// `Block` is a plain channel. The other policies put a relay task between the
+/// two ends that never blocks the sender for long, so it must be called from within a Tokio
+/// runtime; messages turned away are handed `context` to answer whoever waits on them
+pub fn bounded<T: Overflow>(capacity: usize, policy: OverflowPolicy, stats: QueueStats, context: T::Context) -> (Sender<T>, Receiver<T>) {
+    let capacity = capacity.max(1);
+    match policy {
+        OverflowPolicy::Block => mpsc::channel(capacity),
+        OverflowPolicy::Reject => {
+            // the outbound channel is the queue; the inbound one only hands over one message at a time
+            let (tx, rx) = mpsc::channel(1);
+            let (out_tx, out_rx) = mpsc::channel(capacity);
+            tokio::spawn(relay_reject(rx, out_tx, stats, context));
+            (tx, out_rx)
+        }
+        OverflowPolicy::DropOldest => {
+            let (tx, rx) = mpsc::channel(capacity);
+            // the relay's buffer is the queue; the outbound channel only hands over one message at a time
+            let (out_tx, out_rx) = mpsc::channel(1);
+            tokio::spawn(relay_drop_oldest(rx, out_tx, capacity, stats, context));
+            (tx, out_rx)
+        }
+    }
+}
+
+async fn relay_reject<T: Overflow>(mut rx: Receiver<T>, out_tx: Sender<T>, stats: QueueStats, context: T::Context) {
+    while let Some(msg) = rx.recv().await {
+        match out_tx.try_send(msg) {
+            Ok(()) => {}
+            Err(TrySendError::Full(msg)) => {
+                stats.rejected.fetch_add(1, Ordering::Relaxed);
+                msg.overflowed(OverflowPolicy::Reject, &context);
+            }
+            Err(TrySendError::Closed(_)) => break,
+        }
+    }
+}
+
+async fn relay_drop_oldest<T: Overflow>(mut rx: Receiver<T>, out_tx: Sender<T>, capacity: usize, stats: QueueStats, context: T::Context) {
+    let mut buffer: VecDeque<T> = VecDeque::with_capacity(capacity);
+    let mut input_open = true;
+    while input_open || !buffer.is_empty() {
+        tokio::select! {
+            msg = rx.recv(), if input_open => match msg {
+                Some(msg) => {
+                    if buffer.len() == capacity
+                        && let Some(oldest) = buffer.pop_front()
+                    {
+                        stats.dropped.fetch_add(1, Ordering::Relaxed);
+                        oldest.overflowed(OverflowPolicy::DropOldest, &context);
+                    }
+                    buffer.push_back(msg);
+                }
+                None => input_open = false,
+            },
+            permit = out_tx.reserve(), if !buffer.is_empty() => match permit {
+                Ok(permit) => {
+                    if let Some(msg) = buffer.pop_front() {
+                        permit.send(msg);
+                    }
+                }
+                Err(_) => break,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Msg(u32, Sender<u32>);
+
+    impl Overflow for Msg {
+        type Context = ();
+
+        fn overflowed(self, _policy: OverflowPolicy, _context: &()) {
+            let _ = self.1.try_send(self.0);
+        }
+    }
+
+    /// Test that a full drop-oldest queue discards its longest-waiting messages.
+    /// Expected: With room for two behind the message already handed over, sending four more drops 1 and 2 and delivers 0, 3, and 4.
+    #[tokio::test(start_paused = true)]
+    async fn test_drop_oldest() {
+        let stats = QueueStats::default();
+        let (overflow_tx, mut overflow_rx) = mpsc::channel(10);
+        let (tx, mut rx) = bounded(2, OverflowPolicy::DropOldest, stats.clone(), ());
+        // the first message is handed straight to the outbound slot
+        tx.send(Msg(0, overflow_tx.clone())).await.unwrap();
+        settle().await;
+        for i in 1..=4 {
+            tx.send(Msg(i, overflow_tx.clone())).await.unwrap();
+        }
+        settle().await;
+
+        assert_eq!(stats.dropped(), 2);
+        assert_eq!(overflow_rx.recv().await, Some(1));
+        assert_eq!(overflow_rx.recv().await, Some(2));
+        drop(tx);
+        let mut delivered = Vec::new();
+        while let Some(Msg(i, _)) = rx.recv().await {
+            delivered.push(i);
+        }
+        assert_eq!(delivered, vec![0, 3, 4]);
+    }
+
+    /// Test that a full reject queue turns new messages away once it holds its capacity.
+    /// Expected: With room for two and nobody receiving, the third and fourth messages are rejected and the first two are delivered.
+    #[tokio::test(start_paused = true)]
+    async fn test_reject() {
+        let stats = QueueStats::default();
+        let (overflow_tx, mut overflow_rx) = mpsc::channel(10);
+        let (tx, mut rx) = bounded(2, OverflowPolicy::Reject, stats.clone(), ());
+        for i in 1..=4 {
+            tx.send(Msg(i, overflow_tx.clone())).await.unwrap();
+        }
+        settle().await;
+
+        assert_eq!(stats.rejected(), 2);
+        assert_eq!(overflow_rx.recv().await, Some(3));
+        assert_eq!(overflow_rx.recv().await, Some(4));
+        drop(tx);
+        let mut delivered = Vec::new();
+        while let Some(Msg(i, _)) = rx.recv().await {
+            delivered.push(i);
+        }
+        assert_eq!(delivered, vec![1, 2]);
+    }
+
+    /// Test that a turned-away message is handed the queue's context, as payer inboxes answer the clearinghouse through it.
+    /// Expected: The rejected message's id arrives on the context channel given to `bounded`.
+    #[tokio::test(start_paused = true)]
+    async fn test_overflow_context() {
+        #[derive(Debug)]
+        struct Routed(u32);
+
+        impl Overflow for Routed {
+            type Context = Sender<u32>;
+
+            fn overflowed(self, _policy: OverflowPolicy, context: &Sender<u32>) {
+                let _ = context.try_send(self.0);
+            }
+        }
+
+        let (context_tx, mut context_rx) = mpsc::channel(10);
+        let (tx, _rx) = bounded(1, OverflowPolicy::Reject, QueueStats::default(), context_tx);
+        tx.send(Routed(1)).await.unwrap();
+        tx.send(Routed(2)).await.unwrap();
+        settle().await;
+
+        assert_eq!(context_rx.recv().await, Some(2));
+    }
+
+    /// Let the relay task catch up; with time paused the sleep advances the clock rather than waiting
+    async fn settle() {
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+}
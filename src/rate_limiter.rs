@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Mutex;
+use tokio::time::{Duration, sleep};
+
+/// Token bucket settings: sustained claims per second and burst size
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateSettings {
+    pub claims_per_sec: f64,
+    pub burst: u32,
+}
+
+impl RateSettings {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !(self.claims_per_sec.is_finite() && self.claims_per_sec > 0.0) {
+            return Err(anyhow::anyhow!("claims_per_sec must be a positive number"));
+        }
+        if self.burst == 0 {
+            return Err(anyhow::anyhow!("burst must be at least 1"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    settings: RateSettings,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.settings.claims_per_sec).min(self.settings.burst as f64);
+        self.last_refill = now;
+    }
+}
+
+/// Token bucket limiter shaping claim ingestion
+///
+/// Settings can be changed at runtime (e.g. from the HTTP API) while the reader is running
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+impl RateLimiter {
+    /// Create a limiter that starts with a full bucket
+    pub fn new(settings: RateSettings) -> anyhow::Result<Self> {
+        settings.validate()?;
+        Ok(Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                settings,
+                tokens: settings.burst as f64,
+                last_refill: Instant::now(),
+            })),
+        })
+    }
+
+    /// Wait until a token is available and take it
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().await;
+                bucket.refill();
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                (1.0 - bucket.tokens) / bucket.settings.claims_per_sec
+            };
+            sleep(Duration::from_secs_f64(wait)).await;
+        }
+    }
+
+    pub async fn settings(&self) -> RateSettings {
+        self.bucket.lock().await.settings
+    }
+
+    /// Change rate and burst; tokens above the new burst size are discarded
+    pub async fn set_settings(&self, settings: RateSettings) -> anyhow::Result<()> {
+        settings.validate()?;
+        let mut bucket = self.bucket.lock().await;
+        bucket.refill();
+        bucket.settings = settings;
+        bucket.tokens = bucket.tokens.min(settings.burst as f64);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that a full bucket allows a burst, then throttles to the configured rate.
+    /// Expected: Burst of 3 is immediate; the 4th token waits roughly 1/rate seconds.
+    #[tokio::test]
+    async fn test_token_bucket_burst_then_rate() {
+        let limiter = RateLimiter::new(RateSettings { claims_per_sec: 10.0, burst: 3 }).unwrap();
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire().await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(50));
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    /// Test that settings can be changed at runtime and invalid settings are rejected.
+    /// Expected: New settings are visible; zero rate or burst returns an error.
+    #[tokio::test]
+    async fn test_set_settings() {
+        let limiter = RateLimiter::new(RateSettings { claims_per_sec: 1.0, burst: 1 }).unwrap();
+        let new_settings = RateSettings { claims_per_sec: 50.0, burst: 5 };
+        limiter.set_settings(new_settings).await.unwrap();
+        assert_eq!(limiter.settings().await, new_settings);
+        assert!(limiter.set_settings(RateSettings { claims_per_sec: 0.0, burst: 5 }).await.is_err());
+        assert!(limiter.set_settings(RateSettings { claims_per_sec: 1.0, burst: 0 }).await.is_err());
+    }
+}
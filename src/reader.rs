@@ -1,19 +1,193 @@
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::Arc;
 use tokio::fs::File;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::sync::mpsc::Sender;
+use tokio::sync::watch;
 
+use crate::ids::ClaimId;
 use crate::logging::log_claim_event;
+use crate::phi::Deidentification;
+use crate::rate_limiter::RateLimiter;
+use crate::rejects::{LineError, Reject, RejectWriter, parse_claim_line, parse_claim_line_lenient};
 use crate::schema::PayerClaim;
+use crate::validation::{self, AllowedCurrencies, Strictness};
+
+/// Reader progress persisted between runs
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Checkpoint {
+    pub file_path: String,
+    pub lines_processed: u64,
+}
+
+/// Lines a reader has handed to its biller whose claims the biller has not yet submitted
+///
+/// The reader records the line each claim was read from; the biller acknowledges a claim by
+/// its id once submitted, which settles that line whatever order acknowledgments come in
+#[derive(Debug, Clone)]
+pub struct ProcessedClaims {
+    pending: Arc<watch::Sender<PendingLines>>,
+}
+
+#[derive(Debug, Default)]
+struct PendingLines {
+    /// Lines of each claim id not yet acknowledged, oldest first
+    by_claim: HashMap<ClaimId, VecDeque<u64>>,
+    lines: BTreeSet<u64>,
+}
+
+impl Default for ProcessedClaims {
+    fn default() -> Self {
+        Self { pending: Arc::new(watch::channel(PendingLines::default()).0) }
+    }
+}
+
+impl ProcessedClaims {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the claim `claim_id` as submitted
+    ///
+    /// A claim id read from several lines settles the oldest of them still pending
+    pub fn ack(&self, claim_id: &ClaimId) {
+        self.pending.send_if_modified(|pending| {
+            let Some(lines) = pending.by_claim.get_mut(claim_id) else {
+                return false;
+            };
+            let line = lines.pop_front();
+            if lines.is_empty() {
+                pending.by_claim.remove(claim_id);
+            }
+            line.is_some_and(|line| pending.lines.remove(&line))
+        });
+    }
+
+    fn handed_off(&self, claim_id: &ClaimId, line: u64) {
+        self.pending.send_modify(|pending| {
+            pending.by_claim.entry(claim_id.clone()).or_default().push_back(line);
+            pending.lines.insert(line);
+        });
+    }
+
+    /// Lines fully processed of the `read` lines read so far: every line before the oldest pending one
+    fn processed_through(&self, read: u64) -> u64 {
+        self.pending.borrow().lines.first().map_or(read, |line| line - 1)
+    }
+
+    fn is_settled(&self) -> bool {
+        self.pending.borrow().lines.is_empty()
+    }
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression format of a claims file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Detect compression from the file extension, falling back to magic bytes
+    pub fn detect(path: &str, header: &[u8]) -> Self {
+        if path.ends_with(".gz") || header.starts_with(&GZIP_MAGIC) {
+            Compression::Gzip
+        } else if path.ends_with(".zst") || header.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Path that selects stdin instead of a file, for `generate | simulate -` pipelines
+pub const STDIN_PATH: &str = "-";
+
+/// Open a claims file as a buffered line source, transparently decoding `.gz`/`.zst`
+/// 
+/// A path of `-` reads from stdin; compression is then detected from magic bytes only
+pub(crate) async fn open_claims_file(path: &str) -> anyhow::Result<Box<dyn AsyncBufRead + Unpin + Send>> {
+    if path == STDIN_PATH {
+        return decode(path, BufReader::new(tokio::io::stdin())).await;
+    }
+    decode(path, BufReader::new(File::open(path).await?)).await
+}
+
+async fn decode<R>(path: &str, mut reader: BufReader<R>) -> anyhow::Result<Box<dyn AsyncBufRead + Unpin + Send>>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    let header = reader.fill_buf().await?;
+    let source: Box<dyn AsyncBufRead + Unpin + Send> = match Compression::detect(path, header) {
+        Compression::Gzip => {
+            // concatenated members, as appending to or joining rotated dumps produces, read as one file
+            let mut decoder = GzipDecoder::new(reader);
+            decoder.multiple_members(true);
+            Box::new(BufReader::new(decoder))
+        }
+        Compression::Zstd => Box::new(BufReader::new(ZstdDecoder::new(reader))),
+        Compression::None => Box::new(reader),
+    };
+    Ok(source)
+}
 
 /// Stream claims from a JSONL file and send them to the biller
 /// 
 /// Reads claims line by line, parses JSON, and forwards valid claims
 /// Skips invalid JSON lines and continues processing
+/// Gzip and zstd compressed files are decoded on the fly
 pub async fn stream_claims(
     path: &str,
     tx: Sender<PayerClaim>,
     verbose: bool,
 ) -> anyhow::Result<()> {
+    stream_claims_with_options(path, &ReaderOptions::default(), tx, verbose).await
+}
+
+/// Optional reader behaviors
+#[derive(Debug, Clone, Default)]
+pub struct ReaderOptions {
+    /// Checkpoint file recording processed lines so a restart resumes the input
+    pub checkpoint_path: Option<String>,
+    /// Sidecar JSONL file receiving invalid lines with their rejection reasons
+    pub rejects_path: Option<String>,
+    /// Token bucket shaping how fast claims are handed to the biller
+    pub rate_limiter: Option<RateLimiter>,
+    /// How claims with invalid NPIs or EINs are treated
+    pub validation: Strictness,
+    /// Currencies claims may be billed in; claims in any other are rejected
+    pub currencies: AllowedCurrencies,
+    /// De-identify patient demographics in rejected lines and in warning and rejection messages
+    pub deidentification: Deidentification,
+    /// Acknowledgments of the biller reading this input; the checkpoint then only advances past
+    /// lines whose claims were submitted, rather than as each claim is handed off
+    pub processed: Option<ProcessedClaims>,
+}
+
+/// Stream claims with checkpointing and reject capture
+/// 
+/// Lines already recorded in the checkpoint are skipped, so a crashed or
+/// restarted simulation resumes where it left off instead of re-sending claims
+/// The checkpoint is updated after each line is handed to the biller, or with `processed`,
+/// only past lines whose claims the biller has acknowledged submitting
+/// Invalid lines are reported with file, line number, and JSON pointer,
+/// and appended to the rejects sidecar when one is configured
+/// With a rate limiter, each valid claim waits for a token before being sent
+pub async fn stream_claims_with_options(
+    path: &str,
+    options: &ReaderOptions,
+    tx: Sender<PayerClaim>,
+    verbose: bool,
+) -> anyhow::Result<()> {
+    let checkpoint_path = options.checkpoint_path.as_deref();
+    let processed = options.processed.as_ref();
+    let mut rejects = options.rejects_path.as_deref().map(RejectWriter::new);
     if verbose {
         log_claim_event(
             "reader",
@@ -22,11 +196,49 @@ pub async fn stream_claims(
             &format!("Starting claim stream from file: {}", path),
         );
     }
-    let file = File::open(path).await?;
-    let reader = BufReader::new(file);
-    let mut lines = reader.lines();
+    let skip = match checkpoint_path {
+        Some(checkpoint_path) => load_checkpoint(checkpoint_path, path).await,
+        None => 0,
+    };
+    if skip > 0 && verbose {
+        log_claim_event(
+            "reader",
+            "-",
+            "resume",
+            &format!("Resuming {} after {} processed lines", path, skip),
+        );
+    }
+    let mut lines = open_claims_file(path).await?.lines();
+    let mut line_number = 0;
+    let mut saved = skip;
+    let mut acks = processed.map(|processed| processed.pending.subscribe());
     while let Some(line) = lines.next_line().await? {
-        process_line(&line, &tx, verbose).await?;
+        line_number += 1;
+        if line_number <= skip {
+            continue;
+        }
+        process_line(&line, path, line_number, &tx, rejects.as_mut(), options, verbose).await?;
+        let Some(checkpoint_path) = checkpoint_path else {
+            continue;
+        };
+        let through = processed.map_or(line_number, |processed| processed.processed_through(line_number));
+        if through > saved {
+            save_checkpoint(checkpoint_path, path, through).await?;
+            saved = through;
+        }
+    }
+    // the last claims are checkpointed once submitted; any the biller stops before taking are read again on resume
+    if let (Some(checkpoint_path), Some(processed), Some(acks)) = (checkpoint_path, processed, &mut acks) {
+        while !processed.is_settled() {
+            tokio::select! {
+                changed = acks.changed() => if changed.is_err() { break },
+                _ = tx.closed() => break,
+            }
+        }
+        let through = processed.processed_through(line_number);
+        if through > saved {
+            save_checkpoint(checkpoint_path, path, through).await?;
+        }
     }
     if verbose {
         log_claim_event(
@@ -39,16 +251,132 @@ pub async fn stream_claims(
     Ok(())
 }
 
-async fn process_line(line: &str, tx: &Sender<PayerClaim>, verbose: bool) -> anyhow::Result<()> {
-    match serde_json::from_str::<PayerClaim>(line) {
+/// Number of lines already processed for `file_path`, or 0 if there is no usable checkpoint
+pub async fn load_checkpoint(checkpoint_path: &str, file_path: &str) -> u64 {
+    let Ok(contents) = tokio::fs::read_to_string(checkpoint_path).await else {
+        return 0;
+    };
+    match serde_json::from_str::<Checkpoint>(&contents) {
+        Ok(checkpoint) if checkpoint.file_path == file_path => checkpoint.lines_processed,
+        Ok(checkpoint) => {
+            eprintln!(
+                "Checkpoint {} is for {}, not {}; starting from the beginning",
+                checkpoint_path, checkpoint.file_path, file_path
+            );
+            0
+        }
+        Err(err) => {
+            eprintln!("Invalid checkpoint {} ignored: {}", checkpoint_path, err);
+            0
+        }
+    }
+}
+
+/// Write the checkpoint atomically so a crash mid-write never corrupts it
+async fn save_checkpoint(checkpoint_path: &str, file_path: &str, lines_processed: u64) -> anyhow::Result<()> {
+    let checkpoint = Checkpoint {
+        file_path: file_path.to_string(),
+        lines_processed,
+    };
+    let tmp_path = format!("{}.tmp", checkpoint_path);
+    tokio::fs::write(&tmp_path, serde_json::to_string(&checkpoint)?).await?;
+    tokio::fs::rename(&tmp_path, checkpoint_path).await?;
+    Ok(())
+}
+
+async fn process_line(
+    line: &str,
+    path: &str,
+    line_number: u64,
+    tx: &Sender<PayerClaim>,
+    rejects: Option<&mut RejectWriter>,
+    options: &ReaderOptions,
+    verbose: bool,
+) -> anyhow::Result<()> {
+    match check_claim(line, options, path, line_number) {
         Ok(claim) => {
+            if let Some(rate_limiter) = &options.rate_limiter {
+                rate_limiter.acquire().await;
+            }
+            if let Some(processed) = &options.processed {
+                processed.handed_off(&claim.claim_id, line_number);
+            }
             send_claim(claim, tx, verbose).await?;
         }
-        Err(err) => eprintln!("Invalid claim skipped: {}", err),
+        Err(err) => {
+            let pointer = if err.pointer.is_empty() { "<document>" } else { &err.pointer };
+            eprintln!("Invalid claim skipped: {}:{}: {}: {}", path, line_number, pointer, err.reason);
+            if let Some(rejects) = rejects {
+                let reject = Reject {
+                    file: path.to_string(),
+                    line: line_number,
+                    pointer: err.pointer,
+                    reason: err.reason,
+                    raw: options.deidentification.line(line),
+                };
+                if let Err(e) = rejects.write(&reject).await {
+                    eprintln!("Failed to write reject for {}:{}: {}", path, line_number, e);
+                }
+            }
+        }
     }
     Ok(())
 }
 
+/// Parse one claim and apply every check the reader applies to a line of a claims file
+///
+/// `source` and `number` locate the claim in messages, e.g. a file and line number
+pub(crate) fn check_claim(line: &str, options: &ReaderOptions, source: &str, number: u64) -> Result<PayerClaim, LineError> {
+    parse(line, options, source, number)
+        .and_then(|claim| validation::check_currency(claim, &options.currencies))
+        .and_then(check_claim_total)
+        .and_then(|claim| check_identifiers(claim, options, source, number))
+        .map_err(|err| options.deidentification.line_error(err))
+}
+
+/// Parse a claim line, repairing dirty patient demographics unless validation is strict
+///
+/// Repairs are printed under `Warn`
+fn parse(line: &str, options: &ReaderOptions, path: &str, line_number: u64) -> Result<PayerClaim, LineError> {
+    if options.validation == Strictness::Strict {
+        return parse_claim_line(line);
+    }
+    let (claim, repairs) = parse_claim_line_lenient(line)?;
+    if options.validation == Strictness::Warn {
+        for repair in repairs.into_iter().map(|repair| options.deidentification.line_error(repair)) {
+            eprintln!("Claim {} at {}:{}: {}: {} (repaired)", claim.claim_id, path, line_number, repair.pointer, repair.reason);
+        }
+    }
+    Ok(claim)
+}
+
+/// Reject claims whose header total disagrees with their line charges
+fn check_claim_total(claim: PayerClaim) -> Result<PayerClaim, LineError> {
+    match validation::validate_claim_total(&claim) {
+        Some(err) => Err(err),
+        None => Ok(claim),
+    }
+}
+
+/// Apply NPI and EIN validation at the configured strictness
+///
+/// Warnings are printed and the claim passes; under `Strict` the first failure rejects it
+fn check_identifiers(claim: PayerClaim, options: &ReaderOptions, path: &str, line_number: u64) -> Result<PayerClaim, LineError> {
+    if options.validation == Strictness::Off {
+        return Ok(claim);
+    }
+    let mut errors = validation::validate_claim(&claim).into_iter();
+    if options.validation == Strictness::Strict
+        && let Some(err) = errors.next()
+    {
+        return Err(err);
+    }
+    for err in errors.map(|err| options.deidentification.line_error(err)) {
+        eprintln!("Claim {} at {}:{}: {}: {}", claim.claim_id, path, line_number, err.pointer, err.reason);
+    }
+    Ok(claim)
+}
+
 async fn send_claim(claim: PayerClaim, tx: &Sender<PayerClaim>, verbose: bool) -> anyhow::Result<()> {
     if verbose {
         log_claim_event(
@@ -67,7 +395,7 @@ async fn send_claim(claim: PayerClaim, tx: &Sender<PayerClaim>, verbose: bool) -
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::schema::mock_claim;
+    use crate::test_utils::mock_claim;
     use std::io::Write;
     use tempfile::NamedTempFile;
 
@@ -121,4 +449,163 @@ mod tests {
         let result = stream_claims(path, tx, false).await;
         assert!(result.is_ok());
     }
+
+    /// Test that a checkpoint skips already-processed lines and is advanced afterwards.
+    /// Expected: Only the claim after the checkpoint is sent; checkpoint records all lines.
+    #[tokio::test]
+    async fn test_stream_claims_resumes_from_checkpoint() {
+        let mut tmpfile = NamedTempFile::new().unwrap();
+        for id in ["c1", "c2", "c3"] {
+            let mut claim = mock_claim();
+            claim.claim_id = id.into();
+            writeln!(tmpfile, "{}", serde_json::to_string(&claim).unwrap()).unwrap();
+        }
+        let path = tmpfile.path().to_str().unwrap();
+        let checkpoint_file = NamedTempFile::new().unwrap();
+        let checkpoint_path = checkpoint_file.path().to_str().unwrap();
+        save_checkpoint(checkpoint_path, path, 2).await.unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(3);
+        let options = ReaderOptions {
+            checkpoint_path: Some(checkpoint_path.to_string()),
+            ..ReaderOptions::default()
+        };
+        let result = stream_claims_with_options(path, &options, tx, false).await;
+        assert!(result.is_ok());
+        assert_eq!(rx.recv().await.unwrap().claim_id, "c3");
+        assert!(rx.try_recv().is_err());
+        assert_eq!(load_checkpoint(checkpoint_path, path).await, 3);
+    }
+
+    /// Test that with acknowledgments the checkpoint only covers lines whose claims were submitted.
+    /// Expected: Of three claims sent, the third is acknowledged before the first and the second never is,
+    /// so the checkpoint stops at line 1 once the biller goes away.
+    #[tokio::test]
+    async fn test_stream_claims_checkpoints_acknowledged_lines() {
+        let mut tmpfile = NamedTempFile::new().unwrap();
+        for id in ["c1", "c2", "c3"] {
+            let mut claim = mock_claim();
+            claim.claim_id = id.into();
+            writeln!(tmpfile, "{}", serde_json::to_string(&claim).unwrap()).unwrap();
+        }
+        let path = tmpfile.path().to_str().unwrap().to_string();
+        let checkpoint_file = NamedTempFile::new().unwrap();
+        let checkpoint_path = checkpoint_file.path().to_str().unwrap().to_string();
+        let processed = ProcessedClaims::new();
+        let options = ReaderOptions {
+            checkpoint_path: Some(checkpoint_path.clone()),
+            processed: Some(processed.clone()),
+            ..ReaderOptions::default()
+        };
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(3);
+        let reader = tokio::spawn({
+            let path = path.clone();
+            async move { stream_claims_with_options(&path, &options, tx, false).await }
+        });
+        for id in ["c1", "c2", "c3"] {
+            assert_eq!(rx.recv().await.unwrap().claim_id, id);
+        }
+        processed.ack(&"c3".into());
+        processed.ack(&"c1".into());
+        drop(rx);
+        reader.await.unwrap().unwrap();
+        assert_eq!(load_checkpoint(&checkpoint_path, &path).await, 1);
+    }
+
+    /// Test that gzip and zstd compressed files are streamed like plain JSONL.
+    /// Expected: The claim is decoded and sent regardless of compression.
+    #[tokio::test]
+    async fn test_stream_claims_compressed() {
+        use async_compression::tokio::write::{GzipEncoder, ZstdEncoder};
+        use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+        async fn write_compressed<W: AsyncWrite + Unpin>(mut encoder: W, data: &[u8]) {
+            encoder.write_all(data).await.unwrap();
+            encoder.shutdown().await.unwrap();
+        }
+
+        let claim = mock_claim();
+        let line = format!("{}\n", serde_json::to_string(&claim).unwrap());
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["claims.jsonl.gz", "claims.jsonl.zst"] {
+            let path = dir.path().join(name);
+            let file = tokio::fs::File::create(&path).await.unwrap();
+            if name.ends_with(".gz") {
+                write_compressed(GzipEncoder::new(file), line.as_bytes()).await;
+            } else {
+                write_compressed(ZstdEncoder::new(file), line.as_bytes()).await;
+            }
+            let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+            let result = stream_claims(path.to_str().unwrap(), tx, false).await;
+            assert!(result.is_ok(), "{} failed: {:?}", name, result);
+            assert_eq!(rx.recv().await.unwrap().claim_id, claim.claim_id);
+        }
+    }
+
+    /// Test that a gzip file of several concatenated members is read to its end.
+    /// Expected: The claims of both members are sent.
+    #[tokio::test]
+    async fn test_stream_claims_concatenated_gzip() {
+        use async_compression::tokio::write::GzipEncoder;
+        use tokio::io::AsyncWriteExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("claims.jsonl.gz");
+        let mut contents = Vec::new();
+        for id in ["c1", "c2"] {
+            let mut claim = mock_claim();
+            claim.claim_id = id.into();
+            let mut encoder = GzipEncoder::new(Vec::new());
+            encoder.write_all(format!("{}\n", serde_json::to_string(&claim).unwrap()).as_bytes()).await.unwrap();
+            encoder.shutdown().await.unwrap();
+            contents.extend(encoder.into_inner());
+        }
+        std::fs::write(&path, contents).unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(2);
+        stream_claims(path.to_str().unwrap(), tx, false).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap().claim_id, "c1");
+        assert_eq!(rx.recv().await.unwrap().claim_id, "c2");
+    }
+
+    /// Test that compression is detected from magic bytes when the extension is missing.
+    /// Expected: Gzip and zstd headers are recognized; plain JSON is not.
+    #[test]
+    fn test_compression_detect_magic_bytes() {
+        assert_eq!(Compression::detect("claims", &[0x1f, 0x8b, 0x08]), Compression::Gzip);
+        assert_eq!(Compression::detect("claims", &[0x28, 0xb5, 0x2f, 0xfd]), Compression::Zstd);
+        assert_eq!(Compression::detect("claims.jsonl", b"{\"claim_id\""), Compression::None);
+    }
+
+    /// Test that invalid lines are written to the rejects sidecar with line numbers and reasons.
+    /// Expected: Two rejects (lines 1 and 3); the valid claim on line 2 is still sent.
+    #[tokio::test]
+    async fn test_stream_claims_writes_rejects() {
+        let mut tmpfile = NamedTempFile::new().unwrap();
+        let claim = mock_claim();
+        let mut bad_units = serde_json::to_value(&claim).unwrap();
+        bad_units["service_lines"][0]["units"] = serde_json::json!(-1);
+        writeln!(tmpfile, "not a json").unwrap();
+        writeln!(tmpfile, "{}", serde_json::to_string(&claim).unwrap()).unwrap();
+        writeln!(tmpfile, "{}", bad_units).unwrap();
+        let path = tmpfile.path().to_str().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let rejects_path = dir.path().join("rejects.jsonl");
+        let options = ReaderOptions {
+            rejects_path: Some(rejects_path.to_str().unwrap().to_string()),
+            ..ReaderOptions::default()
+        };
+        let (tx, mut rx) = tokio::sync::mpsc::channel(3);
+        stream_claims_with_options(path, &options, tx, false).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap().claim_id, claim.claim_id);
+
+        let contents = std::fs::read_to_string(&rejects_path).unwrap();
+        let rejects: Vec<Reject> = contents.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(rejects.len(), 2);
+        assert_eq!(rejects[0].line, 1);
+        assert_eq!(rejects[0].raw, "not a json");
+        assert_eq!(rejects[1].line, 3);
+        assert_eq!(rejects[1].pointer, "/service_lines/0/units");
+    }
 }
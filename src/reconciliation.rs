@@ -0,0 +1,193 @@
+use chrono::{Days, NaiveDate};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::sleep_until;
+
+use crate::clock::SimClock;
+use crate::deposits::{Deposit, DepositFeed, PaymentMethod};
+use crate::history::History;
+use crate::ids::{ClaimId, PayerId};
+use crate::ledger::{EntryKind, Ledger};
+use crate::report_format::ReportFormat;
+use crate::reporter;
+
+/// Largest difference between deposited and posted dollars treated as rounding
+const MATCH_TOLERANCE: f64 = 1e-2;
+
+/// A deposit the biller's postings do not fully account for
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnmatchedDeposit {
+    pub trace_number: String,
+    pub payer_id: PayerId,
+    pub method: PaymentMethod,
+    pub deposited_on: NaiveDate,
+    pub amount: f64,
+    /// Claims on the deposit whose payments are not posted as deposited
+    pub unposted_claims: usize,
+}
+
+/// A claim the payer paid into the bank that the ledger does not show as paid
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnpostedRemittance {
+    pub claim_id: ClaimId,
+    pub payer_id: PayerId,
+    /// Latest deposit paying the claim
+    pub trace_number: String,
+    pub deposited: f64,
+    pub posted: f64,
+}
+
+/// One billing organization's bank deposits matched against its posted payer payments
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BankReconciliation {
+    pub deposits: usize,
+    pub deposited: f64,
+    pub matched: usize,
+    pub unmatched_deposits: Vec<UnmatchedDeposit>,
+    pub unposted_remittances: Vec<UnpostedRemittance>,
+    /// Payer payments posted but not yet in the bank, such as checks still clearing
+    pub undeposited: f64,
+}
+
+impl BankReconciliation {
+    pub fn is_reconciled(&self) -> bool {
+        self.unmatched_deposits.is_empty() && self.unposted_remittances.is_empty()
+    }
+}
+
+/// Match deposits to the payer payments posted in a ledger, claim by claim
+///
+/// A deposit matches when every claim it pays shows the same payer payment in the ledger
+/// as the deposits have paid on it in total; recoupments count against the claim
+pub fn reconcile<'a>(deposits: impl IntoIterator<Item = &'a Deposit>, ledger: &Ledger) -> BankReconciliation {
+    let mut posted: HashMap<&ClaimId, f64> = HashMap::new();
+    for entry in ledger.entries() {
+        if entry.kind == EntryKind::PayerPayment {
+            *posted.entry(&entry.claim_id).or_default() += entry.amount;
+        }
+    }
+    let deposits: Vec<&Deposit> = deposits.into_iter().collect();
+    let mut deposited: BTreeMap<&ClaimId, (f64, &Deposit)> = BTreeMap::new();
+    for deposit in &deposits {
+        for line in &deposit.lines {
+            let paid = deposited.entry(&line.claim_id).or_insert((0.0, deposit));
+            *paid = (paid.0 + line.amount, deposit);
+        }
+    }
+    let is_posted = |claim_id: &ClaimId| {
+        let paid = deposited.get(claim_id).map_or(0.0, |(paid, _)| *paid);
+        (paid - posted.get(claim_id).copied().unwrap_or_default()).abs() <= MATCH_TOLERANCE
+    };
+
+    let mut report = BankReconciliation {
+        deposits: deposits.len(),
+        deposited: deposits.iter().map(|deposit| deposit.amount()).sum(),
+        ..BankReconciliation::default()
+    };
+    for deposit in &deposits {
+        let unposted_claims = deposit.lines.iter().filter(|line| !is_posted(&line.claim_id)).count();
+        if unposted_claims == 0 {
+            report.matched += 1;
+            continue;
+        }
+        report.unmatched_deposits.push(UnmatchedDeposit {
+            trace_number: deposit.trace_number.clone(),
+            payer_id: deposit.payer_id.clone(),
+            method: deposit.method,
+            deposited_on: deposit.deposited_on,
+            amount: deposit.amount(),
+            unposted_claims,
+        });
+    }
+    for (claim_id, (paid, deposit)) in &deposited {
+        let posted = posted.get(claim_id).copied().unwrap_or_default();
+        if *paid - posted > MATCH_TOLERANCE {
+            report.unposted_remittances.push(UnpostedRemittance {
+                claim_id: (*claim_id).clone(),
+                payer_id: deposit.payer_id.clone(),
+                trace_number: deposit.trace_number.clone(),
+                deposited: *paid,
+                posted,
+            });
+        }
+    }
+    report.undeposited = posted
+        .iter()
+        .map(|(claim_id, posted)| posted - deposited.get(claim_id).map_or(0.0, |(paid, _)| *paid))
+        .filter(|gap| *gap > MATCH_TOLERANCE)
+        .sum();
+    report
+}
+
+/// Run the payers' payment cycles on the virtual clock, reconciling each billing organization's
+/// bank deposits against its ledger after every run and printing the result
+pub async fn run_bank_reconciliation(
+    mut feed: DepositFeed,
+    history: History,
+    ledgers: BTreeMap<String, Arc<Mutex<Ledger>>>,
+    clock: SimClock,
+    format: ReportFormat,
+    verbose: bool,
+) {
+    if verbose {
+        println!("[reconciliation] Starting bank deposit reconciliation");
+    }
+    let cycle = Days::new(feed.settings().cycle_days.into());
+    let mut run_on = clock.now().date() + cycle;
+    loop {
+        sleep_until(clock.instant_of(run_on.and_hms_opt(0, 0, 0).expect("midnight is a valid time")).into()).await;
+        feed.issue(&history.snapshot().await, run_on, &mut rand::rng());
+        for (tenant_id, ledger) in &ledgers {
+            let report = reconcile(feed.deposited_by(tenant_id, run_on), &*ledger.lock().await);
+            if report.deposits > 0 {
+                reporter::print_bank_reconciliation_report(tenant_id, run_on, &report, &format);
+            }
+        }
+        run_on = run_on + cycle;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deposits::DepositLine;
+    use crate::ledger::LedgerEntry;
+
+    /// Test that deposits are matched to posted payments and gaps are reported from both sides.
+    /// Expected: The fully posted deposit matches; the other is unmatched with one unposted claim; a posted check not yet deposited is undeposited.
+    #[test]
+    fn test_reconcile_deposits() {
+        let day = NaiveDate::from_ymd_opt(2025, 3, 3).unwrap();
+        let deposit = |trace: &str, lines: &[(&str, f64)]| Deposit {
+            trace_number: trace.to_string(),
+            tenant_id: "default".to_string(),
+            payer_id: "medicare".into(),
+            method: PaymentMethod::Eft,
+            issued_on: day,
+            deposited_on: day,
+            lines: lines
+                .iter()
+                .map(|(claim_id, amount)| DepositLine { claim_id: (*claim_id).into(), amount: *amount })
+                .collect(),
+        };
+        let deposits = [deposit("medicare-000001", &[("c1", 100.0)]), deposit("medicare-000002", &[("c2", 50.0), ("c3", 25.0)])];
+        let mut ledger = Ledger::new();
+        ledger.post(LedgerEntry::new("c1", "p1", "medicare", EntryKind::PayerPayment, 100.0));
+        ledger.post(LedgerEntry::new("c2", "p1", "medicare", EntryKind::PayerPayment, 50.0));
+        ledger.post(LedgerEntry::new("c4", "p1", "medicare", EntryKind::PayerPayment, 40.0));
+
+        let report = reconcile(&deposits, &ledger);
+        assert_eq!(report.deposits, 2);
+        assert_eq!(report.deposited, 175.0);
+        assert_eq!(report.matched, 1);
+        assert_eq!(report.unmatched_deposits.len(), 1);
+        assert_eq!(report.unmatched_deposits[0].trace_number, "medicare-000002");
+        assert_eq!(report.unmatched_deposits[0].unposted_claims, 1);
+        let [unposted] = report.unposted_remittances.as_slice() else { panic!("Expected one unposted remittance") };
+        assert_eq!(unposted.claim_id, ClaimId::new("c3"));
+        assert_eq!(unposted.deposited, 25.0);
+        assert_eq!(report.undeposited, 40.0);
+        assert!(!report.is_reconciled());
+    }
+}
@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+
+use crate::schema::PayerClaim;
+
+/// Input line that failed validation, written to the rejects sidecar for reprocessing
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Reject {
+    pub file: String,
+    pub line: u64,
+    /// JSON pointer (RFC 6901) of the failing field; empty for the whole document
+    pub pointer: String,
+    pub reason: String,
+    pub raw: String,
+}
+
+/// Why a line could not be turned into a claim
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineError {
+    pub pointer: String,
+    pub reason: String,
+}
+
+/// Parse one JSONL line into a claim, reporting the JSON pointer of the failing field
+pub fn parse_claim_line(line: &str) -> Result<PayerClaim, LineError> {
+    let mut de = serde_json::Deserializer::from_str(line);
+    let claim: PayerClaim = serde_path_to_error::deserialize(&mut de).map_err(|err| LineError {
+        pointer: json_pointer(err.path()),
+        reason: err.inner().to_string(),
+    })?;
+    de.end().map_err(|err| LineError {
+        pointer: String::new(),
+        reason: err.to_string(),
+    })?;
+    Ok(claim)
+}
+
+/// Patient demographics lenient parsing may blank out, with the value each is replaced by
+const REPAIRABLE_FIELDS: [(&str, &str); 2] = [("/patient/gender", "u"), ("/patient/dob", "")];
+
+/// Parse one JSONL line, repairing unreadable patient demographics instead of failing
+///
+/// An unrecognized gender becomes unknown and an unparseable date of birth is dropped;
+/// each repair is returned alongside the claim. Any other error fails the line as in
+/// `parse_claim_line`
+pub(crate) fn parse_claim_line_lenient(line: &str) -> Result<(PayerClaim, Vec<LineError>), LineError> {
+    let mut value: serde_json::Value = serde_json::from_str(line).map_err(|err| LineError {
+        pointer: String::new(),
+        reason: err.to_string(),
+    })?;
+    let mut repairs = Vec::new();
+    loop {
+        let err = match serde_path_to_error::deserialize::<_, PayerClaim>(&value) {
+            Ok(claim) => return Ok((claim, repairs)),
+            Err(err) => LineError {
+                pointer: json_pointer(err.path()),
+                reason: err.inner().to_string(),
+            },
+        };
+        let replacement = REPAIRABLE_FIELDS.iter().find(|(pointer, _)| *pointer == err.pointer);
+        let (Some((_, replacement)), Some(field)) = (replacement, value.pointer_mut(&err.pointer)) else {
+            return Err(err);
+        };
+        // a repaired field deserializes, so the same pointer cannot fail twice
+        if repairs.iter().any(|repair: &LineError| repair.pointer == err.pointer) {
+            return Err(err);
+        }
+        *field = if replacement.is_empty() {
+            serde_json::Value::Null
+        } else {
+            serde_json::Value::from(*replacement)
+        };
+        repairs.push(err);
+    }
+}
+
+fn json_pointer(path: &serde_path_to_error::Path) -> String {
+    use serde_path_to_error::Segment;
+    path.iter()
+        .filter_map(|segment| match segment {
+            Segment::Seq { index } => Some(index.to_string()),
+            Segment::Map { key } => Some(key.replace('~', "~0").replace('/', "~1")),
+            Segment::Enum { variant } => Some(variant.clone()),
+            Segment::Unknown => None,
+        })
+        .map(|token| format!("/{}", token))
+        .collect()
+}
+
+/// Default sidecar location: `rejects.jsonl` next to the input file
+pub(crate) fn default_rejects_path(file_path: &str) -> String {
+    let dir = Path::new(file_path)
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty());
+    match dir {
+        Some(dir) => dir.join("rejects.jsonl").to_string_lossy().into_owned(),
+        None => "rejects.jsonl".to_string(),
+    }
+}
+
+/// Appends rejects to a JSONL sidecar, creating the file on the first reject
+pub struct RejectWriter {
+    path: String,
+    file: Option<File>,
+}
+
+impl RejectWriter {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            file: None,
+        }
+    }
+
+    pub async fn write(&mut self, reject: &Reject) -> anyhow::Result<()> {
+        if self.file.is_none() {
+            let file = OpenOptions::new().create(true).append(true).open(&self.path).await?;
+            self.file = Some(file);
+        }
+        if let Some(file) = self.file.as_mut() {
+            let mut json = serde_json::to_string(reject)?;
+            json.push('\n');
+            file.write_all(json.as_bytes()).await?;
+            file.flush().await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::mock_claim;
+
+    /// Test that a type error deep in the claim is reported with its JSON pointer.
+    /// Expected: Pointer names the service line index and field.
+    #[test]
+    fn test_parse_claim_line_pointer() {
+        let mut json = serde_json::to_value(mock_claim()).unwrap();
+        json["service_lines"][0]["units"] = serde_json::json!("three");
+        let err = parse_claim_line(&json.to_string()).unwrap_err();
+        assert_eq!(err.pointer, "/service_lines/0/units");
+        assert!(err.reason.contains("invalid type"), "{}", err.reason);
+    }
+
+    /// Test that malformed JSON is reported against the whole document.
+    /// Expected: Empty pointer and a syntax error reason.
+    #[test]
+    fn test_parse_claim_line_syntax_error() {
+        let err = parse_claim_line("not a json").unwrap_err();
+        assert_eq!(err.pointer, "");
+        assert!(!err.reason.is_empty());
+    }
+
+    /// Test that lenient parsing repairs dirty demographics but still fails other fields.
+    /// Expected: A bad gender becomes unknown and a bad dob is dropped, each reported; a bad unit count still errors.
+    #[test]
+    fn test_parse_claim_line_lenient() {
+        let mut json = serde_json::to_value(mock_claim()).unwrap();
+        json["patient"]["gender"] = serde_json::json!("X");
+        json["patient"]["dob"] = serde_json::json!("01/02/1990");
+        let (claim, repairs) = parse_claim_line_lenient(&json.to_string()).unwrap();
+        assert_eq!(claim.patient.gender, crate::schema::Gender::Unknown);
+        assert_eq!(claim.patient.dob, None);
+        let mut pointers: Vec<_> = repairs.iter().map(|r| r.pointer.as_str()).collect();
+        pointers.sort();
+        assert_eq!(pointers, vec!["/patient/dob", "/patient/gender"]);
+        assert!(parse_claim_line(&json.to_string()).is_err());
+
+        json["service_lines"][0]["units"] = serde_json::json!("three");
+        let err = parse_claim_line_lenient(&json.to_string()).unwrap_err();
+        assert_eq!(err.pointer, "/service_lines/0/units");
+    }
+
+    /// Test that the sidecar defaults to the input file's directory.
+    /// Expected: rejects.jsonl beside the input, or in the working directory.
+    #[test]
+    fn test_default_rejects_path() {
+        assert_eq!(default_rejects_path("data/claims.jsonl"), "data/rejects.jsonl");
+        assert_eq!(default_rejects_path("claims.jsonl"), "rejects.jsonl");
+        assert_eq!(default_rejects_path("-"), "rejects.jsonl");
+    }
+}
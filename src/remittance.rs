@@ -1,118 +1,938 @@
-use serde::Deserialize;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Instant;
 
-use crate::schema::PayerClaim;
+use crate::audit::Discrepancy;
+use crate::benefits::{Accumulator, PlanDesign, ServiceCategory};
+use crate::control::TELEHEALTH_PARITY;
+use crate::drg;
+use crate::message::ClaimMetadata;
+use crate::modifier;
+use crate::outcomes::Outcome;
+use crate::priority::Priority;
+use crate::schema::{DEFAULT_CURRENCY, PayerClaim, ServiceLine};
+use crate::tenant::DEFAULT_TENANT;
+use crate::validation::is_currency_code;
+use crate::ids::{ClaimId, MemberId, PayerId};
 
-//TODO: switch from pub fields to pub getts
-#[derive(Debug, Deserialize, Clone)]
+/// How a service line's charge was split between the payer, the patient, and the adjustment
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(try_from = "ServiceLineRemittanceFields")]
 pub struct ServiceLineRemittance {
-    pub service_line_id: String,
-    pub payer_paid_amount: f64,
-    pub coinsurance_amount: f64,
-    pub copay_amount: f64,
-    pub deductible_amount: f64,
-    pub not_allowed_amount: f64,
+    service_line_id: String,
+    payer_paid_amount: f64,
+    coinsurance_amount: f64,
+    copay_amount: f64,
+    deductible_amount: f64,
+    not_allowed_amount: f64,
+    /// Pricing changes applied to the line, included in `not_allowed_amount`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    adjustments: Vec<Adjustment>,
+    /// Why the payer denied this line, when it denied it on its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    denial_reason: Option<DenialReason>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// A service line remittance as read, before its amounts are checked
+#[derive(Deserialize)]
+struct ServiceLineRemittanceFields {
+    service_line_id: String,
+    payer_paid_amount: f64,
+    coinsurance_amount: f64,
+    copay_amount: f64,
+    deductible_amount: f64,
+    not_allowed_amount: f64,
+    #[serde(default)]
+    adjustments: Vec<Adjustment>,
+    #[serde(default)]
+    denial_reason: Option<DenialReason>,
+}
+
+impl TryFrom<ServiceLineRemittanceFields> for ServiceLineRemittance {
+    type Error = anyhow::Error;
+
+    /// Holds a line read from a file or the wire to the same rules as `ServiceLineRemittance::new`
+    fn try_from(fields: ServiceLineRemittanceFields) -> Result<Self, Self::Error> {
+        let line = ServiceLineRemittance::new(
+            fields.service_line_id,
+            fields.payer_paid_amount,
+            fields.coinsurance_amount,
+            fields.copay_amount,
+            fields.deductible_amount,
+            fields.not_allowed_amount,
+        )?;
+        Ok(ServiceLineRemittance { adjustments: fields.adjustments, denial_reason: fields.denial_reason, ..line })
+    }
+}
+
+/// Amount a modifier or a downcode took off what the payer would otherwise have allowed, negative
+/// when it raised it, as a bilateral modifier does
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Adjustment {
+    /// The modifier, e.g. `26`, or the adjustment reason code, e.g. `CO-150`
+    pub code: String,
+    pub factor: f64,
+    pub amount: f64,
+    pub description: String,
+}
+
+/// A payer's response to a claim, one line per service line billed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(try_from = "RemittanceFields")]
 pub struct Remittance {
-    pub claim_id: String,
-    pub service_line_remittances: Vec<ServiceLineRemittance>,
+    claim_id: ClaimId,
+    /// Currency of every amount on the remittance, that of the claim's charges
+    #[serde(default = "default_currency")]
+    currency: String,
+    service_line_remittances: Vec<ServiceLineRemittance>,
+    /// Why the payer denied the claim, when it gave a reason
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    denial_reason: Option<DenialReason>,
+    /// DRG an inpatient stay was paid by, absent for claims paid line by line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    drg: Option<String>,
+    /// Whether the payer found the lines balanced against the claim before sending
+    #[serde(default, skip_serializing_if = "Balancing::is_balanced")]
+    balancing: Balancing,
+    /// When the payer finished adjudicating; not part of the remittance file
+    #[serde(skip)]
+    adjudicated_at: Option<Instant>,
+}
+
+/// A remittance as read, before it is checked
+#[derive(Deserialize)]
+struct RemittanceFields {
+    claim_id: ClaimId,
+    #[serde(default = "default_currency")]
+    currency: String,
+    service_line_remittances: Vec<ServiceLineRemittance>,
+    #[serde(default)]
+    denial_reason: Option<DenialReason>,
+    #[serde(default)]
+    drg: Option<String>,
+    #[serde(default)]
+    balancing: Balancing,
+}
+
+impl TryFrom<RemittanceFields> for Remittance {
+    type Error = String;
+
+    /// Checks what a remittance read without its claim can be checked for: a claim id, a
+    /// three-letter currency, one line per service line id, and nothing paid on a denied claim.
+    /// Each line's amounts were checked as it was read
+    fn try_from(fields: RemittanceFields) -> Result<Self, Self::Error> {
+        if fields.claim_id.is_empty() {
+            return Err("remittance has no claim id".to_string());
+        }
+        if !is_currency_code(&fields.currency.to_ascii_uppercase()) {
+            return Err(format!("Claim {}: invalid currency code {:?}", fields.claim_id, fields.currency));
+        }
+        let mut line_ids = HashSet::new();
+        if let Some(line) = fields.service_line_remittances.iter().find(|line| !line_ids.insert(line.service_line_id())) {
+            return Err(format!("Claim {}: service line {} is remitted twice", fields.claim_id, line.service_line_id()));
+        }
+        let remittance = Remittance {
+            claim_id: fields.claim_id,
+            currency: fields.currency,
+            service_line_remittances: fields.service_line_remittances,
+            denial_reason: fields.denial_reason,
+            drg: fields.drg,
+            balancing: fields.balancing,
+            adjudicated_at: None,
+        };
+        if let Some(reason) = remittance.denial_reason
+            && remittance.payer_paid() > 0.0
+        {
+            return Err(format!("Claim {}: denied {} but pays {:.2}", remittance.claim_id, reason, remittance.payer_paid()));
+        }
+        Ok(remittance)
+    }
+}
+
+/// Claim adjustment reason code (CARC) a payer gives for denying a claim
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DenialReason {
+    /// CO-16: the claim lacks information or has errors needed for adjudication
+    ClaimError,
+    /// CO-50: the payer does not deem the service medically necessary
+    MedicalNecessity,
+    /// CO-197: the service required prior authorization that was not obtained
+    NoAuthorization,
+    /// CO-27: the member was not covered on the date of service
+    NotEligible,
+}
+
+impl DenialReason {
+    /// Group and reason code as printed on an 835, e.g. `CO-16`
+    pub fn code(&self) -> &'static str {
+        match self {
+            DenialReason::ClaimError => "CO-16",
+            DenialReason::MedicalNecessity => "CO-50",
+            DenialReason::NoAuthorization => "CO-197",
+            DenialReason::NotEligible => "CO-27",
+        }
+    }
+}
+
+impl fmt::Display for DenialReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// What a payer does with a remittance that does not balance against its claim
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MismatchPolicy {
+    /// Discard it and price the claim again, flagging it if it still fails
+    Regenerate,
+    /// Send it on marked as unbalanced; the biller posts it as usual
+    #[default]
+    Flag,
+    /// Send it on held for review; the biller does not post it
+    Exception,
+}
+
+impl FromStr for MismatchPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "regenerate" => Ok(MismatchPolicy::Regenerate),
+            "flag" => Ok(MismatchPolicy::Flag),
+            "exception" => Ok(MismatchPolicy::Exception),
+            _ => Err(anyhow::anyhow!("Unknown mismatch policy: {} (expected regenerate, flag, or exception)", s)),
+        }
+    }
 }
 
-#[derive(Debug)]
+impl fmt::Display for MismatchPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MismatchPolicy::Regenerate => write!(f, "regenerate"),
+            MismatchPolicy::Flag => write!(f, "flag"),
+            MismatchPolicy::Exception => write!(f, "exception"),
+        }
+    }
+}
+
+/// Result of the payer's balancing check, with the failure reason
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", content = "reason", rename_all = "lowercase")]
+pub enum Balancing {
+    #[default]
+    Balanced,
+    /// Sent on despite failing
+    Flagged(String),
+    /// Held in the exceptions queue instead of being posted
+    Held(String),
+}
+
+impl Balancing {
+    pub fn is_balanced(&self) -> bool {
+        *self == Balancing::Balanced
+    }
+
+    pub fn is_held(&self) -> bool {
+        matches!(self, Balancing::Held(_))
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            Balancing::Balanced => None,
+            Balancing::Flagged(reason) | Balancing::Held(reason) => Some(reason),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct RemittanceRecord {
-    claim: PayerClaim,
-    pub remittance: Remittance, //TODO: make field private
+    claim: Arc<PayerClaim>,
+    remittance: Remittance,
     submitted_at: Instant,
     remitted_at: Instant,
+    tenant_id: String,
+    metadata: Option<ClaimMetadata>,
+    /// What the clearinghouse's audit found wrong with the remittance
+    discrepancies: Vec<Discrepancy>,
+    /// Claim accepted as this one's replacement, after which no other may replace it
+    replaced_by: Option<Arc<PayerClaim>>,
 }
 
 impl RemittanceRecord {
     pub fn new(
-        claim: PayerClaim,
+        claim: impl Into<Arc<PayerClaim>>,
         remittance: Remittance,
         submitted_at: Instant,
         remitted_at: Instant,
     ) -> Self {
         Self {
-            claim,
+            claim: claim.into(),
             remittance,
             submitted_at,
             remitted_at,
+            tenant_id: DEFAULT_TENANT.to_string(),
+            metadata: None,
+            discrepancies: Vec::new(),
+            replaced_by: None,
         }
     }
+
+    /// Attach the correlation id, origin, and hop timestamps the claim was traced with
+    pub fn with_metadata(mut self, metadata: ClaimMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Attach the clearinghouse's audit findings
+    pub fn with_discrepancies(mut self, discrepancies: Vec<Discrepancy>) -> Self {
+        self.discrepancies = discrepancies;
+        self
+    }
+
+    pub fn discrepancies(&self) -> &[Discrepancy] {
+        &self.discrepancies
+    }
+
+    /// Link the record to the claim replacing it, or clear the link when that claim is refused
+    pub fn with_replaced_by(mut self, replacement: Option<Arc<PayerClaim>>) -> Self {
+        self.replaced_by = replacement;
+        self
+    }
+
+    pub fn replaced_by(&self) -> Option<&ClaimId> {
+        self.replaced_by.as_ref().map(|claim| &claim.claim_id)
+    }
+
+    pub fn metadata(&self) -> Option<&ClaimMetadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Attribute the record to a billing organization
+    pub fn with_tenant(mut self, tenant_id: &str) -> Self {
+        self.tenant_id = tenant_id.to_string();
+        self
+    }
+
     pub fn elapsed(&self) -> std::time::Duration {
         self.remitted_at.duration_since(self.submitted_at)
     }
 
-    pub fn patient_id(&self) -> &str {
+    pub fn submitted_at(&self) -> Instant {
+        self.submitted_at
+    }
+
+    pub fn remitted_at(&self) -> Instant {
+        self.remitted_at
+    }
+
+    pub fn patient_id(&self) -> &MemberId {
         &self.claim.insurance.patient_member_id
     }
 
-    pub fn payer_id(&self) -> &str {
+    pub fn payer_id(&self) -> &PayerId {
         &self.claim.insurance.payer_id
     }
+
+    pub fn priority(&self) -> Priority {
+        self.claim.priority()
+    }
+
+    pub fn tenant_id(&self) -> &str {
+        &self.tenant_id
+    }
+
+    pub fn claim(&self) -> &PayerClaim {
+        &self.claim
+    }
+
+    /// The claim itself, shared rather than copied
+    pub fn shared_claim(&self) -> &Arc<PayerClaim> {
+        &self.claim
+    }
+
+    pub fn remittance(&self) -> &Remittance {
+        &self.remittance
+    }
+}
+
+impl ServiceLineRemittance {
+    /// A line's amounts, which must all be finite and non-negative
+    pub fn new(
+        service_line_id: impl Into<String>,
+        payer_paid_amount: f64,
+        coinsurance_amount: f64,
+        copay_amount: f64,
+        deductible_amount: f64,
+        not_allowed_amount: f64,
+    ) -> anyhow::Result<ServiceLineRemittance> {
+        let service_line_id = service_line_id.into();
+        let amounts = [
+            ("payer paid", payer_paid_amount),
+            ("coinsurance", coinsurance_amount),
+            ("copay", copay_amount),
+            ("deductible", deductible_amount),
+            ("not allowed", not_allowed_amount),
+        ];
+        if let Some((name, amount)) = amounts.iter().find(|(_, amount)| !amount.is_finite() || *amount < 0.0) {
+            return Err(anyhow::anyhow!("Service line {}: {} amount {} is negative or not a number", service_line_id, name, amount));
+        }
+        Ok(ServiceLineRemittance {
+            service_line_id,
+            payer_paid_amount,
+            coinsurance_amount,
+            copay_amount,
+            deductible_amount,
+            not_allowed_amount,
+            adjustments: Vec::new(),
+            denial_reason: None,
+        })
+    }
+
+    pub fn service_line_id(&self) -> &str {
+        &self.service_line_id
+    }
+
+    pub fn payer_paid_amount(&self) -> f64 {
+        self.payer_paid_amount
+    }
+
+    pub fn coinsurance_amount(&self) -> f64 {
+        self.coinsurance_amount
+    }
+
+    pub fn copay_amount(&self) -> f64 {
+        self.copay_amount
+    }
+
+    pub fn deductible_amount(&self) -> f64 {
+        self.deductible_amount
+    }
+
+    pub fn not_allowed_amount(&self) -> f64 {
+        self.not_allowed_amount
+    }
+
+    /// Copay, coinsurance, and deductible left for the patient
+    pub fn patient_responsibility(&self) -> f64 {
+        self.copay_amount + self.coinsurance_amount + self.deductible_amount
+    }
+
+    /// Sum of every share, which balances against the billed charge
+    pub fn total(&self) -> f64 {
+        self.payer_paid_amount + self.patient_responsibility() + self.not_allowed_amount
+    }
+
+    pub fn adjustments(&self) -> &[Adjustment] {
+        &self.adjustments
+    }
+
+    pub fn denial_reason(&self) -> Option<DenialReason> {
+        self.denial_reason
+    }
 }
 
 impl Remittance {
-    /// Generate a remittance from a claim using mock payment logic
-    /// 
-    /// Calculates payment amounts based on a simple percentage model:
-    /// 80% paid, 10% coinsurance, 5% copay, 3% deductible, 2% not allowed
-    pub fn from_claim(claim: &PayerClaim) -> Remittance {
+    /// A remittance for `claim` from lines already priced elsewhere
+    ///
+    /// There must be one line per service line billed, in any order, each balancing
+    /// against its charge in the claim's currency, unless the payer pays nothing: a
+    /// denial need not account for the whole charge
+    pub fn new(claim: &PayerClaim, service_line_remittances: Vec<ServiceLineRemittance>) -> anyhow::Result<Remittance> {
+        let remittance = Remittance {
+            claim_id: claim.claim_id.clone(),
+            currency: claim.currency().to_string(),
+            service_line_remittances,
+            denial_reason: None,
+            drg: None,
+            balancing: Balancing::Balanced,
+            adjudicated_at: None,
+        };
+        if let Some(reason) = remittance.match_lines(claim).unmatched_reason() {
+            return Err(anyhow::anyhow!("Claim {}: {}", claim.claim_id, reason));
+        }
+        if !remittance.is_denied() {
+            remittance.validate_against_claim(claim).map_err(anyhow::Error::msg)?;
+        }
+        Ok(remittance)
+    }
+
+    /// Generate a remittance for a patient's first claim of the plan year under the default plan
+    ///
+    /// The fee schedule allows part of each line's charge; the patient's share of that
+    /// comes from the plan's copays, deductible, and coinsurance (see `PlanDesign`), and
+    /// the payer pays the rest. Pediatric patients and telehealth lines have their copay
+    /// waived, paid by the payer instead; services in a facility are paid at a reduced rate.
+    /// Pricing modifiers scale each line's allowed amount (see `modifier`). The patient's age and
+    /// plan year are judged on `on`, the adjudication date, e.g. the payer's `SimClock` date
+    pub fn from_claim(claim: &PayerClaim, on: NaiveDate) -> Remittance {
+        Remittance::adjudicated(claim, &[], &PlanDesign::default(), TELEHEALTH_PARITY, &mut Accumulator::default(), on)
+    }
+
+    /// Price a claim under the patient's plan, ruling on each line with the matching outcome
+    ///
+    /// What the patient owes is added to `accumulator`, their totals so far in the plan year
+    /// containing `on`, the adjudication date; totals from an earlier plan year are reset first.
+    /// Denied lines pay nothing and carry their reason; downcoded lines are allowed
+    /// `DOWNCODE_FACTOR` of the usual amount. Lines without an outcome are paid. Telehealth lines
+    /// (see `PayerClaim::is_telehealth_line`) are allowed `telehealth_rate` of the in-person amount
+    ///
+    /// An inpatient stay is grouped to a DRG (see `drg::group`), falling back to the one the
+    /// hospital coded, and a priced DRG is allowed its payment, spread across the lines by charge
+    /// and never more than was billed, instead of the fee schedule. Outpatient institutional
+    /// bills are paid line by line on the fee schedule
+    pub fn adjudicated(
+        claim: &PayerClaim,
+        outcomes: &[Outcome],
+        plan: &PlanDesign,
+        telehealth_rate: f64,
+        accumulator: &mut Accumulator,
+        on: NaiveDate,
+    ) -> Remittance {
+        accumulator.roll_over(plan.plan_year_of(on));
+        let institutional = claim.institutional();
+        let drg = institutional
+            .filter(|institutional| institutional.is_inpatient())
+            .and_then(|institutional| drg::group(claim).or(institutional.drg.as_deref()))
+            .filter(|drg| drg::weight(drg).is_some());
+        let drg_payment = drg.and_then(drg::payment);
+        let total_charge = claim.total_charge();
+        let pricing = Pricing {
+            plan,
+            pediatric: claim.patient.is_pediatric_on(on),
+            facility: institutional.is_none() && claim.place_of_service_code.is_facility(),
+            rate: match drg_payment {
+                Some(payment) if total_charge > 0.0 => (payment / total_charge).min(1.0),
+                _ => plan.fee_schedule,
+            },
+            telehealth_rate,
+        };
         let service_line_remittances: Vec<ServiceLineRemittance> = claim
             .service_lines
             .iter()
-            .map(|service_line| calculate_service_line_remittance(service_line))
+            .enumerate()
+            .map(|(i, line)| {
+                let outcome = outcomes.get(i).copied().unwrap_or_default();
+                calculate_service_line_remittance(line, claim.is_telehealth_line(line), &pricing, outcome, accumulator)
+            })
             .collect();
 
         Remittance {
             claim_id: claim.claim_id.clone(),
+            currency: claim.currency().to_string(),
             service_line_remittances,
+            denial_reason: None,
+            drg: drg.map(String::from),
+            balancing: Balancing::Balanced,
+            adjudicated_at: None,
+        }
+    }
+
+    /// Start a remittance for `claim` adjudicated on `on`; see `RemittanceBuilder`
+    pub fn builder(claim: &PayerClaim, on: NaiveDate) -> RemittanceBuilder<'_> {
+        RemittanceBuilder::new(claim, on)
+    }
+
+    /// Deny the whole claim with a reason code
+    pub fn denied_for(claim: &PayerClaim, reason: DenialReason) -> Remittance {
+        Remittance {
+            denial_reason: Some(reason),
+            ..Remittance::denied(claim)
+        }
+    }
+
+    /// Generate a remittance denying the whole claim: nothing paid, the full charge not allowed
+    pub fn denied(claim: &PayerClaim) -> Remittance {
+        let service_line_remittances = claim
+            .service_lines
+            .iter()
+            .map(|line| denied_service_line(line, None))
+            .collect();
+
+        Remittance {
+            claim_id: claim.claim_id.clone(),
+            currency: claim.currency().to_string(),
+            service_line_remittances,
+            denial_reason: None,
+            drg: None,
+            balancing: Balancing::Balanced,
+            adjudicated_at: None,
+        }
+    }
+
+    /// Record the outcome of the payer's balancing check
+    pub fn with_balancing(mut self, balancing: Balancing) -> Self {
+        self.balancing = balancing;
+        self
+    }
+
+    /// Drop every line's not-allowed amount, as a payer pricing error would,
+    /// leaving the lines short of the charge; used by payers to inject `error_rate` faults
+    pub(crate) fn with_miscalculation(mut self) -> Self {
+        for line in &mut self.service_line_remittances {
+            line.not_allowed_amount = 0.0;
         }
+        self
+    }
+
+    pub fn denial_reason(&self) -> Option<DenialReason> {
+        self.denial_reason
+    }
+
+    pub fn drg(&self) -> Option<&str> {
+        self.drg.as_deref()
+    }
+
+    pub fn balancing(&self) -> &Balancing {
+        &self.balancing
+    }
+
+    /// Stamp the time the payer finished adjudicating
+    pub fn with_adjudicated_at(mut self, at: Instant) -> Self {
+        self.adjudicated_at = Some(at);
+        self
+    }
+
+    pub fn claim_id(&self) -> &ClaimId {
+        &self.claim_id
+    }
+
+    /// The same remittance under another claim id, as when the clearinghouse
+    /// translates the id a payer knew the claim by back to the biller's
+    pub fn with_claim_id(mut self, claim_id: ClaimId) -> Self {
+        self.claim_id = claim_id;
+        self
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    pub fn service_line_remittances(&self) -> &[ServiceLineRemittance] {
+        &self.service_line_remittances
+    }
+
+    /// When the payer finished adjudicating, if it was stamped in this run
+    pub fn adjudicated_at(&self) -> Option<Instant> {
+        self.adjudicated_at
+    }
+
+    /// Total paid by the payer across all service lines
+    pub fn payer_paid(&self) -> f64 {
+        self.service_line_remittances
+            .iter()
+            .map(|line| line.payer_paid_amount)
+            .sum()
+    }
+
+    /// A claim is denied when the payer pays nothing on any service line
+    pub fn is_denied(&self) -> bool {
+        !self.service_line_remittances.is_empty() && self.payer_paid() <= 0.0
+    }
+
+    /// Pair each remittance line with the billed line it answers, by service line id
+    ///
+    /// Payers need not answer lines in the order they were billed. A service line id
+    /// billed more than once is paired occurrence by occurrence, in order
+    pub fn match_lines<'a>(&'a self, claim: &'a PayerClaim) -> LineMatch<'a> {
+        let mut unmatched: Vec<&ServiceLineRemittance> = self.service_line_remittances.iter().collect();
+        let mut lines = LineMatch::default();
+        for service_line in &claim.service_lines {
+            match unmatched.iter().position(|remit| remit.service_line_id == service_line.service_line_id) {
+                Some(i) => lines.matched.push((unmatched.remove(i), service_line)),
+                None => lines.missing.push(service_line),
+            }
+        }
+        lines.extra = unmatched;
+        lines
     }
 
     /// Validate that remittance amounts match the original billed amounts
     /// 
-    /// Ensures the sum of all payment components equals the total charge
-    /// Returns error if amounts don't balance within rounding tolerance
+    /// Ensures every billed line is answered exactly once, by service line id, and
+    /// the sum of all payment components equals its charge
+    /// Returns error if a line is missing or not billed, if amounts don't balance within
+    /// rounding tolerance, or if a line was billed in a different currency, since the
+    /// amounts cannot be compared
     pub fn validate_against_claim(&self, claim: &PayerClaim) -> Result<(), String> {
-        for (remit, service_line) in self
-            .service_line_remittances
-            .iter()
-            .zip(&claim.service_lines)
-        {
+        if let Some(line) = claim.service_lines.iter().find(|line| line.unit_charge_currency != self.currency) {
+            return Err(format!(
+                "Service line {}: billed in {} but remitted in {}",
+                line.service_line_id, line.unit_charge_currency, self.currency
+            ));
+        }
+        let lines = self.match_lines(claim);
+        if let Some(reason) = lines.unmatched_reason() {
+            return Err(reason);
+        }
+        for (remit, service_line) in lines.matched {
             validate_service_line_remittance(remit, service_line)?;
         }
         Ok(())
     }
 }
 
-fn calculate_service_line_remittance(service_line: &crate::schema::ServiceLine) -> ServiceLineRemittance {
+/// Builds a remittance for a claim a line at a time, checking at `build` that it answers the claim
+///
+/// Lines not set are priced as `Remittance::from_claim` prices them, or denied with the claim,
+/// so only the lines a case is about need setting. Like `Remittance::new`, every line must
+/// balance against its charge unless the claim is denied or the remittance is marked as failing
+/// its balancing check
+#[derive(Debug, Clone)]
+pub struct RemittanceBuilder<'a> {
+    claim: &'a PayerClaim,
+    /// Adjudication date the unset lines are priced on
+    on: NaiveDate,
+    lines: Vec<ServiceLineRemittance>,
+    denied: Option<Option<DenialReason>>,
+    drg: Option<String>,
+    balancing: Balancing,
+    adjudicated_at: Option<Instant>,
+    problems: Vec<String>,
+}
+
+impl<'a> RemittanceBuilder<'a> {
+    pub fn new(claim: &'a PayerClaim, on: NaiveDate) -> Self {
+        Self {
+            claim,
+            on,
+            lines: Vec::new(),
+            denied: None,
+            drg: None,
+            balancing: Balancing::Balanced,
+            adjudicated_at: None,
+            problems: Vec::new(),
+        }
+    }
+
+    fn billed(&mut self, service_line_id: &str) -> Option<&'a ServiceLine> {
+        let line = self.claim.service_lines.iter().find(|line| line.service_line_id == service_line_id);
+        if line.is_none() {
+            self.problems.push(format!("service line {} is not on the claim", service_line_id));
+        }
+        line
+    }
+
+    /// Answer a line with amounts priced elsewhere
+    pub fn line(mut self, line: ServiceLineRemittance) -> Self {
+        self.lines.retain(|set| set.service_line_id != line.service_line_id);
+        self.lines.push(line);
+        self
+    }
+
+    /// Pay `payer_paid` on a line with the patient owing `patient_owes` as coinsurance, the rest
+    /// of the charge not allowed
+    pub fn pay_line(mut self, service_line_id: &str, payer_paid: f64, patient_owes: f64) -> Self {
+        let Some(billed) = self.billed(service_line_id) else {
+            return self;
+        };
+        let charge = billed.unit_charge_amount * billed.units as f64;
+        match ServiceLineRemittance::new(service_line_id, payer_paid, patient_owes, 0.0, 0.0, charge - payer_paid - patient_owes) {
+            Ok(line) => self.line(line),
+            Err(e) => {
+                self.problems.push(e.to_string());
+                self
+            }
+        }
+    }
+
+    /// Deny one line, paying nothing on it
+    pub fn deny_line(mut self, service_line_id: &str, reason: DenialReason) -> Self {
+        match self.billed(service_line_id) {
+            Some(billed) => self.line(denied_service_line(billed, Some(reason))),
+            None => self,
+        }
+    }
+
+    /// Deny the whole claim, with a reason code when given; lines set still answer as set
+    pub fn deny(mut self, reason: Option<DenialReason>) -> Self {
+        self.denied = Some(reason);
+        self
+    }
+
+    /// Pay an inpatient stay by this DRG
+    pub fn drg(mut self, drg: impl Into<String>) -> Self {
+        self.drg = Some(drg.into());
+        self
+    }
+
+    pub fn balancing(mut self, balancing: Balancing) -> Self {
+        self.balancing = balancing;
+        self
+    }
+
+    pub fn adjudicated_at(mut self, at: Instant) -> Self {
+        self.adjudicated_at = Some(at);
+        self
+    }
+
+    /// The remittance, or every reason it does not answer the claim
+    pub fn build(self) -> anyhow::Result<Remittance> {
+        let mut problems = self.problems;
+        let mut remittance = match self.denied {
+            Some(reason) => Remittance { denial_reason: reason, ..Remittance::denied(self.claim) },
+            None => Remittance::from_claim(self.claim, self.on),
+        };
+        for line in self.lines {
+            match remittance.service_line_remittances.iter_mut().find(|remit| remit.service_line_id == line.service_line_id) {
+                Some(remit) => *remit = line,
+                None => problems.push(format!("service line {} is not on the claim", line.service_line_id)),
+            }
+        }
+        remittance.drg = self.drg;
+        remittance.adjudicated_at = self.adjudicated_at;
+        remittance.balancing = self.balancing;
+        if remittance.balancing.is_balanced()
+            && !remittance.is_denied()
+            && let Err(e) = remittance.validate_against_claim(self.claim)
+        {
+            problems.push(e);
+        }
+        problems.dedup();
+        match problems.is_empty() {
+            true => Ok(remittance),
+            false => Err(anyhow::anyhow!("Claim {}: {}", self.claim.claim_id, problems.join("; "))),
+        }
+    }
+}
+
+/// A remittance's lines paired with the billed lines they answer (see `Remittance::match_lines`)
+#[derive(Debug, Default)]
+pub struct LineMatch<'a> {
+    /// Remittance lines with the billed line each answers, in billed order
+    pub matched: Vec<(&'a ServiceLineRemittance, &'a ServiceLine)>,
+    /// Billed lines the remittance does not answer
+    pub missing: Vec<&'a ServiceLine>,
+    /// Remittance lines answering no billed line
+    pub extra: Vec<&'a ServiceLineRemittance>,
+}
+
+impl LineMatch<'_> {
+    /// Service line ids of the missing lines, then of the extra ones
+    pub fn unmatched_ids(&self) -> Vec<String> {
+        let missing = self.missing.iter().map(|line| line.service_line_id.clone());
+        missing.chain(self.extra.iter().map(|remit| remit.service_line_id.clone())).collect()
+    }
+
+    /// Why the lines do not match, naming every unmatched line; `None` when every line matched
+    pub fn unmatched_reason(&self) -> Option<String> {
+        let ids = |ids: Vec<&str>| ids.join(", ");
+        let mut reasons = Vec::new();
+        if !self.missing.is_empty() {
+            let missing = self.missing.iter().map(|line| line.service_line_id.as_str()).collect();
+            reasons.push(format!("service lines {} not remitted", ids(missing)));
+        }
+        if !self.extra.is_empty() {
+            let extra = self.extra.iter().map(|remit| remit.service_line_id.as_str()).collect();
+            reasons.push(format!("remittance lines {} not billed", ids(extra)));
+        }
+        (!reasons.is_empty()).then(|| format!("Unmatched lines: {}", reasons.join("; ")))
+    }
+}
+
+fn default_currency() -> String {
+    DEFAULT_CURRENCY.to_string()
+}
+
+/// How a claim's lines are priced: the patient's plan and the claim-wide rules that adjust it
+struct Pricing<'a> {
+    plan: &'a PlanDesign,
+    /// The patient is a child, so the payer pays the copay, as it does on telehealth lines
+    pediatric: bool,
+    /// Billed from a facility, so the professional payment is reduced
+    facility: bool,
+    /// Fraction of each line's charge allowed before modifiers: the fee schedule, or the DRG payment's share of the charges
+    rate: f64,
+    /// Fraction of the in-person allowed amount the payer allows for a telehealth line
+    telehealth_rate: f64,
+}
+
+/// Share of the payment the payer withholds for the professional service when a facility bills separately
+const FACILITY_REDUCTION: f64 = 0.20;
+
+/// Fraction of the usual allowed amount paid on a line billed at a higher level of service than supported
+const DOWNCODE_FACTOR: f64 = 0.70;
+
+fn calculate_service_line_remittance(
+    service_line: &ServiceLine,
+    telehealth: bool,
+    pricing: &Pricing,
+    outcome: Outcome,
+    accumulator: &mut Accumulator,
+) -> ServiceLineRemittance {
+    match outcome {
+        Outcome::DenyMedicalNecessity => return denied_service_line(service_line, Some(DenialReason::MedicalNecessity)),
+        Outcome::DenyAuthorization => return denied_service_line(service_line, Some(DenialReason::NoAuthorization)),
+        Outcome::Pay | Outcome::Downcode => {}
+    }
     let total_charge = service_line.unit_charge_amount * service_line.units as f64;
-    let payer_paid_amount = total_charge * 0.80;
-    let coinsurance_amount = total_charge * 0.10;
-    let copay_amount = total_charge * 0.05;
-    let deductible_amount = total_charge * 0.03;
-    let not_allowed_amount = total_charge * 0.02;
+    let mut allowed = total_charge * pricing.rate;
+    let mut adjustments = Vec::new();
+    let mut reduce = |allowed: &mut f64, code: &str, factor: f64, description: &str| {
+        adjustments.push(Adjustment {
+            code: code.to_string(),
+            factor,
+            amount: *allowed * (1.0 - factor),
+            description: description.to_string(),
+        });
+        *allowed *= factor;
+    };
+    for rule in service_line.modifiers.iter().flatten().filter_map(|m| modifier::pricing(m)) {
+        reduce(&mut allowed, rule.modifier, rule.factor, rule.description);
+    }
+    if telehealth && pricing.telehealth_rate < 1.0 {
+        reduce(&mut allowed, modifier::TELEHEALTH, pricing.telehealth_rate, "telehealth paid below the in-person rate");
+    }
+    if outcome == Outcome::Downcode {
+        reduce(&mut allowed, "CO-150", DOWNCODE_FACTOR, "paid at a lower level of service");
+    }
+    // a bilateral line can price above its charge, and no payer allows more than was billed
+    if allowed > total_charge {
+        let cap = total_charge / allowed;
+        reduce(&mut allowed, "CO-45", cap, "allowed capped at the billed charge");
+        allowed = total_charge;
+    }
+    let category = ServiceCategory::of(&service_line.procedure_code);
+    let share = pricing.plan.patient_share(category, allowed, pricing.pediatric || telehealth, accumulator);
+    let mut payer_paid = allowed - share.total();
+    if pricing.facility {
+        payer_paid -= payer_paid * FACILITY_REDUCTION;
+    }
+    ServiceLineRemittance {
+        service_line_id: service_line.service_line_id.clone(),
+        payer_paid_amount: payer_paid,
+        coinsurance_amount: share.coinsurance,
+        copay_amount: share.copay,
+        deductible_amount: share.deductible,
+        // rounding must not leave a fully allowed line a hair below zero
+        not_allowed_amount: (total_charge - payer_paid - share.total()).max(0.0),
+        adjustments,
+        denial_reason: None,
+    }
+}
+
+/// Nothing paid on the line, the full charge not allowed
+fn denied_service_line(service_line: &ServiceLine, denial_reason: Option<DenialReason>) -> ServiceLineRemittance {
     ServiceLineRemittance {
         service_line_id: service_line.service_line_id.clone(),
-        payer_paid_amount,
-        coinsurance_amount,
-        copay_amount,
-        deductible_amount,
-        not_allowed_amount,
+        payer_paid_amount: 0.0,
+        coinsurance_amount: 0.0,
+        copay_amount: 0.0,
+        deductible_amount: 0.0,
+        not_allowed_amount: service_line.unit_charge_amount * service_line.units as f64,
+        adjustments: Vec::new(),
+        denial_reason,
     }
 }
 
-fn validate_service_line_remittance(remit: &ServiceLineRemittance, service_line: &crate::schema::ServiceLine) -> Result<(), String> {
+fn validate_service_line_remittance(remit: &ServiceLineRemittance, service_line: &ServiceLine) -> Result<(), String> {
     let billed = service_line.unit_charge_amount * service_line.units as f64;
-    let sum = remit.payer_paid_amount
-        + remit.coinsurance_amount
-        + remit.copay_amount
-        + remit.deductible_amount
-        + remit.not_allowed_amount;
+    let sum = remit.total();
     if (sum - billed).abs() > 1e-2 {
         return Err(format!(
             "Service line {}: remittance sum {:.2} does not match billed amount {:.2}",
@@ -122,11 +942,12 @@ fn validate_service_line_remittance(remit: &ServiceLineRemittance, service_line:
     Ok(())
 }
 
-/// Mock remittance for testing
-#[cfg(test)]
+/// Mock remittance for testing, two lines that balance against no claim in particular
+#[cfg(any(test, feature = "test-utils"))]
 pub fn mock_remittance() -> Remittance {
     Remittance {
-        claim_id: "abc123".to_string(),
+        claim_id: "abc123".into(),
+        currency: DEFAULT_CURRENCY.to_string(),
         service_line_remittances: vec![
             ServiceLineRemittance {
                 service_line_id: "sl1".to_string(),
@@ -135,6 +956,8 @@ pub fn mock_remittance() -> Remittance {
                 copay_amount: 10.0,
                 deductible_amount: 5.0,
                 not_allowed_amount: 0.0,
+                adjustments: Vec::new(),
+                denial_reason: None,
             },
             ServiceLineRemittance {
                 service_line_id: "sl2".to_string(),
@@ -143,7 +966,287 @@ pub fn mock_remittance() -> Remittance {
                 copay_amount: 0.0,
                 deductible_amount: 0.0,
                 not_allowed_amount: 0.0,
+                adjustments: Vec::new(),
+                denial_reason: None,
             },
         ],
+        denial_reason: None,
+        drg: None,
+        balancing: Balancing::Balanced,
+        adjudicated_at: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::place_of_service::PlaceOfService;
+    use crate::schema::{ClaimType, InstitutionalClaim};
+    use crate::test_utils::{ClaimBuilder, mock_claim, mock_adjudication_date};
+
+    /// Test that payer rules adjust the cost share by patient age and place of service.
+    /// Expected: Adults in the office pay the plan's $25 copay; a telehealth visit and a child have it waived; an inpatient visit is paid at the facility rate; every remittance balances.
+    #[test]
+    fn test_cost_share_rules() {
+        let mut claim = mock_claim();
+        let remit = Remittance::from_claim(&claim, mock_adjudication_date());
+        assert_eq!(remit.service_line_remittances[0].copay_amount, 25.0);
+        assert!((remit.payer_paid() - (150.0 * 0.98 - 25.0)).abs() < 1e-9);
+
+        claim.place_of_service_code = PlaceOfService::TELEHEALTH_HOME;
+        let remit = Remittance::from_claim(&claim, mock_adjudication_date());
+        assert_eq!(remit.service_line_remittances[0].copay_amount, 0.0);
+        assert!(remit.validate_against_claim(&claim).is_ok());
+
+        claim.place_of_service_code = PlaceOfService::INPATIENT_HOSPITAL;
+        let remit = Remittance::from_claim(&claim, mock_adjudication_date());
+        assert!((remit.payer_paid() - (150.0 * 0.98 - 25.0) * 0.80).abs() < 1e-9);
+        assert!(remit.validate_against_claim(&claim).is_ok());
+
+        claim.place_of_service_code = PlaceOfService::OFFICE;
+        claim.patient.dob = Some(mock_adjudication_date() - chrono::Days::new(365 * 5));
+        let remit = Remittance::from_claim(&claim, mock_adjudication_date());
+        assert_eq!(remit.service_line_remittances[0].copay_amount, 0.0);
+        assert!(remit.validate_against_claim(&claim).is_ok());
+        // the patient's age is judged on the adjudication date, so the same claim adjudicated once they are grown owes the copay
+        let grown = mock_adjudication_date() + chrono::Days::new(365 * 20);
+        assert_eq!(Remittance::from_claim(&claim, grown).service_line_remittances[0].copay_amount, 25.0);
+    }
+
+    /// Test that a patient's deductible is only charged until met across their claims.
+    /// Expected: The first surgery claim fills the $500 deductible and 20% of the rest; the second owes only coinsurance.
+    #[test]
+    fn test_plan_deductible() {
+        let mut claim = mock_claim();
+        claim.service_lines[0].procedure_code = "27447".to_string();
+        claim.service_lines[0].unit_charge_amount = 1_000.0;
+        let plan = PlanDesign::default();
+        let mut accumulator = Accumulator::default();
+        let today = mock_adjudication_date();
+        let first = Remittance::adjudicated(&claim, &[], &plan, TELEHEALTH_PARITY, &mut accumulator, today);
+        let line = &first.service_line_remittances()[0];
+        assert_eq!(line.deductible_amount(), 500.0);
+        assert!((line.coinsurance_amount() - 480.0 * 0.20).abs() < 1e-9);
+        assert!(first.validate_against_claim(&claim).is_ok());
+
+        let second = Remittance::adjudicated(&claim, &[], &plan, TELEHEALTH_PARITY, &mut accumulator, today);
+        let line = &second.service_line_remittances()[0];
+        assert_eq!(line.deductible_amount(), 0.0);
+        assert!((line.coinsurance_amount() - 980.0 * 0.20).abs() < 1e-9);
+        assert!((accumulator.out_of_pocket - (500.0 + 96.0 + 196.0)).abs() < 1e-9);
+    }
+
+    /// Test that an inpatient stay is paid by its DRG while other institutional bills use the fee schedule.
+    /// Expected: A knee replacement stay billed at $20,000 groups to DRG 470, even when coded otherwise, and is allowed $12,350 with no
+    /// facility reduction; as an outpatient bill it is allowed 98% of the charge with no DRG.
+    #[test]
+    fn test_drg_payment() {
+        let mut claim = mock_claim();
+        claim.place_of_service_code = PlaceOfService::INPATIENT_HOSPITAL;
+        claim.service_lines[0].procedure_code = "27447".to_string();
+        claim.service_lines[0].unit_charge_amount = 20_000.0;
+        let admission_date = mock_adjudication_date() - chrono::Days::new(5);
+        let mut institutional = InstitutionalClaim {
+            bill_type: "0111".to_string(),
+            admission_date,
+            discharge_date: Some(admission_date + chrono::Days::new(3)),
+            drg: Some("470".to_string()),
+        };
+        claim.claim_type = ClaimType::Institutional(institutional.clone());
+        let remit = Remittance::from_claim(&claim, mock_adjudication_date());
+        let line = &remit.service_line_remittances()[0];
+        assert_eq!(remit.drg(), Some("470"));
+        assert!((line.payer_paid_amount() + line.patient_responsibility() - 1.9 * drg::BASE_RATE).abs() < 1e-9);
+        assert!((line.payer_paid_amount() - (12_350.0 - 500.0 - 11_850.0 * 0.20)).abs() < 1e-9);
+        assert!(remit.validate_against_claim(&claim).is_ok());
+
+        // the payer's grouper overrides the DRG the hospital coded
+        institutional.drg = Some("775".to_string());
+        claim.claim_type = ClaimType::Institutional(institutional.clone());
+        assert_eq!(Remittance::from_claim(&claim, mock_adjudication_date()).drg(), Some("470"));
+
+        institutional.bill_type = "0131".to_string();
+        claim.claim_type = ClaimType::Institutional(institutional);
+        let remit = Remittance::from_claim(&claim, mock_adjudication_date());
+        let line = &remit.service_line_remittances()[0];
+        assert_eq!(remit.drg(), None);
+        assert!((line.payer_paid_amount() + line.patient_responsibility() - 20_000.0 * 0.98).abs() < 1e-9);
+    }
+
+    /// Test that modifier 95 marks an in-office line as telehealth and that a payer below parity reduces it.
+    /// Expected: The 95 line has its copay waived and is allowed 98% of the charge at parity, or 80% of that at a 0.8 rate with a `95` adjustment.
+    #[test]
+    fn test_telehealth_rate() {
+        let mut claim = mock_claim();
+        claim.service_lines[0].modifiers.get_or_insert_default().push("95".to_string());
+        assert!(claim.is_telehealth());
+        let today = mock_adjudication_date();
+        let plan = PlanDesign::default();
+        let parity = Remittance::adjudicated(&claim, &[], &plan, TELEHEALTH_PARITY, &mut Accumulator::default(), today);
+        assert_eq!(parity.service_line_remittances()[0].copay_amount(), 0.0);
+        assert!((parity.payer_paid() - 150.0 * 0.98).abs() < 1e-9);
+        assert!(parity.service_line_remittances()[0].adjustments().is_empty());
+
+        let reduced = Remittance::adjudicated(&claim, &[], &plan, 0.8, &mut Accumulator::default(), today);
+        let line = &reduced.service_line_remittances()[0];
+        assert!((reduced.payer_paid() - 150.0 * 0.98 * 0.8).abs() < 1e-9);
+        assert_eq!(line.adjustments()[0].code, "95");
+        assert!(reduced.validate_against_claim(&claim).is_ok());
+    }
+
+    /// Test that a remittance only balances against charges in its own currency.
+    /// Expected: The remittance carries the claim's currency; once a line is billed in another currency validation fails.
+    #[test]
+    fn test_validate_currency_mismatch() {
+        let mut claim = mock_claim();
+        let remit = Remittance::from_claim(&claim, mock_adjudication_date());
+        assert_eq!(remit.currency, "USD");
+        claim.service_lines[0].unit_charge_currency = "EUR".to_string();
+        let err = remit.validate_against_claim(&claim).unwrap_err();
+        assert!(err.contains("EUR"), "{}", err);
+    }
+
+    /// Test that constructors refuse negative amounts and lines that do not balance, except on a denial.
+    /// Expected: A negative copay and an unbalanced paid line are errors; an unbalanced zero-paid line is accepted as a denial.
+    #[test]
+    fn test_remittance_invariants() {
+        let claim = mock_claim();
+        assert!(ServiceLineRemittance::new("sl1", 100.0, 0.0, -5.0, 0.0, 0.0).is_err());
+        let short = ServiceLineRemittance::new("sl1", 100.0, 0.0, 0.0, 0.0, 0.0).unwrap();
+        assert!(Remittance::new(&claim, vec![short]).is_err());
+        let denial = ServiceLineRemittance::new("sl1", 0.0, 0.0, 0.0, 0.0, 0.0).unwrap();
+        assert!(Remittance::new(&claim, vec![denial]).unwrap().is_denied());
+        assert!(Remittance::new(&claim, Vec::new()).is_err());
+    }
+
+    /// Test that a remittance read from JSON is held to the same invariants as one constructed.
+    /// Expected: A written remittance reads back; a negative amount, a repeated line, a bad currency, or a paid denial fails to parse.
+    #[test]
+    fn test_remittance_deserialize_invariants() {
+        let remittance = Remittance::from_claim(&mock_claim(), mock_adjudication_date());
+        let json = serde_json::to_value(&remittance).unwrap();
+        let read: Remittance = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(read.payer_paid(), remittance.payer_paid());
+
+        let invalid = |change: fn(&mut serde_json::Value)| {
+            let mut json = json.clone();
+            change(&mut json);
+            serde_json::from_value::<Remittance>(json).unwrap_err().to_string()
+        };
+        let err = invalid(|json| json["service_line_remittances"][0]["copay_amount"] = (-5.0).into());
+        assert!(err.contains("copay"), "{}", err);
+        let err = invalid(|json| {
+            let line = json["service_line_remittances"][0].clone();
+            json["service_line_remittances"].as_array_mut().unwrap().push(line);
+        });
+        assert!(err.contains("twice"), "{}", err);
+        let err = invalid(|json| json["currency"] = "US$".into());
+        assert!(err.contains("currency"), "{}", err);
+        let err = invalid(|json| json["denial_reason"] = "medical_necessity".into());
+        assert!(err.contains("CO-50"), "{}", err);
+    }
+
+    /// Test that pricing modifiers scale the allowed amount and are listed as adjustments.
+    /// Expected: Modifier 26 allows 40% of the usual 98%, less the copay; the reduction is recorded and not allowed; unknown modifiers are ignored.
+    #[test]
+    fn test_modifier_pricing() {
+        let mut claim = mock_claim();
+        claim.service_lines[0].modifiers = Some(vec!["-26".to_string(), "xyz".to_string()]);
+        let remit = Remittance::from_claim(&claim, mock_adjudication_date());
+        let line = &remit.service_line_remittances[0];
+        assert!((line.payer_paid_amount - (150.0 * 0.98 * 0.40 - 25.0)).abs() < 1e-9);
+        assert_eq!(line.adjustments.len(), 1);
+        assert_eq!(line.adjustments[0].code, "26");
+        assert!((line.adjustments[0].amount - 150.0 * 0.98 * 0.60).abs() < 1e-9);
+        assert!(remit.validate_against_claim(&claim).is_ok());
+    }
+
+    /// Test that the bilateral modifier allows 150% of the single-side amount but never more than the charge.
+    /// Expected: With `51` as well, 150% of 50% of the usual 98% is allowed; alone, the allowed amount is capped at the charge with a CO-45 adjustment.
+    #[test]
+    fn test_bilateral_pricing() {
+        let mut claim = mock_claim();
+        claim.service_lines[0].modifiers = Some(vec!["50".to_string(), "51".to_string()]);
+        let remit = Remittance::from_claim(&claim, mock_adjudication_date());
+        let line = &remit.service_line_remittances[0];
+        assert!((line.payer_paid_amount - (150.0 * 0.98 * 1.5 * 0.5 - 25.0)).abs() < 1e-9);
+        assert!(line.adjustments[0].amount < 0.0, "bilateral raises the allowed amount");
+        assert!(remit.validate_against_claim(&claim).is_ok());
+
+        claim.service_lines[0].modifiers = Some(vec!["-50".to_string()]);
+        let remit = Remittance::from_claim(&claim, mock_adjudication_date());
+        let line = &remit.service_line_remittances[0];
+        assert!((line.payer_paid_amount - (150.0 - 25.0)).abs() < 1e-9);
+        assert!(line.not_allowed_amount.abs() < 1e-9);
+        assert_eq!(line.adjustments.last().map(|a| a.code.as_str()), Some("CO-45"));
+        assert!(remit.validate_against_claim(&claim).is_ok());
+    }
+
+    /// Test that per-line outcomes deny, downcode, or pay each line and still balance.
+    /// Expected: The denied line pays nothing with CO-197; the downcoded line is allowed 70% with a CO-150 adjustment; the claim is not denied overall.
+    #[test]
+    fn test_line_outcomes() {
+        let mut claim = mock_claim();
+        let mut second = claim.service_lines[0].clone();
+        second.service_line_id = "sl2".to_string();
+        claim.service_lines.push(second);
+        claim.total_charge_amount = None;
+        let outcomes = [Outcome::DenyAuthorization, Outcome::Downcode];
+        let today = mock_adjudication_date();
+        let remit = Remittance::adjudicated(&claim, &outcomes, &PlanDesign::default(), TELEHEALTH_PARITY, &mut Accumulator::default(), today);
+        let [denied, downcoded] = remit.service_line_remittances() else { panic!("expected two lines") };
+        assert_eq!(denied.payer_paid_amount(), 0.0);
+        assert_eq!(denied.denial_reason().map(|r| r.code()), Some("CO-197"));
+        assert!((downcoded.payer_paid_amount() - (150.0 * 0.98 * DOWNCODE_FACTOR - 25.0)).abs() < 1e-9);
+        assert_eq!(downcoded.adjustments()[0].code, "CO-150");
+        assert!(!remit.is_denied());
+        assert!(remit.validate_against_claim(&claim).is_ok());
+    }
+
+    /// Test that remittance lines are matched to billed lines by id, whatever their order.
+    /// Expected: Lines answered in reverse order still balance; a missing and an unbilled line are both named, and a claim
+    /// answered without one of its lines cannot be built.
+    #[test]
+    fn test_match_lines_by_id() {
+        let mut claim = mock_claim();
+        let mut second = claim.service_lines[0].clone();
+        second.service_line_id = "sl2".to_string();
+        second.unit_charge_amount = 80.0;
+        claim.service_lines.push(second);
+        claim.total_charge_amount = None;
+        let mut remit = Remittance::from_claim(&claim, mock_adjudication_date());
+        remit.service_line_remittances.reverse();
+        assert!(remit.validate_against_claim(&claim).is_ok());
+        assert_eq!(remit.match_lines(&claim).matched[0].0.service_line_id(), "sl1");
+        assert!(Remittance::new(&claim, remit.service_line_remittances.clone()).is_ok());
+
+        remit.service_line_remittances[0].service_line_id = "sl9".to_string();
+        let lines = remit.match_lines(&claim);
+        assert_eq!(lines.unmatched_ids(), ["sl2", "sl9"]);
+        let err = remit.validate_against_claim(&claim).unwrap_err();
+        assert_eq!(err, "Unmatched lines: service lines sl2 not remitted; remittance lines sl9 not billed");
+        remit.service_line_remittances.remove(0);
+        assert!(Remittance::new(&claim, remit.service_line_remittances).unwrap_err().to_string().contains("sl2 not remitted"));
+    }
+
+    /// Test that the remittance builder prices lines not set and checks the lines set against the claim.
+    /// Expected: Paying one of two lines keeps the other as priced by default; a denial takes its reason; a line paying
+    /// more than its charge or naming no billed line is refused.
+    #[test]
+    fn test_remittance_builder() {
+        let claim = ClaimBuilder::new().lines(2).build();
+        let remittance = Remittance::builder(&claim, mock_adjudication_date()).pay_line("sl1", 100.0, 20.0).build().unwrap();
+        assert_eq!(remittance.service_line_remittances()[0].payer_paid_amount(), 100.0);
+        assert_eq!(remittance.service_line_remittances()[0].not_allowed_amount(), 30.0);
+        let default = Remittance::from_claim(&claim, mock_adjudication_date());
+        assert_eq!(remittance.service_line_remittances()[1].payer_paid_amount(), default.service_line_remittances()[1].payer_paid_amount());
+
+        let denied = Remittance::builder(&claim, mock_adjudication_date()).deny(Some(DenialReason::MedicalNecessity)).build().unwrap();
+        assert!(denied.is_denied() && denied.denial_reason() == Some(DenialReason::MedicalNecessity));
+        let partly = Remittance::builder(&claim, mock_adjudication_date()).deny_line("sl2", DenialReason::MedicalNecessity).build().unwrap();
+        assert!(!partly.is_denied() && partly.service_line_remittances()[1].denial_reason() == Some(DenialReason::MedicalNecessity));
+
+        let err = Remittance::builder(&claim, mock_adjudication_date()).pay_line("sl1", 200.0, 0.0).pay_line("sl9", 1.0, 0.0).build().unwrap_err().to_string();
+        assert!(err.contains("not allowed amount -50") && err.contains("sl9 is not on the claim"), "{}", err);
     }
 }
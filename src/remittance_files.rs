@@ -0,0 +1,252 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::clock::SimClock;
+use crate::ids::{ClaimId, MemberId, PayerId};
+use crate::ledger::Ledger;
+use crate::phi::Deidentification;
+use crate::posting::{self, WriteOffRules};
+use crate::remittance::{Balancing, Remittance};
+use crate::schema::PayerClaim;
+
+/// Directory remittance files are written to inside a run directory, when no other is given
+pub const REMITTANCE_FILES_DIR: &str = "remittances";
+
+/// One claim payment in a remittance file: the remittance, the tenant that billed the claim, and
+/// what posting needs of the claim, as an 835's claim payment loop carries the claim's charges
+///
+/// The patient appears only by member id, so the files hold no demographics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemittanceFileEntry {
+    pub tenant_id: String,
+    pub payer_id: PayerId,
+    /// Simulated day the clearinghouse received the remittance, the file's date
+    pub date: NaiveDate,
+    pub claim_id: ClaimId,
+    /// Patient's member id, pseudonymous under `Deidentification::Pseudonymize`
+    pub member_id: MemberId,
+    /// Billed charges across the claim's service lines
+    pub charge: f64,
+    /// Claim a corrected claim replaces, whose postings it reverses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaces: Option<ClaimId>,
+    pub remittance: Remittance,
+}
+
+enum FileCommand {
+    Write(Box<RemittanceFileEntry>),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Remittances the clearinghouse receives, written to a JSONL file per payer per simulated day,
+/// `<dir>/<payer>/<YYYY-MM-DD>.jsonl`, as payers send a daily 835
+///
+/// A single writer task appends to them, so recording a remittance never waits on the disk
+#[derive(Debug, Clone)]
+pub struct RemittanceFiles {
+    dir: PathBuf,
+    clock: SimClock,
+    deidentification: Deidentification,
+    tx: mpsc::UnboundedSender<FileCommand>,
+}
+
+impl RemittanceFiles {
+    /// Write files under `dir`, dated by `clock`, and start their writer task
+    pub fn create(dir: impl Into<PathBuf>, clock: SimClock) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).with_context(|| format!("creating remittance files directory {}", dir.display()))?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(write_entries(dir.clone(), rx));
+        Ok(Self { dir, clock, deidentification: Deidentification::Off, tx })
+    }
+
+    /// Pseudonymize member ids before they are written
+    pub fn with_deidentification(mut self, deidentification: Deidentification) -> Self {
+        self.deidentification = deidentification;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Queue a remittance for its payer's file for today; never blocks the caller
+    pub fn write(&self, tenant_id: &str, claim: &PayerClaim, remittance: &Remittance) {
+        let entry = RemittanceFileEntry {
+            tenant_id: tenant_id.to_string(),
+            payer_id: claim.insurance.payer_id.clone(),
+            date: self.clock.now().date(),
+            claim_id: claim.claim_id.clone(),
+            member_id: self.deidentification.member_id(&claim.insurance.patient_member_id),
+            charge: claim.total_charge(),
+            replaces: claim.replaces().cloned(),
+            remittance: remittance.clone(),
+        };
+        if self.tx.send(FileCommand::Write(Box::new(entry))).is_err() {
+            eprintln!("Remittance file writer stopped");
+        }
+    }
+
+    /// Wait until every remittance queued so far is written to disk
+    pub async fn flush(&self) {
+        let (done_tx, done_rx) = oneshot::channel();
+        if self.tx.send(FileCommand::Flush(done_tx)).is_ok() {
+            let _ = done_rx.await;
+        }
+    }
+}
+
+/// Append each queued entry to its payer's file for its day, starting a new file on a new day
+async fn write_entries(dir: PathBuf, mut rx: mpsc::UnboundedReceiver<FileCommand>) {
+    // each payer's file for the day being written
+    let mut open: HashMap<PayerId, (NaiveDate, File)> = HashMap::new();
+    while let Some(command) = rx.recv().await {
+        match command {
+            FileCommand::Write(entry) => {
+                if let Err(e) = append(&dir, &mut open, &entry).await {
+                    eprintln!("Failed to write remittance file entry for claim {}: {:#}", entry.claim_id, e);
+                }
+            }
+            FileCommand::Flush(done) => {
+                for (_, file) in open.values_mut() {
+                    let _ = file.flush().await;
+                }
+                let _ = done.send(());
+            }
+        }
+    }
+    for (_, file) in open.values_mut() {
+        let _ = file.flush().await;
+    }
+}
+
+async fn append(dir: &Path, open: &mut HashMap<PayerId, (NaiveDate, File)>, entry: &RemittanceFileEntry) -> anyhow::Result<()> {
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    if open.get(&entry.payer_id).is_none_or(|(day, _)| *day != entry.date) {
+        let payer_dir = dir.join(entry.payer_id.as_str());
+        tokio::fs::create_dir_all(&payer_dir).await?;
+        let path = payer_dir.join(format!("{}.jsonl", entry.date));
+        let file = OpenOptions::new().create(true).append(true).open(&path).await.with_context(|| format!("opening {}", path.display()))?;
+        if let Some((_, mut previous)) = open.insert(entry.payer_id.clone(), (entry.date, file)) {
+            previous.flush().await?;
+        }
+    }
+    let (_, file) = open.get_mut(&entry.payer_id).expect("opened above");
+    file.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+/// Every remittance in the files under `dir`, by date, then payer, then file order
+///
+/// Lines that do not parse are reported and skipped
+pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Vec<RemittanceFileEntry>> {
+    let dir = dir.as_ref();
+    let mut files = Vec::new();
+    for payer_dir in fs::read_dir(dir).with_context(|| format!("reading remittance files directory {}", dir.display()))? {
+        let payer_dir = payer_dir?.path();
+        if !payer_dir.is_dir() {
+            continue;
+        }
+        for file in fs::read_dir(&payer_dir)? {
+            let path = file?.path();
+            let date = path.file_stem().and_then(|stem| stem.to_str()).and_then(|stem| stem.parse::<NaiveDate>().ok());
+            if let Some(date) = date.filter(|_| path.extension().is_some_and(|ext| ext == "jsonl")) {
+                files.push((date, payer_dir.clone(), path));
+            }
+        }
+    }
+    files.sort();
+    let mut entries = Vec::new();
+    for (_, _, path) in files {
+        let contents = fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+        for (index, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => eprintln!("Skipping {}:{}: {}", path.display(), index + 1, e),
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Post remittances to a ledger per tenant as their billers would have on receiving them
+///
+/// Remittances held for review are left unposted, and a corrected claim reverses the postings of
+/// the claim it replaces first
+pub fn post(entries: &[RemittanceFileEntry], rules: &WriteOffRules) -> BTreeMap<String, Ledger> {
+    let mut ledgers: BTreeMap<String, Ledger> = BTreeMap::new();
+    for entry in entries {
+        if matches!(entry.remittance.balancing(), Balancing::Held(_)) {
+            continue;
+        }
+        let ledger = ledgers.entry(entry.tenant_id.clone()).or_default();
+        if let Some(original_id) = &entry.replaces {
+            ledger.reverse_claim(original_id);
+        }
+        posting::post_payment(ledger, &entry.claim_id, &entry.member_id, &entry.payer_id, entry.charge, &entry.remittance, rules);
+    }
+    ledgers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::EntryKind;
+    use crate::pseudonym::Pseudonymizer;
+    use crate::test_utils::{ClaimBuilder, mock_adjudication_date};
+    use chrono::Days;
+
+    /// Test that remittances are written to a file per payer per simulated day and post back from the files.
+    /// Expected: Two payers' remittances over two days make three files holding no patient names or dates of birth;
+    /// loading returns them by date, and posting them books each claim's charge in its tenant's ledger.
+    #[tokio::test]
+    async fn test_remittance_files_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let start = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let claim = |id: &str, payer: &str| ClaimBuilder::new().claim_id(id).payer(payer).build();
+        let write = |date: NaiveDate, tenant_id: &str, claim: PayerClaim| {
+            let files = RemittanceFiles::create(dir.path(), SimClock::new(date, 3600.0)).unwrap();
+            files.write(tenant_id, &claim, &Remittance::from_claim(&claim, mock_adjudication_date()));
+            files
+        };
+        write(start.checked_add_days(Days::new(1)).unwrap(), "acme", claim("c3", "medicare")).flush().await;
+        write(start, "acme", claim("c1", "medicare")).flush().await;
+        write(start, "beta", claim("c2", "anthem")).flush().await;
+        let file = dir.path().join("medicare").join("2026-03-03.jsonl");
+        let contents = fs::read_to_string(file).unwrap();
+        assert!(!contents.contains("Jane") && !contents.contains("1990-01-01"), "{}", contents);
+
+        let entries = load(dir.path()).unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.claim_id.as_str()).collect::<Vec<_>>(), ["c2", "c1", "c3"]);
+        let ledgers = post(&entries, &WriteOffRules::default());
+        let charges = |tenant_id: &str| ledgers[tenant_id].entries().iter().filter(|entry| entry.kind == EntryKind::Charge).count();
+        assert_eq!((charges("acme"), charges("beta")), (2, 1));
+    }
+
+    /// Test that a run pseudonymizing patients writes pseudonymous member ids to its remittance files.
+    /// Expected: The entry's member id is the pseudonymizer's for the claim's member, not the member id billed.
+    #[tokio::test]
+    async fn test_remittance_files_pseudonymize() {
+        let dir = tempfile::tempdir().unwrap();
+        let pseudonymizer = Pseudonymizer::new("key");
+        let files = RemittanceFiles::create(dir.path(), SimClock::default())
+            .unwrap()
+            .with_deidentification(Deidentification::Pseudonymize(pseudonymizer.clone()));
+        let claim = ClaimBuilder::new().build();
+        files.write("acme", &claim, &Remittance::from_claim(&claim, mock_adjudication_date()));
+        files.flush().await;
+
+        let entries = load(dir.path()).unwrap();
+        assert_eq!(entries[0].member_id, pseudonymizer.member_id(&claim.insurance.patient_member_id));
+        assert_ne!(entries[0].member_id, claim.insurance.patient_member_id);
+    }
+}
@@ -0,0 +1,258 @@
+use serde::Serialize;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::Write;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use anyhow::anyhow;
+use rusqlite::{Connection, params};
+
+use crate::ids::{ClaimId, PayerId};
+use crate::posting;
+use crate::remittance::Remittance;
+use crate::schema::PayerClaim;
+
+/// Downstream system told about every remittance a biller receives, such as a
+/// practice-management system
+///
+/// Register one with `BillerSpec::with_sink`. It sees remittances held for review too, marked by
+/// their `balancing`, but not duplicates, rejections, or voids. A sink that fails is logged
+/// and the biller carries on
+pub trait RemittanceSink: Send + Sync + 'static {
+    fn receive(&self, tenant_id: &str, claim: &PayerClaim, remittance: &Remittance) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// `RemittanceSink` with its future boxed, so sinks of any type can be shared behind a pointer
+trait DynRemittanceSink: Send + Sync {
+    fn receive<'a>(&'a self, tenant_id: &'a str, claim: &'a PayerClaim, remittance: &'a Remittance) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+impl<S: RemittanceSink> DynRemittanceSink for S {
+    fn receive<'a>(&'a self, tenant_id: &'a str, claim: &'a PayerClaim, remittance: &'a Remittance) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(RemittanceSink::receive(self, tenant_id, claim, remittance))
+    }
+}
+
+/// A sink shared by every remittance listener of the billers feeding it
+#[derive(Clone)]
+pub struct SharedRemittanceSink(Arc<dyn DynRemittanceSink>);
+
+impl SharedRemittanceSink {
+    pub fn new(sink: impl RemittanceSink) -> Self {
+        Self(Arc::new(sink))
+    }
+
+    pub async fn receive(&self, tenant_id: &str, claim: &PayerClaim, remittance: &Remittance) -> anyhow::Result<()> {
+        self.0.receive(tenant_id, claim, remittance).await
+    }
+}
+
+impl<S: RemittanceSink> From<S> for SharedRemittanceSink {
+    fn from(sink: S) -> Self {
+        Self::new(sink)
+    }
+}
+
+impl fmt::Debug for SharedRemittanceSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedRemittanceSink")
+    }
+}
+
+/// What file and webhook sinks send for each remittance
+#[derive(Debug, Serialize)]
+pub struct Delivery<'a> {
+    pub tenant_id: &'a str,
+    pub claim_id: &'a ClaimId,
+    pub payer_id: &'a PayerId,
+    pub remittance: &'a Remittance,
+}
+
+impl<'a> Delivery<'a> {
+    pub fn new(tenant_id: &'a str, claim: &'a PayerClaim, remittance: &'a Remittance) -> Self {
+        Self {
+            tenant_id,
+            claim_id: &claim.claim_id,
+            payer_id: &claim.insurance.payer_id,
+            remittance,
+        }
+    }
+}
+
+/// Appends each remittance to a JSONL file, one `Delivery` per line
+#[derive(Debug)]
+pub struct FileSink {
+    file: Mutex<File>,
+}
+
+impl FileSink {
+    /// Append to `path`, creating it if needed
+    pub fn open(path: &str) -> anyhow::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file: Mutex::new(file) })
+    }
+}
+
+impl RemittanceSink for FileSink {
+    async fn receive(&self, tenant_id: &str, claim: &PayerClaim, remittance: &Remittance) -> anyhow::Result<()> {
+        let line = serde_json::to_string(&Delivery::new(tenant_id, claim, remittance))?;
+        writeln!(self.file.lock().unwrap(), "{}", line)?;
+        Ok(())
+    }
+}
+
+/// POSTs each remittance as a JSON `Delivery` to a URL over plain HTTP
+#[derive(Debug, Clone)]
+pub struct WebhookSink {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl RemittanceSink for WebhookSink {
+    async fn receive(&self, tenant_id: &str, claim: &PayerClaim, remittance: &Remittance) -> anyhow::Result<()> {
+        self.client
+            .post(&self.url)
+            .json(&Delivery::new(tenant_id, claim, remittance))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Inserts each remittance into the `remittances` table of a SQLite database, kept across runs
+#[derive(Debug)]
+pub struct DatabaseSink {
+    conn: Mutex<Connection>,
+}
+
+impl DatabaseSink {
+    /// Open or create the database at `path` and its `remittances` table
+    pub fn open(path: &str) -> anyhow::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS remittances (
+                tenant_id TEXT NOT NULL,
+                claim_id TEXT NOT NULL,
+                payer_id TEXT NOT NULL,
+                payer_paid REAL NOT NULL,
+                patient_responsibility REAL NOT NULL,
+                denied INTEGER NOT NULL,
+                remittance TEXT NOT NULL
+            );",
+        )?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+}
+
+impl RemittanceSink for DatabaseSink {
+    async fn receive(&self, tenant_id: &str, claim: &PayerClaim, remittance: &Remittance) -> anyhow::Result<()> {
+        let json = serde_json::to_string(remittance)?;
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO remittances VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                tenant_id,
+                claim.claim_id.as_str(),
+                claim.insurance.payer_id.as_str(),
+                remittance.payer_paid(),
+                posting::patient_responsibility(remittance),
+                remittance.is_denied(),
+                json,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+/// A sink named on the command line as `file=<path>`, `webhook=<url>`, or `sqlite=<path>`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SinkSpec {
+    File(String),
+    Webhook(String),
+    Database(String),
+}
+
+impl SinkSpec {
+    pub fn open(&self) -> anyhow::Result<SharedRemittanceSink> {
+        Ok(match self {
+            SinkSpec::File(path) => FileSink::open(path)?.into(),
+            SinkSpec::Webhook(url) => WebhookSink::new(url).into(),
+            SinkSpec::Database(path) => DatabaseSink::open(path)?.into(),
+        })
+    }
+}
+
+impl FromStr for SinkSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, target) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Remittance sink {} is not kind=target", s))?;
+        let target = target.to_string();
+        match kind.trim() {
+            "file" => Ok(SinkSpec::File(target)),
+            "webhook" => Ok(SinkSpec::Webhook(target)),
+            "sqlite" => Ok(SinkSpec::Database(target)),
+            other => Err(anyhow!("Unknown remittance sink: {} (expected file, webhook, or sqlite)", other)),
+        }
+    }
+}
+
+impl fmt::Display for SinkSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SinkSpec::File(path) => write!(f, "file={}", path),
+            SinkSpec::Webhook(url) => write!(f, "webhook={}", url),
+            SinkSpec::Database(path) => write!(f, "sqlite={}", path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{mock_claim, mock_remittance};
+
+    /// Test that the file and database sinks record each remittance delivered to them.
+    /// Expected: One JSONL line naming the tenant and claim; one database row with the remittance's payment.
+    #[tokio::test]
+    async fn test_file_and_database_sinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let claim = mock_claim();
+        let remittance = mock_remittance();
+        let file_path = dir.path().join("remits.jsonl").to_string_lossy().to_string();
+        let db_path = dir.path().join("remits.db").to_string_lossy().to_string();
+        for spec in [SinkSpec::File(file_path.clone()), SinkSpec::Database(db_path.clone())] {
+            spec.open().unwrap().receive("acme", &claim, &remittance).await.unwrap();
+        }
+
+        let contents = std::fs::read_to_string(&file_path).unwrap();
+        let [line] = contents.lines().collect::<Vec<_>>()[..] else { panic!("Expected one line, got {}", contents) };
+        let delivery: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(delivery["tenant_id"], "acme");
+        assert_eq!(delivery["claim_id"], claim.claim_id.as_str());
+
+        let conn = Connection::open(&db_path).unwrap();
+        let (tenant_id, payer_paid): (String, f64) = conn
+            .query_row("SELECT tenant_id, payer_paid FROM remittances", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!(tenant_id, "acme");
+        assert_eq!(payer_paid, remittance.payer_paid());
+        assert!("ftp=x".parse::<SinkSpec>().is_err());
+    }
+}
@@ -0,0 +1,1341 @@
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::clock::SimClock;
+use crate::forecast::PaymentHistory;
+use crate::kpi;
+use crate::message::{ClaimStatus, Stage};
+use crate::remittance::RemittanceRecord;
+use crate::schema::PayerClaim;
+use crate::phi::Deidentification;
+use crate::priority::Priority;
+use crate::ids::{ClaimId, ClaimKey, MemberId, Npi, PayerId};
+use crate::report_format::MoneyFields;
+use crate::sla::PayerSla;
+
+/// Column labels for the AR aging buckets, in bucket order
+pub const AGING_BUCKET_LABELS: [&str; 4] = ["0–1m", "1–2m", "2–3m", "3+m"];
+
+/// Outstanding claim counts for one payer by age bucket
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PayerAging {
+    pub payer_id: PayerId,
+    pub buckets: [u32; 4],
+}
+
+impl PayerAging {
+    pub fn total(&self) -> u32 {
+        self.buckets.iter().sum()
+    }
+}
+
+/// Patient cost sharing on remitted claims
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct PatientSummary {
+    pub patient_id: MemberId,
+    pub copay: f64,
+    pub coinsurance: f64,
+    pub deductible: f64,
+}
+
+/// How quickly a payer turns claims around, from submission to remittance
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct PayerTurnaround {
+    pub payer_id: PayerId,
+    pub remitted: u32,
+    pub avg_secs: f64,
+    pub max_secs: f64,
+}
+
+/// How long a payer takes to acknowledge claims and how long to pay them, each from submission
+///
+/// Acknowledgments count claims still waiting on their payment as well as those remitted
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct PayerLag {
+    pub payer_id: PayerId,
+    pub acknowledged: u32,
+    pub ack_avg_secs: f64,
+    pub ack_p50_secs: f64,
+    pub ack_p95_secs: f64,
+    pub ack_max_secs: f64,
+    pub remitted: u32,
+    pub payment_avg_secs: f64,
+    pub payment_p50_secs: f64,
+    pub payment_p95_secs: f64,
+    pub payment_max_secs: f64,
+}
+
+/// How well a payer is meeting its SLA, counting claims adjudicated on time, late, and not yet
+/// adjudicated but already past the deadline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SlaCompliance {
+    pub payer_id: PayerId,
+    pub target: f64,
+    pub within_days: f64,
+    pub on_time: u32,
+    pub late: u32,
+    /// Still with the payer past the deadline: late whenever they are adjudicated
+    pub overdue: u32,
+    /// Share of counted claims on time; `None` until a claim is counted
+    pub compliance: Option<f64>,
+    pub breached: bool,
+}
+
+/// Remittance latency for one priority class
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PriorityLatency {
+    pub priority: Priority,
+    pub remitted: u32,
+    pub avg_secs: f64,
+    pub max_secs: f64,
+}
+
+/// Time claims spent between two consecutive pipeline stages
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StageLatency {
+    pub from: Stage,
+    pub to: Stage,
+    pub remitted: u32,
+    pub avg_secs: f64,
+    pub p50_secs: f64,
+    pub p95_secs: f64,
+    pub p99_secs: f64,
+    pub max_secs: f64,
+}
+
+/// Claim counts and dollars for one billing organization
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct TenantSummary {
+    pub tenant_id: String,
+    pub pending: u32,
+    pub remitted: u32,
+    /// Cancelled before remittance; not counted as billed
+    pub voided: u32,
+    /// Remitted, then superseded by a replacement claim; not counted as billed or paid
+    pub replaced: u32,
+    pub billed: f64,
+    pub payer_paid: f64,
+}
+
+/// Volume and dollars for one rendering provider
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ProviderSummary {
+    pub npi: Npi,
+    pub name: String,
+    pub claims: u32,
+    pub remitted: u32,
+    pub denied: u32,
+    pub charges: f64,
+    pub payer_paid: f64,
+}
+
+impl ProviderSummary {
+    /// Share of remitted claims that were denied
+    pub fn denial_rate(&self) -> f64 {
+        if self.remitted == 0 {
+            0.0
+        } else {
+            self.denied as f64 / self.remitted as f64
+        }
+    }
+}
+
+/// Billed vs paid vs adjusted dollars for one procedure code
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ProcedureSummary {
+    pub procedure_code: String,
+    pub lines: u32,
+    pub units: u32,
+    pub billed: f64,
+    pub payer_paid: f64,
+    pub patient_responsibility: f64,
+    pub adjusted: f64,
+}
+
+/// Whether care was delivered by telehealth or in person
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CareSetting {
+    InPerson,
+    Telehealth,
+}
+
+impl fmt::Display for CareSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CareSetting::InPerson => write!(f, "In person"),
+            CareSetting::Telehealth => write!(f, "Telehealth"),
+        }
+    }
+}
+
+/// Remitted service line dollars for one care setting
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TelehealthMix {
+    pub setting: CareSetting,
+    pub lines: u32,
+    pub billed: f64,
+    pub payer_paid: f64,
+    pub patient_responsibility: f64,
+}
+
+impl TelehealthMix {
+    /// What the provider collects for the lines, from the payer and the patient
+    pub fn revenue(&self) -> f64 {
+        self.payer_paid + self.patient_responsibility
+    }
+}
+
+/// A claim split to meet its payer's service line limit, its children's totals reassembled
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SplitClaim {
+    pub parent_claim_id: ClaimId,
+    pub payer_id: PayerId,
+    pub children: u32,
+    /// Children remitted so far; the parent is settled once all are
+    pub remitted: u32,
+    /// Charges across every child
+    pub billed: f64,
+    /// Paid across the remitted children
+    pub payer_paid: f64,
+    pub patient_responsibility: f64,
+}
+
+impl SplitClaim {
+    pub fn is_settled(&self) -> bool {
+        self.remitted == self.children
+    }
+}
+
+/// Remittance that failed the payer's balancing check or answers lines other than those billed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RemittanceException {
+    pub claim_id: ClaimId,
+    pub payer_id: PayerId,
+    pub reason: String,
+    /// Held in the exceptions queue rather than posted with a flag
+    pub held: bool,
+    /// Billed lines the remittance does not answer, then remittance lines for nothing billed
+    pub unmatched_lines: Vec<String>,
+}
+
+/// Outcome of the clearinghouse's audit of one payer's remittances
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct PayerReconciliation {
+    pub payer_id: PayerId,
+    pub audited: u32,
+    /// Remittances with at least one discrepancy
+    pub discrepant: u32,
+    pub discrepancies: u32,
+    /// Remitted minus billed dollars across all discrepancies
+    pub variance: f64,
+}
+
+impl MoneyFields for PatientSummary {
+    fn map_money(&mut self, f: impl Fn(f64) -> f64) {
+        self.copay = f(self.copay);
+        self.coinsurance = f(self.coinsurance);
+        self.deductible = f(self.deductible);
+    }
+}
+
+impl MoneyFields for TenantSummary {
+    fn map_money(&mut self, f: impl Fn(f64) -> f64) {
+        self.billed = f(self.billed);
+        self.payer_paid = f(self.payer_paid);
+    }
+}
+
+impl MoneyFields for ProviderSummary {
+    fn map_money(&mut self, f: impl Fn(f64) -> f64) {
+        self.charges = f(self.charges);
+        self.payer_paid = f(self.payer_paid);
+    }
+}
+
+impl MoneyFields for ProcedureSummary {
+    fn map_money(&mut self, f: impl Fn(f64) -> f64) {
+        self.billed = f(self.billed);
+        self.payer_paid = f(self.payer_paid);
+        self.patient_responsibility = f(self.patient_responsibility);
+        self.adjusted = f(self.adjusted);
+    }
+}
+
+impl MoneyFields for TelehealthMix {
+    fn map_money(&mut self, f: impl Fn(f64) -> f64) {
+        self.billed = f(self.billed);
+        self.payer_paid = f(self.payer_paid);
+        self.patient_responsibility = f(self.patient_responsibility);
+    }
+}
+
+impl MoneyFields for SplitClaim {
+    fn map_money(&mut self, f: impl Fn(f64) -> f64) {
+        self.billed = f(self.billed);
+        self.payer_paid = f(self.payer_paid);
+        self.patient_responsibility = f(self.patient_responsibility);
+    }
+}
+
+impl MoneyFields for PayerReconciliation {
+    fn map_money(&mut self, f: impl Fn(f64) -> f64) {
+        self.variance = f(self.variance);
+    }
+}
+
+/// What a settled claim counted toward the totals, so an update repeating it is not counted twice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Settled {
+    Remitted { replaced: bool },
+    Voided,
+}
+
+/// Running totals of claim history that the reports are read from
+///
+/// Claims still with their payers are kept whole, for the reports that age them. A remitted or
+/// voided claim is only counted into the per-tenant, per-payer, per-patient, and per-code totals,
+/// leaving its key and outcome behind. The totals are built from a snapshot of history
+/// (`from_records`) or kept current one update at a time (`apply`), so a subscriber following
+/// history's updates need not keep a copy of it
+#[derive(Debug, Clone, Default)]
+pub struct HistoryTotals {
+    pending: HashMap<ClaimKey, ClaimStatus>,
+    settled: HashMap<ClaimKey, Settled>,
+    /// Settled claims only; pending ones are added as the summary is read
+    tenants: BTreeMap<String, TenantSummary>,
+    /// Remitted claims in force per patient, by real member id
+    patients: BTreeMap<MemberId, (u32, PatientSummary)>,
+    /// Submission to remittance per payer
+    payments: BTreeMap<PayerId, LatencyStats>,
+    /// Submission to acknowledgment per payer, of remitted claims
+    acks: BTreeMap<PayerId, LatencyStats>,
+    /// Submission to adjudication per payer, or to remittance when the payer did not stamp it
+    adjudications: BTreeMap<PayerId, LatencyStats>,
+    priorities: BTreeMap<Priority, LatencyStats>,
+    stages: BTreeMap<(Stage, Stage), LatencyStats>,
+    /// Remitted claims only; pending ones are added as the summary is read
+    providers: BTreeMap<Npi, ProviderSummary>,
+    procedures: BTreeMap<String, ProcedureSummary>,
+    telehealth: BTreeMap<CareSetting, TelehealthMix>,
+    reconciliation: BTreeMap<PayerId, PayerReconciliation>,
+    exceptions: BTreeMap<ClaimKey, RemittanceException>,
+    /// Remitted children, replaced or not; pending ones are added as the splits are read
+    splits: BTreeMap<ClaimId, SplitClaim>,
+    /// What every remittance, replaced or not, shows of each payer's payments, to forecast from
+    paid: BTreeMap<PayerId, PaymentHistory>,
+    /// Remitted claims, replaced or not
+    remitted: u32,
+    /// Remitted claims in force paid on their only submission
+    first_pass: u32,
+}
+
+impl HistoryTotals {
+    /// Totals of every claim in `records`
+    pub fn from_records(records: &HashMap<ClaimKey, ClaimStatus>) -> Self {
+        let replaced = kpi::replaced_claims(records);
+        let mut totals = Self::default();
+        for (key, status) in records {
+            match status {
+                ClaimStatus::Submitted { .. } => {
+                    totals.pending.insert(key.clone(), status.clone());
+                }
+                ClaimStatus::Remitted(record) => {
+                    let replaced = replaced.contains(key) || record.replaced_by().is_some();
+                    totals.count_remitted(key, record, replaced, 1);
+                    totals.settled.insert(key.clone(), Settled::Remitted { replaced });
+                }
+                ClaimStatus::Voided { .. } => {
+                    totals.count_voided(key, 1);
+                    totals.settled.insert(key.clone(), Settled::Voided);
+                }
+            }
+        }
+        totals
+    }
+
+    /// Take a claim's new status into the totals in place of the one it had
+    ///
+    /// A claim stays settled once remitted or voided, so a pending status arriving for it (e.g.
+    /// an update from before the totals' snapshot) is ignored; a remittance linked to or released
+    /// from a replacement is counted again as replaced or in force
+    pub fn apply(&mut self, key: ClaimKey, status: ClaimStatus) {
+        let settled = self.settled.get(&key).copied();
+        let now_settled = match &status {
+            ClaimStatus::Submitted { .. } => {
+                if settled.is_none() {
+                    self.pending.insert(key, status);
+                }
+                return;
+            }
+            ClaimStatus::Remitted(record) => Settled::Remitted { replaced: record.replaced_by().is_some() },
+            ClaimStatus::Voided { .. } => Settled::Voided,
+        };
+        if settled == Some(now_settled) {
+            return;
+        }
+        match (settled, &status) {
+            (None, _) => {
+                self.pending.remove(&key);
+            }
+            // the remittance linked to or released from its replacement is the one counted before
+            (Some(Settled::Remitted { replaced }), ClaimStatus::Remitted(record)) => self.count_remitted(&key, record, replaced, -1),
+            (Some(Settled::Voided), _) => self.count_voided(&key, -1),
+            (Some(Settled::Remitted { .. }), _) => {
+                eprintln!("Claim {} voided after it was remitted; its remittance stays counted", key.claim_id);
+                return;
+            }
+        }
+        match &status {
+            ClaimStatus::Remitted(record) => self.count_remitted(&key, record, record.replaced_by().is_some(), 1),
+            _ => self.count_voided(&key, 1),
+        }
+        self.settled.insert(key, now_settled);
+    }
+
+    /// Claims still with their payers
+    pub fn pending(&self) -> impl Iterator<Item = (&ClaimKey, &ClaimStatus)> {
+        self.pending.iter()
+    }
+
+    /// Charges billed on claims pending or remitted and in force
+    pub(crate) fn gross_charges(&self) -> f64 {
+        let pending: f64 = self.pending.values().map(|status| status.claim().total_charge()).sum();
+        pending + self.tenants.values().map(|tenant| tenant.billed).sum::<f64>()
+    }
+
+    /// Remitted claims, replaced or not, and of those in force, how many were paid on their only submission
+    pub(crate) fn first_pass(&self) -> (u32, u32) {
+        (self.remitted, self.first_pass)
+    }
+
+    /// What remittances show of each payer's payments
+    pub(crate) fn payment_histories(&self) -> &BTreeMap<PayerId, PaymentHistory> {
+        &self.paid
+    }
+
+    fn count_voided(&mut self, key: &ClaimKey, sign: i32) {
+        let tenant = tenant_totals(&mut self.tenants, &key.tenant_id);
+        tally(&mut tenant.voided, sign);
+    }
+
+    /// Count a remittance in (`sign` 1) or out (-1) of the totals, as replaced or in force
+    fn count_remitted(&mut self, key: &ClaimKey, record: &RemittanceRecord, replaced: bool, sign: i32) {
+        let (claim, remittance) = (record.claim(), record.remittance());
+        let weight = sign as f64;
+        tally(&mut self.remitted, sign);
+        self.paid.entry(record.payer_id().clone()).or_default().tally(claim.total_charge(), remittance.payer_paid(), record.elapsed(), sign);
+        if let Some(parent_claim_id) = &claim.parent_claim_id {
+            let split = split_totals(&mut self.splits, parent_claim_id, claim);
+            tally(&mut split.children, sign);
+            tally(&mut split.remitted, sign);
+            split.billed += weight * claim.total_charge();
+            split.payer_paid += weight * remittance.payer_paid();
+            split.patient_responsibility += weight * remittance.service_line_remittances().iter().map(|line| line.patient_responsibility()).sum::<f64>();
+            if split.children == 0 {
+                self.splits.remove(parent_claim_id);
+            }
+        }
+        let tenant = tenant_totals(&mut self.tenants, &key.tenant_id);
+        if replaced {
+            tally(&mut tenant.replaced, sign);
+            return;
+        }
+        tally(&mut tenant.remitted, sign);
+        tenant.billed += weight * claim.total_charge();
+        tenant.payer_paid += weight * remittance.payer_paid();
+        if !remittance.is_denied() && claim.replaces().is_none() {
+            tally(&mut self.first_pass, sign);
+        }
+
+        let (patients, patient) = (&mut self.patients, record.patient_id());
+        let (claims, summary) = patients
+            .entry(patient.clone())
+            .or_insert_with(|| (0, PatientSummary { patient_id: patient.clone(), ..PatientSummary::default() }));
+        tally(claims, sign);
+        for line in remittance.service_line_remittances() {
+            summary.copay += weight * line.copay_amount();
+            summary.coinsurance += weight * line.coinsurance_amount();
+            summary.deductible += weight * line.deductible_amount();
+        }
+        if *claims == 0 {
+            patients.remove(patient);
+        }
+
+        let payer_id = record.payer_id();
+        tally_latency(&mut self.payments, payer_id, record.elapsed(), sign);
+        if let Some(ack) = record.metadata().and_then(|metadata| metadata.time_to(Stage::Acknowledged)) {
+            tally_latency(&mut self.acks, payer_id, ack, sign);
+        }
+        let adjudicated_at = remittance.adjudicated_at().unwrap_or(record.remitted_at());
+        tally_latency(&mut self.adjudications, payer_id, adjudicated_at.saturating_duration_since(record.submitted_at()), sign);
+        tally_latency(&mut self.priorities, &record.priority(), record.elapsed(), sign);
+        for (from, to, elapsed) in record.metadata().map(|metadata| metadata.stage_durations()).unwrap_or_default() {
+            tally_latency(&mut self.stages, &(from, to), elapsed, sign);
+        }
+
+        let provider = &claim.rendering_provider;
+        let totals = provider_totals(&mut self.providers, provider);
+        tally(&mut totals.claims, sign);
+        tally(&mut totals.remitted, sign);
+        totals.charges += weight * claim.total_charge();
+        totals.payer_paid += weight * remittance.payer_paid();
+        if remittance.is_denied() {
+            tally(&mut totals.denied, sign);
+        }
+        if totals.claims == 0 {
+            self.providers.remove(&provider.npi);
+        }
+
+        for line in &claim.service_lines {
+            let Some(remit) = remittance.service_line_remittances().iter().find(|remit| remit.service_line_id() == line.service_line_id) else {
+                continue;
+            };
+            let billed = line.unit_charge_amount * line.units as f64;
+            let totals = self.procedures.entry(line.procedure_code.clone()).or_insert_with(|| ProcedureSummary {
+                procedure_code: line.procedure_code.clone(),
+                ..ProcedureSummary::default()
+            });
+            tally(&mut totals.lines, sign);
+            totals.units = totals.units.saturating_add_signed(sign * line.units as i32);
+            totals.billed += weight * billed;
+            totals.payer_paid += weight * remit.payer_paid_amount();
+            totals.patient_responsibility += weight * remit.patient_responsibility();
+            totals.adjusted += weight * remit.not_allowed_amount();
+            if totals.lines == 0 {
+                self.procedures.remove(&line.procedure_code);
+            }
+
+            let setting = if claim.is_telehealth_line(line) { CareSetting::Telehealth } else { CareSetting::InPerson };
+            let totals = self.telehealth.entry(setting).or_insert(TelehealthMix {
+                setting,
+                lines: 0,
+                billed: 0.0,
+                payer_paid: 0.0,
+                patient_responsibility: 0.0,
+            });
+            tally(&mut totals.lines, sign);
+            totals.billed += weight * billed;
+            totals.payer_paid += weight * remit.payer_paid_amount();
+            totals.patient_responsibility += weight * remit.patient_responsibility();
+            if totals.lines == 0 {
+                self.telehealth.remove(&setting);
+            }
+        }
+
+        let totals = self.reconciliation.entry(payer_id.clone()).or_insert_with(|| PayerReconciliation {
+            payer_id: payer_id.clone(),
+            ..PayerReconciliation::default()
+        });
+        tally(&mut totals.audited, sign);
+        let discrepancies = record.discrepancies();
+        if !discrepancies.is_empty() {
+            tally(&mut totals.discrepant, sign);
+            totals.discrepancies = totals.discrepancies.saturating_add_signed(sign * discrepancies.len() as i32);
+            totals.variance += weight * discrepancies.iter().map(|d| d.variance).sum::<f64>();
+        }
+        if totals.audited == 0 {
+            self.reconciliation.remove(payer_id);
+        }
+
+        match sign > 0 {
+            true => {
+                if let Some(exception) = remittance_exception(record) {
+                    self.exceptions.insert(key.clone(), exception);
+                }
+            }
+            false => {
+                self.exceptions.remove(key);
+            }
+        }
+    }
+}
+
+/// Add `sign` (1 or -1) to a count
+fn tally(count: &mut u32, sign: i32) {
+    *count = count.saturating_add_signed(sign);
+}
+
+/// Add or take out a latency sample, dropping the stats once they hold none
+fn tally_latency<K: Ord + Clone>(stats: &mut BTreeMap<K, LatencyStats>, key: &K, elapsed: Duration, sign: i32) {
+    let entry = stats.entry(key.clone()).or_default();
+    entry.tally(elapsed, sign);
+    if entry.count == 0 {
+        stats.remove(key);
+    }
+}
+
+fn tenant_totals<'t>(tenants: &'t mut BTreeMap<String, TenantSummary>, tenant_id: &str) -> &'t mut TenantSummary {
+    tenants.entry(tenant_id.to_string()).or_insert_with(|| TenantSummary { tenant_id: tenant_id.to_string(), ..TenantSummary::default() })
+}
+
+fn provider_totals<'t>(providers: &'t mut BTreeMap<Npi, ProviderSummary>, provider: &crate::schema::Provider) -> &'t mut ProviderSummary {
+    providers.entry(provider.npi.clone()).or_insert_with(|| ProviderSummary {
+        npi: provider.npi.clone(),
+        name: format!("{} {}", provider.first_name, provider.last_name),
+        ..ProviderSummary::default()
+    })
+}
+
+fn split_totals<'t>(splits: &'t mut BTreeMap<ClaimId, SplitClaim>, parent_claim_id: &ClaimId, child: &PayerClaim) -> &'t mut SplitClaim {
+    splits.entry(parent_claim_id.clone()).or_insert_with(|| SplitClaim {
+        parent_claim_id: parent_claim_id.clone(),
+        payer_id: child.insurance.payer_id.clone(),
+        children: 0,
+        remitted: 0,
+        billed: 0.0,
+        payer_paid: 0.0,
+        patient_responsibility: 0.0,
+    })
+}
+
+/// The remittance as an exception, if it failed its balancing check or answers other lines than those billed
+fn remittance_exception(record: &RemittanceRecord) -> Option<RemittanceException> {
+    let balancing = record.remittance().balancing();
+    let lines = record.remittance().match_lines(record.claim());
+    let reason = balancing.reason().map(str::to_string).or_else(|| lines.unmatched_reason())?;
+    Some(RemittanceException {
+        claim_id: record.remittance().claim_id().clone(),
+        payer_id: record.payer_id().clone(),
+        reason,
+        held: balancing.is_held(),
+        unmatched_lines: lines.unmatched_ids(),
+    })
+}
+
+/// Aggregates claim history into typed reports
+///
+/// Frontends (console tables, CSV, the HTTP API) render these structs; the
+/// engine itself does no I/O. Results are sorted by their key for stable output.
+/// Reports are read from `HistoryTotals`, built from the records given or kept elsewhere
+pub struct ReportEngine<'a> {
+    totals: Cow<'a, HistoryTotals>,
+    now: Instant,
+    /// How rows per patient name the patient
+    deidentification: Deidentification,
+}
+
+impl<'a> ReportEngine<'a> {
+    pub fn new(records: &HashMap<ClaimKey, ClaimStatus>) -> Self {
+        Self::at(records, Instant::now())
+    }
+
+    /// Engine that ages outstanding claims as of `now`
+    pub fn at(records: &HashMap<ClaimKey, ClaimStatus>, now: Instant) -> Self {
+        Self { totals: Cow::Owned(HistoryTotals::from_records(records)), now, deidentification: Deidentification::Off }
+    }
+
+    /// Engine reading totals kept current elsewhere, e.g. by a subscriber to history's updates
+    pub fn of(totals: &'a HistoryTotals) -> Self {
+        Self { totals: Cow::Borrowed(totals), now: Instant::now(), deidentification: Deidentification::Off }
+    }
+
+    /// Engine naming patients by the member ids a de-identified run writes
+    pub fn with_deidentification(mut self, deidentification: Deidentification) -> Self {
+        self.deidentification = deidentification;
+        self
+    }
+
+    /// Outstanding claims per payer by age: under 1, 1–2, 2–3, and 3+ minutes
+    pub fn ar_aging(&self) -> Vec<PayerAging> {
+        let mut aging: BTreeMap<PayerId, [u32; 4]> = BTreeMap::new();
+        for (_, status) in self.totals.pending() {
+            if let ClaimStatus::Submitted { claim, submitted_at, .. } = status {
+                let age_secs = self.now.saturating_duration_since(*submitted_at).as_secs();
+                let bucket = match age_secs {
+                    0..=59 => 0,
+                    60..=119 => 1,
+                    120..=179 => 2,
+                    _ => 3,
+                };
+                aging.entry(claim.insurance.payer_id.clone()).or_insert([0; 4])[bucket] += 1;
+            }
+        }
+        aging
+            .into_iter()
+            .map(|(payer_id, buckets)| PayerAging { payer_id, buckets })
+            .collect()
+    }
+
+    /// Copay, coinsurance, and deductible totals per patient on remitted claims
+    pub fn patient_summary(&self) -> Vec<PatientSummary> {
+        let mut summary: BTreeMap<MemberId, PatientSummary> = BTreeMap::new();
+        for (_, totals) in self.totals.patients.values() {
+            let patient_id = self.deidentification.member_id(&totals.patient_id);
+            let entry = summary
+                .entry(patient_id.clone())
+                .or_insert_with(|| PatientSummary {
+                    patient_id,
+                    ..PatientSummary::default()
+                });
+            entry.copay += totals.copay;
+            entry.coinsurance += totals.coinsurance;
+            entry.deductible += totals.deductible;
+        }
+        summary.into_values().collect()
+    }
+
+    /// Average and worst submission-to-remittance time per payer
+    pub fn payer_turnaround(&self) -> Vec<PayerTurnaround> {
+        self.totals
+            .payments
+            .iter()
+            .map(|(payer_id, stat)| PayerTurnaround {
+                payer_id: payer_id.clone(),
+                remitted: stat.count,
+                avg_secs: stat.average().as_secs_f64(),
+                max_secs: stat.max.as_secs_f64(),
+            })
+            .collect()
+    }
+
+    /// Distribution of submission-to-acknowledgment and submission-to-remittance time per payer
+    ///
+    /// Claims without hop metadata (e.g. replayed from an event log) count toward payment lag only
+    pub fn payer_lag(&self) -> Vec<PayerLag> {
+        let mut acks = self.totals.acks.clone();
+        for (_, status) in self.totals.pending() {
+            if let ClaimStatus::Submitted { claim, metadata, .. } = status
+                && let Some(ack) = metadata.time_to(Stage::Acknowledged)
+            {
+                tally_latency(&mut acks, &claim.insurance.payer_id, ack, 1);
+            }
+        }
+        let mut lags: BTreeMap<PayerId, PayerLag> = BTreeMap::new();
+        for (payer_id, stat) in acks {
+            lags.insert(payer_id.clone(), PayerLag {
+                payer_id,
+                acknowledged: stat.count,
+                ack_avg_secs: stat.average().as_secs_f64(),
+                ack_p50_secs: stat.percentile(50).as_secs_f64(),
+                ack_p95_secs: stat.percentile(95).as_secs_f64(),
+                ack_max_secs: stat.max.as_secs_f64(),
+                ..PayerLag::default()
+            });
+        }
+        for (payer_id, stat) in &self.totals.payments {
+            let lag = lags.entry(payer_id.clone()).or_insert_with(|| PayerLag { payer_id: payer_id.clone(), ..PayerLag::default() });
+            lag.remitted = stat.count;
+            lag.payment_avg_secs = stat.average().as_secs_f64();
+            lag.payment_p50_secs = stat.percentile(50).as_secs_f64();
+            lag.payment_p95_secs = stat.percentile(95).as_secs_f64();
+            lag.payment_max_secs = stat.max.as_secs_f64();
+        }
+        lags.into_values().collect()
+    }
+
+    /// Compliance of each payer with its SLA, measuring days on the simulation clock
+    ///
+    /// A claim counts from submission to adjudication, or to remittance when the payer did not
+    /// stamp when it adjudicated
+    pub fn sla_compliance(&self, slas: &BTreeMap<PayerId, PayerSla>, clock: &SimClock) -> Vec<SlaCompliance> {
+        let mut counts: BTreeMap<&PayerId, (u32, u32, u32)> = slas.keys().map(|payer_id| (payer_id, (0, 0, 0))).collect();
+        for (payer_id, (on_time, late, _)) in counts.iter_mut() {
+            let Some(stat) = self.totals.adjudications.get(*payer_id) else {
+                continue;
+            };
+            *on_time = stat.count_where(|elapsed| clock.days(elapsed) <= slas[*payer_id].within_days);
+            *late = stat.count - *on_time;
+        }
+        for (_, status) in self.totals.pending() {
+            if let ClaimStatus::Submitted { claim, submitted_at, .. } = status
+                && let (Some(sla), Some((_, _, overdue))) = (slas.get(&claim.insurance.payer_id), counts.get_mut(&claim.insurance.payer_id))
+                && clock.days(self.now.saturating_duration_since(*submitted_at)) > sla.within_days
+            {
+                *overdue += 1;
+            }
+        }
+        counts
+            .into_iter()
+            .map(|(payer_id, (on_time, late, overdue))| {
+                let sla = slas[payer_id];
+                let counted = on_time + late + overdue;
+                let compliance = (counted > 0).then(|| on_time as f64 / counted as f64);
+                SlaCompliance {
+                    payer_id: payer_id.clone(),
+                    target: sla.target,
+                    within_days: sla.within_days,
+                    on_time,
+                    late,
+                    overdue,
+                    compliance,
+                    breached: compliance.is_some_and(|compliance| compliance < sla.target),
+                }
+            })
+            .collect()
+    }
+
+    /// Average and worst remittance latency per priority class, high priority first
+    pub fn latency_by_priority(&self) -> Vec<PriorityLatency> {
+        self.totals
+            .priorities
+            .iter()
+            .rev()
+            .map(|(priority, stat)| PriorityLatency {
+                priority: *priority,
+                remitted: stat.count,
+                avg_secs: stat.average().as_secs_f64(),
+                max_secs: stat.max.as_secs_f64(),
+            })
+            .collect()
+    }
+
+    /// Average and worst time between consecutive hops of remitted claims, in pipeline order
+    ///
+    /// Claims without hop metadata (e.g. replayed from an event log) are skipped
+    pub fn stage_latency(&self) -> Vec<StageLatency> {
+        self.totals
+            .stages
+            .iter()
+            .map(|((from, to), stat)| StageLatency {
+                from: *from,
+                to: *to,
+                remitted: stat.count,
+                avg_secs: stat.average().as_secs_f64(),
+                p50_secs: stat.percentile(50).as_secs_f64(),
+                p95_secs: stat.percentile(95).as_secs_f64(),
+                p99_secs: stat.percentile(99).as_secs_f64(),
+                max_secs: stat.max.as_secs_f64(),
+            })
+            .collect()
+    }
+
+    /// Claim volume and dollars per tenant
+    pub fn tenant_summary(&self) -> Vec<TenantSummary> {
+        let mut summary = self.totals.tenants.clone();
+        for (key, status) in self.totals.pending() {
+            let totals = tenant_totals(&mut summary, &key.tenant_id);
+            totals.pending += 1;
+            totals.billed += status.claim().total_charge();
+        }
+        summary.into_values().collect()
+    }
+
+    /// Claims, charges, payments, and denials per rendering provider NPI
+    pub fn provider_summary(&self) -> Vec<ProviderSummary> {
+        let mut summary = self.totals.providers.clone();
+        for (_, status) in self.totals.pending() {
+            let claim = status.claim();
+            let totals = provider_totals(&mut summary, &claim.rendering_provider);
+            totals.claims += 1;
+            totals.charges += claim.total_charge();
+        }
+        summary.into_values().collect()
+    }
+
+    /// Remitted service line dollars per procedure code
+    ///
+    /// Remittance lines are matched to claim lines by service line id
+    pub fn procedure_summary(&self) -> Vec<ProcedureSummary> {
+        self.totals.procedures.values().cloned().collect()
+    }
+
+    /// Remitted service line dollars delivered by telehealth vs in person
+    ///
+    /// A line is telehealth by its claim's place of service or its own modifier 95 (see
+    /// `PayerClaim::is_telehealth_line`); remittance lines are matched by service line id
+    pub fn telehealth_mix(&self) -> Vec<TelehealthMix> {
+        self.totals.telehealth.values().cloned().collect()
+    }
+
+    /// Claims the biller split for their payer's line limit, by parent claim id
+    ///
+    /// Reassembles each parent from its children: every child's charges, and what was paid
+    /// on those remitted. Voided children are left out
+    pub fn split_claims(&self) -> Vec<SplitClaim> {
+        let mut parents = self.totals.splits.clone();
+        for (_, status) in self.totals.pending() {
+            let claim = status.claim();
+            let Some(parent_claim_id) = &claim.parent_claim_id else {
+                continue;
+            };
+            let totals = split_totals(&mut parents, parent_claim_id, claim);
+            totals.children += 1;
+            totals.billed += claim.total_charge();
+        }
+        parents.into_values().collect()
+    }
+
+    /// Clearinghouse audit results per payer over every remitted claim
+    pub fn reconciliation(&self) -> Vec<PayerReconciliation> {
+        self.totals.reconciliation.values().cloned().collect()
+    }
+
+    /// Remittances that did not balance against their claim, held or flagged, by claim id
+    ///
+    /// Remittance lines are matched to claim lines by service line id, so a remittance with
+    /// lines missing or not billed is listed even when its payer sent it as balanced
+    pub fn remittance_exceptions(&self) -> Vec<RemittanceException> {
+        let mut exceptions: Vec<RemittanceException> = self.totals.exceptions.values().cloned().collect();
+        exceptions.sort_by(|a, b| a.claim_id.cmp(&b.claim_id));
+        exceptions
+    }
+}
+
+/// Significant digits of microseconds latencies are bucketed to, keeping each percentile within
+/// 10% of the exact sample in memory that does not grow with the number of claims
+const LATENCY_SIGNIFICANT_DIGITS: u32 = 2;
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LatencyStats {
+    count: u32,
+    total: Duration,
+    max: Duration,
+    /// Samples counted by bucket, keyed by the bucket's lowest latency in microseconds
+    buckets: BTreeMap<u64, u32>,
+}
+
+impl LatencyStats {
+    /// Add (`sign` 1) or take out (-1) a sample
+    ///
+    /// Taking out the worst sample leaves the low end of the worst bucket left as the maximum
+    pub(crate) fn tally(&mut self, elapsed: Duration, sign: i32) {
+        let bucket = latency_bucket(elapsed);
+        let count = self.buckets.entry(bucket).or_default();
+        tally(count, sign);
+        if *count == 0 {
+            self.buckets.remove(&bucket);
+        }
+        tally(&mut self.count, sign);
+        if sign > 0 {
+            self.total += elapsed;
+            self.max = self.max.max(elapsed);
+        } else {
+            self.total = self.total.saturating_sub(elapsed);
+            if elapsed >= self.max {
+                self.max = self.buckets.keys().next_back().map_or(Duration::ZERO, |&bucket| Duration::from_micros(bucket));
+            }
+        }
+    }
+
+    /// Add every sample of `other`
+    pub(crate) fn merge(&mut self, other: &LatencyStats) {
+        self.count += other.count;
+        self.total += other.total;
+        self.max = self.max.max(other.max);
+        for (&bucket, &count) in &other.buckets {
+            *self.buckets.entry(bucket).or_default() += count;
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    fn average(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.count
+        }
+    }
+
+    /// Nearest-rank percentile of the samples, `pct` from 1 to 100, as the low end of its bucket
+    fn percentile(&self, pct: usize) -> Duration {
+        let rank = (pct * self.count as usize).div_ceil(100).max(1);
+        let mut seen = 0;
+        for (&bucket, &count) in &self.buckets {
+            seen += count as usize;
+            if seen >= rank {
+                return Duration::from_micros(bucket).min(self.max);
+            }
+        }
+        Duration::ZERO
+    }
+
+    /// Samples `keep` accepts, each judged by the low end of its bucket
+    pub(crate) fn count_where(&self, keep: impl Fn(Duration) -> bool) -> u32 {
+        self.buckets.iter().filter(|(bucket, _)| keep(Duration::from_micros(**bucket))).map(|(_, count)| count).sum()
+    }
+}
+
+/// Lowest latency, in microseconds, of the bucket `elapsed` falls in
+fn latency_bucket(elapsed: Duration) -> u64 {
+    let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+    let digits = micros.checked_ilog10().map_or(1, |log| log + 1);
+    let scale = 10u64.pow(digits.saturating_sub(LATENCY_SIGNIFICANT_DIGITS));
+    micros / scale * scale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remittance::{Balancing, Remittance, RemittanceRecord};
+    use crate::message::{ClaimMetadata, Hop};
+    use crate::schema::ServiceLine;
+    use crate::tenant::DEFAULT_TENANT;
+    use crate::test_utils::{mock_claim, mock_remittance, mock_adjudication_date};
+
+    fn key(id: &str) -> ClaimKey {
+        ClaimKey::new(DEFAULT_TENANT, id)
+    }
+
+    fn remitted(id: &str, secs: u64) -> (ClaimKey, ClaimStatus) {
+        let now = Instant::now();
+        let mut claim = mock_claim();
+        claim.claim_id = id.into();
+        let record = RemittanceRecord::new(claim, mock_remittance(), now, now + Duration::from_secs(secs));
+        (key(id), ClaimStatus::Remitted(record))
+    }
+
+    /// Test that outstanding claims are bucketed by age per payer.
+    /// Expected: A 90 second old medicare claim lands in the 1–2m bucket.
+    #[test]
+    fn test_ar_aging() {
+        let now = Instant::now();
+        let mut records = HashMap::new();
+        records.insert(
+            key("a"),
+            ClaimStatus::Submitted { claim: mock_claim().into(), tenant_id: "default".to_string(), submitted_at: now, metadata: ClaimMetadata::default() },
+        );
+        let aging = ReportEngine::at(&records, now + Duration::from_secs(90)).ar_aging();
+        assert_eq!(aging, vec![PayerAging { payer_id: "medicare".into(), buckets: [0, 1, 0, 0] }]);
+    }
+
+    /// Test that remitted claims are grouped by priority with count, average, and max latency.
+    /// Expected: High priority first; one high and two normal claims with their averages and maxima.
+    #[test]
+    fn test_latency_by_priority() {
+        let now = Instant::now();
+        let mut records = HashMap::new();
+        for (id, urgent, secs) in [("a", true, 2), ("b", false, 4), ("c", false, 8)] {
+            let mut claim = mock_claim();
+            claim.claim_id = id.into();
+            claim.urgent = Some(urgent);
+            let record = RemittanceRecord::new(claim, mock_remittance(), now, now + Duration::from_secs(secs));
+            records.insert(key(id), ClaimStatus::Remitted(record));
+        }
+        let latency = ReportEngine::new(&records).latency_by_priority();
+        assert_eq!(latency[0].priority, Priority::High);
+        assert_eq!((latency[0].remitted, latency[0].avg_secs), (1, 2.0));
+        assert_eq!(latency[1].priority, Priority::Normal);
+        assert_eq!((latency[1].remitted, latency[1].avg_secs, latency[1].max_secs), (2, 6.0, 8.0));
+    }
+
+    /// Test that turnaround and patient cost sharing are aggregated from remitted claims.
+    /// Expected: One payer with 2 claims averaging 3s; one patient with doubled cost sharing.
+    #[test]
+    fn test_payer_turnaround_and_patient_summary() {
+        let records: HashMap<_, _> = [remitted("a", 2), remitted("b", 4)].into_iter().collect();
+        let engine = ReportEngine::new(&records);
+        let turnaround = engine.payer_turnaround();
+        assert_eq!(turnaround.len(), 1);
+        assert_eq!((turnaround[0].remitted, turnaround[0].avg_secs, turnaround[0].max_secs), (2, 3.0, 4.0));
+        let patients = engine.patient_summary();
+        assert_eq!(patients.len(), 1);
+        assert_eq!(patients[0].copay, 20.0);
+        assert_eq!(patients[0].coinsurance, 70.0);
+    }
+
+    /// Test that claims are aggregated per tenant, even when two tenants used the same claim id.
+    /// Expected: Each tenant counts only its own pending and remitted claims.
+    #[test]
+    fn test_tenant_summary() {
+        let now = Instant::now();
+        let claim_id = mock_claim().claim_id;
+        let mut records = HashMap::new();
+        records.insert(
+            ClaimKey::new("acme", claim_id.clone()),
+            ClaimStatus::Submitted { claim: mock_claim().into(), tenant_id: "acme".to_string(), submitted_at: now, metadata: ClaimMetadata::default() },
+        );
+        let record = RemittanceRecord::new(mock_claim(), mock_remittance(), now, now).with_tenant("beta");
+        records.insert(ClaimKey::new("beta", claim_id), ClaimStatus::Remitted(record));
+
+        let summary = ReportEngine::new(&records).tenant_summary();
+        assert_eq!(summary.len(), 2);
+        assert_eq!((summary[0].tenant_id.as_str(), summary[0].pending, summary[0].remitted), ("acme", 1, 0));
+        assert_eq!((summary[1].tenant_id.as_str(), summary[1].pending, summary[1].remitted), ("beta", 0, 1));
+        assert_eq!(summary[0].billed, mock_claim().total_charge());
+        assert!(summary[1].payer_paid > 0.0);
+    }
+
+    /// Test that audit discrepancies are totaled per payer.
+    /// Expected: Two audited medicare remittances, one with a discrepancy, carrying its variance.
+    #[test]
+    fn test_reconciliation() {
+        let now = Instant::now();
+        let mut records = HashMap::new();
+        records.insert(key("a"), ClaimStatus::Remitted(RemittanceRecord::new(mock_claim(), mock_remittance(), now, now)));
+        let discrepancy = crate::audit::Discrepancy { service_line_id: Some("sl1".to_string()), reason: "short".to_string(), variance: -3.0 };
+        let record = RemittanceRecord::new(mock_claim(), mock_remittance(), now, now).with_discrepancies(vec![discrepancy]);
+        records.insert(key("b"), ClaimStatus::Remitted(record));
+
+        let summary = ReportEngine::new(&records).reconciliation();
+        assert_eq!(summary.len(), 1);
+        assert_eq!((summary[0].audited, summary[0].discrepant, summary[0].variance), (2, 1, -3.0));
+    }
+
+    /// Test that remittances failing the balancing check or answering unbilled lines are listed as exceptions.
+    /// Expected: Of a balanced, a held, and an unmatched remittance, the held one is listed with its reason and the
+    /// unmatched one, sent as balanced, with its extra line.
+    #[test]
+    fn test_remittance_exceptions() {
+        let now = Instant::now();
+        let mut records = HashMap::new();
+        let balanced = Remittance::from_claim(&mock_claim(), mock_adjudication_date());
+        records.insert(key("a"), ClaimStatus::Remitted(RemittanceRecord::new(mock_claim(), balanced, now, now)));
+        let mut claim = mock_claim();
+        claim.claim_id = "b".into();
+        let held = Remittance::from_claim(&claim, mock_adjudication_date()).with_balancing(Balancing::Held("short".to_string()));
+        records.insert(key("b"), ClaimStatus::Remitted(RemittanceRecord::new(claim, held, now, now)));
+
+        let mut claim = mock_claim();
+        claim.claim_id = "c".into();
+        let unmatched = mock_remittance().with_claim_id("c".into());
+        records.insert(key("c"), ClaimStatus::Remitted(RemittanceRecord::new(claim, unmatched, now, now)));
+
+        let exceptions = ReportEngine::new(&records).remittance_exceptions();
+        assert_eq!(exceptions.len(), 2);
+        assert_eq!(exceptions[0].claim_id, "b");
+        assert!(exceptions[0].held);
+        assert_eq!(exceptions[0].reason, "short");
+        assert!(!exceptions[1].held);
+        assert_eq!(exceptions[1].unmatched_lines, ["sl2"]);
+    }
+
+    /// Test that claims are grouped by rendering provider with denial rate over remitted claims.
+    /// Expected: Two claims for one NPI, one remitted and denied, giving a 100% denial rate.
+    #[test]
+    fn test_provider_summary() {
+        let now = Instant::now();
+        let mut records = HashMap::new();
+        records.insert(
+            key("a"),
+            ClaimStatus::Submitted { claim: mock_claim().into(), tenant_id: "default".to_string(), submitted_at: now, metadata: ClaimMetadata::default() },
+        );
+        let denied = Remittance::denied(&mock_claim());
+        records.insert(key("b"), ClaimStatus::Remitted(RemittanceRecord::new(mock_claim(), denied, now, now)));
+
+        let summary = ReportEngine::new(&records).provider_summary();
+        assert_eq!(summary.len(), 1);
+        let totals = &summary[0];
+        assert_eq!(totals.npi, mock_claim().rendering_provider.npi);
+        assert_eq!((totals.claims, totals.remitted, totals.denied), (2, 1, 1));
+        assert_eq!(totals.charges, 2.0 * mock_claim().total_charge());
+        assert_eq!(totals.denial_rate(), 1.0);
+    }
+
+    /// Test that remitted lines are split into telehealth and in-person revenue.
+    /// Expected: A telehealth-place claim and an office claim with a 95 line count as telehealth; the plain office line is in person.
+    #[test]
+    fn test_telehealth_mix() {
+        let now = Instant::now();
+        let mut records = HashMap::new();
+        let mut virtual_visit = mock_claim();
+        virtual_visit.claim_id = "a".into();
+        virtual_visit.place_of_service_code = crate::place_of_service::PlaceOfService::TELEHEALTH_HOME;
+        let mut office = mock_claim();
+        office.claim_id = "b".into();
+        let mut audio_video = office.service_lines[0].clone();
+        audio_video.service_line_id = "sl2".to_string();
+        audio_video.modifiers = Some(vec!["95".to_string()]);
+        office.service_lines.push(audio_video);
+        for claim in [virtual_visit, office] {
+            let remittance = Remittance::from_claim(&claim, mock_adjudication_date());
+            records.insert(key(&claim.claim_id), ClaimStatus::Remitted(RemittanceRecord::new(claim, remittance, now, now)));
+        }
+
+        let mix = ReportEngine::new(&records).telehealth_mix();
+        assert_eq!(mix.iter().map(|totals| (totals.setting, totals.lines)).collect::<Vec<_>>(), [(CareSetting::InPerson, 1), (CareSetting::Telehealth, 2)]);
+        assert_eq!(mix[1].billed, 300.0);
+        assert!((mix[1].revenue() - 2.0 * 150.0 * 0.98).abs() < 1e-9);
+    }
+
+    /// Test that split children are reassembled under their parent, with paid totals from those remitted.
+    /// Expected: Three children of one parent, two remitted, bill the parent's full charge and are not yet settled; unsplit claims are left out.
+    #[test]
+    fn test_split_claims() {
+        let now = Instant::now();
+        let mut claim = mock_claim();
+        claim.service_lines = (1..=3)
+            .map(|i| ServiceLine { service_line_id: format!("sl{}", i), ..claim.service_lines[0].clone() })
+            .collect();
+        let mut records: HashMap<_, _> = [remitted("whole", 1)].into_iter().collect();
+        for (i, child) in claim.clone().split(1, &crate::claim_ids::ClaimIdGenerator::default()).into_iter().enumerate() {
+            let status = if i == 0 {
+                ClaimStatus::Submitted { claim: child.clone().into(), tenant_id: "default".to_string(), submitted_at: now, metadata: ClaimMetadata::default() }
+            } else {
+                let remittance = Remittance::from_claim(&child, mock_adjudication_date());
+                ClaimStatus::Remitted(RemittanceRecord::new(child.clone(), remittance, now, now))
+            };
+            records.insert(key(&child.claim_id), status);
+        }
+
+        let splits = ReportEngine::new(&records).split_claims();
+        assert_eq!(splits.len(), 1);
+        let split = &splits[0];
+        assert_eq!((&split.parent_claim_id, split.children, split.remitted), (&claim.claim_id, 3, 2));
+        assert!(!split.is_settled());
+        assert_eq!(split.billed, claim.total_charge());
+        assert!((split.payer_paid + split.patient_responsibility - 2.0 * 150.0 * 0.98).abs() < 1e-9);
+    }
+
+    /// Test that acknowledgment lag is measured from submission on pending and remitted claims, beside payment lag on remitted ones.
+    /// Expected: Medicare acks in 1 and 3 seconds, one claim still unpaid, and pays in 20; anthem, without hops, shows payment lag only.
+    #[test]
+    fn test_payer_lag() {
+        let now = Instant::now();
+        let hops = |stages: &[(Stage, u64)]| ClaimMetadata {
+            hops: stages.iter().map(|&(stage, secs)| Hop { stage, at: now + Duration::from_secs(secs) }).collect(),
+            ..ClaimMetadata::default()
+        };
+        let metadata = hops(&[(Stage::Submitted, 0), (Stage::Acknowledged, 1), (Stage::Remitted, 20)]);
+        let record = RemittanceRecord::new(mock_claim(), mock_remittance(), now, now + Duration::from_secs(20)).with_metadata(metadata);
+        let mut records: HashMap<ClaimKey, ClaimStatus> = [(key("a"), ClaimStatus::Remitted(record))].into();
+        let metadata = hops(&[(Stage::Ingested, 0), (Stage::Submitted, 2), (Stage::Acknowledged, 5)]);
+        records.insert(
+            key("b"),
+            ClaimStatus::Submitted { claim: mock_claim().into(), tenant_id: "default".to_string(), submitted_at: now, metadata },
+        );
+        let mut claim = mock_claim();
+        claim.insurance.payer_id = "anthem".into();
+        let record = RemittanceRecord::new(claim, mock_remittance(), now, now + Duration::from_secs(60));
+        records.insert(key("c"), ClaimStatus::Remitted(record));
+
+        let lags = ReportEngine::new(&records).payer_lag();
+        let rows: Vec<_> = lags
+            .iter()
+            .map(|lag| (lag.payer_id.as_str(), lag.acknowledged, lag.ack_avg_secs, lag.ack_max_secs, lag.remitted, lag.payment_max_secs))
+            .collect();
+        assert_eq!(rows, vec![("anthem", 0, 0.0, 0.0, 1, 60.0), ("medicare", 2, 2.0, 3.0, 1, 20.0)]);
+    }
+
+    /// Test that hop timestamps are broken down into per-stage latency.
+    /// Expected: One row per consecutive stage pair with the average and nearest-rank p50/p95/p99 time between the hops.
+    #[test]
+    fn test_stage_latency() {
+        let now = Instant::now();
+        // payer adjudication takes 1..=10 seconds across ten claims
+        let records: HashMap<_, _> = (1..=10)
+            .map(|adjudication_secs| {
+                let hops = [
+                    (Stage::Submitted, 0),
+                    (Stage::Received, 1),
+                    (Stage::Routed, 1),
+                    (Stage::Adjudicated, 1 + adjudication_secs),
+                    (Stage::Remitted, 1 + adjudication_secs),
+                ]
+                .into_iter()
+                .map(|(stage, secs)| Hop { stage, at: now + Duration::from_secs(secs) })
+                .collect();
+                let metadata = ClaimMetadata { hops, ..ClaimMetadata::default() };
+                let record = RemittanceRecord::new(mock_claim(), mock_remittance(), now, now).with_metadata(metadata);
+                (key(&adjudication_secs.to_string()), ClaimStatus::Remitted(record))
+            })
+            .collect();
+
+        let latency = ReportEngine::new(&records).stage_latency();
+        let rows: Vec<_> = latency.iter().map(|row| (row.from, row.to, row.avg_secs)).collect();
+        assert_eq!(
+            rows,
+            vec![
+                (Stage::Submitted, Stage::Received, 1.0),
+                (Stage::Received, Stage::Routed, 0.0),
+                (Stage::Routed, Stage::Adjudicated, 5.5),
+                (Stage::Adjudicated, Stage::Remitted, 0.0),
+            ]
+        );
+        let adjudication = &latency[2];
+        assert_eq!(
+            (adjudication.p50_secs, adjudication.p95_secs, adjudication.p99_secs, adjudication.max_secs),
+            (5.0, 10.0, 10.0, 10.0)
+        );
+    }
+
+    /// Test that totals kept one update at a time match totals of the same history counted at once.
+    /// Expected: Through a replacement being linked, refused, and released, a void, a stale pending update, and
+    /// a repeated one, every report read from the running totals equals the report read from the records.
+    #[test]
+    fn test_totals_follow_updates() {
+        let now = Instant::now();
+        let claim = |id: &str| PayerClaim { claim_id: id.into(), ..mock_claim() };
+        let submitted = |claim: PayerClaim| ClaimStatus::Submitted {
+            claim: claim.into(),
+            tenant_id: DEFAULT_TENANT.to_string(),
+            submitted_at: now,
+            metadata: ClaimMetadata::default(),
+        };
+        let original = RemittanceRecord::new(claim("a"), mock_remittance(), now, now + Duration::from_secs(3));
+        let mut replacement = claim("b");
+        replacement.claim_frequency_code = crate::schema::ClaimFrequency::Replacement;
+        replacement.original_claim_id = Some("a".into());
+        let linked = original.clone().with_replaced_by(Some(replacement.clone().into()));
+        let updates = [
+            (key("a"), submitted(claim("a"))),
+            (key("c"), submitted(claim("c"))),
+            (key("a"), ClaimStatus::Remitted(original.clone())),
+            (key("a"), submitted(claim("a"))),
+            (key("a"), ClaimStatus::Remitted(linked.clone())),
+            (key("b"), submitted(replacement.clone())),
+            (key("a"), ClaimStatus::Remitted(linked)),
+            (key("b"), ClaimStatus::Voided { claim: replacement.into(), tenant_id: DEFAULT_TENANT.to_string(), voided_at: now }),
+            (key("a"), ClaimStatus::Remitted(original)),
+            (key("d"), submitted(claim("d"))),
+            (key("c"), ClaimStatus::Voided { claim: claim("c").into(), tenant_id: DEFAULT_TENANT.to_string(), voided_at: now }),
+        ];
+
+        let (mut records, mut totals) = (HashMap::new(), HistoryTotals::default());
+        for (key, status) in updates {
+            // a pending status never replaces a settled one in history either
+            if !matches!(status, ClaimStatus::Submitted { .. }) || !matches!(records.get(&key), Some(ClaimStatus::Remitted(_))) {
+                records.insert(key.clone(), status.clone());
+            }
+            totals.apply(key, status);
+            let (live, counted) = (ReportEngine::of(&totals), ReportEngine::new(&records));
+            assert_eq!(live.tenant_summary(), counted.tenant_summary());
+            assert_eq!(live.provider_summary(), counted.provider_summary());
+            assert_eq!(live.patient_summary(), counted.patient_summary());
+            assert_eq!(live.payer_turnaround(), counted.payer_turnaround());
+            assert_eq!(live.procedure_summary(), counted.procedure_summary());
+            assert_eq!(live.reconciliation(), counted.reconciliation());
+            assert_eq!(totals.gross_charges(), HistoryTotals::from_records(&records).gross_charges());
+            assert_eq!(totals.first_pass(), HistoryTotals::from_records(&records).first_pass());
+        }
+        let summary = ReportEngine::of(&totals).tenant_summary();
+        assert_eq!((summary[0].pending, summary[0].remitted, summary[0].voided, summary[0].replaced), (1, 1, 2, 0));
+    }
+
+    /// Test that latency percentiles come from fixed buckets rather than kept samples.
+    /// Expected: A million samples fill no more than a few hundred buckets, and each percentile is within 10% of the exact one.
+    #[test]
+    fn test_latency_buckets() {
+        let mut stats = LatencyStats::default();
+        for micros in 1..=1_000_000u64 {
+            stats.tally(Duration::from_micros(micros * 7), 1);
+        }
+        assert!(stats.buckets.len() <= 500, "{}", stats.buckets.len());
+        for pct in [50, 95, 99] {
+            let exact = Duration::from_micros(pct as u64 * 10_000 * 7);
+            let estimate = stats.percentile(pct);
+            assert!(estimate <= exact && estimate.as_secs_f64() >= exact.as_secs_f64() * 0.9, "p{}: {:?} vs {:?}", pct, estimate, exact);
+        }
+        assert_eq!(stats.percentile(100), stats.max);
+    }
+
+    /// Test that SLA compliance counts on-time, late, and overdue claims in simulated days.
+    /// Expected: At one real second per day, one claim within 2 days, one late, and one pending past the deadline give a third on time, breaching a 50% target.
+    #[test]
+    fn test_sla_compliance() {
+        let now = Instant::now();
+        let mut records: HashMap<ClaimKey, ClaimStatus> = [remitted("a", 1), remitted("b", 5)].into();
+        let submitted_at = now - Duration::from_secs(3);
+        records.insert(
+            key("c"),
+            ClaimStatus::Submitted { claim: mock_claim().into(), tenant_id: "default".to_string(), submitted_at, metadata: ClaimMetadata::default() },
+        );
+        let slas = BTreeMap::from([(PayerId::from("medicare"), "50@2".parse::<PayerSla>().unwrap())]);
+        let clock = SimClock::new(chrono::Utc::now().date_naive(), 1.0);
+
+        let compliance = ReportEngine::at(&records, now).sla_compliance(&slas, &clock);
+        assert_eq!(compliance.len(), 1);
+        let row = &compliance[0];
+        assert_eq!((row.on_time, row.late, row.overdue), (1, 1, 1));
+        assert!((row.compliance.unwrap() - 1.0 / 3.0).abs() < 1e-9);
+        assert!(row.breached);
+        records.remove(&key("c"));
+        assert!(!ReportEngine::at(&records, now).sla_compliance(&slas, &clock)[0].breached);
+    }
+}
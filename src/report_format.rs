@@ -0,0 +1,290 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Number and currency conventions for report output
+///
+/// Amounts stay in the claims' dollars; a locale only changes how they are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    /// `$1,234.56`
+    #[default]
+    EnUs,
+    /// `1.234,56 $`
+    DeDe,
+    /// `1 234,56 $`
+    FrFr,
+}
+
+impl Locale {
+    fn thousands_separator(&self) -> char {
+        match self {
+            Locale::EnUs => ',',
+            Locale::DeDe => '.',
+            Locale::FrFr => ' ',
+        }
+    }
+
+    fn decimal_separator(&self) -> char {
+        match self {
+            Locale::EnUs => '.',
+            Locale::DeDe | Locale::FrFr => ',',
+        }
+    }
+
+    /// Whether the currency symbol follows the amount
+    fn symbol_after(&self) -> bool {
+        !matches!(self, Locale::EnUs)
+    }
+}
+
+impl FromStr for Locale {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.replace('_', "-").as_str() {
+            "en-US" => Ok(Locale::EnUs),
+            "de-DE" => Ok(Locale::DeDe),
+            "fr-FR" => Ok(Locale::FrFr),
+            _ => Err(anyhow::anyhow!("Unknown locale: {} (expected en-US, de-DE, or fr-FR)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Locale::EnUs => write!(f, "en-US"),
+            Locale::DeDe => write!(f, "de-DE"),
+            Locale::FrFr => write!(f, "fr-FR"),
+        }
+    }
+}
+
+/// Scale money amounts are reported in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Units {
+    #[default]
+    Dollars,
+    /// Thousands of dollars, written with a `K` suffix on the console
+    Thousands,
+}
+
+impl FromStr for Units {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dollars" => Ok(Units::Dollars),
+            "thousands" => Ok(Units::Thousands),
+            _ => Err(anyhow::anyhow!("Unknown units: {} (expected dollars or thousands)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Units {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Units::Dollars => write!(f, "dollars"),
+            Units::Thousands => write!(f, "thousands"),
+        }
+    }
+}
+
+/// Rows a console table lists per patient, provider, or claim before summing the rest into one row
+pub const DEFAULT_MAX_ROWS: usize = 25;
+
+/// How reports write numbers and money, shared by the console, CSV, and JSON outputs
+///
+/// The console gets grouped, symbol-bearing text; CSV gets plain numbers in the locale's decimal
+/// notation; JSON keeps numbers as numbers, only scaled to the units
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportFormat {
+    pub locale: Locale,
+    pub units: Units,
+    /// Rows console tables with a row per patient, provider, or claim list before rolling the
+    /// rest into an "other" row; `None` lists them all. CSV and JSON always get every row
+    pub max_rows: Option<usize>,
+}
+
+impl Default for ReportFormat {
+    fn default() -> Self {
+        Self::new(Locale::default(), Units::default())
+    }
+}
+
+impl ReportFormat {
+    pub fn new(locale: Locale, units: Units) -> Self {
+        Self {
+            locale,
+            units,
+            max_rows: Some(DEFAULT_MAX_ROWS),
+        }
+    }
+
+    pub fn with_max_rows(mut self, max_rows: Option<usize>) -> Self {
+        self.max_rows = max_rows;
+        self
+    }
+
+    /// Split a table's rows into the ones the console lists and the ones it sums into one row
+    ///
+    /// Lists the `max_rows` rows with the largest `weight`, ties going to the earlier row, in
+    /// their original order. Picking them takes linear time, so a huge history costs one pass
+    /// rather than a sort, and the table printed stays `max_rows` long
+    pub fn top_rows<'a, T>(&self, rows: &'a [T], weight: impl Fn(&T) -> f64) -> (Vec<&'a T>, Vec<&'a T>) {
+        let limit = match self.max_rows {
+            Some(limit) if limit < rows.len() => limit,
+            _ => return (rows.iter().collect(), Vec::new()),
+        };
+        let mut ranked: Vec<(f64, usize)> = rows.iter().enumerate().map(|(i, row)| (weight(row), i)).collect();
+        if limit > 0 {
+            ranked.select_nth_unstable_by(limit - 1, |a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+        }
+        let mut listed = vec![false; rows.len()];
+        for (_, i) in &ranked[..limit] {
+            listed[*i] = true;
+        }
+        let (mut top, mut rest) = (Vec::with_capacity(limit), Vec::with_capacity(rows.len() - limit));
+        for (row, listed) in rows.iter().zip(listed) {
+            match listed {
+                true => top.push(row),
+                false => rest.push(row),
+            }
+        }
+        (top, rest)
+    }
+
+    /// Money amount in the report's units
+    pub fn scale(&self, amount: f64) -> f64 {
+        match self.units {
+            Units::Dollars => amount,
+            Units::Thousands => amount / 1_000.0,
+        }
+    }
+
+    /// Money for the console, e.g. `$1,234.56`, `1.234,56 $`, or `$1.23K`
+    pub fn money(&self, amount: f64) -> String {
+        let number = self.number(self.scale(amount), 2);
+        let (sign, digits) = match number.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", number.as_str()),
+        };
+        let suffix = match self.units {
+            Units::Dollars => "",
+            Units::Thousands => "K",
+        };
+        match self.locale.symbol_after() {
+            true => format!("{}{}{} $", sign, digits, suffix),
+            false => format!("{}${}{}", sign, digits, suffix),
+        }
+    }
+
+    /// Number with `decimals` places, digits grouped by thousands, in the locale's notation
+    pub fn number(&self, value: f64, decimals: usize) -> String {
+        let text = format!("{:.*}", decimals, value.abs());
+        let (whole, fraction) = match text.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (text.as_str(), None),
+        };
+        let mut grouped = String::new();
+        for (i, digit) in whole.chars().enumerate() {
+            if i > 0 && (whole.len() - i) % 3 == 0 {
+                grouped.push(self.locale.thousands_separator());
+            }
+            grouped.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            grouped.push(self.locale.decimal_separator());
+            grouped.push_str(fraction);
+        }
+        // no sign on amounts that round to zero
+        let negative = value < 0.0 && text.chars().any(|c| c.is_ascii_digit() && c != '0');
+        match negative {
+            true => format!("-{}", grouped),
+            false => grouped,
+        }
+    }
+
+    /// Percentage of a fraction, e.g. `12.5%` or `12,5 %`
+    pub fn percent(&self, fraction: f64, decimals: usize) -> String {
+        let number = self.number(fraction * 100.0, decimals);
+        match self.locale.symbol_after() {
+            true => format!("{} %", number),
+            false => format!("{}%", number),
+        }
+    }
+
+    /// Money for CSV: the scaled amount to the cent in the locale's decimal notation, ungrouped
+    pub fn csv_money(&self, amount: f64) -> String {
+        format!("{:.2}", self.scale(amount)).replace('.', &self.locale.decimal_separator().to_string())
+    }
+
+    /// CSV field delimiter: a semicolon where the comma is the decimal separator
+    pub fn csv_delimiter(&self) -> char {
+        match self.locale.decimal_separator() {
+            ',' => ';',
+            _ => ',',
+        }
+    }
+
+    /// CSV header of a money column, naming the units when not plain dollars
+    pub fn csv_money_column(&self, name: &str) -> String {
+        match self.units {
+            Units::Dollars => name.to_string(),
+            Units::Thousands => format!("{}_thousands", name),
+        }
+    }
+}
+
+/// Report rows carrying money amounts, so JSON output can be scaled to the report's units
+pub trait MoneyFields {
+    /// Apply `f` to every money amount in the row
+    fn map_money(&mut self, f: impl Fn(f64) -> f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that money and numbers follow the locale's separators and symbol placement, and the units' scale.
+    /// Expected: `$1,234,567.89` in en-US, `1.234.567,89 $` in de-DE, `$1,234.57K` in thousands; CSV uses `;` with comma decimals.
+    #[test]
+    fn test_report_format() {
+        let us = ReportFormat::default();
+        assert_eq!(us.money(1_234_567.891), "$1,234,567.89");
+        assert_eq!(us.money(-5.0), "-$5.00");
+        assert_eq!(us.money(-0.001), "$0.00");
+        assert_eq!(us.percent(0.125, 1), "12.5%");
+        assert_eq!(ReportFormat::new(Locale::DeDe, Units::Dollars).money(1_234_567.891), "1.234.567,89 $");
+        assert_eq!(ReportFormat::new(Locale::FrFr, Units::Dollars).number(1_234.5, 1), "1 234,5");
+
+        let thousands = ReportFormat::new(Locale::EnUs, Units::Thousands);
+        assert_eq!(thousands.money(1_234_567.891), "$1,234.57K");
+        assert_eq!(thousands.csv_money(1_500.0), "1.50");
+        assert_eq!(thousands.csv_money_column("billed"), "billed_thousands");
+
+        let german = ReportFormat::new(Locale::DeDe, Units::Thousands);
+        assert_eq!(german.csv_money(1_500.0), "1,50");
+        assert_eq!(german.csv_delimiter(), ';');
+        assert_eq!("de_DE".parse::<Locale>().unwrap(), Locale::DeDe);
+        assert!("xx".parse::<Units>().is_err());
+    }
+
+    /// Test that console tables keep the heaviest rows in their original order and leave the rest for an "other" row.
+    /// Expected: With two rows allowed, 9 and 7 are listed in input order and the rest set aside; with no limit every row is listed.
+    #[test]
+    fn test_top_rows() {
+        let rows = [3.0, 9.0, 1.0, 7.0, 7.0];
+        let format = ReportFormat::default().with_max_rows(Some(2));
+        let (top, rest) = format.top_rows(&rows, |row| *row);
+        assert_eq!(top, [&9.0, &7.0]);
+        assert_eq!(rest, [&3.0, &1.0, &7.0]);
+
+        let (top, rest) = format.with_max_rows(Some(0)).top_rows(&rows, |row| *row);
+        assert!(top.is_empty());
+        assert_eq!(rest.len(), 5);
+        let (top, rest) = format.with_max_rows(None).top_rows(&rows, |row| *row);
+        assert_eq!(top.len(), 5);
+        assert!(rest.is_empty());
+    }
+}
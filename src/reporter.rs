@@ -1,93 +1,293 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use chrono::NaiveDate;
 use tokio::sync::Mutex;
 use tokio::time;
 
+use crate::benefits::BenefitStatus;
+use crate::forecast::{self, PayerForecast};
+use crate::kpi::{self, RevenueCycleKpis};
+use crate::history::{History, LiveTotals};
+use crate::ids::{ClaimKey, MemberId, PayerId};
+use crate::inventory::{ClaimInventory, StateInventory};
+use crate::clock::SimClock;
+use crate::debugger::DebugSnapshot;
+use crate::ledger::{Ledger, PATIENT_AGING_BUCKET_LABELS, PatientAging, PatientBalance};
+use crate::loadtest::LoadTestReport;
+use crate::monte_carlo::{Distribution, MonteCarloReport};
+use crate::period_close::PeriodReport;
+use crate::report_format::ReportFormat;
+use crate::reconciliation::BankReconciliation;
+use crate::sla::SlaMonitor;
+use crate::run_db::{AR_AGING_DAY_LABELS, DenialsByReason, PayerAgingDollars, WeeklyPayments};
+use crate::what_if::PayerImpact;
+use crate::workqueue::{Workqueue, WorkqueueStats};
 use crate::message::ClaimStatus;
+use crate::payer::PayerPortal;
+use crate::phi::Deidentification;
+use crate::payer_mix::ReimbursementVariance;
+use crate::supervisor::Heartbeat;
+use crate::report_engine::{
+    AGING_BUCKET_LABELS, PayerAging, PatientSummary, PayerLag, PayerReconciliation, PayerTurnaround, PriorityLatency, ProcedureSummary,
+    HistoryTotals, ProviderSummary, RemittanceException, ReportEngine, SlaCompliance, SplitClaim, StageLatency, TelehealthMix, TenantSummary,
+};
 use prettytable::{Table, Row, Cell};
 use colored::*;
 
+/// Shared state the live reports are drawn from
+#[derive(Clone)]
+pub struct ReportSources {
+    pub history: History,
+    pub ledgers: BTreeMap<String, Arc<Mutex<Ledger>>>,
+    pub portals: BTreeMap<PayerId, PayerPortal>,
+    /// Claims in each lifecycle state, kept current by the clearinghouse
+    pub inventory: ClaimInventory,
+    /// Billing staff working denials, underpayments, and exceptions, when any are simulated
+    pub workqueue: Option<Workqueue>,
+    /// How the tables name patients and subscribers: by pseudonymous member id unless off
+    pub deidentification: Deidentification,
+}
+
 /// Periodically generate and display business reports
 /// 
-/// Runs every 5 seconds to show AR aging and patient financial summaries
-/// Uses shared claim history to track processing status
-pub async fn run_reporter(history: Arc<Mutex<HashMap<String, ClaimStatus>>>, verbose: bool) {
+/// Runs every 5 seconds to show the claim inventory by state, AR aging, and patient financial summaries
+/// Follows claim history's updates into running totals (see `LiveTotals`) to track
+/// processing status, so formatting the tables never holds a history lock the clearinghouse
+/// needs; uses each tenant's ledger for patient AR,
+/// and each payer's portal for where patients stand against their plans; patient balances
+/// are aged on the simulation clock, and amounts are written in the report format.
+/// Payers with an SLA get a compliance section, and a breach is alerted as it happens.
+/// With `forecast_days`, payer cash expected over that many simulated days is forecast.
+/// With a workqueue, its backlog and each worker's throughput are shown. Revenue-cycle KPIs
+/// close each report, over every tenant's ledger
+pub async fn run_reporter(
+    sources: ReportSources,
+    sla: SlaMonitor,
+    forecast_days: Option<f64>,
+    clock: SimClock,
+    format: ReportFormat,
+    heartbeat: Heartbeat,
+    verbose: bool,
+) {
     if verbose {
         println!("[reporter] Starting reporter task");
     }
     let mut interval = time::interval(Duration::from_secs(5));
+    let mut live = LiveTotals::new(sources.history.clone()).await;
 
     loop {
-        interval.tick().await;
-        let records = history.lock().await;
+        // apply claim updates as they arrive, so few are left to catch up on at each report
+        tokio::select! {
+            _ = interval.tick() => {}
+            () = live.follow() => continue,
+        }
+        print_inventory_report(&sources.inventory.counts(), &format);
+        let totals = live.totals().await;
+        print_history_reports(totals, &format, &sources.deidentification);
+        if !sla.is_empty() {
+            print_sla_report(&sla.check(&ReportEngine::of(totals), &clock), &format);
+        }
+        if let Some(days) = forecast_days {
+            print_forecast_report(&forecast::forecast_collections(totals, &clock, Instant::now(), days), days, &format);
+        }
+        if let Some(workqueue) = &sources.workqueue {
+            print_workqueue_report(&workqueue.stats(), &format);
+        }
+        let mut benefits: Vec<BenefitStatus> = sources.portals.values().flat_map(PayerPortal::benefit_statuses).collect();
+        for status in &mut benefits {
+            status.coverage.patient_id = sources.deidentification.member_id(&status.coverage.patient_id);
+            status.coverage.subscriber_id = sources.deidentification.member_id(&status.coverage.subscriber_id);
+        }
+        if !benefits.is_empty() {
+            print_benefit_report(&benefits, &format);
+        }
 
-        print_combined_report(&records);
+        let mut guards = Vec::new();
+        for (tenant_id, ledger) in &sources.ledgers {
+            guards.push((tenant_id.as_str(), ledger.lock().await));
+        }
+        print_tenant_ledger_reports(
+            guards.iter().map(|(tenant_id, ledger)| (*tenant_id, &**ledger)),
+            &clock,
+            &format,
+            &sources.deidentification,
+        );
+        let kpis = kpi::revenue_cycle_kpis(totals, guards.iter().map(|(_, ledger)| &**ledger), &clock, Instant::now());
+        print_kpi_report(&kpis, &format);
+        heartbeat.beat();
     }
 }
 
-/// Generate and print combined AR aging and patient financial reports
-/// 
-/// AR Aging: Groups claims by payer and age buckets (0-1m, 1-2m, 2-3m, 3m+)
-/// Patient Summary: Totals copay, coinsurance, and deductible by patient
-#[derive(Default)]
-struct Totals {
-    copay: f64,
-    coins: f64,
-    deduct: f64,
+/// Print the reports derived from claim history: AR aging, patient summary, payer turnaround,
+/// acknowledgment and payment lag, latency by priority and by stage, provider, procedure, and telehealth revenue, remittance reconciliation, remittance exceptions
+/// and split claims when there are any, and a per-tenant summary when more than one billing organization is present;
+/// patients are named as `deidentification` writes them
+pub fn print_history_reports(totals: &HistoryTotals, format: &ReportFormat, deidentification: &Deidentification) {
+    let engine = ReportEngine::of(totals).with_deidentification(deidentification.clone());
+    print_ar_aging_report(&engine.ar_aging());
+    print_patient_summary_report(&engine.patient_summary(), format);
+    print_payer_turnaround_report(&engine.payer_turnaround(), format);
+    print_payer_lag_report(&engine.payer_lag(), format);
+    print_priority_latency_report(&engine.latency_by_priority(), format);
+    print_stage_latency_report(&engine.stage_latency(), format);
+    print_provider_report(&engine.provider_summary(), format);
+    print_procedure_report(&engine.procedure_summary(), format);
+    print_telehealth_mix_report(&engine.telehealth_mix(), format);
+    print_reconciliation_report(&engine.reconciliation(), format);
+    let exceptions = engine.remittance_exceptions();
+    if !exceptions.is_empty() {
+        print_exceptions_report(&exceptions, format);
+    }
+    let splits = engine.split_claims();
+    if !splits.is_empty() {
+        print_split_claims_report(&splits, format);
+    }
+    let summary = engine.tenant_summary();
+    if summary.len() > 1 {
+        print_tenant_summary_report(&summary, format);
+    }
 }
 
-fn print_combined_report(records: &HashMap<String, ClaimStatus>) {
-    let mut aging_buckets: HashMap<String, [u32; 4]> = HashMap::new();
-    let mut patient_summary: HashMap<String, Totals> = HashMap::new();
+/// Print how many claims, and how many billed dollars, sit in each lifecycle state right now
+fn print_inventory_report(inventory: &[StateInventory], format: &ReportFormat) {
+    println!("{}", "\n--- Claim Inventory ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("State").style_spec("bFc"),
+        Cell::new("Claims").style_spec("bFc"),
+        Cell::new("Billed").style_spec("bFc"),
+    ]));
+    for row in inventory {
+        table.add_row(Row::new(vec![
+            Cell::new(&row.state.to_string()),
+            Cell::new(&row.claims.to_string()),
+            Cell::new(&format.money(row.billed)),
+        ]));
+    }
+    table.add_row(Row::new(vec![
+        Cell::new("TOTAL").style_spec("b"),
+        Cell::new(&inventory.iter().map(|row| row.claims).sum::<u32>().to_string()).style_spec("b"),
+        Cell::new(&format.money(inventory.iter().map(|row| row.billed).sum())).style_spec("b"),
+    ]));
+    table.printstd();
+}
+
+/// Print days in AR, collection rates, and first-pass resolution, with a dash for a rate not yet measurable
+pub(crate) fn print_kpi_report(kpis: &RevenueCycleKpis, format: &ReportFormat) {
+    println!("{}", format!("\n--- Revenue Cycle KPIs ({} simulated days) ---", format.number(kpis.days, 1)).bold().blue());
+    let percent = |rate: Option<f64>| rate.map_or("-".to_string(), |rate| format!("{}%", format.number(rate * 100.0, 1)));
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![Cell::new("KPI").style_spec("bFc"), Cell::new("Value").style_spec("bFc")]));
+    for (label, value) in [
+        ("Gross Charges", format.money(kpis.gross_charges)),
+        ("Payments", format.money(kpis.payments)),
+        ("Contractual Adjustments", format.money(kpis.contractual_adjustments)),
+        ("AR Balance", format.money(kpis.ar_balance)),
+        ("Days in AR", kpis.days_in_ar.map_or("-".to_string(), |days| format.number(days, 1))),
+        ("Gross Collection Rate", percent(kpis.gross_collection_rate)),
+        ("Net Collection Rate", percent(kpis.net_collection_rate)),
+        ("First-Pass Resolution Rate", percent(kpis.first_pass_resolution_rate)),
+    ] {
+        table.add_row(Row::new(vec![Cell::new(label), Cell::new(&value)]));
+    }
+    table.printstd();
+}
+
+/// Print each payer's share of claims and reimbursement against what the payer mix assumed,
+/// shortfalls highlighted
+pub(crate) fn print_reimbursement_report(rows: &[ReimbursementVariance], format: &ReportFormat) {
+    println!("{}", "\n--- Expected vs Actual Reimbursement ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(
+        ["Payer", "Mix %", "Actual %", "Remitted", "Billed", "Expected", "Actual", "Variance"]
+            .into_iter()
+            .map(|title| Cell::new(title).style_spec("bFc"))
+            .collect(),
+    ));
+    for row in rows {
+        let variance_style = if row.variance < 0.0 { "Fr" } else { "" };
+        table.add_row(Row::new(vec![
+            Cell::new(&row.payer_id),
+            Cell::new(&format.number(row.mix_percent, 1)),
+            Cell::new(&format.number(row.actual_percent, 1)),
+            Cell::new(&row.remitted.to_string()),
+            Cell::new(&format.money(row.billed)),
+            Cell::new(&format.money(row.expected)),
+            Cell::new(&format.money(row.actual)),
+            Cell::new(&format.money(row.variance)).style_spec(variance_style),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Print ledger reports for each tenant, headed by tenant id when there is more than one
+pub fn print_tenant_ledger_reports<'a>(
+    ledgers: impl IntoIterator<Item = (&'a str, &'a Ledger)>,
+    clock: &SimClock,
+    format: &ReportFormat,
+    deidentification: &Deidentification,
+) {
+    let ledgers: Vec<_> = ledgers.into_iter().collect();
+    for (tenant_id, ledger) in &ledgers {
+        if ledgers.len() > 1 {
+            println!("{}", format!("\n=== Tenant: {} ===", tenant_id).bold().magenta());
+        }
+        print_ledger_reports(ledger, clock, format, deidentification);
+    }
+}
 
-    for status in records.values() {
-        update_aging_buckets(status, &mut aging_buckets);
-        update_patient_summary(status, &mut patient_summary);
+/// Print the reports derived from the ledger: patient AR, patient AR aging as of the clock's date, and write-offs,
+/// patients named as `deidentification` writes them
+pub(crate) fn print_ledger_reports(ledger: &Ledger, clock: &SimClock, format: &ReportFormat, deidentification: &Deidentification) {
+    print_patient_ar_report(ledger, format, deidentification);
+    let mut aging = ledger.patient_aging(clock, clock.now().date());
+    for row in &mut aging {
+        row.patient_id = deidentification.member_id(&row.patient_id);
     }
+    print_patient_aging_report(&aging, format);
+    print_write_off_report(ledger, format);
+}
 
-    // AR Aging Report
+/// Print outstanding claims per payer by age bucket with column totals
+fn print_ar_aging_report(aging: &[PayerAging]) {
     println!("{}", "\n--- AR Aging Report ---".bold().blue());
     let mut ar_table = Table::new();
-    ar_table.add_row(Row::new(vec![
-        Cell::new("Payer").style_spec("bFc"),
-        Cell::new("0–1m").style_spec("bFc"),
-        Cell::new("1–2m").style_spec("bFc"),
-        Cell::new("2–3m").style_spec("bFc"),
-        Cell::new("3+m").style_spec("bFc"),
-    ]));
-    for (payer, [b0, b1, b2, b3]) in &aging_buckets {
-        ar_table.add_row(Row::new(vec![
-            Cell::new(payer),
-            Cell::new(&b0.to_string()),
-            Cell::new(&b1.to_string()),
-            Cell::new(&b2.to_string()),
-            Cell::new(&b3.to_string()),
-        ]));
+    let mut header = vec![Cell::new("Payer").style_spec("bFc")];
+    header.extend(AGING_BUCKET_LABELS.iter().map(|label| Cell::new(label).style_spec("bFc")));
+    ar_table.add_row(Row::new(header));
+    let mut totals = [0u32; 4];
+    for row in aging {
+        let mut cells = vec![Cell::new(&row.payer_id)];
+        for (total, count) in totals.iter_mut().zip(row.buckets) {
+            *total += count;
+            cells.push(Cell::new(&count.to_string()));
+        }
+        ar_table.add_row(Row::new(cells));
     }
     // Add total outstanding claims row
-    let (total_b0, total_b1, total_b2, total_b3): (u32, u32, u32, u32) = aging_buckets.values().fold((0, 0, 0, 0), |acc, buckets| {
-        (acc.0 + buckets[0], acc.1 + buckets[1], acc.2 + buckets[2], acc.3 + buckets[3])
-    });
-    let total_outstanding = total_b0 + total_b1 + total_b2 + total_b3;
-    ar_table.add_row(Row::new(vec![
-        Cell::new("TOTAL OUTSTANDING").style_spec("bFc"),
-        Cell::new(&total_b0.to_string()),
-        Cell::new(&total_b1.to_string()),
-        Cell::new(&total_b2.to_string()),
-        Cell::new(&total_b3.to_string()),
-    ]));
+    let mut total_row = vec![Cell::new("TOTAL OUTSTANDING").style_spec("bFc")];
+    total_row.extend(totals.iter().map(|total| Cell::new(&total.to_string())));
+    ar_table.add_row(Row::new(total_row));
     ar_table.add_row(Row::new(vec![
         Cell::new("").style_spec(""),
         Cell::new("").style_spec("") ,
         Cell::new("").style_spec("") ,
         Cell::new("").style_spec("") ,
-        Cell::new(&format!("Total Claims: {}", total_outstanding)).style_spec("bFc"),
+        Cell::new(&format!("Total Claims: {}", totals.iter().sum::<u32>())).style_spec("bFc"),
     ]));
     ar_table.printstd();
+}
 
-    // Patient Financial Summary
+/// First cell of the row summing what a table left out, e.g. `OTHER (1,204 patients)`
+fn other_label(count: usize, noun: &str, format: &ReportFormat) -> String {
+    format!("OTHER ({} {})", format.number(count as f64, 0), noun)
+}
+
+/// Print copay, coinsurance, and deductible totals by patient
+fn print_patient_summary_report(patients: &[PatientSummary], format: &ReportFormat) {
     println!("{}", "\n--- Patient Financial Summary ---".bold().blue());
     let mut pf_table = Table::new();
     pf_table.add_row(Row::new(vec![
@@ -96,48 +296,1033 @@ fn print_combined_report(records: &HashMap<String, ClaimStatus>) {
         Cell::new("Coinsurance").style_spec("bFc"),
         Cell::new("Deductible").style_spec("bFc"),
     ]));
-    for (patient, totals) in &patient_summary {
+    let (top, rest) = format.top_rows(patients, |totals| totals.copay + totals.coinsurance + totals.deductible);
+    for totals in top {
         pf_table.add_row(Row::new(vec![
-            Cell::new(patient),
-            Cell::new(&format!("${:.2}", totals.copay)),
-            Cell::new(&format!("${:.2}", totals.coins)),
-            Cell::new(&format!("${:.2}", totals.deduct)),
+            Cell::new(&totals.patient_id),
+            Cell::new(&format.money(totals.copay)),
+            Cell::new(&format.money(totals.coinsurance)),
+            Cell::new(&format.money(totals.deductible)),
+        ]));
+    }
+    if !rest.is_empty() {
+        let other = rest.iter().fold(PatientSummary::default(), |mut other, totals| {
+            other.copay += totals.copay;
+            other.coinsurance += totals.coinsurance;
+            other.deductible += totals.deductible;
+            other
+        });
+        pf_table.add_row(Row::new(vec![
+            Cell::new(&other_label(rest.len(), "patients", format)).style_spec("i"),
+            Cell::new(&format.money(other.copay)),
+            Cell::new(&format.money(other.coinsurance)),
+            Cell::new(&format.money(other.deductible)),
         ]));
     }
     // Add total number of patients row
-    let total_patients = patient_summary.len();
     pf_table.add_row(Row::new(vec![
         Cell::new("TOTAL PATIENTS").style_spec("bFc"),
         Cell::new("").style_spec("") ,
         Cell::new("").style_spec("") ,
-        Cell::new(&format!("{}", total_patients)).style_spec("bFc"),
+        Cell::new(&format!("{}", patients.len())).style_spec("bFc"),
     ]));
     pf_table.printstd();
 }
 
-fn update_aging_buckets(status: &ClaimStatus, aging_buckets: &mut HashMap<String, [u32; 4]>) {
-    if let ClaimStatus::Submitted { claim, submitted_at } = status {
-        let payer_id = claim.insurance.payer_id.clone();
-        let age_secs = std::time::Instant::now().duration_since(*submitted_at).as_secs();
-        let bucket = match age_secs {
-            0..=59 => 0,
-            60..=119 => 1,
-            120..=179 => 2,
-            _ => 3,
+/// Print each patient's deductible and out-of-pocket totals for the current plan year, and whether they are met
+fn print_benefit_report(benefits: &[BenefitStatus], format: &ReportFormat) {
+    println!("{}", "\n--- Patient Benefit Accumulators ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Patient").style_spec("bFc"),
+        Cell::new("Subscriber").style_spec("bFc"),
+        Cell::new("Payer").style_spec("bFc"),
+        Cell::new("Plan").style_spec("bFc"),
+        Cell::new("Plan Year").style_spec("bFc"),
+        Cell::new("Deductible").style_spec("bFc"),
+        Cell::new("Out-of-Pocket").style_spec("bFc"),
+    ]));
+    let status = |paid: f64, limit: f64, met: bool| {
+        let label = if met { "met" } else { "not met" };
+        format!("{} / {} ({})", format.money(paid), format.money(limit), label)
+    };
+    let (top, rest) = format.top_rows(benefits, |row| row.out_of_pocket);
+    for row in top {
+        table.add_row(Row::new(vec![
+            Cell::new(&row.coverage.patient_id),
+            Cell::new(&row.coverage.subscriber_id),
+            Cell::new(&row.coverage.payer_id),
+            Cell::new(&row.plan_id),
+            Cell::new(&row.plan_year.to_string()),
+            Cell::new(&status(row.deductible_met, row.deductible, row.is_deductible_met())),
+            Cell::new(&status(row.out_of_pocket, row.out_of_pocket_max, row.is_out_of_pocket_met())),
+        ]));
+    }
+    if !rest.is_empty() {
+        let met = rest.iter().filter(|row| row.is_out_of_pocket_met()).count();
+        let mut cells = vec![Cell::new(&other_label(rest.len(), "coverages", format)).style_spec("i")];
+        cells.extend((0..5).map(|_| Cell::new("")));
+        cells.push(Cell::new(&format!("{} met", met)));
+        table.add_row(Row::new(cells));
+    }
+    table.printstd();
+}
+
+/// Print average and worst-case submission-to-remittance time per payer
+fn print_payer_turnaround_report(turnaround: &[PayerTurnaround], format: &ReportFormat) {
+    println!("{}", "\n--- Payer Turnaround ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Payer").style_spec("bFc"),
+        Cell::new("Remitted").style_spec("bFc"),
+        Cell::new("Avg (s)").style_spec("bFc"),
+        Cell::new("Max (s)").style_spec("bFc"),
+    ]));
+    for row in turnaround {
+        table.add_row(Row::new(vec![
+            Cell::new(&row.payer_id),
+            Cell::new(&row.remitted.to_string()),
+            Cell::new(&format.number(row.avg_secs, 1)),
+            Cell::new(&format.number(row.max_secs, 1)),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Print the spread of each payer's time to acknowledge claims beside its time to pay them,
+/// with a dash for a payer that sent no acknowledgments
+fn print_payer_lag_report(lags: &[PayerLag], format: &ReportFormat) {
+    println!("{}", "\n--- Payer Ack vs Payment Lag ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(
+        ["Payer", "Acked", "Ack p50 (s)", "Ack p95 (s)", "Ack Max (s)", "Remitted", "Pay p50 (s)", "Pay p95 (s)", "Pay Max (s)"]
+            .into_iter()
+            .map(|title| Cell::new(title).style_spec("bFc"))
+            .collect(),
+    ));
+    for row in lags {
+        let secs = |secs: f64, count: u32| match count {
+            0 => "-".to_string(),
+            _ => format.number(secs, 1),
         };
-        aging_buckets.entry(payer_id).or_insert([0; 4])[bucket] += 1;
+        table.add_row(Row::new(vec![
+            Cell::new(&row.payer_id),
+            Cell::new(&row.acknowledged.to_string()),
+            Cell::new(&secs(row.ack_p50_secs, row.acknowledged)),
+            Cell::new(&secs(row.ack_p95_secs, row.acknowledged)),
+            Cell::new(&secs(row.ack_max_secs, row.acknowledged)),
+            Cell::new(&row.remitted.to_string()),
+            Cell::new(&secs(row.payment_p50_secs, row.remitted)),
+            Cell::new(&secs(row.payment_p95_secs, row.remitted)),
+            Cell::new(&secs(row.payment_max_secs, row.remitted)),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Print each payer's SLA and how many claims met it, breaches highlighted
+fn print_sla_report(compliance: &[SlaCompliance], format: &ReportFormat) {
+    println!("{}", "\n--- SLA Compliance ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Payer").style_spec("bFc"),
+        Cell::new("Target").style_spec("bFc"),
+        Cell::new("Within (days)").style_spec("bFc"),
+        Cell::new("On Time").style_spec("bFc"),
+        Cell::new("Late").style_spec("bFc"),
+        Cell::new("Overdue").style_spec("bFc"),
+        Cell::new("Compliance").style_spec("bFc"),
+    ]));
+    for row in compliance {
+        let compliance = row.compliance.map_or("-".to_string(), |compliance| format.percent(compliance, 1));
+        let style = if row.breached { "Fr" } else { "" };
+        table.add_row(Row::new(vec![
+            Cell::new(&row.payer_id),
+            Cell::new(&format.percent(row.target, 1)),
+            Cell::new(&format.number(row.within_days, 1)),
+            Cell::new(&row.on_time.to_string()),
+            Cell::new(&row.late.to_string()),
+            Cell::new(&row.overdue.to_string()),
+            Cell::new(&compliance).style_spec(style),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Print each billing worker's load and output, with the unassigned backlog and overall throughput
+fn print_workqueue_report(stats: &WorkqueueStats, format: &ReportFormat) {
+    println!("{}", "\n--- Workqueue ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Worker").style_spec("bFc"),
+        Cell::new("Held").style_spec("bFc"),
+        Cell::new("Resolved").style_spec("bFc"),
+        Cell::new("Per Day").style_spec("bFc"),
+        Cell::new("Utilization").style_spec("bFc"),
+    ]));
+    for worker in &stats.workers {
+        table.add_row(Row::new(vec![
+            Cell::new(&worker.name),
+            Cell::new(&format!("{}/{}", worker.held, worker.capacity)),
+            Cell::new(&worker.resolved.to_string()),
+            Cell::new(&format.number(worker.throughput, 1)),
+            Cell::new(&format.percent(worker.utilization, 0)),
+        ]));
+    }
+    table.add_row(Row::new(vec![
+        Cell::new("TOTAL").style_spec("b"),
+        Cell::new(&stats.workers.iter().map(|worker| worker.held).sum::<usize>().to_string()).style_spec("b"),
+        Cell::new(&stats.resolved.to_string()).style_spec("b"),
+        Cell::new(&format.number(stats.throughput, 1)).style_spec("b"),
+        Cell::new(""),
+    ]));
+    table.printstd();
+    let backlog: Vec<String> = stats.backlog.iter().map(|(kind, count)| format!("{} {}", count, kind)).collect();
+    let backlog = if backlog.is_empty() { "none".to_string() } else { backlog.join(", ") };
+    let wait = stats.avg_wait_days.map_or("-".to_string(), |days| format.number(days, 1));
+    println!(
+        "Backlog: {} unassigned ({}); {} open; average wait {} days",
+        stats.backlog_total(),
+        backlog,
+        format.money(stats.open_amount),
+        wait
+    );
+}
+
+/// Print open AR and the payer cash expected on it within the horizon, per payer with totals
+fn print_forecast_report(forecast: &[PayerForecast], horizon_days: f64, format: &ReportFormat) {
+    println!("{}", format!("\n--- Expected Collections (next {} days) ---", format.number(horizon_days, 0)).bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Payer").style_spec("bFc"),
+        Cell::new("Open Claims").style_spec("bFc"),
+        Cell::new("Open AR").style_spec("bFc"),
+        Cell::new("Expected").style_spec("bFc"),
+        Cell::new("No History").style_spec("bFc"),
+    ]));
+    let mut total = PayerForecast::default();
+    for row in forecast {
+        table.add_row(Row::new(vec![
+            Cell::new(&row.payer_id),
+            Cell::new(&row.open_claims.to_string()),
+            Cell::new(&format.money(row.open_ar)),
+            Cell::new(&format.money(row.expected)),
+            Cell::new(&row.unforecast.to_string()),
+        ]));
+        total.open_claims += row.open_claims;
+        total.open_ar += row.open_ar;
+        total.expected += row.expected;
+        total.unforecast += row.unforecast;
+    }
+    table.add_row(Row::new(vec![
+        Cell::new("TOTAL").style_spec("bFc"),
+        Cell::new(&total.open_claims.to_string()),
+        Cell::new(&format.money(total.open_ar)),
+        Cell::new(&format.money(total.expected)),
+        Cell::new(&total.unforecast.to_string()),
+    ]));
+    table.printstd();
+}
+
+/// Print self-pay collections: patient responsibility vs collected vs outstanding
+fn print_patient_ar_report(ledger: &Ledger, format: &ReportFormat, deidentification: &Deidentification) {
+    let balances = ledger.patient_balances();
+
+    println!("{}", "\n--- Patient AR (Self-Pay) ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Patient").style_spec("bFc"),
+        Cell::new("Responsibility").style_spec("bFc"),
+        Cell::new("Collected").style_spec("bFc"),
+        Cell::new("Outstanding").style_spec("bFc"),
+    ]));
+    let mut balances: Vec<(MemberId, &PatientBalance)> =
+        balances.iter().map(|(patient, balance)| (deidentification.member_id(patient), balance)).collect();
+    balances.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    let total_resp: f64 = balances.iter().map(|(_, balance)| balance.responsibility).sum();
+    let total_collected: f64 = balances.iter().map(|(_, balance)| balance.collected).sum();
+    let (top, rest) = format.top_rows(&balances, |(_, balance)| balance.outstanding());
+    for (patient, balance) in top {
+        table.add_row(Row::new(vec![
+            Cell::new(patient),
+            Cell::new(&format.money(balance.responsibility)),
+            Cell::new(&format.money(balance.collected)),
+            Cell::new(&format.money(balance.outstanding())),
+        ]));
+    }
+    if !rest.is_empty() {
+        let responsibility: f64 = rest.iter().map(|(_, balance)| balance.responsibility).sum();
+        let collected: f64 = rest.iter().map(|(_, balance)| balance.collected).sum();
+        table.add_row(Row::new(vec![
+            Cell::new(&other_label(rest.len(), "patients", format)).style_spec("i"),
+            Cell::new(&format.money(responsibility)),
+            Cell::new(&format.money(collected)),
+            Cell::new(&format.money(rest.iter().map(|(_, balance)| balance.outstanding()).sum())),
+        ]));
     }
+    table.add_row(Row::new(vec![
+        Cell::new("TOTAL").style_spec("bFc"),
+        Cell::new(&format.money(total_resp)).style_spec("bFc"),
+        Cell::new(&format.money(total_collected)).style_spec("bFc"),
+        Cell::new(&format.money(total_resp - total_collected)).style_spec("bFc"),
+    ]));
+    table.printstd();
 }
 
-fn update_patient_summary(status: &ClaimStatus, patient_summary: &mut HashMap<String, Totals>) {
-    if let ClaimStatus::Remitted(record) = status {
-        let entry = patient_summary
-            .entry(record.patient_id().to_string())
-            .or_default();
-        for line in &record.remittance.service_line_remittances {
-            entry.copay += line.copay_amount;
-            entry.coins += line.coinsurance_amount;
-            entry.deduct += line.deductible_amount;
+/// Print outstanding patient balances per patient by age bucket, with what went to collections
+fn print_patient_aging_report(aging: &[PatientAging], format: &ReportFormat) {
+    println!("{}", "\n--- Patient AR Aging ---".bold().blue());
+    let mut table = Table::new();
+    let mut header = vec![Cell::new("Patient").style_spec("bFc")];
+    header.extend(PATIENT_AGING_BUCKET_LABELS.iter().map(|label| Cell::new(label).style_spec("bFc")));
+    header.push(Cell::new("Collections").style_spec("bFc"));
+    table.add_row(Row::new(header));
+    let sum = |rows: &[&PatientAging]| {
+        rows.iter().fold(PatientAging::default(), |mut sum, row| {
+            for (total, amount) in sum.buckets.iter_mut().zip(row.buckets) {
+                *total += amount;
+            }
+            sum.in_collections += row.in_collections;
+            sum
+        })
+    };
+    let money_cells = |row: &PatientAging| {
+        let mut cells: Vec<Cell> = row.buckets.iter().map(|amount| Cell::new(&format.money(*amount))).collect();
+        cells.push(Cell::new(&format.money(row.in_collections)));
+        cells
+    };
+    let (top, rest) = format.top_rows(aging, |row| row.total() + row.in_collections);
+    for row in &top {
+        let mut cells = vec![Cell::new(&row.patient_id)];
+        cells.extend(money_cells(row));
+        table.add_row(Row::new(cells));
+    }
+    if !rest.is_empty() {
+        let mut cells = vec![Cell::new(&other_label(rest.len(), "patients", format)).style_spec("i")];
+        cells.extend(money_cells(&sum(&rest)));
+        table.add_row(Row::new(cells));
+    }
+    let totals = sum(&aging.iter().collect::<Vec<_>>());
+    let mut total_row = vec![Cell::new("TOTAL").style_spec("bFc")];
+    total_row.extend(totals.buckets.iter().map(|total| Cell::new(&format.money(*total)).style_spec("bFc")));
+    total_row.push(Cell::new(&format.money(totals.in_collections)).style_spec("bFc"));
+    table.add_row(Row::new(total_row));
+    table.printstd();
+}
+
+/// Print adjustments by payer and reason (contractual, small balance, bad debt, collections)
+fn print_write_off_report(ledger: &Ledger, format: &ReportFormat) {
+    let summary = ledger.write_off_summary();
+
+    println!("{}", "\n--- Write-off Summary ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Payer").style_spec("bFc"),
+        Cell::new("Reason").style_spec("bFc"),
+        Cell::new("Amount").style_spec("bFc"),
+    ]));
+    let mut total = 0.0;
+    for (payer, reasons) in &summary {
+        for (reason, amount) in reasons {
+            total += amount;
+            table.add_row(Row::new(vec![
+                Cell::new(payer),
+                Cell::new(&reason.to_string()),
+                Cell::new(&format.money(*amount)),
+            ]));
         }
     }
+    table.add_row(Row::new(vec![
+        Cell::new("TOTAL").style_spec("bFc"),
+        Cell::new(""),
+        Cell::new(&format.money(total)).style_spec("bFc"),
+    ]));
+    table.printstd();
+}
+
+/// Print a closed accounting period: charges, adjustments, net revenue, cash, and AR roll-forward
+pub fn print_period_close_report(tenant_id: &str, report: &PeriodReport, format: &ReportFormat) {
+    println!(
+        "{}",
+        format!(
+            "\n--- Period Close {} to {} ({}) ---",
+            report.period_start,
+            report.period_end.pred_opt().unwrap_or(report.period_end),
+            tenant_id
+        )
+        .bold()
+        .blue()
+    );
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Line").style_spec("bFc"),
+        Cell::new("Amount").style_spec("bFc"),
+    ]));
+    // deductions are shown in parentheses, accounting style
+    let lines = [
+        ("Opening AR", format.money(report.opening_ar)),
+        ("Gross Charges", format.money(report.gross_charges)),
+        ("Contractual Adjustments", format!("({})", format.money(report.contractual_adjustments))),
+        ("Net Revenue", format.money(report.net_revenue())),
+        ("Other Adjustments", format!("({})", format.money(report.other_adjustments))),
+        ("Payer Cash", format!("({})", format.money(report.payer_cash))),
+        ("Patient Cash", format!("({})", format.money(report.patient_cash))),
+    ];
+    for (label, amount) in lines {
+        table.add_row(Row::new(vec![Cell::new(label), Cell::new(&amount)]));
+    }
+    table.add_row(Row::new(vec![
+        Cell::new("ENDING AR").style_spec("bFc"),
+        Cell::new(&format.money(report.ending_ar)).style_spec("bFc"),
+    ]));
+    table.printstd();
+}
+
+/// Print a billing organization's bank deposits against its posted payer payments, listing
+/// deposits the postings do not account for and deposited claims that were never posted
+pub fn print_bank_reconciliation_report(
+    tenant_id: &str,
+    date: NaiveDate,
+    report: &BankReconciliation,
+    format: &ReportFormat,
+) {
+    println!("{}", format!("\n--- Bank Reconciliation {} ({}) ---", date, tenant_id).bold().blue());
+    let mut table = Table::new();
+    for (label, value) in [
+        ("Deposits", report.deposits.to_string()),
+        ("Deposited", format.money(report.deposited)),
+        ("Matched", report.matched.to_string()),
+        ("Unmatched", report.unmatched_deposits.len().to_string()),
+        ("Posted, Not Deposited", format.money(report.undeposited)),
+    ] {
+        table.add_row(Row::new(vec![Cell::new(label).style_spec("bFc"), Cell::new(&value)]));
+    }
+    table.printstd();
+    if !report.unmatched_deposits.is_empty() {
+        println!("{}", "Unmatched deposits".bold());
+        let mut table = Table::new();
+        table.add_row(Row::new(vec![
+            Cell::new("Trace").style_spec("bFc"),
+            Cell::new("Payer").style_spec("bFc"),
+            Cell::new("Method").style_spec("bFc"),
+            Cell::new("Deposited On").style_spec("bFc"),
+            Cell::new("Amount").style_spec("bFc"),
+            Cell::new("Unposted Claims").style_spec("bFc"),
+        ]));
+        for deposit in &report.unmatched_deposits {
+            table.add_row(Row::new(vec![
+                Cell::new(&deposit.trace_number),
+                Cell::new(&deposit.payer_id),
+                Cell::new(&deposit.method.to_string()),
+                Cell::new(&deposit.deposited_on.to_string()),
+                Cell::new(&format.money(deposit.amount)),
+                Cell::new(&deposit.unposted_claims.to_string()),
+            ]));
+        }
+        table.printstd();
+    }
+    if !report.unposted_remittances.is_empty() {
+        println!("{}", "Unposted remittances".bold());
+        let mut table = Table::new();
+        table.add_row(Row::new(vec![
+            Cell::new("Claim").style_spec("bFc"),
+            Cell::new("Payer").style_spec("bFc"),
+            Cell::new("Trace").style_spec("bFc"),
+            Cell::new("Deposited").style_spec("bFc"),
+            Cell::new("Posted").style_spec("bFc"),
+        ]));
+        for remittance in &report.unposted_remittances {
+            table.add_row(Row::new(vec![
+                Cell::new(&remittance.claim_id),
+                Cell::new(&remittance.payer_id),
+                Cell::new(&remittance.trace_number),
+                Cell::new(&format.money(remittance.deposited)),
+                Cell::new(&format.money(remittance.posted)),
+            ]));
+        }
+        table.printstd();
+    }
+}
+
+/// Print claim volume and dollars per tenant with an all-tenant total
+fn print_tenant_summary_report(summary: &[TenantSummary], format: &ReportFormat) {
+    println!("{}", "\n--- Tenant Summary ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Tenant").style_spec("bFc"),
+        Cell::new("Pending").style_spec("bFc"),
+        Cell::new("Remitted").style_spec("bFc"),
+        Cell::new("Voided").style_spec("bFc"),
+        Cell::new("Replaced").style_spec("bFc"),
+        Cell::new("Billed").style_spec("bFc"),
+        Cell::new("Payer Paid").style_spec("bFc"),
+    ]));
+    let mut total = TenantSummary::default();
+    for totals in summary {
+        total.pending += totals.pending;
+        total.remitted += totals.remitted;
+        total.voided += totals.voided;
+        total.replaced += totals.replaced;
+        total.billed += totals.billed;
+        total.payer_paid += totals.payer_paid;
+        table.add_row(Row::new(vec![
+            Cell::new(&totals.tenant_id),
+            Cell::new(&totals.pending.to_string()),
+            Cell::new(&totals.remitted.to_string()),
+            Cell::new(&totals.voided.to_string()),
+            Cell::new(&totals.replaced.to_string()),
+            Cell::new(&format.money(totals.billed)),
+            Cell::new(&format.money(totals.payer_paid)),
+        ]));
+    }
+    table.add_row(Row::new(vec![
+        Cell::new("TOTAL").style_spec("bFc"),
+        Cell::new(&total.pending.to_string()).style_spec("bFc"),
+        Cell::new(&total.remitted.to_string()).style_spec("bFc"),
+        Cell::new(&total.voided.to_string()).style_spec("bFc"),
+        Cell::new(&total.replaced.to_string()).style_spec("bFc"),
+        Cell::new(&format.money(total.billed)).style_spec("bFc"),
+        Cell::new(&format.money(total.payer_paid)).style_spec("bFc"),
+    ]));
+    table.printstd();
+}
+
+/// Print claims, charges, payments, and denial rate per rendering provider
+fn print_provider_report(summary: &[ProviderSummary], format: &ReportFormat) {
+    println!("{}", "\n--- Provider Productivity ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("NPI").style_spec("bFc"),
+        Cell::new("Provider").style_spec("bFc"),
+        Cell::new("Claims").style_spec("bFc"),
+        Cell::new("Charges").style_spec("bFc"),
+        Cell::new("Payments").style_spec("bFc"),
+        Cell::new("Denial Rate").style_spec("bFc"),
+    ]));
+    let (top, rest) = format.top_rows(summary, |totals| totals.charges);
+    let row = |npi: &str, name: &str, totals: &ProviderSummary| {
+        Row::new(vec![
+            Cell::new(npi),
+            Cell::new(name),
+            Cell::new(&totals.claims.to_string()),
+            Cell::new(&format.money(totals.charges)),
+            Cell::new(&format.money(totals.payer_paid)),
+            Cell::new(&format.percent(totals.denial_rate(), 1)),
+        ])
+    };
+    for totals in top {
+        table.add_row(row(&totals.npi, &totals.name, totals));
+    }
+    if !rest.is_empty() {
+        let other = rest.iter().fold(ProviderSummary::default(), |mut other, totals| {
+            other.claims += totals.claims;
+            other.remitted += totals.remitted;
+            other.denied += totals.denied;
+            other.charges += totals.charges;
+            other.payer_paid += totals.payer_paid;
+            other
+        });
+        table.add_row(row(&other_label(rest.len(), "providers", format), "", &other));
+    }
+    table.printstd();
+}
+
+/// Print billed, paid, and adjusted dollars by procedure code
+fn print_procedure_report(summary: &[ProcedureSummary], format: &ReportFormat) {
+    println!("{}", "\n--- Procedure Code Revenue ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Procedure").style_spec("bFc"),
+        Cell::new("Lines").style_spec("bFc"),
+        Cell::new("Billed").style_spec("bFc"),
+        Cell::new("Payer Paid").style_spec("bFc"),
+        Cell::new("Patient Resp").style_spec("bFc"),
+        Cell::new("Adjusted").style_spec("bFc"),
+    ]));
+    let (top, rest) = format.top_rows(summary, |totals| totals.billed);
+    let row = |procedure: &str, totals: &ProcedureSummary| {
+        Row::new(vec![
+            Cell::new(procedure),
+            Cell::new(&totals.lines.to_string()),
+            Cell::new(&format.money(totals.billed)),
+            Cell::new(&format.money(totals.payer_paid)),
+            Cell::new(&format.money(totals.patient_responsibility)),
+            Cell::new(&format.money(totals.adjusted)),
+        ])
+    };
+    for totals in top {
+        table.add_row(row(&totals.procedure_code, totals));
+    }
+    if !rest.is_empty() {
+        let other = rest.iter().fold(ProcedureSummary::default(), |mut other, totals| {
+            other.lines += totals.lines;
+            other.billed += totals.billed;
+            other.payer_paid += totals.payer_paid;
+            other.patient_responsibility += totals.patient_responsibility;
+            other.adjusted += totals.adjusted;
+            other
+        });
+        table.add_row(row(&other_label(rest.len(), "procedures", format), &other));
+    }
+    table.printstd();
+}
+
+/// Print remitted revenue from telehealth vs in-person lines, with each setting's share of the total
+fn print_telehealth_mix_report(mix: &[TelehealthMix], format: &ReportFormat) {
+    println!("{}", "\n--- Telehealth vs In-Person Revenue ---".bold().blue());
+    let total: f64 = mix.iter().map(TelehealthMix::revenue).sum();
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Setting").style_spec("bFc"),
+        Cell::new("Lines").style_spec("bFc"),
+        Cell::new("Billed").style_spec("bFc"),
+        Cell::new("Payer Paid").style_spec("bFc"),
+        Cell::new("Patient Resp").style_spec("bFc"),
+        Cell::new("Revenue Share").style_spec("bFc"),
+    ]));
+    for totals in mix {
+        let share = if total > 0.0 { totals.revenue() / total } else { 0.0 };
+        table.add_row(Row::new(vec![
+            Cell::new(&totals.setting.to_string()),
+            Cell::new(&totals.lines.to_string()),
+            Cell::new(&format.money(totals.billed)),
+            Cell::new(&format.money(totals.payer_paid)),
+            Cell::new(&format.money(totals.patient_responsibility)),
+            Cell::new(&format.percent(share, 1)),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Print claims split for their payer's line limit, reassembled from their children
+fn print_split_claims_report(splits: &[SplitClaim], format: &ReportFormat) {
+    println!("{}", "\n--- Split Claims ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Parent Claim").style_spec("bFc"),
+        Cell::new("Payer").style_spec("bFc"),
+        Cell::new("Children").style_spec("bFc"),
+        Cell::new("Remitted").style_spec("bFc"),
+        Cell::new("Billed").style_spec("bFc"),
+        Cell::new("Payer Paid").style_spec("bFc"),
+        Cell::new("Patient Resp").style_spec("bFc"),
+    ]));
+    let (top, rest) = format.top_rows(splits, |split| split.billed);
+    let row = |parent: &str, payer: &str, split: &SplitClaim| {
+        Row::new(vec![
+            Cell::new(parent),
+            Cell::new(payer),
+            Cell::new(&split.children.to_string()),
+            Cell::new(&split.remitted.to_string()),
+            Cell::new(&format.money(split.billed)),
+            Cell::new(&format.money(split.payer_paid)),
+            Cell::new(&format.money(split.patient_responsibility)),
+        ])
+    };
+    for split in top {
+        table.add_row(row(&split.parent_claim_id, &split.payer_id, split));
+    }
+    if let Some(first) = rest.first() {
+        let other = rest[1..].iter().fold(SplitClaim::clone(first), |mut other, split| {
+            other.children += split.children;
+            other.remitted += split.remitted;
+            other.billed += split.billed;
+            other.payer_paid += split.payer_paid;
+            other.patient_responsibility += split.patient_responsibility;
+            other
+        });
+        table.add_row(row(&other_label(rest.len(), "claims", format), "", &other));
+    }
+    table.printstd();
+}
+
+/// Print the clearinghouse's audit of remittances against submitted claims, per payer
+fn print_reconciliation_report(summary: &[PayerReconciliation], format: &ReportFormat) {
+    println!("{}", "\n--- Remittance Reconciliation ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Payer").style_spec("bFc"),
+        Cell::new("Audited").style_spec("bFc"),
+        Cell::new("Discrepant").style_spec("bFc"),
+        Cell::new("Discrepancies").style_spec("bFc"),
+        Cell::new("Variance").style_spec("bFc"),
+    ]));
+    for totals in summary {
+        table.add_row(Row::new(vec![
+            Cell::new(&totals.payer_id),
+            Cell::new(&totals.audited.to_string()),
+            Cell::new(&totals.discrepant.to_string()),
+            Cell::new(&totals.discrepancies.to_string()),
+            Cell::new(&format.money(totals.variance)),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Print remittances that failed the payer's balancing check, held or flagged, or answer lines not billed
+///
+/// Lists the first `max_rows`; exceptions are worked one by one, so the rest are only counted
+fn print_exceptions_report(exceptions: &[RemittanceException], format: &ReportFormat) {
+    println!("{}", "\n--- Remittance Exceptions ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Claim").style_spec("bFc"),
+        Cell::new("Payer").style_spec("bFc"),
+        Cell::new("Status").style_spec("bFc"),
+        Cell::new("Reason").style_spec("bFc"),
+        Cell::new("Unmatched Lines").style_spec("bFc"),
+    ]));
+    let (top, rest) = format.top_rows(exceptions, |_| 0.0);
+    for exception in top {
+        table.add_row(Row::new(vec![
+            Cell::new(&exception.claim_id),
+            Cell::new(&exception.payer_id),
+            Cell::new(if exception.held { "held" } else { "flagged" }),
+            Cell::new(&exception.reason),
+            Cell::new(&exception.unmatched_lines.join(", ")),
+        ]));
+    }
+    if !rest.is_empty() {
+        let held = rest.iter().filter(|exception| exception.held).count();
+        table.add_row(Row::new(vec![
+            Cell::new(&other_label(rest.len(), "exceptions", format)).style_spec("i"),
+            Cell::new(""),
+            Cell::new(&format!("{} held", held)),
+            Cell::new(""),
+            Cell::new(""),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Print average, percentile, and worst-case time between pipeline stages
+fn print_stage_latency_report(latency: &[StageLatency], format: &ReportFormat) {
+    println!("{}", "\n--- Latency by Stage ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Stage").style_spec("bFc"),
+        Cell::new("Remitted").style_spec("bFc"),
+        Cell::new("Avg (s)").style_spec("bFc"),
+        Cell::new("p50 (s)").style_spec("bFc"),
+        Cell::new("p95 (s)").style_spec("bFc"),
+        Cell::new("p99 (s)").style_spec("bFc"),
+        Cell::new("Max (s)").style_spec("bFc"),
+    ]));
+    for row in latency {
+        table.add_row(Row::new(vec![
+            Cell::new(&format!("{} → {}", row.from, row.to)),
+            Cell::new(&row.remitted.to_string()),
+            Cell::new(&format.number(row.avg_secs, 3)),
+            Cell::new(&format.number(row.p50_secs, 3)),
+            Cell::new(&format.number(row.p95_secs, 3)),
+            Cell::new(&format.number(row.p99_secs, 3)),
+            Cell::new(&format.number(row.max_secs, 3)),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Print the sustained throughput, peak memory, and stage latency of a load test
+pub fn print_load_test_report(report: &LoadTestReport, format: &ReportFormat) {
+    println!("{}", "\n--- Load Test ---".bold().blue());
+    let mut table = Table::new();
+    let memory = match report.peak_memory_kib {
+        Some(kib) => format!("{} MiB", format.number(kib as f64 / 1024.0, 1)),
+        None => "unavailable".to_string(),
+    };
+    for (label, value) in [
+        ("Claims", report.claims.to_string()),
+        ("Remitted", report.remitted.to_string()),
+        ("Elapsed (s)", format.number(report.elapsed.as_secs_f64(), 3)),
+        ("Throughput (claims/s)", format.number(report.throughput(), 1)),
+        ("Peak memory", memory),
+    ] {
+        table.add_row(Row::new(vec![Cell::new(label).style_spec("bFc"), Cell::new(&value)]));
+    }
+    table.printstd();
+    print_stage_latency_report(&report.stage_latency, format);
+}
+
+/// Print outstanding dollars per payer by days since submission, from a stored run
+pub fn print_run_ar_aging_report(as_of: NaiveDate, aging: &[PayerAgingDollars], format: &ReportFormat) {
+    println!("{}", format!("\n--- AR Aging as of {} ---", as_of).bold().blue());
+    let mut table = Table::new();
+    let mut header = vec![Cell::new("Payer").style_spec("bFc"), Cell::new("Claims").style_spec("bFc")];
+    header.extend(AR_AGING_DAY_LABELS.iter().map(|label| Cell::new(label).style_spec("bFc")));
+    header.push(Cell::new("Total").style_spec("bFc"));
+    table.add_row(Row::new(header));
+    let mut totals = PayerAgingDollars { payer_id: PayerId::new("TOTAL"), claims: 0, buckets: [0.0; 4] };
+    for row in aging {
+        let mut cells = vec![Cell::new(&row.payer_id), Cell::new(&row.claims.to_string())];
+        for (total, amount) in totals.buckets.iter_mut().zip(row.buckets) {
+            *total += amount;
+            cells.push(Cell::new(&format.money(amount)));
+        }
+        totals.claims += row.claims;
+        cells.push(Cell::new(&format.money(row.total())));
+        table.add_row(Row::new(cells));
+    }
+    let mut total_row = vec![
+        Cell::new("TOTAL").style_spec("bFc"),
+        Cell::new(&totals.claims.to_string()).style_spec("bFc"),
+    ];
+    total_row.extend(totals.buckets.iter().map(|total| Cell::new(&format.money(*total)).style_spec("bFc")));
+    total_row.push(Cell::new(&format.money(totals.total())).style_spec("bFc"));
+    table.add_row(Row::new(total_row));
+    table.printstd();
+}
+
+/// Print denied lines and charges by claim adjustment reason code, from a stored run
+pub fn print_denials_report(denials: &[DenialsByReason], format: &ReportFormat) {
+    println!("{}", "\n--- Denials by Reason ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Reason").style_spec("bFc"),
+        Cell::new("Claims").style_spec("bFc"),
+        Cell::new("Lines").style_spec("bFc"),
+        Cell::new("Denied").style_spec("bFc"),
+    ]));
+    for row in denials {
+        table.add_row(Row::new(vec![
+            Cell::new(&row.reason_code),
+            Cell::new(&row.claims.to_string()),
+            Cell::new(&row.lines.to_string()),
+            Cell::new(&format.money(row.amount)),
+        ]));
+    }
+    table.add_row(Row::new(vec![
+        Cell::new("TOTAL").style_spec("bFc"),
+        Cell::new(""),
+        Cell::new(&denials.iter().map(|row| row.lines).sum::<u32>().to_string()).style_spec("bFc"),
+        Cell::new(&format.money(denials.iter().map(|row| row.amount).sum())).style_spec("bFc"),
+    ]));
+    table.printstd();
+}
+
+/// Print payer and patient payments posted each week, from a stored run
+pub fn print_weekly_payments_report(weeks: &[WeeklyPayments], format: &ReportFormat) {
+    println!("{}", "\n--- Payments by Week ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Week Of").style_spec("bFc"),
+        Cell::new("Payer").style_spec("bFc"),
+        Cell::new("Patient").style_spec("bFc"),
+        Cell::new("Total").style_spec("bFc"),
+    ]));
+    let (mut payer, mut patient) = (0.0, 0.0);
+    for week in weeks {
+        payer += week.payer;
+        patient += week.patient;
+        table.add_row(Row::new(vec![
+            Cell::new(&week.week_start.to_string()),
+            Cell::new(&format.money(week.payer)),
+            Cell::new(&format.money(week.patient)),
+            Cell::new(&format.money(week.payer + week.patient)),
+        ]));
+    }
+    table.add_row(Row::new(vec![
+        Cell::new("TOTAL").style_spec("bFc"),
+        Cell::new(&format.money(payer)).style_spec("bFc"),
+        Cell::new(&format.money(patient)).style_spec("bFc"),
+        Cell::new(&format.money(payer + patient)).style_spec("bFc"),
+    ]));
+    table.printstd();
+}
+
+/// Print each payer's revenue under baseline and proposed contract terms, and the difference
+pub fn print_what_if_report(impacts: &[PayerImpact], format: &ReportFormat) {
+    println!("{}", "\n--- Contract Revenue Impact ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Payer").style_spec("bFc"),
+        Cell::new("Claims").style_spec("bFc"),
+        Cell::new("Billed").style_spec("bFc"),
+        Cell::new("Baseline Paid").style_spec("bFc"),
+        Cell::new("Proposed Paid").style_spec("bFc"),
+        Cell::new("Baseline Patient").style_spec("bFc"),
+        Cell::new("Proposed Patient").style_spec("bFc"),
+        Cell::new("Denied Lines").style_spec("bFc"),
+        Cell::new("Revenue Change").style_spec("bFc"),
+        Cell::new("Change %").style_spec("bFc"),
+    ]));
+    let total = impacts.iter().fold(PayerImpact::default(), |total, impact| PayerImpact {
+        claims: total.claims + impact.claims,
+        billed: total.billed + impact.billed,
+        baseline_paid: total.baseline_paid + impact.baseline_paid,
+        proposed_paid: total.proposed_paid + impact.proposed_paid,
+        baseline_patient: total.baseline_patient + impact.baseline_patient,
+        proposed_patient: total.proposed_patient + impact.proposed_patient,
+        baseline_denied_lines: total.baseline_denied_lines + impact.baseline_denied_lines,
+        proposed_denied_lines: total.proposed_denied_lines + impact.proposed_denied_lines,
+        ..total
+    });
+    let row = |label: &str, impact: &PayerImpact, style: &str| {
+        let baseline = impact.baseline_paid + impact.baseline_patient;
+        let change = if baseline == 0.0 { "-".to_string() } else { format.percent(impact.revenue_change() / baseline, 1) };
+        Row::new(vec![
+            Cell::new(label).style_spec(style),
+            Cell::new(&impact.claims.to_string()).style_spec(style),
+            Cell::new(&format.money(impact.billed)).style_spec(style),
+            Cell::new(&format.money(impact.baseline_paid)).style_spec(style),
+            Cell::new(&format.money(impact.proposed_paid)).style_spec(style),
+            Cell::new(&format.money(impact.baseline_patient)).style_spec(style),
+            Cell::new(&format.money(impact.proposed_patient)).style_spec(style),
+            Cell::new(&format!("{} -> {}", impact.baseline_denied_lines, impact.proposed_denied_lines)).style_spec(style),
+            Cell::new(&format.money(impact.revenue_change())).style_spec(style),
+            Cell::new(&change).style_spec(style),
+        ])
+    };
+    for impact in impacts {
+        table.add_row(row(impact.payer_id.as_str(), impact, ""));
+    }
+    table.add_row(row("TOTAL", &total, "bFc"));
+    table.printstd();
+}
+
+/// Print the spread of each key metric across Monte Carlo runs, with its 50% and 90% bands
+pub fn print_monte_carlo_report(report: &MonteCarloReport, format: &ReportFormat) {
+    println!("{}", format!("\n--- Monte Carlo ({} runs) ---", report.runs.len()).bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(
+        ["Metric", "Mean", "Std Dev", "Min", "P5", "P25", "P50", "P75", "P95", "Max"]
+            .iter()
+            .map(|label| Cell::new(label).style_spec("bFc"))
+            .collect(),
+    ));
+    let row = |label: &str, distribution: &Distribution, show: &dyn Fn(f64) -> String| {
+        let Distribution { mean, std_dev, min, p5, p25, p50, p75, p95, max } = *distribution;
+        let mut cells = vec![Cell::new(label).style_spec("bFc")];
+        cells.extend([mean, std_dev, min, p5, p25, p50, p75, p95, max].map(|value| Cell::new(&show(value))));
+        Row::new(cells)
+    };
+    table.add_row(row("Net Revenue", &report.net_revenue, &|value| format.money(value)));
+    table.add_row(row("Days in AR", &report.days_in_ar, &|value| format.number(value, 1)));
+    table.add_row(row("Denial Rate", &report.denial_rate, &|value| format.percent(value, 1)));
+    table.printstd();
+}
+
+/// Print each channel's depth and the message the debugger holds on it, then the next to step
+pub(crate) fn print_debug_snapshot(snapshot: &DebugSnapshot) {
+    let state = if snapshot.paused { "paused" } else { "running" };
+    println!("{}", format!("\n--- Debugger ({}, {} steps) ---", state, snapshot.steps).bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Channel").style_spec("bFc"),
+        Cell::new("Queued").style_spec("bFc"),
+        Cell::new("Held").style_spec("bFc"),
+        Cell::new("Next Message").style_spec("bFc"),
+    ]));
+    for view in &snapshot.channels {
+        table.add_row(Row::new(vec![
+            Cell::new(&view.channel),
+            Cell::new(&view.queued.map_or("-".to_string(), |queued| queued.to_string())),
+            Cell::new(&view.held.to_string()),
+            Cell::new(view.next.as_deref().unwrap_or("-")),
+        ]));
+    }
+    table.printstd();
+    match &snapshot.next {
+        Some(held) => println!("Next step: #{} {}: {}", held.seq, held.channel, held.message),
+        None => println!("Next step: no message waiting"),
+    }
+}
+
+/// Print average and worst-case latency per priority class, high priority first
+fn print_priority_latency_report(latency: &[PriorityLatency], format: &ReportFormat) {
+    println!("{}", "\n--- Latency by Priority ---".bold().blue());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Priority").style_spec("bFc"),
+        Cell::new("Remitted").style_spec("bFc"),
+        Cell::new("Avg (s)").style_spec("bFc"),
+        Cell::new("Max (s)").style_spec("bFc"),
+    ]));
+    for row in latency {
+        table.add_row(Row::new(vec![
+            Cell::new(&row.priority.to_string()),
+            Cell::new(&row.remitted.to_string()),
+            Cell::new(&format.number(row.avg_secs, 1)),
+            Cell::new(&format.number(row.max_secs, 1)),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Write the procedure code revenue report as CSV, money in the report format's units and decimal notation
+pub fn write_procedure_csv<W: Write>(
+    mut writer: W,
+    records: &HashMap<ClaimKey, ClaimStatus>,
+    format: &ReportFormat,
+) -> std::io::Result<()> {
+    let delimiter = format.csv_delimiter().to_string();
+    let mut header = vec!["procedure_code".to_string(), "lines".to_string(), "units".to_string()];
+    header.extend(["billed", "payer_paid", "patient_responsibility", "adjusted"].map(|name| format.csv_money_column(name)));
+    writeln!(writer, "{}", header.join(&delimiter))?;
+    for totals in ReportEngine::new(records).procedure_summary() {
+        let row = [
+            csv_field(&totals.procedure_code, &delimiter),
+            totals.lines.to_string(),
+            totals.units.to_string(),
+            format.csv_money(totals.billed),
+            format.csv_money(totals.payer_paid),
+            format.csv_money(totals.patient_responsibility),
+            format.csv_money(totals.adjusted),
+        ];
+        writeln!(writer, "{}", row.join(&delimiter))?;
+    }
+    writer.flush()
+}
+
+/// Quote a CSV field if it contains the delimiter, a quote, or a newline
+fn csv_field(value: &str, delimiter: &str) -> String {
+    if value.contains(delimiter) || value.contains(['"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remittance::{Remittance, RemittanceRecord, ServiceLineRemittance};
+    use crate::report_format::{Locale, Units};
+    use crate::test_utils::mock_claim;
+    use std::time::Instant;
+
+    /// Test that the procedure code revenue report is exported as CSV.
+    /// Expected: A header row and one row per procedure code with billed, paid, and adjusted dollars; in de-DE thousands, semicolon-delimited with comma decimals.
+    #[test]
+    fn test_procedure_summary_csv() {
+        let now = Instant::now();
+        let mut claim = mock_claim();
+        claim.service_lines[0].service_line_id = "sl1".to_string();
+        claim.service_lines[0].unit_charge_amount = 157.5;
+        let line = ServiceLineRemittance::new("sl1", 120.0, 15.0, 10.0, 5.0, 7.5).unwrap();
+        let remittance = Remittance::new(&claim, vec![line]).unwrap();
+        let mut records = HashMap::new();
+        let status = ClaimStatus::Remitted(RemittanceRecord::new(claim, remittance, now, now));
+        records.insert(status.key(), status);
+
+        let mut csv = Vec::new();
+        write_procedure_csv(&mut csv, &records, &ReportFormat::default()).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("procedure_code,lines,units,billed,payer_paid,patient_responsibility,adjusted"));
+        assert!(lines.next().unwrap().ends_with(",120.00,30.00,7.50"));
+
+        let mut csv = Vec::new();
+        write_procedure_csv(&mut csv, &records, &ReportFormat::new(Locale::DeDe, Units::Thousands)).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("procedure_code;lines;units;billed_thousands;"));
+        assert!(csv.lines().nth(1).unwrap().ends_with(";0,12;0,03;0,01"));
+    }
+
+    /// Test that the row summing what a table left out counts in the report's locale.
+    /// Expected: 1,204 patients in en-US and 1.204 in de-DE.
+    #[test]
+    fn test_other_label_locale() {
+        assert_eq!(other_label(1204, "patients", &ReportFormat::default()), "OTHER (1,204 patients)");
+        assert_eq!(other_label(1204, "patients", &ReportFormat::new(Locale::DeDe, Units::Dollars)), "OTHER (1.204 patients)");
+    }
 }
@@ -0,0 +1,152 @@
+use chrono::{Months, NaiveDate};
+use rand::Rng;
+use std::collections::HashMap;
+
+use crate::clock;
+use crate::ids::{MemberId, PayerId};
+
+/// A member's period of coverage with one payer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Enrollment {
+    pub member_id: MemberId,
+    pub enrolled: NaiveDate,
+    /// Last day of coverage, once the member has left
+    pub terminated: Option<NaiveDate>,
+}
+
+impl Enrollment {
+    pub fn is_eligible_on(&self, date: NaiveDate) -> bool {
+        self.enrolled <= date && self.terminated.is_none_or(|last_day| date <= last_day)
+    }
+}
+
+/// Shape of a generated roster
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RosterOptions {
+    /// Members enrolled when the roster starts, each having joined within the two years before
+    pub members: usize,
+    /// Chance an enrolled member leaves in a given month, at its end; each leaver is
+    /// replaced by a new member enrolling on the first of the next month
+    pub monthly_churn: f64,
+    pub start: NaiveDate,
+    /// Months after `start` over which members churn
+    pub months: u32,
+}
+
+impl Default for RosterOptions {
+    fn default() -> Self {
+        Self {
+            members: 500,
+            monthly_churn: 0.02,
+            start: chrono::Utc::now().date_naive(),
+            months: 24,
+        }
+    }
+}
+
+impl RosterOptions {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.members == 0 {
+            return Err(anyhow::anyhow!("a roster needs at least one member"));
+        }
+        if !(0.0..=1.0).contains(&self.monthly_churn) {
+            return Err(anyhow::anyhow!("monthly churn must be between 0 and 1"));
+        }
+        Ok(())
+    }
+}
+
+/// A payer's members and when each was covered
+///
+/// Members not on the roster are not checked, so an empty roster finds everyone eligible
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Roster {
+    enrollments: Vec<Enrollment>,
+    index: HashMap<MemberId, usize>,
+}
+
+impl Roster {
+    pub fn new(enrollments: Vec<Enrollment>) -> Self {
+        let index = enrollments
+            .iter()
+            .enumerate()
+            .map(|(i, enrollment)| (enrollment.member_id.clone(), i))
+            .collect();
+        Self { enrollments, index }
+    }
+
+    /// Roster of members numbered `{payer_id}-000001`, ... churning month by month from `options.start`
+    pub fn generate(payer_id: &PayerId, options: &RosterOptions, rng: &mut impl Rng) -> anyhow::Result<Self> {
+        options.validate()?;
+        let member = |n: usize, enrolled| Enrollment {
+            member_id: MemberId::new(format!("{}-{:06}", payer_id, n)),
+            enrolled,
+            terminated: None,
+        };
+        let first_month = clock::month_start(options.start);
+        let mut enrollments: Vec<Enrollment> = (1..=options.members)
+            .map(|n| member(n, first_month - Months::new(rng.random_range(0..24))))
+            .collect();
+        for month in 0..options.months {
+            let next_month = clock::next_month_start(first_month + Months::new(month));
+            let last_day = next_month.pred_opt().expect("a month has a day before it");
+            let mut leavers = 0;
+            for enrollment in enrollments.iter_mut().filter(|e| e.terminated.is_none() && e.enrolled <= last_day) {
+                if rng.random_bool(options.monthly_churn) {
+                    enrollment.terminated = Some(last_day);
+                    leavers += 1;
+                }
+            }
+            for _ in 0..leavers {
+                enrollments.push(member(enrollments.len() + 1, next_month));
+            }
+        }
+        Ok(Self::new(enrollments))
+    }
+
+    pub fn enrollments(&self) -> &[Enrollment] {
+        &self.enrollments
+    }
+
+    pub fn enrollment(&self, member_id: &str) -> Option<&Enrollment> {
+        self.index.get(member_id).map(|&i| &self.enrollments[i])
+    }
+
+    /// Members covered on `date`
+    pub fn eligible_on(&self, date: NaiveDate) -> impl Iterator<Item = &Enrollment> {
+        self.enrollments.iter().filter(move |enrollment| enrollment.is_eligible_on(date))
+    }
+
+    /// Whether a member was covered on `date`; members not on the roster are assumed to be
+    pub fn is_eligible(&self, member_id: &str, date: NaiveDate) -> bool {
+        self.enrollment(member_id).is_none_or(|enrollment| enrollment.is_eligible_on(date))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that a generated roster churns members out at month ends and replaces them.
+    /// Expected: With full churn every starting member leaves at the end of the first month and a new member covers the next; unknown members are eligible.
+    #[test]
+    fn test_roster_churn() {
+        let start = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let options = RosterOptions { members: 3, monthly_churn: 1.0, start, months: 1 };
+        let roster = Roster::generate(&"anthem".into(), &options, &mut rand::rng()).unwrap();
+        assert_eq!(roster.enrollments().len(), 6);
+        let first = roster.enrollment("anthem-000001").unwrap();
+        let march_31 = NaiveDate::from_ymd_opt(2025, 3, 31).unwrap();
+        assert_eq!(first.terminated, Some(march_31));
+        assert!(roster.is_eligible("anthem-000001", march_31));
+        assert!(!roster.is_eligible("anthem-000001", march_31.succ_opt().unwrap()));
+        let joiner = roster.enrollment("anthem-000004").unwrap();
+        assert!(!joiner.is_eligible_on(march_31));
+        assert!(joiner.is_eligible_on(march_31.succ_opt().unwrap()));
+        assert!(roster.is_eligible("someone-else", march_31));
+
+        let steady = RosterOptions { monthly_churn: 0.0, ..options };
+        assert_eq!(Roster::generate(&"anthem".into(), &steady, &mut rand::rng()).unwrap().enrollments().len(), 3);
+        assert!(Roster::generate(&"anthem".into(), &RosterOptions { monthly_churn: 1.5, ..options }, &mut rand::rng()).is_err());
+    }
+}
@@ -0,0 +1,417 @@
+use anyhow::Context;
+use chrono::NaiveDate;
+use rusqlite::{Connection, OpenFlags, params};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::clock::SimClock;
+use crate::ids::{ClaimKey, PayerId};
+use crate::ledger::{EntryKind, Ledger};
+use crate::message::ClaimStatus;
+use crate::phi::Deidentification;
+
+/// Column labels for the run database's AR aging buckets, in simulated days since submission
+pub const AR_AGING_DAY_LABELS: [&str; 4] = ["0-30 days", "31-60 days", "61-90 days", "90+ days"];
+
+/// Reason code stored for a denied line when the payer gave none
+const UNSPECIFIED_REASON: &str = "unspecified";
+
+/// Tables of a stored run; dates are simulated calendar dates as `YYYY-MM-DD`
+const SCHEMA: &str = "
+DROP TABLE IF EXISTS run;
+DROP TABLE IF EXISTS claims;
+DROP TABLE IF EXISTS denials;
+DROP TABLE IF EXISTS payments;
+CREATE TABLE run (
+    ended_on TEXT NOT NULL
+);
+CREATE TABLE claims (
+    claim_id TEXT NOT NULL,
+    tenant_id TEXT NOT NULL,
+    payer_id TEXT NOT NULL,
+    patient_id TEXT NOT NULL,
+    status TEXT NOT NULL,
+    billed REAL NOT NULL,
+    submitted_on TEXT,
+    remitted_on TEXT,
+    payer_paid REAL NOT NULL,
+    patient_responsibility REAL NOT NULL,
+    PRIMARY KEY (tenant_id, claim_id)
+);
+CREATE TABLE denials (
+    claim_id TEXT NOT NULL,
+    tenant_id TEXT NOT NULL,
+    service_line_id TEXT NOT NULL,
+    reason_code TEXT NOT NULL,
+    amount REAL NOT NULL,
+    FOREIGN KEY (tenant_id, claim_id) REFERENCES claims (tenant_id, claim_id)
+);
+CREATE TABLE payments (
+    claim_id TEXT NOT NULL,
+    tenant_id TEXT NOT NULL,
+    payer_id TEXT NOT NULL,
+    source TEXT NOT NULL,
+    amount REAL NOT NULL,
+    posted_on TEXT NOT NULL
+);
+";
+
+/// Queries the `report` command runs against a stored run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunQuery {
+    /// Outstanding claim dollars per payer by age as of a date
+    ArAging,
+    /// Denied lines and dollars by claim adjustment reason code
+    Denials,
+    /// Payer and patient payments posted per week
+    Payments,
+}
+
+impl FromStr for RunQuery {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ar-aging" => Ok(RunQuery::ArAging),
+            "denials" => Ok(RunQuery::Denials),
+            "payments" => Ok(RunQuery::Payments),
+            _ => Err(anyhow::anyhow!("Unknown report: {} (expected ar-aging, denials, or payments)", s)),
+        }
+    }
+}
+
+impl fmt::Display for RunQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunQuery::ArAging => write!(f, "ar-aging"),
+            RunQuery::Denials => write!(f, "denials"),
+            RunQuery::Payments => write!(f, "payments"),
+        }
+    }
+}
+
+/// Outstanding dollars for one payer by simulated days since submission
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PayerAgingDollars {
+    pub payer_id: PayerId,
+    pub claims: u32,
+    pub buckets: [f64; 4],
+}
+
+impl PayerAgingDollars {
+    pub fn total(&self) -> f64 {
+        self.buckets.iter().sum()
+    }
+}
+
+/// Denied service lines sharing one reason code
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DenialsByReason {
+    /// Group and reason code, e.g. `CO-50`
+    pub reason_code: String,
+    pub claims: u32,
+    pub lines: u32,
+    /// Charges on the denied lines
+    pub amount: f64,
+}
+
+/// Payments posted in the week starting on `week_start`, a Monday
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WeeklyPayments {
+    pub week_start: NaiveDate,
+    pub payer: f64,
+    pub patient: f64,
+}
+
+/// A finished run's claims, denials, and payments in SQLite, for reports without re-running it
+///
+/// Written once at shutdown; every date is on the run's simulated calendar
+pub struct RunDb {
+    conn: Connection,
+    deidentification: Deidentification,
+}
+
+impl RunDb {
+    /// Create the database at `path`, replacing any run already stored there
+    pub fn create(path: &str, ended_on: NaiveDate) -> anyhow::Result<Self> {
+        let conn = Connection::open(path).with_context(|| format!("creating run database {}", path))?;
+        conn.execute_batch(SCHEMA)?;
+        conn.execute("INSERT INTO run (ended_on) VALUES (?1)", [ended_on.to_string()])?;
+        Ok(Self { conn, deidentification: Deidentification::Off })
+    }
+
+    /// Store pseudonymous patient ids when `deidentification` pseudonymizes
+    pub fn with_deidentification(mut self, deidentification: Deidentification) -> Self {
+        self.deidentification = deidentification;
+        self
+    }
+
+    /// Open a stored run for querying
+    pub fn open(path: &str) -> anyhow::Result<Self> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("opening run database {}", path))?;
+        Ok(Self { conn, deidentification: Deidentification::Off })
+    }
+
+    /// Simulated date the run ended on
+    pub fn ended_on(&self) -> anyhow::Result<NaiveDate> {
+        let ended_on: String = self.conn.query_row("SELECT ended_on FROM run", [], |row| row.get(0))?;
+        Ok(ended_on.parse()?)
+    }
+
+    /// Store every claim in the run's history with its remittance totals and denied lines
+    pub fn store_claims(&mut self, records: &HashMap<ClaimKey, ClaimStatus>, clock: &SimClock) -> anyhow::Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut insert_claim = tx.prepare(
+                "INSERT INTO claims (claim_id, tenant_id, payer_id, patient_id, status, billed, submitted_on, remitted_on, payer_paid, patient_responsibility)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            )?;
+            let mut insert_denial =
+                tx.prepare("INSERT INTO denials (claim_id, tenant_id, service_line_id, reason_code, amount) VALUES (?1, ?2, ?3, ?4, ?5)")?;
+            let date = |instant| clock.at(instant).date().to_string();
+            let patient_id = |member_id| self.deidentification.member_id(member_id).to_string();
+            for (ClaimKey { claim_id, .. }, status) in records {
+                match status {
+                    ClaimStatus::Submitted { claim, tenant_id, submitted_at, .. } => {
+                        insert_claim.execute(params![
+                            claim_id.as_str(),
+                            tenant_id,
+                            claim.insurance.payer_id.as_str(),
+                            patient_id(&claim.insurance.patient_member_id),
+                            "submitted",
+                            claim.total_charge(),
+                            date(*submitted_at),
+                            None::<String>,
+                            0.0,
+                            0.0,
+                        ])?;
+                    }
+                    ClaimStatus::Remitted(record) => {
+                        let remittance = record.remittance();
+                        let lines = remittance.service_line_remittances();
+                        insert_claim.execute(params![
+                            claim_id.as_str(),
+                            record.tenant_id(),
+                            record.payer_id().as_str(),
+                            patient_id(record.patient_id()),
+                            "remitted",
+                            record.claim().total_charge(),
+                            date(record.submitted_at()),
+                            date(record.remitted_at()),
+                            remittance.payer_paid(),
+                            lines.iter().map(|line| line.patient_responsibility()).sum::<f64>(),
+                        ])?;
+                        for (line, billed) in remittance.match_lines(record.claim()).matched {
+                            let reason_code = match (line.denial_reason(), remittance.is_denied()) {
+                                (Some(reason), _) => reason.code(),
+                                (None, true) => remittance.denial_reason().map_or(UNSPECIFIED_REASON, |reason| reason.code()),
+                                (None, false) => continue,
+                            };
+                            insert_denial.execute(params![
+                                claim_id.as_str(),
+                                record.tenant_id(),
+                                line.service_line_id(),
+                                reason_code,
+                                billed.unit_charge_amount * billed.units as f64,
+                            ])?;
+                        }
+                    }
+                    ClaimStatus::Voided { claim, tenant_id, .. } => {
+                        insert_claim.execute(params![
+                            claim_id.as_str(),
+                            tenant_id,
+                            claim.insurance.payer_id.as_str(),
+                            patient_id(&claim.insurance.patient_member_id),
+                            "voided",
+                            claim.total_charge(),
+                            None::<String>,
+                            None::<String>,
+                            0.0,
+                            0.0,
+                        ])?;
+                    }
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Store a billing organization's payer and patient payments as posted to its ledger
+    pub fn store_ledger(&mut self, tenant_id: &str, ledger: &Ledger, clock: &SimClock) -> anyhow::Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO payments (claim_id, tenant_id, payer_id, source, amount, posted_on) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for entry in ledger.entries() {
+                let source = match entry.kind {
+                    EntryKind::PayerPayment => "payer",
+                    EntryKind::PatientPayment => "patient",
+                    _ => continue,
+                };
+                insert.execute(params![
+                    entry.claim_id.as_str(),
+                    tenant_id,
+                    entry.payer_id.as_str(),
+                    source,
+                    entry.amount,
+                    clock.at(entry.posted_at).date().to_string(),
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Claims submitted by `as_of` and not yet remitted on it, per payer by days outstanding
+    pub fn ar_aging(&self, as_of: NaiveDate, tenant_id: Option<&str>) -> anyhow::Result<Vec<PayerAgingDollars>> {
+        let mut query = self.conn.prepare(
+            "SELECT payer_id, COUNT(*),
+                    SUM(CASE WHEN age <= 30 THEN billed ELSE 0 END),
+                    SUM(CASE WHEN age > 30 AND age <= 60 THEN billed ELSE 0 END),
+                    SUM(CASE WHEN age > 60 AND age <= 90 THEN billed ELSE 0 END),
+                    SUM(CASE WHEN age > 90 THEN billed ELSE 0 END)
+             FROM (
+                 SELECT payer_id, billed, julianday(?1) - julianday(submitted_on) AS age
+                 FROM claims
+                 WHERE submitted_on <= ?1
+                   AND (remitted_on IS NULL OR remitted_on > ?1)
+                   AND (?2 IS NULL OR tenant_id = ?2)
+             )
+             GROUP BY payer_id
+             ORDER BY payer_id",
+        )?;
+        let rows = query.query_map(params![as_of.to_string(), tenant_id], |row| {
+            Ok(PayerAgingDollars {
+                payer_id: PayerId::new(row.get::<_, String>(0)?),
+                claims: row.get(1)?,
+                buckets: [row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?],
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Denied lines by reason code, most dollars first
+    pub fn denials_by_reason(&self, tenant_id: Option<&str>) -> anyhow::Result<Vec<DenialsByReason>> {
+        let mut query = self.conn.prepare(
+            "SELECT reason_code, COUNT(DISTINCT tenant_id || '/' || claim_id), COUNT(*), SUM(amount)
+             FROM denials
+             WHERE ?1 IS NULL OR tenant_id = ?1
+             GROUP BY reason_code
+             ORDER BY SUM(amount) DESC, reason_code",
+        )?;
+        let rows = query.query_map(params![tenant_id], |row| {
+            Ok(DenialsByReason {
+                reason_code: row.get(0)?,
+                claims: row.get(1)?,
+                lines: row.get(2)?,
+                amount: row.get(3)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Payer and patient payments per week, Monday to Sunday, oldest first
+    pub fn payments_by_week(&self, tenant_id: Option<&str>) -> anyhow::Result<Vec<WeeklyPayments>> {
+        let mut query = self.conn.prepare(
+            "SELECT date(posted_on, 'weekday 0', '-6 days') AS week_start,
+                    SUM(CASE WHEN source = 'payer' THEN amount ELSE 0 END),
+                    SUM(CASE WHEN source = 'patient' THEN amount ELSE 0 END)
+             FROM payments
+             WHERE ?1 IS NULL OR tenant_id = ?1
+             GROUP BY week_start
+             ORDER BY week_start",
+        )?;
+        let rows = query.query_map(params![tenant_id], |row| Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?)))?;
+        let mut weeks = Vec::new();
+        for row in rows {
+            let (week_start, payer, patient) = row?;
+            weeks.push(WeeklyPayments { week_start: week_start.parse()?, payer, patient });
+        }
+        Ok(weeks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tenant::DEFAULT_TENANT;
+    use crate::ledger::LedgerEntry;
+    use crate::message::ClaimMetadata;
+    use crate::remittance::{DenialReason, Remittance, RemittanceRecord};
+    use crate::test_utils::mock_claim;
+    use std::time::{Duration, Instant};
+
+    /// Test that a stored run answers AR aging, denials, and weekly payments from SQL.
+    /// Expected: The unremitted claim ages 40 days into the 31-60 bucket; the denial is counted under CO-50; payments land in their Monday weeks.
+    #[test]
+    fn test_run_db_reports() {
+        let start = NaiveDate::from_ymd_opt(2025, 3, 3).unwrap();
+        let origin = Instant::now();
+        // one simulated day per real second
+        let clock = SimClock::starting_at(origin, start, 1.0);
+        let day = |n: u64| origin + Duration::from_secs(n);
+
+        let mut pending = mock_claim();
+        pending.claim_id = "pending".into();
+        let mut denied = mock_claim();
+        denied.claim_id = "denied".into();
+        let mut records = HashMap::new();
+        records.insert(
+            ClaimKey::new(DEFAULT_TENANT, pending.claim_id.clone()),
+            ClaimStatus::Submitted {
+                claim: pending.clone().into(),
+                tenant_id: "default".to_string(),
+                submitted_at: day(0),
+                metadata: ClaimMetadata::default(),
+            },
+        );
+        let remittance = Remittance::denied_for(&denied, DenialReason::MedicalNecessity);
+        records.insert(
+            ClaimKey::new(DEFAULT_TENANT, denied.claim_id.clone()),
+            ClaimStatus::Remitted(RemittanceRecord::new(denied.clone(), remittance, day(0), day(3))),
+        );
+        let mut ledger = Ledger::new();
+        let mut payment = |kind, amount, posted_on| {
+            let mut entry = LedgerEntry::new("paid", "p1", "medicare", kind, amount);
+            entry.posted_at = day(posted_on);
+            ledger.post(entry);
+        };
+        payment(EntryKind::PayerPayment, 100.0, 1);
+        payment(EntryKind::PatientPayment, 20.0, 6);
+        payment(EntryKind::PayerPayment, 50.0, 7);
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path = file.path().to_str().unwrap();
+        let mut db = RunDb::create(path, start + chrono::Days::new(40)).unwrap();
+        db.store_claims(&records, &clock).unwrap();
+        db.store_ledger("default", &ledger, &clock).unwrap();
+        drop(db);
+
+        let db = RunDb::open(path).unwrap();
+        let as_of = db.ended_on().unwrap();
+        let [aging] = db.ar_aging(as_of, None).unwrap().try_into().unwrap();
+        assert_eq!(aging.claims, 1);
+        assert_eq!(aging.buckets, [0.0, pending.total_charge(), 0.0, 0.0]);
+        assert!(db.ar_aging(start, Some("someone-else")).unwrap().is_empty());
+
+        let [denials] = db.denials_by_reason(None).unwrap().try_into().unwrap();
+        assert_eq!(denials.reason_code, "CO-50");
+        assert_eq!(denials.claims, 1);
+        assert_eq!(denials.amount, denied.total_charge());
+
+        let weeks = db.payments_by_week(Some("default")).unwrap();
+        assert_eq!(
+            weeks,
+            vec![
+                WeeklyPayments { week_start: start, payer: 100.0, patient: 20.0 },
+                WeeklyPayments { week_start: start + chrono::Days::new(7), payer: 50.0, patient: 0.0 },
+            ]
+        );
+        assert_eq!("payments".parse::<RunQuery>().unwrap(), RunQuery::Payments);
+    }
+}
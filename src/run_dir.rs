@@ -0,0 +1,199 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use chrono::{DateTime, Local};
+
+use crate::config::Config;
+use crate::logging::LogSink;
+use crate::remittance_files::REMITTANCE_FILES_DIR;
+
+/// Directory every run directory is made under
+pub const RUNS_ROOT: &str = "runs";
+
+/// Runs kept under `RUNS_ROOT`, newest first, before older ones are removed
+pub const DEFAULT_KEEP_RUNS: usize = 10;
+
+/// Snapshot of the command line and resolved configuration, which also marks a directory as a run's
+pub const CONFIG_SNAPSHOT: &str = "config.txt";
+
+/// Log of every component's claim events, when the run names no sinks of its own
+pub const RUN_LOG: &str = "sim.log";
+
+/// One run's output directory, `runs/<timestamp>[-<name>]/`, collecting its generated claims,
+/// logs, exports, and configuration
+///
+/// Directory names start with the run's local start time, `20261016T194544`, so they sort
+/// oldest first and `prune` can tell which to drop
+#[derive(Debug, Clone)]
+pub struct RunDir {
+    path: PathBuf,
+}
+
+impl RunDir {
+    /// Make the directory of a run started at `started` under `root`, numbering it when
+    /// another run of the same second and name already has the plain one
+    pub fn create(root: impl AsRef<Path>, name: Option<&str>, started: DateTime<Local>) -> anyhow::Result<Self> {
+        let root = root.as_ref();
+        fs::create_dir_all(root).with_context(|| format!("creating runs directory {}", root.display()))?;
+        let mut base = started.format("%Y%m%dT%H%M%S").to_string();
+        if let Some(name) = name.filter(|name| !name.trim().is_empty()) {
+            let name: String = name
+                .trim()
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || "-_.".contains(c) { c } else { '_' })
+                .collect();
+            base = format!("{}-{}", base, name);
+        }
+        for n in 1.. {
+            let path = match n {
+                1 => root.join(&base),
+                n => root.join(format!("{}.{}", base, n)),
+            };
+            match fs::create_dir(&path) {
+                Ok(()) => return Ok(Self { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e).with_context(|| format!("creating run directory {}", path.display())),
+            }
+        }
+        unreachable!("run directory numbering is unbounded")
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path of `file` inside the run directory; absolute paths are left where they point
+    pub fn join(&self, file: &str) -> String {
+        match Path::new(file).is_absolute() {
+            true => file.to_string(),
+            false => self.path.join(file).to_string_lossy().into_owned(),
+        }
+    }
+
+    /// Move the run's outputs into the directory: generated claims, the event log, rejects,
+    /// exports, the run database, the audit log, log files, file drop mailboxes, and the daily
+    /// remittance files, which a run directory always collects
+    ///
+    /// Inputs, such as rule files, tenant claim files, and checkpoints, stay where they are, so
+    /// they are shared across runs. Without `--log-sinks`, claim events go to `RUN_LOG` rather
+    /// than the console
+    pub fn place(&self, config: &mut Config) {
+        if config.file_path == config.generated_claims_path {
+            config.file_path = self.join(&config.file_path);
+        }
+        config.generated_claims_path = self.join(&config.generated_claims_path);
+        for path in [
+            &mut config.event_log_path,
+            &mut config.rejects_path,
+            &mut config.procedure_csv_path,
+            &mut config.run_db_path,
+            &mut config.audit_log_path,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *path = self.join(path);
+        }
+        let sinks = &mut config.log_sinks;
+        if sinks.default == LogSink::Stdout && sinks.components.is_empty() {
+            sinks.default = LogSink::Rolling(RUN_LOG.to_string());
+        }
+        for sink in std::iter::once(&mut sinks.default).chain(sinks.components.values_mut()) {
+            if let LogSink::File(path) | LogSink::Rolling(path) = sink {
+                *path = self.join(path);
+            }
+        }
+        if let Some(claim_log) = &mut sinks.claim_log {
+            claim_log.dir = self.join(&claim_log.dir);
+        }
+        if let Some(file_drop) = &mut config.file_drop {
+            file_drop.dir = self.join(&file_drop.dir);
+        }
+        let remittance_files = config.remittance_files_dir.as_deref().unwrap_or(REMITTANCE_FILES_DIR);
+        config.remittance_files_dir = Some(self.join(remittance_files));
+    }
+
+    /// Record how the run was started and the configuration it ran with, after `place`
+    pub fn write_config(&self, args: &[String], config: &Config) -> anyhow::Result<()> {
+        let path = self.path.join(CONFIG_SNAPSHOT);
+        let mut file = fs::File::create(&path).with_context(|| format!("creating {}", path.display()))?;
+        writeln!(file, "command: {}", args.join(" "))?;
+        writeln!(file, "started: {}", Local::now().to_rfc3339())?;
+        writeln!(file, "{:#?}", config)?;
+        Ok(())
+    }
+}
+
+/// Remove all but the newest `keep` runs under `root`, returning the directories removed
+///
+/// Only directories holding a `CONFIG_SNAPSHOT` count as runs, so nothing else under `root` is
+/// touched. `keep` 0 keeps every run
+pub fn prune(root: impl AsRef<Path>, keep: usize) -> anyhow::Result<Vec<PathBuf>> {
+    let root = root.as_ref();
+    if keep == 0 || !root.exists() {
+        return Ok(Vec::new());
+    }
+    let mut runs: Vec<PathBuf> = fs::read_dir(root)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.join(CONFIG_SNAPSHOT).is_file())
+        .collect();
+    runs.sort();
+    let stale = runs.len().saturating_sub(keep);
+    let removed: Vec<PathBuf> = runs.into_iter().take(stale).collect();
+    for run in &removed {
+        fs::remove_dir_all(run).with_context(|| format!("removing old run {}", run.display()))?;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::LogSinks;
+    use chrono::TimeZone;
+
+    /// Every relative output path in `sinks`, for checking where a run's logs went
+    fn log_paths(sinks: &LogSinks) -> Vec<&str> {
+        std::iter::once(&sinks.default)
+            .chain(sinks.components.values())
+            .filter_map(|sink| match sink {
+                LogSink::File(path) | LogSink::Rolling(path) => Some(path.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Test that a run directory collects the run's outputs and old runs are pruned.
+    /// Expected: Generated claims, the event log, the default log, and remittance files go in the named run's directory while an absolute
+    /// run database stays put; a second run in the same second is numbered, and pruning to one keeps only the newest run.
+    #[test]
+    fn test_run_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let started = Local.with_ymd_and_hms(2026, 10, 16, 19, 45, 44).unwrap();
+        let run = RunDir::create(root.path(), Some("nightly load"), started).unwrap();
+        assert!(run.path().ends_with("20261016T194544-nightly_load"));
+
+        let mut config = Config {
+            event_log_path: Some("events.jsonl".to_string()),
+            run_db_path: Some("/var/tmp/run.db".to_string()),
+            ..Config::default()
+        };
+        run.place(&mut config);
+        run.write_config(&["healthtechsim".to_string()], &config).unwrap();
+        let inside = |path: &str| Path::new(path).starts_with(run.path());
+        assert!(inside(&config.file_path) && config.file_path == config.generated_claims_path);
+        assert!(inside(config.event_log_path.as_deref().unwrap()));
+        assert_eq!(config.run_db_path.as_deref(), Some("/var/tmp/run.db"));
+        assert!(log_paths(&config.log_sinks).iter().all(|path| inside(path) && path.ends_with(RUN_LOG)));
+        assert!(inside(config.remittance_files_dir.as_deref().unwrap()));
+
+        let again = RunDir::create(root.path(), Some("nightly load"), started).unwrap();
+        assert!(again.path().ends_with("20261016T194544-nightly_load.2"));
+        again.write_config(&[], &Config::default()).unwrap();
+        fs::create_dir(root.path().join("notes")).unwrap();
+        assert_eq!(prune(root.path(), 1).unwrap(), [run.path()]);
+        assert!(again.path().exists() && root.path().join("notes").exists());
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use chrono::NaiveDate;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::clock::SimClock;
+use crate::config::Config;
+use crate::event_log::{self, Event, EventLog, EventRecord};
+use crate::ids::{ClaimKey, PayerId};
+use crate::message::ClaimStatus;
+use crate::orchestrator;
+use crate::payer_mix::PayerMix;
+use crate::payer_rules::{RuleBook, RulePaths};
+use crate::report_engine::ReportEngine;
+use crate::report_format::MoneyFields;
+use crate::schema::PayerClaim;
+use crate::simulation::{BillerSpec, PayerSpec, Simulation};
+
+/// Claims of a scenario, which also marks a directory as a scenario's
+pub const SCENARIO_CLAIMS: &str = "claims.jsonl";
+
+/// Optional settings of a scenario: seed, start date, payers, and payer mix
+pub const SCENARIO_SETTINGS: &str = "scenario.json";
+
+/// Directory inside a scenario holding the outputs it is expected to produce
+pub const GOLDEN_DIR: &str = "golden";
+
+/// Golden file of the run's claim events
+pub const GOLDEN_EVENTS: &str = "events.jsonl";
+
+/// Golden file of the run's reports
+pub const GOLDEN_REPORT: &str = "report.json";
+
+/// Rule files a scenario may hold beside its claims, read as `--outcomes`, `--coverage`,
+/// `--attachments`, and `--benefits` would be
+pub const SCENARIO_RULES: [&str; 4] = ["outcomes.json", "coverage.json", "attachments.json", "benefits.json"];
+
+/// One simulated payer of a scenario
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScenarioPayer {
+    pub payer_id: String,
+    #[serde(default)]
+    pub min_delay_secs: u64,
+    #[serde(default)]
+    pub max_delay_secs: u64,
+    /// Range of seconds before each claim is acknowledged, as `[min, max]`; none are when absent
+    #[serde(default)]
+    pub ack_delay_secs: Option<(u64, u64)>,
+    #[serde(default)]
+    pub denial_rate: f64,
+    #[serde(default)]
+    pub error_rate: f64,
+}
+
+/// How a scenario runs, read from its `SCENARIO_SETTINGS`; every field is optional
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ScenarioSettings {
+    /// Seed of every payer's decisions
+    pub seed: u64,
+    /// Simulated date the run starts on, which claims are adjudicated and aged from
+    pub start: NaiveDate,
+    /// Payers claims are routed to; the simulation's own payers when empty
+    pub payers: Vec<ScenarioPayer>,
+    /// Share of the claims billed to each payer and what each is expected to reimburse; the
+    /// claims keep the payers they name when absent
+    pub payer_mix: Option<PayerMix>,
+}
+
+impl Default for ScenarioSettings {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            start: NaiveDate::from_ymd_opt(2026, 1, 1).expect("valid date"),
+            payers: Vec::new(),
+            payer_mix: None,
+        }
+    }
+}
+
+/// A named claim set, payer rules, and settings whose run is expected to produce the outputs
+/// stored in its `GOLDEN_DIR`
+///
+/// Runs are deterministic: payers are seeded and time is virtual, so payer delays pass
+/// instantly and the same scenario always emits the same events and reports. Changing a rule
+/// file or the simulator's adjudication shows up as a difference from the golden outputs
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub name: String,
+    pub dir: PathBuf,
+    pub claims: Vec<PayerClaim>,
+    pub settings: ScenarioSettings,
+    pub rules: RulePaths,
+}
+
+/// What a scenario run emitted, normalized so it compares across runs
+///
+/// Events drop their sequence numbers and timestamps, submissions carry only the claim id, and
+/// each claim's events are kept together in claim id order. Reports are the tables that do
+/// not depend on real time, with money rounded to cents
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenarioOutput {
+    pub events: String,
+    pub report: String,
+}
+
+/// How a scenario's outputs compared to its golden files
+#[derive(Debug, Clone, PartialEq)]
+pub enum GoldenCheck {
+    /// Both outputs match
+    Matched,
+    /// Golden files were written, because they were missing or an update was asked for
+    Written,
+    /// An output differs from its golden file, first at `line` (1-based)
+    Differs { file: PathBuf, line: usize, expected: String, actual: String },
+}
+
+impl Scenario {
+    /// Read the scenario in `dir`, named after the directory
+    pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let dir = dir.as_ref();
+        let name = dir.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let claims_path = dir.join(SCENARIO_CLAIMS);
+        let claims = crate::what_if::load_claims(&claims_path.to_string_lossy())
+            .with_context(|| format!("reading scenario claims {}", claims_path.display()))?;
+        let settings_path = dir.join(SCENARIO_SETTINGS);
+        let settings = match settings_path.exists() {
+            true => serde_json::from_str(&fs::read_to_string(&settings_path)?)
+                .with_context(|| format!("parsing scenario settings {}", settings_path.display()))?,
+            false => ScenarioSettings::default(),
+        };
+        let rule = |file: &str| Some(dir.join(file)).filter(|path| path.exists()).map(|path| path.to_string_lossy().into_owned());
+        let rules = RulePaths {
+            outcomes: rule(SCENARIO_RULES[0]),
+            coverage: rule(SCENARIO_RULES[1]),
+            attachments: rule(SCENARIO_RULES[2]),
+            benefits: rule(SCENARIO_RULES[3]),
+        };
+        Ok(Self { name, dir: dir.to_path_buf(), claims, settings, rules })
+    }
+
+    /// Run the scenario to completion on a virtual clock
+    ///
+    /// The run gets its own runtime with time paused, so this blocks and must not be called
+    /// from inside another runtime
+    pub fn run(&self) -> anyhow::Result<ScenarioOutput> {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().start_paused(true).build()?;
+        runtime.block_on(self.simulate())
+    }
+
+    async fn simulate(&self) -> anyhow::Result<ScenarioOutput> {
+        if self.claims.is_empty() {
+            return Err(anyhow::anyhow!("Scenario {} has no claims", self.name));
+        }
+        let rules = RuleBook::new(self.rules.load()?);
+        let mut claims = self.claims.clone();
+        if let Some(mix) = &self.settings.payer_mix {
+            // drawn with the scenario's seed, so every run bills each claim to the same payer
+            let mut rng = StdRng::seed_from_u64(self.settings.seed);
+            for claim in &mut claims {
+                claim.insurance.payer_id = mix.choose(&mut rng).clone();
+            }
+        }
+        let payers = match self.settings.payers.is_empty() {
+            true => orchestrator::simulated_payers(),
+            false => self
+                .settings
+                .payers
+                .iter()
+                .map(|payer| {
+                    let spec = PayerSpec::new(payer.payer_id.as_str(), payer.min_delay_secs, payer.max_delay_secs)
+                        .with_denial_rate(payer.denial_rate)
+                        .with_error_rate(payer.error_rate);
+                    match payer.ack_delay_secs {
+                        Some((min_secs, max_secs)) => spec.with_ack_delay(min_secs, max_secs),
+                        None => spec,
+                    }
+                })
+                .collect(),
+        };
+        let simulated = |payer_id: &&PayerId| payers.iter().any(|payer| payer.payer_id == **payer_id);
+        if let Some(unknown) = self.settings.payer_mix.iter().flat_map(PayerMix::payer_ids).find(|payer_id| !simulated(payer_id)) {
+            return Err(anyhow::anyhow!("Scenario {} payer mix names unknown payer {}", self.name, unknown));
+        }
+
+        let scratch = tempfile::tempdir()?;
+        let log_path = scratch.path().join(GOLDEN_EVENTS).to_string_lossy().into_owned();
+        let event_log = EventLog::open(&log_path).await?;
+        let config = Config::default();
+        let clock = SimClock::new(self.settings.start, config.secs_per_day);
+        let mut builder = Simulation::builder()
+            .config(config)
+            .clock(clock)
+            .event_log(event_log.clone())
+            .biller(BillerSpec::default().with_source(claims));
+        for payer in payers {
+            builder = builder.payer(payer.with_seed(self.settings.seed).with_rules(rules.clone()));
+        }
+        let mut sim = builder.build()?;
+        sim.wait().await;
+        let records = sim.history().snapshot().await;
+        sim.shutdown();
+        event_log.flush().await;
+
+        Ok(ScenarioOutput {
+            events: normalize_events(event_log::read_event_log(&log_path).await?),
+            report: format!("{}\n", serde_json::to_string_pretty(&report(&records, self.settings.payer_mix.as_ref()))?),
+        })
+    }
+
+    /// Compare `output` to the golden files, or write it as the golden files when `update` is
+    /// set or they do not exist yet
+    pub fn check(&self, output: &ScenarioOutput, update: bool) -> anyhow::Result<GoldenCheck> {
+        let golden = self.dir.join(GOLDEN_DIR);
+        let files = [(golden.join(GOLDEN_EVENTS), &output.events), (golden.join(GOLDEN_REPORT), &output.report)];
+        if update || files.iter().any(|(path, _)| !path.exists()) {
+            fs::create_dir_all(&golden).with_context(|| format!("creating {}", golden.display()))?;
+            for (path, contents) in &files {
+                fs::write(path, contents).with_context(|| format!("writing {}", path.display()))?;
+            }
+            return Ok(GoldenCheck::Written);
+        }
+        for (path, actual) in files {
+            let expected = fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+            if let Some((index, (expected, actual))) = diff(&expected, actual) {
+                return Ok(GoldenCheck::Differs { file: path, line: index + 1, expected, actual });
+            }
+        }
+        Ok(GoldenCheck::Matched)
+    }
+}
+
+/// Scenario directories directly under `root`, by name
+pub fn discover(root: impl AsRef<Path>) -> anyhow::Result<Vec<PathBuf>> {
+    let root = root.as_ref();
+    let mut dirs: Vec<PathBuf> = fs::read_dir(root)
+        .with_context(|| format!("reading scenarios directory {}", root.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.join(SCENARIO_CLAIMS).is_file())
+        .collect();
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// First line where `expected` and `actual` differ, with each side's line; a missing line is empty
+fn diff(expected: &str, actual: &str) -> Option<(usize, (String, String))> {
+    let (mut expected, mut actual) = (expected.lines(), actual.lines());
+    (0..)
+        .map(|index| (index, (expected.next(), actual.next())))
+        .take_while(|(_, lines)| *lines != (None, None))
+        .find(|(_, (expected, actual))| expected != actual)
+        .map(|(index, (expected, actual))| (index, (expected.unwrap_or_default().to_string(), actual.unwrap_or_default().to_string())))
+}
+
+/// Event log as JSONL without run-specific fields, each claim's events together in claim id order
+fn normalize_events(records: Vec<EventRecord>) -> String {
+    let mut events: Vec<(String, Value)> = records
+        .into_iter()
+        .map(|record| {
+            let claim_id = match &record.event {
+                Event::ClaimSubmitted { claim, .. } => claim.claim_id.to_string(),
+                Event::ClaimAdjudicated { remittance, .. } => remittance.claim_id().to_string(),
+                Event::DuplicateSubmitted { claim_id, .. }
+                | Event::ClaimRejected { claim_id, .. }
+                | Event::ClaimRouted { claim_id, .. }
+                | Event::ClaimAcknowledged { claim_id, .. }
+                | Event::ClaimRemitted { claim_id, .. }
+                | Event::ClaimVoided { claim_id, .. }
+                | Event::ClaimStuck { claim_id, .. } => claim_id.to_string(),
+                Event::RulesApplied { .. } => String::new(),
+            };
+            let mut event = serde_json::to_value(&record.event).unwrap_or_default();
+            if let Event::ClaimSubmitted { claim, .. } = &record.event {
+                event["claim"] = json!({ "claim_id": claim.claim_id });
+            }
+            (claim_id, event)
+        })
+        .collect();
+    // stable, so a claim's events keep the order they happened in
+    events.sort_by(|a, b| a.0.cmp(&b.0));
+    events.into_iter().map(|(_, event)| format!("{}\n", event)).collect()
+}
+
+/// The reports that do not depend on real time, money rounded to cents, with expected vs actual
+/// reimbursement under the payer mix if there is one
+fn report(records: &HashMap<ClaimKey, ClaimStatus>, payer_mix: Option<&PayerMix>) -> Value {
+    fn cents<T: MoneyFields + serde::Serialize>(mut rows: Vec<T>) -> Value {
+        rows.iter_mut().for_each(|row| row.map_money(|amount| (amount * 100.0).round() / 100.0));
+        serde_json::to_value(rows).unwrap_or_default()
+    }
+    let engine = ReportEngine::new(records);
+    let mut report = json!({
+        "tenants": cents(engine.tenant_summary()),
+        "patients": cents(engine.patient_summary()),
+        "providers": cents(engine.provider_summary()),
+        "procedures": cents(engine.procedure_summary()),
+        "telehealth": cents(engine.telehealth_mix()),
+        "split_claims": cents(engine.split_claims()),
+        "reconciliation": cents(engine.reconciliation()),
+        "exceptions": engine.remittance_exceptions(),
+    });
+    if let Some(mix) = payer_mix {
+        report["reimbursement"] = cents(mix.reimbursement(records));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::ClaimBuilder;
+    use std::io::Write;
+
+    /// Test that a scenario runs deterministically and its golden files catch a rule change.
+    /// Expected: Two runs emit identical outputs; the first check writes the golden files and the second matches,
+    /// and after an outcome rule denying every line is added the event log differs from its golden file.
+    #[test]
+    fn test_scenario_golden_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let scenario_dir = dir.path().join("denials");
+        fs::create_dir(&scenario_dir).unwrap();
+        let mut claims = fs::File::create(scenario_dir.join(SCENARIO_CLAIMS)).unwrap();
+        for n in 0..6 {
+            let claim = ClaimBuilder::new().claim_id(format!("scn-{}", n)).payer(["medicare", "anthem"][n % 2]).lines(2).build();
+            writeln!(claims, "{}", serde_json::to_string(&claim).unwrap()).unwrap();
+        }
+        let settings = r#"{"seed": 7, "start": "2026-03-02", "payers": [
+            {"payer_id": "medicare", "min_delay_secs": 5, "max_delay_secs": 60, "denial_rate": 0.3},
+            {"payer_id": "anthem", "min_delay_secs": 5, "max_delay_secs": 60}]}"#;
+        fs::write(scenario_dir.join(SCENARIO_SETTINGS), settings).unwrap();
+        assert_eq!(discover(dir.path()).unwrap(), vec![scenario_dir.clone()]);
+
+        let scenario = Scenario::load(&scenario_dir).unwrap();
+        assert_eq!((scenario.name.as_str(), scenario.settings.seed), ("denials", 7));
+        let output = scenario.run().unwrap();
+        assert_eq!(output, scenario.run().unwrap());
+        assert!(output.events.lines().next().unwrap().contains(r#""claim":{"claim_id":"scn-0"}"#));
+        assert_eq!(scenario.check(&output, false).unwrap(), GoldenCheck::Written);
+        assert_eq!(scenario.check(&output, false).unwrap(), GoldenCheck::Matched);
+
+        fs::write(scenario_dir.join(SCENARIO_RULES[0]), r#"[{"codes": "00000-99999", "deny_medical_necessity": 1}]"#).unwrap();
+        let changed = Scenario::load(&scenario_dir).unwrap();
+        match changed.check(&changed.run().unwrap(), false).unwrap() {
+            GoldenCheck::Differs { file, line, .. } => assert!(file.ends_with(GOLDEN_EVENTS) && line > 1, "{}", line),
+            other => panic!("Expected a difference, got {:?}", other),
+        }
+    }
+
+    /// Test that a scenario's payer mix bills its claims to payers drawn with its seed and adds expected vs actual reimbursement to the report.
+    /// Expected: Two runs match; every claim goes to a payer in the mix, each payer has a reimbursement row,
+    /// and a mix naming a payer the scenario does not simulate is refused.
+    #[test]
+    fn test_scenario_payer_mix() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut claims = fs::File::create(dir.path().join(SCENARIO_CLAIMS)).unwrap();
+        for n in 0..8 {
+            let claim = ClaimBuilder::new().claim_id(format!("mix-{}", n)).payer("medicare").build();
+            writeln!(claims, "{}", serde_json::to_string(&claim).unwrap()).unwrap();
+        }
+        let settings = |mix_payer: &str| {
+            format!(
+                r#"{{"seed": 3, "payers": [{{"payer_id": "medicare"}}, {{"payer_id": "anthem"}}],
+                "payer_mix": [{{"payer_id": "medicare", "percent": 50, "reimbursement_rate": 0.5}},
+                    {{"payer_id": "{}", "percent": 50, "reimbursement_rate": 0.8}}]}}"#,
+                mix_payer
+            )
+        };
+        fs::write(dir.path().join(SCENARIO_SETTINGS), settings("anthem")).unwrap();
+
+        let scenario = Scenario::load(dir.path()).unwrap();
+        let output = scenario.run().unwrap();
+        assert_eq!(output, scenario.run().unwrap());
+        let report: Value = serde_json::from_str(&output.report).unwrap();
+        let rows = report["reimbursement"].as_array().unwrap();
+        assert_eq!(rows.iter().map(|row| row["payer_id"].as_str().unwrap()).collect::<Vec<_>>(), ["medicare", "anthem"]);
+        assert_eq!(rows.iter().map(|row| row["remitted"].as_u64().unwrap()).sum::<u64>(), 8);
+
+        fs::write(dir.path().join(SCENARIO_SETTINGS), settings("cigna")).unwrap();
+        assert!(Scenario::load(dir.path()).unwrap().run().is_err());
+    }
+}
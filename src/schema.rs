@@ -1,47 +1,520 @@
+use chrono::{Datelike, NaiveDate};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::claim_ids::ClaimIdGenerator;
+use crate::ids::{ClaimId, MemberId, Npi, PayerId};
+use crate::modifier;
+use crate::place_of_service::PlaceOfService;
+use crate::priority::Priority;
+use crate::validation;
+
+/// Claims billing at least this much are processed as high priority
+pub const HIGH_PRIORITY_CHARGE_THRESHOLD: f64 = 1000.0;
+
+/// Currency claims are billed in unless configured otherwise
+pub const DEFAULT_CURRENCY: &str = "USD";
+
+/// Patients younger than this are covered under pediatric rules
+pub const PEDIATRIC_AGE_LIMIT: u32 = 18;
 
 /// Complete healthcare claim with patient, provider, and billing information
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PayerClaim {
-    pub claim_id: String,
-    pub place_of_service_code: u32,
+    pub claim_id: ClaimId,
+    /// Professional (837P) or institutional (837I, the UB-04) claim
+    #[serde(default, skip_serializing_if = "ClaimType::is_professional")]
+    pub claim_type: ClaimType,
+    pub place_of_service_code: PlaceOfService,
     pub insurance: Insurance,
     pub patient: Patient,
     pub organization: Organization,
     pub rendering_provider: Provider,
+    /// Individual billing under their own NPI, such as a solo practitioner; when absent the organization bills
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub billing_provider: Option<Provider>,
     pub service_lines: Vec<ServiceLine>,
+    /// ICD-10-CM diagnosis codes supporting the services, primary first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diagnosis_codes: Vec<String>,
+    /// Date the services were rendered; payers take the day they adjudicate the claim when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_date: Option<NaiveDate>,
+    pub urgent: Option<bool>,
+    /// Total charge as stated on the claim header; must equal the sum of line charges
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_charge_amount: Option<f64>,
+    #[serde(default, skip_serializing_if = "ClaimFrequency::is_original")]
+    pub claim_frequency_code: ClaimFrequency,
+    /// Claim a replacement corrects; required with frequency code 7
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_claim_id: Option<ClaimId>,
+    /// Claim this one was split from to meet its payer's service line limit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_claim_id: Option<ClaimId>,
+    /// Supporting documents sent with the claim or later on a payer's request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+    /// Specialty segments by key, such as an ambulance transport or DME rental (see `extensions`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extensions: BTreeMap<String, serde_json::Value>,
+}
+
+impl PayerClaim {
+    /// Start a claim a part at a time; see `PayerClaimBuilder`
+    pub fn builder() -> PayerClaimBuilder {
+        PayerClaimBuilder::default()
+    }
+
+    /// Sum of unit charge times units across all service lines
+    pub fn total_charge(&self) -> f64 {
+        self.service_lines
+            .iter()
+            .map(|line| line.unit_charge_amount * line.units as f64)
+            .sum()
+    }
+
+    /// Currency of the claim's charges, taken from its first service line
+    pub fn currency(&self) -> &str {
+        self.service_lines
+            .first()
+            .map(|line| line.unit_charge_currency.as_str())
+            .unwrap_or(DEFAULT_CURRENCY)
+    }
+
+    /// NPI the claim is billed under: the individual billing provider's, or else the organization's
+    pub fn billing_npi(&self) -> Option<&Npi> {
+        match &self.billing_provider {
+            Some(provider) => Some(&provider.npi),
+            None => self.organization.billing_npi.as_ref(),
+        }
+    }
+
+    /// Taxonomy code of whoever the claim is billed under
+    pub fn billing_taxonomy(&self) -> Option<&str> {
+        match &self.billing_provider {
+            Some(provider) => provider.taxonomy_code.as_deref(),
+            None => self.organization.taxonomy_code.as_deref(),
+        }
+    }
+
+    /// Address of whoever the claim is billed under; an individual without one bills from the organization's
+    pub fn billing_address(&self) -> Option<&Address> {
+        self.billing_provider
+            .as_ref()
+            .and_then(|provider| provider.address.as_ref())
+            .or(self.organization.address.as_ref())
+    }
+
+    /// Id of the claim this one replaces, for a replacement claim
+    pub fn replaces(&self) -> Option<&ClaimId> {
+        match self.claim_frequency_code {
+            ClaimFrequency::Replacement => self.original_claim_id.as_ref(),
+            ClaimFrequency::Original => None,
+        }
+    }
+
+    /// Split the claim into child claims of at most `max_lines` service lines each, in line order
+    ///
+    /// Children take new ids from `ids` and name this claim as their parent; each keeps the
+    /// header, diagnoses, and attachments and states its own total. A claim within the limit,
+    /// or an institutional one (a stay is paid as a whole), is returned as is
+    pub fn split(self, max_lines: usize, ids: &ClaimIdGenerator) -> Vec<PayerClaim> {
+        if max_lines == 0 || self.service_lines.len() <= max_lines || !self.claim_type.is_professional() {
+            return vec![self];
+        }
+        self.service_lines
+            .chunks(max_lines)
+            .map(|lines| {
+                let mut child = PayerClaim {
+                    claim_id: ids.generate(),
+                    parent_claim_id: Some(self.claim_id.clone()),
+                    service_lines: lines.to_vec(),
+                    ..self.clone()
+                };
+                if child.total_charge_amount.is_some() {
+                    child.total_charge_amount = Some(child.total_charge());
+                }
+                child
+            })
+            .collect()
+    }
+
+    /// The specialty segment under `key` read as `T`, or `None` when the claim has none
+    pub fn extension<T: DeserializeOwned>(&self, key: &str) -> Option<Result<T, serde_json::Error>> {
+        self.extensions.get(key).map(|value| T::deserialize(value))
+    }
+
+    /// Whether a line was delivered by telehealth: billed at a telehealth place of service, or
+    /// with modifier 95 at the place the patient would have been seen in person
+    pub fn is_telehealth_line(&self, line: &ServiceLine) -> bool {
+        self.place_of_service_code.is_telehealth() || line.modifiers.iter().flatten().any(|m| modifier::is_telehealth(m))
+    }
+
+    /// Whether any of the claim's services were delivered by telehealth
+    pub fn is_telehealth(&self) -> bool {
+        self.service_lines.iter().any(|line| self.is_telehealth_line(line))
+    }
+
+    /// Facility details of an institutional claim
+    pub fn institutional(&self) -> Option<&InstitutionalClaim> {
+        match &self.claim_type {
+            ClaimType::Institutional(institutional) => Some(institutional),
+            ClaimType::Professional => None,
+        }
+    }
+
+    /// High for claims flagged urgent or billing at least the high-priority threshold
+    pub fn priority(&self) -> Priority {
+        if self.urgent == Some(true) || self.total_charge() >= HIGH_PRIORITY_CHARGE_THRESHOLD {
+            Priority::High
+        } else {
+            Priority::Normal
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Insurance {
-    pub payer_id: String,
-    pub patient_member_id: String,
+    pub payer_id: PayerId,
+    pub patient_member_id: MemberId,
+    /// Policy holder, when the patient is a dependent on someone else's policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscriber: Option<Subscriber>,
+}
+
+impl Insurance {
+    /// Member id of the policy the claim is billed under: the subscriber's, or the patient's own
+    pub fn subscriber_id(&self) -> &MemberId {
+        self.subscriber.as_ref().map(|subscriber| &subscriber.member_id).unwrap_or(&self.patient_member_id)
+    }
+
+    /// Patient's relationship to the subscriber; patients without a subscriber block hold the policy
+    pub fn relationship(&self) -> Relationship {
+        self.subscriber.as_ref().map(|subscriber| subscriber.relationship).unwrap_or(Relationship::SelfInsured)
+    }
+
+    /// Key for anything tracked per covered person, such as deductible and out-of-pocket accumulators
+    ///
+    /// Dependents often share the subscriber's member id, so the policy alone would pool a family
+    pub fn coverage_key(&self) -> CoverageKey {
+        CoverageKey {
+            payer_id: self.payer_id.clone(),
+            subscriber_id: self.subscriber_id().clone(),
+            patient_id: self.patient_member_id.clone(),
+        }
+    }
+}
+
+/// One patient's coverage under one payer's policy
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CoverageKey {
+    pub payer_id: PayerId,
+    pub subscriber_id: MemberId,
+    pub patient_id: MemberId,
+}
+
+impl fmt::Display for CoverageKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}/{}", self.payer_id, self.subscriber_id, self.patient_id)
+    }
+}
+
+/// Holder of the policy a dependent patient is covered under
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Subscriber {
+    pub member_id: MemberId,
+    pub first_name: String,
+    pub last_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dob: Option<NaiveDate>,
+    /// Patient's relationship to the subscriber
+    pub relationship: Relationship,
+}
+
+/// Individual relationship code: how the patient is related to the subscriber
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Relationship {
+    /// The patient is the subscriber (code 18)
+    #[default]
+    SelfInsured,
+    /// Code 01
+    Spouse,
+    /// Code 19
+    Child,
+    /// Any other dependent (code G8)
+    Other,
+}
+
+impl Relationship {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Relationship::SelfInsured => "18",
+            Relationship::Spouse => "01",
+            Relationship::Child => "19",
+            Relationship::Other => "G8",
+        }
+    }
+}
+
+impl TryFrom<String> for Relationship {
+    type Error = String;
+
+    /// Accepts the X12 codes and the words `self`, `spouse`, `child`, and `other`, in any case
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "18" | "self" => Ok(Relationship::SelfInsured),
+            "01" | "spouse" => Ok(Relationship::Spouse),
+            "19" | "child" => Ok(Relationship::Child),
+            "g8" | "other" => Ok(Relationship::Other),
+            _ => Err(format!("unknown relationship `{}`, expected 18, 01, 19, or G8", value)),
+        }
+    }
+}
+
+impl From<Relationship> for String {
+    fn from(relationship: Relationship) -> Self {
+        relationship.code().to_string()
+    }
+}
+
+impl fmt::Display for Relationship {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Patient {
     pub first_name: String,
     pub last_name: String,
-    pub gender: String,
-    pub dob: String,
+    pub gender: Gender,
+    /// Date of birth as `YYYY-MM-DD`; absent when unknown
+    pub dob: Option<NaiveDate>,
     pub email: Option<String>,
     pub address: Option<Address>,
 }
 
+impl Patient {
+    /// A patient known by name only, with no date of birth or contact details
+    pub fn new(first_name: impl Into<String>, last_name: impl Into<String>, gender: Gender) -> Self {
+        Self {
+            first_name: first_name.into(),
+            last_name: last_name.into(),
+            gender,
+            dob: None,
+            email: None,
+            address: None,
+        }
+    }
+
+    /// Age in whole years on `date`, if the date of birth is known
+    pub fn age_on(&self, date: NaiveDate) -> Option<u32> {
+        let dob = self.dob?;
+        let mut age = date.year() - dob.year();
+        if (date.month(), date.day()) < (dob.month(), dob.day()) {
+            age -= 1;
+        }
+        u32::try_from(age).ok()
+    }
+
+    /// Younger than `PEDIATRIC_AGE_LIMIT` on `date`; unknown ages are treated as adults
+    pub fn is_pediatric_on(&self, date: NaiveDate) -> bool {
+        self.age_on(date).is_some_and(|age| age < PEDIATRIC_AGE_LIMIT)
+    }
+}
+
+/// Administrative gender, written as a one-letter code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Gender {
+    Male,
+    Female,
+    Other,
+    Unknown,
+}
+
+impl Gender {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Gender::Male => "m",
+            Gender::Female => "f",
+            Gender::Other => "o",
+            Gender::Unknown => "u",
+        }
+    }
+}
+
+impl TryFrom<String> for Gender {
+    type Error = String;
+
+    /// Accepts the one-letter codes and full words, in any case
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_ascii_lowercase().as_str() {
+            "m" | "male" => Ok(Gender::Male),
+            "f" | "female" => Ok(Gender::Female),
+            "o" | "other" => Ok(Gender::Other),
+            "u" | "unknown" => Ok(Gender::Unknown),
+            _ => Err(format!("unknown gender `{}`, expected m, f, o, or u", value)),
+        }
+    }
+}
+
+impl From<Gender> for String {
+    fn from(gender: Gender) -> Self {
+        gender.code().to_string()
+    }
+}
+
+impl fmt::Display for Gender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Claim frequency type code: whether a claim is new or corrects an earlier one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ClaimFrequency {
+    /// First submission of a claim (code 1)
+    #[default]
+    Original,
+    /// Corrected claim replacing an earlier one in full (code 7)
+    Replacement,
+}
+
+impl ClaimFrequency {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ClaimFrequency::Original => "1",
+            ClaimFrequency::Replacement => "7",
+        }
+    }
+
+    pub fn is_original(&self) -> bool {
+        *self == ClaimFrequency::Original
+    }
+}
+
+impl TryFrom<String> for ClaimFrequency {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.trim() {
+            "1" => Ok(ClaimFrequency::Original),
+            "7" => Ok(ClaimFrequency::Replacement),
+            _ => Err(format!("unsupported claim frequency code `{}`, expected 1 or 7", value)),
+        }
+    }
+}
+
+impl From<ClaimFrequency> for String {
+    fn from(frequency: ClaimFrequency) -> Self {
+        frequency.code().to_string()
+    }
+}
+
+impl fmt::Display for ClaimFrequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Which form a claim is billed on
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClaimType {
+    /// A practitioner's services, billed on the 837P (CMS-1500)
+    #[default]
+    Professional,
+    /// A facility's services, billed on the 837I (UB-04)
+    Institutional(InstitutionalClaim),
+}
+
+impl ClaimType {
+    pub fn is_professional(&self) -> bool {
+        *self == ClaimType::Professional
+    }
+}
+
+/// Header fields a facility adds to an institutional claim
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstitutionalClaim {
+    /// Type of bill: facility type, bill classification, and frequency, e.g. `0111` for a hospital inpatient admit-through-discharge claim
+    pub bill_type: String,
+    pub admission_date: NaiveDate,
+    /// Absent while the patient is still admitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discharge_date: Option<NaiveDate>,
+    /// MS-DRG the stay was grouped to, which inpatient payment is based on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drg: Option<String>,
+}
+
+impl InstitutionalClaim {
+    /// Hospital inpatient bill types (`011x`) are paid by DRG; every other bill is paid line by line
+    pub fn is_inpatient(&self) -> bool {
+        self.bill_type.trim_start_matches('0').starts_with("11")
+    }
+
+    /// Nights from admission to discharge, or `None` while the patient is still admitted
+    pub fn length_of_stay(&self) -> Option<i64> {
+        self.discharge_date.map(|discharge| (discharge - self.admission_date).num_days())
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Organization {
     pub name: String,
-    pub billing_npi: Option<String>,
+    pub billing_npi: Option<Npi>,
     pub ein: Option<String>,
     pub contact: Option<Contact>,
     pub address: Option<Address>,
+    /// NUCC health care provider taxonomy code, e.g. `261QP2300X` for a primary care clinic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub taxonomy_code: Option<String>,
+}
+
+impl Organization {
+    /// An organization known by name only, billing under no NPI of its own
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            billing_npi: None,
+            ein: None,
+            contact: None,
+            address: None,
+            taxonomy_code: None,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Provider {
     pub first_name: String,
     pub last_name: String,
-    pub npi: String,
+    pub npi: Npi,
+    /// NUCC health care provider taxonomy code, e.g. `207Q00000X` for family medicine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub taxonomy_code: Option<String>,
+    /// Where the provider practices, when it differs from the organization's address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<Address>,
+}
+
+impl Provider {
+    pub fn new(first_name: impl Into<String>, last_name: impl Into<String>, npi: impl Into<Npi>) -> Self {
+        Self {
+            first_name: first_name.into(),
+            last_name: last_name.into(),
+            npi: npi.into(),
+            taxonomy_code: None,
+            address: None,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -54,6 +527,48 @@ pub struct ServiceLine {
     pub unit_charge_amount: f64,
     pub modifiers: Option<Vec<String>>,
     pub do_not_bill: Option<bool>,
+    /// UB-04 revenue code of the department that provided the service, e.g. `0120` for room and board; institutional claims only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revenue_code: Option<String>,
+}
+
+impl ServiceLine {
+    /// `units` of `procedure_code` at `unit_charge_amount` each, in `DEFAULT_CURRENCY`, without modifiers
+    pub fn new(service_line_id: impl Into<String>, procedure_code: impl Into<String>, units: u32, unit_charge_amount: f64) -> Self {
+        Self {
+            service_line_id: service_line_id.into(),
+            procedure_code: procedure_code.into(),
+            units,
+            details: String::new(),
+            unit_charge_currency: DEFAULT_CURRENCY.to_string(),
+            unit_charge_amount,
+            modifiers: None,
+            do_not_bill: None,
+            revenue_code: None,
+        }
+    }
+
+    pub fn with_modifiers(mut self, modifiers: &[&str]) -> Self {
+        self.modifiers = Some(modifiers.iter().map(|m| m.to_string()).collect());
+        self
+    }
+
+    pub fn with_details(mut self, details: impl Into<String>) -> Self {
+        self.details = details.into();
+        self
+    }
+}
+
+/// Supporting document for a claim, as sent in a 275 attachment
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Attachment {
+    /// Report type code, e.g. `OB` for an operative note or `09` for a progress report
+    pub document_type: String,
+    /// Attachment control number tying the document to the claim
+    pub id: String,
+    /// Where the document itself is stored, if it travels separately
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload_path: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -72,67 +587,233 @@ pub struct Contact {
     pub phone_number: Option<String>,
 }
 
-/// Mock claim for testing
-pub fn mock_claim() -> PayerClaim {
-    PayerClaim {
-        claim_id: "abc123".to_string(),
-        place_of_service_code: 11,
-        insurance: Insurance {
-            payer_id: "medicare".to_string(),
-            patient_member_id: "pmid456".to_string(),
-        },
-        patient: Patient {
-            first_name: "Jane".to_string(),
-            last_name: "Doe".to_string(),
-            gender: "f".to_string(),
-            dob: "1990-01-01".to_string(),
-            email: Some("jane.doe@example.com".to_string()),
-            address: Some(Address {
-                street: Some("123 Main St".to_string()),
-                city: Some("Metropolis".to_string()),
-                state: Some("NY".to_string()),
-                zip: Some("12345".to_string()),
-                country: Some("USA".to_string()),
-            }),
-        },
-        organization: Organization {
-            name: "Health Inc".to_string(),
-            billing_npi: Some("9876543210".to_string()),
-            ein: Some("12-3456789".to_string()),
-            contact: Some(Contact {
-                first_name: Some("Bob".to_string()),
-                last_name: Some("Jones".to_string()),
-                phone_number: Some("555-1234".to_string()),
-            }),
-            address: Some(Address {
-                street: Some("456 Health Ave".to_string()),
-                city: Some("Gotham".to_string()),
-                state: Some("CA".to_string()),
-                zip: Some("67890".to_string()),
-                country: Some("USA".to_string()),
-            }),
-        },
-        rendering_provider: Provider {
-            first_name: "Alice".to_string(),
-            last_name: "Smith".to_string(),
-            npi: "1234567890".to_string(),
-        },
-        service_lines: vec![ServiceLine {
-            service_line_id: "sl1".to_string(),
-            procedure_code: "99213".to_string(),
-            units: 1,
-            details: "Office visit".to_string(),
-            unit_charge_currency: "USD".to_string(),
-            unit_charge_amount: 150.0,
-            modifiers: Some(vec!["A1".to_string(), "B2".to_string()]),
-            do_not_bill: Some(true),
-        }],
+/// Builds a `PayerClaim` a part at a time, checking at `build` that it is complete and consistent
+///
+/// Parts not set take the defaults of a claim read from a file: a professional, original claim
+/// in the office, billed by the organization, with no diagnoses, service date, header total, or
+/// attachments. The claim id, payer, member, patient, organization, rendering provider, and at
+/// least one service line are required
+#[derive(Debug, Clone, Default)]
+pub struct PayerClaimBuilder {
+    claim_id: Option<ClaimId>,
+    claim_type: ClaimType,
+    place_of_service: Option<PlaceOfService>,
+    payer_id: Option<PayerId>,
+    patient_member_id: Option<MemberId>,
+    subscriber: Option<Subscriber>,
+    patient: Option<Patient>,
+    organization: Option<Organization>,
+    rendering_provider: Option<Provider>,
+    billing_provider: Option<Provider>,
+    service_lines: Vec<ServiceLine>,
+    diagnosis_codes: Vec<String>,
+    service_date: Option<NaiveDate>,
+    urgent: Option<bool>,
+    total_charge_amount: Option<f64>,
+    original_claim_id: Option<ClaimId>,
+    parent_claim_id: Option<ClaimId>,
+    attachments: Vec<Attachment>,
+    extensions: BTreeMap<String, serde_json::Value>,
+}
+
+impl PayerClaimBuilder {
+    pub fn claim_id(mut self, claim_id: impl Into<ClaimId>) -> Self {
+        self.claim_id = Some(claim_id.into());
+        self
+    }
+
+    pub fn claim_type(mut self, claim_type: ClaimType) -> Self {
+        self.claim_type = claim_type;
+        self
+    }
+
+    pub fn place_of_service(mut self, place: PlaceOfService) -> Self {
+        self.place_of_service = Some(place);
+        self
+    }
+
+    pub fn payer(mut self, payer_id: impl Into<PayerId>) -> Self {
+        self.payer_id = Some(payer_id.into());
+        self
+    }
+
+    /// Member id the patient is covered under
+    pub fn member(mut self, patient_member_id: impl Into<MemberId>) -> Self {
+        self.patient_member_id = Some(patient_member_id.into());
+        self
+    }
+
+    /// Policyholder the patient is a dependent of; the patient holds the policy when not set
+    pub fn subscriber(mut self, subscriber: Subscriber) -> Self {
+        self.subscriber = Some(subscriber);
+        self
+    }
+
+    pub fn patient(mut self, patient: Patient) -> Self {
+        self.patient = Some(patient);
+        self
+    }
+
+    pub fn organization(mut self, organization: Organization) -> Self {
+        self.organization = Some(organization);
+        self
+    }
+
+    pub fn rendering_provider(mut self, provider: Provider) -> Self {
+        self.rendering_provider = Some(provider);
+        self
+    }
+
+    /// Bill under an individual's NPI rather than the organization's
+    pub fn billing_provider(mut self, provider: Provider) -> Self {
+        self.billing_provider = Some(provider);
+        self
+    }
+
+    /// Add a service line, after those already added
+    pub fn line(mut self, line: ServiceLine) -> Self {
+        self.service_lines.push(line);
+        self
+    }
+
+    /// Add a diagnosis, after those already added; the first is the primary
+    pub fn diagnosis(mut self, code: impl Into<String>) -> Self {
+        self.diagnosis_codes.push(code.into());
+        self
+    }
+
+    pub fn service_date(mut self, date: NaiveDate) -> Self {
+        self.service_date = Some(date);
+        self
+    }
+
+    pub fn urgent(mut self, urgent: bool) -> Self {
+        self.urgent = Some(urgent);
+        self
+    }
+
+    /// State a header total, which must equal the sum of the line charges
+    pub fn total_charge(mut self, amount: f64) -> Self {
+        self.total_charge_amount = Some(amount);
+        self
+    }
+
+    /// Bill the claim as a replacement correcting `original_claim_id`
+    pub fn replaces(mut self, original_claim_id: impl Into<ClaimId>) -> Self {
+        self.original_claim_id = Some(original_claim_id.into());
+        self
+    }
+
+    /// Mark the claim as a child split from `parent_claim_id`
+    pub fn parent(mut self, parent_claim_id: impl Into<ClaimId>) -> Self {
+        self.parent_claim_id = Some(parent_claim_id.into());
+        self
+    }
+
+    pub fn attachment(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    /// Add a specialty segment under `key` (see `extensions`)
+    pub fn extension(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.extensions.insert(key.into(), value);
+        self
+    }
+
+    /// The claim, or every reason it is incomplete or contradicts itself
+    pub fn build(self) -> anyhow::Result<PayerClaim> {
+        let mut problems = Vec::new();
+        let mut required = |missing: bool, what: &str| {
+            if missing {
+                problems.push(format!("{} is required", what));
+            }
+        };
+        required(self.claim_id.as_ref().is_none_or(|id| id.is_empty()), "a claim id");
+        required(self.payer_id.as_ref().is_none_or(|id| id.is_empty()), "a payer id");
+        required(self.patient_member_id.as_ref().is_none_or(|id| id.is_empty()), "a member id");
+        required(self.patient.is_none(), "a patient");
+        required(self.organization.is_none(), "an organization");
+        required(self.rendering_provider.is_none(), "a rendering provider");
+        required(self.service_lines.is_empty(), "at least one service line");
+
+        let mut line_ids = std::collections::HashSet::new();
+        for line in &self.service_lines {
+            if line.service_line_id.is_empty() {
+                problems.push("a service line has no id".to_string());
+            } else if !line_ids.insert(&line.service_line_id) {
+                problems.push(format!("service line {} is billed twice", line.service_line_id));
+            }
+            if line.units == 0 {
+                problems.push(format!("service line {} bills no units", line.service_line_id));
+            }
+            if !(line.unit_charge_amount.is_finite() && line.unit_charge_amount >= 0.0) {
+                problems.push(format!("service line {} charges {}", line.service_line_id, line.unit_charge_amount));
+            }
+        }
+        if let Some(first) = self.service_lines.first()
+            && let Some(line) = self.service_lines.iter().find(|line| !line.unit_charge_currency.eq_ignore_ascii_case(&first.unit_charge_currency))
+        {
+            problems.push(format!(
+                "service line {} is billed in {}, not the claim's {}",
+                line.service_line_id, line.unit_charge_currency, first.unit_charge_currency
+            ));
+        }
+        if let Some(institutional) = match &self.claim_type {
+            ClaimType::Institutional(institutional) => Some(institutional),
+            ClaimType::Professional => None,
+        } && institutional.discharge_date.is_some_and(|discharge| discharge < institutional.admission_date)
+        {
+            problems.push("discharge is before admission".to_string());
+        }
+
+        let (Some(claim_id), Some(payer_id), Some(patient_member_id), Some(patient), Some(organization), Some(rendering_provider)) = (
+            self.claim_id,
+            self.payer_id,
+            self.patient_member_id,
+            self.patient,
+            self.organization,
+            self.rendering_provider,
+        ) else {
+            return Err(anyhow::anyhow!("Invalid claim:\n  - {}", problems.join("\n  - ")));
+        };
+        let claim = PayerClaim {
+            claim_id,
+            claim_type: self.claim_type,
+            place_of_service_code: self.place_of_service.unwrap_or(PlaceOfService::OFFICE),
+            insurance: Insurance { payer_id, patient_member_id, subscriber: self.subscriber },
+            patient,
+            organization,
+            rendering_provider,
+            billing_provider: self.billing_provider,
+            service_lines: self.service_lines,
+            diagnosis_codes: self.diagnosis_codes,
+            service_date: self.service_date,
+            urgent: self.urgent,
+            total_charge_amount: self.total_charge_amount,
+            claim_frequency_code: match self.original_claim_id {
+                Some(_) => ClaimFrequency::Replacement,
+                None => ClaimFrequency::Original,
+            },
+            original_claim_id: self.original_claim_id,
+            parent_claim_id: self.parent_claim_id,
+            attachments: self.attachments,
+            extensions: self.extensions,
+        };
+        if let Some(total) = validation::validate_claim_total(&claim) {
+            problems.push(total.reason);
+        }
+        match problems.is_empty() {
+            true => Ok(claim),
+            false => Err(anyhow::anyhow!("Invalid claim {}:\n  - {}", claim.claim_id, problems.join("\n  - "))),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::mock_claim;
     use serde_json::from_str;
 
     #[test]
@@ -179,7 +860,8 @@ mod tests {
             "rendering_provider": {
                 "first_name": "Alice",
                 "last_name": "Smith",
-                "npi": "1234567890"
+                "npi": "1234567890",
+                "taxonomy_code": "207Q00000X"
             },
             "service_lines": [
                 {
@@ -199,13 +881,13 @@ mod tests {
         let claim: PayerClaim = from_str(json).expect("Failed to parse JSON");
         println!("{:#?}", claim); // use cargo test -- --nocapture to still debug print on success
         assert_eq!(claim.claim_id, "abc123");
-        assert_eq!(claim.place_of_service_code, 11);
+        assert_eq!(claim.place_of_service_code, PlaceOfService::OFFICE);
         assert_eq!(claim.insurance.payer_id, "medicare");
         assert_eq!(claim.insurance.patient_member_id, "pmid456");
         assert_eq!(claim.patient.first_name, "Jane");
         assert_eq!(claim.patient.last_name, "Doe");
-        assert_eq!(claim.patient.gender, "f");
-        assert_eq!(claim.patient.dob, "1990-01-01");
+        assert_eq!(claim.patient.gender, Gender::Female);
+        assert_eq!(claim.patient.dob, NaiveDate::from_ymd_opt(1990, 1, 1));
         assert_eq!(claim.patient.email.as_deref(), Some("jane.doe@example.com"));
         let p_addr = claim
             .patient
@@ -244,6 +926,9 @@ mod tests {
         assert_eq!(claim.rendering_provider.first_name, "Alice");
         assert_eq!(claim.rendering_provider.last_name, "Smith");
         assert_eq!(claim.rendering_provider.npi, "1234567890");
+        assert_eq!(claim.rendering_provider.taxonomy_code.as_deref(), Some("207Q00000X"));
+        assert!(claim.billing_provider.is_none());
+        assert_eq!(claim.billing_npi().map(|npi| npi.as_str()), Some("9876543210"));
         assert_eq!(claim.service_lines.len(), 1);
         let sl = &claim.service_lines[0];
         assert_eq!(sl.service_line_id, "sl1");
@@ -254,5 +939,140 @@ mod tests {
         assert_eq!(sl.unit_charge_amount, 150.0);
         assert_eq!(sl.modifiers.as_ref().unwrap(), &["A1", "B2"]);
         assert_eq!(sl.do_not_bill, Some(true));
+        assert!(claim.claim_type.is_professional());
+    }
+
+    /// Test that an institutional claim reads its type tag and facility fields, and a professional claim omits them.
+    /// Expected: A `0111` bill with a three-night stay parses as inpatient; the mock professional claim serializes without `claim_type`.
+    #[test]
+    fn test_institutional_claim_type() {
+        let claim_type: ClaimType = from_str(
+            r#"{"type": "institutional", "bill_type": "0111", "admission_date": "2024-03-01", "discharge_date": "2024-03-04", "drg": "470"}"#,
+        )
+        .unwrap();
+        let ClaimType::Institutional(institutional) = &claim_type else {
+            panic!("expected an institutional claim, got {:?}", claim_type);
+        };
+        assert!(institutional.is_inpatient());
+        assert_eq!(institutional.length_of_stay(), Some(3));
+        assert_eq!(institutional.drg.as_deref(), Some("470"));
+
+        let mut claim = mock_claim();
+        assert!(serde_json::to_value(&claim).unwrap().get("claim_type").is_none());
+        claim.claim_type = claim_type;
+        let round_trip: PayerClaim = serde_json::from_value(serde_json::to_value(&claim).unwrap()).unwrap();
+        assert_eq!(round_trip.institutional().map(|institutional| institutional.bill_type.as_str()), Some("0111"));
+    }
+
+    /// Test that an oversized claim splits into children of at most the limit, each with a new id and totalling its own lines.
+    /// Expected: Five lines split by two give three children with sorted generated ids naming the parent, with totals summing to the parent's; a claim within the limit comes back whole.
+    #[test]
+    fn test_split_claim() {
+        let mut claim = mock_claim();
+        claim.service_lines = (1..=5)
+            .map(|i| ServiceLine {
+                service_line_id: format!("sl{}", i),
+                unit_charge_amount: 10.0 * i as f64,
+                ..claim.service_lines[0].clone()
+            })
+            .collect();
+        claim.total_charge_amount = Some(claim.total_charge());
+        let ids = ClaimIdGenerator::new("split");
+        let children = claim.clone().split(2, &ids);
+        let child_ids: Vec<&ClaimId> = children.iter().map(|child| &child.claim_id).collect();
+        assert!(child_ids.is_sorted() && child_ids.iter().all(|id| id.as_str().starts_with("split-")));
+        assert_eq!(children.iter().map(|child| child.service_lines.len()).collect::<Vec<_>>(), [2, 2, 1]);
+        assert!(children.iter().all(|child| child.parent_claim_id.as_ref() == Some(&claim.claim_id)));
+        assert!(children.iter().all(|child| child.total_charge_amount == Some(child.total_charge())));
+        assert_eq!(children.iter().map(PayerClaim::total_charge).sum::<f64>(), claim.total_charge());
+        let whole = claim.clone().split(5, &ids);
+        assert_eq!((whole.len(), &whole[0].claim_id, &whole[0].parent_claim_id), (1, &claim.claim_id, &None));
+    }
+
+    /// Test that priority is derived from the urgent flag or the dollar amount.
+    /// Expected: Small claims are normal unless flagged urgent; large claims are high.
+    #[test]
+    fn test_claim_priority() {
+        let mut claim = mock_claim();
+        assert_eq!(claim.priority(), Priority::Normal);
+        claim.urgent = Some(true);
+        assert_eq!(claim.priority(), Priority::High);
+        claim.urgent = None;
+        claim.service_lines[0].units = 10;
+        assert_eq!(claim.total_charge(), 1500.0);
+        assert_eq!(claim.priority(), Priority::High);
+    }
+
+    /// Test that gender accepts codes and words in any case, and that age counts whole years.
+    /// Expected: "FEMALE" parses as female and serializes as "f"; a birthday not yet reached this year is not counted.
+    #[test]
+    fn test_patient_demographics() {
+        let gender: Gender = from_str("\"FEMALE\"").unwrap();
+        assert_eq!(gender, Gender::Female);
+        assert_eq!(serde_json::to_string(&gender).unwrap(), "\"f\"");
+        assert!(from_str::<Gender>("\"x\"").is_err());
+
+        let mut patient = mock_claim().patient;
+        patient.dob = NaiveDate::from_ymd_opt(2010, 6, 15);
+        assert_eq!(patient.age_on(NaiveDate::from_ymd_opt(2028, 6, 14).unwrap()), Some(17));
+        assert!(patient.is_pediatric_on(NaiveDate::from_ymd_opt(2028, 6, 14).unwrap()));
+        assert!(!patient.is_pediatric_on(NaiveDate::from_ymd_opt(2028, 6, 15).unwrap()));
+        patient.dob = None;
+        assert!(!patient.is_pediatric_on(NaiveDate::from_ymd_opt(2028, 6, 15).unwrap()));
+    }
+
+    /// Test that a subscriber block parses with its relationship code and keys coverage by policy and patient.
+    /// Expected: Two children on one policy share a subscriber id but get distinct coverage keys; no block means the patient holds the policy.
+    #[test]
+    fn test_subscriber_coverage() {
+        let insurance: Insurance = from_str(
+            r#"{"payer_id": "anthem", "patient_member_id": "sub789-01",
+                "subscriber": {"member_id": "sub789", "first_name": "John", "last_name": "Doe", "relationship": "19"}}"#,
+        )
+        .unwrap();
+        assert_eq!(insurance.relationship(), Relationship::Child);
+        assert_eq!(insurance.subscriber_id(), "sub789");
+        let mut sibling = insurance.clone();
+        sibling.patient_member_id = "sub789-02".into();
+        assert_ne!(insurance.coverage_key(), sibling.coverage_key());
+        assert_eq!(insurance.coverage_key().subscriber_id, sibling.coverage_key().subscriber_id);
+
+        let own = mock_claim().insurance;
+        assert_eq!(own.relationship(), Relationship::SelfInsured);
+        assert_eq!(own.subscriber_id(), &own.patient_member_id);
+        assert!(from_str::<Relationship>("\"99\"").is_err());
+    }
+
+    /// Test that the claim builder fills in defaults and lists every problem with an incomplete or contradictory claim.
+    /// Expected: A minimal claim is an original office claim; a claim missing its payer with a repeated line and a wrong total names all three.
+    #[test]
+    fn test_payer_claim_builder() {
+        let builder = PayerClaim::builder()
+            .claim_id("clm-1")
+            .payer("anthem")
+            .member("m-1")
+            .patient(Patient::new("Jane", "Doe", Gender::Female))
+            .organization(Organization::new("Health Inc"))
+            .rendering_provider(Provider::new("Alice", "Smith", "1234567893"))
+            .line(ServiceLine::new("sl1", "99213", 2, 75.0));
+        let claim = builder.clone().replaces("clm-0").build().unwrap();
+        assert_eq!(claim.place_of_service_code, PlaceOfService::OFFICE);
+        assert_eq!(claim.total_charge(), 150.0);
+        assert_eq!(claim.replaces().map(ClaimId::as_str), Some("clm-0"));
+
+        let err = PayerClaim::builder()
+            .claim_id("clm-2")
+            .member("m-1")
+            .patient(Patient::new("Jane", "Doe", Gender::Female))
+            .organization(Organization::new("Health Inc"))
+            .rendering_provider(Provider::new("Alice", "Smith", "1234567893"))
+            .line(ServiceLine::new("sl1", "99213", 1, 75.0))
+            .line(ServiceLine::new("sl1", "99214", 1, 90.0))
+            .build()
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("a payer id is required") && err.contains("sl1 is billed twice"), "{}", err);
+        let err = builder.total_charge(100.0).build().unwrap_err().to_string();
+        assert!(err.contains("claim total 100.00 does not match line charges 150.00"), "{}", err);
     }
 }
@@ -0,0 +1,1065 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::JoinHandle;
+
+use crate::biller::{self, BillerHooks};
+#[cfg(feature = "chaos")]
+use crate::chaos::{ChaosLayer, ChaosSettings, ChaosStats};
+use crate::clearinghouse::{Clearinghouse, DEFAULT_CLEARINGHOUSE_SHARDS};
+use crate::clock::SimClock;
+use crate::config::Config;
+use crate::control::{BillerHandle, Controls, PayerSettings, TELEHEALTH_PARITY};
+use crate::debugger::Debugger;
+use crate::edits::{EditRates, EditStats};
+use crate::audit_log::AuditLog;
+use crate::event_log::EventLog;
+use crate::file_drop::{self, FileDropSettings};
+#[cfg(feature = "grpc")]
+use crate::grpc_payer::GrpcPayer;
+use crate::held_remittances::HeldRemittances;
+use crate::history::History;
+use crate::inventory::ClaimInventory;
+use crate::ledger::Ledger;
+use crate::medical_necessity::CoveragePolicy;
+use crate::message::{ClaimMessage, ClaimStatus, PatientMessage, PayerMessage, RemittanceMessage};
+use crate::adjudicator::SharedAdjudicator;
+use crate::attachments::AttachmentPolicy;
+use crate::claim_ids::ClaimIdGenerator;
+use crate::claim_source::{BoxedClaimSource, ClaimSource};
+use crate::benefits::BenefitPlans;
+use crate::outcomes::OutcomeTable;
+use crate::payer_rules::RuleBook;
+use crate::patient_payer::{CollectionsPolicy, PatientPayer};
+use crate::payer::{DEFAULT_PAYER_WORKERS, Payer, PayerPortal};
+use crate::posting::WriteOffRules;
+use crate::phi::Deidentification;
+use crate::queue::{self, Overflow, OverflowPolicy, QueueStats};
+use crate::reader::ProcessedClaims;
+use crate::roster::Roster;
+use crate::remittance::MismatchPolicy;
+use crate::remittance_files::RemittanceFiles;
+use crate::remittance_sink::SharedRemittanceSink;
+use crate::schema::PayerClaim;
+use crate::supervisor::{Heartbeat, Supervisor};
+use crate::tenant::DEFAULT_TENANT;
+use crate::ids::{ClaimId, PayerId};
+
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 100;
+
+/// One simulated insurance company, its adjudication and acknowledgment delay ranges in seconds, number of
+/// adjudicators, denial rate, per-procedure outcomes, medical necessity rules, documentation
+/// requirements, benefit plans, member roster, and how it handles remittances that do not balance,
+/// or the custom adjudicator deciding its claims instead
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayerSpec {
+    pub payer_id: PayerId,
+    pub min_delay_secs: u64,
+    pub max_delay_secs: u64,
+    /// Range of seconds before the payer acknowledges a claim it takes in; none when it never does
+    pub ack_delay_secs: Option<(u64, u64)>,
+    pub workers: usize,
+    pub denial_rate: f64,
+    pub error_rate: f64,
+    pub mismatch_policy: MismatchPolicy,
+    pub telehealth_rate: f64,
+    pub outcomes: Arc<OutcomeTable>,
+    pub coverage: Arc<CoveragePolicy>,
+    pub attachments: Arc<AttachmentPolicy>,
+    pub benefits: Arc<BenefitPlans>,
+    pub roster: Arc<Roster>,
+    /// Rules shared with other payers and replaced while they run, in place of the outcomes,
+    /// coverage, attachments, and benefits above
+    pub rules: Option<RuleBook>,
+    /// Decides claims in place of the simulated payer when set
+    pub adjudicator: Option<SharedAdjudicator>,
+    /// Seeds the simulated payer's random draws when set, making its decisions repeatable
+    pub seed: Option<u64>,
+}
+
+impl PayerSpec {
+    pub fn new(payer_id: impl Into<PayerId>, min_delay_secs: u64, max_delay_secs: u64) -> Self {
+        Self {
+            payer_id: payer_id.into(),
+            min_delay_secs,
+            max_delay_secs,
+            ack_delay_secs: None,
+            workers: DEFAULT_PAYER_WORKERS,
+            denial_rate: 0.0,
+            error_rate: 0.0,
+            mismatch_policy: MismatchPolicy::default(),
+            telehealth_rate: TELEHEALTH_PARITY,
+            outcomes: Arc::default(),
+            coverage: Arc::default(),
+            attachments: Arc::default(),
+            benefits: Arc::default(),
+            roster: Arc::default(),
+            rules: None,
+            adjudicator: None,
+            seed: None,
+        }
+    }
+
+    /// Adjudicate at most this many claims at once
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Acknowledge each claim taken in after `min_delay_secs..=max_delay_secs`, well before the
+    /// adjudication delay ends for a payer that acks quickly and pays slowly
+    pub fn with_ack_delay(mut self, min_delay_secs: u64, max_delay_secs: u64) -> Self {
+        self.ack_delay_secs = Some((min_delay_secs, max_delay_secs));
+        self
+    }
+
+    /// Deny this fraction of claims outright
+    pub fn with_denial_rate(mut self, denial_rate: f64) -> Self {
+        self.denial_rate = denial_rate;
+        self
+    }
+
+    /// Price this fraction of remittances wrong, so they fail the balancing check
+    pub fn with_error_rate(mut self, error_rate: f64) -> Self {
+        self.error_rate = error_rate;
+        self
+    }
+
+    pub fn with_mismatch_policy(mut self, mismatch_policy: MismatchPolicy) -> Self {
+        self.mismatch_policy = mismatch_policy;
+        self
+    }
+
+    /// Pay telehealth lines this fraction of the in-person allowed amount
+    pub fn with_telehealth_rate(mut self, telehealth_rate: f64) -> Self {
+        self.telehealth_rate = telehealth_rate;
+        self
+    }
+
+    /// Rule on each line by its procedure code, e.g. to deny imaging without authorization
+    pub fn with_outcomes(mut self, outcomes: Arc<OutcomeTable>) -> Self {
+        self.outcomes = outcomes;
+        self
+    }
+
+    /// Deny lines whose procedure the claim's diagnoses do not justify
+    pub fn with_coverage(mut self, coverage: Arc<CoveragePolicy>) -> Self {
+        self.coverage = coverage;
+        self
+    }
+
+    /// Hold claims for procedures that need documentation until the biller sends it
+    pub fn with_attachments(mut self, attachments: Arc<AttachmentPolicy>) -> Self {
+        self.attachments = attachments;
+        self
+    }
+
+    /// Plan designs and the members enrolled in each, setting patients' cost sharing
+    pub fn with_benefits(mut self, benefits: Arc<BenefitPlans>) -> Self {
+        self.benefits = benefits;
+        self
+    }
+
+    /// Members and their coverage dates; claims for members not covered on the date of service are denied
+    pub fn with_roster(mut self, roster: Arc<Roster>) -> Self {
+        self.roster = roster;
+        self
+    }
+
+    /// Adjudicate under rules a `RuleWatcher` may replace while the payer runs
+    pub fn with_rules(mut self, rules: RuleBook) -> Self {
+        self.rules = Some(rules);
+        self
+    }
+
+    /// Draw denials, line outcomes, delays, and pricing errors from `seed` and each claim's id
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Decide claims with a custom adjudicator; the outcome, coverage, roster, benefit, and denial
+    /// rate settings configure only the simulated one
+    pub fn with_adjudicator(mut self, adjudicator: impl Into<SharedAdjudicator>) -> Self {
+        self.adjudicator = Some(adjudicator.into());
+        self
+    }
+
+    /// The payer's adjudication behavior as its control handle reports it
+    pub fn settings(&self) -> PayerSettings {
+        PayerSettings {
+            min_response_time_secs: self.min_delay_secs,
+            max_response_time_secs: self.max_delay_secs,
+            ack_time_secs: self.ack_delay_secs,
+            denial_rate: self.denial_rate,
+            error_rate: self.error_rate,
+            mismatch_policy: self.mismatch_policy,
+            telehealth_rate: self.telehealth_rate,
+        }
+    }
+}
+
+/// Patient payment behavior: probability a statement is paid, and the delay range in seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PatientPayerSpec {
+    payment_probability: f64,
+    min_delay_secs: u64,
+    max_delay_secs: u64,
+}
+
+/// One billing organization: its claim source, expected claim count, and posting behavior
+pub struct BillerSpec {
+    tenant_id: String,
+    source: Option<BoxedClaimSource>,
+    total_claims: Option<usize>,
+    write_off_rules: WriteOffRules,
+    patient_payer: Option<PatientPayerSpec>,
+    collections: CollectionsPolicy,
+    notify_tx: Option<mpsc::Sender<ClaimId>>,
+    sinks: Vec<SharedRemittanceSink>,
+    claim_ids: ClaimIdGenerator,
+    processed: Option<ProcessedClaims>,
+}
+
+impl BillerSpec {
+    pub fn new(tenant_id: impl Into<String>) -> Self {
+        Self {
+            tenant_id: tenant_id.into(),
+            source: None,
+            total_claims: None,
+            write_off_rules: WriteOffRules::default(),
+            patient_payer: None,
+            collections: CollectionsPolicy::default(),
+            notify_tx: None,
+            sinks: Vec::new(),
+            claim_ids: ClaimIdGenerator::default(),
+            processed: None,
+        }
+    }
+
+    pub fn with_source(mut self, source: impl ClaimSource) -> Self {
+        self.source = Some(BoxedClaimSource::new(source));
+        self
+    }
+
+    /// Remittances to wait for before signalling completion
+    ///
+    /// Defaults to the source's `expected_claims`, otherwise to "until the input ends"
+    pub fn with_total_claims(mut self, total_claims: usize) -> Self {
+        self.total_claims = Some(total_claims);
+        self
+    }
+
+    pub fn with_write_off_rules(mut self, write_off_rules: WriteOffRules) -> Self {
+        self.write_off_rules = write_off_rules;
+        self
+    }
+
+    /// Issue patient statements and simulate patients paying them
+    pub fn with_patient_payer(mut self, payment_probability: f64, min_delay_secs: u64, max_delay_secs: u64) -> Self {
+        self.patient_payer = Some(PatientPayerSpec {
+            payment_probability,
+            min_delay_secs,
+            max_delay_secs,
+        });
+        self
+    }
+
+    /// Statement cycle for balances the patient payer leaves unpaid
+    pub fn with_collections(mut self, collections: CollectionsPolicy) -> Self {
+        self.collections = collections;
+        self
+    }
+
+    /// Receive the claim id of every remittance the biller gets back
+    pub fn with_notify(mut self, notify_tx: mpsc::Sender<ClaimId>) -> Self {
+        self.notify_tx = Some(notify_tx);
+        self
+    }
+
+    /// Tell a downstream system about every remittance this biller receives
+    pub fn with_sink(mut self, sink: impl Into<SharedRemittanceSink>) -> Self {
+        self.sinks.push(sink.into());
+        self
+    }
+
+    /// Identify the claims this biller creates, such as split children, with `ids`' prefix
+    pub fn with_claim_ids(mut self, claim_ids: ClaimIdGenerator) -> Self {
+        self.claim_ids = claim_ids;
+        self
+    }
+
+    /// Acknowledge each claim submitted to `processed`, shared with a checkpointing reader's options
+    pub fn with_processed_claims(mut self, processed: ProcessedClaims) -> Self {
+        self.processed = Some(processed);
+        self
+    }
+}
+
+impl Default for BillerSpec {
+    fn default() -> Self {
+        Self::new(DEFAULT_TENANT)
+    }
+}
+
+/// Collects payers, billers, and claim sources, then wires and spawns the whole pipeline
+#[derive(Default)]
+pub struct SimulationBuilder {
+    config: Config,
+    payers: Vec<PayerSpec>,
+    billers: Vec<BillerSpec>,
+    event_log: Option<EventLog>,
+    audit_log: Option<AuditLog>,
+    debugger: Option<Debugger>,
+    supervisor: Option<Supervisor>,
+    channel_capacity: Option<usize>,
+    clearinghouse_shards: Option<usize>,
+    edits: EditRates,
+    overflow_policy: OverflowPolicy,
+    clock: Option<SimClock>,
+    file_drop: Option<FileDropSettings>,
+    deidentification: Deidentification,
+    remittance_files_dir: Option<String>,
+    #[cfg(feature = "chaos")]
+    chaos: Option<ChaosSettings>,
+    #[cfg(feature = "grpc")]
+    grpc_payers: Vec<(PayerId, String)>,
+}
+
+impl SimulationBuilder {
+    /// Settings passed to each biller (verbosity, ingest rate)
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn payer(mut self, payer: PayerSpec) -> Self {
+        self.payers.push(payer);
+        self
+    }
+
+    pub fn biller(mut self, biller: BillerSpec) -> Self {
+        self.billers.push(biller);
+        self
+    }
+
+    /// Set the claim source of the most recently added biller, adding a default-tenant biller if there is none
+    pub fn claim_source(mut self, source: impl ClaimSource) -> Self {
+        if self.billers.is_empty() {
+            self.billers.push(BillerSpec::default());
+        }
+        if let Some(biller) = self.billers.last_mut() {
+            biller.source = Some(BoxedClaimSource::new(source));
+        }
+        self
+    }
+
+    /// Record pipeline events to `event_log`
+    pub fn event_log(mut self, event_log: EventLog) -> Self {
+        self.event_log = Some(event_log);
+        self
+    }
+
+    /// Record who creates, discloses, remits, voids, and posts each claim record to `audit_log`
+    pub fn audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Hold every message at the component taking it up while `debugger` is paused, and
+    /// show the depth of the channels between components
+    pub fn debugger(mut self, debugger: Debugger) -> Self {
+        self.debugger = Some(debugger);
+        self
+    }
+
+    /// Run the clearinghouse, payers, and billers under `supervisor`, which tracks their health,
+    /// restarts payers that panic or stall, and reports the rest failed
+    pub fn supervisor(mut self, supervisor: Supervisor) -> Self {
+        self.supervisor = Some(supervisor);
+        self
+    }
+
+    /// Capacity of every internal channel (default 100)
+    pub fn channel_capacity(mut self, capacity: usize) -> Self {
+        self.channel_capacity = Some(capacity);
+        self
+    }
+
+    /// Worker shards the clearinghouse splits claims across (default 4)
+    pub fn clearinghouse_shards(mut self, shards: usize) -> Self {
+        self.clearinghouse_shards = Some(shards);
+        self
+    }
+
+    /// Fraction of claims the clearinghouse rejects back to their billers at each level of
+    /// front-end edits (default: none)
+    pub fn edits(mut self, edits: EditRates) -> Self {
+        self.edits = edits;
+        self
+    }
+
+    /// What the clearinghouse's and payers' claim inboxes do when full (default: block the sender)
+    ///
+    /// Claims that are dropped or rejected are answered with `RemittanceMessage::Rejected`
+    /// and counted in `SimulationMetrics`; remittance and patient channels always block, since
+    /// money already paid cannot be turned away
+    pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// Simulated calendar payers date claims by (default: starting today, advancing a day
+    /// every `Config::secs_per_day` real seconds)
+    pub fn clock(mut self, clock: SimClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Have billers submit claims as batch files the clearinghouse polls a directory for, and
+    /// the clearinghouse answer with remittance files polled for in turn, instead of messages
+    pub fn file_drop(mut self, settings: FileDropSettings) -> Self {
+        self.file_drop = Some(settings);
+        self
+    }
+
+    /// Have the clearinghouse write each remittance it records to a file per payer per
+    /// simulated day under `dir`
+    pub fn remittance_files(mut self, dir: impl Into<String>) -> Self {
+        self.remittance_files_dir = Some(dir.into());
+        self
+    }
+
+    /// How patient demographics are de-identified in the files the run leaves behind: archived
+    /// file-drop batches and remittance files' member ids (default: written as received)
+    pub fn deidentification(mut self, deidentification: Deidentification) -> Self {
+        self.deidentification = deidentification;
+        self
+    }
+
+    /// Relay traffic between the clearinghouse and payers through a layer that closes
+    /// channels, delays messages, and delivers them twice at the given rates
+    #[cfg(feature = "chaos")]
+    pub fn chaos(mut self, settings: ChaosSettings) -> Self {
+        self.chaos = Some(settings);
+        self
+    }
+
+    /// Route a payer id's claims to an external `PayerService` at `endpoint`, e.g. `http://localhost:50051`,
+    /// instead of a simulated payer
+    #[cfg(feature = "grpc")]
+    pub fn grpc_payer(mut self, payer_id: impl Into<PayerId>, endpoint: impl Into<String>) -> Self {
+        self.grpc_payers.push((payer_id.into(), endpoint.into()));
+        self
+    }
+
+    /// Create the channels and spawn the clearinghouse, payers, and each biller's tasks
+    ///
+    /// Must be called from within a Tokio runtime
+    pub fn build(self) -> anyhow::Result<Simulation> {
+        #[cfg(feature = "grpc")]
+        let external = self.grpc_payers.len();
+        #[cfg(not(feature = "grpc"))]
+        let external = 0;
+        if self.payers.len() + external == 0 {
+            return Err(anyhow!("Simulation needs at least one payer"));
+        }
+        if self.billers.is_empty() {
+            return Err(anyhow!("Simulation needs at least one biller"));
+        }
+        let mut tenant_ids = HashSet::new();
+        for biller in &self.billers {
+            if !tenant_ids.insert(biller.tenant_id.as_str()) {
+                return Err(anyhow!("Duplicate biller tenant: {}", biller.tenant_id));
+            }
+            if biller.source.is_none() {
+                return Err(anyhow!("Biller {} has no claim source", biller.tenant_id));
+            }
+        }
+        for payer in &self.payers {
+            payer.settings().validate().map_err(|e| anyhow!("Payer {}: {}", payer.payer_id, e))?;
+        }
+        let capacity = self.channel_capacity.unwrap_or(DEFAULT_CHANNEL_CAPACITY).max(1);
+        let queue_stats = QueueStats::default();
+        let channels = Channels {
+            capacity,
+            overflow_policy: self.overflow_policy,
+            queue_stats: queue_stats.clone(),
+            #[cfg(feature = "chaos")]
+            chaos: self.chaos.map(ChaosLayer::new).transpose()?,
+        };
+        let verbose = self.config.verbose;
+        let clock = self
+            .clock
+            .unwrap_or_else(|| SimClock::new(chrono::Utc::now().date_naive(), self.config.secs_per_day));
+        let mut tasks = Vec::new();
+        let mut controls = Controls::default();
+        if let Some(supervisor) = &self.supervisor {
+            tasks.push(tokio::spawn(supervisor.clone().monitor()));
+        }
+        let heartbeat = |component, id: &str| self.supervisor.as_ref().map_or_else(Heartbeat::default, |supervisor| supervisor.register(component, id));
+
+        let (claim_tx, claim_rx) = channels.inbox(());
+        let (remit_tx, remit_rx) = channels.channel();
+        if let Some(debugger) = &self.debugger {
+            debugger.watch_channel("clearinghouse:claims", &claim_tx);
+            debugger.watch_channel("clearinghouse:remittances", &remit_tx);
+        }
+        let history = History::new(self.clearinghouse_shards.unwrap_or(DEFAULT_CLEARINGHOUSE_SHARDS));
+
+        let mut payer_txs = HashMap::new();
+        let mut portals = BTreeMap::new();
+        for spec in self.payers {
+            let (payer_tx, payer_rx) = channels.payer_inbox(remit_tx.clone());
+            if let Some(debugger) = &self.debugger {
+                debugger.watch_channel(format!("payer:{}", spec.payer_id), &payer_tx);
+            }
+            payer_txs.insert(spec.payer_id.clone(), payer_tx.clone());
+            let mut payer = Payer::new(
+                spec.payer_id.clone(),
+                spec.min_delay_secs,
+                spec.max_delay_secs,
+                remit_tx.clone(),
+                payer_rx,
+                verbose,
+            )
+            .with_workers(spec.workers)
+            .with_outcomes(spec.outcomes.clone())
+            .with_coverage(spec.coverage.clone())
+            .with_attachments(spec.attachments.clone())
+            .with_benefits(spec.benefits.clone())
+            .with_roster(spec.roster.clone())
+            .with_clock(clock);
+            if let Some(rules) = spec.rules {
+                payer = payer.with_rules(rules);
+            }
+            if let Some(adjudicator) = spec.adjudicator {
+                payer = payer.with_adjudicator(adjudicator);
+            }
+            if let Some(seed) = spec.seed {
+                payer = payer.with_seed(seed);
+            }
+            let handle = payer.handle();
+            handle.reconfigure(PayerSettings {
+                ack_time_secs: spec.ack_delay_secs,
+                denial_rate: spec.denial_rate,
+                error_rate: spec.error_rate,
+                mismatch_policy: spec.mismatch_policy,
+                telehealth_rate: spec.telehealth_rate,
+                ..handle.settings()
+            })?;
+            let heartbeat = heartbeat("payer", spec.payer_id.as_str());
+            heartbeat.watch_inbox(&payer_tx);
+            heartbeat.watch_control(&handle);
+            payer = payer.with_heartbeat(heartbeat.clone());
+            portals.insert(spec.payer_id.clone(), payer.portal());
+            controls.payers.insert(spec.payer_id, handle);
+            if let Some(event_log) = &self.event_log {
+                payer = payer.with_event_log(event_log.clone());
+            }
+            if let Some(audit_log) = &self.audit_log {
+                payer = payer.with_audit_log(audit_log.clone());
+            }
+            if let Some(debugger) = &self.debugger {
+                payer = payer.with_debugger(debugger.clone());
+            }
+            tasks.push(match &self.supervisor {
+                // a replacement takes over the same inbox and claim store
+                Some(supervisor) => supervisor.supervise(&heartbeat, move || payer.replacement().run()),
+                None => tokio::spawn(payer.run()),
+            });
+        }
+
+        #[cfg(feature = "grpc")]
+        for (payer_id, endpoint) in self.grpc_payers {
+            if payer_txs.contains_key(&payer_id) {
+                return Err(anyhow!("Payer {} is both simulated and external", payer_id));
+            }
+            let (payer_tx, payer_rx) = channels.payer_inbox(remit_tx.clone());
+            payer_txs.insert(payer_id.clone(), payer_tx);
+            tasks.push(tokio::spawn(GrpcPayer::new(payer_id, endpoint, remit_tx.clone(), payer_rx, verbose).run()));
+        }
+
+        let mut clearinghouse = Clearinghouse::new(
+            claim_rx,
+            payer_txs,
+            remit_rx,
+            history.clone(),
+            verbose,
+        )
+        .with_edits(self.edits);
+        let edit_stats = clearinghouse.edit_stats();
+        let inventory = clearinghouse.inventory();
+        if let Some(event_log) = self.event_log {
+            clearinghouse = clearinghouse.with_event_log(event_log);
+        }
+        if let Some(audit_log) = &self.audit_log {
+            clearinghouse = clearinghouse.with_audit_log(audit_log.clone());
+        }
+        let remittance_files = match &self.remittance_files_dir {
+            Some(dir) => Some(RemittanceFiles::create(dir, clock)?.with_deidentification(self.deidentification.clone())),
+            None => None,
+        };
+        if let Some(files) = &remittance_files {
+            clearinghouse = clearinghouse.with_remittance_files(files.clone());
+        }
+        if let Some(debugger) = &self.debugger {
+            clearinghouse = clearinghouse.with_debugger(debugger.clone());
+        }
+        let clearinghouse_heartbeat = heartbeat("clearinghouse", "clearinghouse");
+        clearinghouse_heartbeat.watch_inbox(&claim_tx);
+        clearinghouse_heartbeat.watch_inbox(&remit_tx);
+        clearinghouse_heartbeat.watch_control(&clearinghouse.handle());
+        clearinghouse = clearinghouse.with_heartbeat(clearinghouse_heartbeat.clone());
+        controls.clearinghouse = Some(clearinghouse.handle());
+        tasks.push(spawn_once(self.supervisor.as_ref(), &clearinghouse_heartbeat, clearinghouse.run()));
+
+        let billers = self.billers.len();
+        let (shutdown_tx, shutdown_rx) = mpsc::channel(billers);
+        let mut ledgers = BTreeMap::new();
+        let mut held_remittances = BTreeMap::new();
+        for spec in self.billers {
+            let ledger = Arc::new(Mutex::new(Ledger::new()));
+            ledgers.insert(spec.tenant_id.clone(), ledger.clone());
+
+            let patient_tx = spec.patient_payer.map(|patient| {
+                let (patient_tx, patient_rx) = mpsc::channel::<PatientMessage>(capacity);
+                let patient_payer = PatientPayer::new(
+                    patient.payment_probability,
+                    patient.min_delay_secs,
+                    patient.max_delay_secs,
+                    patient_rx,
+                    ledger.clone(),
+                    verbose,
+                )
+                .with_collections(spec.collections)
+                .with_clock(clock);
+                tasks.push(tokio::spawn(patient_payer.run()));
+                patient_tx
+            });
+
+            let Some(source) = spec.source else {
+                continue;
+            };
+            let source_file = source.source_file().map(str::to_string);
+            let (claims_tx, claims_rx) = mpsc::channel(capacity);
+            if let Some(debugger) = &self.debugger {
+                debugger.watch_channel(format!("biller:{}", spec.tenant_id), &claims_tx);
+            }
+            let claims_tx_probe = claims_tx.clone();
+            let default_total = spawn_claim_source(source, claims_tx, verbose, &mut tasks);
+            let held = HeldRemittances::new();
+            held_remittances.insert(spec.tenant_id.clone(), held.clone());
+            let control = BillerHandle::new(());
+            controls.billers.insert(spec.tenant_id.clone(), control.clone());
+            let biller_heartbeat = heartbeat("biller", &spec.tenant_id);
+            biller_heartbeat.watch_inbox(&claims_tx_probe);
+            biller_heartbeat.watch_control(&control);
+            let hooks = BillerHooks {
+                ledger: Some(ledger),
+                patient_tx,
+                write_off_rules: spec.write_off_rules,
+                tenant_id: Some(spec.tenant_id.clone()),
+                control: Some(control),
+                source_file,
+                sinks: spec.sinks,
+                audit_log: self.audit_log.clone(),
+                debugger: self.debugger.clone(),
+                heartbeat: biller_heartbeat.clone(),
+                claim_ids: spec.claim_ids,
+                processed: spec.processed,
+                held: held.clone(),
+            };
+            let submit_tx = match &self.file_drop {
+                Some(settings) => file_drop::spawn(settings, &self.deidentification, &spec.tenant_id, claim_tx.clone(), capacity, &mut tasks)?,
+                None => claim_tx.clone(),
+            };
+            let biller = biller::run_biller_with_hooks(
+                self.config.clone(),
+                claims_rx,
+                submit_tx,
+                spec.notify_tx,
+                spec.total_claims.unwrap_or(default_total),
+                shutdown_tx.clone(),
+                hooks,
+            );
+            let tenant_id = spec.tenant_id;
+            let biller = async move {
+                if let Err(e) = biller.await {
+                    eprintln!("Biller {} failed: {:?}", tenant_id, e);
+                }
+            };
+            tasks.push(spawn_once(self.supervisor.as_ref(), &biller_heartbeat, biller));
+        }
+
+        Ok(Simulation {
+            claim_tx,
+            #[cfg(feature = "chaos")]
+            chaos_stats: channels.chaos.as_ref().map(ChaosLayer::stats),
+            history,
+            queue_stats,
+            edit_stats,
+            inventory,
+            ledgers,
+            held_remittances,
+            controls,
+            portals,
+            clock,
+            remittance_files,
+            shutdown_rx,
+            billers_remaining: billers,
+            tasks,
+        })
+    }
+}
+
+/// Creates the channels between the clearinghouse and payers
+struct Channels {
+    capacity: usize,
+    /// What claim inboxes do when full
+    overflow_policy: OverflowPolicy,
+    queue_stats: QueueStats,
+    #[cfg(feature = "chaos")]
+    chaos: Option<ChaosLayer>,
+}
+
+impl Channels {
+    fn channel<T: Clone + Send + 'static>(&self) -> (mpsc::Sender<T>, mpsc::Receiver<T>) {
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = &self.chaos {
+            return chaos.channel(self.capacity);
+        }
+        mpsc::channel(self.capacity)
+    }
+
+    /// Claim inbox applying the overflow policy, answering claims it turns away through `context`
+    fn inbox<T: Overflow>(&self, context: T::Context) -> (mpsc::Sender<T>, mpsc::Receiver<T>) {
+        queue::bounded(self.capacity, self.overflow_policy, self.queue_stats.clone(), context)
+    }
+
+    /// Payer inbox applying the overflow policy, rejecting turned-away claims through `remit_tx`
+    ///
+    /// With chaos, messages pass through the chaos relay before they reach the queue
+    fn payer_inbox(&self, remit_tx: mpsc::Sender<RemittanceMessage>) -> (mpsc::Sender<PayerMessage>, mpsc::Receiver<PayerMessage>) {
+        let (tx, rx) = self.inbox(remit_tx);
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = &self.chaos {
+            let (chaos_tx, mut chaos_rx) = chaos.channel(self.capacity);
+            tokio::spawn(async move {
+                while let Some(msg) = chaos_rx.recv().await {
+                    if tx.send(msg).await.is_err() {
+                        break;
+                    }
+                }
+            });
+            return (chaos_tx, rx);
+        }
+        (tx, rx)
+    }
+}
+
+/// Spawn a component that cannot be rebuilt, under `supervisor` if there is one
+fn spawn_once<F>(supervisor: Option<&Supervisor>, heartbeat: &Heartbeat, task: F) -> JoinHandle<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    match supervisor {
+        Some(supervisor) => supervisor.supervise_once(heartbeat, task),
+        None => tokio::spawn(task),
+    }
+}
+
+/// Start streaming `source` into `tx`, returning its default claim count
+fn spawn_claim_source(
+    source: BoxedClaimSource,
+    tx: mpsc::Sender<PayerClaim>,
+    verbose: bool,
+    tasks: &mut Vec<JoinHandle<()>>,
+) -> usize {
+    let total = source.expected_claims().unwrap_or(0);
+    tasks.push(tokio::spawn(async move {
+        if let Err(e) = source.stream(tx, verbose).await {
+            eprintln!("Claim stream failed: {:?}", e);
+        }
+    }));
+    total
+}
+
+/// Point-in-time claim counts from the shared history
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SimulationMetrics {
+    pub pending: usize,
+    pub remitted: usize,
+    /// Claims their biller cancelled before they were remitted
+    pub voided: usize,
+    /// Claims discarded by a full clearinghouse or payer inbox under `OverflowPolicy::DropOldest`
+    pub dropped: u64,
+    /// Claims turned away by a full clearinghouse or payer inbox under `OverflowPolicy::Reject`
+    pub rejected: u64,
+    /// Claims rejected back to their biller at a front-end edit
+    pub edit_rejected: u64,
+}
+
+/// A running simulation: shared history, per-tenant ledgers, component controls, and completion/shutdown
+pub struct Simulation {
+    claim_tx: mpsc::Sender<ClaimMessage>,
+    history: History,
+    queue_stats: QueueStats,
+    edit_stats: EditStats,
+    inventory: ClaimInventory,
+    ledgers: BTreeMap<String, Arc<Mutex<Ledger>>>,
+    held_remittances: BTreeMap<String, HeldRemittances>,
+    controls: Controls,
+    portals: BTreeMap<PayerId, PayerPortal>,
+    clock: SimClock,
+    remittance_files: Option<RemittanceFiles>,
+    shutdown_rx: mpsc::Receiver<()>,
+    billers_remaining: usize,
+    tasks: Vec<JoinHandle<()>>,
+    #[cfg(feature = "chaos")]
+    chaos_stats: Option<ChaosStats>,
+}
+
+impl Simulation {
+    pub fn builder() -> SimulationBuilder {
+        SimulationBuilder::default()
+    }
+
+    /// Claim status by claim id, shared with the clearinghouse
+    pub fn history(&self) -> History {
+        self.history.clone()
+    }
+
+    /// What the chaos layer has done so far, if one is configured
+    #[cfg(feature = "chaos")]
+    pub fn chaos_stats(&self) -> Option<ChaosStats> {
+        self.chaos_stats.clone()
+    }
+
+    /// Void a claim a tenant submitted, if its payer has not yet remitted it
+    ///
+    /// The tenant's biller is answered with `RemittanceMessage::Voided` instead of a remittance
+    pub async fn cancel_claim(&self, tenant_id: &str, claim_id: impl Into<ClaimId>) -> anyhow::Result<()> {
+        let claim_id = claim_id.into();
+        self.claim_tx
+            .send(ClaimMessage::Cancel { claim_id: claim_id.clone(), tenant_id: tenant_id.to_string() })
+            .await
+            .map_err(|_| anyhow!("Clearinghouse stopped, claim {} not cancelled", claim_id))
+    }
+
+    /// The clearinghouse's claim inbox, e.g. for the HTTP API to send cancellations
+    pub fn claim_inbox(&self) -> mpsc::Sender<ClaimMessage> {
+        self.claim_tx.clone()
+    }
+
+    /// Ledger of every tenant, keyed by tenant id
+    pub fn ledgers(&self) -> &BTreeMap<String, Arc<Mutex<Ledger>>> {
+        &self.ledgers
+    }
+
+    pub fn ledger(&self, tenant_id: &str) -> Option<Arc<Mutex<Ledger>>> {
+        self.ledgers.get(tenant_id).cloned()
+    }
+
+    /// Remittances each tenant's biller holds for review, keyed by tenant id
+    pub fn held_remittances(&self) -> &BTreeMap<String, HeldRemittances> {
+        &self.held_remittances
+    }
+
+    /// Pause/resume/stop handles for the clearinghouse, each payer, and each biller
+    pub fn controls(&self) -> &Controls {
+        &self.controls
+    }
+
+    /// Simulated calendar the payers date claims by
+    pub fn clock(&self) -> SimClock {
+        self.clock
+    }
+
+    /// Each payer's own claim store, keyed by payer id, for status inquiries
+    pub fn portals(&self) -> &BTreeMap<PayerId, PayerPortal> {
+        &self.portals
+    }
+
+    /// The files remittances are written to, if the run writes them
+    pub fn remittance_files(&self) -> Option<&RemittanceFiles> {
+        self.remittance_files.as_ref()
+    }
+
+    /// Claims the clearinghouse rejected at each level of front-end edits
+    pub fn edit_stats(&self) -> &EditStats {
+        &self.edit_stats
+    }
+
+    /// Claims in each lifecycle state, from scrubbing through paid or appealed
+    pub fn inventory(&self) -> &ClaimInventory {
+        &self.inventory
+    }
+
+    /// Count pending, remitted, overflowed, and edit-rejected claims
+    pub async fn metrics(&self) -> SimulationMetrics {
+        let mut metrics = SimulationMetrics {
+            dropped: self.queue_stats.dropped(),
+            rejected: self.queue_stats.rejected(),
+            edit_rejected: self.edit_stats.total(),
+            ..SimulationMetrics::default()
+        };
+        self.history
+            .for_each(|_, status| match status {
+                ClaimStatus::Submitted { .. } => metrics.pending += 1,
+                ClaimStatus::Remitted(_) => metrics.remitted += 1,
+                ClaimStatus::Voided { .. } => metrics.voided += 1,
+            })
+            .await;
+        metrics
+    }
+
+    /// Wait until every biller has received all its remittances
+    ///
+    /// Cancel-safe, so it can be raced against other shutdown signals
+    pub async fn wait(&mut self) {
+        while self.billers_remaining > 0 {
+            if self.shutdown_rx.recv().await.is_none() {
+                break;
+            }
+            self.billers_remaining -= 1;
+        }
+    }
+
+    /// Stop every task the simulation spawned
+    pub fn shutdown(self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::ClaimKey;
+    use crate::inventory::ClaimState;
+    use crate::message::Stage;
+    use crate::payer::PayerClaimStatus;
+    use crate::test_utils::mock_claim;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    /// Test that a built simulation runs claims end to end and reports completion.
+    /// Expected: wait() returns once the claim is remitted with every hop traced; metrics, inventory, and ledger reflect it.
+    #[tokio::test]
+    async fn test_builder_runs_claims() {
+        let mut sim = Simulation::builder()
+            .payer(PayerSpec::new("medicare", 1, 2))
+            .biller(BillerSpec::new("acme"))
+            .claim_source(vec![mock_claim()])
+            .build()
+            .unwrap();
+
+        timeout(Duration::from_secs(5), sim.wait()).await.expect("Timeout waiting for simulation");
+        match sim.history().get(&ClaimKey::new("acme", mock_claim().claim_id)).await {
+            Some(ClaimStatus::Remitted(record)) => {
+                let stages: Vec<_> = record.metadata().unwrap().hops.iter().map(|hop| hop.stage).collect();
+                assert_eq!(stages, vec![Stage::Ingested, Stage::Submitted, Stage::Received, Stage::Routed, Stage::Adjudicated, Stage::Remitted]);
+            }
+            other => panic!("Expected a remitted claim, got {:?}", other),
+        }
+        assert_eq!(sim.metrics().await, SimulationMetrics { pending: 0, remitted: 1, ..SimulationMetrics::default() });
+        assert_eq!(sim.inventory().state(&ClaimKey::new("acme", mock_claim().claim_id)), Some(ClaimState::Paid));
+        assert!(!sim.ledger("acme").unwrap().lock().await.entries().is_empty());
+        sim.shutdown();
+    }
+
+    /// Test that claims two billers submit under the same id are kept apart end to end.
+    /// Expected: Both are remitted under their own tenant and posted to their own ledger; the payer holds one under
+    /// the plain id and the other under the id the clearinghouse made for its tenant.
+    #[tokio::test]
+    async fn test_same_claim_id_from_two_billers() {
+        let mut sim = Simulation::builder()
+            .payer(PayerSpec::new("medicare", 0, 0))
+            .biller(BillerSpec::new("acme").with_source(vec![mock_claim()]))
+            .biller(BillerSpec::new("beta").with_source(vec![mock_claim()]))
+            .build()
+            .unwrap();
+        timeout(Duration::from_secs(5), sim.wait()).await.expect("Timeout waiting for simulation");
+        let claim_id = mock_claim().claim_id;
+        for tenant_id in ["acme", "beta"] {
+            let status = sim.history().get(&ClaimKey::new(tenant_id, claim_id.clone())).await;
+            assert!(matches!(status, Some(ClaimStatus::Remitted(_))), "{} claim not remitted", tenant_id);
+            assert!(!sim.ledger(tenant_id).unwrap().lock().await.entries().is_empty());
+        }
+        assert_eq!(sim.metrics().await.remitted, 2);
+        let portal = sim.portals().get("medicare").unwrap();
+        // which tenant keeps the plain id depends on which claim reaches the clearinghouse first
+        let surrogates = ["abc123~acme", "abc123~beta"].into_iter().filter(|id| portal.claim_status(id).is_some()).count();
+        assert!(portal.claim_status(&claim_id).is_some());
+        assert_eq!(surrogates, 1);
+        sim.shutdown();
+    }
+
+    /// Test that a biller can cancel a claim while its payer is still adjudicating it.
+    /// Expected: Only the owning tenant's cancel takes effect; the claim is voided in history and at the payer, leaves the
+    /// inventory, and wait() returns.
+    #[tokio::test]
+    async fn test_cancel_claim() {
+        let mut sim = Simulation::builder()
+            .payer(PayerSpec::new("medicare", 5, 5))
+            .biller(BillerSpec::new("acme"))
+            .claim_source(vec![mock_claim()])
+            .build()
+            .unwrap();
+        let claim_id = mock_claim().claim_id;
+        let portal = sim.portals().get("medicare").unwrap().clone();
+        timeout(Duration::from_secs(5), async {
+            while portal.claim_status(&claim_id).is_none() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("payer never received the claim");
+
+        sim.cancel_claim("someone-else", claim_id.clone()).await.unwrap();
+        sim.cancel_claim("acme", claim_id.clone()).await.unwrap();
+        timeout(Duration::from_secs(2), sim.wait()).await.expect("biller should be answered without waiting for the payer");
+        assert!(matches!(sim.history().get(&ClaimKey::new("acme", claim_id.clone())).await, Some(ClaimStatus::Voided { .. })));
+        assert_eq!(portal.claim_status(&claim_id), Some(PayerClaimStatus::Voided { reversed_payment: 0.0 }));
+        assert_eq!(sim.metrics().await.voided, 1);
+        assert!(sim.inventory().counts().iter().all(|row| row.claims == 0));
+        sim.shutdown();
+    }
+
+    /// Test the attachment round trip: payer request, biller reply through the clearinghouse, then adjudication.
+    /// Expected: wait() returns with the claim remitted and paid rather than denied for missing documentation.
+    #[tokio::test]
+    async fn test_attachment_round_trip() {
+        let rules = serde_json::from_str(r#"[{"procedures": "99213", "document_type": "09"}]"#).unwrap();
+        let mut sim = Simulation::builder()
+            .payer(PayerSpec::new("medicare", 0, 0).with_attachments(Arc::new(AttachmentPolicy::new(rules).unwrap())))
+            .biller(BillerSpec::new("acme"))
+            .claim_source(vec![mock_claim()])
+            .build()
+            .unwrap();
+        timeout(Duration::from_secs(5), sim.wait()).await.expect("biller should answer the attachment request");
+        match sim.history().get(&ClaimKey::new("acme", mock_claim().claim_id)).await {
+            Some(ClaimStatus::Remitted(record)) => assert!(!record.remittance().is_denied()),
+            other => panic!("Expected a remitted claim, got {:?}", other),
+        }
+        sim.shutdown();
+    }
+
+    /// Test that incomplete topologies are rejected.
+    /// Expected: Missing payers, a biller without a source, and duplicate tenants are errors.
+    #[tokio::test]
+    async fn test_builder_validation() {
+        assert!(Simulation::builder().claim_source(Vec::<PayerClaim>::new()).build().is_err());
+        assert!(Simulation::builder().payer(PayerSpec::new("medicare", 1, 2)).biller(BillerSpec::default()).build().is_err());
+        let duplicate = Simulation::builder()
+            .payer(PayerSpec::new("medicare", 1, 2))
+            .biller(BillerSpec::new("a").with_source(Vec::<PayerClaim>::new()))
+            .biller(BillerSpec::new("a").with_source(Vec::<PayerClaim>::new()));
+        assert!(duplicate.build().is_err());
+    }
+}
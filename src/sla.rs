@@ -0,0 +1,84 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use crate::clock::SimClock;
+use crate::ids::PayerId;
+use crate::report_engine::{ReportEngine, SlaCompliance};
+
+/// A payer's service level: the share of claims it must adjudicate within a number of simulated days
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PayerSla {
+    /// Fraction of claims, between 0 and 1
+    pub target: f64,
+    pub within_days: f64,
+}
+
+impl FromStr for PayerSla {
+    type Err = anyhow::Error;
+
+    /// Parse `<percent>@<days>`, e.g. `95@14` for 95% of claims within 14 days
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || anyhow::anyhow!("Invalid SLA: {} (expected <percent>@<days>, e.g. 95@14)", s);
+        let (percent, days) = s.split_once('@').ok_or_else(invalid)?;
+        let percent: f64 = percent.trim().trim_end_matches('%').parse().map_err(|_| invalid())?;
+        let within_days: f64 = days.trim().parse().map_err(|_| invalid())?;
+        let in_range = percent > 0.0 && percent <= 100.0 && within_days > 0.0 && within_days.is_finite();
+        if !in_range {
+            return Err(invalid());
+        }
+        Ok(PayerSla { target: percent / 100.0, within_days })
+    }
+}
+
+impl fmt::Display for PayerSla {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.target * 100.0, self.within_days)
+    }
+}
+
+/// Tracks each payer's compliance with its SLA, alerting when it falls below target and when it recovers
+///
+/// Breach state outlives the reporter, so a restarted reporter does not alert again
+#[derive(Debug, Clone, Default)]
+pub struct SlaMonitor {
+    slas: BTreeMap<PayerId, PayerSla>,
+    breached: Arc<Mutex<BTreeSet<PayerId>>>,
+}
+
+impl SlaMonitor {
+    pub fn new(slas: BTreeMap<PayerId, PayerSla>) -> Self {
+        Self { slas, breached: Arc::default() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slas.is_empty()
+    }
+
+    /// Compliance of every payer with an SLA, alerting on payers that crossed their target since the last check
+    pub fn check(&self, engine: &ReportEngine, clock: &SimClock) -> Vec<SlaCompliance> {
+        let compliance = engine.sla_compliance(&self.slas, clock);
+        let mut breached = self.breached.lock().unwrap();
+        for row in &compliance {
+            match (row.breached, breached.contains(&row.payer_id)) {
+                (true, false) => {
+                    eprintln!(
+                        "SLA breach: payer {} adjudicated {:.1}% of claims within {} days, below its {:.1}% target",
+                        row.payer_id,
+                        row.compliance.unwrap_or_default() * 100.0,
+                        row.within_days,
+                        row.target * 100.0
+                    );
+                    breached.insert(row.payer_id.clone());
+                }
+                (false, true) => {
+                    eprintln!("SLA restored: payer {} is back at its {:.1}% target", row.payer_id, row.target * 100.0);
+                    breached.remove(&row.payer_id);
+                }
+                _ => {}
+            }
+        }
+        compliance
+    }
+}
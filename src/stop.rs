@@ -0,0 +1,214 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::clock::SimClock;
+use crate::history::History;
+use crate::message::ClaimStatus;
+
+/// Spread of remittances per check, as a fraction of their mean, within which a run is steady
+pub const DEFAULT_STEADY_TOLERANCE: f64 = 0.1;
+
+/// Consecutive checks whose remittance counts must agree before a run is steady
+pub const STEADY_CHECKS: usize = 5;
+
+/// When a run ends on its own, besides every biller's claims being remitted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopCondition {
+    /// Real time since the run started
+    WallClock(Duration),
+    /// Simulated days since the run started
+    SimDays(f64),
+    /// Billed charges of the claims remitted so far, in dollars
+    Adjudicated(f64),
+    /// Claims remitted per check stay within this fraction of their mean over `STEADY_CHECKS` checks
+    SteadyState(f64),
+}
+
+impl FromStr for StopCondition {
+    type Err = anyhow::Error;
+
+    /// `wall=<secs>`, `days=<days>`, `adjudicated=<dollars>`, or `steady[=<tolerance>]`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = s.trim().split_once('=').unwrap_or((s.trim(), ""));
+        let number = || {
+            value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite() && *value > 0.0)
+                .ok_or_else(|| anyhow::anyhow!("Stop condition {} needs a positive number, not {:?}", kind, value))
+        };
+        match kind.trim() {
+            "wall" => Ok(StopCondition::WallClock(Duration::from_secs_f64(number()?))),
+            "days" => Ok(StopCondition::SimDays(number()?)),
+            "adjudicated" => Ok(StopCondition::Adjudicated(number()?)),
+            "steady" if value.is_empty() => Ok(StopCondition::SteadyState(DEFAULT_STEADY_TOLERANCE)),
+            "steady" => Ok(StopCondition::SteadyState(number()?)),
+            _ => Err(anyhow::anyhow!("Unknown stop condition: {} (expected wall, days, adjudicated, or steady)", s)),
+        }
+    }
+}
+
+impl fmt::Display for StopCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopCondition::WallClock(after) => write!(f, "wall={}", after.as_secs_f64()),
+            StopCondition::SimDays(days) => write!(f, "days={}", days),
+            StopCondition::Adjudicated(dollars) => write!(f, "adjudicated={}", dollars),
+            StopCondition::SteadyState(tolerance) => write!(f, "steady={}", tolerance),
+        }
+    }
+}
+
+/// The condition that ended a run and where the run stood when it did
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stopped {
+    pub condition: StopCondition,
+    pub elapsed: Duration,
+    pub remitted: usize,
+    /// Billed charges of the claims remitted
+    pub adjudicated: f64,
+}
+
+impl fmt::Display for Stopped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let met = match self.condition {
+            StopCondition::WallClock(_) => "run time reached".to_string(),
+            StopCondition::SimDays(days) => format!("{} simulated days passed", days),
+            StopCondition::Adjudicated(_) => "adjudicated charges reached".to_string(),
+            StopCondition::SteadyState(_) => format!("remittances steady over {} checks", STEADY_CHECKS),
+        };
+        write!(
+            f,
+            "{} ({}) after {:.1}s, {} claims remitted for ${:.2}",
+            met,
+            self.condition,
+            self.elapsed.as_secs_f64(),
+            self.remitted,
+            self.adjudicated
+        )
+    }
+}
+
+/// Watches a run for any of its stop conditions
+///
+/// The first condition met ends the run; with none configured it never does
+pub struct StopMonitor {
+    conditions: Vec<StopCondition>,
+    history: History,
+    clock: SimClock,
+    started: Instant,
+    remitted: usize,
+    /// Claims remitted between each of the last `STEADY_CHECKS` checks
+    recent: VecDeque<usize>,
+}
+
+impl StopMonitor {
+    /// Watch `history` from now on, counting simulated days on `clock`
+    pub fn new(conditions: Vec<StopCondition>, history: History, clock: SimClock) -> Self {
+        Self {
+            conditions,
+            history,
+            clock,
+            started: Instant::now(),
+            remitted: 0,
+            recent: VecDeque::with_capacity(STEADY_CHECKS),
+        }
+    }
+
+    /// Check the conditions every `interval`, returning once one is met
+    pub async fn run(mut self, interval: Duration) -> Stopped {
+        if self.conditions.is_empty() {
+            return std::future::pending().await;
+        }
+        loop {
+            tokio::time::sleep(interval).await;
+            if let Some(stopped) = self.check().await {
+                return stopped;
+            }
+        }
+    }
+
+    /// Check the conditions once, in the order given, returning the first one met
+    pub async fn check(&mut self) -> Option<Stopped> {
+        let (mut remitted, mut adjudicated) = (0usize, 0.0);
+        self.history
+            .for_each(|_, status| {
+                if let ClaimStatus::Remitted(record) = status {
+                    remitted += 1;
+                    adjudicated += record.claim().total_charge();
+                }
+            })
+            .await;
+        if self.recent.len() == STEADY_CHECKS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(remitted.saturating_sub(self.remitted));
+        self.remitted = remitted;
+
+        let elapsed = self.started.elapsed();
+        let condition = self.conditions.iter().copied().find(|condition| match *condition {
+            StopCondition::WallClock(after) => elapsed >= after,
+            StopCondition::SimDays(days) => self.clock.days(elapsed) >= days,
+            StopCondition::Adjudicated(dollars) => adjudicated >= dollars,
+            StopCondition::SteadyState(tolerance) => self.is_steady(tolerance),
+        })?;
+        Some(Stopped { condition, elapsed, remitted, adjudicated })
+    }
+
+    /// Claims were remitted at every one of the last `STEADY_CHECKS` checks, each count within
+    /// `tolerance` of their mean
+    fn is_steady(&self, tolerance: f64) -> bool {
+        if self.recent.len() < STEADY_CHECKS || self.recent.contains(&0) {
+            return false;
+        }
+        let mean = self.recent.iter().sum::<usize>() as f64 / self.recent.len() as f64;
+        self.recent.iter().all(|count| (*count as f64 - mean).abs() <= tolerance * mean)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remittance::{Remittance, RemittanceRecord};
+    use crate::schema::PayerClaim;
+    use crate::test_utils::{mock_claim, mock_adjudication_date};
+    use std::sync::Arc;
+
+    async fn remit(history: &History, claim_id: &str) {
+        let claim = Arc::new(PayerClaim { claim_id: claim_id.into(), ..mock_claim() });
+        let remittance = Remittance::from_claim(&claim, mock_adjudication_date());
+        let status = ClaimStatus::Remitted(RemittanceRecord::new(claim, remittance, Instant::now(), Instant::now()));
+        history.insert(status.key(), status).await;
+    }
+
+    /// Test that stop conditions parse and end a run once adjudicated charges or a steady remittance rate is reached.
+    /// Expected: Each form round-trips and bad values are rejected; the adjudicated limit stops after the second claim,
+    /// and a steady monitor stops only once `STEADY_CHECKS` checks in a row remitted one claim each.
+    #[tokio::test]
+    async fn test_stop_conditions() {
+        for spec in ["wall=90", "days=30", "adjudicated=5000", "steady=0.2"] {
+            assert_eq!(spec.parse::<StopCondition>().unwrap().to_string(), spec);
+        }
+        assert_eq!("steady".parse::<StopCondition>().unwrap(), StopCondition::SteadyState(DEFAULT_STEADY_TOLERANCE));
+        assert!("wall=-1".parse::<StopCondition>().is_err() && "claims=10".parse::<StopCondition>().is_err());
+
+        let history = History::new(2);
+        let charge = mock_claim().total_charge();
+        let mut adjudicated = StopMonitor::new(vec![StopCondition::Adjudicated(charge * 1.5)], history.clone(), SimClock::default());
+        let mut steady = StopMonitor::new(vec![StopCondition::SteadyState(0.1)], history.clone(), SimClock::default());
+        for n in 0..STEADY_CHECKS {
+            remit(&history, &format!("clm-{}", n)).await;
+            let stopped = adjudicated.check().await;
+            assert_eq!(stopped.is_some(), n >= 1, "{}", n);
+            let stopped = steady.check().await;
+            assert_eq!(stopped.is_some(), n + 1 == STEADY_CHECKS, "{}", n);
+        }
+        assert_eq!(steady.check().await, None, "a check with nothing remitted breaks the steady rate");
+
+        let mut timed = StopMonitor::new(vec![StopCondition::WallClock(Duration::ZERO)], history, SimClock::default());
+        assert_eq!(timed.check().await.map(|stopped| stopped.remitted), Some(STEADY_CHECKS));
+    }
+}
@@ -0,0 +1,375 @@
+use serde::Serialize;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{Sender, WeakSender};
+use tokio::task::{AbortHandle, JoinHandle};
+
+use crate::control::{ComponentHandle, RunState};
+
+/// How long a component may go without progress while work waits for it, and how often it is restarted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SupervisorSettings {
+    /// `None` never treats a component as stalled
+    pub stall_after: Option<Duration>,
+    /// Restarts allowed per component before it is left failed
+    pub max_restarts: u32,
+}
+
+impl Default for SupervisorSettings {
+    fn default() -> Self {
+        Self {
+            stall_after: Some(Duration::from_secs(60)),
+            max_restarts: 3,
+        }
+    }
+}
+
+/// Whether a supervised component is doing its job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Health {
+    Running,
+    /// Made no progress for longer than the stall limit while work waited for it
+    Stalled,
+    /// Panicked or stalled and could not be restarted
+    Failed,
+    /// Returned on its own, its input exhausted or stopped
+    Finished,
+}
+
+impl fmt::Display for Health {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Health::Running => write!(f, "running"),
+            Health::Stalled => write!(f, "stalled"),
+            Health::Failed => write!(f, "failed"),
+            Health::Finished => write!(f, "finished"),
+        }
+    }
+}
+
+/// Health of one component, as reported by the HTTP API
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComponentHealth {
+    pub component: &'static str,
+    pub id: String,
+    pub health: Health,
+    pub restarts: u32,
+    /// Seconds since the component last made progress
+    pub idle_secs: f64,
+    /// Why it was last restarted or failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+type DepthProbe = Box<dyn Fn() -> usize + Send + Sync>;
+type HoldProbe = Box<dyn Fn() -> bool + Send + Sync>;
+
+#[derive(Debug)]
+struct AttemptState {
+    health: Health,
+    restarts: u32,
+    last_error: Option<String>,
+    /// The task now running the component
+    task: Option<AbortHandle>,
+    /// Whether a stalled task may be aborted and started again
+    restartable: bool,
+    /// Set when the monitor aborts a stalled task, so its cancellation is not taken as a shutdown
+    stall_aborted: bool,
+}
+
+struct Component {
+    component: &'static str,
+    id: String,
+    epoch: Instant,
+    /// Milliseconds after `epoch` of the last progress
+    last_progress: AtomicU64,
+    state: Mutex<AttemptState>,
+    inboxes: Mutex<Vec<DepthProbe>>,
+    hold: Mutex<Option<HoldProbe>>,
+}
+
+/// A component's progress and inputs, as its supervisor sees them
+///
+/// Components call `beat` whenever they finish a piece of work. One that is not registered
+/// with a supervisor beats into a heartbeat nobody watches
+#[derive(Clone)]
+pub struct Heartbeat {
+    inner: Arc<Component>,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self::new("", String::new())
+    }
+}
+
+impl Heartbeat {
+    fn new(component: &'static str, id: String) -> Self {
+        Self {
+            inner: Arc::new(Component {
+                component,
+                id,
+                epoch: Instant::now(),
+                last_progress: AtomicU64::new(0),
+                state: Mutex::new(AttemptState {
+                    health: Health::Running,
+                    restarts: 0,
+                    last_error: None,
+                    task: None,
+                    restartable: false,
+                    stall_aborted: false,
+                }),
+                inboxes: Mutex::new(Vec::new()),
+                hold: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Record that the component made progress
+    pub fn beat(&self) {
+        self.inner.last_progress.store(self.inner.epoch.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Count messages waiting on `tx`'s channel as work for this component, without keeping it open
+    ///
+    /// A component with no inbox is expected to make progress on its own, so any idle stretch
+    /// past the stall limit is a stall
+    pub fn watch_inbox<T: Send + 'static>(&self, tx: &Sender<T>) {
+        let weak: WeakSender<T> = tx.downgrade();
+        let probe: DepthProbe = Box::new(move || weak.upgrade().map_or(0, |tx| tx.max_capacity() - tx.capacity()));
+        self.inner.inboxes.lock().unwrap().push(probe);
+    }
+
+    /// Never treat the component as stalled while `handle` has it paused or stopped
+    pub fn watch_control<S: Clone + Send + Sync + 'static>(&self, handle: &ComponentHandle<S>) {
+        let handle = handle.clone();
+        *self.inner.hold.lock().unwrap() = Some(Box::new(move || handle.state() != RunState::Running));
+    }
+
+    fn idle(&self) -> Duration {
+        let last = Duration::from_millis(self.inner.last_progress.load(Ordering::Relaxed));
+        self.inner.epoch.elapsed().saturating_sub(last)
+    }
+
+    fn has_work(&self) -> bool {
+        let inboxes = self.inner.inboxes.lock().unwrap();
+        inboxes.is_empty() || inboxes.iter().any(|queued| queued() > 0)
+    }
+
+    fn held(&self) -> bool {
+        self.inner.hold.lock().unwrap().as_ref().is_some_and(|held| held())
+    }
+
+    pub fn health(&self) -> ComponentHealth {
+        let state = self.inner.state.lock().unwrap();
+        ComponentHealth {
+            component: self.inner.component,
+            id: self.inner.id.clone(),
+            health: state.health,
+            restarts: state.restarts,
+            idle_secs: self.idle().as_secs_f64(),
+            last_error: state.last_error.clone(),
+        }
+    }
+}
+
+/// Runs components as tasks, restarting those that panic or stall, and tracks their health
+///
+/// A restartable component is started afresh from its shared state: whatever it kept only in
+/// its own task is lost
+#[derive(Clone, Default)]
+pub struct Supervisor {
+    settings: SupervisorSettings,
+    components: Arc<Mutex<Vec<Heartbeat>>>,
+}
+
+impl Supervisor {
+    pub fn new(settings: SupervisorSettings) -> Self {
+        Self {
+            settings,
+            components: Arc::default(),
+        }
+    }
+
+    /// Track a component, returning the heartbeat it reports its progress on
+    pub fn register(&self, component: &'static str, id: impl Into<String>) -> Heartbeat {
+        let heartbeat = Heartbeat::new(component, id.into());
+        self.components.lock().unwrap().push(heartbeat.clone());
+        heartbeat
+    }
+
+    /// Health of every registered component, in registration order
+    pub fn health(&self) -> Vec<ComponentHealth> {
+        self.components.lock().unwrap().iter().map(Heartbeat::health).collect()
+    }
+
+    /// Run the component `start` returns, starting it again whenever it panics or stalls
+    pub fn supervise<F, Fut>(&self, heartbeat: &Heartbeat, start: F) -> JoinHandle<()>
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        tokio::spawn(self.clone().run_attempts(heartbeat.clone(), true, start))
+    }
+
+    /// Run a component that cannot be rebuilt, marking it failed if it panics
+    pub fn supervise_once<Fut>(&self, heartbeat: &Heartbeat, task: Fut) -> JoinHandle<()>
+    where
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut task = Some(task);
+        let start = move || task.take().expect("a component that cannot be rebuilt is started once");
+        tokio::spawn(self.clone().run_attempts(heartbeat.clone(), false, start))
+    }
+
+    async fn run_attempts<F, Fut>(self, heartbeat: Heartbeat, restartable: bool, mut start: F)
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let component = &heartbeat.inner;
+        loop {
+            heartbeat.beat();
+            let task = tokio::spawn(start());
+            // aborting the supervision aborts the component with it
+            let _guard = AbortOnDrop(task.abort_handle());
+            {
+                let mut state = component.state.lock().unwrap();
+                state.task = Some(task.abort_handle());
+                state.restartable = restartable;
+                state.health = Health::Running;
+            }
+            let result = task.await;
+            let mut state = component.state.lock().unwrap();
+            state.task = None;
+            let reason = match result {
+                Ok(()) => None,
+                Err(e) if e.is_panic() => Some(format!("panicked: {}", panic_message(e.into_panic()))),
+                Err(_) if std::mem::take(&mut state.stall_aborted) => Some("stalled".to_string()),
+                Err(_) => None,
+            };
+            let Some(reason) = reason else {
+                state.health = Health::Finished;
+                return;
+            };
+            state.last_error = Some(reason.clone());
+            if !restartable || state.restarts >= self.settings.max_restarts {
+                eprintln!("Component {} {} failed: {}", component.component, component.id, reason);
+                state.health = Health::Failed;
+                return;
+            }
+            state.restarts += 1;
+            eprintln!(
+                "Restarting component {} {} ({}), restart {} of {}",
+                component.component, component.id, reason, state.restarts, self.settings.max_restarts
+            );
+        }
+    }
+
+    /// Check every component for stalls until the task is dropped, restarting those that can be
+    pub async fn monitor(self) {
+        let Some(stall_after) = self.settings.stall_after else {
+            return;
+        };
+        let mut interval = tokio::time::interval((stall_after / 4).min(Duration::from_secs(1)));
+        loop {
+            interval.tick().await;
+            let components = self.components.lock().unwrap().clone();
+            for heartbeat in components {
+                let stalled = heartbeat.idle() >= stall_after && !heartbeat.held() && heartbeat.has_work();
+                let component = &heartbeat.inner;
+                let mut state = component.state.lock().unwrap();
+                match (state.health, stalled) {
+                    (Health::Running, true) => {
+                        eprintln!(
+                            "Component {} {} stalled: no progress in {:.0}s",
+                            component.component,
+                            component.id,
+                            heartbeat.idle().as_secs_f64()
+                        );
+                        state.health = Health::Stalled;
+                        if let Some(task) = state.task.clone().filter(|_| state.restartable) {
+                            state.stall_aborted = true;
+                            task.abort();
+                        }
+                    }
+                    (Health::Stalled, false) => state.health = Health::Running,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+fn panic_message(panic: Box<dyn std::any::Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => panic.downcast_ref::<&str>().map_or("unknown panic".to_string(), |message| message.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use tokio::time::timeout;
+
+    /// Test that components which panic or stall are restarted, and those that cannot be are failed.
+    /// Expected: A panicking and a stalled component each finish after one restart; a panicking one-off fails; health lists all three.
+    #[tokio::test]
+    async fn test_supervise_restarts_and_fails() {
+        let supervisor = Supervisor::new(SupervisorSettings { stall_after: Some(Duration::from_millis(100)), max_restarts: 2 });
+        tokio::spawn(supervisor.clone().monitor());
+
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let panicking = supervisor.register("payer", "medicare");
+        let counter = attempts.clone();
+        let task = supervisor.supervise(&panicking, move || {
+            let attempt = counter.fetch_add(1, Ordering::Relaxed);
+            async move {
+                if attempt == 0 {
+                    panic!("boom");
+                }
+            }
+        });
+        timeout(Duration::from_secs(5), task).await.unwrap().unwrap();
+        let health = panicking.health();
+        assert_eq!((health.health, health.restarts), (Health::Finished, 1));
+        assert_eq!(health.last_error.as_deref(), Some("panicked: boom"));
+
+        let stalling = supervisor.register("reporter", "reporter");
+        let (heartbeat, stalls) = (stalling.clone(), Arc::new(AtomicUsize::new(0)));
+        let counter = stalls.clone();
+        let task = supervisor.supervise(&stalling, move || {
+            let (attempt, heartbeat) = (counter.fetch_add(1, Ordering::Relaxed), heartbeat.clone());
+            async move {
+                if attempt == 0 {
+                    std::future::pending::<()>().await;
+                }
+                heartbeat.beat();
+            }
+        });
+        timeout(Duration::from_secs(5), task).await.unwrap().unwrap();
+        let health = stalling.health();
+        assert_eq!((health.health, health.restarts), (Health::Finished, 1));
+        assert_eq!(health.last_error.as_deref(), Some("stalled"));
+
+        let once = supervisor.register("biller", "acme");
+        let task = supervisor.supervise_once(&once, async { panic!("lost routing state") });
+        timeout(Duration::from_secs(5), task).await.unwrap().unwrap();
+        assert_eq!((once.health().health, once.health().restarts), (Health::Failed, 0));
+        assert_eq!(supervisor.health().len(), 3);
+    }
+}
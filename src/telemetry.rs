@@ -0,0 +1,57 @@
+/// Keeps span export running; flushes pending spans when dropped
+#[derive(Default)]
+pub struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+/// Export claim lifecycle spans to an OTLP collector (e.g. Jaeger on port 4317)
+///
+/// With no endpoint, no subscriber is installed and spans cost next to nothing.
+/// Span filtering follows `RUST_LOG` (default: `healthtechsim=info`).
+#[cfg(feature = "otel")]
+pub fn init(otlp_endpoint: Option<&str>) -> anyhow::Result<TelemetryGuard> {
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_otlp::WithExportConfig;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    let Some(endpoint) = otlp_endpoint else {
+        return Ok(TelemetryGuard::default());
+    };
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(opentelemetry_sdk::Resource::builder().with_service_name("healthtechsim").build())
+        .build();
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("healthtechsim=info"));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("healthtechsim")))
+        .try_init()?;
+    Ok(TelemetryGuard { provider: Some(provider) })
+}
+
+/// Export claim lifecycle spans to an OTLP collector; requires the `otel` feature
+#[cfg(not(feature = "otel"))]
+pub fn init(otlp_endpoint: Option<&str>) -> anyhow::Result<TelemetryGuard> {
+    match otlp_endpoint {
+        Some(_) => Err(anyhow::anyhow!("Span export requires building with `--features otel`")),
+        None => Ok(TelemetryGuard::default()),
+    }
+}
+
+#[cfg(feature = "otel")]
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take()
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("Failed to flush spans: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::path::Path;
+
+/// Tenant id used when the simulation runs a single billing organization
+pub const DEFAULT_TENANT: &str = "default";
+
+/// One billing organization with its own claim stream
+#[derive(Debug, Clone, PartialEq)]
+pub struct TenantConfig {
+    pub tenant_id: String,
+    pub file_path: String,
+}
+
+/// Parse a comma-separated tenant list, e.g. `acme=acme.jsonl,beta=beta.jsonl`
+///
+/// An entry without `name=` uses the file stem as the tenant id
+pub fn parse_tenants(spec: &str) -> Vec<TenantConfig> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((tenant_id, file_path)) => TenantConfig {
+                tenant_id: tenant_id.to_string(),
+                file_path: file_path.to_string(),
+            },
+            None => TenantConfig {
+                tenant_id: file_stem(entry),
+                file_path: entry.to_string(),
+            },
+        })
+        .collect()
+}
+
+/// Check a tenant id can name files, claim id prefixes, and API paths
+///
+/// Only ASCII letters, digits, `-`, and `_` are allowed: `/` and `~` separate the tenant from the
+/// claim id in claim keys and payer-facing ids, and `.` would blur scoped file suffixes
+pub fn check_tenant_id(tenant_id: &str) -> anyhow::Result<()> {
+    if tenant_id.is_empty() {
+        return Err(anyhow::anyhow!("tenant id must not be empty"));
+    }
+    match tenant_id.chars().find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_')) {
+        Some(c) => Err(anyhow::anyhow!("tenant id {} may only use letters, digits, '-', and '_', not {:?}", tenant_id, c)),
+        None => Ok(()),
+    }
+}
+
+/// Per-tenant variant of a shared path, e.g. `ckpt.json` -> `ckpt.json.acme`
+pub(crate) fn scoped_path(path: &str, tenant_id: &str) -> String {
+    format!("{}.{}", path, tenant_id)
+}
+
+fn file_stem(path: &str) -> String {
+    let name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string());
+    name.split('.').next().unwrap_or(&name).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that named and unnamed tenant entries are parsed.
+    /// Expected: Explicit names are kept; unnamed entries use the file stem.
+    #[test]
+    fn test_parse_tenants() {
+        let tenants = parse_tenants("acme=data/a.jsonl, data/beta.jsonl.gz");
+        assert_eq!(
+            tenants,
+            vec![
+                TenantConfig { tenant_id: "acme".to_string(), file_path: "data/a.jsonl".to_string() },
+                TenantConfig { tenant_id: "beta".to_string(), file_path: "data/beta.jsonl.gz".to_string() },
+            ]
+        );
+        assert!(parse_tenants("").is_empty());
+    }
+
+    /// Test that tenant ids are limited to characters safe in paths and claim keys.
+    /// Expected: Letters, digits, dashes, and underscores pass; empty ids and ids with separators fail.
+    #[test]
+    fn test_check_tenant_id() {
+        check_tenant_id("acme_east-2").unwrap();
+        for bad in ["", "acme/east", "acme~1", "a.b", "acme east"] {
+            assert!(check_tenant_id(bad).is_err(), "{:?}", bad);
+        }
+    }
+}
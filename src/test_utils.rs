@@ -0,0 +1,165 @@
+use chrono::NaiveDate;
+
+use crate::ids::ClaimId;
+use crate::place_of_service::PlaceOfService;
+pub use crate::remittance::mock_remittance;
+use crate::schema::{Address, Contact, Gender, Organization, Patient, PayerClaim, PayerClaimBuilder, Provider, ServiceLine};
+
+/// The mock claim's `PayerClaim::builder()` preset, changing only what a test is about
+///
+/// `ClaimBuilder::new().payer("anthem").lines(3).build()` is the mock claim billed to Anthem
+/// with three copies of its office visit, `sl1` to `sl3`. Anything the preset does not cover is
+/// set on the underlying builder through `with`
+#[derive(Debug, Clone)]
+pub struct ClaimBuilder {
+    claim: PayerClaimBuilder,
+    line: ServiceLine,
+    lines: usize,
+}
+
+impl Default for ClaimBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClaimBuilder {
+    pub fn new() -> Self {
+        let address = |street: &str, city: &str, state: &str, zip: &str| Address {
+            street: Some(street.to_string()),
+            city: Some(city.to_string()),
+            state: Some(state.to_string()),
+            zip: Some(zip.to_string()),
+            country: Some("USA".to_string()),
+        };
+        let claim = PayerClaim::builder()
+            .claim_id("abc123")
+            .payer("medicare")
+            .member("pmid456")
+            .patient(Patient {
+                dob: NaiveDate::from_ymd_opt(1990, 1, 1),
+                email: Some("jane.doe@example.com".to_string()),
+                address: Some(address("123 Main St", "Metropolis", "NY", "12345")),
+                ..Patient::new("Jane", "Doe", Gender::Female)
+            })
+            .organization(Organization {
+                billing_npi: Some("1987654328".into()),
+                ein: Some("12-3456789".to_string()),
+                contact: Some(Contact {
+                    first_name: Some("Bob".to_string()),
+                    last_name: Some("Jones".to_string()),
+                    phone_number: Some("555-1234".to_string()),
+                }),
+                address: Some(address("456 Health Ave", "Gotham", "CA", "67890")),
+                taxonomy_code: Some("261QP2300X".to_string()),
+                ..Organization::new("Health Inc")
+            })
+            .rendering_provider(Provider {
+                taxonomy_code: Some("207Q00000X".to_string()),
+                ..Provider::new("Alice", "Smith", "1234567893")
+            })
+            .diagnosis("J06.9");
+        let line = ServiceLine {
+            do_not_bill: Some(true),
+            ..ServiceLine::new("sl1", "99213", 1, 150.0).with_details("Office visit").with_modifiers(&["A1", "B2"])
+        };
+        Self { claim, line, lines: 1 }
+    }
+
+    /// Set anything else on the underlying `PayerClaimBuilder`
+    pub fn with(mut self, f: impl FnOnce(PayerClaimBuilder) -> PayerClaimBuilder) -> Self {
+        self.claim = f(self.claim);
+        self
+    }
+
+    pub fn claim_id(self, claim_id: impl Into<ClaimId>) -> Self {
+        self.with(|claim| claim.claim_id(claim_id))
+    }
+
+    pub fn payer(self, payer_id: &str) -> Self {
+        self.with(|claim| claim.payer(payer_id))
+    }
+
+    pub fn member(self, member_id: &str) -> Self {
+        self.with(|claim| claim.member(member_id))
+    }
+
+    /// Bill `n` service lines, each a copy of the office visit
+    pub fn lines(mut self, n: usize) -> Self {
+        self.lines = n;
+        self
+    }
+
+    /// Procedure code of every line
+    pub fn procedure(mut self, code: &str) -> Self {
+        self.line.procedure_code = code.to_string();
+        self
+    }
+
+    /// Charge per unit of every line
+    pub fn unit_charge(mut self, amount: f64) -> Self {
+        self.line.unit_charge_amount = amount;
+        self
+    }
+
+    pub fn place_of_service(self, place: PlaceOfService) -> Self {
+        self.with(|claim| claim.place_of_service(place))
+    }
+
+    pub fn service_date(self, date: NaiveDate) -> Self {
+        self.with(|claim| claim.service_date(date))
+    }
+
+    pub fn urgent(self) -> Self {
+        self.with(|claim| claim.urgent(true))
+    }
+
+    /// The claim, checked by `PayerClaimBuilder::build`; panics if the test made it invalid
+    pub fn build(self) -> PayerClaim {
+        let line = self.line;
+        (1..=self.lines)
+            .fold(self.claim, |claim, n| claim.line(ServiceLine { service_line_id: format!("sl{}", n), ..line.clone() }))
+            .build()
+            .expect("the preset claim is complete")
+    }
+}
+
+/// Mock claim for testing
+pub fn mock_claim() -> PayerClaim {
+    ClaimBuilder::new().build()
+}
+
+/// Date the mock claims are adjudicated on, a fixed simulated day so pricing does not depend on the wall clock
+pub fn mock_adjudication_date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 3, 2).expect("a valid date")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that the claim builder changes only what it is told to on the mock claim.
+    /// Expected: An Anthem claim with three $200 lines numbered sl1 to sl3 billing $600, otherwise the mock claim.
+    #[test]
+    fn test_claim_builder() {
+        let claim = ClaimBuilder::new().claim_id("clm-7").payer("anthem").lines(3).unit_charge(200.0).build();
+        assert_eq!(claim.claim_id.as_str(), "clm-7");
+        assert_eq!(claim.insurance.payer_id.as_str(), "anthem");
+        let ids: Vec<&str> = claim.service_lines.iter().map(|line| line.service_line_id.as_str()).collect();
+        assert_eq!(ids, ["sl1", "sl2", "sl3"]);
+        assert_eq!(claim.total_charge(), 600.0);
+        let json = |claim: &PayerClaim| serde_json::to_value(claim).unwrap();
+        assert_eq!(json(&claim)["patient"], json(&mock_claim())["patient"]);
+        assert_eq!(json(&ClaimBuilder::new().build()), json(&mock_claim()));
+        let claim = ClaimBuilder::new().with(|claim| claim.replaces("abc000")).build();
+        assert_eq!(claim.original_claim_id.as_ref().map(|id| id.as_str()), Some("abc000"));
+    }
+
+    /// Test that the preset is checked like any built claim.
+    /// Expected: Emptying the claim id panics rather than yielding a claim `PayerClaim::builder()` would refuse.
+    #[test]
+    #[should_panic(expected = "the preset claim is complete")]
+    fn test_claim_builder_validates() {
+        ClaimBuilder::new().claim_id("").build();
+    }
+}
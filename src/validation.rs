@@ -0,0 +1,510 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::extensions;
+use crate::rejects::LineError;
+use crate::schema::{ClaimFrequency, DEFAULT_CURRENCY, PayerClaim, Relationship};
+
+/// Luhn sum contribution of the `80840` card-issuer prefix NPIs are checked under
+const NPI_PREFIX_SUM: u32 = 24;
+
+/// EIN prefixes the IRS has never assigned
+const UNASSIGNED_EIN_PREFIXES: [&str; 17] = [
+    "00", "07", "08", "09", "17", "18", "19", "28", "29", "49", "69", "70", "78", "79", "89", "96", "97",
+];
+
+/// How the reader treats claims whose identifiers fail validation
+///
+/// Below `Strict`, unreadable patient gender and date of birth are also repaired
+/// rather than rejecting the line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// Skip identifier checks
+    Off,
+    /// Report failures and repairs and send the claim on anyway
+    #[default]
+    Warn,
+    /// Reject the claim to the rejects sidecar
+    Strict,
+}
+
+impl FromStr for Strictness {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Strictness::Off),
+            "warn" => Ok(Strictness::Warn),
+            "strict" => Ok(Strictness::Strict),
+            _ => Err(anyhow::anyhow!("Unknown validation strictness: {} (expected off, warn, or strict)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Strictness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strictness::Off => write!(f, "off"),
+            Strictness::Warn => write!(f, "warn"),
+            Strictness::Strict => write!(f, "strict"),
+        }
+    }
+}
+
+/// ISO 4217 currency codes the payers accept charges in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllowedCurrencies(Vec<String>);
+
+impl AllowedCurrencies {
+    /// Whether `currency` is accepted, in any case
+    pub fn contains(&self, currency: &str) -> bool {
+        self.0.iter().any(|allowed| allowed.eq_ignore_ascii_case(currency))
+    }
+}
+
+impl Default for AllowedCurrencies {
+    fn default() -> Self {
+        Self(vec![DEFAULT_CURRENCY.to_string()])
+    }
+}
+
+impl FromStr for AllowedCurrencies {
+    type Err = anyhow::Error;
+
+    /// Comma-separated three-letter codes, e.g. `USD,CAD`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let currencies: Vec<String> = s.split(',').map(|code| code.trim().to_ascii_uppercase()).collect();
+        if let Some(code) = currencies.iter().find(|code| !is_currency_code(code)) {
+            return Err(anyhow::anyhow!("Invalid currency code: {:?} (expected three letters, e.g. USD)", code));
+        }
+        Ok(Self(currencies))
+    }
+}
+
+impl fmt::Display for AllowedCurrencies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.join(","))
+    }
+}
+
+pub(crate) fn is_currency_code(code: &str) -> bool {
+    code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// Every service line billed in a currency outside `allowed`, or in a different
+/// currency from the claim's first line, ignoring case
+///
+/// Unlike identifier checks these always reject: the payers cannot price such a claim
+pub fn validate_currency(claim: &PayerClaim, allowed: &AllowedCurrencies) -> Vec<LineError> {
+    let claim_currency = claim.currency();
+    claim
+        .service_lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let currency = &line.unit_charge_currency;
+            let reason = if !allowed.contains(currency) {
+                format!("currency {} not accepted (allowed: {})", currency, allowed)
+            } else if !currency.eq_ignore_ascii_case(claim_currency) {
+                format!("currency {} differs from the claim's {}", currency, claim_currency)
+            } else {
+                return None;
+            };
+            Some(LineError {
+                pointer: format!("/service_lines/{}/unit_charge_currency", i),
+                reason,
+            })
+        })
+        .collect()
+}
+
+/// The claim with its currency codes upper-cased, or the first line in a currency it may not be billed in
+///
+/// Run on every claim whatever its source, since the payers and the remittance checks compare codes exactly
+pub fn check_currency(mut claim: PayerClaim, allowed: &AllowedCurrencies) -> Result<PayerClaim, LineError> {
+    if let Some(err) = validate_currency(&claim, allowed).into_iter().next() {
+        return Err(err);
+    }
+    for line in &mut claim.service_lines {
+        line.unit_charge_currency.make_ascii_uppercase();
+    }
+    Ok(claim)
+}
+
+/// Largest gap between the header total and the line charges treated as rounding
+const TOTAL_TOLERANCE: f64 = 1e-2;
+
+/// A stated header total that does not equal the sum of the line charges
+///
+/// Claims without a header total are balanced by definition. Like currency
+/// checks this always rejects: the claim contradicts itself
+pub fn validate_claim_total(claim: &PayerClaim) -> Option<LineError> {
+    let stated = claim.total_charge_amount?;
+    let lines = claim.total_charge();
+    ((stated - lines).abs() > TOTAL_TOLERANCE).then(|| LineError {
+        pointer: "/total_charge_amount".to_string(),
+        reason: format!("claim total {:.2} does not match line charges {:.2}", stated, lines),
+    })
+}
+
+/// Check digit completing the first nine digits of an NPI
+///
+/// NPIs use the Luhn algorithm over the digits prefixed with `80840`
+pub(crate) fn npi_check_digit(first_nine: &str) -> Option<u32> {
+    if first_nine.len() != 9 {
+        return None;
+    }
+    let mut sum = NPI_PREFIX_SUM;
+    // rightmost of the nine sits next to the check digit, so it is doubled
+    for (i, c) in first_nine.chars().rev().enumerate() {
+        let digit = c.to_digit(10)?;
+        sum += match i % 2 {
+            0 if digit * 2 > 9 => digit * 2 - 9,
+            0 => digit * 2,
+            _ => digit,
+        };
+    }
+    Some((10 - sum % 10) % 10)
+}
+
+/// Ten digits, starting with 1 or 2, with a valid check digit
+pub fn is_valid_npi(npi: &str) -> bool {
+    if npi.len() != 10 || !(npi.starts_with('1') || npi.starts_with('2')) {
+        return false;
+    }
+    let (first_nine, check) = npi.split_at(9);
+    match (npi_check_digit(first_nine), check.parse::<u32>()) {
+        (Some(expected), Ok(check)) => expected == check,
+        _ => false,
+    }
+}
+
+/// `NN-NNNNNNN` with a prefix the IRS assigns
+pub fn is_valid_ein(ein: &str) -> bool {
+    let Some((prefix, serial)) = ein.split_once('-') else {
+        return false;
+    };
+    prefix.len() == 2
+        && serial.len() == 7
+        && prefix.chars().chain(serial.chars()).all(|c| c.is_ascii_digit())
+        && !UNASSIGNED_EIN_PREFIXES.contains(&prefix)
+}
+
+/// NUCC taxonomy code: three digits, six digits or capital letters, and a closing `X`, e.g. `207Q00000X`
+pub fn is_valid_taxonomy(code: &str) -> bool {
+    code.len() == 10
+        && code.ends_with('X')
+        && code[..3].chars().all(|c| c.is_ascii_digit())
+        && code[3..9].chars().all(|c| c.is_ascii_digit() || c.is_ascii_uppercase())
+}
+
+/// Every invalid provider identifier on a claim, with the JSON pointer of its field
+pub fn validate_claim(claim: &PayerClaim) -> Vec<LineError> {
+    let mut errors = Vec::new();
+    if let Some(npi) = &claim.organization.billing_npi
+        && !is_valid_npi(npi)
+    {
+        errors.push(LineError {
+            pointer: "/organization/billing_npi".to_string(),
+            reason: format!("invalid NPI {}", npi),
+        });
+    }
+    if let Some(ein) = &claim.organization.ein
+        && !is_valid_ein(ein)
+    {
+        errors.push(LineError {
+            pointer: "/organization/ein".to_string(),
+            reason: format!("invalid EIN {}", ein),
+        });
+    }
+    if !is_valid_npi(&claim.rendering_provider.npi) {
+        errors.push(LineError {
+            pointer: "/rendering_provider/npi".to_string(),
+            reason: format!("invalid NPI {}", claim.rendering_provider.npi),
+        });
+    }
+    if let Some(provider) = &claim.billing_provider
+        && !is_valid_npi(&provider.npi)
+    {
+        errors.push(LineError {
+            pointer: "/billing_provider/npi".to_string(),
+            reason: format!("invalid NPI {}", provider.npi),
+        });
+    }
+    let taxonomies = [
+        ("/organization/taxonomy_code", claim.organization.taxonomy_code.as_ref()),
+        ("/rendering_provider/taxonomy_code", claim.rendering_provider.taxonomy_code.as_ref()),
+        ("/billing_provider/taxonomy_code", claim.billing_provider.as_ref().and_then(|provider| provider.taxonomy_code.as_ref())),
+    ];
+    for (pointer, code) in taxonomies {
+        if let Some(code) = code
+            && !is_valid_taxonomy(code)
+        {
+            errors.push(LineError {
+                pointer: pointer.to_string(),
+                reason: format!("invalid taxonomy code {}", code),
+            });
+        }
+    }
+    errors.extend(validate_frequency(claim));
+    errors.extend(validate_subscriber(claim));
+    errors.extend(validate_institutional(claim));
+    errors.extend(extensions::validate(claim));
+    errors
+}
+
+/// Institutional header fields and revenue codes must be well formed, and only institutional claims carry revenue codes
+///
+/// The type of bill is three or four digits, the stay cannot end before it began, and an
+/// MS-DRG is three digits; revenue codes are four digits
+fn validate_institutional(claim: &PayerClaim) -> Vec<LineError> {
+    let mut errors = Vec::new();
+    let is_digits = |code: &str, lengths: &[usize]| lengths.contains(&code.len()) && code.chars().all(|c| c.is_ascii_digit());
+    let institutional = claim.institutional();
+    if let Some(institutional) = institutional {
+        if !is_digits(&institutional.bill_type, &[3, 4]) {
+            errors.push(LineError {
+                pointer: "/claim_type/bill_type".to_string(),
+                reason: format!("invalid type of bill {}", institutional.bill_type),
+            });
+        }
+        if let Some(discharge) = institutional.discharge_date
+            && discharge < institutional.admission_date
+        {
+            errors.push(LineError {
+                pointer: "/claim_type/discharge_date".to_string(),
+                reason: format!("discharged {} before admission on {}", discharge, institutional.admission_date),
+            });
+        }
+        if let Some(drg) = &institutional.drg
+            && !is_digits(drg, &[3])
+        {
+            errors.push(LineError {
+                pointer: "/claim_type/drg".to_string(),
+                reason: format!("invalid DRG {}", drg),
+            });
+        }
+    }
+    for (i, line) in claim.service_lines.iter().enumerate() {
+        let Some(code) = &line.revenue_code else { continue };
+        let reason = match institutional {
+            None => format!("revenue code {} on a professional claim", code),
+            Some(_) if !is_digits(code, &[4]) => format!("invalid revenue code {}", code),
+            Some(_) => continue,
+        };
+        errors.push(LineError {
+            pointer: format!("/service_lines/{}/revenue_code", i),
+            reason,
+        });
+    }
+    errors
+}
+
+/// A subscriber block must agree with the patient it covers
+///
+/// A patient who is their own subscriber carries their own member id, and a child cannot be
+/// older than the parent whose policy covers them
+fn validate_subscriber(claim: &PayerClaim) -> Option<LineError> {
+    let subscriber = claim.insurance.subscriber.as_ref()?;
+    let (pointer, reason) = match subscriber.relationship {
+        Relationship::SelfInsured if subscriber.member_id != claim.insurance.patient_member_id => (
+            "/insurance/subscriber/member_id",
+            format!(
+                "subscriber {} is the patient (relationship 18) but the patient's member id is {}",
+                subscriber.member_id, claim.insurance.patient_member_id
+            ),
+        ),
+        Relationship::Child => match (subscriber.dob, claim.patient.dob) {
+            (Some(parent), Some(child)) if parent >= child => (
+                "/insurance/subscriber/dob",
+                format!("subscriber born {} is not older than their child born {}", parent, child),
+            ),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(LineError {
+        pointer: pointer.to_string(),
+        reason,
+    })
+}
+
+/// A replacement claim must name the claim it replaces, and only a replacement may
+fn validate_frequency(claim: &PayerClaim) -> Option<LineError> {
+    let reason = match (claim.claim_frequency_code, &claim.original_claim_id) {
+        (ClaimFrequency::Replacement, None) => "replacement claim has no original_claim_id".to_string(),
+        (ClaimFrequency::Replacement, Some(original)) if *original == claim.claim_id => {
+            format!("claim {} cannot replace itself", original)
+        }
+        (ClaimFrequency::Original, Some(original)) => {
+            format!("original_claim_id {} given, but frequency code is not 7 (replacement)", original)
+        }
+        _ => return None,
+    };
+    Some(LineError {
+        pointer: "/original_claim_id".to_string(),
+        reason,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{ClaimType, InstitutionalClaim, Subscriber};
+    use crate::test_utils::mock_claim;
+    use chrono::NaiveDate;
+
+    /// Test the NPI check digit against the CMS example NPI 1234567893.
+    /// Expected: The check digit is 3; a wrong check digit, wrong length, or leading 3 is invalid.
+    #[test]
+    fn test_npi_check_digit() {
+        assert_eq!(npi_check_digit("123456789"), Some(3));
+        assert!(is_valid_npi("1234567893"));
+        assert!(!is_valid_npi("1234567890"));
+        assert!(!is_valid_npi("123456789"));
+        assert!(!is_valid_npi("3234567893"));
+    }
+
+    /// Test EIN format and prefix checks, and that claim validation points at the bad field.
+    /// Expected: Only `NN-NNNNNNN` with an assigned prefix passes; a bad rendering NPI, a replacement without its original,
+    /// and a subscriber inconsistent with the patient are reported at their pointers.
+    #[test]
+    fn test_ein_and_claim_validation() {
+        assert!(is_valid_ein("12-3456789"));
+        assert!(!is_valid_ein("123456789"));
+        assert!(!is_valid_ein("12-345678"));
+        assert!(!is_valid_ein("07-3456789"));
+
+        let mut claim = mock_claim();
+        assert!(validate_claim(&claim).is_empty());
+        claim.rendering_provider.npi = "1234567890".into();
+        let errors = validate_claim(&claim);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].pointer, "/rendering_provider/npi");
+
+        let mut replacement = mock_claim();
+        replacement.claim_frequency_code = ClaimFrequency::Replacement;
+        assert_eq!(validate_claim(&replacement)[0].pointer, "/original_claim_id");
+        replacement.original_claim_id = Some("abc000".into());
+        assert!(validate_claim(&replacement).is_empty());
+
+        let mut dependent = mock_claim();
+        dependent.insurance.subscriber = Some(Subscriber {
+            member_id: "sub789".into(),
+            first_name: "John".to_string(),
+            last_name: "Doe".to_string(),
+            dob: NaiveDate::from_ymd_opt(2000, 1, 1),
+            relationship: Relationship::Child,
+        });
+        assert_eq!(validate_claim(&dependent)[0].pointer, "/insurance/subscriber/dob");
+        dependent.insurance.subscriber.as_mut().unwrap().relationship = Relationship::SelfInsured;
+        assert_eq!(validate_claim(&dependent)[0].pointer, "/insurance/subscriber/member_id");
+    }
+
+    /// Test taxonomy code format checks and that an individual billing provider is validated like the rendering one.
+    /// Expected: NUCC-shaped codes pass and malformed ones fail; a bad billing provider NPI and taxonomy are reported at their pointers.
+    #[test]
+    fn test_taxonomy_and_billing_provider_validation() {
+        assert!(is_valid_taxonomy("207Q00000X"));
+        assert!(is_valid_taxonomy("2084P0800X"));
+        assert!(!is_valid_taxonomy("207Q00000"));
+        assert!(!is_valid_taxonomy("20AQ00000X"));
+        assert!(!is_valid_taxonomy("207q00000X"));
+
+        let mut claim = mock_claim();
+        let mut solo = claim.rendering_provider.clone();
+        solo.npi = "1234567890".into();
+        solo.taxonomy_code = Some("family".to_string());
+        claim.billing_provider = Some(solo);
+        let pointers: Vec<String> = validate_claim(&claim).into_iter().map(|error| error.pointer).collect();
+        assert_eq!(pointers, ["/billing_provider/npi", "/billing_provider/taxonomy_code"]);
+    }
+
+    /// Test institutional header and revenue code checks.
+    /// Expected: A malformed bill type, a discharge before admission, and a bad DRG are reported on an institutional claim;
+    /// a revenue code on a professional claim is reported at its line.
+    #[test]
+    fn test_institutional_validation() {
+        let admission_date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let mut claim = mock_claim();
+        claim.service_lines[0].revenue_code = Some("0120".to_string());
+        assert_eq!(validate_claim(&claim)[0].pointer, "/service_lines/0/revenue_code");
+
+        claim.claim_type = ClaimType::Institutional(InstitutionalClaim {
+            bill_type: "0111".to_string(),
+            admission_date,
+            discharge_date: admission_date.succ_opt(),
+            drg: Some("470".to_string()),
+        });
+        assert!(validate_claim(&claim).is_empty());
+
+        claim.claim_type = ClaimType::Institutional(InstitutionalClaim {
+            bill_type: "11".to_string(),
+            admission_date,
+            discharge_date: admission_date.pred_opt(),
+            drg: Some("MS470".to_string()),
+        });
+        let pointers: Vec<String> = validate_claim(&claim).into_iter().map(|error| error.pointer).collect();
+        assert_eq!(pointers, ["/claim_type/bill_type", "/claim_type/discharge_date", "/claim_type/drg"]);
+    }
+
+    /// Test that claims outside the allowed currencies, or mixing currencies, are caught.
+    /// Expected: USD passes by default; EUR is rejected unless allowed; a second line in CAD is reported as mixed.
+    #[test]
+    fn test_validate_currency() {
+        let mut claim = mock_claim();
+        assert!(validate_currency(&claim, &AllowedCurrencies::default()).is_empty());
+        claim.service_lines[0].unit_charge_currency = "EUR".to_string();
+        assert_eq!(validate_currency(&claim, &AllowedCurrencies::default())[0].pointer, "/service_lines/0/unit_charge_currency");
+
+        let allowed: AllowedCurrencies = "usd, eur,CAD".parse().unwrap();
+        assert!(validate_currency(&claim, &allowed).is_empty());
+        let mut second = claim.service_lines[0].clone();
+        second.unit_charge_currency = "CAD".to_string();
+        claim.service_lines.push(second);
+        let errors = validate_currency(&claim, &allowed);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].pointer, "/service_lines/1/unit_charge_currency");
+        assert!("US".parse::<AllowedCurrencies>().is_err());
+    }
+
+    /// Test that currency codes are accepted in any case and upper-cased on the checked claim.
+    /// Expected: A claim in `usd` with a second line in `Usd` passes the default and comes back in `USD`; `eur` is still rejected.
+    #[test]
+    fn test_check_currency_ignores_case() {
+        let mut claim = mock_claim();
+        claim.service_lines[0].unit_charge_currency = "usd".to_string();
+        let mut second = claim.service_lines[0].clone();
+        second.service_line_id = "sl2".to_string();
+        second.unit_charge_currency = "Usd".to_string();
+        claim.service_lines.push(second);
+        let checked = check_currency(claim.clone(), &AllowedCurrencies::default()).unwrap();
+        assert!(checked.service_lines.iter().all(|line| line.unit_charge_currency == "USD"));
+
+        claim.service_lines.iter_mut().for_each(|line| line.unit_charge_currency = "eur".to_string());
+        assert!(check_currency(claim, &AllowedCurrencies::default()).is_err());
+    }
+
+    /// Test that a stated header total must match the line charges.
+    /// Expected: No total or a matching one passes; an off total is reported at its pointer.
+    #[test]
+    fn test_validate_claim_total() {
+        let mut claim = mock_claim();
+        assert_eq!(validate_claim_total(&claim), None);
+        claim.total_charge_amount = Some(150.0);
+        assert_eq!(validate_claim_total(&claim), None);
+        claim.total_charge_amount = Some(160.0);
+        assert_eq!(validate_claim_total(&claim).unwrap().pointer, "/total_charge_amount");
+    }
+
+    /// Test that generated claims carry valid identifiers and balanced totals.
+    /// Expected: No validation errors across a batch of fake claims.
+    #[test]
+    fn test_fake_claims_are_valid() {
+        for _ in 0..100 {
+            let claim = crate::json_faker::fake_payer_claim();
+            assert_eq!(validate_claim(&claim), Vec::new(), "claim {:?}", claim.organization);
+            assert_eq!(validate_claim_total(&claim), None);
+        }
+    }
+}
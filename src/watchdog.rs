@@ -0,0 +1,283 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Sender;
+
+use crate::event_log::{Event, EventLog};
+use crate::history::History;
+use crate::ids::{ClaimId, ClaimKey, PayerId};
+use crate::message::{ClaimMessage, ClaimStatus};
+use crate::payer::{PayerClaimStatus, PayerPortal};
+
+/// What the watchdog does about a claim stuck with its payer, beyond alerting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StuckAction {
+    /// Only raise an alert
+    #[default]
+    Alert,
+    /// Ask the payer where the claim stands and include its answer in the alert
+    Inquire,
+    /// Inquire, and send the claim again if the payer has no record of it
+    Resubmit,
+}
+
+impl FromStr for StuckAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "alert" => Ok(StuckAction::Alert),
+            "inquire" => Ok(StuckAction::Inquire),
+            "resubmit" => Ok(StuckAction::Resubmit),
+            _ => Err(anyhow::anyhow!("Unknown stuck claim action: {} (expected alert, inquire, or resubmit)", s)),
+        }
+    }
+}
+
+impl fmt::Display for StuckAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StuckAction::Alert => write!(f, "alert"),
+            StuckAction::Inquire => write!(f, "inquire"),
+            StuckAction::Resubmit => write!(f, "resubmit"),
+        }
+    }
+}
+
+/// How long a claim may wait on its payer before it is stuck, and what is done about it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WatchdogSettings {
+    pub stuck_after: Duration,
+    pub action: StuckAction,
+}
+
+/// A payer's answer to a status inquiry
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inquiry {
+    /// The payer has no record of the claim: it was lost on the way
+    NoRecord,
+    /// The payer takes no inquiries, as for external payers
+    Unanswered,
+    Status(PayerClaimStatus),
+}
+
+impl fmt::Display for Inquiry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Inquiry::NoRecord => write!(f, "payer has no record of it"),
+            Inquiry::Unanswered => write!(f, "payer takes no status inquiries"),
+            Inquiry::Status(PayerClaimStatus::Received) => write!(f, "payer has it queued"),
+            Inquiry::Status(PayerClaimStatus::Pending) => write!(f, "payer is adjudicating it"),
+            Inquiry::Status(PayerClaimStatus::AwaitingAttachments { document_types }) => {
+                write!(f, "payer is waiting on attachments: {}", document_types.join(", "))
+            }
+            Inquiry::Status(PayerClaimStatus::Finished { .. }) => write!(f, "payer remitted it; the remittance was lost"),
+            Inquiry::Status(PayerClaimStatus::Voided { .. }) => write!(f, "payer voided it"),
+            Inquiry::Status(PayerClaimStatus::Replaced { replacement, .. }) => {
+                write!(f, "payer replaced it with claim {}", replacement)
+            }
+        }
+    }
+}
+
+/// A claim found waiting on its payer past the allowed time
+#[derive(Debug, Clone, PartialEq)]
+pub struct StuckClaim {
+    pub claim_id: ClaimId,
+    pub tenant_id: String,
+    pub payer_id: PayerId,
+    pub pending: Duration,
+    /// `None` unless the action inquires
+    pub inquiry: Option<Inquiry>,
+    pub resubmitted: bool,
+}
+
+/// What one pass of the watchdog found
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scan {
+    /// Claims newly stuck, or still stuck a full allowed time after their last alert
+    pub stuck: Vec<StuckClaim>,
+    /// Claims waiting on their payer, stuck or not
+    pub pending: usize,
+    /// Time since a claim was last remitted, once it passes the allowed time with claims pending
+    pub stalled_for: Option<Duration>,
+}
+
+/// Watches claim history for claims their payer never answers and for a pipeline that stops remitting
+///
+/// A stuck claim is alerted on once per allowed time for as long as it stays stuck, so a
+/// resubmission gets a full allowed time to be answered before the claim is flagged again
+pub struct Watchdog {
+    settings: WatchdogSettings,
+    history: History,
+    portals: BTreeMap<PayerId, PayerPortal>,
+    claims: Option<Sender<ClaimMessage>>,
+    event_log: Option<EventLog>,
+    /// When each stuck claim was last alerted on
+    alerted: HashMap<ClaimKey, Instant>,
+    remitted: usize,
+    progress_at: Instant,
+    stall_alerted: bool,
+}
+
+impl Watchdog {
+    pub fn new(settings: WatchdogSettings, history: History) -> Self {
+        Self {
+            settings,
+            history,
+            portals: BTreeMap::new(),
+            claims: None,
+            event_log: None,
+            alerted: HashMap::new(),
+            remitted: 0,
+            progress_at: Instant::now(),
+            stall_alerted: false,
+        }
+    }
+
+    /// Payer portals to send status inquiries to
+    pub fn with_portals(mut self, portals: BTreeMap<PayerId, PayerPortal>) -> Self {
+        self.portals = portals;
+        self
+    }
+
+    /// Clearinghouse inbox resubmissions are sent to; without it claims are never resubmitted
+    pub fn with_claim_inbox(mut self, claims: Sender<ClaimMessage>) -> Self {
+        self.claims = Some(claims);
+        self
+    }
+
+    /// Record each stuck claim alert to an event log
+    pub fn with_event_log(mut self, event_log: EventLog) -> Self {
+        self.event_log = Some(event_log);
+        self
+    }
+
+    /// Scan history every `interval` until the task is dropped
+    pub async fn run(mut self, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            self.scan().await;
+        }
+    }
+
+    /// Check history once, alerting on and acting on every claim due
+    pub async fn scan(&mut self) -> Scan {
+        let now = Instant::now();
+        let stuck_after = self.settings.stuck_after;
+        let (mut pending, mut remitted, mut overdue) = (0, 0, Vec::new());
+        self.history
+            .for_each(|key, status| match status {
+                ClaimStatus::Submitted { claim, submitted_at, .. } => {
+                    pending += 1;
+                    let waited = now.saturating_duration_since(*submitted_at);
+                    if waited >= stuck_after {
+                        let payer_claim_id = status.payer_claim_id().clone();
+                        overdue.push((key.clone(), payer_claim_id, claim.insurance.payer_id.clone(), waited));
+                    }
+                }
+                ClaimStatus::Remitted(_) => remitted += 1,
+                ClaimStatus::Voided { .. } => {}
+            })
+            .await;
+
+        // claims answered since their last alert are forgotten, so they alert afresh if stuck again
+        self.alerted.retain(|key, _| overdue.iter().any(|(overdue_key, ..)| overdue_key == key));
+        let mut stuck = Vec::new();
+        for (key, payer_claim_id, payer_id, pending) in overdue {
+            let due = self.alerted.get(&key).is_none_or(|at| now.saturating_duration_since(*at) >= stuck_after);
+            if due {
+                self.alerted.insert(key.clone(), now);
+                stuck.push(self.act(key, payer_claim_id, payer_id, pending).await);
+            }
+        }
+
+        if remitted > self.remitted {
+            (self.remitted, self.progress_at, self.stall_alerted) = (remitted, now, false);
+        }
+        let idle = now.saturating_duration_since(self.progress_at);
+        let stalled_for = (pending > 0 && idle >= stuck_after).then_some(idle);
+        if stalled_for.is_some() && !std::mem::replace(&mut self.stall_alerted, true) {
+            eprintln!("No claim remitted in {:.0}s with {} claims waiting on their payers", idle.as_secs_f64(), pending);
+        }
+        Scan { stuck, pending, stalled_for }
+    }
+
+    /// The payer is asked about the claim by the id the clearinghouse sent it under
+    async fn act(&self, key: ClaimKey, payer_claim_id: ClaimId, payer_id: PayerId, pending: Duration) -> StuckClaim {
+        let ClaimKey { tenant_id, claim_id } = key;
+        let inquiry = match self.settings.action {
+            StuckAction::Alert => None,
+            StuckAction::Inquire | StuckAction::Resubmit => Some(match self.portals.get(&payer_id) {
+                Some(portal) => portal.claim_status(&payer_claim_id).map_or(Inquiry::NoRecord, Inquiry::Status),
+                None => Inquiry::Unanswered,
+            }),
+        };
+        let mut resubmitted = false;
+        if self.settings.action == StuckAction::Resubmit
+            && inquiry == Some(Inquiry::NoRecord)
+            && let Some(claims) = &self.claims
+        {
+            let resubmit = ClaimMessage::Resubmit { claim_id: claim_id.clone(), tenant_id: tenant_id.clone() };
+            resubmitted = claims.send(resubmit).await.is_ok();
+        }
+
+        let mut alert = format!("Claim {} from {} stuck with payer {} for {:.0}s", claim_id, tenant_id, payer_id, pending.as_secs_f64());
+        if let Some(inquiry) = &inquiry {
+            alert.push_str(&format!(": {}", inquiry));
+        }
+        if resubmitted {
+            alert.push_str("; resubmitted");
+        }
+        eprintln!("{}", alert);
+        if let Some(event_log) = &self.event_log {
+            event_log.record(Event::ClaimStuck {
+                claim_id: claim_id.clone(),
+                payer_id: payer_id.clone(),
+                pending_secs: pending.as_secs(),
+            });
+        }
+        StuckClaim { claim_id, tenant_id, payer_id, pending, inquiry, resubmitted }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::ClaimMetadata;
+    use crate::tenant::DEFAULT_TENANT;
+    use crate::test_utils::mock_claim;
+
+    fn submitted(claim_id: &str, waited: Duration) -> (ClaimKey, ClaimStatus) {
+        let claim = crate::schema::PayerClaim { claim_id: ClaimId::new(claim_id), ..mock_claim() };
+        let status = ClaimStatus::Submitted {
+            claim: claim.into(),
+            tenant_id: DEFAULT_TENANT.to_string(),
+            submitted_at: Instant::now() - waited,
+            metadata: ClaimMetadata::default(),
+        };
+        (status.key(), status)
+    }
+
+    /// Test that a claim past its allowed time is flagged once, inquired about, and resubmitted when its payer lost it.
+    /// Expected: Only the old claim is stuck; the payer has no record, so a resubmission reaches the clearinghouse inbox; an immediate rescan flags nothing.
+    #[tokio::test]
+    async fn test_stuck_claim_inquired_and_resubmitted() {
+        let history = History::from_records([submitted("lost", Duration::from_secs(10)), submitted("fresh", Duration::ZERO)].into());
+        let portals = BTreeMap::from([(PayerId::from("medicare"), PayerPortal::default())]);
+        let (claims, mut inbox) = tokio::sync::mpsc::channel(4);
+        let settings = WatchdogSettings { stuck_after: Duration::from_secs(5), action: StuckAction::Resubmit };
+        let mut watchdog = Watchdog::new(settings, history).with_portals(portals).with_claim_inbox(claims);
+
+        let scan = watchdog.scan().await;
+        assert_eq!(scan.pending, 2);
+        assert_eq!(scan.stuck.len(), 1);
+        let stuck = &scan.stuck[0];
+        assert_eq!((stuck.claim_id.as_str(), &stuck.inquiry, stuck.resubmitted), ("lost", &Some(Inquiry::NoRecord), true));
+        assert_eq!(inbox.try_recv().unwrap().summary(), "resubmit claim lost from default");
+
+        assert!(watchdog.scan().await.stuck.is_empty());
+        assert!(inbox.try_recv().is_err());
+    }
+}
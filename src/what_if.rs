@@ -0,0 +1,184 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use crate::benefits::{Accumulator, BenefitPlans};
+use crate::control::TELEHEALTH_PARITY;
+use crate::event_log::{Event, EventRecord};
+use crate::ids::PayerId;
+use crate::medical_necessity::CoveragePolicy;
+use crate::outcomes::OutcomeTable;
+use crate::payer;
+use crate::rejects;
+use crate::remittance::Remittance;
+use crate::schema::{CoverageKey, PayerClaim};
+
+/// Payer contract terms a claim set is adjudicated under: fee schedules and cost sharing by
+/// plan, and the rules deciding which lines are paid
+#[derive(Debug, Clone, Default)]
+pub struct ContractTerms {
+    pub benefits: Arc<BenefitPlans>,
+    pub outcomes: Arc<OutcomeTable>,
+    pub coverage: Arc<CoveragePolicy>,
+}
+
+/// Files contract terms are read from; each absent file keeps the simulator's default
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContractPaths {
+    pub benefits: Option<String>,
+    pub outcomes: Option<String>,
+    pub coverage: Option<String>,
+}
+
+impl ContractPaths {
+    /// These paths, falling back to `baseline`'s for any not given
+    pub fn or(&self, baseline: &ContractPaths) -> ContractPaths {
+        ContractPaths {
+            benefits: self.benefits.clone().or_else(|| baseline.benefits.clone()),
+            outcomes: self.outcomes.clone().or_else(|| baseline.outcomes.clone()),
+            coverage: self.coverage.clone().or_else(|| baseline.coverage.clone()),
+        }
+    }
+
+    pub fn load(&self) -> anyhow::Result<ContractTerms> {
+        Ok(ContractTerms {
+            benefits: Arc::new(self.benefits.as_deref().map(BenefitPlans::load).transpose()?.unwrap_or_default()),
+            outcomes: Arc::new(self.outcomes.as_deref().map(OutcomeTable::load).transpose()?.unwrap_or_default()),
+            coverage: Arc::new(self.coverage.as_deref().map(CoveragePolicy::load).transpose()?.unwrap_or_default()),
+        })
+    }
+}
+
+/// Revenue of one payer's claims under the baseline and proposed terms
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PayerImpact {
+    pub payer_id: PayerId,
+    pub claims: usize,
+    pub billed: f64,
+    pub baseline_paid: f64,
+    pub proposed_paid: f64,
+    pub baseline_patient: f64,
+    pub proposed_patient: f64,
+    pub baseline_denied_lines: usize,
+    pub proposed_denied_lines: usize,
+}
+
+impl PayerImpact {
+    /// Change in payer payments, proposed less baseline
+    pub fn paid_change(&self) -> f64 {
+        self.proposed_paid - self.baseline_paid
+    }
+
+    /// Change in expected revenue, payer and patient together
+    pub fn revenue_change(&self) -> f64 {
+        self.proposed_paid + self.proposed_patient - self.baseline_paid - self.baseline_patient
+    }
+}
+
+/// Claims stored in a claims JSONL file or submitted in an event log, in file order
+///
+/// Lines of an event log other than submissions are skipped, as are lines that are not claims
+pub fn load_claims(path: &str) -> anyhow::Result<Vec<PayerClaim>> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut claims = Vec::new();
+    for (index, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        if let Ok(record) = serde_json::from_str::<EventRecord>(line) {
+            if let Event::ClaimSubmitted { claim, .. } = record.event {
+                claims.push(Arc::unwrap_or_clone(claim));
+            }
+            continue;
+        }
+        match rejects::parse_claim_line(line) {
+            Ok(claim) => claims.push(claim),
+            Err(err) => eprintln!("Skipping {}:{}: {}: {}", path, index + 1, err.pointer, err.reason),
+        }
+    }
+    Ok(claims)
+}
+
+/// Adjudicate `claims` offline under both sets of terms and total each payer's revenue
+///
+/// Claims are priced in date of service order so deductibles and out-of-pocket maximums fill
+/// as they would have. Outcomes drawn at random use the same draws under both terms, seeded
+/// per claim from `seed`, so differences come from the terms alone. Eligibility, attachment
+/// requests, and random denials are not simulated
+pub fn compare(claims: &[PayerClaim], baseline: &ContractTerms, proposed: &ContractTerms, seed: u64) -> Vec<PayerImpact> {
+    let mut ordered: Vec<&PayerClaim> = claims.iter().collect();
+    ordered.sort_by(|a, b| (a.service_date, &a.claim_id).cmp(&(b.service_date, &b.claim_id)));
+    let baseline_remits = adjudicate_all(&ordered, baseline, seed);
+    let proposed_remits = adjudicate_all(&ordered, proposed, seed);
+
+    let mut impacts: BTreeMap<PayerId, PayerImpact> = BTreeMap::new();
+    for ((claim, before), after) in ordered.iter().zip(&baseline_remits).zip(&proposed_remits) {
+        let payer_id = &claim.insurance.payer_id;
+        let impact = impacts.entry(payer_id.clone()).or_insert_with(|| PayerImpact {
+            payer_id: payer_id.clone(),
+            ..PayerImpact::default()
+        });
+        impact.claims += 1;
+        impact.billed += claim.total_charge();
+        impact.baseline_paid += before.payer_paid();
+        impact.proposed_paid += after.payer_paid();
+        impact.baseline_patient += patient_share(before);
+        impact.proposed_patient += patient_share(after);
+        impact.baseline_denied_lines += denied_lines(before);
+        impact.proposed_denied_lines += denied_lines(after);
+    }
+    impacts.into_values().collect()
+}
+
+fn adjudicate_all(claims: &[&PayerClaim], terms: &ContractTerms, seed: u64) -> Vec<Remittance> {
+    let mut accumulators: HashMap<CoverageKey, Accumulator> = HashMap::new();
+    claims
+        .iter()
+        .map(|claim| {
+            let mut rng = payer::claim_rng(Some(seed), "adjudicate", &claim.claim_id);
+            let outcomes = payer::line_outcomes(claim, &terms.coverage, &terms.outcomes, &mut rng);
+            let plan = terms.benefits.plan(&claim.insurance);
+            let accumulator = accumulators.entry(claim.insurance.coverage_key()).or_default();
+            let on = claim.service_date.unwrap_or_else(|| chrono::Utc::now().date_naive());
+            Remittance::adjudicated(claim, &outcomes, plan, TELEHEALTH_PARITY, accumulator, on)
+        })
+        .collect()
+}
+
+fn patient_share(remittance: &Remittance) -> f64 {
+    remittance.service_line_remittances().iter().map(|line| line.patient_responsibility()).sum()
+}
+
+fn denied_lines(remittance: &Remittance) -> usize {
+    match remittance.is_denied() {
+        true => remittance.service_line_remittances().len(),
+        false => remittance.service_line_remittances().iter().filter(|line| line.denial_reason().is_some()).count(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benefits::PlanDesign;
+    use crate::test_utils::mock_claim;
+
+    /// Test that a lower fee schedule in the proposed terms shows as lost payer revenue.
+    /// Expected: Same claim counts and billed amounts; proposed payer payments below baseline; identical terms show no change.
+    #[test]
+    fn test_compare_fee_schedules() {
+        let claims = vec![mock_claim()];
+        let baseline = ContractTerms::default();
+        let plan = PlanDesign {
+            fee_schedule: 0.5,
+            ..PlanDesign::default()
+        };
+        let proposed = ContractTerms {
+            benefits: Arc::new(BenefitPlans::new(vec![plan], HashMap::new()).unwrap()),
+            ..ContractTerms::default()
+        };
+
+        let [impact] = &compare(&claims, &baseline, &proposed, 7)[..] else { panic!("Expected one payer") };
+        assert_eq!(impact.claims, 1);
+        assert_eq!(impact.billed, claims[0].total_charge());
+        assert!(impact.paid_change() < 0.0, "{:?}", impact);
+        let [unchanged] = &compare(&claims, &baseline, &baseline, 7)[..] else { panic!("Expected one payer") };
+        assert_eq!(unchanged.paid_change(), 0.0);
+    }
+}
@@ -0,0 +1,363 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::claim_notes::{ClaimNote, ClaimNotes, NoteAction};
+use crate::clock::SimClock;
+use crate::history::History;
+use crate::ids::{ClaimId, ClaimKey, PayerId};
+use crate::message::ClaimStatus;
+use crate::remittance::RemittanceRecord;
+
+/// Why a remitted claim needs a billing worker's attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkKind {
+    /// The payer denied the claim
+    Denial,
+    /// The payer paid less than the claim was priced at
+    Underpayment,
+    /// The remittance failed balancing
+    Exception,
+}
+
+impl WorkKind {
+    /// What a worker does to resolve an item of this kind
+    fn action(self) -> NoteAction {
+        match self {
+            WorkKind::Denial => NoteAction::Appeal,
+            WorkKind::Underpayment => NoteAction::PayerCall,
+            WorkKind::Exception => NoteAction::Correction,
+        }
+    }
+}
+
+impl fmt::Display for WorkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WorkKind::Denial => "denial",
+            WorkKind::Underpayment => "underpayment",
+            WorkKind::Exception => "exception",
+        })
+    }
+}
+
+/// A simulated billing worker: how many items they resolve per simulated day and how many they hold at once
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaffMember {
+    pub name: String,
+    pub per_day: f64,
+    pub capacity: usize,
+}
+
+impl FromStr for StaffMember {
+    type Err = anyhow::Error;
+
+    /// Parse `<name>=<per day>/<capacity>`, e.g. `jsmith=20/10`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || anyhow::anyhow!("Invalid staff member: {} (expected <name>=<per day>/<capacity>, e.g. jsmith=20/10)", s);
+        let (name, rates) = s.split_once('=').ok_or_else(invalid)?;
+        let (per_day, capacity) = rates.split_once('/').ok_or_else(invalid)?;
+        let per_day: f64 = per_day.trim().parse().map_err(|_| invalid())?;
+        let capacity: usize = capacity.trim().parse().map_err(|_| invalid())?;
+        let in_range = !name.trim().is_empty() && per_day > 0.0 && per_day.is_finite() && capacity > 0;
+        if !in_range {
+            return Err(invalid());
+        }
+        Ok(StaffMember { name: name.trim().to_string(), per_day, capacity })
+    }
+}
+
+/// A remitted claim waiting to be worked
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkItem {
+    pub tenant_id: String,
+    pub claim_id: ClaimId,
+    pub payer_id: PayerId,
+    pub kind: WorkKind,
+    /// Dollars at stake: the charge on a denial or exception, the shortfall on an underpayment
+    pub amount: f64,
+    /// Simulated day the item was found, counted from the start of the workqueue
+    found_day: f64,
+}
+
+impl WorkItem {
+    /// The item a remittance calls for, if any; a denial outranks an underpayment, which outranks an exception
+    fn from_record(record: &RemittanceRecord, found_day: f64) -> Option<Self> {
+        let remittance = record.remittance();
+        let shortfall: f64 = record.discrepancies().iter().map(|discrepancy| -discrepancy.variance).filter(|short| *short > 0.0).sum();
+        let (kind, amount) = if remittance.is_denied() {
+            (WorkKind::Denial, record.claim().total_charge())
+        } else if shortfall > 0.0 {
+            (WorkKind::Underpayment, shortfall)
+        } else if remittance.balancing().reason().is_some() {
+            (WorkKind::Exception, record.claim().total_charge())
+        } else {
+            return None;
+        };
+        Some(WorkItem {
+            tenant_id: record.tenant_id().to_string(),
+            claim_id: remittance.claim_id().clone(),
+            payer_id: record.payer_id().clone(),
+            kind,
+            amount,
+            found_day,
+        })
+    }
+
+    fn key(&self) -> ClaimKey {
+        ClaimKey::new(self.tenant_id.clone(), self.claim_id.clone())
+    }
+}
+
+/// One worker's load and output so far
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkerStats {
+    pub name: String,
+    /// Items assigned and not yet resolved
+    pub held: usize,
+    pub capacity: usize,
+    pub resolved: u32,
+    /// Items resolved per simulated day since the workqueue started
+    pub throughput: f64,
+    /// Share of the elapsed time the worker had something to work on
+    pub utilization: f64,
+}
+
+/// Backlog and resolution throughput of the whole workqueue
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WorkqueueStats {
+    /// Simulated days since the workqueue started
+    pub elapsed_days: f64,
+    /// Items found and not yet assigned, by kind
+    pub backlog: BTreeMap<WorkKind, u32>,
+    /// Dollars at stake on unresolved items, assigned or not
+    pub open_amount: f64,
+    pub resolved: u32,
+    /// Items resolved per simulated day
+    pub throughput: f64,
+    /// Mean simulated days from an item being found to its resolution
+    pub avg_wait_days: Option<f64>,
+    pub workers: Vec<WorkerStats>,
+}
+
+impl WorkqueueStats {
+    /// Items found and not yet assigned
+    pub fn backlog_total(&self) -> u32 {
+        self.backlog.values().sum()
+    }
+}
+
+/// A worker's desk: what they hold and how far into the current item they are
+#[derive(Debug)]
+struct Desk {
+    staff: StaffMember,
+    held: VecDeque<WorkItem>,
+    /// Items' worth of work done toward the one at the front
+    progress: f64,
+    resolved: u32,
+    busy_days: f64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    seen: HashSet<ClaimKey>,
+    backlog: VecDeque<WorkItem>,
+    desks: Vec<Desk>,
+    elapsed_days: f64,
+    resolved: u32,
+    wait_days: f64,
+}
+
+/// Distributes denials, underpayments, and remittance exceptions across simulated billing staff
+///
+/// Items are found in claim history as remittances arrive and queued in the order they were
+/// found. Each worker holds up to their capacity and works what they hold one item at a time at
+/// their rate, taking the next item from the backlog as soon as they have room; new items go to
+/// the least loaded worker first. With claim notes, each resolution is noted on its claim under
+/// `workqueue:<name>`
+#[derive(Debug, Clone)]
+pub struct Workqueue {
+    state: Arc<Mutex<State>>,
+    notes: Option<ClaimNotes>,
+}
+
+impl Workqueue {
+    pub fn new(staff: Vec<StaffMember>) -> Self {
+        let desks = staff
+            .into_iter()
+            .map(|staff| Desk { staff, held: VecDeque::new(), progress: 0.0, resolved: 0, busy_days: 0.0 })
+            .collect();
+        Self { state: Arc::new(Mutex::new(State { desks, ..State::default() })), notes: None }
+    }
+
+    /// Note each resolution on its claim
+    pub fn with_notes(mut self, notes: ClaimNotes) -> Self {
+        self.notes = Some(notes);
+        self
+    }
+
+    /// Take in new items from history and work them every `interval` until the task is dropped
+    pub async fn run(self, history: History, clock: SimClock, interval: Duration) {
+        let mut last = Instant::now();
+        loop {
+            tokio::time::sleep(interval).await;
+            let now = Instant::now();
+            self.intake(&history.snapshot().await);
+            self.work(clock.days(now.saturating_duration_since(last)));
+            last = now;
+        }
+    }
+
+    /// Queue an item for every remitted claim that needs one and has not been queued before
+    pub fn intake(&self, records: &HashMap<ClaimKey, ClaimStatus>) {
+        let mut state = self.state.lock().unwrap();
+        let found_day = state.elapsed_days;
+        let mut found: Vec<WorkItem> = records
+            .iter()
+            .filter_map(|(key, status)| match status {
+                ClaimStatus::Remitted(record) if !state.seen.contains(key) => WorkItem::from_record(record, found_day),
+                _ => None,
+            })
+            .collect();
+        found.sort_by_key(WorkItem::key);
+        for item in found {
+            state.seen.insert(item.key());
+            state.backlog.push_back(item);
+        }
+        assign(&mut state);
+    }
+
+    /// Let `days` simulated days of work pass
+    pub fn work(&self, days: f64) {
+        let mut state = self.state.lock().unwrap();
+        state.elapsed_days += days;
+        let now = state.elapsed_days;
+        let mut resolved = Vec::new();
+        let State { backlog, desks, .. } = &mut *state;
+        for desk in desks.iter_mut() {
+            desk.progress += desk.staff.per_day * days;
+            let mut done = 0;
+            loop {
+                if desk.held.is_empty() {
+                    match backlog.pop_front() {
+                        Some(item) => desk.held.push_back(item),
+                        None => {
+                            desk.progress = 0.0;
+                            break;
+                        }
+                    }
+                }
+                if desk.progress < 1.0 {
+                    break;
+                }
+                desk.progress -= 1.0;
+                done += 1;
+                resolved.push((desk.staff.name.clone(), desk.held.pop_front().expect("desk holds an item")));
+            }
+            desk.resolved += done;
+            desk.busy_days += if desk.held.is_empty() { days.min(done as f64 / desk.staff.per_day) } else { days };
+        }
+        for (name, item) in resolved {
+            state.resolved += 1;
+            state.wait_days += now - item.found_day;
+            if let Some(notes) = &self.notes {
+                let text = format!("Resolved {} on {:.2} from payer {}", item.kind, item.amount, item.payer_id);
+                notes.add(&item.key(), ClaimNote::new(format!("workqueue:{}", name), item.kind.action(), text));
+            }
+        }
+        assign(&mut state);
+    }
+
+    /// Backlog, throughput, and each worker's load so far
+    pub fn stats(&self) -> WorkqueueStats {
+        let state = self.state.lock().unwrap();
+        let elapsed = state.elapsed_days;
+        let per_day = |count: u32| if elapsed > 0.0 { count as f64 / elapsed } else { 0.0 };
+        let mut backlog = BTreeMap::new();
+        for item in &state.backlog {
+            *backlog.entry(item.kind).or_insert(0) += 1;
+        }
+        let held = state.desks.iter().flat_map(|desk| &desk.held);
+        WorkqueueStats {
+            elapsed_days: elapsed,
+            backlog,
+            open_amount: state.backlog.iter().chain(held).map(|item| item.amount).sum(),
+            resolved: state.resolved,
+            throughput: per_day(state.resolved),
+            avg_wait_days: (state.resolved > 0).then(|| state.wait_days / state.resolved as f64),
+            workers: state
+                .desks
+                .iter()
+                .map(|desk| WorkerStats {
+                    name: desk.staff.name.clone(),
+                    held: desk.held.len(),
+                    capacity: desk.staff.capacity,
+                    resolved: desk.resolved,
+                    throughput: per_day(desk.resolved),
+                    utilization: if elapsed > 0.0 { desk.busy_days / elapsed } else { 0.0 },
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Hand backlog items, oldest first, to the least loaded worker with room until no one has room
+fn assign(state: &mut State) {
+    while !state.backlog.is_empty() {
+        let Some(desk) = state
+            .desks
+            .iter_mut()
+            .filter(|desk| desk.held.len() < desk.staff.capacity)
+            .min_by_key(|desk| desk.held.len())
+        else {
+            break;
+        };
+        desk.held.extend(state.backlog.pop_front());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remittance::Remittance;
+    use crate::schema::PayerClaim;
+    use crate::tenant::DEFAULT_TENANT;
+    use crate::test_utils::{mock_claim, mock_remittance};
+
+    /// Test that found items are spread over workers within capacity and worked at their rates.
+    /// Expected: Five denials fill both desks and leave one in the backlog; a day later the faster worker has resolved two and the slower one, so the backlog is drained, and each resolution is noted on its claim.
+    #[test]
+    fn test_workqueue_assignment_and_throughput() {
+        let remitted = |claim_id: &str, denied: bool| {
+            let claim = PayerClaim { claim_id: ClaimId::new(claim_id), ..mock_claim() };
+            let remittance = if denied { Remittance::denied(&claim) } else { mock_remittance() };
+            let record = RemittanceRecord::new(claim, remittance, Instant::now(), Instant::now());
+            (ClaimKey::new(DEFAULT_TENANT, claim_id), ClaimStatus::Remitted(record))
+        };
+        let records: HashMap<ClaimKey, ClaimStatus> =
+            ["a", "b", "c", "d", "e"].map(|claim_id| remitted(claim_id, true)).into_iter().chain([remitted("paid", false)]).collect();
+        let staff = vec!["fast=2/2".parse().unwrap(), "slow=1/2".parse().unwrap()];
+        let notes = ClaimNotes::new();
+        let workqueue = Workqueue::new(staff).with_notes(notes.clone());
+
+        workqueue.intake(&records);
+        workqueue.intake(&records);
+        let stats = workqueue.stats();
+        assert_eq!(stats.backlog, BTreeMap::from([(WorkKind::Denial, 1)]));
+        assert_eq!(stats.workers.iter().map(|worker| worker.held).collect::<Vec<_>>(), vec![2, 2]);
+
+        workqueue.work(1.0);
+        let stats = workqueue.stats();
+        assert_eq!((stats.resolved, stats.backlog_total()), (3, 0));
+        assert_eq!(stats.workers.iter().map(|worker| (worker.resolved, worker.held)).collect::<Vec<_>>(), vec![(2, 1), (1, 1)]);
+        assert_eq!(stats.avg_wait_days, Some(1.0));
+        assert!((stats.workers[0].utilization - 1.0).abs() < 1e-9);
+        let key = ClaimKey::new(DEFAULT_TENANT, "a");
+        assert_eq!(notes.for_claim(&key)[0].author, "workqueue:fast");
+        assert_eq!(notes.for_claim(&key)[0].action, NoteAction::Appeal);
+    }
+}
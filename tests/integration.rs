@@ -1,18 +1,25 @@
 use healthtechsim::biller::run_biller;
 use healthtechsim::clearinghouse::Clearinghouse;
 use healthtechsim::config::Config;
+use healthtechsim::history::History;
+use healthtechsim::ids::{ClaimId, ClaimKey};
 use healthtechsim::message::{ClaimMessage, PayerMessage, RemittanceMessage};
 use healthtechsim::payer::Payer;
 use healthtechsim::reader::stream_claims;
-use healthtechsim::schema::{PayerClaim, mock_claim};
+use healthtechsim::schema::PayerClaim;
+use healthtechsim::tenant::DEFAULT_TENANT;
+use healthtechsim::test_utils::mock_claim;
 use std::collections::HashMap;
 use std::io::Write;
-use std::sync::Arc;
 use std::time::Duration;
 use tempfile::NamedTempFile;
-use tokio::sync::Mutex;
 use tokio::time::timeout;
 
+/// History key of a claim submitted by the default tenant's biller
+fn default_key(claim: &PayerClaim) -> ClaimKey {
+    ClaimKey::new(DEFAULT_TENANT, claim.claim_id.clone())
+}
+
 /// Test that a claim flows correctly through Reader -> Biller -> Clearinghouse -> Payer -> Remittance back to Biller
 /// This is the core data flow integrity test
 #[tokio::test]
@@ -27,6 +34,7 @@ async fn test_core_data_flow_integrity() {
         file_path: tmpfile.path().to_str().unwrap().to_string(),
         ingest_rate: 1,
         verbose: false,
+        ..Config::default()
     };
 
     // Set up channels
@@ -34,18 +42,20 @@ async fn test_core_data_flow_integrity() {
     let (claim_tx, claim_rx) = tokio::sync::mpsc::channel::<ClaimMessage>(1);
     let (payer_tx, payer_rx) = tokio::sync::mpsc::channel::<PayerMessage>(1);
     let (remit_tx, remit_rx) = tokio::sync::mpsc::channel::<RemittanceMessage>(1);
-    let biller_txs = Arc::new(Mutex::new(HashMap::new()));
-    let remittance_history = Arc::new(Mutex::new(HashMap::new()));
+    let remittance_history = History::default();
 
     // Notification channel to track when biller receives remittance
-    let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel::<String>(1);
+    let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel::<ClaimId>(1);
 
     // Spawn biller
+    let (shutdown_tx, _shutdown_rx) = tokio::sync::mpsc::channel::<()>(1);
     tokio::spawn(run_biller(
         config.clone(),
         claim_input_rx,
         claim_tx,
         Some(notify_tx),
+        1,
+        shutdown_tx,
     ));
 
     // Spawn reader
@@ -55,12 +65,11 @@ async fn test_core_data_flow_integrity() {
 
     // Spawn clearinghouse
     let mut payer_txs = HashMap::new();
-    payer_txs.insert("medicare".to_string(), payer_tx);
+    payer_txs.insert("medicare".into(), payer_tx);
     let clearinghouse = Clearinghouse::new(
         claim_rx,
         payer_txs,
         remit_rx,
-        biller_txs.clone(),
         remittance_history.clone(),
         false,
     );
@@ -83,14 +92,14 @@ async fn test_core_data_flow_integrity() {
     assert_eq!(received_claim_id, claim.claim_id, "Claim ID should match");
 
     // Verify the claim was processed by checking history
-    let history = remittance_history.lock().await;
+    let history = remittance_history.snapshot().await;
     assert!(
-        history.contains_key(&claim.claim_id),
+        history.contains_key(&default_key(&claim)),
         "Claim should be in history"
     );
 
     // Verify claim status transitioned to Remitted
-    match history.get(&claim.claim_id) {
+    match history.get(&default_key(&claim)) {
         Some(healthtechsim::message::ClaimStatus::Remitted(_)) => {
             // Success - claim was properly remitted
         }
@@ -105,6 +114,7 @@ async fn test_remittance_amount_validation() {
         file_path: "mock_path.json".to_string(),
         ingest_rate: 1,
         verbose: false,
+        ..Config::default()
     };
 
     // Set up channels
@@ -112,20 +122,19 @@ async fn test_remittance_amount_validation() {
     let (claim_tx, claim_rx) = tokio::sync::mpsc::channel::<ClaimMessage>(1);
     let (payer_tx, payer_rx) = tokio::sync::mpsc::channel::<PayerMessage>(1);
     let (remit_tx, remit_rx) = tokio::sync::mpsc::channel::<RemittanceMessage>(1);
-    let biller_txs = Arc::new(Mutex::new(HashMap::new()));
-    let remittance_history = Arc::new(Mutex::new(HashMap::new()));
+    let remittance_history = History::default();
 
     // Spawn biller
-    tokio::spawn(run_biller(config.clone(), claim_input_rx, claim_tx, None));
+    let (shutdown_tx, _shutdown_rx) = tokio::sync::mpsc::channel::<()>(1);
+    tokio::spawn(run_biller(config.clone(), claim_input_rx, claim_tx, None, 1, shutdown_tx));
 
     // Spawn clearinghouse
     let mut payer_txs = HashMap::new();
-    payer_txs.insert("medicare".to_string(), payer_tx);
+    payer_txs.insert("medicare".into(), payer_tx);
     let clearinghouse = Clearinghouse::new(
         claim_rx,
         payer_txs,
         remit_rx,
-        biller_txs.clone(),
         remittance_history.clone(),
         false,
     );
@@ -148,10 +157,10 @@ async fn test_remittance_amount_validation() {
     // Wait for processing and check history
     tokio::time::sleep(Duration::from_secs(3)).await;
 
-    let history = remittance_history.lock().await;
-    match history.get(&claim.claim_id) {
+    let history = remittance_history.snapshot().await;
+    match history.get(&default_key(&claim)) {
         Some(healthtechsim::message::ClaimStatus::Remitted(record)) => {
-            let remittance = &record.remittance;
+            let remittance = &record.remittance();
 
             // Verify remittance validation passes
             assert!(
@@ -162,12 +171,12 @@ async fn test_remittance_amount_validation() {
             // Verify amounts add up to billed amount
             let billed_amount =
                 claim.service_lines[0].unit_charge_amount * claim.service_lines[0].units as f64;
-            let remittance_line = &remittance.service_line_remittances[0];
-            let total_remitted = remittance_line.payer_paid_amount
-                + remittance_line.coinsurance_amount
-                + remittance_line.copay_amount
-                + remittance_line.deductible_amount
-                + remittance_line.not_allowed_amount;
+            let remittance_line = &remittance.service_line_remittances()[0];
+            let total_remitted = remittance_line.payer_paid_amount()
+                + remittance_line.coinsurance_amount()
+                + remittance_line.copay_amount()
+                + remittance_line.deductible_amount()
+                + remittance_line.not_allowed_amount();
 
             // Allow for small floating point differences
             assert!(
@@ -186,6 +195,7 @@ async fn test_claim_routing_to_correct_payer() {
         file_path: "mock_path.json".to_string(),
         ingest_rate: 1,
         verbose: false,
+        ..Config::default()
     };
 
     // Set up channels
@@ -194,21 +204,20 @@ async fn test_claim_routing_to_correct_payer() {
     let (medicare_tx, medicare_rx) = tokio::sync::mpsc::channel::<PayerMessage>(1);
     let (anthem_tx, anthem_rx) = tokio::sync::mpsc::channel::<PayerMessage>(1);
     let (remit_tx, remit_rx) = tokio::sync::mpsc::channel::<RemittanceMessage>(2);
-    let biller_txs = Arc::new(Mutex::new(HashMap::new()));
-    let remittance_history = Arc::new(Mutex::new(HashMap::new()));
+    let remittance_history = History::default();
 
     // Spawn biller
-    tokio::spawn(run_biller(config.clone(), claim_input_rx, claim_tx, None));
+    let (shutdown_tx, _shutdown_rx) = tokio::sync::mpsc::channel::<()>(1);
+    tokio::spawn(run_biller(config.clone(), claim_input_rx, claim_tx, None, 2, shutdown_tx));
 
     // Spawn clearinghouse with multiple payers
     let mut payer_txs = HashMap::new();
-    payer_txs.insert("medicare".to_string(), medicare_tx);
-    payer_txs.insert("anthem".to_string(), anthem_tx);
+    payer_txs.insert("medicare".into(), medicare_tx);
+    payer_txs.insert("anthem".into(), anthem_tx);
     let clearinghouse = Clearinghouse::new(
         claim_rx,
         payer_txs,
         remit_rx,
-        biller_txs.clone(),
         remittance_history.clone(),
         false,
     );
@@ -242,12 +251,12 @@ async fn test_claim_routing_to_correct_payer() {
 
     // Send claims for different payers
     let mut medicare_claim = mock_claim();
-    medicare_claim.claim_id = "medicare_claim".to_string();
-    medicare_claim.insurance.payer_id = "medicare".to_string();
+    medicare_claim.claim_id = "medicare_claim".into();
+    medicare_claim.insurance.payer_id = "medicare".into();
 
     let mut anthem_claim = mock_claim();
-    anthem_claim.claim_id = "anthem_claim".to_string();
-    anthem_claim.insurance.payer_id = "anthem".to_string();
+    anthem_claim.claim_id = "anthem_claim".into();
+    anthem_claim.insurance.payer_id = "anthem".into();
 
     claim_input_tx.send(medicare_claim.clone()).await.unwrap();
     claim_input_tx.send(anthem_claim.clone()).await.unwrap();
@@ -256,23 +265,23 @@ async fn test_claim_routing_to_correct_payer() {
     tokio::time::sleep(Duration::from_secs(5)).await;
 
     // Verify both claims were processed
-    let history = remittance_history.lock().await;
+    let history = remittance_history.snapshot().await;
     assert!(
-        history.contains_key(&medicare_claim.claim_id),
+        history.contains_key(&default_key(&medicare_claim)),
         "Medicare claim should be processed"
     );
     assert!(
-        history.contains_key(&anthem_claim.claim_id),
+        history.contains_key(&default_key(&anthem_claim)),
         "Anthem claim should be processed"
     );
 
     // Verify both claims are in Remitted status
-    match history.get(&medicare_claim.claim_id) {
+    match history.get(&default_key(&medicare_claim)) {
         Some(healthtechsim::message::ClaimStatus::Remitted(_)) => {}
         _ => panic!("Medicare claim should be remitted"),
     }
 
-    match history.get(&anthem_claim.claim_id) {
+    match history.get(&default_key(&anthem_claim)) {
         Some(healthtechsim::message::ClaimStatus::Remitted(_)) => {}
         _ => panic!("Anthem claim should be remitted"),
     }
@@ -296,6 +305,7 @@ async fn test_invalid_json_handling() {
         file_path: tmpfile.path().to_str().unwrap().to_string(),
         ingest_rate: 1,
         verbose: false,
+        ..Config::default()
     };
 
     // Set up channels
@@ -303,18 +313,20 @@ async fn test_invalid_json_handling() {
     let (claim_tx, claim_rx) = tokio::sync::mpsc::channel::<ClaimMessage>(1);
     let (payer_tx, payer_rx) = tokio::sync::mpsc::channel::<PayerMessage>(1);
     let (remit_tx, remit_rx) = tokio::sync::mpsc::channel::<RemittanceMessage>(1);
-    let biller_txs = Arc::new(Mutex::new(HashMap::new()));
-    let remittance_history = Arc::new(Mutex::new(HashMap::new()));
+    let remittance_history = History::default();
 
     // Notification channel
-    let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel::<String>(1);
+    let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel::<ClaimId>(1);
 
     // Spawn biller
+    let (shutdown_tx, _shutdown_rx) = tokio::sync::mpsc::channel::<()>(1);
     tokio::spawn(run_biller(
         config.clone(),
         claim_input_rx,
         claim_tx,
         Some(notify_tx),
+        1,
+        shutdown_tx,
     ));
 
     // Spawn reader
@@ -324,12 +336,11 @@ async fn test_invalid_json_handling() {
 
     // Spawn clearinghouse
     let mut payer_txs = HashMap::new();
-    payer_txs.insert("medicare".to_string(), payer_tx);
+    payer_txs.insert("medicare".into(), payer_tx);
     let clearinghouse = Clearinghouse::new(
         claim_rx,
         payer_txs,
         remit_rx,
-        biller_txs.clone(),
         remittance_history.clone(),
         false,
     );
@@ -355,10 +366,10 @@ async fn test_invalid_json_handling() {
     );
 
     // Verify only the valid claim is in history
-    let history = remittance_history.lock().await;
+    let history = remittance_history.snapshot().await;
     assert_eq!(history.len(), 1, "Only one claim should be in history");
     assert!(
-        history.contains_key(&claim.claim_id),
+        history.contains_key(&default_key(&claim)),
         "Valid claim should be in history"
     );
 }
@@ -370,6 +381,7 @@ async fn test_unknown_payer_handling() {
         file_path: "mock_path.json".to_string(),
         ingest_rate: 1,
         verbose: false,
+        ..Config::default()
     };
 
     // Set up channels
@@ -377,20 +389,19 @@ async fn test_unknown_payer_handling() {
     let (claim_tx, claim_rx) = tokio::sync::mpsc::channel::<ClaimMessage>(1);
     let (payer_tx, payer_rx) = tokio::sync::mpsc::channel::<PayerMessage>(1);
     let (remit_tx, remit_rx) = tokio::sync::mpsc::channel::<RemittanceMessage>(1);
-    let biller_txs = Arc::new(Mutex::new(HashMap::new()));
-    let remittance_history = Arc::new(Mutex::new(HashMap::new()));
+    let remittance_history = History::default();
 
     // Spawn biller
-    tokio::spawn(run_biller(config.clone(), claim_input_rx, claim_tx, None));
+    let (shutdown_tx, _shutdown_rx) = tokio::sync::mpsc::channel::<()>(1);
+    tokio::spawn(run_biller(config.clone(), claim_input_rx, claim_tx, None, 1, shutdown_tx));
 
     // Spawn clearinghouse with only medicare payer
     let mut payer_txs = HashMap::new();
-    payer_txs.insert("medicare".to_string(), payer_tx);
+    payer_txs.insert("medicare".into(), payer_tx);
     let clearinghouse = Clearinghouse::new(
         claim_rx,
         payer_txs,
         remit_rx,
-        biller_txs.clone(),
         remittance_history.clone(),
         false,
     );
@@ -406,15 +417,15 @@ async fn test_unknown_payer_handling() {
 
     // Send a claim with unknown payer
     let mut claim = mock_claim();
-    claim.insurance.payer_id = "unknown_payer".to_string();
+    claim.insurance.payer_id = "unknown_payer".into();
     claim_input_tx.send(claim.clone()).await.unwrap();
 
     // Wait a bit for processing
     tokio::time::sleep(Duration::from_secs(2)).await;
 
     // The claim should be in history but not remitted (since unknown payer)
-    let history = remittance_history.lock().await;
-    match history.get(&claim.claim_id) {
+    let history = remittance_history.snapshot().await;
+    match history.get(&default_key(&claim)) {
         Some(healthtechsim::message::ClaimStatus::Submitted { .. }) => {
             // Success - claim was submitted but not remitted due to unknown payer
         }
@@ -429,6 +440,7 @@ async fn test_concurrent_claim_processing() {
         file_path: "mock_path.json".to_string(),
         ingest_rate: 1,
         verbose: false,
+        ..Config::default()
     };
 
     // Set up channels with larger buffers
@@ -436,28 +448,29 @@ async fn test_concurrent_claim_processing() {
     let (claim_tx, claim_rx) = tokio::sync::mpsc::channel::<ClaimMessage>(10);
     let (payer_tx, payer_rx) = tokio::sync::mpsc::channel::<PayerMessage>(10);
     let (remit_tx, remit_rx) = tokio::sync::mpsc::channel::<RemittanceMessage>(10);
-    let biller_txs = Arc::new(Mutex::new(HashMap::new()));
-    let remittance_history = Arc::new(Mutex::new(HashMap::new()));
+    let remittance_history = History::default();
 
     // Notification channel
-    let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel::<String>(10);
+    let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel::<ClaimId>(10);
 
     // Spawn biller
+    let (shutdown_tx, _shutdown_rx) = tokio::sync::mpsc::channel::<()>(1);
     tokio::spawn(run_biller(
         config.clone(),
         claim_input_rx,
         claim_tx,
         Some(notify_tx),
+        5,
+        shutdown_tx,
     ));
 
     // Spawn clearinghouse
     let mut payer_txs = HashMap::new();
-    payer_txs.insert("medicare".to_string(), payer_tx);
+    payer_txs.insert("medicare".into(), payer_tx);
     let clearinghouse = Clearinghouse::new(
         claim_rx,
         payer_txs,
         remit_rx,
-        biller_txs.clone(),
         remittance_history.clone(),
         false,
     );
@@ -475,7 +488,7 @@ async fn test_concurrent_claim_processing() {
     let mut claims = Vec::new();
     for i in 0..5 {
         let mut claim = mock_claim();
-        claim.claim_id = format!("claim_{}", i);
+        claim.claim_id = format!("claim_{}", i).into();
         claims.push(claim.clone());
         claim_input_tx.send(claim).await.unwrap();
     }
@@ -497,10 +510,10 @@ async fn test_concurrent_claim_processing() {
         "All 5 claims should be processed"
     );
 
-    let history = remittance_history.lock().await;
+    let history = remittance_history.snapshot().await;
     for claim in &claims {
         assert!(
-            history.contains_key(&claim.claim_id),
+            history.contains_key(&default_key(claim)),
             "Claim {} should be in history",
             claim.claim_id
         );
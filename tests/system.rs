@@ -1,64 +1,32 @@
-use healthtechsim::biller::run_biller;
-use healthtechsim::clearinghouse::Clearinghouse;
-use healthtechsim::config::Config;
-use healthtechsim::message::{ClaimMessage, PayerMessage, RemittanceMessage};
-use healthtechsim::payer::Payer;
-use healthtechsim::schema::{PayerClaim, mock_claim};
-use std::collections::HashMap;
-use std::sync::Arc;
+use healthtechsim::event_log;
+use healthtechsim::ledger::EntryKind;
+use healthtechsim::orchestrator;
+use healthtechsim::payer::PayerClaimStatus;
+use healthtechsim::prelude::*;
+use healthtechsim::test_utils::mock_claim;
 use std::time::Duration;
-use tokio::sync::Mutex;
 use tokio::time::timeout;
 
+/// History key of a claim submitted by the default tenant's biller
+fn default_key(claim: &PayerClaim) -> ClaimKey {
+    ClaimKey::new(DEFAULT_TENANT, claim.claim_id.clone())
+}
+
 /// Test the full claim lifecycle: reader -> biller -> clearinghouse -> payer -> remittance back to biller.
 /// Expected: The remittance matches the original claim, and all modules interact as expected.
 #[tokio::test]
 async fn test_full_claim_lifecycle_happy_path() {
-    // Setup config
-    let config = Config {
-        file_path: "mock_path.json".to_string(),
-        ingest_rate: 1,
-        verbose: false,
-    };
-    // Channels
-    let (claim_input_tx, claim_input_rx) = tokio::sync::mpsc::channel::<PayerClaim>(1);
-    let (claim_tx, claim_rx) = tokio::sync::mpsc::channel::<ClaimMessage>(1);
-    let (payer_tx, payer_rx) = tokio::sync::mpsc::channel::<PayerMessage>(1);
-    let (remit_tx, remit_rx) = tokio::sync::mpsc::channel::<RemittanceMessage>(1);
-    let biller_txs = Arc::new(Mutex::new(HashMap::new()));
-    let remittance_history = Arc::new(Mutex::new(HashMap::new()));
-
     // Create a notification channel to track when biller receives remittance
-    let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel::<String>(1);
-
-    // Spawn biller
-    tokio::spawn(run_biller(
-        config.clone(),
-        claim_input_rx,
-        claim_tx,
-        Some(notify_tx),
-    ));
-
-    // Spawn clearinghouse
-    let mut payer_txs = HashMap::new();
-    payer_txs.insert("medicare".to_string(), payer_tx);
-    let clearinghouse = Clearinghouse::new(
-        claim_rx,
-        payer_txs,
-        remit_rx,
-        biller_txs.clone(),
-        remittance_history.clone(),
-        false,
-    );
-    tokio::spawn(async move {
-        clearinghouse.run().await;
-    });
+    let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel::<ClaimId>(1);
+    let (claim_input_tx, claim_input_rx) = tokio::sync::mpsc::channel::<PayerClaim>(1);
 
-    // Spawn payer
-    let payer = Payer::new("medicare".to_string(), 1, 2, remit_tx, payer_rx, false);
-    tokio::spawn(async move {
-        payer.run().await;
-    });
+    let sim = Simulation::builder()
+        .channel_capacity(1)
+        .payer(PayerSpec::new("medicare", 1, 2))
+        .biller(BillerSpec::default().with_total_claims(1).with_notify(notify_tx))
+        .claim_source(claim_input_rx)
+        .build()
+        .unwrap();
 
     // Send a mock claim
     let claim = mock_claim();
@@ -73,9 +41,9 @@ async fn test_full_claim_lifecycle_happy_path() {
     assert_eq!(received_claim_id, claim.claim_id);
 
     // Verify the claim was processed by checking history
-    let history = remittance_history.lock().await;
+    let history = sim.history();
     assert!(
-        history.contains_key(&claim.claim_id),
+        history.contains(&default_key(&claim)).await,
         "Claim should be in history"
     );
 }
@@ -84,76 +52,25 @@ async fn test_full_claim_lifecycle_happy_path() {
 /// Expected: Each claim is routed to the correct payer, and remittances are returned to the correct biller.
 #[tokio::test]
 async fn test_multiple_claims_and_payers() {
-    let config = Config {
-        file_path: "mock_path.json".to_string(),
-        ingest_rate: 1,
-        verbose: false,
-    };
-    let (claim_input_tx, claim_input_rx) = tokio::sync::mpsc::channel::<PayerClaim>(2);
-    let (claim_tx, claim_rx) = tokio::sync::mpsc::channel::<ClaimMessage>(2);
-    let (payer1_tx, payer1_rx) = tokio::sync::mpsc::channel::<PayerMessage>(2);
-    let (payer2_tx, payer2_rx) = tokio::sync::mpsc::channel::<PayerMessage>(2);
-    let (remit_tx, remit_rx) = tokio::sync::mpsc::channel::<RemittanceMessage>(2);
-    let biller_txs = Arc::new(Mutex::new(HashMap::new()));
-    let remittance_history = Arc::new(Mutex::new(HashMap::new()));
-
     // Create a notification channel to track when biller receives remittances
-    let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel::<String>(2);
-
-    // Spawn biller
-    tokio::spawn(run_biller(
-        config.clone(),
-        claim_input_rx,
-        claim_tx,
-        Some(notify_tx),
-    ));
+    let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel::<ClaimId>(2);
+    let (claim_input_tx, claim_input_rx) = tokio::sync::mpsc::channel::<PayerClaim>(2);
 
-    // Spawn clearinghouse
-    let mut payer_txs = HashMap::new();
-    payer_txs.insert("medicare".to_string(), payer1_tx);
-    payer_txs.insert("anthem".to_string(), payer2_tx);
-    let clearinghouse = Clearinghouse::new(
-        claim_rx,
-        payer_txs,
-        remit_rx,
-        biller_txs.clone(),
-        remittance_history.clone(),
-        false,
-    );
-    tokio::spawn(async move {
-        clearinghouse.run().await;
-    });
-
-    // Spawn payers
-    let payer1 = Payer::new(
-        "medicare".to_string(),
-        1,
-        2,
-        remit_tx.clone(),
-        payer1_rx,
-        false,
-    );
-    let payer2 = Payer::new(
-        "anthem".to_string(),
-        1,
-        2,
-        remit_tx.clone(),
-        payer2_rx,
-        false,
-    );
-    tokio::spawn(async move {
-        payer1.run().await;
-    });
-    tokio::spawn(async move {
-        payer2.run().await;
-    });
+    let sim = Simulation::builder()
+        .channel_capacity(2)
+        .payer(PayerSpec::new("medicare", 1, 2))
+        .payer(PayerSpec::new("anthem", 1, 2))
+        .biller(BillerSpec::default().with_total_claims(2).with_notify(notify_tx))
+        .claim_source(claim_input_rx)
+        .build()
+        .unwrap();
 
     // Send two claims for different payers
     let mut claim1 = mock_claim();
-    claim1.insurance.payer_id = "medicare".to_string();
+    claim1.insurance.payer_id = "medicare".into();
     let mut claim2 = mock_claim();
-    claim2.claim_id = "claim2".to_string();
-    claim2.insurance.payer_id = "anthem".to_string();
+    claim2.claim_id = "claim2".into();
+    claim2.insurance.payer_id = "anthem".into();
 
     claim_input_tx.send(claim1.clone()).await.unwrap();
     claim_input_tx.send(claim2.clone()).await.unwrap();
@@ -169,17 +86,133 @@ async fn test_multiple_claims_and_payers() {
         .expect("Timeout waiting for remittance notification 2")
         .expect("Expected remittance notification 2");
 
-    assert_eq!(received_claim_id1, claim1.claim_id);
-    assert_eq!(received_claim_id2, claim2.claim_id);
+    // Payer delays are random, so remittances may arrive in either order
+    let mut received = vec![received_claim_id1, received_claim_id2];
+    received.sort();
+    let mut expected = vec![claim1.claim_id.clone(), claim2.claim_id.clone()];
+    expected.sort();
+    assert_eq!(received, expected);
 
     // Verify both claims were processed by checking history
-    let history = remittance_history.lock().await;
+    let history = sim.history();
     assert!(
-        history.contains_key(&claim1.claim_id),
+        history.contains(&default_key(&claim1)).await,
         "Claim1 should be in history"
     );
     assert!(
-        history.contains_key(&claim2.claim_id),
+        history.contains(&default_key(&claim2)).await,
         "Claim2 should be in history"
     );
 }
+
+/// Test that a finished run, including a resubmitted claim, upholds every simulation invariant.
+/// Expected: No claim is left pending, no remittance is posted twice, and every posting balances.
+#[tokio::test]
+async fn test_run_upholds_invariants() {
+    let claim = mock_claim();
+    let mut other = mock_claim();
+    other.claim_id = "claim2".into();
+    let mut sim = Simulation::builder()
+        .payer(PayerSpec::new("medicare", 0, 0))
+        .biller(BillerSpec::default().with_patient_payer(1.0, 0, 0))
+        .claim_source(vec![claim.clone(), claim, other])
+        .build()
+        .unwrap();
+    timeout(Duration::from_secs(5), sim.wait())
+        .await
+        .expect("Timeout waiting for remittances");
+
+    let violations = InvariantChecker::default()
+        .check_finished(&sim.history(), sim.ledgers())
+        .await;
+    assert!(violations.is_empty(), "{:?}", violations);
+}
+
+/// Test that a corrected claim (frequency code 7) supersedes its remitted original end to end.
+/// Expected: The payer reverses the original's payment, the biller offsets its postings, reports count it as replaced,
+/// and a replacement for an unknown claim is rejected.
+#[tokio::test]
+async fn test_replacement_claim_reverses_original() {
+    let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel::<ClaimId>(1);
+    let (claim_input_tx, claim_input_rx) = tokio::sync::mpsc::channel::<PayerClaim>(1);
+    let sim = Simulation::builder()
+        .payer(PayerSpec::new("medicare", 0, 0))
+        .biller(BillerSpec::default().with_total_claims(3).with_notify(notify_tx))
+        .claim_source(claim_input_rx)
+        .build()
+        .unwrap();
+    let original = mock_claim();
+    let mut replacement = mock_claim();
+    replacement.claim_id = "abc123-corrected".into();
+    replacement.claim_frequency_code = ClaimFrequency::Replacement;
+    replacement.original_claim_id = Some(original.claim_id.clone());
+    replacement.service_lines[0].units = 2;
+    let mut orphan = replacement.clone();
+    orphan.claim_id = "orphan".into();
+    orphan.original_claim_id = Some("never-submitted".into());
+
+    for claim in [&original, &replacement, &orphan] {
+        claim_input_tx.send(claim.clone()).await.unwrap();
+        timeout(Duration::from_secs(5), notify_rx.recv())
+            .await
+            .expect("Timeout waiting for remittance")
+            .unwrap();
+    }
+
+    let portal = sim.portals().get("medicare").unwrap();
+    assert!(matches!(
+        portal.claim_status(&original.claim_id),
+        Some(PayerClaimStatus::Replaced { replacement: id, reversed_payment }) if id == replacement.claim_id && reversed_payment > 0.0
+    ));
+    assert!(matches!(sim.history().get(&default_key(&replacement)).await, Some(ClaimStatus::Remitted(_))));
+    assert!(!sim.history().contains(&default_key(&orphan)).await);
+
+    let ledger = sim.ledger(DEFAULT_TENANT).unwrap();
+    let net_charge: f64 = ledger
+        .lock()
+        .await
+        .entries()
+        .iter()
+        .filter(|entry| entry.claim_id == original.claim_id && entry.kind == EntryKind::Charge)
+        .map(|entry| entry.amount)
+        .sum();
+    assert!(net_charge.abs() < 1e-9);
+
+    let records = sim.history().snapshot().await;
+    let tenants = ReportEngine::new(&records).tenant_summary();
+    assert_eq!((tenants[0].remitted, tenants[0].replaced), (1, 1));
+    let violations = InvariantChecker::default().check_finished(&sim.history(), sim.ledgers()).await;
+    assert!(violations.is_empty(), "{:?}", violations);
+}
+
+/// Test that the orchestrator runs a config end to end with the binary's payers and tasks.
+/// Expected: The ten generated claims are all remitted, the run completes without invariant violations, and finishing
+/// writes every adjudication to the event log.
+#[tokio::test]
+async fn test_orchestrated_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = |file: &str| dir.path().join(file).to_string_lossy().into_owned();
+    let config = Config {
+        file_path: path("claims.jsonl"),
+        generated_claims_path: path("claims.jsonl"),
+        event_log_path: Some(path("events.jsonl")),
+        burst: 100,
+        invariant_max_pending_secs: Some(30),
+        ..Config::default()
+    };
+    // the binary's payers, answering at once
+    let payers = orchestrator::simulated_payers()
+        .into_iter()
+        .map(|payer| PayerSpec { min_delay_secs: 0, max_delay_secs: 0, ..payer })
+        .collect();
+
+    let mut handles = orchestrator::start(&config, payers).await.unwrap();
+    let end = timeout(Duration::from_secs(30), handles.wait()).await.expect("Timeout waiting for the run to end");
+    assert_eq!(end.unwrap(), RunEnd::Completed);
+    let remitted = handles.history.snapshot().await.values().filter(|status| matches!(status, ClaimStatus::Remitted(_))).count();
+    assert_eq!(remitted, 10);
+    handles.finish().await.unwrap();
+
+    let records = event_log::read_event_log(&path("events.jsonl")).await.unwrap();
+    assert_eq!(records.iter().filter(|record| matches!(record.event, Event::ClaimAdjudicated { .. })).count(), 10);
+}